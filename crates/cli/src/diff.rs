@@ -0,0 +1,732 @@
+//! Event-by-event comparison of two logs, typically the output of two
+//! parser versions over the same capture, and its standalone HTML report.
+//!
+//! Events are aligned on `(timestamp, symbol, venue)` rather than position,
+//! so one event gained or lost shows up as a single difference instead of
+//! shifting everything after it. [`diff_books`] compares rebuilt book
+//! state at sampled times instead, so same-timestamp events in a different
+//! order are not differences unless they leave a different book.
+
+use md_clients::format_event;
+use md_core::{Event, Payload, QuoteTicks, Timestamp};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+const REPORT_TEMPLATE: &str = include_str!("ui/diff_report.html");
+
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    /// Mismatches listed individually; all of them are still counted.
+    pub max_rows: usize,
+    /// Time buckets in the divergence chart.
+    pub buckets: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            max_rows: 1000,
+            buckets: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchKind {
+    Payload,
+    OnlyLeft,
+    OnlyRight,
+}
+
+impl MismatchKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Payload => "payload",
+            Self::OnlyLeft => "only left",
+            Self::OnlyRight => "only right",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub kind: MismatchKind,
+    pub timestamp_ns: Timestamp,
+    pub symbol: String,
+    pub venue: String,
+    pub left_sequence: Option<u64>,
+    pub right_sequence: Option<u64>,
+    /// Payload fields that differ; `kind` when a trade became a quote.
+    pub fields: Vec<&'static str>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolDiff {
+    pub symbol: String,
+    pub left_events: usize,
+    pub right_events: usize,
+    pub mismatches: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DivergenceBucket {
+    pub from_ns: i64,
+    pub to_ns: i64,
+    pub payload: usize,
+    pub only_left: usize,
+    pub only_right: usize,
+}
+
+impl DivergenceBucket {
+    fn total(&self) -> usize {
+        self.payload + self.only_left + self.only_right
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LogDiff {
+    pub left_events: usize,
+    pub right_events: usize,
+    pub matched: usize,
+    /// Matched events carrying a different sequence number.
+    pub resequenced: usize,
+    pub payload_mismatches: usize,
+    pub only_left: usize,
+    pub only_right: usize,
+    pub symbols: Vec<SymbolDiff>,
+    pub buckets: Vec<DivergenceBucket>,
+    pub mismatches: Vec<Mismatch>,
+    /// More mismatches exist than are listed.
+    pub truncated: bool,
+}
+
+impl LogDiff {
+    pub fn mismatch_count(&self) -> usize {
+        self.payload_mismatches + self.only_left + self.only_right
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.mismatch_count() == 0 && self.resequenced == 0
+    }
+}
+
+fn key(event: &Event) -> (Timestamp, &str, &str) {
+    (event.timestamp_ns, &event.symbol, &event.venue)
+}
+
+fn sorted(events: &[Event]) -> Vec<&Event> {
+    let mut out = events.iter().collect::<Vec<_>>();
+    out.sort_by(|a, b| key(a).cmp(&key(b)).then(a.sequence.cmp(&b.sequence)));
+    out
+}
+
+fn payload_fields(left: &Payload, right: &Payload) -> Vec<&'static str> {
+    match (left, right) {
+        (
+            Payload::Trade {
+                price_ticks: lp,
+                size: ls,
+            },
+            Payload::Trade {
+                price_ticks: rp,
+                size: rs,
+            },
+        ) => [("price_ticks", lp != rp), ("size", ls != rs)]
+            .into_iter()
+            .filter_map(|(name, differs)| differs.then_some(name))
+            .collect(),
+        (
+            Payload::Quote {
+                bid_px: lbp,
+                bid_sz: lbs,
+                ask_px: lap,
+                ask_sz: las,
+            },
+            Payload::Quote {
+                bid_px: rbp,
+                bid_sz: rbs,
+                ask_px: rap,
+                ask_sz: ras,
+            },
+        ) => [
+            ("bid_px", lbp != rbp),
+            ("bid_sz", lbs != rbs),
+            ("ask_px", lap != rap),
+            ("ask_sz", las != ras),
+        ]
+        .into_iter()
+        .filter_map(|(name, differs)| differs.then_some(name))
+        .collect(),
+        _ => vec!["kind"],
+    }
+}
+
+pub fn diff_events(left: &[Event], right: &[Event], options: &DiffOptions) -> LogDiff {
+    let left = sorted(left);
+    let right = sorted(right);
+    let mut diff = LogDiff {
+        left_events: left.len(),
+        right_events: right.len(),
+        ..LogDiff::default()
+    };
+
+    let first = [left.first(), right.first()]
+        .into_iter()
+        .flatten()
+        .map(|e| e.timestamp_ns.as_nanos())
+        .min();
+    let last = [left.last(), right.last()]
+        .into_iter()
+        .flatten()
+        .map(|e| e.timestamp_ns.as_nanos())
+        .max();
+    let (Some(first), Some(last)) = (first, last) else {
+        return diff;
+    };
+    let bucket_count = options.buckets.max(1);
+    let span = (last as i128 - first as i128 + 1).max(1);
+    let width = ((span + bucket_count as i128 - 1) / bucket_count as i128).max(1);
+    diff.buckets = (0..bucket_count)
+        .map(|i| {
+            let from = first as i128 + width * i as i128;
+            DivergenceBucket {
+                from_ns: from.min(i64::MAX as i128) as i64,
+                to_ns: (from + width).min(i64::MAX as i128) as i64,
+                ..DivergenceBucket::default()
+            }
+        })
+        .collect();
+
+    let mut symbols = BTreeMap::<String, SymbolDiff>::new();
+    for event in &left {
+        symbols.entry(event.symbol.clone()).or_default().left_events += 1;
+    }
+    for event in &right {
+        symbols
+            .entry(event.symbol.clone())
+            .or_default()
+            .right_events += 1;
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        let order = match (left.get(i), right.get(j)) {
+            (Some(l), Some(r)) => key(l).cmp(&key(r)),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        let (kind, l, r) = match order {
+            Ordering::Equal => {
+                let (l, r) = (left[i], right[j]);
+                i += 1;
+                j += 1;
+                if l.payload == r.payload {
+                    diff.matched += 1;
+                    if l.sequence != r.sequence {
+                        diff.resequenced += 1;
+                    }
+                    continue;
+                }
+                (MismatchKind::Payload, Some(l), Some(r))
+            }
+            Ordering::Less => {
+                i += 1;
+                (MismatchKind::OnlyLeft, Some(left[i - 1]), None)
+            }
+            Ordering::Greater => {
+                j += 1;
+                (MismatchKind::OnlyRight, None, Some(right[j - 1]))
+            }
+        };
+        let Some(event) = l.or(r) else {
+            continue;
+        };
+        let offset = (event.timestamp_ns.as_nanos() as i128 - first as i128) / width;
+        let bucket = &mut diff.buckets[(offset.max(0) as usize).min(bucket_count - 1)];
+        match kind {
+            MismatchKind::Payload => {
+                diff.payload_mismatches += 1;
+                bucket.payload += 1;
+            }
+            MismatchKind::OnlyLeft => {
+                diff.only_left += 1;
+                bucket.only_left += 1;
+            }
+            MismatchKind::OnlyRight => {
+                diff.only_right += 1;
+                bucket.only_right += 1;
+            }
+        }
+        symbols.entry(event.symbol.clone()).or_default().mismatches += 1;
+        if diff.mismatches.len() == options.max_rows {
+            diff.truncated = true;
+            continue;
+        }
+        diff.mismatches.push(Mismatch {
+            kind,
+            timestamp_ns: event.timestamp_ns,
+            symbol: event.symbol.clone(),
+            venue: event.venue.clone(),
+            left_sequence: l.map(|e| e.sequence),
+            right_sequence: r.map(|e| e.sequence),
+            fields: match (l, r) {
+                (Some(l), Some(r)) => payload_fields(&l.payload, &r.payload),
+                _ => Vec::new(),
+            },
+            left: l.map(format_event),
+            right: r.map(format_event),
+        });
+    }
+
+    diff.symbols = symbols
+        .into_iter()
+        .map(|(symbol, counts)| SymbolDiff { symbol, ..counts })
+        .collect();
+    diff
+}
+
+/// Book of one symbol at one venue as of a sample time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BookSnapshot {
+    pub quote: Option<QuoteTicks>,
+    pub last_price: Option<i64>,
+    pub last_size: Option<i64>,
+    pub trades: u64,
+    pub volume: i64,
+}
+
+impl BookSnapshot {
+    fn apply(&mut self, payload: &Payload) {
+        match *payload {
+            Payload::Trade { price_ticks, size } => {
+                self.last_price = Some(price_ticks);
+                self.last_size = Some(size);
+                self.trades += 1;
+                self.volume = self.volume.saturating_add(size);
+            }
+            Payload::Quote {
+                bid_px,
+                bid_sz,
+                ask_px,
+                ask_sz,
+            } => {
+                self.quote = Some(QuoteTicks {
+                    bid_px,
+                    bid_sz,
+                    ask_px,
+                    ask_sz,
+                });
+            }
+        }
+    }
+
+    fn fields(&self, other: &Self) -> Vec<&'static str> {
+        let quote = |q: &Self| q.quote.map(|q| [q.bid_px, q.bid_sz, q.ask_px, q.ask_sz]);
+        let (lq, rq) = (quote(self), quote(other));
+        [
+            ("bid_px", lq.map(|q| q[0]) != rq.map(|q| q[0])),
+            ("bid_sz", lq.map(|q| q[1]) != rq.map(|q| q[1])),
+            ("ask_px", lq.map(|q| q[2]) != rq.map(|q| q[2])),
+            ("ask_sz", lq.map(|q| q[3]) != rq.map(|q| q[3])),
+            ("last_price", self.last_price != other.last_price),
+            ("last_size", self.last_size != other.last_size),
+            ("trades", self.trades != other.trades),
+            ("volume", self.volume != other.volume),
+        ]
+        .into_iter()
+        .filter_map(|(name, differs)| differs.then_some(name))
+        .collect()
+    }
+}
+
+/// A symbol and venue whose book differs at one sample time. A missing
+/// side has seen no events for the pair yet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BookMismatch {
+    pub timestamp_ns: Timestamp,
+    pub symbol: String,
+    pub venue: String,
+    pub fields: Vec<&'static str>,
+    pub left: Option<BookSnapshot>,
+    pub right: Option<BookSnapshot>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BookDiff {
+    pub left_events: usize,
+    pub right_events: usize,
+    pub samples: Vec<Timestamp>,
+    /// Books compared, summed over samples.
+    pub compared: usize,
+    pub mismatch_count: usize,
+    /// Sample times with at least one differing book.
+    pub diverged_samples: usize,
+    pub first_divergence: Option<Timestamp>,
+    pub mismatches: Vec<BookMismatch>,
+    /// More mismatches exist than are listed.
+    pub truncated: bool,
+}
+
+impl BookDiff {
+    pub fn is_identical(&self) -> bool {
+        self.mismatch_count == 0
+    }
+}
+
+type Books = BTreeMap<(String, String), BookSnapshot>;
+
+/// Applies events up to and including `until` and returns how far it got.
+fn advance(books: &mut Books, events: &[&Event], mut at: usize, until: Timestamp) -> usize {
+    while let Some(event) = events.get(at).filter(|e| e.timestamp_ns <= until) {
+        books
+            .entry((event.symbol.clone(), event.venue.clone()))
+            .or_default()
+            .apply(&event.payload);
+        at += 1;
+    }
+    at
+}
+
+/// Rebuilds each symbol and venue's book on both sides at `samples` times
+/// spread evenly over the combined span, the last at the final event, and
+/// compares the books. At most `max_rows` mismatches are listed.
+pub fn diff_books(left: &[Event], right: &[Event], samples: usize, max_rows: usize) -> BookDiff {
+    let left = sorted(left);
+    let right = sorted(right);
+    let mut diff = BookDiff {
+        left_events: left.len(),
+        right_events: right.len(),
+        ..BookDiff::default()
+    };
+    let ends = [left.first(), right.first(), left.last(), right.last()]
+        .into_iter()
+        .flatten()
+        .map(|e| e.timestamp_ns.as_nanos() as i128)
+        .collect::<Vec<_>>();
+    let (Some(&first), Some(&last)) = (ends.iter().min(), ends.iter().max()) else {
+        return diff;
+    };
+    let samples = samples.max(1) as i128;
+    let span = last - first;
+    diff.samples = (1..=samples)
+        .map(|k| Timestamp::from_nanos((first + span * k / samples) as i64))
+        .collect();
+    diff.samples.dedup();
+
+    let (mut left_books, mut right_books) = (Books::new(), Books::new());
+    let (mut i, mut j) = (0, 0);
+    for &at in &diff.samples {
+        i = advance(&mut left_books, &left, i, at);
+        j = advance(&mut right_books, &right, j, at);
+        let mut keys = left_books
+            .keys()
+            .chain(right_books.keys())
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        let mut diverged = false;
+        for key in keys {
+            diff.compared += 1;
+            let (l, r) = (left_books.get(key), right_books.get(key));
+            let fields = match (l, r) {
+                (Some(l), Some(r)) if l == r => continue,
+                (Some(l), Some(r)) => l.fields(r),
+                _ => Vec::new(),
+            };
+            diverged = true;
+            diff.mismatch_count += 1;
+            if diff.mismatches.len() == max_rows {
+                diff.truncated = true;
+                continue;
+            }
+            diff.mismatches.push(BookMismatch {
+                timestamp_ns: at,
+                symbol: key.0.clone(),
+                venue: key.1.clone(),
+                fields,
+                left: l.copied(),
+                right: r.copied(),
+            });
+        }
+        if diverged {
+            diff.diverged_samples += 1;
+            diff.first_divergence.get_or_insert(at);
+        }
+    }
+    diff
+}
+
+fn escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Stacked bars of mismatches per bucket, with the running total as a line.
+fn divergence_chart(buckets: &[DivergenceBucket]) -> String {
+    const W: f64 = 960.0;
+    const H: f64 = 240.0;
+    const PAD: f64 = 28.0;
+    let peak = buckets
+        .iter()
+        .map(DivergenceBucket::total)
+        .max()
+        .unwrap_or(0);
+    let total = buckets.iter().map(DivergenceBucket::total).sum::<usize>();
+    if total == 0 {
+        return String::from(r#"<p class="muted">No divergence.</p>"#);
+    }
+    let slot = (W - 2.0 * PAD) / buckets.len().max(1) as f64;
+    let scale = (H - 2.0 * PAD) / peak.max(1) as f64;
+    let mut svg = format!(
+        r#"<svg viewBox="0 0 {W} {H}" width="100%" role="img" aria-label="mismatches over time">"#
+    );
+    let _ = write!(
+        svg,
+        r#"<line class="axis" x1="{PAD}" y1="{0}" x2="{1}" y2="{0}"/>"#,
+        H - PAD,
+        W - PAD
+    );
+    let mut running = 0usize;
+    let mut line = Vec::with_capacity(buckets.len());
+    for (i, bucket) in buckets.iter().enumerate() {
+        let x = PAD + slot * i as f64;
+        let mut y = H - PAD;
+        for (class, count) in [
+            ("payload", bucket.payload),
+            ("only-left", bucket.only_left),
+            ("only-right", bucket.only_right),
+        ] {
+            if count == 0 {
+                continue;
+            }
+            let h = count as f64 * scale;
+            y -= h;
+            let _ = write!(
+                svg,
+                r#"<rect class="{class}" x="{x:.1}" y="{y:.1}" width="{:.1}" height="{h:.1}"><title>{}..{} ns: {count} {class}</title></rect>"#,
+                (slot - 1.0).max(1.0),
+                bucket.from_ns,
+                bucket.to_ns
+            );
+        }
+        running += bucket.total();
+        let ly = H - PAD - (H - 2.0 * PAD) * running as f64 / total as f64;
+        line.push(format!("{:.1},{ly:.1}", x + slot / 2.0));
+    }
+    let _ = write!(
+        svg,
+        r#"<polyline class="cumulative" points="{}"/>"#,
+        line.join(" ")
+    );
+    let _ = write!(
+        svg,
+        r#"<text x="{PAD}" y="16">peak {peak} per bucket</text><text x="{0}" y="16" text-anchor="end">cumulative {total}</text>"#,
+        W - PAD
+    );
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Renders `diff` into a self-contained HTML page: no scripts, no external
+/// assets, and no timestamps of its own, so the same inputs give the same
+/// file.
+pub fn render_html(diff: &LogDiff, left_name: &str, right_name: &str) -> String {
+    let verdict = if diff.is_identical() {
+        r#"<span class="ok">identical</span>"#
+    } else {
+        r#"<span class="bad">different</span>"#
+    };
+    let mut summary = String::new();
+    for (label, value) in [
+        ("left events", diff.left_events),
+        ("right events", diff.right_events),
+        ("matched", diff.matched),
+        ("payload mismatches", diff.payload_mismatches),
+        ("only in left", diff.only_left),
+        ("only in right", diff.only_right),
+        ("resequenced", diff.resequenced),
+    ] {
+        let _ = write!(
+            summary,
+            r#"<div class="stat"><div class="value">{value}</div><div class="label">{label}</div></div>"#
+        );
+    }
+
+    let mut symbols = String::new();
+    for s in &diff.symbols {
+        let _ = write!(
+            symbols,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&s.symbol),
+            s.left_events,
+            s.right_events,
+            s.mismatches
+        );
+    }
+
+    let mut rows = String::new();
+    for m in &diff.mismatches {
+        let seq = |s: Option<u64>| s.map_or_else(|| String::from("-"), |s| s.to_string());
+        let _ = write!(
+            rows,
+            r#"<tr class="{}"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td><td><code>{}</code></td></tr>"#,
+            m.kind.label().replace(' ', "-"),
+            m.kind.label(),
+            m.timestamp_ns.as_nanos(),
+            escape(&m.symbol),
+            escape(&m.venue),
+            seq(m.left_sequence),
+            seq(m.right_sequence),
+            m.fields.join(", "),
+            escape(m.left.as_deref().unwrap_or("")),
+            escape(m.right.as_deref().unwrap_or(""))
+        );
+    }
+    let truncated = if diff.truncated {
+        format!(
+            r#"<p class="muted">Showing the first {} of {} mismatches.</p>"#,
+            diff.mismatches.len(),
+            diff.mismatch_count()
+        )
+    } else {
+        String::new()
+    };
+
+    REPORT_TEMPLATE
+        .replace("{{left}}", &escape(left_name))
+        .replace("{{right}}", &escape(right_name))
+        .replace("{{verdict}}", verdict)
+        .replace("{{summary}}", &summary)
+        .replace("{{chart}}", &divergence_chart(&diff.buckets))
+        .replace("{{symbols}}", &symbols)
+        .replace("{{truncated}}", &truncated)
+        .replace("{{mismatches}}", &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    fn quote(ts: i64, seq: u64, bid_px: i64) -> Event {
+        Event::quote(
+            ts,
+            seq,
+            "X",
+            "AAPL",
+            QuoteTicks {
+                bid_px,
+                bid_sz: 1,
+                ask_px: 200,
+                ask_sz: 1,
+            },
+        )
+    }
+
+    #[test]
+    fn aligns_on_time_so_one_extra_event_is_one_difference() {
+        let left = vec![
+            Event::trade(10, 1, "X", "AAPL", 100, 5),
+            quote(20, 2, 99),
+            quote(30, 3, 98),
+            Event::trade(40, 4, "X", "MSFT", 300, 1),
+        ];
+        let right = vec![
+            Event::trade(10, 1, "X", "AAPL", 100, 5),
+            Event::trade(15, 2, "X", "AAPL", 101, 1),
+            quote(20, 3, 99),
+            quote(30, 4, 97),
+        ];
+        let diff = diff_events(
+            &left,
+            &right,
+            &DiffOptions {
+                max_rows: 2,
+                buckets: 3,
+            },
+        );
+        assert_eq!(
+            (
+                diff.matched,
+                diff.resequenced,
+                diff.payload_mismatches,
+                diff.only_left,
+                diff.only_right
+            ),
+            (2, 1, 1, 1, 1)
+        );
+        assert!(!diff.is_identical());
+        assert!(diff.truncated);
+        assert_eq!(diff.mismatches[0].kind, MismatchKind::OnlyRight);
+        assert_eq!(diff.mismatches[1].fields, vec!["bid_px"]);
+        assert_eq!(
+            diff.buckets
+                .iter()
+                .map(DivergenceBucket::total)
+                .collect::<Vec<_>>(),
+            vec![1, 1, 1]
+        );
+        assert_eq!(diff.symbols[0].mismatches, 2);
+
+        let html = render_html(&diff, "old<parser>.eventlog", "new.eventlog");
+        assert!(html.contains("old&lt;parser&gt;.eventlog"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("Showing the first 2 of 3 mismatches"));
+        assert!(!html.contains("{{"));
+
+        let same = diff_events(&left, &left, &DiffOptions::default());
+        assert!(same.is_identical());
+        assert!(render_html(&same, "a", "a").contains("No divergence."));
+    }
+
+    #[test]
+    fn book_diff_ignores_reordering_that_leaves_the_same_book() {
+        let left = vec![
+            Event::trade(10, 1, "X", "AAPL", 100, 5),
+            quote(10, 2, 99),
+            Event::trade(10, 3, "X", "MSFT", 300, 1),
+            quote(20, 4, 98),
+        ];
+        // Same book at every sample, different order within timestamp 10.
+        let right = vec![
+            Event::trade(10, 1, "X", "MSFT", 300, 1),
+            quote(10, 2, 99),
+            Event::trade(10, 3, "X", "AAPL", 100, 5),
+            quote(20, 4, 98),
+        ];
+        let options = DiffOptions::default();
+        assert!(!diff_events(&left, &right, &options).is_identical());
+        let same = diff_books(&left, &right, 4, 10);
+        assert!(same.is_identical());
+        assert_eq!(same.samples.len(), 4);
+
+        let mut changed = right.clone();
+        changed[3] = quote(20, 4, 97);
+        changed.push(Event::trade(20, 5, "X", "SPY", 50, 2));
+        let diff = diff_books(&left, &changed, 2, 10);
+        assert_eq!(
+            diff.samples,
+            vec![Timestamp::from_nanos(15), Timestamp::from_nanos(20)]
+        );
+        assert_eq!((diff.mismatch_count, diff.diverged_samples), (2, 1));
+        assert_eq!(diff.first_divergence, Some(Timestamp::from_nanos(20)));
+        assert_eq!(diff.mismatches[0].fields, vec!["bid_px"]);
+        assert_eq!(diff.mismatches[1].symbol, "SPY");
+        assert!(diff.mismatches[1].left.is_none());
+    }
+}
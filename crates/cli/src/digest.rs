@@ -0,0 +1,494 @@
+//! What happened in a slice of the tape, for incident reviews: the largest
+//! price moves, spread blowouts, volume spikes and halts of an interval,
+//! instead of eyeballing raw event tables.
+
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DigestOptions {
+    /// Entries listed per section.
+    pub top: usize,
+    /// A spread at least this many times its median is a blowout.
+    pub spread_multiple: f64,
+    /// Width of the buckets trade volume is compared across.
+    pub bucket_ns: i64,
+    /// A bucket with at least this many times the symbol's mean bucket
+    /// volume is a spike.
+    pub volume_multiple: f64,
+    /// A symbol silent for at least this long between two of its events
+    /// counts as halted.
+    pub halt_ns: i64,
+}
+
+impl Default for DigestOptions {
+    fn default() -> Self {
+        Self {
+            top: 5,
+            spread_multiple: 5.0,
+            bucket_ns: 1_000_000_000,
+            volume_multiple: 5.0,
+            halt_ns: 30_000_000_000,
+        }
+    }
+}
+
+/// First to last price of a symbol: trade prices if it traded in the
+/// interval, quote mids otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceMove {
+    pub symbol: String,
+    /// `trades` or `mids`.
+    pub basis: &'static str,
+    pub open: f64,
+    pub close: f64,
+    pub high: f64,
+    pub low: f64,
+    pub change_ticks: f64,
+    pub change_bps: f64,
+}
+
+/// A run of quotes on one venue whose spread stayed at or above
+/// `spread_multiple` times the venue's median spread for the symbol.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpreadBlowout {
+    pub symbol: String,
+    pub venue: String,
+    pub start_ns: Timestamp,
+    /// The quote that brought the spread back; `None` if it was still wide
+    /// at the end of the interval.
+    pub end_ns: Option<Timestamp>,
+    pub peak_spread_ticks: i64,
+    pub median_spread_ticks: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolumeSpike {
+    pub symbol: String,
+    pub from_ns: Timestamp,
+    pub to_ns: Timestamp,
+    pub volume: i64,
+    pub trades: u64,
+    /// The symbol's mean volume per bucket over the interval.
+    pub mean_volume: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Halt {
+    pub symbol: String,
+    pub from_ns: Timestamp,
+    pub to_ns: Timestamp,
+    pub duration_ns: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Digest {
+    pub from_ns: Timestamp,
+    pub to_ns: Timestamp,
+    pub events: usize,
+    pub trades: usize,
+    pub quotes: usize,
+    pub symbols: usize,
+    /// Largest absolute moves in basis points first.
+    pub price_moves: Vec<PriceMove>,
+    /// Widest relative to the median first.
+    pub spread_blowouts: Vec<SpreadBlowout>,
+    /// Largest relative to the mean first.
+    pub volume_spikes: Vec<VolumeSpike>,
+    /// Longest first.
+    pub halts: Vec<Halt>,
+}
+
+#[derive(Default)]
+struct Prices {
+    trades: Vec<f64>,
+    mids: Vec<f64>,
+}
+
+/// Blowout being tracked for one symbol and venue.
+struct Episode {
+    start_ns: Timestamp,
+    peak: i64,
+}
+
+/// Digests the events stamped in `[from, to]`; an open bound falls back to
+/// the first or last event.
+pub fn build_digest(
+    events: &[Event],
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    options: &DigestOptions,
+) -> Digest {
+    let mut events = events
+        .iter()
+        .filter(|e| from.is_none_or(|from| e.timestamp_ns >= from))
+        .filter(|e| to.is_none_or(|to| e.timestamp_ns <= to))
+        .collect::<Vec<_>>();
+    events.sort_by_key(|e| (e.timestamp_ns, e.sequence));
+    let from_ns = from
+        .or_else(|| events.first().map(|e| e.timestamp_ns))
+        .unwrap_or_default();
+    let to_ns = to
+        .or_else(|| events.last().map(|e| e.timestamp_ns))
+        .unwrap_or(from_ns);
+
+    let mut prices = BTreeMap::<&str, Prices>::new();
+    let mut spreads = BTreeMap::<(&str, &str), Vec<(Timestamp, i64)>>::new();
+    let mut seen = BTreeMap::<&str, Timestamp>::new();
+    let mut halts = Vec::new();
+    let (mut trades, mut quotes) = (0, 0);
+    for event in &events {
+        if let Some(last) = seen.insert(&event.symbol, event.timestamp_ns) {
+            let duration_ns = event.timestamp_ns.nanos_since(last);
+            if duration_ns >= options.halt_ns {
+                halts.push(Halt {
+                    symbol: event.symbol.clone(),
+                    from_ns: last,
+                    to_ns: event.timestamp_ns,
+                    duration_ns,
+                });
+            }
+        }
+        let entry = prices.entry(&event.symbol).or_default();
+        match event.payload {
+            Payload::Trade { price_ticks, .. } => {
+                trades += 1;
+                entry.trades.push(price_ticks as f64);
+            }
+            Payload::Quote { bid_px, ask_px, .. } => {
+                quotes += 1;
+                // One-sided and crossed quotes have no meaningful spread.
+                if bid_px > 0 && ask_px >= bid_px {
+                    entry.mids.push((bid_px + ask_px) as f64 / 2.0);
+                    spreads
+                        .entry((&event.symbol, &event.venue))
+                        .or_default()
+                        .push((event.timestamp_ns, ask_px - bid_px));
+                }
+            }
+        }
+    }
+
+    let mut price_moves = prices
+        .iter()
+        .filter_map(|(symbol, p)| price_move(symbol, p))
+        .collect::<Vec<_>>();
+    price_moves.sort_by(|a, b| b.change_bps.abs().total_cmp(&a.change_bps.abs()));
+
+    let mut spread_blowouts = spreads
+        .into_iter()
+        .flat_map(|((symbol, venue), quotes)| {
+            spread_blowouts(symbol, venue, &quotes, options.spread_multiple)
+        })
+        .collect::<Vec<_>>();
+    let ratio =
+        |b: &SpreadBlowout| b.peak_spread_ticks as f64 / b.median_spread_ticks.max(1) as f64;
+    spread_blowouts.sort_by(|a, b| ratio(b).total_cmp(&ratio(a)));
+
+    let mut volume_spikes = volume_spikes(&events, from_ns, to_ns, options);
+    let ratio = |s: &VolumeSpike| s.volume as f64 / s.mean_volume;
+    volume_spikes.sort_by(|a, b| ratio(b).total_cmp(&ratio(a)));
+
+    halts.sort_by_key(|h| std::cmp::Reverse(h.duration_ns));
+
+    let top = options.top;
+    price_moves.truncate(top);
+    spread_blowouts.truncate(top);
+    volume_spikes.truncate(top);
+    halts.truncate(top);
+    Digest {
+        from_ns,
+        to_ns,
+        events: events.len(),
+        trades,
+        quotes,
+        symbols: seen.len(),
+        price_moves,
+        spread_blowouts,
+        volume_spikes,
+        halts,
+    }
+}
+
+fn price_move(symbol: &str, prices: &Prices) -> Option<PriceMove> {
+    let (basis, series) = if prices.trades.is_empty() {
+        ("mids", &prices.mids)
+    } else {
+        ("trades", &prices.trades)
+    };
+    let (&open, &close) = (series.first()?, series.last()?);
+    let change_ticks = close - open;
+    Some(PriceMove {
+        symbol: symbol.to_string(),
+        basis,
+        open,
+        close,
+        high: series.iter().copied().fold(f64::MIN, f64::max),
+        low: series.iter().copied().fold(f64::MAX, f64::min),
+        change_ticks,
+        change_bps: if open > 0.0 {
+            change_ticks / open * 10_000.0
+        } else {
+            0.0
+        },
+    })
+}
+
+fn spread_blowouts(
+    symbol: &str,
+    venue: &str,
+    quotes: &[(Timestamp, i64)],
+    multiple: f64,
+) -> Vec<SpreadBlowout> {
+    let mut sorted = quotes.iter().map(|(_, s)| *s).collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let Some(&median) = sorted.get(sorted.len() / 2) else {
+        return Vec::new();
+    };
+    // A locked market's median of zero would make every tick a blowout.
+    let threshold = multiple * median.max(1) as f64;
+    let mut out = Vec::new();
+    let mut open: Option<Episode> = None;
+    let close = |episode: Episode, end_ns| SpreadBlowout {
+        symbol: symbol.to_string(),
+        venue: venue.to_string(),
+        start_ns: episode.start_ns,
+        end_ns,
+        peak_spread_ticks: episode.peak,
+        median_spread_ticks: median,
+    };
+    for &(at, spread) in quotes {
+        if spread > median && spread as f64 >= threshold {
+            let episode = open.get_or_insert(Episode {
+                start_ns: at,
+                peak: spread,
+            });
+            episode.peak = episode.peak.max(spread);
+        } else if let Some(episode) = open.take() {
+            out.push(close(episode, Some(at)));
+        }
+    }
+    if let Some(episode) = open {
+        out.push(close(episode, None));
+    }
+    out
+}
+
+fn volume_spikes(
+    events: &[&Event],
+    from_ns: Timestamp,
+    to_ns: Timestamp,
+    options: &DigestOptions,
+) -> Vec<VolumeSpike> {
+    let width = options.bucket_ns.max(1) as i128;
+    let span = to_ns.as_nanos() as i128 - from_ns.as_nanos() as i128 + 1;
+    let buckets = ((span + width - 1) / width).max(1);
+    // A single bucket has nothing to stand out from.
+    if buckets < 2 {
+        return Vec::new();
+    }
+    let mut volume = BTreeMap::<(&str, i128), (i64, u64)>::new();
+    for event in events {
+        if let Payload::Trade { size, .. } = event.payload {
+            let bucket =
+                (event.timestamp_ns.as_nanos() as i128 - from_ns.as_nanos() as i128) / width;
+            let entry = volume.entry((&event.symbol, bucket)).or_default();
+            entry.0 = entry.0.saturating_add(size);
+            entry.1 += 1;
+        }
+    }
+    let mut totals = BTreeMap::<&str, i64>::new();
+    for ((symbol, _), (size, _)) in &volume {
+        let total = totals.entry(symbol).or_default();
+        *total = total.saturating_add(*size);
+    }
+    let bound = |offset: i128| {
+        Timestamp::from_nanos((from_ns.as_nanos() as i128 + offset).min(i64::MAX as i128) as i64)
+    };
+    volume
+        .into_iter()
+        .filter_map(|((symbol, bucket), (size, trades))| {
+            let mean_volume = totals.get(symbol).copied().unwrap_or(0) as f64 / buckets as f64;
+            (mean_volume > 0.0 && size as f64 >= options.volume_multiple * mean_volume).then(|| {
+                VolumeSpike {
+                    symbol: symbol.to_string(),
+                    from_ns: bound(bucket * width),
+                    to_ns: bound((bucket + 1) * width),
+                    volume: size,
+                    trades,
+                    mean_volume,
+                }
+            })
+        })
+        .collect()
+}
+
+fn clock(ts: Timestamp) -> String {
+    ts.to_utc().format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+}
+
+fn seconds(ns: i64) -> String {
+    format!("{:.3} s", ns as f64 / 1e9)
+}
+
+/// Renders `digest` as plain text, a line per finding.
+pub fn render_text(digest: &Digest) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} .. {} ({}): {} events, {} trades, {} quotes, {} symbols",
+        clock(digest.from_ns),
+        clock(digest.to_ns),
+        seconds(digest.to_ns.nanos_since(digest.from_ns)),
+        digest.events,
+        digest.trades,
+        digest.quotes,
+        digest.symbols
+    );
+    out.push_str("largest price moves:\n");
+    for m in &digest.price_moves {
+        let _ = writeln!(
+            out,
+            "  {} {:+} ticks ({:+.1} bps) {} -> {}, range {} .. {} ({})",
+            m.symbol, m.change_ticks, m.change_bps, m.open, m.close, m.low, m.high, m.basis
+        );
+    }
+    out.push_str("spread blowouts:\n");
+    for b in &digest.spread_blowouts {
+        let lasted = match b.end_ns {
+            Some(end) => format!("for {}", seconds(end.nanos_since(b.start_ns))),
+            None => String::from("until the end"),
+        };
+        let _ = writeln!(
+            out,
+            "  {} {} {} ticks against a median of {} at {}, {lasted}",
+            b.symbol,
+            b.venue,
+            b.peak_spread_ticks,
+            b.median_spread_ticks,
+            clock(b.start_ns)
+        );
+    }
+    out.push_str("volume spikes:\n");
+    for s in &digest.volume_spikes {
+        let _ = writeln!(
+            out,
+            "  {} volume {} ({} trades) at {}, {:.1}x its mean of {:.1}",
+            s.symbol,
+            s.volume,
+            s.trades,
+            clock(s.from_ns),
+            s.volume as f64 / s.mean_volume,
+            s.mean_volume
+        );
+    }
+    out.push_str("halts:\n");
+    for h in &digest.halts {
+        let _ = writeln!(
+            out,
+            "  {} silent for {} from {} to {}",
+            h.symbol,
+            seconds(h.duration_ns),
+            clock(h.from_ns),
+            clock(h.to_ns)
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    const SEC: i64 = 1_000_000_000;
+
+    fn quote(ts: i64, symbol: &str, bid_px: i64, ask_px: i64) -> Event {
+        Event::quote(
+            ts,
+            0,
+            "X",
+            symbol,
+            QuoteTicks {
+                bid_px,
+                bid_sz: 1,
+                ask_px,
+                ask_sz: 1,
+            },
+        )
+    }
+
+    #[test]
+    fn finds_moves_blowouts_spikes_and_halts() {
+        let mut events = Vec::new();
+        for s in 0..10 {
+            events.push(quote(s * SEC, "AAPL", 100, 102));
+            events.push(Event::trade(s * SEC + 1, 0, "X", "AAPL", 101 + s, 10));
+        }
+        // MSFT's spread blows out from 2 to 20 ticks for two seconds.
+        for (s, ask) in [(0, 202), (1, 202), (3, 220), (4, 215), (5, 202), (9, 202)] {
+            events.push(quote(s * SEC, "MSFT", 200, ask));
+        }
+        events.push(Event::trade(6 * SEC, 0, "X", "MSFT", 201, 1_000));
+        // SPY goes quiet from 1 s to 9 s.
+        events.push(quote(SEC, "SPY", 49, 51));
+        events.push(quote(9 * SEC, "SPY", 48, 50));
+        for (i, event) in events.iter_mut().enumerate() {
+            event.sequence = i as u64 + 1;
+        }
+
+        let options = DigestOptions {
+            halt_ns: 5 * SEC,
+            ..DigestOptions::default()
+        };
+        let digest = build_digest(&events, None, None, &options);
+        assert_eq!((digest.events, digest.trades, digest.symbols), (29, 11, 3));
+        let aapl = &digest.price_moves[0];
+        assert_eq!((aapl.symbol.as_str(), aapl.basis), ("AAPL", "trades"));
+        assert_eq!((aapl.open, aapl.close, aapl.high), (101.0, 110.0, 110.0));
+        let spy = &digest.price_moves[1];
+        assert_eq!((spy.symbol.as_str(), spy.basis), ("SPY", "mids"));
+        assert_eq!((spy.change_ticks, spy.change_bps), (-1.0, -200.0));
+
+        let blowout = &digest.spread_blowouts[0];
+        assert_eq!(blowout.symbol, "MSFT");
+        assert_eq!(
+            (blowout.peak_spread_ticks, blowout.median_spread_ticks),
+            (20, 2)
+        );
+        assert_eq!(blowout.start_ns, Timestamp::from_nanos(3 * SEC));
+        assert_eq!(blowout.end_ns, Some(Timestamp::from_nanos(5 * SEC)));
+        assert_eq!(digest.spread_blowouts.len(), 1);
+
+        assert_eq!(digest.volume_spikes.len(), 1);
+        let spike = &digest.volume_spikes[0];
+        assert_eq!((spike.symbol.as_str(), spike.volume), ("MSFT", 1_000));
+        assert_eq!(spike.from_ns, Timestamp::from_nanos(6 * SEC));
+
+        assert_eq!(
+            digest.halts,
+            vec![Halt {
+                symbol: String::from("SPY"),
+                from_ns: Timestamp::from_nanos(SEC),
+                to_ns: Timestamp::from_nanos(9 * SEC),
+                duration_ns: 8 * SEC,
+            }]
+        );
+
+        let text = render_text(&digest);
+        assert!(text.contains("MSFT X 20 ticks against a median of 2"));
+        assert!(text.contains("SPY silent for 8.000 s"));
+
+        // Only the quiet stretch: nothing trades, SPY's halt is cut away.
+        let window = build_digest(
+            &events,
+            Some(Timestamp::from_nanos(2 * SEC)),
+            Some(Timestamp::from_nanos(2 * SEC + 1)),
+            &options,
+        );
+        assert_eq!(window.events, 2);
+        assert!(window.halts.is_empty() && window.spread_blowouts.is_empty());
+    }
+}
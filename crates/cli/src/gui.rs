@@ -1,13 +1,17 @@
+use crate::digest::{build_digest, render_text, Digest, DigestOptions};
 use anyhow::{anyhow, Result};
 use axum::extract::{Query, State};
+use axum::http::StatusCode;
 use axum::response::Html;
 use axum::routing::get;
 use axum::{Json, Router};
 use md_clients::{format_event, run_feature, FeatureConfig};
-use md_core::{Event, Payload};
+use md_core::{Event, Payload, Timestamp};
+use md_export::EventScript;
 use md_replay_engine::read_events;
+use md_storage::{EventLogFooter, EventLogReader};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -26,24 +30,41 @@ struct Meta {
     events: usize,
     trades: usize,
     quotes: usize,
-    first_timestamp_ns: u64,
-    last_timestamp_ns: u64,
+    first_timestamp_ns: Timestamp,
+    last_timestamp_ns: Timestamp,
     first_sequence: u64,
     last_sequence: u64,
     symbols: Vec<String>,
+    venues: Vec<VenueMeta>,
+    log_footer: Option<EventLogFooter>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct VenueMeta {
+    venue: String,
+    events: usize,
+    trades: usize,
+    quotes: usize,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
 struct DataQuery {
     symbol: Option<String>,
+    venue: Option<String>,
+    /// Keep a separate book per venue instead of one consolidated book.
+    by_venue: Option<bool>,
     from_seq: Option<u64>,
     to_seq: Option<u64>,
     limit: Option<usize>,
+    /// Rhai filter applied before `limit`.
+    filter: Option<String>,
+    /// Newline-separated `name=expr` derived columns.
+    derive: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 struct EventRow {
-    timestamp_ns: u64,
+    timestamp_ns: Timestamp,
     sequence: u64,
     venue: String,
     symbol: String,
@@ -54,18 +75,43 @@ struct EventRow {
     bid_sz: Option<i64>,
     ask_px: Option<i64>,
     ask_sz: Option<i64>,
+    derived: Vec<Option<f64>>,
 }
 
 #[derive(Debug, Serialize)]
 struct EventBatch {
+    /// Names of the `derived` values in each row.
+    derived: Vec<String>,
     rows: Vec<EventRow>,
 }
 
+#[derive(Debug, Clone, Deserialize, Default)]
+struct DigestQuery {
+    /// Nanoseconds or RFC 3339; open bounds cover the loaded events.
+    from: Option<String>,
+    to: Option<String>,
+    top: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct DigestPage {
+    #[serde(flatten)]
+    digest: Digest,
+    /// The digest as `md-replay digest` prints it.
+    text: String,
+}
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+/// Selected events and, with a script, their derived values.
+type Selected = (Vec<Event>, Vec<Vec<Option<f64>>>);
+
 #[derive(Debug, Serialize)]
 struct SeriesPoint {
     sequence: u64,
-    timestamp_ns: u64,
+    timestamp_ns: Timestamp,
     symbol: String,
+    /// `None` for consolidated series.
+    venue: Option<String>,
     mid: f64,
     spread: i64,
     imbalance: f64,
@@ -135,8 +181,8 @@ pub async fn serve_ui(
     index: Option<PathBuf>,
     compare_log: Option<PathBuf>,
     compare_index: Option<PathBuf>,
-    from_ns: Option<u64>,
-    to_ns: Option<u64>,
+    from_ns: Option<Timestamp>,
+    to_ns: Option<Timestamp>,
     addr: SocketAddr,
 ) -> Result<()> {
     let events = read_events(&log, index.as_deref(), from_ns, to_ns)?;
@@ -154,8 +200,11 @@ pub async fn serve_ui(
         None => None,
     };
 
+    let mut meta = build_meta(&events);
+    meta.log_footer = EventLogReader::open(&log)?.footer().cloned();
+
     let state = UiState {
-        meta: build_meta(&events),
+        meta,
         events: Arc::new(events),
         compare_events,
     };
@@ -166,6 +215,7 @@ pub async fn serve_ui(
         .route("/api/events", get(events_page))
         .route("/api/series", get(series_page))
         .route("/api/diff", get(diff_page))
+        .route("/api/digest", get(digest_page))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -184,40 +234,112 @@ async fn meta_page(State(state): State<UiState>) -> Json<Meta> {
 async fn events_page(
     State(state): State<UiState>,
     Query(query): Query<DataQuery>,
-) -> Json<EventBatch> {
-    let rows = select_events(&state.events, &query, 500)
+) -> ApiResult<EventBatch> {
+    let script = query_script(&query)?;
+    let (events, derived) = select_events(&state.events, &query, 500, script.as_ref())?;
+    let mut derived = derived.into_iter();
+    let rows = events
         .into_iter()
-        .map(to_row)
+        .map(|event| to_row(event, derived.next().unwrap_or_default()))
         .collect::<Vec<_>>();
-    Json(EventBatch { rows })
+    Ok(Json(EventBatch {
+        derived: script.map(|s| s.column_names()).unwrap_or_default(),
+        rows,
+    }))
 }
 
 async fn series_page(
     State(state): State<UiState>,
     Query(query): Query<DataQuery>,
-) -> Json<Vec<SeriesPoint>> {
-    let events = select_events(&state.events, &query, 3000);
-    Json(compute_series(&events, &FeatureConfig::default()))
+) -> ApiResult<Vec<SeriesPoint>> {
+    let script = query_script(&query)?;
+    let (events, _) = select_events(&state.events, &query, 3000, script.as_ref())?;
+    let by_venue = query.by_venue.unwrap_or(false);
+    Ok(Json(compute_series(
+        &events,
+        &FeatureConfig::default(),
+        by_venue,
+    )))
 }
 
 async fn diff_page(
     State(state): State<UiState>,
     Query(query): Query<DataQuery>,
-) -> Json<DiffReport> {
-    let base = select_events(&state.events, &query, 10_000);
+) -> ApiResult<DiffReport> {
+    let script = query_script(&query)?;
+    let (base, _) = select_events(&state.events, &query, 10_000, script.as_ref())?;
     let determinism = deterministic_report(&base);
-    let parser = state
-        .compare_events
-        .as_ref()
-        .map(|other| parser_diff(&base, &select_events(other, &query, 10_000)));
-    Json(DiffReport {
+    let parser = match &state.compare_events {
+        Some(other) => {
+            let (other, _) = select_events(other, &query, 10_000, script.as_ref())?;
+            Some(parser_diff(&base, &other))
+        }
+        None => None,
+    };
+    Ok(Json(DiffReport {
         determinism,
         parser,
-    })
+    }))
 }
 
-fn select_events(events: &[Event], query: &DataQuery, fallback_limit: usize) -> Vec<Event> {
+async fn digest_page(
+    State(state): State<UiState>,
+    Query(query): Query<DigestQuery>,
+) -> ApiResult<DigestPage> {
+    let bound = |raw: &Option<String>| {
+        raw.as_deref()
+            .map(str::trim)
+            .filter(|raw| !raw.is_empty())
+            .map(str::parse::<Timestamp>)
+            .transpose()
+            .map_err(bad_request)
+    };
+    let defaults = DigestOptions::default();
+    let options = DigestOptions {
+        top: query.top.unwrap_or(defaults.top).clamp(1, 100),
+        ..defaults
+    };
+    let digest = build_digest(
+        &state.events,
+        bound(&query.from)?,
+        bound(&query.to)?,
+        &options,
+    );
+    let text = render_text(&digest);
+    Ok(Json(DigestPage { digest, text }))
+}
+
+fn bad_request(err: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, err.to_string())
+}
+
+fn query_script(query: &DataQuery) -> Result<Option<EventScript>, (StatusCode, String)> {
+    let columns = query
+        .derive
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let filter = query.filter.as_deref().filter(|f| !f.trim().is_empty());
+    if filter.is_none() && columns.is_empty() {
+        return Ok(None);
+    }
+    EventScript::compile(filter, &columns)
+        .map(Some)
+        .map_err(bad_request)
+}
+
+fn select_events(
+    events: &[Event],
+    query: &DataQuery,
+    fallback_limit: usize,
+    script: Option<&EventScript>,
+) -> Result<Selected, (StatusCode, String)> {
     let mut out = Vec::new();
+    let mut derived = Vec::new();
     let limit = query.limit.unwrap_or(fallback_limit).clamp(1, 100_000);
 
     for event in events {
@@ -234,25 +356,52 @@ fn select_events(events: &[Event], query: &DataQuery, fallback_limit: usize) ->
         {
             continue;
         }
+        if query
+            .venue
+            .as_deref()
+            .is_some_and(|venue| !event.venue.eq_ignore_ascii_case(venue))
+        {
+            continue;
+        }
+        if let Some(script) = script {
+            match script.evaluate(event).map_err(bad_request)? {
+                Some(values) => derived.push(values),
+                None => continue,
+            }
+        }
         out.push(event.clone());
         if out.len() == limit {
             break;
         }
     }
 
-    out
+    Ok((out, derived))
 }
 
 fn build_meta(events: &[Event]) -> Meta {
     let mut symbols = BTreeSet::new();
+    let mut venues = BTreeMap::<String, VenueMeta>::new();
     let mut trades = 0usize;
     let mut quotes = 0usize;
 
     for event in events {
         symbols.insert(event.symbol.clone());
+        let venue = venues
+            .entry(event.venue.clone())
+            .or_insert_with(|| VenueMeta {
+                venue: event.venue.clone(),
+                ..VenueMeta::default()
+            });
+        venue.events += 1;
         match &event.payload {
-            Payload::Trade { .. } => trades += 1,
-            Payload::Quote { .. } => quotes += 1,
+            Payload::Trade { .. } => {
+                trades += 1;
+                venue.trades += 1;
+            }
+            Payload::Quote { .. } => {
+                quotes += 1;
+                venue.quotes += 1;
+            }
         }
     }
 
@@ -262,15 +411,17 @@ fn build_meta(events: &[Event]) -> Meta {
         events: events.len(),
         trades,
         quotes,
-        first_timestamp_ns: first.map_or(0, |e| e.timestamp_ns),
-        last_timestamp_ns: last.map_or(0, |e| e.timestamp_ns),
+        first_timestamp_ns: first.map_or(Timestamp::UNIX_EPOCH, |e| e.timestamp_ns),
+        last_timestamp_ns: last.map_or(Timestamp::UNIX_EPOCH, |e| e.timestamp_ns),
         first_sequence: first.map_or(0, |e| e.sequence),
         last_sequence: last.map_or(0, |e| e.sequence),
         symbols: symbols.into_iter().collect(),
+        venues: venues.into_values().collect(),
+        log_footer: None,
     }
 }
 
-fn to_row(event: Event) -> EventRow {
+fn to_row(event: Event, derived: Vec<Option<f64>>) -> EventRow {
     match event.payload {
         Payload::Trade { price_ticks, size } => EventRow {
             timestamp_ns: event.timestamp_ns,
@@ -284,6 +435,7 @@ fn to_row(event: Event) -> EventRow {
             bid_sz: None,
             ask_px: None,
             ask_sz: None,
+            derived,
         },
         Payload::Quote {
             bid_px,
@@ -302,16 +454,18 @@ fn to_row(event: Event) -> EventRow {
             bid_sz: Some(bid_sz),
             ask_px: Some(ask_px),
             ask_sz: Some(ask_sz),
+            derived,
         },
     }
 }
 
-fn compute_series(events: &[Event], cfg: &FeatureConfig) -> Vec<SeriesPoint> {
-    let mut st = std::collections::BTreeMap::<String, BookState>::new();
+fn compute_series(events: &[Event], cfg: &FeatureConfig, by_venue: bool) -> Vec<SeriesPoint> {
+    let mut st = BTreeMap::<(String, Option<String>), BookState>::new();
     let mut out = Vec::with_capacity(events.len());
 
     for event in events {
-        let book = st.entry(event.symbol.clone()).or_default();
+        let venue = by_venue.then(|| event.venue.clone());
+        let book = st.entry((event.symbol.clone(), venue.clone())).or_default();
         match &event.payload {
             Payload::Quote {
                 bid_px,
@@ -387,6 +541,7 @@ fn compute_series(events: &[Event], cfg: &FeatureConfig) -> Vec<SeriesPoint> {
             sequence: event.sequence,
             timestamp_ns: event.timestamp_ns,
             symbol: event.symbol.clone(),
+            venue,
             mid: rolling_mid,
             spread,
             imbalance,
@@ -512,11 +667,72 @@ mod tests {
             ),
             Event::trade(2, 2, "X", "AAPL", 150, 4),
         ];
-        let out = compute_series(&events, &FeatureConfig::default());
+        let out = compute_series(&events, &FeatureConfig::default(), false);
         assert_eq!(out.len(), 2);
         assert!(out[0].signal.is_some());
     }
 
+    #[test]
+    fn series_keeps_separate_books_per_venue() {
+        let quote = |ts: i64, venue: &str, bid_px: i64, ask_px: i64| {
+            Event::quote(
+                ts,
+                ts as u64,
+                venue,
+                "AAPL",
+                QuoteTicks {
+                    bid_px,
+                    bid_sz: 10,
+                    ask_px,
+                    ask_sz: 10,
+                },
+            )
+        };
+        let events = vec![quote(1, "XNAS", 100, 102), quote(2, "ARCX", 100, 110)];
+        let cfg = FeatureConfig::default();
+
+        let consolidated = compute_series(&events, &cfg, false);
+        assert_eq!(consolidated[1].spread, 10);
+        assert!(consolidated[1].venue.is_none());
+
+        let split = compute_series(&events, &cfg, true);
+        assert_eq!(split[0].venue.as_deref(), Some("XNAS"));
+        assert_eq!(split[1].venue.as_deref(), Some("ARCX"));
+        assert_eq!((split[0].spread, split[1].spread), (2, 10));
+
+        let meta = build_meta(&events);
+        assert_eq!(
+            meta.venues
+                .iter()
+                .map(|v| v.venue.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ARCX", "XNAS"]
+        );
+
+        let query = DataQuery {
+            venue: Some(String::from("xnas")),
+            ..DataQuery::default()
+        };
+        let (selected, _) = select_events(&events, &query, 10, None).expect("select");
+        assert_eq!(selected.len(), 1);
+
+        let query = DataQuery {
+            filter: Some(String::from("bid_px == 100 && ask_px > 105")),
+            derive: Some(String::from("spread = ask_px - bid_px\n")),
+            ..DataQuery::default()
+        };
+        let script = query_script(&query).expect("script");
+        let (selected, derived) =
+            select_events(&events, &query, 10, script.as_ref()).expect("select");
+        assert_eq!(selected[0].venue, "ARCX");
+        assert_eq!(derived, vec![vec![Some(10.0)]]);
+        let bad = DataQuery {
+            filter: Some(String::from("bid_px ==")),
+            ..DataQuery::default()
+        };
+        assert!(query_script(&bad).is_err());
+    }
+
     #[test]
     fn parser_diff_detects_change() {
         let left = vec![Event::trade(1, 1, "X", "AAPL", 100, 1)];
@@ -1,22 +1,60 @@
+mod diff;
+mod digest;
 mod gui;
 
 use anyhow::{anyhow, Context, Result};
 use clap::{Args, Parser, Subcommand};
-use md_clients::{format_event, run_feature, verify_feature_determinism, FeatureConfig};
-use md_core::TickTable;
-use md_ingest::gen_pcap::generate_pcap;
-use md_ingest::{ingest_csv_a, ingest_csv_b, ingest_csv_c, ingest_pcap, ingest_yahoo};
-use md_replay_engine::{read_events, serve_grpc, ReplayConfig};
-use md_storage::{default_schema_hash, EventLogReader, EventLogWriter, IndexWriter};
+use diff::{diff_books, diff_events, render_html, BookDiff, DiffOptions};
+use digest::{build_digest, render_text, DigestOptions};
+use md_clients::{
+    format_event, run_feature, run_stats, verify_feature_determinism, FeatureConfig, SymbolStats,
+};
+use md_core::{Event, EventType, OrderEvent, PendingEvent, TickTable, Timestamp, VenueRegistry};
+use md_export::{
+    format_derived, join_logs, join_orders, scaled_columns, write_arrow_file_with,
+    write_arrow_stream_with, write_jsonl_with, write_parquet_with, Derived, EventScript,
+    JoinOptions,
+};
+use md_ingest::gen_pcap::{generate_capture, CaptureFormat, ClockModel};
+use md_ingest::gen_tape::{generate_tape, TapeConfig, VenueFeed};
+use md_ingest::{
+    apply_clock_offsets, capture_live, checkpoint_path_for, drop_duplicates,
+    estimate_clock_offsets, expand_inputs, for_each_csv_a_with, for_each_csv_b_with,
+    for_each_csv_c_with, for_each_csv_with_schema, for_each_jsonl, ingest_alphavantage,
+    ingest_files, ingest_fix, ingest_mdp3, ingest_orders, ingest_pcap_with, ingest_polygon,
+    ingest_soup, ingest_stooq, ingest_tiingo, ingest_yahoo_with, live_provider, load_security_map,
+    parse_clock_offset, parse_csv_a, parse_csv_b, parse_csv_c, parse_jsonl, scan_symbols,
+    stream_files_from, stream_provider, Alert, AlertMonitor, AlertThresholds, AlphaVantageOptions,
+    BarExpansion, ClockOffsets, CsvSchema, CsvTimestamp, CsvTimestampFormat, DedupReport,
+    Deduplicator, FixTagMap, Framing, GapReport, GatewayFormat, HttpOptions, IngestCheckpoint,
+    IngestError, IngestReport, LiveItem, LiveOptions, LiveProvider, Notification, Notifier,
+    ParseIssue, PolygonOptions, Provenance, ProviderOptions, ResponseCache, RetryPolicy,
+    RowVisitor, StooqOptions, TiingoOptions, TimestampSpec, YahooOptions, DEFAULT_CACHE_DIR,
+};
+use md_replay_engine::{
+    publish, read_audit_log, read_events, replay_requests, run_stress, serve_grpc,
+    AnonymizeSymbols, AuditLog, Backpressure, Compression, LogFile, LogSource, NatsOptions,
+    NatsSink, RedisOptions, RedisSink, ReplayConfig, ReplayRequestsOptions, RescaleSizes,
+    ServerSecurity, SessionLimits, ShiftTimestamps, SpawnStrategy, StreamTuning, StressOptions,
+    StressProfile, StressReport, TlsIdentity, Transforms, WasmPlugin, ZmqOptions, ZmqSink,
+    MIN_EVENTS_PER_SEC,
+};
+use md_storage::{
+    default_schema_hash, filter_log, log_stats, merge_logs, needs_migration, repair_log,
+    slice_top_symbols, split_log, write_order_log, ActivityBy, EventFilter, EventLogReader,
+    EventLogWriter, IndexReader, IndexWriter, LogStats, SchemaVersion, SplitBy,
+};
 use rand::Rng;
 use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::hint::black_box;
+use std::io::{BufWriter, Write};
 use std::net::SocketAddr;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
-use tracing::info;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
@@ -31,21 +69,234 @@ enum Command {
     IngestCsvA(IngestCsvArgs),
     IngestCsvB(IngestCsvArgs),
     IngestCsvC(IngestCsvArgs),
-    IngestReal(IngestRealArgs),
+    /// Ingests any CSV laid out as a `--schema` mapping describes.
+    IngestCsv(IngestCsvSchemaArgs),
+    IngestJsonl(IngestJsonlArgs),
+    IngestReal(Box<IngestRealArgs>),
     IngestPcap(IngestPcapArgs),
+    IngestSoup(IngestSoupArgs),
+    IngestLive(IngestLiveArgs),
+    IngestOrders(IngestOrdersArgs),
+    IngestFix(IngestFixArgs),
     GenPcap(GenPcapArgs),
-    Serve(ServeArgs),
+    GenTape(GenTapeArgs),
+    Serve(Box<ServeArgs>),
+    ReplayRequests(ReplayRequestsArgs),
+    Stress(StressArgs),
     Ui(UiArgs),
     Print(ReadArgs),
     Feature(FeatureArgs),
+    Stats(StatsArgs),
     Verify(VerifyArgs),
+    Diff(DiffArgs),
+    /// Summarizes an interval: price moves, spread blowouts, volume spikes
+    /// and halts.
+    Digest(DigestArgs),
     Bench(BenchArgs),
+    Info(InfoArgs),
+    Migrate(MigrateArgs),
+    Export(ExportArgs),
+    Join(JoinArgs),
+    JoinOrders(JoinOrdersArgs),
+    Merge(MergeArgs),
+    Split(SplitArgs),
+    Filter(FilterArgs),
+    /// Keeps only the most active symbols of a log.
+    Slice(SliceArgs),
+    Repair(RepairArgs),
 }
 
 #[derive(Args)]
-struct IngestCsvArgs {
+struct VenueArgs {
     #[arg(long)]
-    input: PathBuf,
+    venue_config: Option<PathBuf>,
+    #[arg(long, default_value = "warn")]
+    unknown_venue: String,
+}
+
+// Duplicate dropping, for multicast captures and merged A/B feeds.
+#[derive(Args)]
+struct DedupArgs {
+    /// Drop events repeating an earlier one's timestamp, venue, symbol and
+    /// payload. Runs after venue normalization, so A and B feeds under
+    /// different venue aliases still match.
+    #[arg(long, default_value_t = false)]
+    dedup: bool,
+    /// Write kept and dropped counts, with drops per symbol, as JSON.
+    #[arg(long, requires = "dedup")]
+    dedup_report: Option<PathBuf>,
+}
+
+impl DedupArgs {
+    /// Returns how many events were dropped.
+    fn apply(&self, events: &mut Vec<Event>) -> Result<u64> {
+        if !self.dedup {
+            return Ok(0);
+        }
+        let report = drop_duplicates(events);
+        self.finish(&report)?;
+        Ok(report.dropped)
+    }
+
+    fn stage(&self) -> Option<Deduplicator> {
+        self.dedup.then(Deduplicator::default)
+    }
+
+    fn finish(&self, report: &DedupReport) -> Result<()> {
+        let by_symbol = report
+            .by_symbol
+            .iter()
+            .map(|(symbol, count)| format!("{symbol}={count}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        info!(
+            kept = report.kept,
+            dropped = report.dropped,
+            by_symbol = %by_symbol,
+            "dropped duplicates"
+        );
+        if let Some(path) = &self.dedup_report {
+            std::fs::write(path, serde_json::to_string_pretty(report)?)
+                .with_context(|| format!("failed writing {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+// Per-venue capture clock correction, applied after venue normalization
+// and before duplicates are dropped.
+#[derive(Args)]
+struct ClockArgs {
+    /// `VENUE=OFFSET` added to that venue's timestamps, e.g. `XNAS=+1500us`
+    /// (units ns, us, ms, s); repeat for more venues. `auto` estimates the
+    /// rest from trades printed on more than one venue.
+    #[arg(long)]
+    clock_offset: Vec<String>,
+    /// Venue `auto` lines the others up with; by default the one with the
+    /// most trades.
+    #[arg(long)]
+    clock_reference: Option<String>,
+    /// Farthest apart two prints may be to count as the same trade.
+    #[arg(long, default_value_t = 1_000_000_000)]
+    clock_max_skew_ns: i64,
+}
+
+impl ClockArgs {
+    /// Explicit offsets, and whether `auto` was asked for.
+    fn offsets(&self) -> Result<(ClockOffsets, bool)> {
+        let mut offsets = ClockOffsets::new();
+        let mut auto = false;
+        for raw in &self.clock_offset {
+            if raw == "auto" {
+                auto = true;
+                continue;
+            }
+            let (venue, ns) = parse_clock_offset(raw)?;
+            offsets.insert(venue, ns);
+        }
+        Ok((offsets, auto))
+    }
+
+    fn apply(&self, events: &mut [Event]) -> Result<()> {
+        let (mut offsets, auto) = self.offsets()?;
+        if auto {
+            let estimates = estimate_clock_offsets(
+                events,
+                self.clock_reference.as_deref(),
+                self.clock_max_skew_ns,
+            );
+            for estimate in estimates {
+                info!(
+                    venue = %estimate.venue,
+                    offset_ns = estimate.offset_ns,
+                    matches = estimate.matches,
+                    "estimated clock offset"
+                );
+                offsets.entry(estimate.venue).or_insert(estimate.offset_ns);
+            }
+        }
+        apply_clock_offsets(events, &offsets);
+        Ok(())
+    }
+}
+
+/// What runs on parsed events before they are written, in field order.
+struct EventStages<'a> {
+    venues: &'a VenueArgs,
+    clock: &'a ClockArgs,
+    dedup: &'a DedupArgs,
+}
+
+impl EventStages<'_> {
+    /// Returns how many events were dropped.
+    fn apply(&self, events: &mut Vec<Event>) -> Result<u64> {
+        normalize_venues(events, self.venues)?;
+        self.clock.apply(events)?;
+        self.dedup.apply(events)
+    }
+}
+
+// Ingest summary in the one shape every source shares.
+#[derive(Args)]
+struct ReportArgs {
+    /// Write counts, skipped rows, issues, time range and provenance as
+    /// JSON.
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+impl ReportArgs {
+    fn write(&self, report: &IngestReport) -> Result<()> {
+        if let Some(path) = &self.report {
+            std::fs::write(path, serde_json::to_string_pretty(report)?)
+                .with_context(|| format!("failed writing {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Reports a log written in one go from `events`.
+    fn write_batch(
+        &self,
+        provenance: Provenance,
+        events: &[Event],
+        issues: &[ParseIssue],
+        skipped: u64,
+    ) -> Result<()> {
+        let mut report = IngestReport::new(provenance);
+        report.record_all(events);
+        report.record_issues(issues);
+        report.skip(skipped);
+        self.write(&report)
+    }
+}
+
+// Rhai filter and derived columns, applied after any plugin.
+#[derive(Args)]
+struct ScriptArgs {
+    /// Keep only events for which this expression is true.
+    #[arg(long)]
+    filter: Option<String>,
+    /// Derived column as `name=expr`; repeat for more columns.
+    #[arg(long = "derive")]
+    derive: Vec<String>,
+}
+
+impl ScriptArgs {
+    fn apply(&self, events: Vec<Event>) -> Result<(Vec<Event>, Derived)> {
+        if self.filter.is_none() && self.derive.is_empty() {
+            return Ok((events, Derived::default()));
+        }
+        let script = EventScript::compile(self.filter.as_deref(), &self.derive)?;
+        Ok(script.apply(events)?)
+    }
+}
+
+#[derive(Args)]
+struct IngestCsvArgs {
+    /// Input file or glob pattern; repeat for more. Files are parsed in
+    /// parallel and merged by timestamp.
+    #[arg(long, required = true)]
+    input: Vec<String>,
     #[arg(long)]
     venue: String,
     #[arg(long)]
@@ -54,6 +305,119 @@ struct IngestCsvArgs {
     index_stride: u32,
     #[arg(long)]
     tick_config: Option<PathBuf>,
+    #[command(flatten)]
+    timestamps: TimestampArgs,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    clock: ClockArgs,
+    #[command(flatten)]
+    dedup: DedupArgs,
+    #[command(flatten)]
+    stream: StreamIngestArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+// How the timestamp column is read, in place of the layout's default
+// (RFC 3339 for A, epoch milliseconds for B, mixed for C) or the schema's.
+#[derive(Args)]
+struct TimestampArgs {
+    /// rfc3339, s, ms, us, ns, mixed, or a chrono pattern such as
+    /// `%Y-%m-%d %H:%M:%S%.f`.
+    #[arg(long)]
+    ts_format: Option<String>,
+    /// Epoch unit: s, ms, us or ns.
+    #[arg(long, conflicts_with = "ts_format")]
+    ts_unit: Option<String>,
+    /// IANA zone naive timestamps are in, e.g. `America/New_York`; UTC by
+    /// default.
+    #[arg(long)]
+    tz: Option<String>,
+}
+
+impl TimestampArgs {
+    fn format(&self) -> Result<Option<CsvTimestampFormat>> {
+        if let Some(unit) = &self.ts_unit {
+            if !matches!(unit.as_str(), "s" | "ms" | "us" | "ns") {
+                return Err(anyhow!(
+                    "unknown --ts-unit {unit}, expected s, ms, us or ns"
+                ));
+            }
+        }
+        self.ts_format
+            .as_ref()
+            .or(self.ts_unit.as_ref())
+            .map(|raw| CsvTimestampFormat::try_from(raw.clone()).map_err(|e| anyhow!(e)))
+            .transpose()
+    }
+
+    fn spec(&self, default: CsvTimestampFormat) -> Result<TimestampSpec> {
+        let format = self.format()?.unwrap_or(default);
+        Ok(TimestampSpec::new(format, self.tz.as_deref())?)
+    }
+
+    fn override_schema(&self, timestamp: &mut CsvTimestamp) -> Result<()> {
+        if let Some(format) = self.format()? {
+            timestamp.format = format;
+        }
+        if let Some(tz) = &self.tz {
+            timestamp.tz = Some(tz.clone());
+        }
+        Ok(())
+    }
+}
+
+// Bounded-memory ingest for inputs larger than RAM.
+#[derive(Args)]
+struct StreamIngestArgs {
+    /// Write the log while reading, holding only `--reorder-window-ns` of
+    /// rows in memory. Inputs are read in order, not in parallel.
+    #[arg(long, default_value_t = false)]
+    stream: bool,
+    /// How far out of timestamp order rows may arrive with `--stream`.
+    #[arg(long, default_value_t = 1_000_000_000)]
+    reorder_window_ns: u64,
+    /// Save progress to `<out>.checkpoint` every this many events; 0 never
+    /// does.
+    #[arg(long, default_value_t = 1_000_000)]
+    checkpoint_events: u64,
+    /// Continue an interrupted `--stream` ingest from its checkpoint; with
+    /// none, start over.
+    #[arg(long, default_value_t = false, requires = "stream")]
+    resume: bool,
+}
+
+#[derive(Args)]
+struct IngestCsvSchemaArgs {
+    /// TOML mapping of columns, timestamp format and row kinds.
+    #[arg(long)]
+    schema: PathBuf,
+    #[command(flatten)]
+    csv: IngestCsvArgs,
+}
+
+#[derive(Args)]
+struct IngestJsonlArgs {
+    /// Input file or glob pattern; repeat for more.
+    #[arg(long, required = true)]
+    input: Vec<String>,
+    #[arg(long)]
+    venue: String,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    clock: ClockArgs,
+    #[command(flatten)]
+    dedup: DedupArgs,
+    #[command(flatten)]
+    stream: StreamIngestArgs,
+    #[command(flatten)]
+    report: ReportArgs,
 }
 
 #[derive(Args)]
@@ -68,6 +432,46 @@ struct IngestPcapArgs {
     out: PathBuf,
     #[arg(long, default_value_t = 1024)]
     index_stride: u32,
+    /// raw (one message per datagram) or mold (MoldUDP64).
+    #[arg(long, default_value = "raw")]
+    framing: Framing,
+    /// Tick sizes for converting mdp3 prices.
+    #[arg(long)]
+    tick_config: Option<PathBuf>,
+    /// `security_id,symbol` CSV naming mdp3 instruments.
+    #[arg(long)]
+    security_map: Option<PathBuf>,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    clock: ClockArgs,
+    #[command(flatten)]
+    dedup: DedupArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+/// `--input` is a pcap/pcapng session capture or a raw SoupBinTCP stream.
+#[derive(Args)]
+struct IngestSoupArgs {
+    #[arg(long)]
+    input: PathBuf,
+    #[arg(long)]
+    schema: String,
+    #[arg(long)]
+    venue: String,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    clock: ClockArgs,
+    #[command(flatten)]
+    dedup: DedupArgs,
+    #[command(flatten)]
+    report: ReportArgs,
 }
 
 #[derive(Args)]
@@ -88,6 +492,161 @@ struct IngestRealArgs {
     index_stride: u32,
     #[arg(long)]
     tick_config: Option<PathBuf>,
+    #[arg(long, default_value_t = 1)]
+    expand_bars: usize,
+    #[arg(long, default_value_t = 42)]
+    expand_seed: u64,
+    #[arg(long)]
+    http_config: Option<PathBuf>,
+    /// HTTP(S) proxy for provider requests; overrides `--http-config` and
+    /// `MD_REPLAY_HTTP_PROXY`.
+    #[arg(long)]
+    proxy: Option<String>,
+    /// PEM bundle added to the trusted roots; overrides `--http-config` and
+    /// `MD_REPLAY_CA_CERT`.
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// Per-request timeout; overrides `--http-config` and
+    /// `MD_REPLAY_HTTP_TIMEOUT_SECS`.
+    #[arg(long)]
+    http_timeout_secs: Option<u64>,
+    #[arg(long, default_value_t = md_ingest::yahoo::DEFAULT_CONCURRENCY)]
+    concurrency: usize,
+    /// Yahoo retries of a throttled (429) or failed (5xx) request.
+    #[arg(long, default_value_t = RetryPolicy::default().max_retries)]
+    max_retries: u32,
+    /// Cap on Yahoo requests started per second.
+    #[arg(long)]
+    requests_per_sec: Option<f64>,
+    /// Where raw provider responses are cached between runs.
+    #[arg(long, default_value = DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+    /// Always fetch from the provider, and cache nothing.
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+    #[arg(long, default_value_t = false)]
+    since_last: bool,
+    /// API key for Polygon, Alpha Vantage or Tiingo; falls back to
+    /// `POLYGON_API_KEY`, `ALPHAVANTAGE_API_KEY` or `TIINGO_API_KEY`.
+    #[arg(long)]
+    api_key: Option<String>,
+    /// Alpha Vantage month to fetch (`YYYY-MM`) instead of the last 30 days.
+    #[arg(long)]
+    month: Option<String>,
+    #[arg(long, default_value_t = md_ingest::alphavantage::DEFAULT_REQUESTS_PER_MINUTE)]
+    requests_per_minute: u32,
+    /// Start of the Polygon, Stooq or Tiingo window (inclusive), ns or
+    /// RFC 3339. Daily providers round it to the UTC date.
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    /// End of the window, ns or RFC 3339: exclusive for Polygon, the last
+    /// date fetched for daily providers.
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    /// Websocket base URL for streaming providers.
+    #[arg(long)]
+    stream_url: Option<String>,
+    #[arg(long, default_value_t = 500_000_000)]
+    reorder_window_ns: u64,
+    #[command(flatten)]
+    limits: LiveLimitArgs,
+    #[command(flatten)]
+    alerts: AlertArgs,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+// When a live ingest syncs the log and when it stops.
+#[derive(Args)]
+struct LiveLimitArgs {
+    #[arg(long, default_value_t = 1000)]
+    flush_ms: u64,
+    #[arg(long)]
+    max_events: Option<u64>,
+    #[arg(long)]
+    duration_secs: Option<u64>,
+}
+
+// Health checks on a live ingest, evaluated once per window.
+#[derive(Args)]
+struct AlertArgs {
+    #[arg(long, default_value_t = 60)]
+    alert_window_secs: u64,
+    /// Alert when fewer events than this arrive per second.
+    #[arg(long)]
+    alert_min_rate: Option<f64>,
+    #[arg(long)]
+    alert_max_gaps_per_min: Option<f64>,
+    /// Alert when this fraction of messages fails to decode.
+    #[arg(long)]
+    alert_max_issue_ratio: Option<f64>,
+    /// Expected quotes per trade as `MIN:MAX`.
+    #[arg(long)]
+    alert_quote_trade_ratio: Option<String>,
+    /// POST each alert, and a failed capture, as JSON to this URL.
+    #[arg(long)]
+    alert_webhook: Option<String>,
+}
+
+impl AlertArgs {
+    fn thresholds(&self) -> Result<AlertThresholds> {
+        let quote_trade_ratio = self
+            .alert_quote_trade_ratio
+            .as_deref()
+            .map(|raw| {
+                raw.split_once(':')
+                    .and_then(|(min, max)| {
+                        Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+                    })
+                    .filter(|(min, max): &(f64, f64)| min <= max)
+                    .ok_or_else(|| {
+                        anyhow!("invalid --alert-quote-trade-ratio {raw}, expected MIN:MAX")
+                    })
+            })
+            .transpose()?;
+        Ok(AlertThresholds {
+            min_events_per_sec: self.alert_min_rate,
+            max_gaps_per_min: self.alert_max_gaps_per_min,
+            max_issue_ratio: self.alert_max_issue_ratio,
+            quote_trade_ratio,
+            window: Duration::from_secs(self.alert_window_secs.max(1)),
+        })
+    }
+}
+
+#[derive(Args)]
+struct IngestLiveArgs {
+    #[arg(long)]
+    iface: String,
+    #[arg(long)]
+    schema: String,
+    #[arg(long)]
+    venue: String,
+    #[arg(long)]
+    out: PathBuf,
+    /// Symbols written to the log header; packets for other symbols are
+    /// dropped.
+    #[arg(long)]
+    symbols: String,
+    /// BPF capture filter, e.g. "udp port 50000".
+    #[arg(long)]
+    filter: Option<String>,
+    #[arg(long, default_value = "raw")]
+    framing: Framing,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+    #[arg(long, default_value_t = 1_000_000)]
+    reorder_window_ns: u64,
+    #[command(flatten)]
+    limits: LiveLimitArgs,
+    #[command(flatten)]
+    alerts: AlertArgs,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    report: ReportArgs,
 }
 
 #[derive(Args)]
@@ -100,26 +659,272 @@ struct GenPcapArgs {
     events: usize,
     #[arg(long, default_value_t = 42)]
     seed: u64,
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    clock_offset_ns: i64,
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    clock_drift_ppm: f64,
+    #[arg(long, default_value_t = 0)]
+    clock_jitter_ns: u64,
+    #[arg(long, default_value = "pcap")]
+    format: CaptureFormat,
+    #[arg(long, default_value = "raw")]
+    framing: Framing,
+}
+
+#[derive(Args)]
+struct GenTapeArgs {
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value = "AAPL")]
+    symbols: String,
+    /// Comma-separated `VENUE[:LATENCY_NS]` list.
+    #[arg(long, default_value = "XNAS,ARCX:150000,BATS:400000")]
+    venues: String,
+    #[arg(long, default_value_t = 1000)]
+    updates: usize,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    #[arg(long, default_value_t = 0.005)]
+    crossed_rate: f64,
+    #[arg(long, default_value_t = 3)]
+    crossed_quotes: usize,
+    #[arg(long, default_value_t = 0.2)]
+    trade_rate: f64,
+    #[arg(long, default_value_t = 0)]
+    jitter_ns: u64,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
 }
 
 #[derive(Args)]
 struct ServeArgs {
+    /// Repeat with `--stitch` to serve daily logs as one replay, or with
+    /// `--merge` to serve logs of the same period as one feed.
+    #[arg(long, required_unless_present = "source")]
+    log: Vec<PathBuf>,
+    /// Ingests `KIND:INPUT` into memory at startup and serves it without
+    /// writing a log. KIND is csv_a, csv_b, csv_c or jsonl, and INPUT may
+    /// be a glob; repeat to merge inputs.
+    #[arg(long, conflicts_with_all = ["log", "index", "stitch", "merge"])]
+    source: Vec<String>,
+    /// Venue of `--source` rows.
+    #[arg(long, default_value = "X")]
+    venue: String,
+    /// Tick config for `--source` prices.
     #[arg(long)]
-    log: PathBuf,
+    tick_config: Option<PathBuf>,
+    /// Only with a single `--log`; stitched days and merged logs use their
+    /// sidecar indexes.
     #[arg(long)]
     index: Option<PathBuf>,
+    /// Chains the `--log` days in the order given, skipping the gap between
+    /// them and marking where each day starts.
+    #[arg(long, default_value_t = false)]
+    stitch: bool,
+    /// Interleaves the `--log` files by timestamp as they stream, such as
+    /// one log per venue. Ties go to the log given first.
+    #[arg(long, default_value_t = false, conflicts_with = "stitch")]
+    merge: bool,
+    /// Shortens longer pauses between events to this, in event time; with
+    /// `--stitch` the gap between days is shortened to it instead of skipped.
+    #[arg(long)]
+    max_gap_ns: Option<i64>,
     #[arg(long, default_value = "1x")]
     speed: String,
-    #[arg(long)]
-    from: Option<u64>,
-    #[arg(long)]
-    to: Option<u64>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
     #[arg(long, default_value_t = false)]
     max_speed: bool,
     #[arg(long, default_value_t = false)]
     step_mode: bool,
+    /// Attach mid, spread, imbalance and volatility to every streamed
+    /// trade and quote, as if each request set `enrich`.
+    #[arg(long, default_value_t = false)]
+    enrich: bool,
+    /// `SYMBOL=TIME`: replay the symbol from this historical time, lined
+    /// up with the earliest such start. Repeat per symbol; requests may
+    /// send their own.
+    #[arg(long)]
+    symbol_start: Vec<String>,
+    /// UTC time of the closing cross, `HH:MM:SS[.fff]`. Events stamped
+    /// exactly then go out together instead of paced one by one.
+    #[arg(long, value_parser = parse_time_of_day)]
+    closing_cross: Option<i64>,
+    /// Send each closing cross as one `ClosingCross` message, as if each
+    /// request set `batch_closing_cross`.
+    #[arg(long, default_value_t = false)]
+    batch_closing_cross: bool,
+    /// Send events that are due together as `EventBatch` messages of up
+    /// to this many, for requests that set no `max_batch` of their own.
+    #[arg(long, default_value_t = 0)]
+    max_batch: usize,
+    /// Delays every paced event by this much wall-clock time, for
+    /// requests that set no `latency_ns` of their own.
+    #[arg(long, default_value_t = 0)]
+    latency_ns: i64,
+    /// Delays each paced event by a further random amount up to this,
+    /// drawn from `--jitter-seed`.
+    #[arg(long, default_value_t = 0)]
+    jitter_ns: i64,
+    #[arg(long, default_value_t = 0)]
+    jitter_seed: u64,
+    /// Chance, from 0 to 1, that an event starts a simulated loss, for
+    /// requests that set no `drop_rate` of their own.
+    #[arg(long, default_value_t = 0.0)]
+    drop_rate: f64,
+    /// Events lost together once a loss starts.
+    #[arg(long, default_value_t = 1)]
+    drop_burst: usize,
+    #[arg(long, default_value_t = 0)]
+    drop_seed: u64,
+    /// Send each event at its UTC time of day on today's clock instead of
+    /// from the moment a stream opens, as if each request set `wall_clock`.
+    #[arg(long, default_value_t = false)]
+    wall_clock: bool,
+    /// Sends a resume checkpoint after about every N events, for requests
+    /// that set no `checkpoint_every` of their own; 0 sends none.
+    #[arg(long, default_value_t = 0)]
+    checkpoint_every: usize,
+    /// Rewrites events before they are paced, in the order given:
+    /// `rescale-sizes=FACTOR`, `anonymize-symbols[=SALT]` or
+    /// `shift-ns=NANOS`. Request filters still match the stored symbols.
+    #[arg(long = "transform", allow_hyphen_values = true)]
+    transforms: Vec<String>,
+    /// Sends at most this many events a second, max speed included, for
+    /// requests that set no `max_events_per_sec` of their own. At least
+    /// 0.001; lower request caps are raised to that.
+    #[arg(long, value_parser = parse_events_per_sec)]
+    max_events_per_sec: Option<f64>,
     #[arg(long, default_value = "127.0.0.1:50051")]
     addr: String,
+    #[arg(long)]
+    max_session_events: Option<u64>,
+    #[arg(long)]
+    max_session_secs: Option<u64>,
+    #[arg(long)]
+    max_sessions_per_token: Option<usize>,
+    /// Messages a session may queue for a slow client before
+    /// `--backpressure` applies.
+    #[arg(long, default_value_t = StreamTuning::default().channel_depth)]
+    channel_depth: usize,
+    /// Where each session's pacing runs: `task` on the shared runtime, or
+    /// `thread` on a dedicated thread with its own runtime.
+    #[arg(long, default_value = "task")]
+    spawn: SpawnStrategy,
+    /// What a session does once `--channel-depth` messages wait for its
+    /// client: `block` its pacing, or `drop-oldest` of them and go on.
+    /// Requests may choose their own.
+    #[arg(long, default_value = "block")]
+    backpressure: Backpressure,
+    /// Messages built at once, ahead of their send time.
+    #[arg(long, default_value_t = StreamTuning::default().read_ahead)]
+    read_ahead: usize,
+    /// Compress replies to clients that accept it: `gzip` or `zstd`.
+    #[arg(long)]
+    compression: Option<Compression>,
+    /// Largest gRPC message sent or accepted, in bytes (tonic's default
+    /// decoding limit is 4 MiB).
+    #[arg(long)]
+    max_message_bytes: Option<usize>,
+    /// WASM transform plugin applied to every session (`wasm` feature).
+    #[arg(long)]
+    plugin: Option<PathBuf>,
+    /// Appends every request the server receives to this JSON-lines file.
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+    /// PEM certificate chain to serve TLS with; needs `--tls-key`.
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key for `--tls-cert`.
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    /// Bearer token callers must present; repeat to accept several. With
+    /// none, and no `--auth-token-file`, any caller is served.
+    #[arg(long)]
+    auth_token: Vec<String>,
+    /// File of accepted bearer tokens, one per line, so they stay out of
+    /// the process list.
+    #[arg(long)]
+    auth_token_file: Option<PathBuf>,
+    /// Publishes one replay to a message bus instead of serving it, e.g.
+    /// `--sink nats nats://127.0.0.1:4222`.
+    #[arg(long, num_args = 2, value_names = ["KIND", "ADDR"])]
+    sink: Vec<String>,
+    /// Events go to the NATS subject `<prefix>.<symbol>` or the Redis
+    /// stream `<prefix>:<symbol>`.
+    #[arg(long, default_value = "md")]
+    sink_prefix: String,
+    /// JetStream stream a NATS sink publishes into, waiting for its acks.
+    #[arg(long)]
+    jetstream: Option<String>,
+    /// Trims each Redis stream to about this many entries.
+    #[arg(long)]
+    stream_max_len: Option<u64>,
+    /// Events a ZeroMQ subscriber may fall behind by before further ones
+    /// are dropped for it.
+    #[arg(long, default_value_t = 1000)]
+    high_water_mark: usize,
+    /// Holds a ZeroMQ replay back until this many subscribers have
+    /// subscribed.
+    #[arg(long, default_value_t = 0)]
+    wait_subscribers: usize,
+}
+
+#[derive(Args)]
+struct ReplayRequestsArgs {
+    /// Audit log written by `serve --audit-log`.
+    #[arg(long)]
+    audit: PathBuf,
+    #[arg(long, default_value = "http://127.0.0.1:50051")]
+    addr: String,
+    /// Issue every request at once instead of at the recorded spacing.
+    #[arg(long, default_value_t = false)]
+    no_wait: bool,
+    /// Bearer token to call with; repeat for several. Each call goes with
+    /// the token whose fingerprint the log recorded for it, or the first
+    /// one when none matches.
+    #[arg(long)]
+    token: Vec<String>,
+    /// File of bearer tokens, one per line, so they stay out of the
+    /// process list.
+    #[arg(long)]
+    token_file: Option<PathBuf>,
+    /// PEM certificates to trust; calls go over TLS when set.
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// Outcome file (JSON lines); stdout when absent.
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct StressArgs {
+    #[arg(long, default_value = "http://127.0.0.1:50051")]
+    target: String,
+    #[arg(long, default_value_t = 50)]
+    sessions: usize,
+    /// `flat`, `ramp` or `bursty`.
+    #[arg(long, default_value = "bursty")]
+    profile: String,
+    /// Symbols to draw session filters from; every session streams all
+    /// symbols when absent.
+    #[arg(long)]
+    symbols: Option<String>,
+    /// Sessions still running after this are stopped.
+    #[arg(long, default_value_t = 30)]
+    duration_secs: u64,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Bearer token sent with every session.
+    #[arg(long)]
+    token: Option<String>,
+    #[arg(long, default_value_t = false)]
+    json: bool,
+    /// Per-session outcomes (JSON lines).
+    #[arg(long)]
+    out: Option<PathBuf>,
 }
 
 #[derive(Args)]
@@ -132,10 +937,10 @@ struct UiArgs {
     compare_log: Option<PathBuf>,
     #[arg(long)]
     compare_index: Option<PathBuf>,
-    #[arg(long)]
-    from: Option<u64>,
-    #[arg(long)]
-    to: Option<u64>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
     #[arg(long, default_value = "127.0.0.1:8080")]
     addr: String,
 }
@@ -146,12 +951,24 @@ struct ReadArgs {
     log: PathBuf,
     #[arg(long)]
     index: Option<PathBuf>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
     #[arg(long)]
-    from: Option<u64>,
+    out: Option<PathBuf>,
+    #[arg(long, default_value = "text")]
+    format: String,
+    /// Keep the log open and print records as another process appends them.
     #[arg(long)]
-    to: Option<u64>,
+    follow: bool,
+    #[arg(long, default_value_t = 250)]
+    poll_ms: u64,
+    /// WASM transform plugin applied before printing (`wasm` feature).
     #[arg(long)]
-    out: Option<PathBuf>,
+    plugin: Option<PathBuf>,
+    #[command(flatten)]
+    script: ScriptArgs,
 }
 
 #[derive(Args)]
@@ -160,16 +977,31 @@ struct FeatureArgs {
     log: PathBuf,
     #[arg(long)]
     index: Option<PathBuf>,
-    #[arg(long)]
-    from: Option<u64>,
-    #[arg(long)]
-    to: Option<u64>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
     #[arg(long, default_value_t = 42)]
     seed: u64,
     #[arg(long)]
     out: Option<PathBuf>,
 }
 
+/// Per-symbol spread, depth and BBO presence, time-weighted.
+#[derive(Args)]
+struct StatsArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    index: Option<PathBuf>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
 #[derive(Args)]
 struct VerifyArgs {
     #[arg(long)]
@@ -182,6 +1014,84 @@ struct VerifyArgs {
     seed: u64,
     #[arg(long, default_value = "verify.out")]
     out: PathBuf,
+    /// POST a notification to this URL when verification fails.
+    #[arg(long)]
+    alert_webhook: Option<String>,
+}
+
+/// Compares two logs event by event, e.g. before and after a parser change.
+#[derive(Args)]
+struct DiffArgs {
+    #[arg(long)]
+    left: PathBuf,
+    #[arg(long)]
+    left_index: Option<PathBuf>,
+    #[arg(long)]
+    right: PathBuf,
+    #[arg(long)]
+    right_index: Option<PathBuf>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    /// Write a standalone HTML report here.
+    #[arg(long, conflicts_with = "book")]
+    report: Option<PathBuf>,
+    #[arg(long, default_value_t = false)]
+    json: bool,
+    /// Mismatches listed individually in the output and report.
+    #[arg(long, default_value_t = 1000)]
+    max_rows: usize,
+    #[arg(long, default_value_t = 60)]
+    buckets: usize,
+    /// Compare rebuilt book state at sampled times instead of events.
+    #[arg(long, default_value_t = false)]
+    book: bool,
+    /// Sample times for `--book`, spread evenly over both logs.
+    #[arg(long, default_value_t = 100)]
+    samples: usize,
+}
+
+#[derive(Args)]
+struct DigestArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    index: Option<PathBuf>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    /// End of the interval (inclusive).
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    /// Entries listed per section.
+    #[arg(long, default_value_t = DigestOptions::default().top)]
+    top: usize,
+    /// A spread at least this many times its median is a blowout.
+    #[arg(long, default_value_t = DigestOptions::default().spread_multiple)]
+    spread_multiple: f64,
+    /// Width of the buckets trade volume is compared across.
+    #[arg(long, default_value_t = DigestOptions::default().bucket_ns)]
+    bucket_ns: i64,
+    /// A bucket with this many times the symbol's mean volume is a spike.
+    #[arg(long, default_value_t = DigestOptions::default().volume_multiple)]
+    volume_multiple: f64,
+    /// A symbol silent at least this long counts as halted.
+    #[arg(long, default_value_t = DigestOptions::default().halt_ns)]
+    halt_ns: i64,
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+impl DigestArgs {
+    fn options(&self) -> DigestOptions {
+        DigestOptions {
+            top: self.top,
+            spread_multiple: self.spread_multiple,
+            bucket_ns: self.bucket_ns,
+            volume_multiple: self.volume_multiple,
+            halt_ns: self.halt_ns,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -192,6 +1102,205 @@ struct BenchArgs {
     index: Option<PathBuf>,
 }
 
+#[derive(Args)]
+struct InfoArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    index: Option<PathBuf>,
+    #[arg(long, default_value_t = false)]
+    json: bool,
+    #[arg(long)]
+    gap_threshold_ns: Option<i64>,
+    /// POST a notification to this URL when gaps are found.
+    #[arg(long)]
+    alert_webhook: Option<String>,
+}
+
+#[derive(Args)]
+struct MigrateArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct MergeArgs {
+    #[arg(long, num_args = 1.., required = true)]
+    log: Vec<PathBuf>,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct SplitArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long, default_value = "symbol")]
+    by: SplitBy,
+    #[arg(long)]
+    out_dir: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long = "symbol", value_delimiter = ',')]
+    symbols: Vec<String>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    #[arg(long = "type")]
+    event_type: Option<EventType>,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct SliceArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    /// How many symbols to keep.
+    #[arg(long)]
+    top_symbols: usize,
+    /// Rank symbols by trade count or traded size: trades|volume.
+    #[arg(long, default_value = "trades")]
+    by: ActivityBy,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct RepairArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[arg(long)]
+    log: PathBuf,
+    #[arg(long)]
+    index: Option<PathBuf>,
+    #[arg(long, allow_hyphen_values = true)]
+    from: Option<Timestamp>,
+    #[arg(long, allow_hyphen_values = true)]
+    to: Option<Timestamp>,
+    #[arg(long, default_value = "parquet")]
+    format: String,
+    #[arg(long)]
+    out: PathBuf,
+    /// WASM transform plugin applied before writing (`wasm` feature).
+    #[arg(long)]
+    plugin: Option<PathBuf>,
+    #[command(flatten)]
+    script: ScriptArgs,
+    /// Adds decimal price and quantity columns, scaled by this tick
+    /// config's tick and lot sizes.
+    #[arg(long)]
+    tick_config: Option<PathBuf>,
+}
+
+/// As-of join: every `--left` event with the newest `--right` state at or
+/// before it, written to Parquet.
+#[derive(Args)]
+struct JoinArgs {
+    #[arg(long)]
+    left: PathBuf,
+    #[arg(long)]
+    right: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    /// Match on venue as well as symbol.
+    #[arg(long)]
+    match_venue: bool,
+    /// Leave matches older than this unjoined.
+    #[arg(long)]
+    tolerance_ns: Option<u64>,
+}
+
+/// Order-gateway log (CSV or JSONL) to an order log, a JSON-lines file of
+/// normalized orders sorted by client timestamp.
+#[derive(Args)]
+struct IngestOrdersArgs {
+    #[arg(long)]
+    input: PathBuf,
+    /// csv or jsonl.
+    #[arg(long, default_value = "csv")]
+    format: GatewayFormat,
+    /// Venue for rows that do not name one.
+    #[arg(long)]
+    venue: String,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long)]
+    tick_config: Option<PathBuf>,
+    #[command(flatten)]
+    venues: VenueArgs,
+}
+
+/// FIX 4.2/4.4 message log (market data and execution reports) to an
+/// event log.
+#[derive(Args)]
+struct IngestFixArgs {
+    #[arg(long)]
+    input: PathBuf,
+    /// Venue for messages without `SecurityExchange` or `LastMkt`.
+    #[arg(long)]
+    venue: String,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 1024)]
+    index_stride: u32,
+    #[arg(long)]
+    tick_config: Option<PathBuf>,
+    /// TOML overriding the tags and codes read from each message.
+    #[arg(long)]
+    tag_config: Option<PathBuf>,
+    #[command(flatten)]
+    venues: VenueArgs,
+    #[command(flatten)]
+    clock: ClockArgs,
+    #[command(flatten)]
+    dedup: DedupArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+/// As-of join of an order log against a market log, written to Parquet.
+#[derive(Args)]
+struct JoinOrdersArgs {
+    #[arg(long)]
+    orders: PathBuf,
+    #[arg(long)]
+    market: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    /// Match on venue as well as symbol.
+    #[arg(long)]
+    match_venue: bool,
+    /// Leave matches older than this unjoined.
+    #[arg(long)]
+    tolerance_ns: Option<u64>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -205,33 +1314,182 @@ async fn main() -> Result<()> {
     match cli.command {
         Command::IngestCsvA(args) => {
             let ticks = load_tick_table(args.tick_config.as_deref())?;
-            let events = ingest_csv_a(&args.input, &args.venue, &ticks)?;
-            write_log_and_index(&events, &args.out, args.index_stride)?;
-            info!(events = events.len(), out = %args.out.display(), "ingested csv_a");
+            let timestamps = args.timestamps.spec(CsvTimestampFormat::Rfc3339)?;
+            let report = ingest_csv_inputs(&args, "csv_a", &ticks, &|path, on_event| {
+                for_each_csv_a_with(path, &args.venue, &ticks, &timestamps, on_event)
+            })?;
+            info!(events = report.events, out = %args.out.display(), "ingested csv_a");
         }
         Command::IngestCsvB(args) => {
             let ticks = load_tick_table(args.tick_config.as_deref())?;
-            let events = ingest_csv_b(&args.input, &args.venue, &ticks)?;
-            write_log_and_index(&events, &args.out, args.index_stride)?;
-            info!(events = events.len(), out = %args.out.display(), "ingested csv_b");
+            let timestamps = args.timestamps.spec(CsvTimestampFormat::Epoch(1_000_000))?;
+            let report = ingest_csv_inputs(&args, "csv_b", &ticks, &|path, on_event| {
+                for_each_csv_b_with(path, &args.venue, &ticks, &timestamps, on_event)
+            })?;
+            info!(events = report.events, out = %args.out.display(), "ingested csv_b");
         }
         Command::IngestCsvC(args) => {
             let ticks = load_tick_table(args.tick_config.as_deref())?;
-            let events = ingest_csv_c(&args.input, &args.venue, &ticks)?;
-            write_log_and_index(&events, &args.out, args.index_stride)?;
-            info!(events = events.len(), out = %args.out.display(), "ingested csv_c");
+            let timestamps = args.timestamps.spec(CsvTimestampFormat::Mixed)?;
+            let report = ingest_csv_inputs(&args, "csv_c", &ticks, &|path, on_event| {
+                for_each_csv_c_with(path, &args.venue, &ticks, &timestamps, on_event)
+            })?;
+            info!(events = report.events, out = %args.out.display(), "ingested csv_c");
+        }
+        Command::IngestCsv(args) => {
+            let raw = std::fs::read_to_string(&args.schema)
+                .with_context(|| format!("failed reading {}", args.schema.display()))?;
+            let mut schema = CsvSchema::from_toml_str(&raw)?;
+            let csv = &args.csv;
+            csv.timestamps.override_schema(&mut schema.timestamp)?;
+            let ticks = load_tick_table(csv.tick_config.as_deref())?;
+            let report = ingest_csv_inputs(csv, "csv", &ticks, &|path, on_event| {
+                for_each_csv_with_schema(path, &csv.venue, &ticks, &schema, on_event)
+            })?;
+            info!(events = report.events, out = %csv.out.display(), "ingested csv");
+        }
+        Command::IngestJsonl(args) => {
+            let paths = expand_inputs(&args.input)?;
+            let report = ingest_inputs(
+                &paths,
+                &args.out,
+                args.index_stride,
+                None,
+                &EventStages {
+                    venues: &args.venues,
+                    clock: &args.clock,
+                    dedup: &args.dedup,
+                },
+                &args.stream,
+                Provenance::new("jsonl", display_paths(&paths), &args.venue, &args.out),
+                &|path, on_event| for_each_jsonl(path, &args.venue, on_event),
+            )?;
+            args.report.write(&report)?;
+            info!(events = report.events, out = %args.out.display(), "ingested jsonl");
         }
         Command::IngestReal(args) => {
-            if args.provider != "yahoo" {
-                return Err(anyhow!("unsupported real-data provider {}", args.provider));
+            if !matches!(
+                args.provider.as_str(),
+                "yahoo" | "polygon" | "alphavantage" | "stooq" | "tiingo"
+            ) {
+                return match live_provider(&args.provider) {
+                    Some(provider) => run_ingest_stream(*args, provider.as_ref()).await,
+                    None => Err(anyhow!("unsupported real-data provider {}", args.provider)),
+                };
             }
             let ticks = load_tick_table(args.tick_config.as_deref())?;
             let symbols = parse_symbols(&args.symbols)?;
-            let events =
-                ingest_yahoo(&symbols, &args.venue, &ticks, &args.interval, &args.range).await?;
-            write_log_and_index(&events, &args.out, args.index_stride)?;
+            let append = args.since_last && args.out.exists();
+            let since = if append {
+                last_timestamps_by_symbol(&args.out, &symbols)?
+            } else {
+                BTreeMap::new()
+            };
+            let http = args.http_options()?;
+            let expansion = BarExpansion {
+                steps: args.expand_bars,
+                seed: args.expand_seed,
+            };
+            let cache = (!args.no_cache).then(|| ResponseCache::new(&args.cache_dir));
+            let mut events = match args.provider.as_str() {
+                "polygon" => {
+                    let (Some(from), Some(to)) = (args.from, args.to) else {
+                        return Err(anyhow!("polygon needs --from and --to"));
+                    };
+                    let options = PolygonOptions {
+                        api_key: provider_api_key(&args, "POLYGON_API_KEY")?,
+                        http,
+                        from,
+                        to,
+                        since,
+                        cache,
+                        ..PolygonOptions::default()
+                    };
+                    ingest_polygon(&symbols, &args.venue, &ticks, &options).await?
+                }
+                "alphavantage" => {
+                    let options = AlphaVantageOptions {
+                        api_key: provider_api_key(&args, "ALPHAVANTAGE_API_KEY")?,
+                        http,
+                        month: args.month.clone(),
+                        requests_per_minute: args.requests_per_minute,
+                        expansion,
+                        since,
+                        cache,
+                        ..AlphaVantageOptions::default()
+                    };
+                    ingest_alphavantage(&symbols, &args.venue, &ticks, &args.interval, &options)
+                        .await?
+                }
+                "stooq" => {
+                    let options = StooqOptions {
+                        http,
+                        from: args.from,
+                        to: args.to,
+                        expansion,
+                        since,
+                        cache,
+                        ..StooqOptions::default()
+                    };
+                    ingest_stooq(&symbols, &args.venue, &ticks, &options).await?
+                }
+                "tiingo" => {
+                    let options = TiingoOptions {
+                        api_key: provider_api_key(&args, "TIINGO_API_KEY")?,
+                        http,
+                        from: args.from,
+                        to: args.to,
+                        expansion,
+                        since,
+                        cache,
+                        ..TiingoOptions::default()
+                    };
+                    ingest_tiingo(&symbols, &args.venue, &ticks, &options).await?
+                }
+                _ => {
+                    let options = YahooOptions {
+                        expansion,
+                        http,
+                        concurrency: args.concurrency,
+                        since,
+                        retry: RetryPolicy {
+                            max_retries: args.max_retries,
+                            ..RetryPolicy::default()
+                        },
+                        requests_per_second: args.requests_per_sec,
+                        cache,
+                        ..YahooOptions::default()
+                    };
+                    ingest_yahoo_with(
+                        &symbols,
+                        &args.venue,
+                        &ticks,
+                        &args.interval,
+                        &args.range,
+                        &options,
+                    )
+                    .await?
+                }
+            };
+            normalize_venues(&mut events, &args.venues)?;
+            let mut report = IngestReport::new(Provenance::new(
+                &args.provider,
+                symbols,
+                &args.venue,
+                &args.out,
+            ));
+            if append {
+                append_log_and_index(&events, &args.out, &mut report)?;
+            } else {
+                let units = price_units(&ticks, events.iter().map(|e| e.symbol.as_str()));
+                write_log_and_index(&events, &args.out, args.index_stride, &units)?;
+                report.record_all(&events);
+            }
+            args.report.write(&report)?;
+            let written = report.events;
             info!(
-                events = events.len(),
+                events = written,
+                appended = append,
                 out = %args.out.display(),
                 provider = %args.provider,
                 symbols = %args.symbols,
@@ -239,23 +1497,197 @@ async fn main() -> Result<()> {
             );
         }
         Command::IngestPcap(args) => {
+            let (mut output, ticks) = match args.schema.as_str() {
+                "mock_itch" => (
+                    ingest_pcap_with(&args.pcap, &args.venue, args.framing)?,
+                    None,
+                ),
+                "mdp3" => {
+                    let ticks = load_tick_table(args.tick_config.as_deref())?;
+                    let securities = match &args.security_map {
+                        Some(path) => load_security_map(path)?,
+                        None => HashMap::new(),
+                    };
+                    let output = ingest_mdp3(&args.pcap, &args.venue, &ticks, &securities)?;
+                    (output, Some(ticks))
+                }
+                other => return Err(anyhow!("unsupported schema {other}")),
+            };
+            let skipped = EventStages {
+                venues: &args.venues,
+                clock: &args.clock,
+                dedup: &args.dedup,
+            }
+            .apply(&mut output.events)?;
+            let units = ticks
+                .map(|t| price_units(&t, output.events.iter().map(|e| e.symbol.as_str())))
+                .unwrap_or_default();
+            write_log_and_index(&output.events, &args.out, args.index_stride, &units)?;
+            args.report.write_batch(
+                Provenance::new(
+                    "pcap",
+                    vec![args.pcap.display().to_string()],
+                    &args.venue,
+                    &args.out,
+                ),
+                &output.events,
+                &output.issues,
+                skipped,
+            )?;
+            let gaps = GapReport::from_issues(&output.issues);
+            if args.schema == "mdp3" || args.framing == Framing::MoldUdp64 {
+                let path = gap_report_path_for_log(&args.out);
+                std::fs::write(&path, serde_json::to_string_pretty(&gaps)?)
+                    .with_context(|| format!("failed writing {}", path.display()))?;
+            }
+            info!(
+                events = output.events.len(),
+                issues = output.issues.len(),
+                gaps = gaps.gaps,
+                missing = gaps.estimated_messages,
+                out = %args.out.display(),
+                "ingested pcap"
+            );
+        }
+        Command::IngestSoup(args) => {
             if args.schema != "mock_itch" {
                 return Err(anyhow!("unsupported schema {}", args.schema));
             }
-            let output = ingest_pcap(&args.pcap, &args.venue)?;
-            write_log_and_index(&output.events, &args.out, args.index_stride)?;
+            let mut output = ingest_soup(&args.input, &args.venue)?;
+            let skipped = EventStages {
+                venues: &args.venues,
+                clock: &args.clock,
+                dedup: &args.dedup,
+            }
+            .apply(&mut output.events)?;
+            write_log_and_index(
+                &output.events,
+                &args.out,
+                args.index_stride,
+                &BTreeMap::new(),
+            )?;
+            args.report.write_batch(
+                Provenance::new(
+                    "soupbintcp",
+                    vec![args.input.display().to_string()],
+                    &args.venue,
+                    &args.out,
+                ),
+                &output.events,
+                &output.issues,
+                skipped,
+            )?;
             info!(
                 events = output.events.len(),
                 issues = output.issues.len(),
                 out = %args.out.display(),
-                "ingested pcap"
+                "ingested soupbintcp session"
+            );
+        }
+        Command::IngestLive(args) => {
+            if args.schema != "mock_itch" {
+                return Err(anyhow!("unsupported schema {}", args.schema));
+            }
+            if !cfg!(feature = "pcap") {
+                return Err(md_ingest::IngestError::PcapUnavailable.into());
+            }
+            tokio::task::spawn_blocking(move || run_ingest_live(args)).await??;
+        }
+        Command::IngestOrders(args) => {
+            let ticks = load_tick_table(args.tick_config.as_deref())?;
+            let mut orders = ingest_orders(&args.input, args.format, &args.venue, &ticks)?;
+            normalize_order_venues(&mut orders, &args.venues)?;
+            write_order_log(&args.out, &orders)?;
+            info!(orders = orders.len(), out = %args.out.display(), "ingested gateway log");
+        }
+        Command::IngestFix(args) => {
+            let ticks = load_tick_table(args.tick_config.as_deref())?;
+            let tags = match &args.tag_config {
+                Some(path) => {
+                    let raw = std::fs::read_to_string(path)
+                        .with_context(|| format!("failed reading {}", path.display()))?;
+                    FixTagMap::from_toml_str(&raw)?
+                }
+                None => FixTagMap::default(),
+            };
+            let mut output = ingest_fix(&args.input, &args.venue, &ticks, &tags)?;
+            let skipped = EventStages {
+                venues: &args.venues,
+                clock: &args.clock,
+                dedup: &args.dedup,
+            }
+            .apply(&mut output.events)?;
+            let units = price_units(&ticks, output.events.iter().map(|e| e.symbol.as_str()));
+            write_log_and_index(&output.events, &args.out, args.index_stride, &units)?;
+            args.report.write_batch(
+                Provenance::new(
+                    "fix",
+                    vec![args.input.display().to_string()],
+                    &args.venue,
+                    &args.out,
+                ),
+                &output.events,
+                &output.issues,
+                skipped,
+            )?;
+            info!(
+                events = output.events.len(),
+                issues = output.issues.len(),
+                out = %args.out.display(),
+                "ingested fix log"
             );
         }
         Command::GenPcap(args) => {
             let symbols = parse_symbols(&args.symbols)?;
-            generate_pcap(&args.out, &symbols, args.events, args.seed)?;
+            let clock = ClockModel {
+                offset_ns: args.clock_offset_ns,
+                drift_ppm: args.clock_drift_ppm,
+                jitter_ns: args.clock_jitter_ns,
+            };
+            generate_capture(
+                &args.out,
+                &symbols,
+                args.events,
+                args.seed,
+                &clock,
+                args.format,
+                args.framing,
+            )?;
             info!(out = %args.out.display(), events = args.events, "generated pcap");
         }
+        Command::GenTape(args) => {
+            let venues = args
+                .venues
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::parse::<VenueFeed>)
+                .collect::<Result<Vec<_>, _>>()?;
+            let cfg = TapeConfig {
+                symbols: parse_symbols(&args.symbols)?,
+                venues,
+                updates: args.updates,
+                seed: args.seed,
+                crossed_rate: args.crossed_rate,
+                crossed_quotes: args.crossed_quotes,
+                trade_rate: args.trade_rate,
+                jitter_ns: args.jitter_ns,
+            };
+            let tape = generate_tape(&cfg)?;
+            write_log_and_index(&tape.events, &args.out, args.index_stride, &BTreeMap::new())?;
+            for episode in &tape.crossed {
+                println!(
+                    "crossed {} {} {} {}",
+                    episode.venue, episode.symbol, episode.start_ns, episode.end_ns
+                );
+            }
+            info!(
+                out = %args.out.display(),
+                events = tape.events.len(),
+                crossed = tape.crossed.len(),
+                "generated tape"
+            );
+        }
         Command::Serve(args) => {
             let addr: SocketAddr = args
                 .addr
@@ -268,9 +1700,170 @@ async fn main() -> Result<()> {
                 speed,
                 max_speed: args.max_speed,
                 step_mode: args.step_mode,
+                max_gap_ns: args.max_gap_ns,
+                enrich: args.enrich,
+                symbol_starts: args
+                    .symbol_start
+                    .iter()
+                    .map(|raw| parse_symbol_start(raw))
+                    .collect::<Result<_>>()?,
+                closing_cross: args.closing_cross,
+                batch_closing_cross: args.batch_closing_cross,
+                venue_channels: Vec::new(),
+                max_batch: args.max_batch,
+                latency_ns: args.latency_ns,
+                jitter_ns: args.jitter_ns,
+                jitter_seed: args.jitter_seed,
+                drop_rate: args.drop_rate,
+                drop_burst: args.drop_burst,
+                drop_seed: args.drop_seed,
+                wall_clock: args.wall_clock,
+                checkpoint_every: args.checkpoint_every,
+                resume: None,
+                transforms: parse_transforms(&args.transforms)?,
+                max_events_per_sec: args.max_events_per_sec,
             };
-            let index = args.index.or_else(|| maybe_index_path(&args.log));
-            serve_grpc(args.log, index, addr, cfg).await?;
+            let limits = SessionLimits {
+                max_events: args.max_session_events,
+                max_duration: args.max_session_secs.map(Duration::from_secs),
+                max_sessions_per_token: args.max_sessions_per_token,
+            };
+            let tuning = StreamTuning {
+                channel_depth: args.channel_depth,
+                spawn: args.spawn,
+                backpressure: args.backpressure,
+                read_ahead: args.read_ahead,
+                compression: args.compression,
+                max_message_bytes: args.max_message_bytes,
+            };
+            let source = if args.source.is_empty() {
+                serve_source(args.log, args.index, args.stitch, args.merge)?
+            } else {
+                ingest_serve_source(&args.source, &args.venue, args.tick_config.as_deref())?
+            };
+            let plugin = args
+                .plugin
+                .as_deref()
+                .map(WasmPlugin::from_file)
+                .transpose()?;
+            if let [kind, target] = args.sink.as_slice() {
+                let published = match kind.as_str() {
+                    "nats" => {
+                        let options = NatsOptions {
+                            prefix: args.sink_prefix,
+                            jetstream: args.jetstream,
+                        };
+                        let mut sink = NatsSink::connect(target, options).await?;
+                        publish(&source, cfg, plugin.as_ref(), &mut sink).await?
+                    }
+                    "redis" => {
+                        let options = RedisOptions {
+                            prefix: args.sink_prefix,
+                            max_len: args.stream_max_len,
+                        };
+                        let mut sink = RedisSink::connect(target, options).await?;
+                        publish(&source, cfg, plugin.as_ref(), &mut sink).await?
+                    }
+                    "zmq" => {
+                        let options = ZmqOptions {
+                            high_water_mark: args.high_water_mark,
+                        };
+                        let mut sink = ZmqSink::bind(target, options).await?;
+                        if args.wait_subscribers > 0 {
+                            info!(
+                                addr = %sink.local_addr(),
+                                subscribers = args.wait_subscribers,
+                                "waiting for zmq subscribers"
+                            );
+                            sink.wait_for_subscribers(args.wait_subscribers).await?;
+                        }
+                        let published = publish(&source, cfg, plugin.as_ref(), &mut sink).await?;
+                        if sink.dropped() > 0 {
+                            warn!(dropped = sink.dropped(), "zmq subscribers fell behind");
+                        }
+                        published
+                    }
+                    other => {
+                        return Err(anyhow!("unsupported sink {other}, expected nats|redis|zmq"))
+                    }
+                };
+                info!(sink = %kind, published, "replay published");
+                return Ok(());
+            }
+            let audit = args.audit_log.as_deref().map(AuditLog::open).transpose()?;
+            let tokens = read_tokens(args.auth_token, args.auth_token_file.as_deref())?;
+            let security = ServerSecurity {
+                tls: args
+                    .tls_cert
+                    .zip(args.tls_key)
+                    .map(|(cert, key)| TlsIdentity { cert, key }),
+                tokens,
+            };
+            serve_grpc(source, addr, cfg, limits, tuning, plugin, audit, security).await?;
+        }
+        Command::ReplayRequests(args) => {
+            let records = read_audit_log(&args.audit)?;
+            let addr = if args.addr.contains("://") {
+                args.addr.clone()
+            } else {
+                format!("http://{}", args.addr)
+            };
+            let options = ReplayRequestsOptions {
+                no_wait: args.no_wait,
+                tokens: read_tokens(args.token, args.token_file.as_deref())?,
+                ca_cert: args.ca_cert,
+            };
+            let outcomes = replay_requests(&addr, &records, options).await?;
+            let mut out: Box<dyn Write> = match &args.out {
+                Some(path) => Box::new(BufWriter::new(std::fs::File::create(path)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            for outcome in &outcomes {
+                serde_json::to_writer(&mut out, outcome)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+            let failed = outcomes.iter().filter(|o| o.end != "completed").count();
+            info!(
+                requests = records.len(),
+                outcomes = outcomes.len(),
+                not_completed = failed,
+                addr = %addr,
+                "replayed requests"
+            );
+        }
+        Command::Stress(args) => {
+            let target = if args.target.contains("://") {
+                args.target.clone()
+            } else {
+                format!("http://{}", args.target)
+            };
+            let options = StressOptions {
+                sessions: args.sessions,
+                profile: args
+                    .profile
+                    .parse::<StressProfile>()
+                    .map_err(|e| anyhow!(e))?,
+                symbols: args
+                    .symbols
+                    .as_deref()
+                    .map(parse_symbols)
+                    .transpose()?
+                    .unwrap_or_default(),
+                duration: Duration::from_secs(args.duration_secs),
+                seed: args.seed,
+                token: args.token,
+            };
+            let report = run_stress(&target, &options).await?;
+            if let Some(path) = &args.out {
+                let mut out = BufWriter::new(std::fs::File::create(path)?);
+                for outcome in &report.outcomes {
+                    serde_json::to_writer(&mut out, outcome)?;
+                    writeln!(out)?;
+                }
+                out.flush()?;
+            }
+            print_stress(&report, args.json)?;
         }
         Command::Ui(args) => {
             let addr: SocketAddr = args
@@ -299,18 +1892,61 @@ async fn main() -> Result<()> {
             )
             .await?;
         }
+        Command::Print(args) if args.follow => {
+            if args.format != "text" {
+                return Err(anyhow!("--follow only supports the text format"));
+            }
+            if args.plugin.is_some() {
+                return Err(anyhow!("--follow does not support --plugin"));
+            }
+            if args.script.filter.is_some() || !args.script.derive.is_empty() {
+                return Err(anyhow!("--follow does not support --filter or --derive"));
+            }
+            let idx_path = args.index.or_else(|| maybe_index_path(&args.log));
+            follow_log(
+                &args.log,
+                idx_path.as_deref(),
+                args.from,
+                args.to,
+                args.out.as_deref(),
+                Duration::from_millis(args.poll_ms.max(1)),
+            )
+            .await?;
+        }
         Command::Print(args) => {
             let idx_path = args.index.or_else(|| maybe_index_path(&args.log));
-            let events = read_events(&args.log, idx_path.as_deref(), args.from, args.to)?;
-            let lines = events
-                .iter()
-                .map(format_event)
-                .collect::<Vec<_>>()
-                .join("\n");
-            if let Some(out) = args.out {
-                std::fs::write(out, format!("{}\n", lines))?;
-            } else {
-                println!("{}", lines);
+            let mut events = read_events(&args.log, idx_path.as_deref(), args.from, args.to)?;
+            if let Some(path) = &args.plugin {
+                events = WasmPlugin::from_file(path)?.apply(events)?;
+            }
+            let (events, derived) = args.script.apply(events)?;
+            match args.format.as_str() {
+                "text" => {
+                    let lines = events
+                        .iter()
+                        .enumerate()
+                        .map(|(i, event)| {
+                            format!(
+                                "{}{}",
+                                format_event(event),
+                                format_derived(&derived.names, derived.row(i))
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if let Some(out) = args.out {
+                        std::fs::write(out, format!("{}\n", lines))?;
+                    } else {
+                        println!("{}", lines);
+                    }
+                }
+                "arrow" => match args.out {
+                    Some(out) => {
+                        write_arrow_file_with(&events, &derived, std::fs::File::create(out)?)?
+                    }
+                    None => write_arrow_stream_with(&events, &derived, std::io::stdout().lock())?,
+                },
+                other => return Err(anyhow!("unsupported print format {other}")),
             }
         }
         Command::Feature(args) => {
@@ -324,15 +1960,202 @@ async fn main() -> Result<()> {
                 println!("{}", lines);
             }
         }
-        Command::Verify(args) => {
-            if args.client != "feature" {
-                return Err(anyhow!("unsupported verify client {}", args.client));
+        Command::Stats(args) => {
+            let idx_path = args.index.or_else(|| maybe_index_path(&args.log));
+            let events = read_events(&args.log, idx_path.as_deref(), args.from, args.to)?;
+            print_stats(&run_stats(&events), args.json)?;
+        }
+        Command::Verify(args) => {
+            if args.client != "feature" {
+                return Err(anyhow!("unsupported verify client {}", args.client));
+            }
+            let verified =
+                verify_feature_determinism(&args.log, args.index.as_deref(), args.seed, &args.out);
+            if let Err(err) = verified {
+                let notifier = notifier_for(args.alert_webhook.as_deref())?;
+                let notification = Notification {
+                    source: args.log.display().to_string(),
+                    check: String::from("verify"),
+                    firing: true,
+                    summary: format!("{err:#}"),
+                    details: serde_json::json!({
+                        "client": args.client,
+                        "seed": args.seed,
+                        "out": args.out.display().to_string(),
+                    }),
+                };
+                notify(notifier.as_ref(), &notification).await;
+                return Err(err.into());
+            }
+            info!(out = %args.out.display(), "verify passed");
+        }
+        Command::Diff(args) => run_diff(&args)?,
+        Command::Digest(args) => {
+            let idx_path = args.index.clone().or_else(|| maybe_index_path(&args.log));
+            let events = read_events(&args.log, idx_path.as_deref(), args.from, args.to)?;
+            let digest = build_digest(&events, args.from, args.to, &args.options());
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&digest)?);
+            } else {
+                print!("{}", render_text(&digest));
+            }
+        }
+        Command::Bench(args) => {
+            run_bench(&args.log, args.index.as_deref())?;
+        }
+        Command::Info(args) => {
+            let idx_path = args.index.or_else(|| maybe_index_path(&args.log));
+            let stats = run_info(
+                &args.log,
+                idx_path.as_deref(),
+                args.gap_threshold_ns,
+                args.json,
+            )?;
+            let gaps = stats.gaps.unwrap_or_default();
+            if let (Some(threshold), false) = (args.gap_threshold_ns, gaps.is_empty()) {
+                let notifier = notifier_for(args.alert_webhook.as_deref())?;
+                let notification = Notification {
+                    source: args.log.display().to_string(),
+                    check: String::from("gaps"),
+                    firing: true,
+                    summary: format!("{} gaps longer than {threshold} ns", gaps.len()),
+                    details: serde_json::json!({ "gaps": gaps }),
+                };
+                notify(notifier.as_ref(), &notification).await;
+            }
+        }
+        Command::Migrate(args) => {
+            run_migrate(&args.log, &args.out, args.index_stride)?;
+        }
+        Command::Export(args) => {
+            let idx_path = args.index.or_else(|| maybe_index_path(&args.log));
+            let mut events = read_events(&args.log, idx_path.as_deref(), args.from, args.to)?;
+            if let Some(path) = &args.plugin {
+                events = WasmPlugin::from_file(path)?.apply(events)?;
+            }
+            let (events, mut derived) = args.script.apply(events)?;
+            if let Some(path) = &args.tick_config {
+                let ticks = load_tick_table(Some(path))?;
+                check_price_units(&args.log, &ticks)?;
+                derived.extend(scaled_columns(&events, &ticks)?)?;
+            }
+            match args.format.as_str() {
+                "parquet" => write_parquet_with(&events, &derived, &args.out)?,
+                "arrow" => {
+                    write_arrow_file_with(&events, &derived, std::fs::File::create(&args.out)?)?
+                }
+                "jsonl" => write_jsonl_with(
+                    &events,
+                    &derived,
+                    std::io::BufWriter::new(std::fs::File::create(&args.out)?),
+                )?,
+                other => return Err(anyhow!("unsupported export format {other}")),
+            }
+            info!(
+                events = events.len(),
+                format = %args.format,
+                out = %args.out.display(),
+                "exported log"
+            );
+        }
+        Command::Join(args) => {
+            let options = JoinOptions {
+                match_venue: args.match_venue,
+                tolerance_ns: args.tolerance_ns,
+            };
+            let summary = join_logs(&args.left, &args.right, &args.out, &options)?;
+            info!(
+                rows = summary.rows,
+                matched = summary.matched,
+                out = %args.out.display(),
+                "joined logs"
+            );
+        }
+        Command::JoinOrders(args) => {
+            let options = JoinOptions {
+                match_venue: args.match_venue,
+                tolerance_ns: args.tolerance_ns,
+            };
+            let summary = join_orders(&args.orders, &args.market, &args.out, &options)?;
+            info!(
+                rows = summary.rows,
+                matched = summary.matched,
+                out = %args.out.display(),
+                "joined orders"
+            );
+        }
+        Command::Merge(args) => {
+            let idx_path = index_path_for_log(&args.out);
+            let summary = merge_logs(&args.log, &args.out, &idx_path, args.index_stride)?;
+            info!(
+                inputs = summary.inputs,
+                events = summary.events,
+                out = %args.out.display(),
+                "merged logs"
+            );
+        }
+        Command::Split(args) => {
+            let parts = split_log(&args.log, &args.out_dir, args.by, args.index_stride)?;
+            for part in &parts {
+                println!("{} {} {}", part.key, part.events, part.path.display());
+            }
+            info!(parts = parts.len(), out_dir = %args.out_dir.display(), "split log");
+        }
+        Command::Filter(args) => {
+            let filter = EventFilter {
+                symbols: args.symbols,
+                from_ns: args.from,
+                to_ns: args.to,
+                event_type: args.event_type,
+            };
+            let idx_path = index_path_for_log(&args.out);
+            let summary = filter_log(&args.log, &args.out, &idx_path, &filter, args.index_stride)?;
+            info!(
+                read = summary.read,
+                written = summary.written,
+                out = %args.out.display(),
+                "filtered log"
+            );
+        }
+        Command::Slice(args) => {
+            let idx_path = index_path_for_log(&args.out);
+            let summary = slice_top_symbols(
+                &args.log,
+                &args.out,
+                &idx_path,
+                args.top_symbols,
+                args.by,
+                args.index_stride,
+            )?;
+            for (symbol, activity) in &summary.symbols {
+                println!(
+                    "{symbol} trades={} volume={}",
+                    activity.trades, activity.volume
+                );
             }
-            verify_feature_determinism(&args.log, args.index.as_deref(), args.seed, &args.out)?;
-            info!(out = %args.out.display(), "verify passed");
+            info!(
+                symbols = summary.symbols.len(),
+                read = summary.filter.read,
+                written = summary.filter.written,
+                out = %args.out.display(),
+                "sliced log"
+            );
         }
-        Command::Bench(args) => {
-            run_bench(&args.log, args.index.as_deref())?;
+        Command::Repair(args) => {
+            let idx_path = index_path_for_log(&args.out);
+            let report = repair_log(&args.log, &args.out, &idx_path, args.index_stride)?;
+            for range in &report.skipped {
+                println!(
+                    "skipped offset={} bytes={} reason={}",
+                    range.offset, range.len, range.reason
+                );
+            }
+            println!(
+                "recovered={} dropped={} damaged_ranges={}",
+                report.recovered,
+                report.dropped_records,
+                report.skipped.len()
+            );
         }
     }
 
@@ -352,6 +2175,110 @@ fn load_tick_table(path: Option<&Path>) -> Result<TickTable> {
     }
 }
 
+/// Fails when `ticks` would scale a symbol's prices by another unit than
+/// the one `log` was ingested with, as a missing `[scales]` entry would.
+fn check_price_units(log: &Path, ticks: &TickTable) -> Result<()> {
+    let reader = EventLogReader::open(log)?;
+    for (symbol, unit) in &reader.header().price_units {
+        let configured = ticks.price_unit(symbol).normalize().to_string();
+        if configured != *unit {
+            return Err(anyhow!(
+                "{} was ingested with price unit {unit} for {symbol}, but the tick config gives {configured}",
+                log.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn print_stats(stats: &[SymbolStats], json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(stats)?);
+        return Ok(());
+    }
+    let num = |v: Option<f64>| v.map_or_else(|| String::from("-"), |v| format!("{v:.3}"));
+    println!(
+        "{:<12} {:>8} {:>8} {:>12} {:>10} {:>10} {:>10} {:>10} {:>9}",
+        "symbol",
+        "quotes",
+        "trades",
+        "volume",
+        "ev_spread",
+        "tw_spread",
+        "tw_bid_sz",
+        "tw_ask_sz",
+        "bbo_pct"
+    );
+    for s in stats {
+        println!(
+            "{:<12} {:>8} {:>8} {:>12} {:>10} {:>10} {:>10} {:>10} {:>9}",
+            s.symbol,
+            s.quotes,
+            s.trades,
+            s.volume,
+            num(s.event_spread),
+            num(s.time_spread),
+            num(s.time_bid_depth),
+            num(s.time_ask_depth),
+            s.bbo_presence
+                .map_or_else(|| String::from("-"), |p| format!("{:.2}", p * 100.0))
+        );
+    }
+    Ok(())
+}
+
+fn print_stress(report: &StressReport, json: bool) -> Result<()> {
+    if json {
+        let mut summary = serde_json::to_value(report)?;
+        if let Some(map) = summary.as_object_mut() {
+            map.remove("outcomes");
+        }
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    println!(
+        "sessions: {} (completed {}, stopped {}, failed {})",
+        report.sessions, report.completed, report.stopped, report.failed
+    );
+    for (code, count) in &report.errors {
+        println!("  {code}: {count}");
+    }
+    println!(
+        "events: {} in {:.0} ms ({:.2} events/sec)",
+        report.events, report.elapsed_ms, report.events_per_sec
+    );
+    for (name, d) in [
+        ("connect", &report.connect_ms),
+        ("first event", &report.first_event_ms),
+        ("max gap", &report.max_gap_ms),
+    ] {
+        println!(
+            "{name} ms: p50 {:.2} p90 {:.2} p99 {:.2} max {:.2} (n={})",
+            d.p50, d.p90, d.p99, d.max, d.count
+        );
+    }
+    Ok(())
+}
+
+/// `tokens` plus the non-blank lines of `file`, which must hold at least
+/// one.
+fn read_tokens(mut tokens: Vec<String>, file: Option<&Path>) -> Result<Vec<String>> {
+    if let Some(path) = file {
+        let raw =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        tokens.extend(
+            raw.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        );
+        if tokens.is_empty() {
+            return Err(anyhow!("{} holds no tokens", path.display()));
+        }
+    }
+    Ok(tokens)
+}
+
 fn parse_symbols(raw: &str) -> Result<Vec<String>> {
     let syms = raw
         .split(',')
@@ -377,14 +2304,198 @@ fn parse_speed(raw: &str) -> Result<f64> {
     Ok(speed)
 }
 
-fn write_log_and_index(events: &[md_core::Event], out: &Path, stride: u32) -> Result<()> {
+fn ingest_csv_inputs(
+    args: &IngestCsvArgs,
+    source: &str,
+    ticks: &TickTable,
+    visit: &RowVisitor,
+) -> Result<IngestReport> {
+    let paths = expand_inputs(&args.input)?;
+    let provenance = Provenance::new(source, display_paths(&paths), &args.venue, &args.out);
+    let report = ingest_inputs(
+        &paths,
+        &args.out,
+        args.index_stride,
+        Some(ticks),
+        &EventStages {
+            venues: &args.venues,
+            clock: &args.clock,
+            dedup: &args.dedup,
+        },
+        &args.stream,
+        provenance,
+        visit,
+    )?;
+    args.report.write(&report)?;
+    Ok(report)
+}
+
+fn display_paths(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().map(|p| p.display().to_string()).collect()
+}
+
+/// Parses `paths` into a log at `out`, all at once or, with `--stream`,
+/// through a bounded reorder window. `ticks` is the table prices were
+/// parsed with, recorded as the header's price units.
+#[allow(clippy::too_many_arguments)]
+fn ingest_inputs(
+    paths: &[PathBuf],
+    out: &Path,
+    stride: u32,
+    ticks: Option<&TickTable>,
+    stages: &EventStages,
+    stream: &StreamIngestArgs,
+    provenance: Provenance,
+    visit: &RowVisitor,
+) -> Result<IngestReport> {
+    let mut report = IngestReport::new(provenance);
+    let venues = stages.venues;
+    let dedup = stages.dedup;
+    if !stream.stream {
+        let mut events = ingest_files(paths, |path| {
+            let mut rows = Vec::new();
+            visit(path, &mut |event| {
+                rows.push(event);
+                Ok(())
+            })?;
+            Ok(rows)
+        })?;
+        report.skip(stages.apply(&mut events)?);
+        let units = ticks
+            .map(|t| price_units(t, events.iter().map(|e| e.symbol.as_str())))
+            .unwrap_or_default();
+        write_log_and_index(&events, out, stride, &units)?;
+        report.record_all(&events);
+        return Ok(report);
+    }
+
+    // Offsets shift rows before the reorder window sequences them; `auto`
+    // needs every trade up front, so it only works without `--stream`.
+    let (offsets, auto) = stages.clock.offsets()?;
+    if auto {
+        return Err(anyhow!("--clock-offset auto cannot be used with --stream"));
+    }
+    let registry = load_venue_registry(venues)?;
+    let shifted =
+        |path: &Path, on_event: &mut dyn FnMut(PendingEvent) -> Result<(), IngestError>| {
+            visit(path, &mut |mut event| {
+                let venue = registry.resolve(&event.venue).unwrap_or(&event.venue);
+                if let Some(&ns) = offsets.get(venue) {
+                    event.timestamp_ns = event.timestamp_ns.saturating_add_nanos(ns);
+                }
+                on_event(event)
+            })
+        };
+    let visit: &RowVisitor = if offsets.is_empty() { visit } else { &shifted };
+    let checkpoint_path = checkpoint_path_for(out);
+    let resume = match stream.resume {
+        true => IngestCheckpoint::load(&checkpoint_path)?,
+        false => None,
+    };
+    let (mut writer, mut idx) = match &resume {
+        Some(checkpoint) => {
+            checkpoint.check_inputs(paths)?;
+            let writer = EventLogWriter::resume(out, checkpoint.log_end)?;
+            let records = writer.footer().record_count;
+            let idx = IndexWriter::resume(&index_path_for_log(out), records, checkpoint.index_end)?;
+            (writer, idx)
+        }
+        None => {
+            let symbols = scan_symbols(paths, visit)?;
+            let units = ticks
+                .map(|t| price_units(t, symbols.iter().map(String::as_str)))
+                .unwrap_or_default();
+            (
+                EventLogWriter::create_with_price_units(
+                    out,
+                    &symbols,
+                    default_schema_hash(),
+                    &units,
+                )?,
+                IndexWriter::create(&index_path_for_log(out), stride)?,
+            )
+        }
+    };
+    let inputs = IngestCheckpoint::fingerprint(paths)?;
+    let mut since_checkpoint = 0u64;
+    let mut unknown = BTreeSet::new();
+    let mut dedup_stage = dedup.stage();
+    let position = resume.as_ref().map(|checkpoint| &checkpoint.position);
+    stream_files_from(
+        paths,
+        stream.reorder_window_ns,
+        visit,
+        position,
+        |mut batch, position| {
+            let fresh = registry.normalize_events(&mut batch);
+            if venues.unknown_venue == "fail" {
+                check_unknown_venues(&fresh, venues)?;
+            }
+            unknown.extend(fresh);
+            if let Some(stage) = &mut dedup_stage {
+                batch = stage.filter(batch);
+            }
+            for event in &batch {
+                let offset = writer.append(event)?;
+                idx.maybe_add(event, offset)?;
+                report.record(event);
+            }
+            since_checkpoint += batch.len() as u64;
+            if stream.checkpoint_events > 0 && since_checkpoint >= stream.checkpoint_events {
+                writer.sync()?;
+                idx.flush()?;
+                IngestCheckpoint {
+                    inputs: inputs.clone(),
+                    position: *position,
+                    log_end: writer.offset(),
+                    index_end: idx.offset(),
+                }
+                .save(&checkpoint_path)?;
+                since_checkpoint = 0;
+            }
+            Ok::<_, anyhow::Error>(())
+        },
+    )?;
+    check_unknown_venues(&unknown.into_iter().collect::<Vec<_>>(), venues)?;
+    if let Some(stage) = &dedup_stage {
+        dedup.finish(stage.report())?;
+        report.skip(stage.report().dropped);
+    }
+    writer.flush()?;
+    idx.flush()?;
+    match std::fs::remove_file(&checkpoint_path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+    Ok(report)
+}
+
+/// The decimal price of one stored tick of each of `symbols`, for the
+/// log header, so readers can rescale prices without the tick config.
+fn price_units<'a>(
+    ticks: &TickTable,
+    symbols: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, String> {
+    symbols
+        .into_iter()
+        .map(|s| (s.to_string(), ticks.price_unit(s).normalize().to_string()))
+        .collect()
+}
+
+fn write_log_and_index(
+    events: &[md_core::Event],
+    out: &Path,
+    stride: u32,
+    price_units: &BTreeMap<String, String>,
+) -> Result<()> {
     let mut symbols = BTreeSet::new();
     for event in events {
         symbols.insert(event.symbol.clone());
     }
     let symbols = symbols.into_iter().collect::<Vec<_>>();
 
-    let mut writer = EventLogWriter::create(out, &symbols, default_schema_hash())?;
+    let mut writer =
+        EventLogWriter::create_with_price_units(out, &symbols, default_schema_hash(), price_units)?;
     let idx_path = index_path_for_log(out);
     let mut idx = IndexWriter::create(&idx_path, stride)?;
 
@@ -398,6 +2509,655 @@ fn write_log_and_index(events: &[md_core::Event], out: &Path, stride: u32) -> Re
     Ok(())
 }
 
+/// Captures from `--iface` straight into a log, syncing records and index
+/// every `--flush-ms` so `print --follow` can tail the capture. The footer
+/// is only written on a clean stop (`--max-events` / `--duration-secs`); an
+/// interrupted capture still reads back, just without the summary.
+fn run_ingest_live(args: IngestLiveArgs) -> Result<()> {
+    let venue = resolve_live_venue(&args.venue, &args.venues)?;
+    let symbols = parse_symbols(&args.symbols)?;
+    let provenance = Provenance::new("live", vec![args.iface.clone()], &args.venue, &args.out);
+    let mut sink = LiveSink::open(
+        &args.out,
+        &symbols,
+        &BTreeMap::new(),
+        args.index_stride,
+        false,
+        &args.limits,
+        provenance,
+    )?
+    .with_alerts(&args.alerts, &load_http_options(None)?)?;
+    let opts = LiveOptions {
+        iface: args.iface.clone(),
+        venue,
+        framing: args.framing,
+        bpf_filter: args.filter.clone(),
+        reorder_window_ns: args.reorder_window_ns,
+        ..LiveOptions::default()
+    };
+
+    info!(iface = %args.iface, out = %args.out.display(), "capturing");
+    let captured = capture_live(&opts, |item| sink.on_item(item))
+        .map_err(anyhow::Error::from)
+        .and_then(|summary| sink.finish().map(|()| summary));
+    let summary = match captured {
+        Ok(summary) => summary,
+        Err(err) => {
+            tokio::runtime::Handle::current().block_on(sink.notify_failure(&err));
+            return Err(err);
+        }
+    };
+    args.report.write(&sink.report)?;
+    info!(
+        packets = summary.packets,
+        events = sink.written,
+        issues = summary.issues,
+        gaps = summary.gaps,
+        late = summary.late,
+        other_symbols = sink.skipped_symbols,
+        alerts = sink.alerts_fired,
+        out = %args.out.display(),
+        "live capture stopped"
+    );
+    Ok(())
+}
+
+async fn run_ingest_stream(args: IngestRealArgs, provider: &dyn LiveProvider) -> Result<()> {
+    let ticks = load_tick_table(args.tick_config.as_deref())?;
+    let symbols = parse_symbols(&args.symbols)?
+        .iter()
+        .map(|s| provider.normalize_symbol(s))
+        .collect::<Vec<_>>();
+    let append = args.since_last && args.out.exists();
+    let http = args.http_options()?;
+    let provenance = Provenance::new(provider.name(), symbols.clone(), &args.venue, &args.out);
+    let mut sink = LiveSink::open(
+        &args.out,
+        &symbols,
+        &price_units(&ticks, symbols.iter().map(String::as_str)),
+        args.index_stride,
+        append,
+        &args.limits,
+        provenance,
+    )?
+    .with_alerts(&args.alerts, &http)?;
+    let options = ProviderOptions {
+        url: args.stream_url.clone(),
+        venue: resolve_live_venue(&args.venue, &args.venues)?,
+        reorder_window_ns: args.reorder_window_ns,
+        ..ProviderOptions::default()
+    };
+
+    info!(provider = provider.name(), symbols = %args.symbols, out = %args.out.display(), appended = append, "streaming");
+    let streamed = stream_provider(provider, &symbols, &ticks, &options, |item| {
+        sink.on_item(item)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|summary| sink.finish().map(|()| summary));
+    let summary = match streamed {
+        Ok(summary) => summary,
+        Err(err) => {
+            sink.notify_failure(&err).await;
+            return Err(err);
+        }
+    };
+    args.report.write(&sink.report)?;
+    info!(
+        provider = provider.name(),
+        messages = summary.packets,
+        events = sink.written,
+        issues = summary.issues,
+        late = summary.late,
+        older_than_log = sink.stale,
+        alerts = sink.alerts_fired,
+        out = %args.out.display(),
+        "stream stopped"
+    );
+    Ok(())
+}
+
+fn resolve_live_venue(venue: &str, args: &VenueArgs) -> Result<String> {
+    let registry = load_venue_registry(args)?;
+    match registry.resolve(venue) {
+        Some(mic) => Ok(mic.to_string()),
+        None => {
+            check_unknown_venues(&[venue.to_string()], args)?;
+            Ok(venue.to_string())
+        }
+    }
+}
+
+/// Writes live events to a log and its index as they arrive, syncing
+/// every `--flush-ms` and stopping at `--max-events` or `--duration-secs`.
+struct LiveSink<'a> {
+    writer: EventLogWriter,
+    idx: IndexWriter,
+    symbols: &'a [String],
+    limits: &'a LiveLimitArgs,
+    started: Instant,
+    last_flush: Instant,
+    /// End of the log being appended to; older events are dropped.
+    floor: Option<Timestamp>,
+    last_sequence: u64,
+    written: u64,
+    skipped_symbols: u64,
+    stale: u64,
+    alerts: Option<AlertMonitor>,
+    /// Alerts and a failed capture are posted here, labelled with `source`.
+    webhook: Option<Notifier>,
+    source: String,
+    alerts_fired: u64,
+    failure: Option<md_storage::StorageError>,
+    report: IngestReport,
+}
+
+impl<'a> LiveSink<'a> {
+    fn open(
+        out: &Path,
+        symbols: &'a [String],
+        price_units: &BTreeMap<String, String>,
+        index_stride: u32,
+        append: bool,
+        limits: &'a LiveLimitArgs,
+        provenance: Provenance,
+    ) -> Result<Self> {
+        let (writer, idx) = if append {
+            let writer = EventLogWriter::open_append(out)?;
+            let records = writer.footer().record_count;
+            let idx = IndexWriter::open_append(&index_path_for_log(out), records)?;
+            (writer, idx)
+        } else {
+            (
+                EventLogWriter::create_with_price_units(
+                    out,
+                    symbols,
+                    default_schema_hash(),
+                    price_units,
+                )?,
+                IndexWriter::create(&index_path_for_log(out), index_stride)?,
+            )
+        };
+        let footer = writer.footer();
+        let floor = (footer.record_count > 0).then_some(footer.last_timestamp_ns);
+        let last_sequence = footer.last_sequence;
+        Ok(Self {
+            writer,
+            idx,
+            symbols,
+            limits,
+            started: Instant::now(),
+            last_flush: Instant::now(),
+            floor,
+            last_sequence,
+            written: 0,
+            skipped_symbols: 0,
+            stale: 0,
+            alerts: None,
+            webhook: None,
+            source: out.display().to_string(),
+            alerts_fired: 0,
+            failure: None,
+            report: IngestReport::new(provenance),
+        })
+    }
+
+    fn with_alerts(mut self, args: &AlertArgs, http: &HttpOptions) -> Result<Self> {
+        let thresholds = args.thresholds()?;
+        if !thresholds.is_empty() {
+            self.alerts = Some(AlertMonitor::new(thresholds));
+        }
+        self.webhook = args
+            .alert_webhook
+            .as_deref()
+            .map(|url| Notifier::new(url, http))
+            .transpose()?;
+        Ok(self)
+    }
+
+    fn raise(&mut self, alert: Alert) {
+        if alert.firing {
+            self.alerts_fired += 1;
+            warn!(
+                alert = alert.kind.as_str(),
+                value = alert.value,
+                detail = %alert.detail,
+                "live ingest alert"
+            );
+        } else {
+            info!(
+                alert = alert.kind.as_str(),
+                value = alert.value,
+                detail = %alert.detail,
+                "live ingest alert resolved"
+            );
+        }
+        if let Some(webhook) = self.webhook.clone() {
+            let notification = alert.notification(&self.source);
+            tokio::spawn(async move { notify(Some(&webhook), &notification).await });
+        }
+    }
+
+    fn on_item(&mut self, item: LiveItem) -> ControlFlow<()> {
+        let alerts = match &mut self.alerts {
+            Some(monitor) => monitor.observe(&item, Instant::now()),
+            None => Vec::new(),
+        };
+        for alert in alerts {
+            self.raise(alert);
+        }
+        if let LiveItem::Issue(issue) = &item {
+            self.report.record_issue(issue);
+        }
+        if let LiveItem::Event(mut event) = item {
+            if !self.symbols.contains(&event.symbol) {
+                self.skipped_symbols += 1;
+                self.report.skip(1);
+            } else if self.floor.is_some_and(|floor| event.timestamp_ns < floor) {
+                self.stale += 1;
+                self.report.skip(1);
+            } else {
+                self.written += 1;
+                event.sequence = self.last_sequence + self.written;
+                let appended = self
+                    .writer
+                    .append(&event)
+                    .and_then(|offset| self.idx.maybe_add(&event, offset));
+                if let Err(err) = appended {
+                    self.failure = Some(err);
+                    return ControlFlow::Break(());
+                }
+                self.report.record(&event);
+            }
+        }
+        if self.last_flush.elapsed() >= Duration::from_millis(self.limits.flush_ms.max(1)) {
+            self.last_flush = Instant::now();
+            if let Err(err) = self.writer.sync().and_then(|()| self.idx.flush()) {
+                self.failure = Some(err);
+                return ControlFlow::Break(());
+            }
+        }
+        let done = self
+            .limits
+            .max_events
+            .is_some_and(|max| self.written >= max)
+            || self
+                .limits
+                .duration_secs
+                .is_some_and(|secs| self.started.elapsed() >= Duration::from_secs(secs));
+        if done {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    /// Reports a capture that stopped on an error.
+    async fn notify_failure(&self, err: &anyhow::Error) {
+        let notification = Notification {
+            source: self.source.clone(),
+            check: String::from("live_capture"),
+            firing: true,
+            summary: format!("{err:#}"),
+            details: serde_json::json!({ "events_written": self.written }),
+        };
+        notify(self.webhook.as_ref(), &notification).await;
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(err) = self.failure.take() {
+            return Err(err.into());
+        }
+        self.writer.flush()?;
+        self.idx.flush()?;
+        Ok(())
+    }
+}
+
+/// `--api-key`, or the provider's environment variable.
+fn provider_api_key(args: &IngestRealArgs, env: &str) -> Result<String> {
+    match &args.api_key {
+        Some(key) => Ok(key.clone()),
+        None => {
+            std::env::var(env).map_err(|_| anyhow!("{} needs --api-key or {env}", args.provider))
+        }
+    }
+}
+
+fn notifier_for(url: Option<&str>) -> Result<Option<Notifier>> {
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    Ok(Some(Notifier::new(url, &load_http_options(None)?)?))
+}
+
+/// Posts `notification` if a webhook is configured. A webhook that is down
+/// is only logged, so it never masks the failure being reported.
+async fn notify(notifier: Option<&Notifier>, notification: &Notification) {
+    let Some(notifier) = notifier else {
+        return;
+    };
+    if let Err(err) = notifier.send(notification).await {
+        warn!(check = %notification.check, error = %err, "notification webhook failed");
+    }
+}
+
+/// Rewrites venues to ISO 10383 MICs. `--unknown-venue` decides whether an
+/// unresolvable venue is kept silently (`allow`), kept with a warning
+/// (`warn`) or aborts the ingest (`fail`).
+fn normalize_venues(events: &mut [md_core::Event], args: &VenueArgs) -> Result<()> {
+    let registry = load_venue_registry(args)?;
+    let unknown = registry.normalize_events(events);
+    check_unknown_venues(&unknown, args)
+}
+
+fn normalize_order_venues(orders: &mut [OrderEvent], args: &VenueArgs) -> Result<()> {
+    let registry = load_venue_registry(args)?;
+    let mut unknown = BTreeSet::new();
+    for order in orders.iter_mut() {
+        match registry.resolve(&order.venue) {
+            Some(mic) => order.venue = mic.to_string(),
+            None => {
+                unknown.insert(order.venue.clone());
+            }
+        }
+    }
+    check_unknown_venues(&unknown.into_iter().collect::<Vec<_>>(), args)
+}
+
+fn load_venue_registry(args: &VenueArgs) -> Result<VenueRegistry> {
+    match &args.venue_config {
+        Some(p) => {
+            let raw = std::fs::read_to_string(p)
+                .with_context(|| format!("failed reading {}", p.display()))?;
+            Ok(VenueRegistry::from_toml_str(&raw).context("invalid venue config")?)
+        }
+        None => Ok(VenueRegistry::default()),
+    }
+}
+
+fn check_unknown_venues(unknown: &[String], args: &VenueArgs) -> Result<()> {
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    match args.unknown_venue.as_str() {
+        "allow" => Ok(()),
+        "warn" => {
+            warn!(venues = %unknown.join(","), "venues are not known MIC codes");
+            Ok(())
+        }
+        "fail" => Err(anyhow!("unknown venues {}", unknown.join(","))),
+        other => Err(anyhow!("unsupported unknown-venue policy {other}")),
+    }
+}
+
+impl IngestRealArgs {
+    /// `--http-config`, then the environment, then the HTTP flags.
+    fn http_options(&self) -> Result<HttpOptions> {
+        let mut http = load_http_options(self.http_config.as_deref())?;
+        if let Some(proxy) = &self.proxy {
+            http.proxy = Some(proxy.clone());
+        }
+        if let Some(path) = &self.ca_cert {
+            http.ca_cert = Some(path.clone());
+        }
+        if let Some(secs) = self.http_timeout_secs {
+            http.timeout_secs = Some(secs);
+        }
+        Ok(http)
+    }
+}
+
+fn load_http_options(path: Option<&Path>) -> Result<HttpOptions> {
+    let base = match path {
+        Some(p) => {
+            let raw = std::fs::read_to_string(p)
+                .with_context(|| format!("failed reading {}", p.display()))?;
+            HttpOptions::from_toml_str(&raw).context("invalid http config")?
+        }
+        None => HttpOptions::default(),
+    };
+    base.with_env().context("invalid http environment")
+}
+
+/// Latest timestamp per symbol in an existing log, used as the lower bound
+/// for `--since-last`. Symbols must already be listed in the log header
+/// because appending cannot extend it.
+fn last_timestamps_by_symbol(
+    log: &Path,
+    symbols: &[String],
+) -> Result<BTreeMap<String, Timestamp>> {
+    let mut reader = EventLogReader::open(log)?;
+    let missing = symbols
+        .iter()
+        .filter(|s| !reader.header().symbols.contains(s))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "symbols {} are not in {}; run a full ingest to add them",
+            missing.join(","),
+            log.display()
+        ));
+    }
+    let mut last = BTreeMap::new();
+    while let Some(record) = reader.next_record()? {
+        last.insert(record.event.symbol, record.event.timestamp_ns);
+    }
+    Ok(last)
+}
+
+/// Appends to an existing log and index, continuing its sequence numbers.
+/// Events older than the log's last timestamp would break ordering and are
+/// skipped. Both count in `report`.
+fn append_log_and_index(
+    events: &[md_core::Event],
+    out: &Path,
+    report: &mut IngestReport,
+) -> Result<()> {
+    let mut writer = EventLogWriter::open_append(out)?;
+    let existing = writer.footer().clone();
+    let mut idx = IndexWriter::open_append(&index_path_for_log(out), existing.record_count)?;
+
+    let mut appended = 0u64;
+    let mut skipped = 0u64;
+    for event in events {
+        if existing.record_count > 0 && event.timestamp_ns < existing.last_timestamp_ns {
+            skipped += 1;
+            continue;
+        }
+        appended += 1;
+        let mut event = event.clone();
+        event.sequence = existing.last_sequence + appended;
+        let offset = writer.append(&event)?;
+        idx.maybe_add(&event, offset)?;
+        report.record(&event);
+    }
+
+    writer.flush()?;
+    idx.flush()?;
+    report.skip(skipped);
+    if skipped > 0 {
+        warn!(
+            skipped,
+            "skipped events older than the end of the existing log"
+        );
+    }
+    Ok(())
+}
+
+/// Prints records as they land in `log`, polling for growth. Runs until
+/// `to` is passed or the process is interrupted.
+async fn follow_log(
+    log: &Path,
+    index: Option<&Path>,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    out: Option<&Path>,
+    poll: Duration,
+) -> Result<()> {
+    let mut reader = EventLogReader::open(log)?;
+    let start = match (from, index) {
+        (Some(from), Some(index)) if index.exists() => IndexReader::open(index)?.seek_offset(from),
+        _ => None,
+    };
+    match start {
+        Some(offset) => reader.seek(offset)?,
+        None => reader.rewind_to_data()?,
+    }
+
+    let mut sink: Box<dyn Write> = match out {
+        Some(path) => Box::new(BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    loop {
+        let Some(record) = reader.poll_record()? else {
+            sink.flush()?;
+            tokio::time::sleep(poll).await;
+            continue;
+        };
+        let ts = record.event.timestamp_ns;
+        if to.is_some_and(|to| ts > to) {
+            break;
+        }
+        if from.is_some_and(|from| ts < from) {
+            continue;
+        }
+        writeln!(sink, "{}", format_event(&record.event))?;
+    }
+    sink.flush()?;
+    Ok(())
+}
+
+fn serve_source(
+    logs: Vec<PathBuf>,
+    index: Option<PathBuf>,
+    stitch: bool,
+    merge: bool,
+) -> Result<LogSource> {
+    if stitch || merge {
+        if index.is_some() {
+            return Err(anyhow!(
+                "--index only applies to a single --log; stitched days and merged logs use their sidecar indexes"
+            ));
+        }
+        let files = logs.into_iter().map(LogFile::new).collect();
+        return Ok(if stitch {
+            LogSource::Stitched(files)
+        } else {
+            LogSource::Merged(files)
+        });
+    }
+    let [log] = <[PathBuf; 1]>::try_from(logs)
+        .map_err(|_| anyhow!("several --log files need --stitch or --merge"))?;
+    let index = index.or_else(|| maybe_index_path(&log));
+    Ok(LogSource::Single(LogFile { log, index }))
+}
+
+/// Parses `SYMBOL=TIME`, where TIME is nanoseconds or RFC 3339.
+fn parse_symbol_start(raw: &str) -> Result<(String, Timestamp)> {
+    let (symbol, time) = raw
+        .split_once('=')
+        .filter(|(symbol, _)| !symbol.trim().is_empty())
+        .ok_or_else(|| anyhow!("invalid --symbol-start {raw}, expected SYMBOL=TIME"))?;
+    let time = time
+        .trim()
+        .parse::<Timestamp>()
+        .map_err(|e| anyhow!("invalid --symbol-start {raw}: {e}"))?;
+    Ok((symbol.trim().to_string(), time))
+}
+
+/// Builds the `serve --transform` chain, in the order given.
+fn parse_transforms(specs: &[String]) -> Result<Transforms> {
+    let mut transforms = Transforms::new();
+    for raw in specs {
+        let (name, value) = match raw.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (raw.trim(), None),
+        };
+        let invalid = || anyhow!("invalid --transform {raw}");
+        match (name, value) {
+            ("rescale-sizes", Some(factor)) => {
+                let factor = factor.parse::<f64>().map_err(|_| invalid())?;
+                if !factor.is_finite() || factor < 0.0 {
+                    return Err(anyhow!("--transform {raw}: factor must be 0 or more"));
+                }
+                transforms.push(RescaleSizes { factor });
+            }
+            ("anonymize-symbols", salt) => {
+                let salt = salt.map_or(Ok(0), str::parse).map_err(|_| invalid())?;
+                transforms.push(AnonymizeSymbols { salt });
+            }
+            ("shift-ns", Some(ns)) => {
+                let ns = ns.parse().map_err(|_| invalid())?;
+                transforms.push(ShiftTimestamps { ns });
+            }
+            _ => {
+                return Err(anyhow!(
+                    "unknown --transform {raw}, expected rescale-sizes=FACTOR, anonymize-symbols[=SALT] or shift-ns=NANOS"
+                ))
+            }
+        }
+    }
+    Ok(transforms)
+}
+
+/// Parses a UTC time of day, `HH:MM:SS[.fff]`, into nanoseconds after
+/// midnight.
+fn parse_time_of_day(raw: &str) -> Result<i64, String> {
+    Timestamp::from_rfc3339(&format!("1970-01-01T{raw}Z"))
+        .map(Timestamp::as_nanos)
+        .map_err(|_| format!("invalid time of day {raw}, expected HH:MM:SS[.fff]"))
+}
+
+/// Parses an events-per-second cap no lower than the engine honours.
+fn parse_events_per_sec(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(rate) if rate >= MIN_EVENTS_PER_SEC => Ok(rate),
+        _ => Err(format!(
+            "invalid events per second {raw}, expected a number of at least {MIN_EVENTS_PER_SEC}"
+        )),
+    }
+}
+
+/// Parses `serve --source` inputs into one in-memory tape, sequenced as
+/// an ingest of the same files would be.
+fn ingest_serve_source(
+    specs: &[String],
+    venue: &str,
+    tick_config: Option<&Path>,
+) -> Result<LogSource> {
+    let ticks = load_tick_table(tick_config)?;
+    let mut paths = Vec::new();
+    let mut kinds = HashMap::new();
+    for spec in specs {
+        let (kind, input) = spec.split_once(':').ok_or_else(|| {
+            anyhow!("invalid --source {spec}, expected KIND:INPUT such as csv_a:trades.csv")
+        })?;
+        if !matches!(kind, "csv_a" | "csv_b" | "csv_c" | "jsonl") {
+            return Err(anyhow!(
+                "unknown --source kind {kind}, expected csv_a, csv_b, csv_c or jsonl"
+            ));
+        }
+        for path in expand_inputs(&[input.to_string()])? {
+            kinds.insert(path.clone(), kind);
+            paths.push(path);
+        }
+    }
+    let events = ingest_files(&paths, |path| match kinds.get(path).copied() {
+        Some("csv_a") => parse_csv_a(path, venue, &ticks),
+        Some("csv_b") => parse_csv_b(path, venue, &ticks),
+        Some("csv_c") => parse_csv_c(path, venue, &ticks),
+        _ => parse_jsonl(path, venue),
+    })?;
+    info!(
+        events = events.len(),
+        inputs = paths.len(),
+        "ingested source"
+    );
+    Ok(LogSource::Events(events.into()))
+}
+
 fn maybe_index_path(log: &Path) -> Option<PathBuf> {
     let path = index_path_for_log(log);
     if path.exists() {
@@ -408,7 +3168,12 @@ fn maybe_index_path(log: &Path) -> Option<PathBuf> {
 }
 
 fn index_path_for_log(log: &Path) -> PathBuf {
-    PathBuf::from(format!("{}.idx", log.display()))
+    md_storage::index_path_for(log)
+}
+
+/// Sequence-gap report written next to logs of natively sequenced feeds.
+fn gap_report_path_for_log(log: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.gaps.json", log.display()))
 }
 
 fn seeded_feature_config(seed: u64) -> FeatureConfig {
@@ -468,3 +3233,221 @@ fn run_bench(log: &Path, index: Option<&Path>) -> Result<()> {
 
     Ok(())
 }
+
+fn run_info(
+    log: &Path,
+    index: Option<&Path>,
+    gap_threshold_ns: Option<i64>,
+    json: bool,
+) -> Result<LogStats> {
+    let stats = log_stats(log, index, gap_threshold_ns)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(stats);
+    }
+
+    let footer = &stats.summary;
+    println!("version: {}", stats.version);
+    println!(
+        "schema_hash: {:#018x} ({})",
+        stats.schema_hash, stats.schema
+    );
+    println!("symbols: {}", stats.symbols.join(","));
+    if !stats.price_units.is_empty() {
+        let units = stats
+            .price_units
+            .iter()
+            .map(|(symbol, unit)| format!("{symbol}={unit}"))
+            .collect::<Vec<_>>();
+        println!("price_units: {}", units.join(","));
+    }
+    println!("records: {} (from {:?})", footer.record_count, stats.source);
+    println!(
+        "timestamps: {}..{}",
+        footer.first_timestamp_ns, footer.last_timestamp_ns
+    );
+    println!(
+        "span_ns: {}",
+        footer
+            .last_timestamp_ns
+            .nanos_since(footer.first_timestamp_ns)
+    );
+    println!(
+        "sequences: {}..{}",
+        footer.first_sequence, footer.last_sequence
+    );
+    for (symbol, counts) in &footer.symbols {
+        println!(
+            "  {} trades={} quotes={}",
+            symbol, counts.trades, counts.quotes
+        );
+    }
+    if let Some(gaps) = &stats.gaps {
+        println!("gaps: {}", gaps.len());
+        for gap in gaps {
+            println!(
+                "  after seq {}: {}..{} ({} ns)",
+                gap.after_sequence, gap.from_ns, gap.to_ns, gap.duration_ns
+            );
+        }
+    }
+    match &stats.index {
+        Some(idx) => println!(
+            "index: stride={} entries={}/{} last_seq={} complete={}",
+            idx.stride,
+            idx.entries,
+            idx.expected_entries,
+            idx.last_sequence
+                .map_or_else(|| String::from("-"), |s| s.to_string()),
+            idx.complete
+        ),
+        None => println!("index: none"),
+    }
+    Ok(stats)
+}
+
+fn run_diff(args: &DiffArgs) -> Result<()> {
+    let left_index = args
+        .left_index
+        .clone()
+        .or_else(|| maybe_index_path(&args.left));
+    let right_index = args
+        .right_index
+        .clone()
+        .or_else(|| maybe_index_path(&args.right));
+    let left = read_events(&args.left, left_index.as_deref(), args.from, args.to)?;
+    let right = read_events(&args.right, right_index.as_deref(), args.from, args.to)?;
+    if args.book {
+        return print_book_diff(
+            args,
+            &diff_books(&left, &right, args.samples, args.max_rows),
+        );
+    }
+    let options = DiffOptions {
+        max_rows: args.max_rows,
+        buckets: args.buckets,
+    };
+    let result = diff_events(&left, &right, &options);
+
+    if let Some(path) = &args.report {
+        let html = render_html(
+            &result,
+            &args.left.display().to_string(),
+            &args.right.display().to_string(),
+        );
+        std::fs::write(path, html).with_context(|| format!("failed writing {}", path.display()))?;
+        info!(report = %path.display(), "wrote diff report");
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        println!(
+            "left: {} ({} events)",
+            args.left.display(),
+            result.left_events
+        );
+        println!(
+            "right: {} ({} events)",
+            args.right.display(),
+            result.right_events
+        );
+        println!("matched: {}", result.matched);
+        println!("resequenced: {}", result.resequenced);
+        println!("payload mismatches: {}", result.payload_mismatches);
+        println!("only in left: {}", result.only_left);
+        println!("only in right: {}", result.only_right);
+        for m in result.mismatches.iter().take(10) {
+            println!(
+                "  {} {} {} {} [{}]",
+                m.kind.label(),
+                m.timestamp_ns.as_nanos(),
+                m.symbol,
+                m.venue,
+                m.fields.join(",")
+            );
+        }
+    }
+    if !result.is_identical() {
+        return Err(anyhow!(
+            "logs differ: {} mismatches, {} resequenced",
+            result.mismatch_count(),
+            result.resequenced
+        ));
+    }
+    Ok(())
+}
+
+fn print_book_diff(args: &DiffArgs, result: &BookDiff) -> Result<()> {
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    } else {
+        println!(
+            "left: {} ({} events)",
+            args.left.display(),
+            result.left_events
+        );
+        println!(
+            "right: {} ({} events)",
+            args.right.display(),
+            result.right_events
+        );
+        println!(
+            "samples: {} ({} diverged)",
+            result.samples.len(),
+            result.diverged_samples
+        );
+        println!("books compared: {}", result.compared);
+        println!("book mismatches: {}", result.mismatch_count);
+        if let Some(at) = result.first_divergence {
+            println!("first divergence: {}", at.as_nanos());
+        }
+        for m in result.mismatches.iter().take(10) {
+            let side = match (m.left, m.right) {
+                (Some(_), None) => "only left ",
+                (None, Some(_)) => "only right ",
+                _ => "",
+            };
+            println!(
+                "  {side}{} {} {} [{}]",
+                m.timestamp_ns.as_nanos(),
+                m.symbol,
+                m.venue,
+                m.fields.join(",")
+            );
+        }
+    }
+    if !result.is_identical() {
+        return Err(anyhow!(
+            "books differ: {} mismatches over {} samples",
+            result.mismatch_count,
+            result.diverged_samples
+        ));
+    }
+    Ok(())
+}
+
+fn run_migrate(log: &Path, out: &Path, stride: u32) -> Result<()> {
+    if log == out {
+        return Err(anyhow!("--out must differ from --log"));
+    }
+    let mut reader = EventLogReader::open(log)?;
+    let header = reader.header().clone();
+    if !needs_migration(&header) {
+        info!(log = %log.display(), "log already at current version; rewriting anyway");
+    }
+
+    let mut events = Vec::new();
+    while let Some(record) = reader.next_record()? {
+        events.push(record.event);
+    }
+    write_log_and_index(&events, out, stride, &header.price_units)?;
+    info!(
+        events = events.len(),
+        from_version = header.version,
+        from_schema = ?header.schema,
+        to_schema = ?SchemaVersion::CURRENT,
+        out = %out.display(),
+        "migrated log"
+    );
+    Ok(())
+}
@@ -1,16 +1,17 @@
 use md_clients::{format_event, verify_feature_determinism};
 #[cfg(feature = "pcap")]
 use md_clients::{run_feature, FeatureConfig};
-use md_core::TickTable;
+use md_core::{Payload, TickError, TickTable};
+use md_export::write_jsonl;
 #[cfg(feature = "pcap")]
 use md_ingest::gen_pcap::generate_pcap;
-use md_ingest::ingest_csv_a;
 #[cfg(feature = "pcap")]
 use md_ingest::ingest_pcap;
+use md_ingest::{ingest_csv_a, ingest_jsonl};
 use md_replay_engine::read_events;
-use md_storage::{default_schema_hash, EventLogWriter, IndexWriter};
+use md_storage::{default_schema_hash, EventLogReader, EventLogWriter, IndexWriter};
 use rust_decimal::Decimal;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 use tempfile::tempdir;
 
@@ -90,6 +91,101 @@ fn verify_twice_same_bytes() {
     assert_eq!(b1, b2);
 }
 
+#[test]
+fn jsonl_export_round_trips() {
+    let dir = tempdir().expect("tempdir");
+    let csv_path = dir.path().join("a.csv");
+    std::fs::write(
+        &csv_path,
+        "timestamp,symbol,bid_px,bid_sz,ask_px,ask_sz\n2024-01-02T10:00:00Z,AAPL,100.00,10,100.02,11\n2024-01-02T10:00:00Z,MSFT,200.00,1,200.01,2\n",
+    )
+    .expect("write csv");
+
+    let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+    let events = ingest_csv_a(&csv_path, "X", &ticks).expect("ingest csv");
+    let jsonl_path = dir.path().join("events.jsonl");
+    write_jsonl(
+        &events,
+        std::fs::File::create(&jsonl_path).expect("create jsonl"),
+    )
+    .expect("write jsonl");
+
+    let back = ingest_jsonl(&jsonl_path, "ignored").expect("ingest jsonl");
+    assert_eq!(back, events);
+}
+
+#[test]
+fn scaled_prices_round_trip_through_the_log_header() {
+    let dir = tempdir().expect("tempdir");
+    let csv_path = dir.path().join("a.csv");
+    std::fs::write(
+        &csv_path,
+        "timestamp,symbol,bid_px,bid_sz,ask_px,ask_sz\n2024-01-02T10:00:00Z,BTCUSDT,67321.12345678,1,67321.12345679,2\n2024-01-02T10:00:00Z,IDX,123456789012.34567891,1,123456789012.345679,2\n",
+    )
+    .expect("write csv");
+
+    let config =
+        "default_tick = \"0.01\"\n[symbols]\nBTCUSDT = \"0.00000001\"\nIDX = \"0.00000001\"\n";
+    let unscaled = TickTable::from_toml_str(config).expect("tick table");
+    let err = ingest_csv_a(&csv_path, "X", &unscaled).expect_err("overflow");
+    assert!(
+        matches!(&err, md_ingest::IngestError::Tick(TickError::Overflow { symbol, .. }) if symbol == "IDX"),
+        "{err}"
+    );
+
+    let ticks =
+        TickTable::from_toml_str(&format!("{config}[scales]\nIDX = 2\n")).expect("tick table");
+    let events = ingest_csv_a(&csv_path, "X", &ticks).expect("ingest csv");
+    let log_path = dir.path().join("norm.eventlog");
+    let units = ["BTCUSDT", "IDX"]
+        .map(|s| (s.to_string(), ticks.price_unit(s).normalize().to_string()))
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let mut writer = EventLogWriter::create_with_price_units(
+        &log_path,
+        &[String::from("BTCUSDT"), String::from("IDX")],
+        default_schema_hash(),
+        &units,
+    )
+    .expect("eventlog writer");
+    for e in &events {
+        writer.append(e).expect("append");
+    }
+    writer.flush().expect("flush writer");
+
+    // A reader rescales with the header's units alone.
+    let reader = EventLogReader::open(&log_path).expect("reader");
+    let header_units = &reader.header().price_units;
+    assert_eq!(header_units["BTCUSDT"], "0.00000001");
+    assert_eq!(header_units["IDX"], "0.000001");
+    let mut table = TickTable::uniform(Decimal::ONE).expect("tick table");
+    for (symbol, unit) in header_units {
+        table = table
+            .with_tick(symbol, unit.parse().expect("unit"))
+            .expect("tick");
+    }
+    let prices = read_events(&log_path, None, None, None)
+        .expect("read events")
+        .iter()
+        .map(|e| match e.payload {
+            Payload::Quote { bid_px, ask_px, .. } => [bid_px, ask_px].map(|px| {
+                table
+                    .ticks_to_price(&e.symbol, px)
+                    .expect("price")
+                    .to_string()
+            }),
+            Payload::Trade { .. } => unreachable!("quotes only"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        prices,
+        [
+            ["67321.12345678", "67321.12345679"],
+            ["123456789012.345679", "123456789012.345679"]
+        ]
+    );
+}
+
 fn write_log(events: &[md_core::Event], log_path: &Path) -> PathBuf {
     let mut symbols = BTreeSet::new();
     for e in events {
@@ -0,0 +1,58 @@
+//! Replays a server's stream at full speed into a book builder and prints
+//! the final top of book of every symbol.
+//!
+//! ```bash
+//! cargo run -p md-clients --example book_printer -- 127.0.0.1:50051
+//! ```
+
+use anyhow::Result;
+use md_clients::{BookBuilder, ReplayClient, TopOfBook};
+use md_replay_engine::pb::StreamRequest;
+use std::io::Write;
+
+fn describe(symbol: &str, book: &TopOfBook) -> String {
+    let side = |px: i64, sz: i64, present: bool| {
+        if present {
+            format!("{px}x{sz}")
+        } else {
+            String::from("-")
+        }
+    };
+    let last = match (book.last_price, book.last_size) {
+        (Some(px), Some(sz)) => format!("{px}x{sz}"),
+        _ => String::from("-"),
+    };
+    format!(
+        "{symbol} bid={} ask={} spread={} last={last} trades={} volume={}",
+        side(book.bid_px, book.bid_sz, book.has_bid()),
+        side(book.ask_px, book.ask_sz, book.has_ask()),
+        book.spread()
+            .map_or_else(|| String::from("-"), |s| s.to_string()),
+        book.trades,
+        book.volume
+    )
+}
+
+/// Builds the books from the whole stream and writes one line per symbol.
+pub async fn run(endpoint: &str, out: &mut dyn Write) -> Result<BookBuilder> {
+    let mut client = ReplayClient::connect(endpoint).await?;
+    let request = StreamRequest {
+        max_speed: true,
+        ..StreamRequest::default()
+    };
+    let mut books = BookBuilder::new();
+    client.consume(request, &mut books).await?;
+    for (symbol, book) in books.books() {
+        writeln!(out, "{}", describe(symbol, book))?;
+    }
+    Ok(books)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let endpoint = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("127.0.0.1:50051"));
+    run(&endpoint, &mut std::io::stdout().lock()).await?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Feeds a live dashboard: streams a replay and writes one JSON line per
+//! event with the symbol's book and any feature signal, ready to pipe
+//! into a websocket or a file a dashboard tails.
+//!
+//! ```bash
+//! cargo run -p md-clients --example feature_feed -- 127.0.0.1:50051 1 > feed.jsonl
+//! ```
+
+use anyhow::Result;
+use md_clients::{BookBuilder, FeatureConfig, FeatureEngine, ReplayClient, StreamItem};
+use md_replay_engine::pb::StreamRequest;
+use serde_json::json;
+use std::io::Write;
+
+/// Streams at `speed` and writes the feed to `out`. Returns the number of
+/// lines written.
+pub async fn run(endpoint: &str, speed: f64, out: &mut dyn Write) -> Result<u64> {
+    let mut client = ReplayClient::connect(endpoint).await?;
+    let mut stream = client
+        .stream(StreamRequest {
+            speed,
+            ..StreamRequest::default()
+        })
+        .await?;
+    let mut books = BookBuilder::new();
+    let mut features = FeatureEngine::new(FeatureConfig::default());
+    let mut lines = 0;
+    while let Some(item) = stream.next().await {
+        let events = match item? {
+            StreamItem::Event(event) => vec![event],
+            StreamItem::ClosingCross(events) => events,
+            StreamItem::DayBoundary { day, log } => {
+                writeln!(out, "{}", json!({ "day": day, "log": log }))?;
+                lines += 1;
+                continue;
+            }
+        };
+        for event in events {
+            let signal = features.update(&event);
+            let book = books.apply(&event);
+            let line = json!({
+                "sequence": event.sequence,
+                "timestamp_ns": event.timestamp_ns,
+                "symbol": event.symbol,
+                "book": book,
+                "mid": book.mid(),
+                "spread": book.spread(),
+                "signal": signal,
+            });
+            writeln!(out, "{line}")?;
+            lines += 1;
+        }
+    }
+    Ok(lines)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let endpoint = args
+        .next()
+        .unwrap_or_else(|| String::from("127.0.0.1:50051"));
+    let speed = args.next().map(|s| s.parse()).transpose()?.unwrap_or(1.0);
+    run(&endpoint, speed, &mut std::io::stdout().lock()).await?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Streams a replay at a chosen speed and prints each event as it arrives,
+//! with a line for every stitched day.
+//!
+//! ```bash
+//! cargo run -p md-clients --example paced_consumer -- 127.0.0.1:50051 10 AAPL,MSFT
+//! ```
+
+use anyhow::Result;
+use md_clients::{format_event, ReplayClient, StreamConsumer};
+use md_core::Event;
+use md_replay_engine::pb::StreamRequest;
+use md_replay_engine::ReplayOutcome;
+use std::io::Write;
+use std::ops::ControlFlow;
+
+struct Printer<'a> {
+    out: &'a mut dyn Write,
+    error: Option<std::io::Error>,
+}
+
+impl Printer<'_> {
+    fn write(&mut self, line: &str) -> ControlFlow<()> {
+        match writeln!(self.out, "{line}") {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                self.error = Some(err);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+impl StreamConsumer for Printer<'_> {
+    fn on_event(&mut self, event: &Event) -> ControlFlow<()> {
+        self.write(&format_event(event))
+    }
+
+    fn on_day(&mut self, day: u32, log: &str) {
+        let _ = self.write(&format!("-- day {day} ({log})"));
+    }
+}
+
+/// Streams `symbols` (all when empty) at `speed` into `out`.
+pub async fn run(
+    endpoint: &str,
+    speed: f64,
+    symbols: Vec<String>,
+    out: &mut dyn Write,
+) -> Result<ReplayOutcome> {
+    let mut client = ReplayClient::connect(endpoint).await?;
+    let request = StreamRequest {
+        speed,
+        symbols,
+        ..StreamRequest::default()
+    };
+    let mut printer = Printer { out, error: None };
+    let outcome = client.consume(request, &mut printer).await?;
+    if let Some(err) = printer.error {
+        return Err(err.into());
+    }
+    Ok(outcome)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let endpoint = args
+        .next()
+        .unwrap_or_else(|| String::from("127.0.0.1:50051"));
+    let speed = args.next().map(|s| s.parse()).transpose()?.unwrap_or(1.0);
+    let symbols = args
+        .next()
+        .map(|s| s.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    let outcome = run(&endpoint, speed, symbols, &mut std::io::stdout().lock()).await?;
+    eprintln!("{outcome:?}");
+    Ok(())
+}
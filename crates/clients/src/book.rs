@@ -0,0 +1,138 @@
+//! Top of book per symbol, built up from a stream of events.
+
+use crate::client::StreamConsumer;
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+/// Latest quote and trade of one symbol. Prices are in ticks; a side with
+/// no price or size is empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TopOfBook {
+    pub bid_px: i64,
+    pub bid_sz: i64,
+    pub ask_px: i64,
+    pub ask_sz: i64,
+    pub last_price: Option<i64>,
+    pub last_size: Option<i64>,
+    pub trades: u64,
+    pub volume: i64,
+    pub updated_ns: Timestamp,
+}
+
+impl TopOfBook {
+    pub fn has_bid(&self) -> bool {
+        self.bid_px > 0 && self.bid_sz > 0
+    }
+
+    pub fn has_ask(&self) -> bool {
+        self.ask_px > 0 && self.ask_sz > 0
+    }
+
+    /// In ticks, when both sides are present.
+    pub fn spread(&self) -> Option<i64> {
+        (self.has_bid() && self.has_ask()).then(|| self.ask_px - self.bid_px)
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        (self.has_bid() && self.has_ask()).then(|| (self.bid_px + self.ask_px) as f64 / 2.0)
+    }
+}
+
+/// Each quote replaces its symbol's book; trades update the last trade and
+/// volume and leave the quote as it was.
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    books: BTreeMap<String, TopOfBook>,
+    events: u64,
+}
+
+impl BookBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `event` and returns its symbol's updated book.
+    pub fn apply(&mut self, event: &Event) -> &TopOfBook {
+        self.events += 1;
+        let book = self.books.entry(event.symbol.clone()).or_default();
+        match event.payload {
+            Payload::Quote {
+                bid_px,
+                bid_sz,
+                ask_px,
+                ask_sz,
+            } => {
+                book.bid_px = bid_px;
+                book.bid_sz = bid_sz;
+                book.ask_px = ask_px;
+                book.ask_sz = ask_sz;
+            }
+            Payload::Trade { price_ticks, size } => {
+                book.last_price = Some(price_ticks);
+                book.last_size = Some(size);
+                book.trades += 1;
+                book.volume = book.volume.saturating_add(size);
+            }
+        }
+        book.updated_ns = event.timestamp_ns;
+        book
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&TopOfBook> {
+        self.books.get(symbol)
+    }
+
+    /// Books in symbol order.
+    pub fn books(&self) -> impl Iterator<Item = (&str, &TopOfBook)> {
+        self.books
+            .iter()
+            .map(|(symbol, book)| (symbol.as_str(), book))
+    }
+
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+}
+
+impl StreamConsumer for BookBuilder {
+    fn on_event(&mut self, event: &Event) -> ControlFlow<()> {
+        self.apply(event);
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    #[test]
+    fn quotes_replace_the_book_and_trades_keep_it() {
+        let mut builder = BookBuilder::new();
+        let quote = QuoteTicks {
+            bid_px: 100,
+            bid_sz: 5,
+            ask_px: 104,
+            ask_sz: 0,
+        };
+        builder.apply(&Event::quote(1, 1, "X", "AAPL", quote));
+        assert_eq!(builder.book("AAPL").and_then(TopOfBook::spread), None);
+
+        let quote = QuoteTicks { ask_sz: 7, ..quote };
+        builder.apply(&Event::quote(2, 2, "X", "AAPL", quote));
+        let book = *builder.apply(&Event::trade(3, 3, "X", "AAPL", 102, 4));
+        assert_eq!((book.spread(), book.mid()), (Some(4), Some(102.0)));
+        assert_eq!(
+            (book.last_price, book.trades, book.volume),
+            (Some(102), 1, 4)
+        );
+        assert_eq!(book.updated_ns, Timestamp::from_nanos(3));
+
+        builder.apply(&Event::trade(4, 4, "X", "MSFT", 300, 1));
+        let symbols = builder.books().map(|(s, _)| s).collect::<Vec<_>>();
+        assert_eq!(symbols, ["AAPL", "MSFT"]);
+        assert_eq!(builder.events(), 4);
+    }
+}
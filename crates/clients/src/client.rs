@@ -0,0 +1,483 @@
+//! gRPC client for a replay server that hands back core events instead of
+//! protobuf messages.
+
+use hyper_util::rt::TokioIo;
+use md_core::{Event, Timestamp};
+use md_replay_engine::engine::from_proto;
+use md_replay_engine::pb::control_command::Command;
+use md_replay_engine::pb::event_message::Payload;
+use md_replay_engine::pb::replay_service_client::ReplayServiceClient;
+use md_replay_engine::pb::{
+    CloseSessionRequest, ControlCommand, CreateSessionRequest, EventMessage, FeedLine,
+    FeedLineRequest, GetLogInfoRequest, GetRangeRequest, ListSessionsRequest, LogInfo, SessionInfo,
+    Step, StreamRequest,
+};
+use md_replay_engine::{Compression, ReplayOutcome};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::{Request, Status, Streaming};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("server error: {0}")]
+    Status(#[from] Status),
+    #[error("token is not a valid header value")]
+    Token,
+    #[error("replay has ended")]
+    Ended,
+    #[error("tls error: {0}")]
+    Tls(String),
+}
+
+/// Opens the TCP connection for a channel and runs the TLS handshake on
+/// it, naming the host of the channel's URI.
+#[derive(Clone)]
+struct TlsConnect(TlsConnector);
+
+impl tower_service::Service<Uri> for TlsConnect {
+    type Response = TokioIo<TlsStream<TcpStream>>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.0.clone();
+        Box::pin(async move {
+            // IPv6 hosts come bracketed, as written in the URI.
+            let host = uri.host().unwrap_or_default().trim_matches(['[', ']']);
+            let port = uri.port_u16().unwrap_or(443);
+            let name = ServerName::try_from(host.to_string())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            let tcp = TcpStream::connect((name.to_str().as_ref(), port)).await?;
+            Ok(TokioIo::new(connector.connect(name, tcp).await?))
+        })
+    }
+}
+
+/// One message of a replay stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem {
+    Event(Event),
+    /// The next events start stitched day `day`, read from `log`.
+    DayBoundary {
+        day: u32,
+        log: String,
+    },
+    /// Every event of a closing cross, when the request asked for them
+    /// batched.
+    ClosingCross(Vec<Event>),
+}
+
+/// Events returned by [`ReplayClient::get_range`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventPage {
+    pub events: Vec<Event>,
+    /// Where the next page starts; `None` once the range is exhausted.
+    pub next_seq: Option<u64>,
+}
+
+/// Receives a replay stream from [`ReplayClient::consume`].
+pub trait StreamConsumer {
+    /// `Break` ends the stream early.
+    fn on_event(&mut self, event: &Event) -> ControlFlow<()>;
+
+    fn on_day(&mut self, _day: u32, _log: &str) {}
+
+    /// A batched closing cross. By default each event goes to
+    /// [`on_event`](Self::on_event) in order.
+    fn on_closing_cross(&mut self, events: &[Event]) -> ControlFlow<()> {
+        events.iter().try_for_each(|event| self.on_event(event))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayClient {
+    inner: ReplayServiceClient<Channel>,
+    token: Option<String>,
+}
+
+impl ReplayClient {
+    /// Connects to `endpoint`, e.g. `http://127.0.0.1:50051`; a bare
+    /// `host:port` is taken as plain HTTP.
+    pub async fn connect(endpoint: &str) -> Result<Self, ClientError> {
+        let endpoint = if endpoint.contains("://") {
+            endpoint.to_string()
+        } else {
+            format!("http://{endpoint}")
+        };
+        let channel = Endpoint::from_shared(endpoint)?.connect().await?;
+        Ok(Self::from_channel(channel))
+    }
+
+    /// Connects over TLS to a server started with a certificate that
+    /// `ca_pem` (one or more PEM certificates) vouches for. `endpoint` is
+    /// `https://host:port` or a bare `host:port`; the host is the name the
+    /// certificate must carry.
+    pub async fn connect_tls(endpoint: &str, ca_pem: &[u8]) -> Result<Self, ClientError> {
+        let authority = endpoint.strip_prefix("https://").unwrap_or(endpoint);
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_slice_iter(ca_pem) {
+            let cert = cert.map_err(|err| ClientError::Tls(err.to_string()))?;
+            roots
+                .add(cert)
+                .map_err(|err| ClientError::Tls(err.to_string()))?;
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|err| ClientError::Tls(err.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        let connector = TlsConnect(TlsConnector::from(Arc::new(config)));
+        // The handshake happens in the connector, so the channel itself is
+        // addressed as plain HTTP/2.
+        let channel = Endpoint::from_shared(format!("http://{authority}"))?
+            .connect_with_connector(connector)
+            .await?;
+        Ok(Self::from_channel(channel))
+    }
+
+    /// Accepts gzip or zstd replies, so a server set to compress does.
+    fn from_channel(channel: Channel) -> Self {
+        Self {
+            inner: ReplayServiceClient::new(channel)
+                .accept_compressed(CompressionEncoding::Gzip)
+                .accept_compressed(CompressionEncoding::Zstd),
+            token: None,
+        }
+    }
+
+    /// Compresses requests with `compression`.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.inner = self.inner.send_compressed(compression.encoding());
+        self
+    }
+
+    /// Largest message sent or accepted, in bytes. Big batches can exceed
+    /// tonic's 4 MiB default.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.inner = self
+            .inner
+            .max_decoding_message_size(bytes)
+            .max_encoding_message_size(bytes);
+        self
+    }
+
+    /// Sends `token` as a bearer token, which the server's per-token limits
+    /// key on.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub async fn stream(&mut self, request: StreamRequest) -> Result<EventStream, ClientError> {
+        let request = self.authorized(request).ok_or(ClientError::Token)?;
+        let inner = self.inner.stream_events(request).await?.into_inner();
+        Ok(EventStream::new(inner))
+    }
+
+    /// Streams `request` like [`stream`](Self::stream), and returns a
+    /// handle that pauses, resumes, changes speed or seeks it mid-replay.
+    pub async fn control(
+        &mut self,
+        request: StreamRequest,
+    ) -> Result<(ReplayControl, EventStream), ClientError> {
+        let (tx, rx) = mpsc::channel(16);
+        let control = ReplayControl { tx };
+        control.send(Command::Start(request)).await?;
+        let request = self
+            .authorized(ReceiverStream::new(rx))
+            .ok_or(ClientError::Token)?;
+        let inner = self.inner.control(request).await?.into_inner();
+        Ok((control, EventStream::new(inner)))
+    }
+
+    /// Streams `request` as a session named `name`, which
+    /// [`list_sessions`](Self::list_sessions) reports on and
+    /// [`close_session`](Self::close_session) can end from any client with
+    /// the same token.
+    pub async fn create_session(
+        &mut self,
+        name: &str,
+        request: StreamRequest,
+    ) -> Result<EventStream, ClientError> {
+        let request = self
+            .authorized(CreateSessionRequest {
+                name: name.to_string(),
+                request: Some(request),
+            })
+            .ok_or(ClientError::Token)?;
+        let inner = self.inner.create_session(request).await?.into_inner();
+        Ok(EventStream::new(inner))
+    }
+
+    /// Streams one `line` of the A/B feed pair named `pair`. Events start
+    /// once the other line has connected with the same `request`; each line
+    /// applies its own loss and jitter.
+    pub async fn feed_line(
+        &mut self,
+        pair: &str,
+        line: FeedLine,
+        request: StreamRequest,
+    ) -> Result<EventStream, ClientError> {
+        let request = self
+            .authorized(FeedLineRequest {
+                pair: pair.to_string(),
+                line: line as i32,
+                request: Some(request),
+            })
+            .ok_or(ClientError::Token)?;
+        let inner = self.inner.stream_feed_line(request).await?.into_inner();
+        Ok(EventStream::new(inner))
+    }
+
+    /// The server's open named sessions, with how far each has got.
+    pub async fn list_sessions(&mut self) -> Result<Vec<SessionInfo>, ClientError> {
+        let request = self
+            .authorized(ListSessionsRequest {})
+            .ok_or(ClientError::Token)?;
+        Ok(self
+            .inner
+            .list_sessions(request)
+            .await?
+            .into_inner()
+            .sessions)
+    }
+
+    /// Ends the named session, returning its state as it closed.
+    pub async fn close_session(&mut self, name: &str) -> Result<SessionInfo, ClientError> {
+        let request = self
+            .authorized(CloseSessionRequest {
+                name: name.to_string(),
+            })
+            .ok_or(ClientError::Token)?;
+        Ok(self.inner.close_session(request).await?.into_inner())
+    }
+
+    /// Header, symbols, time bounds and counts of the log the server
+    /// replays.
+    pub async fn log_info(&mut self) -> Result<LogInfo, ClientError> {
+        let request = self
+            .authorized(GetLogInfoRequest {})
+            .ok_or(ClientError::Token)?;
+        Ok(self.inner.get_log_info(request).await?.into_inner())
+    }
+
+    /// Up to `limit` events with sequences from `from_seq` to `to_seq`,
+    /// unpaced. A `limit` of 0 asks for as many as the server allows.
+    pub async fn get_range(
+        &mut self,
+        from_seq: u64,
+        to_seq: Option<u64>,
+        limit: u32,
+    ) -> Result<EventPage, ClientError> {
+        let request = self
+            .authorized(GetRangeRequest {
+                from_seq,
+                to_seq,
+                limit,
+            })
+            .ok_or(ClientError::Token)?;
+        let page = self.inner.get_range(request).await?.into_inner();
+        Ok(EventPage {
+            events: page.events.iter().filter_map(from_proto).collect(),
+            next_seq: page.next_seq,
+        })
+    }
+
+    /// `message` with the bearer token attached; `None` if the token is
+    /// not a valid header value.
+    fn authorized<T>(&self, message: T) -> Option<Request<T>> {
+        let mut request = Request::new(message);
+        if let Some(token) = &self.token {
+            let value = MetadataValue::try_from(format!("Bearer {token}")).ok()?;
+            request.metadata_mut().insert("authorization", value);
+        }
+        Some(request)
+    }
+
+    /// Streams `request` into `consumer` until the server ends the stream
+    /// or the consumer breaks.
+    pub async fn consume<C>(
+        &mut self,
+        request: StreamRequest,
+        consumer: &mut C,
+    ) -> Result<ReplayOutcome, ClientError>
+    where
+        C: StreamConsumer + ?Sized,
+    {
+        let mut stream = self.stream(request).await?;
+        let mut delivered = 0u64;
+        while let Some(item) = stream.next().await {
+            match item? {
+                StreamItem::Event(event) => {
+                    delivered += 1;
+                    if consumer.on_event(&event).is_break() {
+                        return Ok(ReplayOutcome::Stopped { delivered });
+                    }
+                }
+                StreamItem::ClosingCross(events) => {
+                    delivered += events.len() as u64;
+                    if consumer.on_closing_cross(&events).is_break() {
+                        return Ok(ReplayOutcome::Stopped { delivered });
+                    }
+                }
+                StreamItem::DayBoundary { day, log } => consumer.on_day(day, &log),
+            }
+        }
+        Ok(ReplayOutcome::Completed { delivered })
+    }
+}
+
+/// Steers a replay started by [`ReplayClient::control`]. Commands take
+/// effect from the next event on; dropping every handle leaves the replay
+/// running as it was.
+#[derive(Debug, Clone)]
+pub struct ReplayControl {
+    tx: mpsc::Sender<ControlCommand>,
+}
+
+impl ReplayControl {
+    pub async fn pause(&self) -> Result<(), ClientError> {
+        self.send(Command::Pause(true)).await
+    }
+
+    pub async fn resume(&self) -> Result<(), ClientError> {
+        self.send(Command::Resume(true)).await
+    }
+
+    /// Also ends max-speed replay. The server ignores speeds that are not
+    /// positive.
+    pub async fn set_speed(&self, speed: f64) -> Result<(), ClientError> {
+        self.send(Command::Speed(speed)).await
+    }
+
+    /// Replays from the first event at or after `ts`, forwards or back.
+    pub async fn seek(&self, ts: Timestamp) -> Result<(), ClientError> {
+        self.send(Command::SeekNs(ts.as_nanos())).await
+    }
+
+    /// Replays from the first event at or after `sequence`.
+    pub async fn seek_sequence(&self, sequence: u64) -> Result<(), ClientError> {
+        self.send(Command::SeekSequence(sequence)).await
+    }
+
+    /// Releases `count` more events of a step-mode replay, or `count`
+    /// timestamps' worth when `by_timestamp` is set.
+    pub async fn step(&self, count: u32, by_timestamp: bool) -> Result<(), ClientError> {
+        self.send(Command::Step(Step {
+            count,
+            by_timestamp,
+        }))
+        .await
+    }
+
+    async fn send(&self, command: Command) -> Result<(), ClientError> {
+        self.tx
+            .send(ControlCommand {
+                command: Some(command),
+            })
+            .await
+            .map_err(|_| ClientError::Ended)
+    }
+}
+
+/// Server stream of [`StreamItem`]s.
+pub struct EventStream {
+    inner: Streaming<EventMessage>,
+    /// Rest of the last `EventBatch` received.
+    batched: VecDeque<EventMessage>,
+    resume_token: Option<String>,
+    overflowed: u64,
+}
+
+impl EventStream {
+    fn new(inner: Streaming<EventMessage>) -> Self {
+        Self {
+            inner,
+            batched: VecDeque::new(),
+            resume_token: None,
+            overflowed: 0,
+        }
+    }
+
+    /// Token of the latest checkpoint received. Sent back as the request's
+    /// `resume_token`, it continues the replay after the events returned
+    /// so far, as long as every item up to the checkpoint was read.
+    pub fn resume_token(&self) -> Option<&str> {
+        self.resume_token.as_deref()
+    }
+
+    /// Messages the server has discarded so far because this stream read
+    /// too slowly, on a drop-oldest stream.
+    pub fn overflowed(&self) -> u64 {
+        self.overflowed
+    }
+
+    /// The next item, or `None` once the server ends the stream. Batched
+    /// events come out one by one, as if sent singly; checkpoints, stats
+    /// and messages without a payload are skipped.
+    pub async fn next(&mut self) -> Option<Result<StreamItem, ClientError>> {
+        loop {
+            let msg = match self.batched.pop_front() {
+                Some(msg) => msg,
+                None => match self.inner.message().await {
+                    Ok(Some(msg)) => msg,
+                    Ok(None) => return None,
+                    Err(status) => return Some(Err(status.into())),
+                },
+            };
+            match msg.payload {
+                Some(Payload::Batch(batch)) => {
+                    self.batched.extend(batch.events);
+                    continue;
+                }
+                Some(Payload::DayBoundary(day)) => {
+                    return Some(Ok(StreamItem::DayBoundary {
+                        day: day.day,
+                        log: day.log,
+                    }))
+                }
+                Some(Payload::ClosingCross(cross)) => {
+                    let events = cross.events.iter().filter_map(from_proto).collect();
+                    return Some(Ok(StreamItem::ClosingCross(events)));
+                }
+                Some(Payload::Checkpoint(checkpoint)) => {
+                    self.resume_token = Some(checkpoint.resume_token);
+                    continue;
+                }
+                Some(Payload::Stats(stats)) => {
+                    self.overflowed = stats.overflowed;
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(event) = from_proto(&msg) {
+                return Some(Ok(StreamItem::Event(event)));
+            }
+        }
+    }
+}
@@ -1,7 +1,23 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+pub mod book;
+pub mod client;
 pub mod feature;
 pub mod printer;
+pub mod stats;
+pub mod timeweighted;
 pub mod verify;
 
-pub use feature::{run_feature, FeatureConfig};
+pub use book::{BookBuilder, TopOfBook};
+pub use client::{
+    ClientError, EventPage, EventStream, ReplayClient, ReplayControl, StreamConsumer, StreamItem,
+};
+pub use feature::{run_feature, FeatureConfig, FeatureEngine, Features};
+pub use md_replay_engine::Compression;
 pub use printer::format_event;
+pub use stats::{run_stats, SymbolStats};
+pub use timeweighted::{EventWeighted, TimeWeighted};
 pub use verify::{verify_feature_determinism, VerifyError};
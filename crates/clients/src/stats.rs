@@ -0,0 +1,148 @@
+//! Per-symbol quote statistics, time-weighted next to the event-weighted
+//! figures they replace.
+
+use crate::timeweighted::{EventWeighted, TimeWeighted};
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolStats {
+    pub symbol: String,
+    pub quotes: u64,
+    pub trades: u64,
+    pub volume: i64,
+    /// From the symbol's first event to the last event of the input.
+    pub observed_ns: i128,
+    /// Mean spread in ticks over quotes with both sides present.
+    pub event_spread: Option<f64>,
+    /// Mean spread in ticks over the time both sides were present.
+    pub time_spread: Option<f64>,
+    /// Time-weighted size on each side while that side was present.
+    pub time_bid_depth: Option<f64>,
+    pub time_ask_depth: Option<f64>,
+    /// Share of `observed_ns` with both sides present.
+    pub bbo_presence: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct SymbolState {
+    quotes: u64,
+    trades: u64,
+    volume: i64,
+    event_spread: EventWeighted,
+    spread: TimeWeighted,
+    bid_depth: TimeWeighted,
+    ask_depth: TimeWeighted,
+}
+
+fn side(px: i64, sz: i64) -> Option<f64> {
+    (px > 0 && sz > 0).then_some(sz as f64)
+}
+
+/// Runs over `events` in order. Each quote replaces the symbol's book;
+/// trades leave it as it was.
+pub fn run_stats(events: &[Event]) -> Vec<SymbolStats> {
+    let mut state = BTreeMap::<&str, SymbolState>::new();
+    let mut end = None::<Timestamp>;
+    for event in events {
+        end = Some(end.map_or(event.timestamp_ns, |e| e.max(event.timestamp_ns)));
+        let st = state.entry(event.symbol.as_str()).or_default();
+        match event.payload {
+            Payload::Trade { size, .. } => {
+                st.trades += 1;
+                st.volume = st.volume.saturating_add(size);
+                // Starts the symbol's clock even before its first quote.
+                st.spread.advance(event.timestamp_ns);
+                st.bid_depth.advance(event.timestamp_ns);
+                st.ask_depth.advance(event.timestamp_ns);
+            }
+            Payload::Quote {
+                bid_px,
+                bid_sz,
+                ask_px,
+                ask_sz,
+            } => {
+                st.quotes += 1;
+                let (bid, ask) = (side(bid_px, bid_sz), side(ask_px, ask_sz));
+                let spread = bid.and(ask).map(|_| (ask_px - bid_px) as f64);
+                if let Some(spread) = spread {
+                    st.event_spread.add(spread);
+                }
+                st.spread.update(event.timestamp_ns, spread);
+                st.bid_depth.update(event.timestamp_ns, bid);
+                st.ask_depth.update(event.timestamp_ns, ask);
+            }
+        }
+    }
+
+    state
+        .into_iter()
+        .map(|(symbol, mut st)| {
+            if let Some(end) = end {
+                st.spread.advance(end);
+                st.bid_depth.advance(end);
+                st.ask_depth.advance(end);
+            }
+            SymbolStats {
+                symbol: symbol.to_string(),
+                quotes: st.quotes,
+                trades: st.trades,
+                volume: st.volume,
+                observed_ns: st.spread.total_ns(),
+                event_spread: st.event_spread.mean(),
+                time_spread: st.spread.mean(),
+                time_bid_depth: st.bid_depth.mean(),
+                time_ask_depth: st.ask_depth.mean(),
+                bbo_presence: st.spread.presence(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    fn quote(ts: i64, bid_px: i64, ask_px: i64, ask_sz: i64) -> Event {
+        Event::quote(
+            ts,
+            0,
+            "X",
+            "AAPL",
+            QuoteTicks {
+                bid_px,
+                bid_sz: 10,
+                ask_px,
+                ask_sz,
+            },
+        )
+    }
+
+    #[test]
+    fn quote_churn_does_not_dominate_time_weighted_spread() {
+        let events = vec![
+            Event::trade(0, 0, "X", "AAPL", 100, 5),
+            quote(100, 100, 101, 10),
+            quote(900, 100, 110, 10),
+            quote(901, 100, 110, 10),
+            quote(902, 100, 110, 10),
+            quote(903, 100, 101, 0),
+            Event::trade(1_000, 0, "X", "MSFT", 300, 1),
+        ];
+        let stats = run_stats(&events);
+        let aapl = &stats[0];
+        assert_eq!((aapl.quotes, aapl.trades, aapl.volume), (5, 1, 5));
+        assert_eq!(aapl.observed_ns, 1_000);
+        assert_eq!(aapl.event_spread, Some(7.75));
+        assert_eq!(aapl.time_spread, Some((800.0 + 3.0 * 10.0) / 803.0));
+        assert_eq!(aapl.bbo_presence, Some(0.803));
+        assert_eq!(aapl.time_bid_depth, Some(10.0));
+        assert_eq!(aapl.time_ask_depth, Some(10.0));
+
+        let msft = &stats[1];
+        assert_eq!((msft.observed_ns, msft.bbo_presence), (0, None));
+        assert_eq!(msft.time_spread, None);
+    }
+}
@@ -0,0 +1,119 @@
+//! Time-weighted aggregation: a value holds from the event that set it
+//! until the next one, and averages weigh it by how long it held.
+//! Event-weighted averages over-count whatever updates often, such as a
+//! spread that flickers wide for a few microseconds of quote churn.
+
+use md_core::Timestamp;
+
+/// Integral of a piecewise-constant value over time. `None` means the value
+/// is absent, e.g. a one-sided book has no spread; absent time counts
+/// towards [`presence`](Self::presence) but not the mean.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeWeighted {
+    current: Option<f64>,
+    since: Option<Timestamp>,
+    integral: f64,
+    present_ns: i128,
+    total_ns: i128,
+}
+
+impl TimeWeighted {
+    /// Closes the current value at `at` and holds `value` from there.
+    pub fn update(&mut self, at: Timestamp, value: Option<f64>) {
+        self.advance(at);
+        self.current = value;
+    }
+
+    /// Accrues the current value up to `at`; times before the last update
+    /// are ignored.
+    pub fn advance(&mut self, at: Timestamp) {
+        let Some(since) = self.since else {
+            self.since = Some(at);
+            return;
+        };
+        let held = i128::from(at.nanos_since(since).max(0));
+        self.total_ns += held;
+        if let Some(value) = self.current {
+            self.integral += value * held as f64;
+            self.present_ns += held;
+        }
+        self.since = Some(since.max(at));
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        self.current
+    }
+
+    /// Time-weighted mean over the time the value was present.
+    pub fn mean(&self) -> Option<f64> {
+        (self.present_ns > 0).then(|| self.integral / self.present_ns as f64)
+    }
+
+    /// Share of the observed time the value was present, in `[0, 1]`.
+    pub fn presence(&self) -> Option<f64> {
+        (self.total_ns > 0).then(|| self.present_ns as f64 / self.total_ns as f64)
+    }
+
+    pub fn present_ns(&self) -> i128 {
+        self.present_ns
+    }
+
+    pub fn total_ns(&self) -> i128 {
+        self.total_ns
+    }
+}
+
+/// Plain per-event mean, for comparison with [`TimeWeighted`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EventWeighted {
+    sum: f64,
+    count: u64,
+}
+
+impl EventWeighted {
+    pub fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ns: i64) -> Timestamp {
+        Timestamp::from_nanos(ns)
+    }
+
+    #[test]
+    fn weighs_values_by_how_long_they_held() {
+        let mut spread = TimeWeighted::default();
+        let mut events = EventWeighted::default();
+        // One long tight spell, then a burst of wide quotes.
+        for (ns, value) in [(0, Some(1.0)), (90, Some(9.0)), (91, Some(9.0)), (92, None)] {
+            spread.update(at(ns), value);
+            if let Some(value) = value {
+                events.add(value);
+            }
+        }
+        spread.advance(at(100));
+        assert_eq!(spread.total_ns(), 100);
+        assert_eq!(spread.present_ns(), 92);
+        assert_eq!(spread.mean(), Some((90.0 + 9.0 * 2.0) / 92.0));
+        assert_eq!(spread.presence(), Some(0.92));
+        assert!(events.mean() > Some(6.0));
+
+        // Going back in time accrues nothing.
+        spread.advance(at(50));
+        assert_eq!(spread.total_ns(), 100);
+        assert_eq!(TimeWeighted::default().mean(), None);
+    }
+}
@@ -0,0 +1,437 @@
+//! Runs the `examples/` programs against a server on a temp log, so the
+//! client SDK, consumer trait and book builder stay usable from outside
+//! the workspace's own binaries.
+
+#[allow(dead_code)]
+#[path = "../examples/book_printer.rs"]
+mod book_printer;
+#[allow(dead_code)]
+#[path = "../examples/feature_feed.rs"]
+mod feature_feed;
+#[allow(dead_code)]
+#[path = "../examples/paced_consumer.rs"]
+mod paced_consumer;
+
+use md_clients::{ClientError, Compression, EventPage, ReplayClient, StreamItem};
+use md_core::{Event, QuoteTicks, Timestamp};
+use md_replay_engine::pb::StreamRequest;
+use md_replay_engine::{
+    client_fingerprint, replay_requests, serve_grpc, AuditCall, AuditRecord, LogFile, LogSource,
+    ReplayConfig, ReplayOutcome, ReplayRequestsOptions, ServerSecurity, SessionLimits,
+    StreamTuning, TlsIdentity,
+};
+use md_storage::{default_schema_hash, EventLogWriter, SchemaVersion};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MS: i64 = 1_000_000;
+
+fn quote(ts: i64, seq: u64, symbol: &str, bid_px: i64, ask_px: i64) -> Event {
+    let ticks = QuoteTicks {
+        bid_px,
+        bid_sz: 10,
+        ask_px,
+        ask_sz: 12,
+    };
+    Event::quote(ts, seq, "X", symbol, ticks)
+}
+
+fn write_log(path: &Path) {
+    let events = [
+        quote(0, 1, "AAPL", 10_000, 10_002),
+        quote(MS, 2, "MSFT", 30_000, 30_004),
+        Event::trade(2 * MS, 3, "X", "AAPL", 10_001, 5),
+        // Wide enough to fire the spread signal.
+        quote(3 * MS, 4, "AAPL", 10_000, 10_040),
+        Event::trade(4 * MS, 5, "X", "MSFT", 30_002, 3),
+    ];
+    let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+    let mut writer = EventLogWriter::create(path, &symbols, default_schema_hash()).expect("writer");
+    for event in &events {
+        writer.append(event).expect("append");
+    }
+    writer.flush().expect("flush");
+}
+
+/// Serves a fresh log on a free port and returns the port's address.
+fn spawn_server(dir: &Path, tuning: StreamTuning, security: ServerSecurity) -> SocketAddr {
+    let log = dir.join("examples.eventlog");
+    write_log(&log);
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .expect("free port");
+    tokio::spawn(serve_grpc(
+        LogSource::Single(LogFile::new(log)),
+        addr,
+        ReplayConfig::default(),
+        SessionLimits::default(),
+        tuning,
+        None,
+        None,
+        security,
+    ));
+    addr
+}
+
+/// Serves a fresh log and returns its endpoint once it accepts connections.
+async fn start_server(dir: &Path) -> String {
+    start_tuned_server(dir, StreamTuning::default()).await
+}
+
+async fn start_tuned_server(dir: &Path, tuning: StreamTuning) -> String {
+    let addr = spawn_server(dir, tuning, ServerSecurity::default());
+    let endpoint = format!("http://{addr}");
+    for _ in 0..100 {
+        if ReplayClient::connect(&endpoint).await.is_ok() {
+            return endpoint;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server on {addr} never came up");
+}
+
+#[tokio::test]
+async fn examples_run_against_a_server() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+
+    let mut out = Vec::new();
+    let outcome = paced_consumer::run(&endpoint, 10.0, vec![String::from("AAPL")], &mut out)
+        .await
+        .expect("paced consumer");
+    assert_eq!(outcome, ReplayOutcome::Completed { delivered: 3 });
+    let printed = String::from_utf8(out).expect("utf8");
+    assert_eq!(printed.lines().count(), 3);
+    assert!(printed.lines().all(|l| l.contains(" AAPL ")), "{printed}");
+
+    let mut out = Vec::new();
+    let books = book_printer::run(&endpoint, &mut out)
+        .await
+        .expect("book printer");
+    assert_eq!(books.events(), 5);
+    assert_eq!(
+        String::from_utf8(out).expect("utf8"),
+        "AAPL bid=10000x10 ask=10040x12 spread=40 last=10001x5 trades=1 volume=5\n\
+         MSFT bid=30000x10 ask=30004x12 spread=4 last=30002x3 trades=1 volume=3\n"
+    );
+
+    let mut out = Vec::new();
+    let lines = feature_feed::run(&endpoint, 0.0, &mut out)
+        .await
+        .expect("feature feed");
+    assert_eq!(lines, 5);
+    let feed = String::from_utf8(out)
+        .expect("utf8")
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).expect("json"))
+        .collect::<Vec<_>>();
+    assert_eq!(feed[3]["symbol"], "AAPL");
+    assert_eq!(feed[3]["spread"], 40);
+    assert!(feed[3]["signal"]
+        .as_str()
+        .is_some_and(|s| s.contains("spread")));
+    assert!(feed[0]["signal"].is_null());
+}
+
+#[tokio::test]
+async fn control_handle_seeks_and_speeds_up() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let mut client = ReplayClient::connect(&endpoint).await.expect("connect");
+    // A millisecond of the log takes a second at this speed.
+    let request = StreamRequest {
+        speed: 0.001,
+        ..StreamRequest::default()
+    };
+    let (control, mut stream) = client.control(request).await.expect("control");
+
+    let mut seen = Vec::new();
+    while let Some(item) = stream.next().await {
+        if let StreamItem::Event(event) = item.expect("item") {
+            seen.push(event.sequence);
+        }
+        if seen == [1] {
+            control.seek_sequence(3).await.expect("seek");
+            control.set_speed(1_000.0).await.expect("speed");
+        }
+    }
+    assert_eq!(seen, [1, 3, 4, 5]);
+}
+
+fn tls_fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/tls")
+        .join(name)
+}
+
+#[tokio::test]
+async fn tls_server_requires_a_known_token() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let security = ServerSecurity {
+        tls: Some(TlsIdentity {
+            cert: tls_fixture("server.pem"),
+            key: tls_fixture("server.key"),
+        }),
+        tokens: vec![String::from("s3cret")],
+    };
+    let addr = spawn_server(dir.path(), StreamTuning::default(), security);
+    let ca = std::fs::read(tls_fixture("ca.pem")).expect("ca");
+    let endpoint = format!("https://localhost:{}", addr.port());
+    let mut client = None;
+    for _ in 0..100 {
+        if let Ok(connected) = ReplayClient::connect_tls(&endpoint, &ca).await {
+            client = Some(connected);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let client = client.expect("tls server never came up");
+
+    let unauthenticated = |result: Result<_, ClientError>| matches!(result, Err(ClientError::Status(s)) if s.code() == tonic::Code::Unauthenticated);
+    let request = StreamRequest {
+        max_speed: true,
+        ..StreamRequest::default()
+    };
+    assert!(unauthenticated(
+        client.clone().stream(request.clone()).await
+    ));
+    let mut wrong = client.clone().with_token("guess");
+    assert!(unauthenticated(wrong.stream(request.clone()).await));
+
+    let mut client = client.with_token("s3cret");
+    let mut stream = client.stream(request.clone()).await.expect("stream");
+    let mut events = 0;
+    while let Some(item) = stream.next().await {
+        item.expect("item");
+        events += 1;
+    }
+    assert_eq!(events, 5);
+
+    // Plaintext never gets as far as the service.
+    let plain = ReplayClient::connect(&addr.to_string()).await;
+    let streamed = match plain {
+        Ok(plain) => plain
+            .with_token("s3cret")
+            .stream(request.clone())
+            .await
+            .is_ok(),
+        Err(_) => false,
+    };
+    assert!(!streamed);
+
+    // Recorded calls go back over TLS with the token they were made with.
+    let record = AuditRecord {
+        received_ns: Timestamp::now(),
+        connection: 1,
+        client: client_fingerprint("s3cret"),
+        peer: None,
+        call: AuditCall::StreamEvents {
+            request: (&request).into(),
+        },
+    };
+    let options = ReplayRequestsOptions {
+        no_wait: true,
+        tokens: vec![String::from("other"), String::from("s3cret")],
+        ca_cert: Some(tls_fixture("ca.pem")),
+    };
+    let outcomes = replay_requests(&endpoint, &[record], options)
+        .await
+        .expect("replay requests");
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].end, "completed");
+    assert_eq!(outcomes[0].events, 5);
+}
+
+#[tokio::test]
+async fn health_and_reflection_are_served() {
+    use tonic_health::pb::health_check_response::ServingStatus;
+    use tonic_health::pb::health_client::HealthClient;
+    use tonic_health::pb::HealthCheckRequest;
+    use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+    use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+    use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+    use tonic_reflection::pb::v1::ServerReflectionRequest;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let channel = tonic::transport::Endpoint::from_shared(endpoint)
+        .expect("endpoint")
+        .connect()
+        .await
+        .expect("connect");
+    let mut health = HealthClient::new(channel.clone());
+    let status = health
+        .check(HealthCheckRequest {
+            service: String::from("replay.ReplayService"),
+        })
+        .await
+        .expect("check")
+        .into_inner()
+        .status;
+    assert_eq!(status, ServingStatus::Serving as i32);
+
+    let mut reflection = ServerReflectionClient::new(channel);
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut replies = reflection
+        .server_reflection_info(tokio_stream::once(request))
+        .await
+        .expect("list")
+        .into_inner();
+    let reply = replies.message().await.expect("reply").expect("one reply");
+    let Some(MessageResponse::ListServicesResponse(list)) = reply.message_response else {
+        panic!("unexpected reply {reply:?}");
+    };
+    assert!(list
+        .service
+        .iter()
+        .any(|s| s.name == "replay.ReplayService"));
+}
+
+#[tokio::test]
+async fn batched_streams_come_out_event_by_event() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let mut client = ReplayClient::connect(&endpoint).await.expect("connect");
+    let request = StreamRequest {
+        max_speed: true,
+        max_batch: 2,
+        ..StreamRequest::default()
+    };
+    let mut stream = client.stream(request).await.expect("stream");
+    let mut seen = Vec::new();
+    while let Some(item) = stream.next().await {
+        if let StreamItem::Event(event) = item.expect("item") {
+            seen.push(event.sequence);
+        }
+    }
+    assert_eq!(seen, [1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn compressed_batches_respect_the_message_limit() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let tuning = StreamTuning {
+        compression: Some(Compression::Zstd),
+        ..StreamTuning::default()
+    };
+    let endpoint = start_tuned_server(dir.path(), tuning).await;
+    let request = StreamRequest {
+        max_speed: true,
+        max_batch: 5,
+        ..StreamRequest::default()
+    };
+    let client = ReplayClient::connect(&endpoint)
+        .await
+        .expect("connect")
+        .with_compression(Compression::Gzip);
+    let mut stream = client
+        .clone()
+        .stream(request.clone())
+        .await
+        .expect("stream");
+    let mut events = 0;
+    while let Some(item) = stream.next().await {
+        item.expect("item");
+        events += 1;
+    }
+    assert_eq!(events, 5);
+
+    // One batch of all five events is over 64 bytes even compressed.
+    let mut small = client.with_max_message_size(64);
+    let mut stream = small.stream(request).await.expect("stream");
+    let failed = stream.next().await.expect("an item");
+    assert!(matches!(failed, Err(ClientError::Status(_))));
+}
+
+#[tokio::test]
+async fn log_info_describes_the_served_log() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let mut client = ReplayClient::connect(&endpoint).await.expect("connect");
+    let info = client.log_info().await.expect("info");
+    assert_eq!(info.schema_hash, default_schema_hash());
+    assert_eq!(info.schema, SchemaVersion::CURRENT.description());
+    assert_eq!(info.symbols, ["AAPL", "MSFT"]);
+    assert_eq!(info.logs, ["examples.eventlog"]);
+    assert_eq!(info.record_count, 5);
+    assert_eq!((info.first_ns, info.last_ns), (Some(0), Some(4 * MS)));
+    assert_eq!((info.first_sequence, info.last_sequence), (1, 5));
+    let counts = info
+        .counts
+        .iter()
+        .map(|c| (c.symbol.as_str(), c.trades, c.quotes))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, [("AAPL", 1, 2), ("MSFT", 1, 1)]);
+}
+
+#[tokio::test]
+async fn ranges_page_through_the_log() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let mut client = ReplayClient::connect(&endpoint).await.expect("connect");
+    let seqs = |page: &EventPage| page.events.iter().map(|e| e.sequence).collect::<Vec<_>>();
+    let first = client.get_range(2, None, 2).await.expect("range");
+    assert_eq!(seqs(&first), [2, 3]);
+    assert_eq!(first.next_seq, Some(4));
+    let rest = client.get_range(4, None, 0).await.expect("range");
+    assert_eq!(seqs(&rest), [4, 5]);
+    assert_eq!(rest.next_seq, None);
+    let bounded = client.get_range(1, Some(2), 10).await.expect("range");
+    assert_eq!(seqs(&bounded), [1, 2]);
+    assert_eq!(bounded.next_seq, None);
+    let backwards = client.get_range(3, Some(1), 10).await;
+    assert!(
+        matches!(backwards, Err(ClientError::Status(s)) if s.code() == tonic::Code::InvalidArgument)
+    );
+}
+
+#[tokio::test]
+async fn named_sessions_are_listed_and_closed() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let endpoint = start_server(dir.path()).await;
+    let mut client = ReplayClient::connect(&endpoint).await.expect("connect");
+    // A millisecond of the log takes a second at this speed.
+    let slow = StreamRequest {
+        speed: 0.001,
+        ..StreamRequest::default()
+    };
+    let mut stream = client
+        .create_session("slow", slow.clone())
+        .await
+        .expect("create");
+    assert!(matches!(
+        stream.next().await,
+        Some(Ok(StreamItem::Event(_)))
+    ));
+    let taken = client.create_session("slow", slow).await;
+    assert!(matches!(taken, Err(ClientError::Status(s)) if s.code() == tonic::Code::AlreadyExists));
+
+    let sessions = client.list_sessions().await.expect("list");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].name, "slow");
+    assert_eq!(sessions[0].events_sent, 1);
+    assert_eq!(sessions[0].last_event_ns, Some(0));
+
+    // Only the token that opened a session may see or close it.
+    let mut other = client.clone().with_token("other");
+    assert!(other.list_sessions().await.expect("list").is_empty());
+    assert!(other.close_session("slow").await.is_err());
+    let closed = client.close_session("slow").await.expect("close");
+    assert_eq!(closed.events_sent, 1);
+    let ended = stream.next().await;
+    assert!(
+        matches!(ended, Some(Err(ClientError::Status(s))) if s.code() == tonic::Code::Cancelled)
+    );
+    assert!(stream.next().await.is_none());
+    for _ in 0..100 {
+        if client.list_sessions().await.expect("list").is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("closed session is still listed");
+}
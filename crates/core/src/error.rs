@@ -0,0 +1,75 @@
+/// Coarse classification of a failure so callers can choose a retry policy
+/// without matching on error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Worth retrying as-is: interrupted or timed-out IO, dropped
+    /// connections, busy resources.
+    Transient,
+    /// The data itself is damaged or unreadable; retrying will not help.
+    Corrupt,
+    /// Wrong path, missing permissions, or input this build does not
+    /// support. Needs a fix before retrying.
+    Config,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Transient)
+    }
+
+    pub fn from_io(err: &std::io::Error) -> Self {
+        use std::io::ErrorKind as Io;
+        match err.kind() {
+            Io::Interrupted
+            | Io::WouldBlock
+            | Io::TimedOut
+            | Io::ConnectionReset
+            | Io::ConnectionAborted
+            | Io::ConnectionRefused
+            | Io::NotConnected
+            | Io::BrokenPipe
+            | Io::AddrInUse => Self::Transient,
+            Io::UnexpectedEof | Io::InvalidData => Self::Corrupt,
+            Io::NotFound
+            | Io::PermissionDenied
+            | Io::InvalidInput
+            | Io::AlreadyExists
+            | Io::AddrNotAvailable
+            | Io::Unsupported => Self::Config,
+            _ => Self::Other,
+        }
+    }
+
+    /// Classifies by the first `std::io::Error` in the source chain, for
+    /// wrappers such as transport errors that do not expose their cause
+    /// directly.
+    pub fn from_source_chain(err: &(dyn std::error::Error + 'static)) -> Self {
+        let mut current = Some(err);
+        while let Some(e) = current {
+            if let Some(io) = e.downcast_ref::<std::io::Error>() {
+                return Self::from_io(io);
+            }
+            current = e.source();
+        }
+        Self::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn io_errors_are_classified() {
+        let kind = |k| ErrorKind::from_io(&io::Error::from(k));
+        assert_eq!(kind(io::ErrorKind::TimedOut), ErrorKind::Transient);
+        assert_eq!(kind(io::ErrorKind::UnexpectedEof), ErrorKind::Corrupt);
+        assert_eq!(kind(io::ErrorKind::NotFound), ErrorKind::Config);
+        assert_eq!(kind(io::ErrorKind::OutOfMemory), ErrorKind::Other);
+        assert!(kind(io::ErrorKind::ConnectionReset).is_retryable());
+        assert!(!kind(io::ErrorKind::PermissionDenied).is_retryable());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::time::Timestamp;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -6,7 +7,19 @@ pub enum EventType {
     Quote,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+impl std::str::FromStr for EventType {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_ascii_lowercase().as_str() {
+            "trade" => Ok(Self::Trade),
+            "quote" => Ok(Self::Quote),
+            other => Err(format!("unknown event type {other}, expected trade|quote")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Payload {
     Trade {
         price_ticks: i64,
@@ -30,21 +43,27 @@ pub struct QuoteTicks {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Event {
-    pub timestamp_ns: u64,
+    pub timestamp_ns: Timestamp,
     pub sequence: u64,
     pub venue: String,
     pub symbol: String,
     pub event_type: EventType,
     pub payload: Payload,
+    /// The feed's own sequence number, when the source has one. Unlike
+    /// `sequence`, it is never reassigned, so gaps in the venue's
+    /// numbering stay visible after normalization.
+    #[serde(default)]
+    pub source_sequence: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PendingEvent {
-    pub timestamp_ns: u64,
+    pub timestamp_ns: Timestamp,
     pub venue: String,
     pub symbol: String,
     pub payload: Payload,
     pub ingest_order: u64,
+    pub source_sequence: Option<u64>,
 }
 
 impl PendingEvent {
@@ -60,6 +79,7 @@ impl PendingEvent {
             symbol: self.symbol,
             event_type,
             payload: self.payload,
+            source_sequence: self.source_sequence,
         }
     }
 }
@@ -81,7 +101,7 @@ pub fn assign_sequences(mut pending: Vec<PendingEvent>) -> Vec<Event> {
 
 impl Event {
     pub fn trade(
-        timestamp_ns: u64,
+        timestamp_ns: impl Into<Timestamp>,
         sequence: u64,
         venue: impl Into<String>,
         symbol: impl Into<String>,
@@ -89,24 +109,25 @@ impl Event {
         size: i64,
     ) -> Self {
         Self {
-            timestamp_ns,
+            timestamp_ns: timestamp_ns.into(),
             sequence,
             venue: venue.into(),
             symbol: symbol.into(),
             event_type: EventType::Trade,
             payload: Payload::Trade { price_ticks, size },
+            source_sequence: None,
         }
     }
 
     pub fn quote(
-        timestamp_ns: u64,
+        timestamp_ns: impl Into<Timestamp>,
         sequence: u64,
         venue: impl Into<String>,
         symbol: impl Into<String>,
         quote: QuoteTicks,
     ) -> Self {
         Self {
-            timestamp_ns,
+            timestamp_ns: timestamp_ns.into(),
             sequence,
             venue: venue.into(),
             symbol: symbol.into(),
@@ -117,6 +138,12 @@ impl Event {
                 ask_px: quote.ask_px,
                 ask_sz: quote.ask_sz,
             },
+            source_sequence: None,
         }
     }
+
+    pub fn with_source_sequence(mut self, source_sequence: u64) -> Self {
+        self.source_sequence = Some(source_sequence);
+        self
+    }
 }
@@ -1,5 +1,23 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+pub mod error;
 pub mod event;
+pub mod feature;
+pub mod order;
+pub mod ordering;
+pub mod testing;
 pub mod tick;
+pub mod time;
+pub mod venue;
 
+pub use error::ErrorKind;
 pub use event::{assign_sequences, Event, EventType, Payload, PendingEvent, QuoteTicks};
-pub use tick::{TickConfigFile, TickError, TickTable};
+pub use feature::{run_feature, FeatureConfig, FeatureEngine, Features};
+pub use order::{OrderAction, OrderEvent, OrderSide};
+pub use ordering::OrderCheck;
+pub use tick::{TickConfigFile, TickError, TickTable, MAX_SCALE};
+pub use time::{Timestamp, TimestampError};
+pub use venue::{VenueConfigFile, VenueError, VenueRegistry};
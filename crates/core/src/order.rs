@@ -0,0 +1,87 @@
+use crate::time::Timestamp;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Buy => "buy",
+            Self::Sell => "sell",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderSide {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "buy" | "b" => Ok(Self::Buy),
+            "sell" | "s" => Ok(Self::Sell),
+            other => Err(format!("unknown side {other}, expected buy|sell")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OrderAction {
+    New,
+    Replace,
+    Cancel,
+    Fill,
+    Reject,
+}
+
+impl OrderAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Replace => "replace",
+            Self::Cancel => "cancel",
+            Self::Fill => "fill",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderAction {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "new" | "submit" => Ok(Self::New),
+            "replace" | "amend" => Ok(Self::Replace),
+            "cancel" => Ok(Self::Cancel),
+            "fill" | "partial_fill" => Ok(Self::Fill),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!(
+                "unknown action {other}, expected new|replace|cancel|fill|reject"
+            )),
+        }
+    }
+}
+
+/// One order-gateway record: a strategy action or the gateway's answer,
+/// stamped with the client clock. Kept apart from market [`Event`]s so the
+/// tape schema stays unchanged.
+///
+/// [`Event`]: crate::Event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrderEvent {
+    pub timestamp_ns: Timestamp,
+    pub sequence: u64,
+    pub venue: String,
+    pub symbol: String,
+    pub order_id: String,
+    pub action: OrderAction,
+    pub side: OrderSide,
+    /// Limit or fill price; none for market orders and cancels.
+    pub price_ticks: Option<i64>,
+    /// Order quantity, or the filled quantity for fills.
+    pub size: i64,
+}
@@ -0,0 +1,85 @@
+//! Ordering assertions for event streams. With the `order-checks` feature
+//! every observed event is checked and a violation panics; without it
+//! [`OrderCheck::observe`] compiles to nothing.
+
+use crate::event::Event;
+#[cfg(any(test, feature = "order-checks"))]
+use crate::time::Timestamp;
+
+/// Asserts that events leave a stream in strictly increasing sequence
+/// order and never go back in time.
+#[derive(Debug, Clone)]
+pub struct OrderCheck {
+    #[cfg(any(test, feature = "order-checks"))]
+    label: &'static str,
+    #[cfg(any(test, feature = "order-checks"))]
+    last: Option<(u64, Timestamp)>,
+}
+
+impl OrderCheck {
+    /// `label` names the stream in failure messages.
+    pub fn new(label: &'static str) -> Self {
+        #[cfg(not(any(test, feature = "order-checks")))]
+        let _ = label;
+        Self {
+            #[cfg(any(test, feature = "order-checks"))]
+            label,
+            #[cfg(any(test, feature = "order-checks"))]
+            last: None,
+        }
+    }
+
+    #[inline]
+    pub fn observe(&mut self, event: &Event) {
+        #[cfg(any(test, feature = "order-checks"))]
+        {
+            if let Some((sequence, timestamp_ns)) = self.last {
+                assert!(
+                    event.sequence > sequence,
+                    "{}: sequence {} sent after {sequence}",
+                    self.label,
+                    event.sequence
+                );
+                assert!(
+                    event.timestamp_ns >= timestamp_ns,
+                    "{}: sequence {} at {} sent after {timestamp_ns}",
+                    self.label,
+                    event.sequence,
+                    event.timestamp_ns
+                );
+            }
+            self.last = Some((event.sequence, event.timestamp_ns));
+        }
+        #[cfg(not(any(test, feature = "order-checks")))]
+        let _ = event;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ordered_streams() {
+        let mut check = OrderCheck::new("test");
+        check.observe(&Event::trade(1, 1, "X", "AAPL", 100, 1));
+        check.observe(&Event::trade(1, 2, "X", "AAPL", 100, 1));
+        check.observe(&Event::trade(5, 7, "X", "AAPL", 100, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "test: sequence 2 sent after 2")]
+    fn rejects_repeated_sequences() {
+        let mut check = OrderCheck::new("test");
+        check.observe(&Event::trade(1, 2, "X", "AAPL", 100, 1));
+        check.observe(&Event::trade(2, 2, "X", "AAPL", 100, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "test: sequence 3 at 4 sent after 5")]
+    fn rejects_timestamp_inversions() {
+        let mut check = OrderCheck::new("test");
+        check.observe(&Event::trade(5, 2, "X", "AAPL", 100, 1));
+        check.observe(&Event::trade(4, 3, "X", "AAPL", 100, 1));
+    }
+}
@@ -0,0 +1,246 @@
+//! Fixtures for tests: a small builder for event tapes.
+//!
+//! ```
+//! use md_core::testing::Tape;
+//!
+//! let events = Tape::new()
+//!     .quote("AAPL", "100.00/100.02", 10, 11)
+//!     .at("+5ms")
+//!     .trade("AAPL", "100.01", 3)
+//!     .build();
+//! assert_eq!(events[1].timestamp_ns.nanos_since(events[0].timestamp_ns), 5_000_000);
+//! ```
+//!
+//! Builder methods panic on malformed prices or offsets, pointing at the
+//! calling test.
+
+#![allow(clippy::panic)]
+
+use crate::{assign_sequences, Event, Payload, PendingEvent, TickTable, Timestamp};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// 2023-11-14T22:13:20Z, the epoch the sample data uses.
+pub const DEFAULT_START: Timestamp = Timestamp::from_nanos(1_700_000_000_000_000_000);
+
+#[derive(Debug, Clone)]
+pub struct Tape {
+    start: Timestamp,
+    now: Timestamp,
+    venue: String,
+    ticks: TickTable,
+    events: Vec<PendingEvent>,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tape {
+    /// Starts at [`DEFAULT_START`] on venue `X` with a 0.01 tick.
+    pub fn new() -> Self {
+        Self::starting_at(DEFAULT_START)
+    }
+
+    pub fn starting_at(start: impl Into<Timestamp>) -> Self {
+        let start = start.into();
+        Self {
+            start,
+            now: start,
+            venue: String::from("X"),
+            ticks: TickTable::uniform(Decimal::new(1, 2))
+                .unwrap_or_else(|err| panic!("default tick table: {err}")),
+            events: Vec::new(),
+        }
+    }
+
+    /// Venue for the events added after this call.
+    pub fn venue(mut self, venue: &str) -> Self {
+        self.venue = venue.to_string();
+        self
+    }
+
+    /// Uniform tick size used to convert the prices added after this call.
+    #[track_caller]
+    pub fn tick_size(mut self, tick: &str) -> Self {
+        let tick = Decimal::from_str(tick).unwrap_or_else(|err| panic!("tick size {tick}: {err}"));
+        self.ticks = TickTable::uniform(tick).unwrap_or_else(|err| panic!("tick size: {err}"));
+        self
+    }
+
+    pub fn ticks(mut self, ticks: TickTable) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Moves the clock. `+5ms` (or `-5ms`) is relative to the current
+    /// time, `5ms` is relative to the start and anything else is read as
+    /// a [`Timestamp`] (nanoseconds or RFC 3339). Units are `ns`, `us`,
+    /// `ms`, `s`, `m` and `h`.
+    #[track_caller]
+    pub fn at(mut self, when: &str) -> Self {
+        let relative = |raw: &str| {
+            parse_duration_ns(raw).unwrap_or_else(|| panic!("invalid tape offset {when}"))
+        };
+        self.now = if let Some(rest) = when.strip_prefix('+') {
+            self.now.saturating_add_nanos(relative(rest))
+        } else if let Some(rest) = when.strip_prefix('-') {
+            self.now.saturating_add_nanos(-relative(rest))
+        } else if let Some(offset) = parse_duration_ns(when) {
+            self.start.saturating_add_nanos(offset)
+        } else {
+            Timestamp::from_str(when)
+                .unwrap_or_else(|err| panic!("invalid tape time {when}: {err}"))
+        };
+        self
+    }
+
+    /// A quote at the current time; `book` is `BID/ASK`.
+    #[track_caller]
+    pub fn quote(mut self, symbol: &str, book: &str, bid_sz: i64, ask_sz: i64) -> Self {
+        let (bid, ask) = book
+            .split_once('/')
+            .unwrap_or_else(|| panic!("quote {book} is not BID/ASK"));
+        let payload = Payload::Quote {
+            bid_px: self.price(symbol, bid),
+            bid_sz,
+            ask_px: self.price(symbol, ask),
+            ask_sz,
+        };
+        self.push(symbol, payload);
+        self
+    }
+
+    /// A trade at the current time.
+    #[track_caller]
+    pub fn trade(mut self, symbol: &str, price: &str, size: i64) -> Self {
+        let payload = Payload::Trade {
+            price_ticks: self.price(symbol, price),
+            size,
+        };
+        self.push(symbol, payload);
+        self
+    }
+
+    /// Events in the order they were added, before sequencing.
+    pub fn pending(&self) -> Vec<PendingEvent> {
+        self.events.clone()
+    }
+
+    /// Sequenced events, sorted the way an ingest would sort them.
+    pub fn build(&self) -> Vec<Event> {
+        assign_sequences(self.pending())
+    }
+
+    /// Distinct symbols, sorted, as a log header lists them.
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols = self
+            .events
+            .iter()
+            .map(|e| e.symbol.clone())
+            .collect::<Vec<_>>();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    #[track_caller]
+    fn price(&self, symbol: &str, raw: &str) -> i64 {
+        self.ticks
+            .price_str_to_ticks(symbol, raw.trim())
+            .unwrap_or_else(|err| panic!("price {raw} for {symbol}: {err}"))
+    }
+
+    fn push(&mut self, symbol: &str, payload: Payload) {
+        let ingest_order = self.events.len() as u64;
+        self.events.push(PendingEvent {
+            timestamp_ns: self.now,
+            venue: self.venue.clone(),
+            symbol: symbol.to_string(),
+            payload,
+            ingest_order,
+            source_sequence: None,
+        });
+    }
+}
+
+fn parse_duration_ns(raw: &str) -> Option<i64> {
+    let split = raw.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = raw.split_at(split);
+    let count: i64 = count.parse().ok()?;
+    let scale = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60_000_000_000,
+        "h" => 3_600_000_000_000,
+        _ => return None,
+    };
+    count.checked_mul(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_sequenced_events_on_a_moving_clock() {
+        let tape = Tape::starting_at(1_000)
+            .quote("MSFT", "300.00/300.05", 2, 3)
+            .at("+5ms")
+            .venue("XNAS")
+            .trade("AAPL", "100.01", 7)
+            .at("1us")
+            .trade("AAPL", "100.02", 1);
+        let events = tape.build();
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.timestamp_ns.as_nanos(), e.sequence, e.symbol.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1_000, 1, "MSFT"),
+                (2_000, 2, "AAPL"),
+                (5_001_000, 3, "AAPL")
+            ]
+        );
+        assert_eq!(
+            events[0],
+            Event::quote(
+                1_000,
+                1,
+                "X",
+                "MSFT",
+                crate::QuoteTicks {
+                    bid_px: 30_000,
+                    bid_sz: 2,
+                    ask_px: 30_005,
+                    ask_sz: 3,
+                }
+            )
+        );
+        assert_eq!(events[2].venue, "XNAS");
+        assert_eq!(tape.symbols(), vec!["AAPL", "MSFT"]);
+
+        let coarse = Tape::new()
+            .tick_size("0.25")
+            .trade("ES", "4500.50", 1)
+            .build();
+        assert_eq!(
+            coarse[0].payload,
+            Payload::Trade {
+                price_ticks: 18_002,
+                size: 1
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is not BID/ASK")]
+    fn malformed_quote_panics() {
+        let _ = Tape::new().quote("AAPL", "100.00", 1, 1);
+    }
+}
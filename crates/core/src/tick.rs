@@ -11,36 +11,97 @@ pub enum TickError {
     InvalidDecimal(String),
     #[error("tick size must be positive")]
     NonPositiveTick,
-    #[error("tick conversion overflow")]
-    Overflow,
+    #[error("lot size must be positive")]
+    NonPositiveLot,
+    /// The price is too many ticks away from zero for an event payload.
+    /// Crypto prices on very fine ticks hit this well before equity
+    /// prices do; a coarser tick or a `scales` entry for the symbol
+    /// avoids it. `tick` is the scaled unit.
+    #[error("price {price} for {symbol} overflows 64-bit ticks of {tick}")]
+    Overflow {
+        symbol: String,
+        price: Decimal,
+        tick: Decimal,
+    },
+    #[error("{ticks} ticks of {tick} for {symbol} overflow a decimal price")]
+    PriceOverflow {
+        symbol: String,
+        ticks: i64,
+        tick: Decimal,
+    },
+    #[error("size {size} for {symbol} overflows 64-bit lots of {lot}")]
+    SizeOverflow {
+        symbol: String,
+        size: String,
+        lot: Decimal,
+    },
+    #[error("scale {scale} for {symbol} is past {MAX_SCALE} or overflows its tick")]
+    ScaleTooLarge { symbol: String, scale: u32 },
     #[error("tick config parse failed: {0}")]
     ConfigParse(String),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct TickConfigFile {
     pub default_tick: String,
     #[serde(default)]
     pub symbols: HashMap<String, String>,
+    /// Quantity one unit of `size` stands for, like a tick for prices.
+    /// Defaults to 1, i.e. whole units.
+    #[serde(default)]
+    pub default_lot: Option<String>,
+    #[serde(default)]
+    pub lots: HashMap<String, String>,
+    /// Powers of ten a symbol's stored prices are coarsened by: at 2, one
+    /// stored unit is 100 ticks. Keeps prices far from zero on fine ticks
+    /// within 64 bits. Defaults to 0.
+    #[serde(default)]
+    pub scales: HashMap<String, u32>,
 }
 
+/// Largest `scales` entry; 10^18 still fits a `u64`.
+pub const MAX_SCALE: u32 = 18;
+
 #[derive(Debug, Clone)]
 pub struct TickTable {
     default_tick: Decimal,
     symbols: HashMap<String, Decimal>,
+    default_lot: Decimal,
+    lots: HashMap<String, Decimal>,
+    scales: HashMap<String, u32>,
+    /// Tick times ten to the scale, for scaled symbols.
+    units: HashMap<String, Decimal>,
 }
 
 impl TickTable {
     pub fn from_config(config: TickConfigFile) -> Result<Self, TickError> {
-        let default_tick = parse_positive_decimal(&config.default_tick)?;
+        let tick = |raw: &str| parse_positive_decimal(raw, TickError::NonPositiveTick);
+        let lot = |raw: &str| parse_positive_decimal(raw, TickError::NonPositiveLot);
+        let default_tick = tick(&config.default_tick)?;
         let mut symbols = HashMap::with_capacity(config.symbols.len());
         for (sym, raw_tick) in config.symbols {
-            symbols.insert(sym, parse_positive_decimal(&raw_tick)?);
+            symbols.insert(sym, tick(&raw_tick)?);
         }
-        Ok(Self {
+        let default_lot = config
+            .default_lot
+            .as_deref()
+            .map_or(Ok(Decimal::ONE), lot)?;
+        let mut lots = HashMap::with_capacity(config.lots.len());
+        for (sym, raw_lot) in config.lots {
+            lots.insert(sym, lot(&raw_lot)?);
+        }
+        let mut table = Self {
             default_tick,
             symbols,
-        })
+            default_lot,
+            lots,
+            scales: HashMap::new(),
+            units: HashMap::new(),
+        };
+        for (sym, scale) in config.scales {
+            table = table.with_scale(&sym, scale)?;
+        }
+        Ok(table)
     }
 
     pub fn from_toml_str(raw: &str) -> Result<Self, TickError> {
@@ -56,9 +117,55 @@ impl TickTable {
         Ok(Self {
             default_tick: tick_size,
             symbols: HashMap::new(),
+            default_lot: Decimal::ONE,
+            lots: HashMap::new(),
+            scales: HashMap::new(),
+            units: HashMap::new(),
         })
     }
 
+    /// Sets the tick size of `symbol`, keeping its scale.
+    pub fn with_tick(mut self, symbol: &str, tick: Decimal) -> Result<Self, TickError> {
+        if tick <= Decimal::ZERO {
+            return Err(TickError::NonPositiveTick);
+        }
+        self.symbols.insert(symbol.to_string(), tick);
+        let scale = self.scale_for(symbol);
+        self.with_scale(symbol, scale)
+    }
+
+    /// Stores `symbol`'s prices in units of `10^scale` ticks.
+    pub fn with_scale(mut self, symbol: &str, scale: u32) -> Result<Self, TickError> {
+        let too_large = || TickError::ScaleTooLarge {
+            symbol: symbol.to_string(),
+            scale,
+        };
+        if scale > MAX_SCALE {
+            return Err(too_large());
+        }
+        if scale == 0 {
+            self.scales.remove(symbol);
+            self.units.remove(symbol);
+            return Ok(self);
+        }
+        let unit = self
+            .tick_for(symbol)
+            .checked_mul(Decimal::from(10u64.pow(scale)))
+            .ok_or_else(too_large)?;
+        self.scales.insert(symbol.to_string(), scale);
+        self.units.insert(symbol.to_string(), unit);
+        Ok(self)
+    }
+
+    /// Sets the lot size of `symbol`.
+    pub fn with_lot(mut self, symbol: &str, lot: Decimal) -> Result<Self, TickError> {
+        if lot <= Decimal::ZERO {
+            return Err(TickError::NonPositiveLot);
+        }
+        self.lots.insert(symbol.to_string(), lot);
+        Ok(self)
+    }
+
     pub fn tick_for(&self, symbol: &str) -> Decimal {
         self.symbols
             .get(symbol)
@@ -66,30 +173,95 @@ impl TickTable {
             .unwrap_or(self.default_tick)
     }
 
+    pub fn scale_for(&self, symbol: &str) -> u32 {
+        self.scales.get(symbol).copied().unwrap_or(0)
+    }
+
+    /// The price one stored tick of `symbol` stands for: its tick, times
+    /// ten to its scale.
+    pub fn price_unit(&self, symbol: &str) -> Decimal {
+        self.units
+            .get(symbol)
+            .copied()
+            .unwrap_or_else(|| self.tick_for(symbol))
+    }
+
+    pub fn lot_for(&self, symbol: &str) -> Decimal {
+        self.lots.get(symbol).copied().unwrap_or(self.default_lot)
+    }
+
+    /// Whole lots in a decimal quantity, rounded half away from zero.
+    pub fn quantity_str_to_lots(&self, symbol: &str, quantity: &str) -> Result<i64, TickError> {
+        let qty =
+            Decimal::from_str(quantity).map_err(|_| TickError::InvalidDecimal(quantity.into()))?;
+        self.quantity_to_lots(symbol, qty)
+    }
+
+    pub fn quantity_to_lots(&self, symbol: &str, quantity: Decimal) -> Result<i64, TickError> {
+        let lot = self.lot_for(symbol);
+        quantity
+            .checked_div(lot)
+            .map(|r| r.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero))
+            .and_then(|r| r.to_i64())
+            .ok_or_else(|| TickError::SizeOverflow {
+                symbol: symbol.to_string(),
+                size: quantity.to_string(),
+                lot,
+            })
+    }
+
+    pub fn lots_to_quantity(&self, symbol: &str, lots: i64) -> Result<Decimal, TickError> {
+        let lot = self.lot_for(symbol);
+        Decimal::from(lots)
+            .checked_mul(lot)
+            .ok_or_else(|| TickError::SizeOverflow {
+                symbol: symbol.to_string(),
+                size: format!("{lots} lots"),
+                lot,
+            })
+    }
+
     pub fn price_str_to_ticks(&self, symbol: &str, price: &str) -> Result<i64, TickError> {
         let px = Decimal::from_str(price).map_err(|_| TickError::InvalidDecimal(price.into()))?;
         self.price_to_ticks(symbol, px)
     }
 
+    /// Stored ticks of `price`, in units of [`price_unit`](Self::price_unit)
+    /// and rounded half away from zero.
     pub fn price_to_ticks(&self, symbol: &str, price: Decimal) -> Result<i64, TickError> {
-        let tick = self.tick_for(symbol);
+        let tick = self.price_unit(symbol);
         if tick <= Decimal::ZERO {
             return Err(TickError::NonPositiveTick);
         }
-        let ratio = price / tick;
-        let rounded = ratio.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero);
-        rounded.to_i64().ok_or(TickError::Overflow)
+        // `Decimal` division panics past its 96-bit mantissa.
+        price
+            .checked_div(tick)
+            .map(|r| r.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero))
+            .and_then(|r| r.to_i64())
+            .ok_or_else(|| TickError::Overflow {
+                symbol: symbol.to_string(),
+                price,
+                tick,
+            })
     }
 
-    pub fn ticks_to_price(&self, symbol: &str, ticks: i64) -> Decimal {
-        Decimal::from(ticks) * self.tick_for(symbol)
+    /// Exact `Decimal` price of a stored tick count.
+    pub fn ticks_to_price(&self, symbol: &str, ticks: i64) -> Result<Decimal, TickError> {
+        let tick = self.price_unit(symbol);
+        Decimal::from(ticks)
+            .checked_mul(tick)
+            .ok_or_else(|| TickError::PriceOverflow {
+                symbol: symbol.to_string(),
+                ticks,
+                tick,
+            })
     }
 }
 
-fn parse_positive_decimal(input: &str) -> Result<Decimal, TickError> {
+fn parse_positive_decimal(input: &str, non_positive: TickError) -> Result<Decimal, TickError> {
     let v = Decimal::from_str(input).map_err(|_| TickError::InvalidDecimal(input.into()))?;
     if v <= Decimal::ZERO {
-        return Err(TickError::NonPositiveTick);
+        return Err(non_positive);
     }
     Ok(v)
 }
@@ -122,6 +294,7 @@ mod tests {
         let cfg = TickConfigFile {
             default_tick: "0.01".into(),
             symbols: HashMap::from([(String::from("MSFT"), String::from("0.05"))]),
+            ..TickConfigFile::default()
         };
         let table = TickTable::from_config(cfg).expect("tick table");
         assert_eq!(
@@ -133,4 +306,143 @@ mod tests {
             2000
         );
     }
+
+    #[test]
+    fn overflow_is_reported_not_wrapped() {
+        let cfg = TickConfigFile {
+            default_tick: "0.01".into(),
+            symbols: HashMap::from([(
+                String::from("SHIB-ETH"),
+                String::from("0.000000000000000001"),
+            )]),
+            ..TickConfigFile::default()
+        };
+        let table = TickTable::from_config(cfg).expect("tick table");
+        // 12.5 ETH in wei-sized ticks is past i64::MAX.
+        let err = table
+            .price_str_to_ticks("SHIB-ETH", "12.5")
+            .expect_err("overflow");
+        assert!(
+            matches!(&err, TickError::Overflow { symbol, .. } if symbol == "SHIB-ETH"),
+            "{err}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "price 12.5 for SHIB-ETH overflows 64-bit ticks of 0.000000000000000001"
+        );
+        assert_eq!(
+            table
+                .price_str_to_ticks("SHIB-ETH", "0.0000125")
+                .expect("ticks"),
+            12_500_000_000_000
+        );
+
+        // Past Decimal's range the division fails instead of panicking.
+        assert!(matches!(
+            table.price_str_to_ticks("SHIB-ETH", "79228162514264337593543950335"),
+            Err(TickError::Overflow { .. })
+        ));
+        assert!(matches!(
+            TickTable::uniform(Decimal::MAX)
+                .expect("tick table")
+                .ticks_to_price("X", 2),
+            Err(TickError::PriceOverflow { ticks: 2, .. })
+        ));
+        assert_eq!(
+            table.ticks_to_price("AAPL", i64::MIN).expect("price"),
+            Decimal::from(i64::MIN) / Decimal::ONE_HUNDRED
+        );
+    }
+
+    #[test]
+    fn lots_scale_fractional_sizes() {
+        let table =
+            TickTable::from_toml_str("default_tick = \"0.01\"\n[lots]\nBTCUSDT = \"0.00001\"\n")
+                .expect("tick table");
+        assert_eq!(
+            table
+                .quantity_str_to_lots("BTCUSDT", "0.01234567")
+                .expect("lots"),
+            1_235
+        );
+        assert_eq!(
+            table.lots_to_quantity("BTCUSDT", 1_235).expect("qty"),
+            Decimal::new(1_235, 5)
+        );
+        // Symbols without a lot keep whole units.
+        assert_eq!(table.quantity_str_to_lots("AAPL", "2.5").expect("lots"), 3);
+        assert!(matches!(
+            TickTable::from_toml_str("default_tick = \"0.01\"\ndefault_lot = \"0\"\n"),
+            Err(TickError::NonPositiveLot)
+        ));
+        let err = table
+            .quantity_str_to_lots("BTCUSDT", "1000000000000000")
+            .expect_err("overflow");
+        assert!(matches!(err, TickError::SizeOverflow { .. }), "{err}");
+    }
+
+    #[test]
+    fn scales_fit_wide_prices_on_fine_ticks() {
+        let table = TickTable::from_toml_str(
+            "default_tick = \"0.01\"\n[symbols]\nBTCUSDT = \"0.00000001\"\nIDX = \"0.00000001\"\n[scales]\nIDX = 2\n",
+        )
+        .expect("tick table");
+        assert_eq!(table.scale_for("BTCUSDT"), 0);
+        assert_eq!(table.scale_for("IDX"), 2);
+        assert_eq!(table.price_unit("IDX"), Decimal::new(1, 6));
+
+        // Realistic prices at 1e-8 ticks round-trip exactly.
+        for price in [
+            "67321.12345678",
+            "0.00000001",
+            "-42.5",
+            "92233720368.54775807",
+        ] {
+            let ticks = table.price_str_to_ticks("BTCUSDT", price).expect("ticks");
+            let back = table.ticks_to_price("BTCUSDT", ticks).expect("price");
+            assert_eq!(back, price.parse::<Decimal>().expect("decimal"));
+        }
+        assert_eq!(
+            table
+                .price_str_to_ticks("BTCUSDT", "67321.12345678")
+                .expect("ticks"),
+            6_732_112_345_678
+        );
+        let top = table.ticks_to_price("BTCUSDT", i64::MAX).expect("price");
+        assert_eq!(
+            table.price_to_ticks("BTCUSDT", top).expect("ticks"),
+            i64::MAX
+        );
+
+        // Past 64 bits of 1e-8 ticks unscaled, the error is explicit...
+        let wide = "123456789012.34567891";
+        let err = table
+            .price_str_to_ticks("BTCUSDT", wide)
+            .expect_err("overflow");
+        assert_eq!(
+            err.to_string(),
+            "price 123456789012.34567891 for BTCUSDT overflows 64-bit ticks of 0.00000001"
+        );
+        // ...and a scale fits it, rounded to the coarser unit, which then
+        // round-trips.
+        let ticks = table.price_str_to_ticks("IDX", wide).expect("ticks");
+        assert_eq!(ticks, 123_456_789_012_345_679);
+        let back = table.ticks_to_price("IDX", ticks).expect("price");
+        assert_eq!(back, Decimal::new(123_456_789_012_345_679, 6));
+        assert_eq!(table.price_to_ticks("IDX", back).expect("ticks"), ticks);
+
+        let built = TickTable::uniform(Decimal::new(1, 2))
+            .and_then(|t| t.with_scale("IDX", 2))
+            .and_then(|t| t.with_tick("IDX", Decimal::new(1, 8)))
+            .expect("tick table");
+        assert_eq!(built.price_unit("IDX"), Decimal::new(1, 6));
+        assert!(matches!(
+            table.clone().with_scale("IDX", MAX_SCALE + 1),
+            Err(TickError::ScaleTooLarge { scale: 19, .. })
+        ));
+        assert!(matches!(
+            TickTable::uniform(Decimal::MAX).and_then(|t| t.with_scale("X", 1)),
+            Err(TickError::ScaleTooLarge { .. })
+        ));
+    }
 }
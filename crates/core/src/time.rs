@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TimestampError {
+    #[error("timestamp out of range: {0}")]
+    OutOfRange(String),
+    #[error("invalid timestamp: {0}")]
+    Parse(String),
+}
+
+/// Signed nanoseconds since the Unix epoch, covering 1677-09-21 to
+/// 2262-04-11. Serializes as a bare `i64`, which is byte-identical to the
+/// old `u64` field for every non-negative value.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const UNIX_EPOCH: Timestamp = Timestamp(0);
+    pub const MIN: Timestamp = Timestamp(i64::MIN);
+    pub const MAX: Timestamp = Timestamp(i64::MAX);
+
+    pub const fn from_nanos(ns: i64) -> Self {
+        Self(ns)
+    }
+
+    pub fn from_micros(us: i64) -> Result<Self, TimestampError> {
+        scale(us, 1_000, "us")
+    }
+
+    pub fn from_millis(ms: i64) -> Result<Self, TimestampError> {
+        scale(ms, 1_000_000, "ms")
+    }
+
+    pub fn from_secs(secs: i64) -> Result<Self, TimestampError> {
+        scale(secs, 1_000_000_000, "s")
+    }
+
+    /// Interprets an unsigned nanosecond count, as used on legacy wire
+    /// formats, failing past 2262.
+    pub fn from_unsigned_nanos(ns: u64) -> Result<Self, TimestampError> {
+        i64::try_from(ns)
+            .map(Self)
+            .map_err(|_| TimestampError::OutOfRange(format!("{ns}ns")))
+    }
+
+    pub fn from_rfc3339(raw: &str) -> Result<Self, TimestampError> {
+        let dt = DateTime::parse_from_rfc3339(raw)
+            .map_err(|e| TimestampError::Parse(format!("{raw}: {e}")))?;
+        dt.timestamp_nanos_opt()
+            .map(Self)
+            .ok_or_else(|| TimestampError::OutOfRange(raw.to_string()))
+    }
+
+    pub const fn as_nanos(self) -> i64 {
+        self.0
+    }
+
+    /// Wall-clock time, saturating at the representable range.
+    pub fn now() -> Self {
+        Self(Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX))
+    }
+
+    pub fn to_utc(self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.0)
+    }
+
+    /// `None` for pre-1970 timestamps.
+    pub fn to_unsigned_nanos(self) -> Option<u64> {
+        u64::try_from(self.0).ok()
+    }
+
+    /// Signed distance `self - earlier`, saturating at the `i64` bounds.
+    pub fn nanos_since(self, earlier: Timestamp) -> i64 {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    pub fn checked_add_nanos(self, ns: i64) -> Option<Self> {
+        self.0.checked_add(ns).map(Self)
+    }
+
+    pub fn saturating_add_nanos(self, ns: i64) -> Self {
+        Self(self.0.saturating_add(ns))
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(ns: i64) -> Self {
+        Self(ns)
+    }
+}
+
+impl From<Timestamp> for i64 {
+    fn from(ts: Timestamp) -> Self {
+        ts.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Accepts integer nanoseconds (possibly negative) or RFC3339.
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        match raw.parse::<i64>() {
+            Ok(ns) => Ok(Self(ns)),
+            Err(_) => Self::from_rfc3339(raw),
+        }
+    }
+}
+
+fn scale(value: i64, factor: i64, unit: &str) -> Result<Timestamp, TimestampError> {
+    value
+        .checked_mul(factor)
+        .map(Timestamp)
+        .ok_or_else(|| TimestampError::OutOfRange(format!("{value}{unit}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pre_epoch_and_units() {
+        let ts = Timestamp::from_rfc3339("1969-12-31T23:59:59Z").expect("rfc3339");
+        assert_eq!(ts.as_nanos(), -1_000_000_000);
+        assert_eq!(ts.to_unsigned_nanos(), None);
+        assert_eq!(
+            Timestamp::from_millis(-1).expect("ms").as_nanos(),
+            -1_000_000
+        );
+        assert_eq!(
+            Timestamp::from_secs(1).expect("s").nanos_since(ts),
+            2_000_000_000
+        );
+        assert!(Timestamp::from_secs(i64::MAX).is_err());
+        assert!(Timestamp::from_unsigned_nanos(u64::MAX).is_err());
+        assert_eq!("-5".parse::<Timestamp>().expect("ns").as_nanos(), -5);
+        assert_eq!(
+            "1970-01-01T00:00:01Z".parse::<Timestamp>(),
+            Timestamp::from_secs(1)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::event::Event;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// ISO 10383 MICs known out of the box, with common free-form aliases.
+const BUILTIN: &[(&str, &[&str])] = &[
+    ("XNAS", &["NASDAQ", "NSDQ", "NASDAQ GS"]),
+    ("XNYS", &["NYSE"]),
+    ("ARCX", &["NYSE ARCA", "ARCA"]),
+    ("XASE", &["NYSE AMERICAN", "AMEX"]),
+    ("XCIS", &["NYSE NATIONAL"]),
+    ("XCHI", &["NYSE CHICAGO", "CHX"]),
+    ("BATS", &["CBOE BZX", "BZX"]),
+    ("BATY", &["CBOE BYX", "BYX"]),
+    ("EDGX", &["CBOE EDGX"]),
+    ("EDGA", &["CBOE EDGA"]),
+    ("IEXG", &["IEX"]),
+    ("MEMX", &["MEMBERS EXCHANGE"]),
+    ("EPRL", &["MIAX PEARL"]),
+    ("XCME", &["CME"]),
+    ("XCBT", &["CBOT"]),
+    ("XNYM", &["NYMEX"]),
+    ("XCEC", &["COMEX"]),
+    ("XEUR", &["EUREX"]),
+    ("XLON", &["LSE", "LONDON"]),
+    ("XPAR", &["EURONEXT PARIS"]),
+    ("XAMS", &["EURONEXT AMSTERDAM"]),
+    ("XETR", &["XETRA"]),
+    ("XSWX", &["SIX"]),
+    ("XTKS", &["TSE", "TOKYO"]),
+    ("XHKG", &["HKEX"]),
+    ("XASX", &["ASX"]),
+    ("XTSE", &["TSX"]),
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VenueError {
+    #[error("unknown venue {0:?}")]
+    Unknown(String),
+    #[error("invalid MIC {0:?}")]
+    InvalidMic(String),
+    #[error("venue config parse failed: {0}")]
+    ConfigParse(String),
+}
+
+/// Extra MICs and aliases layered on top of the builtin table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VenueConfigFile {
+    #[serde(default)]
+    pub mics: Vec<String>,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VenueRegistry {
+    mics: BTreeSet<String>,
+    aliases: BTreeMap<String, String>,
+}
+
+impl Default for VenueRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            mics: BTreeSet::new(),
+            aliases: BTreeMap::new(),
+        };
+        for (mic, aliases) in BUILTIN {
+            registry.mics.insert((*mic).to_string());
+            for alias in *aliases {
+                registry
+                    .aliases
+                    .insert(alias_key(alias), (*mic).to_string());
+            }
+        }
+        registry
+    }
+}
+
+impl VenueRegistry {
+    pub fn from_config(config: VenueConfigFile) -> Result<Self, VenueError> {
+        let mut registry = Self::default();
+        for mic in config.mics {
+            registry.mics.insert(validate_mic(&mic)?);
+        }
+        for (alias, mic) in config.aliases {
+            let mic = validate_mic(&mic)?;
+            if !registry.mics.contains(&mic) {
+                return Err(VenueError::Unknown(mic));
+            }
+            registry.aliases.insert(alias_key(&alias), mic);
+        }
+        Ok(registry)
+    }
+
+    pub fn from_toml_str(raw: &str) -> Result<Self, VenueError> {
+        let parsed: VenueConfigFile =
+            toml::from_str(raw).map_err(|e| VenueError::ConfigParse(e.to_string()))?;
+        Self::from_config(parsed)
+    }
+
+    /// Maps a MIC or alias (case- and whitespace-insensitive) to its MIC.
+    pub fn resolve(&self, raw: &str) -> Option<&str> {
+        let key = alias_key(raw);
+        if let Some(mic) = self.mics.get(&key) {
+            return Some(mic);
+        }
+        self.aliases.get(&key).map(String::as_str)
+    }
+
+    /// Rewrites every event venue to its MIC. Unresolvable venues are left
+    /// untouched and returned, deduplicated, for the caller to warn or fail.
+    pub fn normalize_events(&self, events: &mut [Event]) -> Vec<String> {
+        let mut unknown = BTreeSet::new();
+        let mut cache = BTreeMap::<String, Option<String>>::new();
+        for event in events.iter_mut() {
+            let resolved = cache
+                .entry(event.venue.clone())
+                .or_insert_with(|| self.resolve(&event.venue).map(str::to_string));
+            match resolved {
+                Some(mic) => {
+                    if event.venue != *mic {
+                        event.venue.clone_from(mic);
+                    }
+                }
+                None => {
+                    unknown.insert(event.venue.clone());
+                }
+            }
+        }
+        unknown.into_iter().collect()
+    }
+}
+
+fn alias_key(raw: &str) -> String {
+    raw.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_uppercase()
+}
+
+fn validate_mic(raw: &str) -> Result<String, VenueError> {
+    let mic = raw.trim().to_ascii_uppercase();
+    if mic.len() == 4 && mic.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        Ok(mic)
+    } else {
+        Err(VenueError::InvalidMic(raw.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_collapse_to_one_mic() {
+        let registry = VenueRegistry::default();
+        assert_eq!(registry.resolve("XNAS"), Some("XNAS"));
+        assert_eq!(registry.resolve("nasdaq"), Some("XNAS"));
+        assert_eq!(registry.resolve(" nsdq "), Some("XNAS"));
+        assert_eq!(registry.resolve("nyse  arca"), Some("ARCX"));
+        assert_eq!(registry.resolve("somewhere"), None);
+
+        let mut events = vec![
+            Event::trade(1, 1, "nsdq", "AAPL", 1, 1),
+            Event::trade(2, 2, "XNAS", "AAPL", 1, 1),
+            Event::trade(3, 3, "mystery", "AAPL", 1, 1),
+        ];
+        let unknown = registry.normalize_events(&mut events);
+        assert_eq!(events[0].venue, "XNAS");
+        assert_eq!(events[1].venue, "XNAS");
+        assert_eq!(unknown, vec![String::from("mystery")]);
+    }
+
+    #[test]
+    fn config_adds_mics_and_aliases() {
+        let registry = VenueRegistry::from_toml_str(
+            "mics = [\"xoff\"]\n[aliases]\n\"dark pool\" = \"XOFF\"\nlocal = \"XNAS\"\n",
+        )
+        .expect("config");
+        assert_eq!(registry.resolve("Dark Pool"), Some("XOFF"));
+        assert_eq!(registry.resolve("LOCAL"), Some("XNAS"));
+        assert!(VenueRegistry::from_toml_str("[aliases]\nx = \"ZZZZ\"\n").is_err());
+        assert!(VenueRegistry::from_toml_str("mics = [\"TOOLONG\"]\n").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::script::Derived;
+use crate::ExportError;
+use arrow::array::{ArrayRef, Float64Builder, Int64Builder, StringBuilder, UInt64Builder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use md_core::{Event, OrderEvent, Payload};
+use md_storage::EventLogReader;
+use std::sync::Arc;
+
+/// One row per event; trade and quote columns are null for the other kind.
+pub fn event_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp_ns", DataType::Int64, false),
+        Field::new("sequence", DataType::UInt64, false),
+        Field::new("venue", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("price_ticks", DataType::Int64, true),
+        Field::new("size", DataType::Int64, true),
+        Field::new("bid_px", DataType::Int64, true),
+        Field::new("bid_sz", DataType::Int64, true),
+        Field::new("ask_px", DataType::Int64, true),
+        Field::new("ask_sz", DataType::Int64, true),
+    ]))
+}
+
+pub fn events_to_arrow(events: &[Event]) -> Result<RecordBatch, ExportError> {
+    let n = events.len();
+    let mut timestamp_ns = Int64Builder::with_capacity(n);
+    let mut sequence = UInt64Builder::with_capacity(n);
+    let mut venue = StringBuilder::new();
+    let mut symbol = StringBuilder::new();
+    let mut kind = StringBuilder::new();
+    let mut price_ticks = Int64Builder::with_capacity(n);
+    let mut size = Int64Builder::with_capacity(n);
+    let mut bid_px = Int64Builder::with_capacity(n);
+    let mut bid_sz = Int64Builder::with_capacity(n);
+    let mut ask_px = Int64Builder::with_capacity(n);
+    let mut ask_sz = Int64Builder::with_capacity(n);
+
+    for event in events {
+        timestamp_ns.append_value(event.timestamp_ns.as_nanos());
+        sequence.append_value(event.sequence);
+        venue.append_value(&event.venue);
+        symbol.append_value(&event.symbol);
+        match &event.payload {
+            Payload::Trade {
+                price_ticks: px,
+                size: sz,
+            } => {
+                kind.append_value("trade");
+                price_ticks.append_value(*px);
+                size.append_value(*sz);
+                bid_px.append_null();
+                bid_sz.append_null();
+                ask_px.append_null();
+                ask_sz.append_null();
+            }
+            Payload::Quote {
+                bid_px: bp,
+                bid_sz: bs,
+                ask_px: ap,
+                ask_sz: asz,
+            } => {
+                kind.append_value("quote");
+                price_ticks.append_null();
+                size.append_null();
+                bid_px.append_value(*bp);
+                bid_sz.append_value(*bs);
+                ask_px.append_value(*ap);
+                ask_sz.append_value(*asz);
+            }
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(timestamp_ns.finish()),
+        Arc::new(sequence.finish()),
+        Arc::new(venue.finish()),
+        Arc::new(symbol.finish()),
+        Arc::new(kind.finish()),
+        Arc::new(price_ticks.finish()),
+        Arc::new(size.finish()),
+        Arc::new(bid_px.finish()),
+        Arc::new(bid_sz.finish()),
+        Arc::new(ask_px.finish()),
+        Arc::new(ask_sz.finish()),
+    ];
+    Ok(RecordBatch::try_new(event_schema(), columns)?)
+}
+
+/// Event columns, then one nullable float column per derived name.
+pub fn event_schema_with(names: &[String]) -> SchemaRef {
+    let mut fields = event_schema()
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone())
+        .collect::<Vec<_>>();
+    fields.extend(
+        names
+            .iter()
+            .map(|name| Field::new(name, DataType::Float64, true)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+/// `derived.rows[i]` belongs to `events[i]`.
+pub fn events_to_arrow_with(
+    events: &[Event],
+    derived: &Derived,
+) -> Result<RecordBatch, ExportError> {
+    derived_to_arrow(events, &derived.names, &derived.rows)
+}
+
+/// Like [`events_to_arrow_with`] for a chunk of events and their rows.
+pub(crate) fn derived_to_arrow(
+    events: &[Event],
+    names: &[String],
+    rows: &[Vec<Option<f64>>],
+) -> Result<RecordBatch, ExportError> {
+    if names.is_empty() {
+        return events_to_arrow(events);
+    }
+    if rows.len() != events.len() {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "{} events but {} derived rows",
+            events.len(),
+            rows.len()
+        ))
+        .into());
+    }
+    let mut columns = events_to_arrow(events)?.columns().to_vec();
+    for i in 0..names.len() {
+        let mut column = Float64Builder::with_capacity(rows.len());
+        for row in rows {
+            column.append_option(row.get(i).copied().flatten());
+        }
+        columns.push(Arc::new(column.finish()));
+    }
+    Ok(RecordBatch::try_new(event_schema_with(names), columns)?)
+}
+
+/// One row per order-gateway record.
+pub fn order_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp_ns", DataType::Int64, false),
+        Field::new("sequence", DataType::UInt64, false),
+        Field::new("venue", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("action", DataType::Utf8, false),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("price_ticks", DataType::Int64, true),
+        Field::new("size", DataType::Int64, false),
+    ]))
+}
+
+pub fn orders_to_arrow(orders: &[OrderEvent]) -> Result<RecordBatch, ExportError> {
+    let n = orders.len();
+    let mut timestamp_ns = Int64Builder::with_capacity(n);
+    let mut sequence = UInt64Builder::with_capacity(n);
+    let mut venue = StringBuilder::new();
+    let mut symbol = StringBuilder::new();
+    let mut order_id = StringBuilder::new();
+    let mut action = StringBuilder::new();
+    let mut side = StringBuilder::new();
+    let mut price_ticks = Int64Builder::with_capacity(n);
+    let mut size = Int64Builder::with_capacity(n);
+
+    for order in orders {
+        timestamp_ns.append_value(order.timestamp_ns.as_nanos());
+        sequence.append_value(order.sequence);
+        venue.append_value(&order.venue);
+        symbol.append_value(&order.symbol);
+        order_id.append_value(&order.order_id);
+        action.append_value(order.action.as_str());
+        side.append_value(order.side.as_str());
+        price_ticks.append_option(order.price_ticks);
+        size.append_value(order.size);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(timestamp_ns.finish()),
+        Arc::new(sequence.finish()),
+        Arc::new(venue.finish()),
+        Arc::new(symbol.finish()),
+        Arc::new(order_id.finish()),
+        Arc::new(action.finish()),
+        Arc::new(side.finish()),
+        Arc::new(price_ticks.finish()),
+        Arc::new(size.finish()),
+    ];
+    Ok(RecordBatch::try_new(order_schema(), columns)?)
+}
+
+/// Converts a log into record batches of at most `batch_size` rows without
+/// holding the whole log in memory.
+pub struct LogBatches {
+    reader: EventLogReader,
+    batch_size: usize,
+    done: bool,
+}
+
+impl LogBatches {
+    pub fn new(mut reader: EventLogReader, batch_size: usize) -> Result<Self, ExportError> {
+        reader.rewind_to_data()?;
+        Ok(Self {
+            reader,
+            batch_size: batch_size.max(1),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for LogBatches {
+    type Item = Result<RecordBatch, ExportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut events = Vec::with_capacity(self.batch_size);
+        while events.len() < self.batch_size {
+            match self.reader.next_record() {
+                Ok(Some(record)) => events.push(record.event),
+                Ok(None) => {
+                    self.done = true;
+                    break;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+        if events.is_empty() {
+            None
+        } else {
+            Some(events_to_arrow(&events))
+        }
+    }
+}
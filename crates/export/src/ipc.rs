@@ -0,0 +1,96 @@
+use crate::arrow::{derived_to_arrow, event_schema_with};
+use crate::script::Derived;
+use crate::ExportError;
+use arrow::ipc::writer::{FileWriter, StreamWriter};
+use arrow::record_batch::RecordBatch;
+use md_core::Event;
+use std::io::Write;
+
+pub(crate) const ROWS_PER_BATCH: usize = 64 * 1024;
+
+/// Arrow IPC file format, also readable as Feather v2.
+pub fn write_arrow_file<W: Write>(events: &[Event], out: W) -> Result<(), ExportError> {
+    write_arrow_file_with(events, &Derived::default(), out)
+}
+
+/// Arrow IPC stream format, suitable for pipes.
+pub fn write_arrow_stream<W: Write>(events: &[Event], out: W) -> Result<(), ExportError> {
+    write_arrow_stream_with(events, &Derived::default(), out)
+}
+
+pub fn write_arrow_file_with<W: Write>(
+    events: &[Event],
+    derived: &Derived,
+    out: W,
+) -> Result<(), ExportError> {
+    let mut writer = FileWriter::try_new(out, &event_schema_with(&derived.names))?;
+    for start in (0..events.len()).step_by(ROWS_PER_BATCH) {
+        writer.write(&chunk(events, derived, start)?)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+pub fn write_arrow_stream_with<W: Write>(
+    events: &[Event],
+    derived: &Derived,
+    out: W,
+) -> Result<(), ExportError> {
+    let mut writer = StreamWriter::try_new(out, &event_schema_with(&derived.names))?;
+    for start in (0..events.len()).step_by(ROWS_PER_BATCH) {
+        writer.write(&chunk(events, derived, start)?)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Up to `ROWS_PER_BATCH` rows from `start`.
+pub(crate) fn chunk(
+    events: &[Event],
+    derived: &Derived,
+    start: usize,
+) -> Result<RecordBatch, ExportError> {
+    let end = (start + ROWS_PER_BATCH).min(events.len());
+    let rows = derived.rows.get(start..end).unwrap_or(&[]);
+    derived_to_arrow(&events[start..end], &derived.names, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::LogBatches;
+    use arrow::ipc::reader::FileReader;
+    use md_storage::{default_schema_hash, EventLogReader, EventLogWriter};
+    use std::io::Cursor;
+
+    #[test]
+    fn file_round_trip_matches_log_batches() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let log = dir.path().join("a.eventlog");
+        let events = (1..=5)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100 + i as i64, 1))
+            .collect::<Vec<_>>();
+        let mut writer =
+            EventLogWriter::create(&log, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        for event in &events {
+            writer.append(event).expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let batches = LogBatches::new(EventLogReader::open(&log).expect("open"), 2)
+            .expect("batches")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("batches");
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+
+        let mut buf = Vec::new();
+        write_arrow_file(&events, &mut buf).expect("write ipc");
+        let reader = FileReader::try_new(Cursor::new(buf), None).expect("ipc reader");
+        let rows: usize = reader.map(|b| b.expect("batch").num_rows()).sum();
+        assert_eq!(rows, 5);
+    }
+}
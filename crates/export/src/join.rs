@@ -0,0 +1,519 @@
+use crate::arrow::{event_schema, events_to_arrow, order_schema, orders_to_arrow};
+use crate::ExportError;
+use arrow::array::{ArrayRef, Int64Builder, StringBuilder, UInt64Builder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use md_core::{Event, OrderEvent, Payload, QuoteTicks, Timestamp};
+use md_storage::{EventLogReader, OrderLogReader, StorageError};
+use parquet::arrow::ArrowWriter;
+use std::collections::HashMap;
+use std::fs::File;
+use std::iter::Peekable;
+use std::path::Path;
+use std::sync::Arc;
+
+const ROWS_PER_BATCH: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JoinOptions {
+    /// Match only right-hand events from the same venue, not just the same
+    /// symbol.
+    pub match_venue: bool,
+    /// Leave the right-hand columns null when the newest match is older
+    /// than this.
+    pub tolerance_ns: Option<u64>,
+}
+
+/// Right-hand state of one symbol as of some point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsofState {
+    /// Newest event at or before the left event.
+    pub last: Event,
+    /// Newest quote, i.e. the top of book.
+    pub quote: Option<QuoteTicks>,
+    /// Newest trade as `(price_ticks, size)`.
+    pub trade: Option<(i64, i64)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JoinSummary {
+    pub rows: u64,
+    pub matched: u64,
+}
+
+type JoinKey = (String, Option<String>);
+type ToArrow<L> = fn(&[L], &[Option<AsofState>]) -> Result<RecordBatch, ExportError>;
+
+/// Left-hand rows: when they happened and what they are matched on.
+pub trait AsofKey {
+    /// Timestamp, venue and symbol.
+    fn asof_key(&self) -> (Timestamp, &str, &str);
+}
+
+impl AsofKey for Event {
+    fn asof_key(&self) -> (Timestamp, &str, &str) {
+        (self.timestamp_ns, &self.venue, &self.symbol)
+    }
+}
+
+impl AsofKey for OrderEvent {
+    fn asof_key(&self) -> (Timestamp, &str, &str) {
+        (self.timestamp_ns, &self.venue, &self.symbol)
+    }
+}
+
+/// Streaming as-of join. Left events must be looked up in timestamp order
+/// and `right` must yield events in timestamp order, as logs store them;
+/// right-hand events stamped at the same nanosecond as the left event
+/// count as already known.
+pub struct AsofJoin<R: Iterator> {
+    right: Peekable<R>,
+    state: HashMap<JoinKey, AsofState>,
+    options: JoinOptions,
+}
+
+impl<R, E> AsofJoin<R>
+where
+    R: Iterator<Item = Result<Event, E>>,
+{
+    pub fn new(right: R, options: JoinOptions) -> Self {
+        Self {
+            right: right.peekable(),
+            state: HashMap::new(),
+            options,
+        }
+    }
+
+    fn key(&self, venue: &str, symbol: &str) -> JoinKey {
+        let venue = self.options.match_venue.then(|| venue.to_string());
+        (symbol.to_string(), venue)
+    }
+
+    pub fn lookup(&mut self, left: &impl AsofKey) -> Result<Option<&AsofState>, E> {
+        let (timestamp_ns, venue, symbol) = left.asof_key();
+        while let Some(next) = self.right.next_if(|next| match next {
+            Ok(event) => event.timestamp_ns <= timestamp_ns,
+            Err(_) => true,
+        }) {
+            let event = next?;
+            let key = self.key(&event.venue, &event.symbol);
+            let (quote, trade) = match event.payload {
+                Payload::Quote {
+                    bid_px,
+                    bid_sz,
+                    ask_px,
+                    ask_sz,
+                } => (
+                    Some(QuoteTicks {
+                        bid_px,
+                        bid_sz,
+                        ask_px,
+                        ask_sz,
+                    }),
+                    None,
+                ),
+                Payload::Trade { price_ticks, size } => (None, Some((price_ticks, size))),
+            };
+            match self.state.get_mut(&key) {
+                Some(state) => {
+                    state.quote = quote.or(state.quote);
+                    state.trade = trade.or(state.trade);
+                    state.last = event;
+                }
+                None => {
+                    self.state.insert(
+                        key,
+                        AsofState {
+                            last: event,
+                            quote,
+                            trade,
+                        },
+                    );
+                }
+            }
+        }
+
+        let tolerance = self.options.tolerance_ns;
+        Ok(self.state.get(&self.key(venue, symbol)).filter(|state| {
+            tolerance.is_none_or(|tol| {
+                u64::try_from(timestamp_ns.nanos_since(state.last.timestamp_ns))
+                    .is_ok_and(|lag| lag <= tol)
+            })
+        }))
+    }
+}
+
+/// Matched right-hand state, prefixed `b_`. `b_lag_ns` is left minus
+/// right timestamp.
+fn asof_fields() -> [Field; 11] {
+    [
+        Field::new("b_timestamp_ns", DataType::Int64, true),
+        Field::new("b_sequence", DataType::UInt64, true),
+        Field::new("b_venue", DataType::Utf8, true),
+        Field::new("b_kind", DataType::Utf8, true),
+        Field::new("b_lag_ns", DataType::Int64, true),
+        Field::new("b_bid_px", DataType::Int64, true),
+        Field::new("b_bid_sz", DataType::Int64, true),
+        Field::new("b_ask_px", DataType::Int64, true),
+        Field::new("b_ask_sz", DataType::Int64, true),
+        Field::new("b_last_price_ticks", DataType::Int64, true),
+        Field::new("b_last_size", DataType::Int64, true),
+    ]
+}
+
+fn with_asof_fields(left: SchemaRef) -> SchemaRef {
+    let mut fields = left
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone())
+        .collect::<Vec<_>>();
+    fields.extend(asof_fields());
+    Arc::new(Schema::new(fields))
+}
+
+/// Event columns of the left log, then the matched right-hand state.
+pub fn joined_schema() -> SchemaRef {
+    with_asof_fields(event_schema())
+}
+
+/// Order columns, then the market state each order saw.
+pub fn orders_joined_schema() -> SchemaRef {
+    with_asof_fields(order_schema())
+}
+
+fn asof_columns<L: AsofKey>(
+    left: &[L],
+    right: &[Option<AsofState>],
+) -> Result<[ArrayRef; 11], ExportError> {
+    if left.len() != right.len() {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "{} left rows but {} matches",
+            left.len(),
+            right.len()
+        ))
+        .into());
+    }
+    let n = right.len();
+    let mut timestamp_ns = Int64Builder::with_capacity(n);
+    let mut sequence = UInt64Builder::with_capacity(n);
+    let mut venue = StringBuilder::new();
+    let mut kind = StringBuilder::new();
+    let mut lag_ns = Int64Builder::with_capacity(n);
+    let mut bid_px = Int64Builder::with_capacity(n);
+    let mut bid_sz = Int64Builder::with_capacity(n);
+    let mut ask_px = Int64Builder::with_capacity(n);
+    let mut ask_sz = Int64Builder::with_capacity(n);
+    let mut last_price = Int64Builder::with_capacity(n);
+    let mut last_size = Int64Builder::with_capacity(n);
+
+    for (row, state) in left.iter().zip(right) {
+        let (left_ts, _, _) = row.asof_key();
+        let last = state.as_ref().map(|s| &s.last);
+        timestamp_ns.append_option(last.map(|e| e.timestamp_ns.as_nanos()));
+        sequence.append_option(last.map(|e| e.sequence));
+        venue.append_option(last.map(|e| e.venue.as_str()));
+        kind.append_option(last.map(|e| match e.payload {
+            Payload::Trade { .. } => "trade",
+            Payload::Quote { .. } => "quote",
+        }));
+        lag_ns.append_option(last.map(|e| left_ts.nanos_since(e.timestamp_ns)));
+        let quote = state.as_ref().and_then(|s| s.quote);
+        bid_px.append_option(quote.map(|q| q.bid_px));
+        bid_sz.append_option(quote.map(|q| q.bid_sz));
+        ask_px.append_option(quote.map(|q| q.ask_px));
+        ask_sz.append_option(quote.map(|q| q.ask_sz));
+        let trade = state.as_ref().and_then(|s| s.trade);
+        last_price.append_option(trade.map(|t| t.0));
+        last_size.append_option(trade.map(|t| t.1));
+    }
+
+    Ok([
+        Arc::new(timestamp_ns.finish()),
+        Arc::new(sequence.finish()),
+        Arc::new(venue.finish()),
+        Arc::new(kind.finish()),
+        Arc::new(lag_ns.finish()),
+        Arc::new(bid_px.finish()),
+        Arc::new(bid_sz.finish()),
+        Arc::new(ask_px.finish()),
+        Arc::new(ask_sz.finish()),
+        Arc::new(last_price.finish()),
+        Arc::new(last_size.finish()),
+    ])
+}
+
+/// One row per `left` event; `right[i]` is the state matched to `left[i]`.
+pub fn joined_to_arrow(
+    left: &[Event],
+    right: &[Option<AsofState>],
+) -> Result<RecordBatch, ExportError> {
+    let matched = asof_columns(left, right)?;
+    let mut columns = events_to_arrow(left)?.columns().to_vec();
+    columns.extend(matched);
+    Ok(RecordBatch::try_new(joined_schema(), columns)?)
+}
+
+/// One row per order; `right[i]` is the state matched to `orders[i]`.
+pub fn orders_joined_to_arrow(
+    orders: &[OrderEvent],
+    right: &[Option<AsofState>],
+) -> Result<RecordBatch, ExportError> {
+    let matched = asof_columns(orders, right)?;
+    let mut columns = orders_to_arrow(orders)?.columns().to_vec();
+    columns.extend(matched);
+    Ok(RecordBatch::try_new(orders_joined_schema(), columns)?)
+}
+
+fn log_events(mut reader: EventLogReader) -> impl Iterator<Item = Result<Event, StorageError>> {
+    std::iter::from_fn(move || reader.next_record().transpose()).map(|r| r.map(|rec| rec.event))
+}
+
+/// As-of joins every event of `left` against `right` and writes the rows
+/// to Parquet. Both logs are streamed; memory holds one state per symbol
+/// of `right` plus one batch of output rows.
+pub fn join_logs(
+    left: &Path,
+    right: &Path,
+    out: &Path,
+    options: &JoinOptions,
+) -> Result<JoinSummary, ExportError> {
+    let lefts = log_events(EventLogReader::open(left)?);
+    let join = AsofJoin::new(log_events(EventLogReader::open(right)?), *options);
+    write_joined(lefts, join, out, joined_schema(), joined_to_arrow)
+}
+
+/// As-of joins every record of an order log against the market log
+/// `market`, so each order carries the book and last trade it was sent
+/// against (by client timestamp).
+pub fn join_orders(
+    orders: &Path,
+    market: &Path,
+    out: &Path,
+    options: &JoinOptions,
+) -> Result<JoinSummary, ExportError> {
+    let lefts = OrderLogReader::open(orders)?;
+    let join = AsofJoin::new(log_events(EventLogReader::open(market)?), *options);
+    write_joined(
+        lefts,
+        join,
+        out,
+        orders_joined_schema(),
+        orders_joined_to_arrow,
+    )
+}
+
+fn write_joined<L, R>(
+    mut lefts: impl Iterator<Item = Result<L, StorageError>>,
+    mut join: AsofJoin<R>,
+    out: &Path,
+    schema: SchemaRef,
+    to_arrow: ToArrow<L>,
+) -> Result<JoinSummary, ExportError>
+where
+    L: AsofKey,
+    R: Iterator<Item = Result<Event, StorageError>>,
+{
+    let mut writer = ArrowWriter::try_new(File::create(out)?, schema, None)?;
+    let mut summary = JoinSummary::default();
+
+    let mut batch = Vec::with_capacity(ROWS_PER_BATCH);
+    let mut states = Vec::with_capacity(ROWS_PER_BATCH);
+    while let Some(row) = lefts.next().transpose()? {
+        let state = join.lookup(&row)?.cloned();
+        summary.rows += 1;
+        summary.matched += u64::from(state.is_some());
+        batch.push(row);
+        states.push(state);
+        if batch.len() == ROWS_PER_BATCH {
+            writer.write(&to_arrow(&batch, &states)?)?;
+            batch.clear();
+            states.clear();
+        }
+    }
+    if !batch.is_empty() {
+        writer.write(&to_arrow(&batch, &states)?)?;
+    }
+    writer.close()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int64Array};
+    use md_core::{OrderAction, OrderSide};
+    use md_storage::{default_schema_hash, write_order_log, EventLogWriter};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn quote(ts: i64, seq: u64, venue: &str, bid_px: i64, ask_px: i64) -> Event {
+        Event::quote(
+            ts,
+            seq,
+            venue,
+            "AAPL",
+            QuoteTicks {
+                bid_px,
+                bid_sz: 1,
+                ask_px,
+                ask_sz: 1,
+            },
+        )
+    }
+
+    fn join_all(left: &[Event], right: &[Event], options: JoinOptions) -> Vec<Option<AsofState>> {
+        let mut join = AsofJoin::new(right.iter().cloned().map(Ok::<_, ()>), options);
+        left.iter()
+            .map(|e| join.lookup(e).expect("lookup").cloned())
+            .collect()
+    }
+
+    #[test]
+    fn attaches_latest_right_state_at_or_before_each_left_event() {
+        let right = vec![
+            quote(10, 1, "XNAS", 99, 101),
+            Event::trade(20, 2, "XNAS", "AAPL", 100, 5),
+            quote(30, 3, "ARCX", 100, 102),
+            Event::trade(40, 4, "XNAS", "MSFT", 300, 1),
+        ];
+        let left = vec![
+            Event::trade(5, 1, "GW", "AAPL", 100, 1),
+            Event::trade(20, 2, "GW", "AAPL", 100, 1),
+            Event::trade(35, 3, "GW", "AAPL", 101, 1),
+        ];
+
+        let rows = join_all(&left, &right, JoinOptions::default());
+        assert!(rows[0].is_none());
+        let at_20 = rows[1].as_ref().expect("match");
+        assert_eq!(at_20.last.sequence, 2);
+        assert_eq!(at_20.quote.map(|q| q.bid_px), Some(99));
+        assert_eq!(at_20.trade, Some((100, 5)));
+        let at_35 = rows[2].as_ref().expect("match");
+        assert_eq!(at_35.last.sequence, 3);
+        assert_eq!(at_35.quote.map(|q| q.ask_px), Some(102));
+        assert_eq!(at_35.trade, Some((100, 5)));
+
+        let by_venue = JoinOptions {
+            match_venue: true,
+            ..JoinOptions::default()
+        };
+        let venue_left = vec![quote(35, 1, "ARCX", 0, 0), quote(35, 2, "XNAS", 0, 0)];
+        let rows = join_all(&venue_left, &right, by_venue);
+        assert_eq!(rows[0].as_ref().map(|s| s.last.sequence), Some(3));
+        assert_eq!(rows[1].as_ref().map(|s| s.last.sequence), Some(2));
+
+        let tight = JoinOptions {
+            tolerance_ns: Some(4),
+            ..JoinOptions::default()
+        };
+        let rows = join_all(&left, &right, tight);
+        assert!(rows[1].is_some());
+        assert!(rows[2].is_none());
+    }
+
+    #[test]
+    fn join_logs_writes_parquet_rows_for_every_left_event() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let write_log = |name: &str, events: &[Event]| {
+            let path = dir.path().join(name);
+            let mut writer =
+                EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                    .expect("writer");
+            for event in events {
+                writer.append(event).expect("append");
+            }
+            writer.flush().expect("flush");
+            path
+        };
+        let left = write_log(
+            "left.eventlog",
+            &[
+                Event::trade(5, 1, "GW", "AAPL", 100, 1),
+                Event::trade(25, 2, "GW", "AAPL", 101, 1),
+            ],
+        );
+        let right = write_log("right.eventlog", &[quote(10, 1, "XNAS", 99, 101)]);
+        let out = dir.path().join("joined.parquet");
+
+        let summary = join_logs(&left, &right, &out, &JoinOptions::default()).expect("join");
+        assert_eq!(
+            summary,
+            JoinSummary {
+                rows: 2,
+                matched: 1
+            }
+        );
+
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).expect("open"))
+            .expect("reader builder")
+            .build()
+            .expect("reader")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("batches");
+        assert_eq!(batches[0].schema(), joined_schema());
+        let lag = batches[0]
+            .column_by_name("b_lag_ns")
+            .expect("lag column")
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .expect("int64");
+        assert!(lag.is_null(0));
+        assert_eq!(lag.value(1), 15);
+    }
+
+    #[test]
+    fn join_orders_attaches_the_book_each_order_saw() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let market = dir.path().join("market.eventlog");
+        let mut writer =
+            EventLogWriter::create(&market, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        writer
+            .append(&quote(10, 1, "XNAS", 99, 101))
+            .expect("append");
+        writer
+            .append(&quote(30, 2, "XNAS", 100, 102))
+            .expect("append");
+        writer.flush().expect("flush");
+
+        let order = |ts: i64, sequence: u64, action: OrderAction| OrderEvent {
+            timestamp_ns: Timestamp::from_nanos(ts),
+            sequence,
+            venue: String::from("XNAS"),
+            symbol: String::from("AAPL"),
+            order_id: String::from("o1"),
+            action,
+            side: OrderSide::Buy,
+            price_ticks: Some(101),
+            size: 10,
+        };
+        let orders = dir.path().join("orders.jsonl");
+        write_order_log(
+            &orders,
+            &[
+                order(20, 1, OrderAction::New),
+                order(35, 2, OrderAction::Fill),
+            ],
+        )
+        .expect("order log");
+        let out = dir.path().join("orders.parquet");
+
+        let summary =
+            join_orders(&orders, &market, &out, &JoinOptions::default()).expect("join orders");
+        assert_eq!(summary.matched, 2);
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).expect("open"))
+            .expect("reader builder")
+            .build()
+            .expect("reader")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("batches");
+        assert_eq!(batches[0].schema(), orders_joined_schema());
+        let ask = batches[0]
+            .column_by_name("b_ask_px")
+            .expect("ask column")
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .expect("int64");
+        assert_eq!((ask.value(0), ask.value(1)), (101, 102));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::script::Derived;
+use crate::ExportError;
+use md_core::Event;
+use serde_json::Value;
+use std::io::Write;
+
+/// Writes one serde-encoded `Event` per line, matching what `ingest-jsonl`
+/// reads back.
+pub fn write_jsonl<W: Write>(events: &[Event], mut out: W) -> Result<(), ExportError> {
+    for event in events {
+        serde_json::to_writer(&mut out, event)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Like [`write_jsonl`], with each derived column as an extra top-level
+/// key (`null` when the script returned no value).
+pub fn write_jsonl_with<W: Write>(
+    events: &[Event],
+    derived: &Derived,
+    mut out: W,
+) -> Result<(), ExportError> {
+    if derived.is_empty() {
+        return write_jsonl(events, out);
+    }
+    for (i, event) in events.iter().enumerate() {
+        let mut object = serde_json::to_value(event)?;
+        if let Value::Object(map) = &mut object {
+            for (name, value) in derived.names.iter().zip(derived.row(i)) {
+                map.insert(name.clone(), value.map_or(Value::Null, Value::from));
+            }
+        }
+        serde_json::to_writer(&mut out, &object)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_object_per_line() {
+        let events = vec![
+            Event::trade(1, 1, "X", "AAPL", 100, 2),
+            Event::trade(-5, 2, "X", "MSFT", 200, 3),
+        ];
+        let mut buf = Vec::new();
+        write_jsonl(&events, &mut buf).expect("write");
+        let text = String::from_utf8(buf).expect("utf8");
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let back: Event = serde_json::from_str(lines[1]).expect("decode");
+        assert_eq!(back, events[1]);
+    }
+}
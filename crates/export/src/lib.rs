@@ -0,0 +1,48 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+pub mod arrow;
+pub mod ipc;
+pub mod join;
+pub mod jsonl;
+pub mod parquet;
+pub mod scale;
+pub mod script;
+
+pub use crate::arrow::{
+    event_schema, event_schema_with, events_to_arrow, events_to_arrow_with, order_schema,
+    orders_to_arrow, LogBatches,
+};
+pub use crate::ipc::{
+    write_arrow_file, write_arrow_file_with, write_arrow_stream, write_arrow_stream_with,
+};
+pub use crate::join::{
+    join_logs, join_orders, joined_schema, joined_to_arrow, orders_joined_schema,
+    orders_joined_to_arrow, AsofJoin, AsofKey, AsofState, JoinOptions, JoinSummary,
+};
+pub use crate::jsonl::{write_jsonl, write_jsonl_with};
+pub use crate::parquet::{write_parquet, write_parquet_with};
+pub use crate::scale::{scaled_columns, SCALED_COLUMNS};
+pub use crate::script::{format_derived, Derived, EventScript};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("arrow error: {0}")]
+    Arrow(#[from] ::arrow::error::ArrowError),
+    #[error("parquet error: {0}")]
+    Parquet(#[from] ::parquet::errors::ParquetError),
+    #[error("storage error: {0}")]
+    Storage(#[from] md_storage::StorageError),
+    #[error("script error: {0}")]
+    Script(String),
+    #[error("tick error: {0}")]
+    Tick(#[from] md_core::TickError),
+}
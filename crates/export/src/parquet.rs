@@ -0,0 +1,99 @@
+use crate::arrow::event_schema_with;
+use crate::ipc::{chunk, ROWS_PER_BATCH};
+use crate::script::Derived;
+use crate::ExportError;
+use md_core::Event;
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::path::Path;
+
+pub fn write_parquet(events: &[Event], out: &Path) -> Result<(), ExportError> {
+    write_parquet_with(events, &Derived::default(), out)
+}
+
+pub fn write_parquet_with(
+    events: &[Event],
+    derived: &Derived,
+    out: &Path,
+) -> Result<(), ExportError> {
+    let file = File::create(out)?;
+    let mut writer = ArrowWriter::try_new(file, event_schema_with(&derived.names), None)?;
+    for start in (0..events.len()).step_by(ROWS_PER_BATCH) {
+        writer.write(&chunk(events, derived, start)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn round_trips_rows_with_nullable_columns() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("events.parquet");
+        let events = vec![
+            Event::trade(1, 1, "X", "AAPL", 100, 2),
+            Event::quote(
+                2,
+                2,
+                "X",
+                "AAPL",
+                QuoteTicks {
+                    bid_px: 99,
+                    bid_sz: 5,
+                    ask_px: 101,
+                    ask_sz: 6,
+                },
+            ),
+        ];
+        write_parquet(&events, &out).expect("write parquet");
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).expect("open"))
+            .expect("reader builder")
+            .build()
+            .expect("reader");
+        let batches = reader.collect::<Result<Vec<_>, _>>().expect("batches");
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 2);
+        let price = batches[0]
+            .column_by_name("price_ticks")
+            .expect("price column");
+        assert_eq!(price.null_count(), 1);
+    }
+
+    #[test]
+    fn derived_columns_follow_event_columns() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("derived.parquet");
+        let events = vec![
+            Event::trade(1, 1, "X", "AAPL", 100, 2),
+            Event::trade(2, 2, "X", "AAPL", 101, 3),
+        ];
+        let derived = Derived {
+            names: vec![String::from("notional")],
+            rows: vec![vec![Some(200.0)], vec![None]],
+        };
+        write_parquet_with(&events, &derived, &out).expect("write parquet");
+
+        let batch = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).expect("open"))
+            .expect("reader builder")
+            .build()
+            .expect("reader")
+            .next()
+            .expect("batch")
+            .expect("batch");
+        assert_eq!(batch.num_columns(), 12);
+        let notional = batch.column_by_name("notional").expect("derived column");
+        assert_eq!(notional.null_count(), 1);
+
+        let short = Derived {
+            rows: vec![vec![Some(1.0)]],
+            ..derived
+        };
+        assert!(write_parquet_with(&events, &short, &out).is_err());
+    }
+}
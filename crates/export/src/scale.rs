@@ -0,0 +1,100 @@
+//! Decimal prices and quantities next to the integer tick and lot columns.
+
+use crate::script::Derived;
+use crate::ExportError;
+use md_core::{Event, Payload, TickTable};
+use rust_decimal::prelude::ToPrimitive;
+
+/// Names of the columns [`scaled_columns`] adds, in order.
+pub const SCALED_COLUMNS: [&str; 6] = [
+    "price",
+    "quantity",
+    "bid",
+    "bid_quantity",
+    "ask",
+    "ask_quantity",
+];
+
+/// Prices in currency and sizes in instrument units, converted with the
+/// symbol's tick and lot sizes. Columns of the other event kind are null.
+pub fn scaled_columns(events: &[Event], ticks: &TickTable) -> Result<Derived, ExportError> {
+    let price = |symbol: &str, value: i64| -> Result<Option<f64>, ExportError> {
+        let px = ticks.ticks_to_price(symbol, value)?;
+        Ok(px.to_f64())
+    };
+    let quantity = |symbol: &str, value: i64| -> Result<Option<f64>, ExportError> {
+        let qty = ticks.lots_to_quantity(symbol, value)?;
+        Ok(qty.to_f64())
+    };
+    let mut rows = Vec::with_capacity(events.len());
+    for event in events {
+        let symbol = event.symbol.as_str();
+        rows.push(match &event.payload {
+            Payload::Trade { price_ticks, size } => vec![
+                price(symbol, *price_ticks)?,
+                quantity(symbol, *size)?,
+                None,
+                None,
+                None,
+                None,
+            ],
+            Payload::Quote {
+                bid_px,
+                bid_sz,
+                ask_px,
+                ask_sz,
+            } => vec![
+                None,
+                None,
+                price(symbol, *bid_px)?,
+                quantity(symbol, *bid_sz)?,
+                price(symbol, *ask_px)?,
+                quantity(symbol, *ask_sz)?,
+            ],
+        });
+    }
+    Ok(Derived {
+        names: SCALED_COLUMNS.iter().map(|n| n.to_string()).collect(),
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn scales_by_symbol_tick_and_lot() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2))
+            .expect("ticks")
+            .with_lot("BTCUSDT", Decimal::new(1, 8))
+            .expect("lot");
+        let events = vec![
+            Event::trade(1, 1, "X", "BTCUSDT", 3_700_012, 260_000_000),
+            Event::quote(
+                2,
+                2,
+                "X",
+                "AAPL",
+                QuoteTicks {
+                    bid_px: 10_000,
+                    bid_sz: 5,
+                    ask_px: 10_001,
+                    ask_sz: 7,
+                },
+            ),
+        ];
+        let scaled = scaled_columns(&events, &ticks).expect("scale");
+        assert_eq!(scaled.names, SCALED_COLUMNS);
+        assert_eq!(
+            scaled.rows[0],
+            vec![Some(37_000.12), Some(2.6), None, None, None, None]
+        );
+        assert_eq!(
+            scaled.rows[1],
+            vec![None, None, Some(100.0), Some(5.0), Some(100.01), Some(7.0)]
+        );
+    }
+}
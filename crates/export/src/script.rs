@@ -0,0 +1,301 @@
+//! Rhai expressions over events, for filters and derived columns.
+//!
+//! Each event is visible as variables `timestamp_ns`, `sequence`, `venue`,
+//! `symbol`, `kind` (`"trade"` or `"quote"`), `price_ticks`, `size`,
+//! `bid_px`, `bid_sz`, `ask_px` and `ask_sz`; fields of the other kind
+//! are `()`. A filter must return a bool. A derived column must return a
+//! number, or `()` for null.
+
+use crate::arrow::event_schema;
+use crate::ExportError;
+use md_core::{Event, Payload};
+use rhai::{Dynamic, Engine, Scope, AST};
+
+/// Bounds each evaluation so a runaway loop fails instead of hanging.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Columns computed per event, one row per exported event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Derived {
+    pub names: Vec<String>,
+    pub rows: Vec<Vec<Option<f64>>>,
+}
+
+impl Derived {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Row `i`, or nothing when there are no derived columns.
+    pub fn row(&self, i: usize) -> &[Option<f64>] {
+        self.rows.get(i).map_or(&[], Vec::as_slice)
+    }
+
+    /// Appends `other`'s columns; both must cover the same events.
+    pub fn extend(&mut self, other: Derived) -> Result<(), ExportError> {
+        if let Some(name) = other.names.iter().find(|n| self.names.contains(n)) {
+            return Err(script_error(format!("column {name} is defined twice")));
+        }
+        if self.is_empty() {
+            *self = other;
+            return Ok(());
+        }
+        if self.rows.len() != other.rows.len() {
+            return Err(script_error(format!(
+                "{} rows but {} to append",
+                self.rows.len(),
+                other.rows.len()
+            )));
+        }
+        self.names.extend(other.names);
+        for (row, more) in self.rows.iter_mut().zip(other.rows) {
+            row.extend(more);
+        }
+        Ok(())
+    }
+}
+
+pub struct EventScript {
+    engine: Engine,
+    filter: Option<AST>,
+    columns: Vec<(String, AST)>,
+}
+
+fn script_error(err: impl std::fmt::Display) -> ExportError {
+    ExportError::Script(err.to_string())
+}
+
+impl EventScript {
+    /// Compiles an optional filter and `name=expr` column definitions.
+    pub fn compile(filter: Option<&str>, columns: &[String]) -> Result<Self, ExportError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let filter = filter
+            .map(|raw| {
+                engine
+                    .compile(raw)
+                    .map_err(|err| script_error(format!("filter: {err}")))
+            })
+            .transpose()?;
+
+        let reserved = event_schema();
+        let mut compiled = Vec::<(String, AST)>::with_capacity(columns.len());
+        for def in columns {
+            let (name, expr) = def
+                .split_once('=')
+                .ok_or_else(|| script_error(format!("column {def}: expected name=expr")))?;
+            let name = name.trim();
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(script_error(format!(
+                    "column name {name:?} is not an identifier"
+                )));
+            }
+            if reserved.field_with_name(name).is_ok()
+                || name == "event_type"
+                || name == "payload"
+                || compiled.iter().any(|(n, _)| n == name)
+            {
+                return Err(script_error(format!("column {name} already exists")));
+            }
+            let ast = engine
+                .compile(expr)
+                .map_err(|err| script_error(format!("column {name}: {err}")))?;
+            compiled.push((name.to_string(), ast));
+        }
+
+        Ok(Self {
+            engine,
+            filter,
+            columns: compiled,
+        })
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn eval(&self, scope: &mut Scope, ast: &AST, event: &Event) -> Result<Dynamic, ExportError> {
+        self.engine
+            .eval_ast_with_scope::<Dynamic>(scope, ast)
+            .map_err(|err| script_error(format!("event {}: {err}", event.sequence)))
+    }
+
+    /// Evaluates the filter (if any) and, for kept events, every column.
+    pub fn evaluate(&self, event: &Event) -> Result<Option<Vec<Option<f64>>>, ExportError> {
+        let mut scope = event_scope(event);
+        if let Some(filter) = &self.filter {
+            let keep = self.eval(&mut scope, filter, event)?;
+            let keep = keep
+                .as_bool()
+                .map_err(|ty| script_error(format!("filter returned {ty}, expected bool")))?;
+            if !keep {
+                return Ok(None);
+            }
+        }
+        let mut row = Vec::with_capacity(self.columns.len());
+        for (name, ast) in &self.columns {
+            let value = self.eval(&mut scope, ast, event)?;
+            row.push(to_number(&value).map_err(|ty| {
+                script_error(format!("column {name} returned {ty}, expected a number"))
+            })?);
+        }
+        Ok(Some(row))
+    }
+
+    /// Keeps the events the filter accepts and derives their columns.
+    pub fn apply(&self, events: Vec<Event>) -> Result<(Vec<Event>, Derived), ExportError> {
+        let mut kept = Vec::with_capacity(events.len());
+        let mut derived = Derived {
+            names: self.column_names(),
+            rows: Vec::new(),
+        };
+        for event in events {
+            if let Some(row) = self.evaluate(&event)? {
+                if !derived.names.is_empty() {
+                    derived.rows.push(row);
+                }
+                kept.push(event);
+            }
+        }
+        Ok((kept, derived))
+    }
+}
+
+fn to_number(value: &Dynamic) -> Result<Option<f64>, &'static str> {
+    if value.is_unit() {
+        return Ok(None);
+    }
+    if let Ok(v) = value.as_int() {
+        return Ok(Some(v as f64));
+    }
+    value.as_float().map(Some)
+}
+
+fn event_scope(event: &Event) -> Scope<'static> {
+    let mut scope = Scope::new();
+    scope.push_constant("timestamp_ns", event.timestamp_ns.as_nanos());
+    scope.push_constant("sequence", event.sequence as i64);
+    scope.push_constant("venue", event.venue.clone());
+    scope.push_constant("symbol", event.symbol.clone());
+    let (kind, fields) = match event.payload {
+        Payload::Trade { price_ticks, size } => (
+            "trade",
+            [Some(price_ticks), Some(size), None, None, None, None],
+        ),
+        Payload::Quote {
+            bid_px,
+            bid_sz,
+            ask_px,
+            ask_sz,
+        } => (
+            "quote",
+            [
+                None,
+                None,
+                Some(bid_px),
+                Some(bid_sz),
+                Some(ask_px),
+                Some(ask_sz),
+            ],
+        ),
+    };
+    scope.push_constant("kind", kind);
+    let names = [
+        "price_ticks",
+        "size",
+        "bid_px",
+        "bid_sz",
+        "ask_px",
+        "ask_sz",
+    ];
+    for (name, value) in names.into_iter().zip(fields) {
+        scope.push_constant(name, value.map_or(Dynamic::UNIT, Dynamic::from));
+    }
+    scope
+}
+
+/// `name=value` pairs appended to a text line; nulls print as `null`.
+pub fn format_derived(names: &[String], row: &[Option<f64>]) -> String {
+    names
+        .iter()
+        .zip(row)
+        .map(|(name, value)| match value {
+            Some(v) => format!(" {name}={v}"),
+            None => format!(" {name}=null"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::trade(1, 1, "X", "AAPL", 100, 2),
+            Event::quote(
+                2,
+                2,
+                "X",
+                "AAPL",
+                QuoteTicks {
+                    bid_px: 99,
+                    bid_sz: 5,
+                    ask_px: 102,
+                    ask_sz: 6,
+                },
+            ),
+            Event::trade(3, 3, "Y", "MSFT", 300, 1),
+        ]
+    }
+
+    #[test]
+    fn filters_and_derives_columns() {
+        let script = EventScript::compile(
+            Some(r#"symbol == "AAPL""#),
+            &[
+                String::from("mid = if kind == \"quote\" { (bid_px + ask_px) / 2.0 }"),
+                String::from("notional=if kind == \"trade\" { price_ticks * size }"),
+            ],
+        )
+        .expect("compile");
+        let (kept, derived) = script.apply(events()).expect("apply");
+        assert_eq!(
+            kept.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(derived.names, vec!["mid", "notional"]);
+        assert_eq!(
+            derived.rows,
+            vec![vec![None, Some(200.0)], vec![Some(100.5), None]]
+        );
+        assert_eq!(
+            format_derived(&derived.names, derived.row(1)),
+            " mid=100.5 notional=null"
+        );
+    }
+
+    #[test]
+    fn bad_scripts_are_reported() {
+        let compile = |filter: Option<&str>, column: &str| {
+            EventScript::compile(filter, &[String::from(column)]).map(|_| ())
+        };
+        assert!(compile(None, "mid").is_err());
+        assert!(compile(None, "size=1").is_err());
+        assert!(compile(None, "x=(").is_err());
+
+        let script = EventScript::compile(Some("size"), &[]).expect("compile");
+        let err = script.apply(events()).expect_err("not a bool");
+        assert!(err.to_string().contains("expected bool"), "{err}");
+
+        let script = EventScript::compile(None, &[String::from("m=bid_px + 1")]).expect("compile");
+        let err = script.apply(events()).expect_err("unit arithmetic");
+        assert!(err.to_string().contains("event 1"), "{err}");
+
+        let script = EventScript::compile(Some("loop {}"), &[]).expect("compile");
+        assert!(script.apply(events()).is_err());
+    }
+}
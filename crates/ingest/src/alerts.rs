@@ -0,0 +1,321 @@
+//! Health alerts for live captures. [`AlertMonitor`] counts what a capture
+//! delivers and, once per window, reports checks that started or stopped
+//! failing, so a feed that quietly dies or degrades is noticed.
+
+use crate::frames::IssueKind;
+use crate::live::LiveItem;
+use crate::notify::Notification;
+use md_core::Payload;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertThresholds {
+    /// Fire when fewer events than this arrive per second.
+    pub min_events_per_sec: Option<f64>,
+    /// Fire when more sequence gaps than this are reported per minute.
+    pub max_gaps_per_min: Option<f64>,
+    /// Fire when undecodable messages exceed this fraction of all messages.
+    pub max_issue_ratio: Option<f64>,
+    /// Fire when quotes per trade leave `(min, max)`.
+    pub quote_trade_ratio: Option<(f64, f64)>,
+    /// Every check looks at the counts of one window.
+    pub window: Duration,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            min_events_per_sec: None,
+            max_gaps_per_min: None,
+            max_issue_ratio: None,
+            quote_trade_ratio: None,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AlertThresholds {
+    pub fn is_empty(&self) -> bool {
+        self.min_events_per_sec.is_none()
+            && self.max_gaps_per_min.is_none()
+            && self.max_issue_ratio.is_none()
+            && self.quote_trade_ratio.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    RateCollapse,
+    GapRate,
+    IssueRate,
+    QuoteTradeRatio,
+}
+
+impl AlertKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RateCollapse => "rate_collapse",
+            Self::GapRate => "gap_rate",
+            Self::IssueRate => "issue_rate",
+            Self::QuoteTradeRatio => "quote_trade_ratio",
+        }
+    }
+}
+
+/// A check that started (`firing`) or stopped failing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub firing: bool,
+    /// The measured value for the window that changed the state.
+    pub value: f64,
+    pub detail: String,
+}
+
+impl Alert {
+    pub fn notification(&self, source: &str) -> Notification {
+        Notification {
+            source: source.to_string(),
+            check: self.kind.as_str().to_string(),
+            firing: self.firing,
+            summary: self.detail.clone(),
+            details: serde_json::json!({ "value": self.value }),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct WindowCounts {
+    events: u64,
+    trades: u64,
+    quotes: u64,
+    malformed: u64,
+    gaps: u64,
+}
+
+#[derive(Debug)]
+pub struct AlertMonitor {
+    thresholds: AlertThresholds,
+    window_start: Option<Instant>,
+    counts: WindowCounts,
+    firing: BTreeSet<AlertKind>,
+}
+
+impl AlertMonitor {
+    pub fn new(thresholds: AlertThresholds) -> Self {
+        Self {
+            thresholds,
+            window_start: None,
+            counts: WindowCounts::default(),
+            firing: BTreeSet::new(),
+        }
+    }
+
+    /// Counts `item`, and when a window has passed returns the alerts that
+    /// changed state. Idle items keep the windows turning over on a dead
+    /// feed.
+    pub fn observe(&mut self, item: &LiveItem, now: Instant) -> Vec<Alert> {
+        if self.thresholds.is_empty() {
+            return Vec::new();
+        }
+        match item {
+            LiveItem::Event(event) => {
+                self.counts.events += 1;
+                match event.payload {
+                    Payload::Trade { .. } => self.counts.trades += 1,
+                    Payload::Quote { .. } => self.counts.quotes += 1,
+                }
+            }
+            LiveItem::Issue(issue) => match issue.kind {
+                IssueKind::Malformed => self.counts.malformed += 1,
+                IssueKind::SequenceGap => self.counts.gaps += 1,
+                IssueKind::Late => {}
+            },
+            LiveItem::Idle => {}
+        }
+        let start = *self.window_start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < self.thresholds.window {
+            return Vec::new();
+        }
+        let counts = std::mem::take(&mut self.counts);
+        self.window_start = Some(now);
+        self.evaluate(&counts, elapsed.as_secs_f64())
+    }
+
+    fn evaluate(&mut self, counts: &WindowCounts, secs: f64) -> Vec<Alert> {
+        let t = &self.thresholds;
+        let mut checks = Vec::<(AlertKind, bool, f64, String)>::new();
+        if let Some(min) = t.min_events_per_sec {
+            let rate = counts.events as f64 / secs;
+            checks.push((
+                AlertKind::RateCollapse,
+                rate < min,
+                rate,
+                format!("{rate:.2} events/s over {secs:.0}s, minimum {min}"),
+            ));
+        }
+        if let Some(max) = t.max_gaps_per_min {
+            let rate = counts.gaps as f64 * 60.0 / secs;
+            checks.push((
+                AlertKind::GapRate,
+                rate > max,
+                rate,
+                format!("{rate:.2} sequence gaps/min over {secs:.0}s, maximum {max}"),
+            ));
+        }
+        if let Some(max) = t.max_issue_ratio {
+            let messages = counts.events + counts.malformed;
+            let ratio = if messages == 0 {
+                0.0
+            } else {
+                counts.malformed as f64 / messages as f64
+            };
+            checks.push((
+                AlertKind::IssueRate,
+                ratio > max,
+                ratio,
+                format!(
+                    "{} of {messages} messages undecodable, maximum ratio {max}",
+                    counts.malformed
+                ),
+            ));
+        }
+        if let Some((min, max)) = t.quote_trade_ratio {
+            // An empty window says nothing about the mix; the rate check
+            // covers silence.
+            if counts.events > 0 {
+                let ratio = if counts.trades == 0 {
+                    f64::INFINITY
+                } else {
+                    counts.quotes as f64 / counts.trades as f64
+                };
+                checks.push((
+                    AlertKind::QuoteTradeRatio,
+                    ratio < min || ratio > max,
+                    ratio,
+                    format!(
+                        "{} quotes to {} trades, expected {min}..={max} per trade",
+                        counts.quotes, counts.trades
+                    ),
+                ));
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (kind, failing, value, detail) in checks {
+            let was_firing = self.firing.contains(&kind);
+            if failing == was_firing {
+                continue;
+            }
+            if failing {
+                self.firing.insert(kind);
+            } else {
+                self.firing.remove(&kind);
+            }
+            changed.push(Alert {
+                kind,
+                firing: failing,
+                value,
+                detail,
+            });
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseIssue;
+    use md_core::{Event, QuoteTicks};
+
+    fn issue(kind: IssueKind) -> LiveItem {
+        LiveItem::Issue(ParseIssue {
+            packet_index: 0,
+            offset: 0,
+            kind,
+            detail: String::new(),
+            gap: None,
+        })
+    }
+
+    #[test]
+    fn fires_and_resolves_once_per_state_change() {
+        let mut monitor = AlertMonitor::new(AlertThresholds {
+            min_events_per_sec: Some(2.0),
+            max_gaps_per_min: Some(30.0),
+            max_issue_ratio: Some(0.25),
+            quote_trade_ratio: Some((1.0, 10.0)),
+            window: Duration::from_secs(1),
+        });
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+        let trade = LiveItem::Event(Event::trade(1, 1, "X", "AAPL", 100, 1));
+
+        // First window: one trade, a malformed message and a gap per second.
+        assert!(monitor.observe(&trade, at(0)).is_empty());
+        assert!(monitor
+            .observe(&issue(IssueKind::Malformed), at(500))
+            .is_empty());
+        assert!(monitor
+            .observe(&issue(IssueKind::SequenceGap), at(600))
+            .is_empty());
+        let fired = monitor.observe(&LiveItem::Idle, at(1000));
+        let kinds = fired.iter().map(|a| a.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                AlertKind::RateCollapse,
+                AlertKind::GapRate,
+                AlertKind::IssueRate,
+                AlertKind::QuoteTradeRatio
+            ]
+        );
+        assert!(fired.iter().all(|a| a.firing));
+        assert_eq!(fired[0].value, 1.0);
+
+        // A quiet window: the gap and issue checks recover, the rate check
+        // keeps firing without repeating itself and the mix is not judged.
+        let changed = monitor.observe(&LiveItem::Idle, at(2000));
+        assert_eq!(
+            changed
+                .iter()
+                .map(|a| (a.kind, a.firing))
+                .collect::<Vec<_>>(),
+            vec![(AlertKind::GapRate, false), (AlertKind::IssueRate, false)]
+        );
+
+        // Healthy window: three trades and six quotes.
+        let quote = LiveItem::Event(Event::quote(
+            1,
+            1,
+            "X",
+            "AAPL",
+            QuoteTicks {
+                bid_px: 99,
+                bid_sz: 1,
+                ask_px: 101,
+                ask_sz: 1,
+            },
+        ));
+        for i in 0..9 {
+            let item = if i % 3 == 0 { &trade } else { &quote };
+            assert!(monitor.observe(item, at(2100 + i * 10)).is_empty());
+        }
+        let changed = monitor.observe(&LiveItem::Idle, at(3200));
+        assert_eq!(
+            changed
+                .iter()
+                .map(|a| (a.kind, a.firing))
+                .collect::<Vec<_>>(),
+            vec![
+                (AlertKind::RateCollapse, false),
+                (AlertKind::QuoteTradeRatio, false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Alpha Vantage intraday bars (`TIME_SERIES_INTRADAY`), converted to
+//! events the same way as Yahoo bars. Bars are stamped in the exchange's
+//! local time zone, which the response names in its metadata.
+
+use crate::cache::{fetch_cached, CacheKey, ResponseCache};
+use crate::http::RateLimiter;
+use crate::yahoo::{bars_to_events, Bar, BarExpansion};
+use crate::{HttpOptions, IngestError};
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use md_core::{Event, TickTable, Timestamp};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const DEFAULT_URL: &str = "https://www.alphavantage.co";
+/// The free tier's allowance.
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 5;
+
+/// `month` (`YYYY-MM`) fetches that month instead of the trailing 30 days.
+/// Symbols present in `since` keep only bars strictly after it. Responses
+/// are read from and saved to `cache` when set, except for symbols in
+/// `since`.
+#[derive(Debug, Clone)]
+pub struct AlphaVantageOptions {
+    pub api_key: String,
+    pub base_url: String,
+    pub http: HttpOptions,
+    pub month: Option<String>,
+    pub requests_per_minute: u32,
+    pub expansion: BarExpansion,
+    pub since: BTreeMap<String, Timestamp>,
+    pub cache: Option<ResponseCache>,
+}
+
+impl Default for AlphaVantageOptions {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: String::from(DEFAULT_URL),
+            http: HttpOptions::default(),
+            month: None,
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+            expansion: BarExpansion::default(),
+            since: BTreeMap::new(),
+            cache: None,
+        }
+    }
+}
+
+/// Alpha Vantage's name for an interval. Yahoo spellings (`5m`, `1h`) are
+/// accepted so one `--interval` works for both providers.
+pub fn intraday_interval(interval: &str) -> Option<(&'static str, i64)> {
+    let minutes = match interval {
+        "1m" | "1min" => 1,
+        "5m" | "5min" => 5,
+        "15m" | "15min" => 15,
+        "30m" | "30min" => 30,
+        "60m" | "60min" | "1h" => 60,
+        _ => return None,
+    };
+    let name = match minutes {
+        1 => "1min",
+        5 => "5min",
+        15 => "15min",
+        30 => "30min",
+        _ => "60min",
+    };
+    Some((name, minutes * 60_000_000_000))
+}
+
+pub async fn ingest_alphavantage(
+    symbols: &[String],
+    venue: &str,
+    ticks: &TickTable,
+    interval: &str,
+    options: &AlphaVantageOptions,
+) -> Result<Vec<Event>, IngestError> {
+    if symbols.is_empty() {
+        return Err(IngestError::Parse(String::from("empty symbols list")));
+    }
+    if options.api_key.is_empty() {
+        return Err(IngestError::Parse(String::from(
+            "alphavantage needs an API key",
+        )));
+    }
+    let (interval, bar_ns) = intraday_interval(interval).ok_or_else(|| {
+        IngestError::Parse(format!(
+            "unsupported alphavantage interval {interval}, expected 1m, 5m, 15m, 30m or 60m"
+        ))
+    })?;
+    let client = options.http.build_client()?;
+    let mut limiter = RateLimiter::new(
+        "alphavantage",
+        options.requests_per_minute,
+        Duration::from_secs(60),
+    );
+    let mut per_symbol = Vec::with_capacity(symbols.len());
+    let range = options.month.as_deref().unwrap_or("30d");
+    for symbol in symbols {
+        let cache = options
+            .cache
+            .as_ref()
+            .filter(|_| !options.since.contains_key(symbol));
+        let key = CacheKey {
+            provider: "alphavantage",
+            symbol,
+            range,
+            interval,
+        };
+        let fetch = async {
+            limiter.acquire().await;
+            fetch_intraday(&client, options, symbol, interval).await
+        };
+        let bars = fetch_cached(cache, &key, fetch, |raw| parse_intraday(raw, symbol)).await?;
+        per_symbol.push((symbol.clone(), bars));
+    }
+    bars_to_events(
+        &per_symbol,
+        venue,
+        ticks,
+        bar_ns,
+        &options.expansion,
+        &options.since,
+    )
+}
+
+async fn fetch_intraday(
+    client: &Client,
+    options: &AlphaVantageOptions,
+    symbol: &str,
+    interval: &str,
+) -> Result<String, IngestError> {
+    let mut url = Url::parse(&options.base_url).map_err(|e| IngestError::Parse(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| IngestError::Parse(String::from("invalid alphavantage url")))?
+        .pop_if_empty()
+        .push("query");
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("function", "TIME_SERIES_INTRADAY")
+            .append_pair("symbol", symbol)
+            .append_pair("interval", interval)
+            .append_pair("outputsize", "full")
+            .append_pair("extended_hours", "false");
+        if let Some(month) = &options.month {
+            query.append_pair("month", month);
+        }
+        query.append_pair("apikey", &options.api_key);
+    }
+    let response = client.get(url).send().await?.error_for_status()?;
+    response.text().await.map_err(IngestError::from)
+}
+
+#[derive(Debug, Deserialize)]
+struct IntradayResponse {
+    #[serde(rename = "Meta Data")]
+    meta: Option<BTreeMap<String, String>>,
+    #[serde(rename = "Error Message")]
+    error: Option<String>,
+    /// Throttling and premium-only notices arrive as a 200 with one of
+    /// these instead of data.
+    #[serde(rename = "Note")]
+    note: Option<String>,
+    #[serde(rename = "Information")]
+    information: Option<String>,
+    #[serde(flatten)]
+    rest: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntradayBar {
+    #[serde(rename = "1. open")]
+    open: String,
+    #[serde(rename = "2. high")]
+    high: String,
+    #[serde(rename = "3. low")]
+    low: String,
+    #[serde(rename = "4. close")]
+    close: String,
+    #[serde(rename = "5. volume")]
+    volume: String,
+}
+
+/// Bars in time order, stamped in UTC.
+fn parse_intraday(raw: &str, symbol: &str) -> Result<Vec<Bar>, IngestError> {
+    let response: IntradayResponse = serde_json::from_str(raw)?;
+    if let Some(msg) = response.error.or(response.note).or(response.information) {
+        return Err(IngestError::Parse(format!("{symbol}: {msg}")));
+    }
+    let tz = response
+        .meta
+        .as_ref()
+        .and_then(|meta| {
+            meta.iter()
+                .find(|(key, _)| key.ends_with("Time Zone"))
+                .map(|(_, zone)| zone.clone())
+        })
+        .ok_or_else(|| IngestError::Parse(format!("{symbol}: missing time zone")))?;
+    let tz: Tz = tz
+        .parse()
+        .map_err(|_| IngestError::Parse(format!("{symbol}: unknown time zone {tz}")))?;
+    let series = response
+        .rest
+        .into_iter()
+        .find(|(key, _)| key.starts_with("Time Series"))
+        .map(|(_, value)| value)
+        .ok_or_else(|| IngestError::Parse(format!("{symbol}: missing time series")))?;
+    // Keys sort chronologically ("YYYY-MM-DD HH:MM:SS").
+    let series: BTreeMap<String, IntradayBar> = serde_json::from_value(series)?;
+
+    let number = |raw: &str| raw.parse::<f64>().ok().filter(|v| v.is_finite());
+    let mut bars = Vec::with_capacity(series.len());
+    for (local, bar) in series {
+        let naive = NaiveDateTime::parse_from_str(&local, "%Y-%m-%d %H:%M:%S")
+            .map_err(|e| IngestError::Parse(format!("{symbol}: bad time {local}: {e}")))?;
+        let utc = tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .and_then(|t| t.timestamp_nanos_opt())
+            .ok_or_else(|| {
+                IngestError::Parse(format!("{symbol}: {local} does not exist in {tz}"))
+            })?;
+        bars.push(Bar {
+            start: Timestamp::from_nanos(utc),
+            open: number(&bar.open),
+            high: number(&bar.high),
+            low: number(&bar.low),
+            close: number(&bar.close),
+            volume: bar.volume.parse().ok(),
+        });
+    }
+    Ok(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yahoo::bar_events;
+    use md_core::Payload;
+    use rust_decimal::Decimal;
+
+    const INTRADAY: &str = r#"{
+      "Meta Data": {
+        "1. Information": "Intraday (5min) open, high, low, close prices and volume",
+        "2. Symbol": "IBM",
+        "3. Last Refreshed": "2024-07-01 15:55:00",
+        "4. Interval": "5min",
+        "5. Output Size": "Full size",
+        "6. Time Zone": "US/Eastern"
+      },
+      "Time Series (5min)": {
+        "2024-07-01 15:55:00": {"1. open": "175.1000", "2. high": "175.3000", "3. low": "175.0000", "4. close": "175.1000", "5. volume": "90"},
+        "2024-07-01 09:30:00": {"1. open": "173.5000", "2. high": "174.0000", "3. low": "173.4000", "4. close": "173.9500", "5. volume": "1200"}
+      }
+    }"#;
+
+    #[test]
+    fn parses_bars_in_exchange_time() {
+        let bars = parse_intraday(INTRADAY, "IBM").expect("parse");
+        // 09:30 EDT is 13:30 UTC.
+        assert_eq!(
+            bars[0].start,
+            Timestamp::from_rfc3339("2024-07-01T13:30:00Z").expect("ts")
+        );
+        assert_eq!(bars[1].close, Some(175.1));
+
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let events = bar_events(&bars, "IBM", "XNYS", &ticks, 0).expect("events");
+        assert_eq!(
+            events[0].payload,
+            Payload::Trade {
+                price_ticks: 17_395,
+                size: 1200
+            }
+        );
+
+        let throttled = r#"{"Note": "Thank you for using Alpha Vantage! Our standard API call frequency is 5 calls per minute."}"#;
+        let err = parse_intraday(throttled, "IBM").expect_err("throttled");
+        assert!(err.to_string().contains("5 calls per minute"), "{err}");
+        assert_eq!(intraday_interval("1h"), Some(("60min", 3_600_000_000_000)));
+        assert_eq!(intraday_interval("1d"), None);
+    }
+}
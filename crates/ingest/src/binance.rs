@@ -0,0 +1,175 @@
+//! Binance spot market data: the `<symbol>@trade` and `<symbol>@bookTicker`
+//! streams of every symbol on one combined stream. Trades carry the
+//! exchange trade time; book ticker updates have no timestamp and are
+//! stamped on arrival.
+
+use crate::ws::{price_ticks, quantity, LiveProvider, ProviderEvent};
+use md_core::{Payload, TickTable, Timestamp};
+use serde::Deserialize;
+
+pub const DEFAULT_URL: &str = "wss://stream.binance.com:9443";
+
+pub struct Binance;
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    stream: String,
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeData {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    qty: String,
+    #[serde(rename = "T")]
+    trade_time_ms: i64,
+    #[serde(rename = "t", default)]
+    trade_id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BookTickerData {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid_px: String,
+    #[serde(rename = "B")]
+    bid_qty: String,
+    #[serde(rename = "a")]
+    ask_px: String,
+    #[serde(rename = "A")]
+    ask_qty: String,
+    #[serde(rename = "u", default)]
+    update_id: Option<u64>,
+}
+
+impl LiveProvider for Binance {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn default_url(&self) -> &'static str {
+        DEFAULT_URL
+    }
+
+    /// Combined-stream URL; Binance wants lower-case stream names.
+    fn connect_url(&self, base: &str, symbols: &[String]) -> String {
+        let streams = symbols
+            .iter()
+            .flat_map(|s| {
+                let s = s.to_ascii_lowercase();
+                [format!("{s}@trade"), format!("{s}@bookTicker")]
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("{}/stream?streams={streams}", base.trim_end_matches('/'))
+    }
+
+    fn decode(
+        &self,
+        text: &str,
+        ticks: &TickTable,
+        received: Timestamp,
+    ) -> Result<Vec<ProviderEvent>, String> {
+        let envelope: Envelope = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        if envelope.stream.ends_with("@trade") {
+            let trade: TradeData =
+                serde_json::from_value(envelope.data).map_err(|err| err.to_string())?;
+            let payload = Payload::Trade {
+                price_ticks: price_ticks(ticks, &trade.symbol, &trade.price)?,
+                size: quantity(ticks, &trade.symbol, &trade.qty)?,
+            };
+            return Ok(vec![ProviderEvent {
+                timestamp_ns: Timestamp::from_millis(trade.trade_time_ms)
+                    .map_err(|err| err.to_string())?,
+                symbol: trade.symbol,
+                payload,
+                source_sequence: trade.trade_id,
+            }]);
+        }
+        if envelope.stream.ends_with("@bookTicker") {
+            let book: BookTickerData =
+                serde_json::from_value(envelope.data).map_err(|err| err.to_string())?;
+            let payload = Payload::Quote {
+                bid_px: price_ticks(ticks, &book.symbol, &book.bid_px)?,
+                bid_sz: quantity(ticks, &book.symbol, &book.bid_qty)?,
+                ask_px: price_ticks(ticks, &book.symbol, &book.ask_px)?,
+                ask_sz: quantity(ticks, &book.symbol, &book.ask_qty)?,
+            };
+            return Ok(vec![ProviderEvent {
+                timestamp_ns: received,
+                symbol: book.symbol,
+                payload,
+                source_sequence: book.update_id,
+            }]);
+        }
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    const TRADE: &str = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1700000000001,"s":"BTCUSDT","t":1,"p":"37000.12000000","q":"2.60000000","T":1700000000000,"m":true,"M":true}}"#;
+    const BOOK: &str = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,"s":"BTCUSDT","b":"37000.11000000","B":"3.00000000","a":"37000.13000000","A":"0.40000000"}}"#;
+
+    #[test]
+    fn decodes_trades_and_book_ticker() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let received = Timestamp::from_nanos(42);
+        let decode = |text: &str| Binance.decode(text, &ticks, received);
+
+        assert_eq!(
+            decode(TRADE).expect("trade"),
+            vec![ProviderEvent {
+                timestamp_ns: Timestamp::from_nanos(1_700_000_000_000_000_000),
+                symbol: String::from("BTCUSDT"),
+                payload: Payload::Trade {
+                    price_ticks: 3_700_012,
+                    size: 3
+                },
+                source_sequence: Some(1),
+            }]
+        );
+        assert_eq!(
+            decode(BOOK).expect("quote"),
+            vec![ProviderEvent {
+                timestamp_ns: received,
+                symbol: String::from("BTCUSDT"),
+                payload: Payload::Quote {
+                    bid_px: 3_700_011,
+                    bid_sz: 3,
+                    ask_px: 3_700_013,
+                    ask_sz: 0
+                },
+                source_sequence: Some(400_900_217),
+            }]
+        );
+        let lots = ticks
+            .clone()
+            .with_lot("BTCUSDT", Decimal::new(1, 8))
+            .expect("lot");
+        let scaled = Binance.decode(TRADE, &lots, received).expect("trade");
+        assert_eq!(
+            scaled[0].payload,
+            Payload::Trade {
+                price_ticks: 3_700_012,
+                size: 260_000_000
+            }
+        );
+        assert!(decode(r#"{"stream":"btcusdt@depth","data":{}}"#)
+            .expect("other stream")
+            .is_empty());
+        assert!(decode("{}").is_err());
+        assert_eq!(
+            Binance.connect_url("wss://x/", &[String::from("BTCUSDT")]),
+            "wss://x/stream?streams=btcusdt@trade/btcusdt@bookTicker"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Raw provider responses kept on disk, so repeated ingests during
+//! development neither hit the API again nor produce a different log.
+
+use crate::IngestError;
+use std::fmt::Write as _;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+pub const DEFAULT_CACHE_DIR: &str = ".md-replay-cache";
+
+/// What a response answers. `range` is the window asked for, spelled as
+/// the provider's request spells it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheKey<'a> {
+    pub provider: &'a str,
+    pub symbol: &'a str,
+    pub range: &'a str,
+    pub interval: &'a str,
+}
+
+/// One file per response under `dir/<provider>/<symbol>/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, key: &CacheKey<'_>) -> PathBuf {
+        self.dir
+            .join(file_safe(key.provider))
+            .join(file_safe(key.symbol))
+            .join(format!(
+                "{}_{}.body",
+                file_safe(key.interval),
+                file_safe(key.range)
+            ))
+    }
+
+    pub fn get(&self, key: &CacheKey<'_>) -> Result<Option<String>, IngestError> {
+        match fs::read_to_string(self.path(key)) {
+            Ok(body) => Ok(Some(body)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes through a temporary file, so an interrupted run never leaves
+    /// a truncated response behind.
+    pub fn put(&self, key: &CacheKey<'_>, body: &str) -> Result<(), IngestError> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, body)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// Escapes everything but ASCII alphanumerics, `-` and `.` as `%XX`, so
+/// symbols such as `^SPX` or `EURUSD=X` make valid, distinct file names.
+fn file_safe(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || (byte == b'.' && !out.is_empty()) {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+/// Parses the cached response for `key` or, on a miss, the one `fetch`
+/// returns. A fetched response is stored only once `parse` accepts it, so
+/// throttling notices and error pages are never replayed.
+pub(crate) async fn fetch_cached<T>(
+    cache: Option<&ResponseCache>,
+    key: &CacheKey<'_>,
+    fetch: impl Future<Output = Result<String, IngestError>>,
+    parse: impl FnOnce(&str) -> Result<T, IngestError>,
+) -> Result<T, IngestError> {
+    if let Some(raw) = cache.map(|c| c.get(key)).transpose()?.flatten() {
+        debug!(provider = key.provider, symbol = key.symbol, "cache hit");
+        return parse(&raw);
+    }
+    let raw = fetch.await?;
+    let parsed = parse(&raw)?;
+    if let Some(cache) = cache {
+        cache.put(key, &raw)?;
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_only_accepted_responses() {
+        let dir = std::env::temp_dir().join(format!("md_response_cache_{}", std::process::id()));
+        let cache = ResponseCache::new(&dir);
+        let key = CacheKey {
+            provider: "yahoo",
+            symbol: "^SPX",
+            range: "1d",
+            interval: "1m",
+        };
+        let path = cache.path(&key);
+        assert_eq!(path, dir.join("yahoo").join("%5ESPX").join("1m_1d.body"));
+        let parse = |raw: &str| match raw {
+            "throttled" => Err(IngestError::Parse(String::from("throttled"))),
+            raw => Ok(raw.len()),
+        };
+
+        let fetch = async { Ok(String::from("throttled")) };
+        assert!(fetch_cached(Some(&cache), &key, fetch, parse)
+            .await
+            .is_err());
+        assert!(cache.get(&key).expect("get").is_none());
+
+        let fetch = async { Ok(String::from("bars")) };
+        let len = fetch_cached(Some(&cache), &key, fetch, parse).await;
+        assert_eq!(len.expect("fetched"), 4);
+        let fetch = async { Err(IngestError::Parse(String::from("offline"))) };
+        let len = fetch_cached(Some(&cache), &key, fetch, parse).await;
+        assert_eq!(len.expect("cached"), 4);
+
+        // Without a cache every call goes to the network.
+        let fetch = async { Err(IngestError::Parse(String::from("offline"))) };
+        assert!(fetch_cached(None, &key, fetch, parse).await.is_err());
+        assert_ne!(file_safe("A/B"), file_safe("A_B"));
+        assert_eq!(file_safe(".."), "%2E.");
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,130 @@
+//! Progress of a streaming ingest, saved beside its log so an interrupted
+//! run can pick up where it stopped instead of starting over.
+
+use crate::stream::StreamPosition;
+use crate::IngestError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An input as it was when the checkpoint was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFingerprint {
+    pub path: String,
+    pub len: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestCheckpoint {
+    pub inputs: Vec<InputFingerprint>,
+    pub position: StreamPosition,
+    /// Bytes of the log and its index holding the events handed over up to
+    /// `position`; anything past them is cut on resume.
+    pub log_end: u64,
+    pub index_end: u64,
+}
+
+/// `<log>.checkpoint`.
+pub fn checkpoint_path_for(log: &Path) -> PathBuf {
+    let mut path = log.as_os_str().to_os_string();
+    path.push(".checkpoint");
+    PathBuf::from(path)
+}
+
+impl IngestCheckpoint {
+    pub fn fingerprint(paths: &[PathBuf]) -> Result<Vec<InputFingerprint>, IngestError> {
+        paths
+            .iter()
+            .map(|path| {
+                Ok(InputFingerprint {
+                    path: path.display().to_string(),
+                    len: fs::metadata(path)?.len(),
+                })
+            })
+            .collect()
+    }
+
+    /// The checkpoint at `path`, or `None` if there is none.
+    pub fn load(path: &Path) -> Result<Option<Self>, IngestError> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|err| IngestError::Checkpoint(format!("{}: {err}", path.display()))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes through a temporary file and renames it over `path`, so a
+    /// crash mid-write leaves the previous checkpoint in place.
+    pub fn save(&self, path: &Path) -> Result<(), IngestError> {
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Errors unless `paths` are the inputs the checkpoint was taken from.
+    /// Inputs the run had not finished may have grown since.
+    pub fn check_inputs(&self, paths: &[PathBuf]) -> Result<(), IngestError> {
+        let now = Self::fingerprint(paths)?;
+        if now.len() != self.inputs.len() {
+            return Err(IngestError::Checkpoint(format!(
+                "checkpoint has {} inputs, got {}",
+                self.inputs.len(),
+                now.len()
+            )));
+        }
+        for (i, (then, now)) in self.inputs.iter().zip(&now).enumerate() {
+            let grown = i >= self.position.inputs_done && now.len >= then.len;
+            if then.path != now.path || (then.len != now.len && !grown) {
+                return Err(IngestError::Checkpoint(format!(
+                    "input {} changed since the checkpoint was taken",
+                    now.path
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_rejects_changed_inputs() {
+        let dir = std::env::temp_dir().join(format!("md_checkpoint_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("dir");
+        let a = dir.join("a.csv");
+        let b = dir.join("b.csv");
+        fs::write(&a, "one\ntwo\n").expect("write");
+        fs::write(&b, "three\n").expect("write");
+        let inputs = vec![a.clone(), b.clone()];
+        let checkpoint = IngestCheckpoint {
+            inputs: IngestCheckpoint::fingerprint(&inputs).expect("fingerprint"),
+            position: StreamPosition {
+                inputs_done: 1,
+                rows_done: 2,
+                ..StreamPosition::default()
+            },
+            log_end: 100,
+            index_end: 38,
+        };
+        let path = checkpoint_path_for(&dir.join("out.log"));
+        assert!(IngestCheckpoint::load(&path).expect("load").is_none());
+        checkpoint.save(&path).expect("save");
+        let loaded = IngestCheckpoint::load(&path).expect("load");
+        assert_eq!(loaded.as_ref(), Some(&checkpoint));
+
+        // The unfinished input may grow; the finished one may not.
+        fs::write(&b, "three\nfour\n").expect("write");
+        assert!(checkpoint.check_inputs(&inputs).is_ok());
+        fs::write(&a, "one\n").expect("write");
+        assert!(checkpoint.check_inputs(&inputs).is_err());
+        assert!(checkpoint.check_inputs(&inputs[..1]).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
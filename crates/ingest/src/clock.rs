@@ -0,0 +1,225 @@
+//! Per-venue capture clock correction, so merged venues interleave in the
+//! order events really happened.
+
+use crate::IngestError;
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Nanoseconds added to each venue's timestamps.
+pub type ClockOffsets = BTreeMap<String, i64>;
+
+/// Fewest matched trades an estimate is based on.
+const MIN_MATCHES: usize = 3;
+
+/// Parses `VENUE=OFFSET`, where the offset is a signed count with a unit
+/// of `ns`, `us`, `ms` or `s`, e.g. `XNAS=+1500us` or `BATS=-2ms`.
+pub fn parse_clock_offset(raw: &str) -> Result<(String, i64), IngestError> {
+    let bad = || {
+        IngestError::Parse(format!(
+            "invalid clock offset {raw}, expected VENUE=OFFSET such as XNAS=+1500us"
+        ))
+    };
+    let (venue, offset) = raw.split_once('=').ok_or_else(bad)?;
+    let venue = venue.trim();
+    let offset = offset.trim();
+    let (negative, magnitude) = match offset.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, offset.strip_prefix('+').unwrap_or(offset)),
+    };
+    let split = magnitude
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(bad)?;
+    let (count, unit) = magnitude.split_at(split);
+    let count = count.parse::<i64>().map_err(|_| bad())?;
+    let scale = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        _ => return Err(bad()),
+    };
+    if venue.is_empty() {
+        return Err(bad());
+    }
+    let ns = count.checked_mul(scale).ok_or_else(bad)?;
+    Ok((venue.to_string(), if negative { -ns } else { ns }))
+}
+
+/// Shifts each venue's events by its offset, then restores timestamp order
+/// and renumbers sequences from the first one. Events that tie keep their
+/// previous order, as `assign_sequences` would have given them.
+pub fn apply_clock_offsets(events: &mut [Event], offsets: &ClockOffsets) {
+    if offsets.values().all(|&ns| ns == 0) {
+        return;
+    }
+    let Some(base) = events.iter().map(|e| e.sequence).min() else {
+        return;
+    };
+    for event in events.iter_mut() {
+        if let Some(&ns) = offsets.get(&event.venue) {
+            event.timestamp_ns = event.timestamp_ns.saturating_add_nanos(ns);
+        }
+    }
+    events.sort_by_key(|e| (e.timestamp_ns, e.sequence));
+    for (i, event) in events.iter_mut().enumerate() {
+        event.sequence = base + i as u64;
+    }
+}
+
+/// An offset estimated from trades seen on both a venue and the reference.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClockEstimate {
+    pub venue: String,
+    /// Add this to the venue's timestamps to line them up with the reference.
+    pub offset_ns: i64,
+    /// Trades the estimate is based on.
+    pub matches: usize,
+}
+
+/// Estimates each venue's offset against `reference` (by default the venue
+/// with the most trades) from overlapping trades: the same symbol, price
+/// and size on both, at most `max_skew_ns` apart. Each trade is paired with
+/// the nearest reference trade and the median difference wins, so
+/// coincidental matches do not move it. Venues with fewer than three
+/// matches get no estimate.
+pub fn estimate_clock_offsets(
+    events: &[Event],
+    reference: Option<&str>,
+    max_skew_ns: i64,
+) -> Vec<ClockEstimate> {
+    let mut trades = BTreeMap::<&str, u64>::new();
+    for event in events {
+        if matches!(event.payload, Payload::Trade { .. }) {
+            *trades.entry(event.venue.as_str()).or_default() += 1;
+        }
+    }
+    let reference = match reference {
+        Some(reference) => reference,
+        None => match trades
+            .iter()
+            .max_by_key(|(venue, count)| (**count, std::cmp::Reverse(**venue)))
+        {
+            Some((venue, _)) => venue,
+            None => return Vec::new(),
+        },
+    };
+
+    let mut prints = HashMap::<(&str, i64, i64), Vec<Timestamp>>::new();
+    for event in events.iter().filter(|e| e.venue == reference) {
+        if let Payload::Trade { price_ticks, size } = event.payload {
+            prints
+                .entry((event.symbol.as_str(), price_ticks, size))
+                .or_default()
+                .push(event.timestamp_ns);
+        }
+    }
+    for times in prints.values_mut() {
+        times.sort_unstable();
+    }
+
+    let mut diffs = BTreeMap::<&str, Vec<i64>>::new();
+    for event in events.iter().filter(|e| e.venue != reference) {
+        let Payload::Trade { price_ticks, size } = event.payload else {
+            continue;
+        };
+        let Some(times) = prints.get(&(event.symbol.as_str(), price_ticks, size)) else {
+            continue;
+        };
+        let at = times.partition_point(|t| *t < event.timestamp_ns);
+        let nearest = [at.checked_sub(1), Some(at)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| times.get(i))
+            .map(|t| t.nanos_since(event.timestamp_ns))
+            .min_by_key(|d| d.unsigned_abs());
+        if let Some(diff) = nearest.filter(|d| d.unsigned_abs() <= max_skew_ns.unsigned_abs()) {
+            diffs.entry(event.venue.as_str()).or_default().push(diff);
+        }
+    }
+
+    diffs
+        .into_iter()
+        .filter(|(_, d)| d.len() >= MIN_MATCHES)
+        .map(|(venue, mut d)| {
+            d.sort_unstable();
+            ClockEstimate {
+                venue: venue.to_string(),
+                offset_ns: d[d.len() / 2],
+                matches: d.len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signed_offsets_with_units() {
+        let parsed = parse_clock_offset("XNAS=+1500us").expect("offset");
+        assert_eq!(parsed, (String::from("XNAS"), 1_500_000));
+        assert_eq!(
+            parse_clock_offset("BATS=-2ms").expect("offset").1,
+            -2_000_000
+        );
+        assert_eq!(parse_clock_offset("X=7ns").expect("offset").1, 7);
+        for bad in ["XNAS", "XNAS=15", "XNAS=1.5ms", "=1ms", "XNAS=+us"] {
+            assert!(parse_clock_offset(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn estimated_offsets_fix_interleaving() {
+        // B's clock runs 400 ns behind A's: each print shows up on B
+        // before the quote A sent ahead of it.
+        let quote = md_core::QuoteTicks {
+            bid_px: 99,
+            bid_sz: 1,
+            ask_px: 101,
+            ask_sz: 1,
+        };
+        let mut events = Vec::new();
+        for i in 0..5i64 {
+            let at = 10_000 * i;
+            events.push(Event::quote(at - 200, 0, "A", "AAPL", quote));
+            events.push(Event::trade(at, 0, "A", "AAPL", 100 + i, 10));
+            events.push(Event::trade(at - 400, 0, "B", "AAPL", 100 + i, 10));
+        }
+        events.push(Event::trade(25_000, 0, "A", "AAPL", 999, 1));
+        events.sort_by_key(|e| e.timestamp_ns);
+        for (i, event) in events.iter_mut().enumerate() {
+            event.sequence = i as u64 + 1;
+        }
+        assert_eq!(events[0].venue, "B");
+
+        let estimates = estimate_clock_offsets(&events, None, 1_000);
+        assert_eq!(
+            estimates,
+            vec![ClockEstimate {
+                venue: String::from("B"),
+                offset_ns: 400,
+                matches: 5
+            }]
+        );
+        let offsets = estimates
+            .into_iter()
+            .map(|e| (e.venue, e.offset_ns))
+            .collect::<ClockOffsets>();
+        apply_clock_offsets(&mut events, &offsets);
+        let order = events
+            .iter()
+            .map(|e| (e.sequence, e.venue.as_str()))
+            .take(4)
+            .collect::<Vec<_>>();
+        // Prints now tie, and keep the order they had.
+        assert_eq!(order, vec![(1, "A"), (2, "B"), (3, "A"), (4, "A")]);
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].timestamp_ns <= w[1].timestamp_ns));
+
+        let again = estimate_clock_offsets(&events, Some("A"), 100);
+        assert_eq!(again[0].offset_ns, 0);
+    }
+}
@@ -0,0 +1,158 @@
+//! Coinbase Exchange market data: the `matches` channel for trades and
+//! the `ticker` channel for best bid and offer. Both carry the exchange's
+//! own RFC 3339 time.
+
+use crate::ws::{price_ticks, quantity, LiveProvider, ProviderEvent};
+use md_core::{Payload, TickTable, Timestamp};
+use serde::Deserialize;
+
+pub const DEFAULT_URL: &str = "wss://ws-feed.exchange.coinbase.com";
+
+pub struct Coinbase;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FeedMessage {
+    Match(MatchData),
+    Ticker(TickerData),
+    Error {
+        message: String,
+        #[serde(default)]
+        reason: String,
+    },
+    /// `last_match` (the most recent historical trade, sent on subscribe),
+    /// `subscriptions`, `heartbeat` and anything newer.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatchData {
+    #[serde(default)]
+    sequence: Option<u64>,
+    product_id: String,
+    time: String,
+    price: String,
+    size: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TickerData {
+    #[serde(default)]
+    sequence: Option<u64>,
+    product_id: String,
+    time: String,
+    best_bid: String,
+    best_bid_size: String,
+    best_ask: String,
+    best_ask_size: String,
+}
+
+impl LiveProvider for Coinbase {
+    fn name(&self) -> &'static str {
+        "coinbase"
+    }
+
+    fn default_url(&self) -> &'static str {
+        DEFAULT_URL
+    }
+
+    fn subscribe_messages(&self, symbols: &[String]) -> Vec<String> {
+        let subscribe = serde_json::json!({
+            "type": "subscribe",
+            "product_ids": symbols,
+            "channels": ["matches", "ticker"],
+        });
+        vec![subscribe.to_string()]
+    }
+
+    fn decode(
+        &self,
+        text: &str,
+        ticks: &TickTable,
+        _received: Timestamp,
+    ) -> Result<Vec<ProviderEvent>, String> {
+        let message: FeedMessage = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let time = |raw: &str| Timestamp::from_rfc3339(raw).map_err(|err| err.to_string());
+        let event = match message {
+            FeedMessage::Match(m) => ProviderEvent {
+                timestamp_ns: time(&m.time)?,
+                payload: Payload::Trade {
+                    price_ticks: price_ticks(ticks, &m.product_id, &m.price)?,
+                    size: quantity(ticks, &m.product_id, &m.size)?,
+                },
+                symbol: m.product_id,
+                source_sequence: m.sequence,
+            },
+            FeedMessage::Ticker(t) => ProviderEvent {
+                timestamp_ns: time(&t.time)?,
+                payload: Payload::Quote {
+                    bid_px: price_ticks(ticks, &t.product_id, &t.best_bid)?,
+                    bid_sz: quantity(ticks, &t.product_id, &t.best_bid_size)?,
+                    ask_px: price_ticks(ticks, &t.product_id, &t.best_ask)?,
+                    ask_sz: quantity(ticks, &t.product_id, &t.best_ask_size)?,
+                },
+                symbol: t.product_id,
+                source_sequence: t.sequence,
+            },
+            FeedMessage::Error { message, reason } => {
+                return Err(format!("coinbase error: {message} {reason}")
+                    .trim_end()
+                    .to_string())
+            }
+            FeedMessage::Other => return Ok(Vec::new()),
+        };
+        Ok(vec![event])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn decodes_matches_and_ticker() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let decode = |text: &str| Coinbase.decode(text, &ticks, Timestamp::from_nanos(1));
+
+        let trade = r#"{"type":"match","trade_id":10,"sequence":50,"maker_order_id":"a","taker_order_id":"b","time":"2024-01-02T10:00:00.000001Z","product_id":"BTC-USD","size":"5.5","price":"42000.01","side":"sell"}"#;
+        let expected_ts = Timestamp::from_rfc3339("2024-01-02T10:00:00.000001Z").expect("ts");
+        assert_eq!(
+            decode(trade).expect("match"),
+            vec![ProviderEvent {
+                timestamp_ns: expected_ts,
+                symbol: String::from("BTC-USD"),
+                payload: Payload::Trade {
+                    price_ticks: 4_200_001,
+                    size: 6
+                },
+                source_sequence: Some(50),
+            }]
+        );
+
+        let ticker = r#"{"type":"ticker","sequence":51,"product_id":"BTC-USD","price":"42000.01","best_bid":"42000.00","best_bid_size":"1.2","best_ask":"42000.02","best_ask_size":"3","side":"buy","time":"2024-01-02T10:00:00.000001Z","trade_id":10,"last_size":"5.5"}"#;
+        assert_eq!(
+            decode(ticker).expect("ticker")[0].payload,
+            Payload::Quote {
+                bid_px: 4_200_000,
+                bid_sz: 1,
+                ask_px: 4_200_002,
+                ask_sz: 3
+            }
+        );
+
+        for ignored in [
+            r#"{"type":"heartbeat","sequence":1}"#,
+            r#"{"type":"subscriptions","channels":[]}"#,
+            r#"{"type":"last_match","time":"2024-01-02T09:00:00Z","product_id":"BTC-USD","size":"1","price":"1"}"#,
+        ] {
+            assert!(decode(ignored).expect("ignored").is_empty());
+        }
+        let err = decode(r#"{"type":"error","message":"Failed to subscribe","reason":"BAD-USD is not a valid product"}"#)
+            .expect_err("error message");
+        assert!(err.contains("BAD-USD"), "{err}");
+        assert!(Coinbase.subscribe_messages(&[String::from("BTC-USD")])[0]
+            .contains(r#""channels":["matches","ticker"]"#));
+    }
+}
@@ -1,5 +1,6 @@
+use crate::csv_schema::CsvTimestampFormat;
+use crate::csv_schema::TimestampSpec;
 use crate::IngestError;
-use chrono::DateTime;
 use md_core::{Payload, PendingEvent, TickTable};
 use serde::Deserialize;
 use std::path::Path;
@@ -16,7 +17,7 @@ struct RowA {
 
 #[derive(Debug, Deserialize)]
 struct RowB {
-    timestamp_ms: u64,
+    timestamp_ms: String,
     symbol: String,
     price: String,
     size: i64,
@@ -46,16 +47,43 @@ pub fn parse_csv_a(
     venue: &str,
     ticks: &TickTable,
 ) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for_each_csv_a(path, venue, ticks, |event| {
+        out.push(event);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+/// Hands rows to `on_event` one at a time instead of collecting them.
+pub fn for_each_csv_a(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
+    for_each_csv_a_with(path, venue, ticks, &TimestampSpec::default(), on_event)
+}
+
+/// [`for_each_csv_a`] reading the timestamp column as `timestamps`.
+pub fn for_each_csv_a_with(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    timestamps: &TimestampSpec,
+    mut on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
     let mut rdr = csv::ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_path(path)?;
-    let mut out = Vec::new();
     for (idx, row) in rdr.deserialize::<RowA>().enumerate() {
         let row = row?;
-        let ts = parse_rfc3339_ns(&row.timestamp)?;
+        let ts = timestamps
+            .parse(&row.timestamp)
+            .map_err(|e| IngestError::Parse(format!("row {}: {e}", idx + 1)))?;
         let bid_px = ticks.price_str_to_ticks(&row.symbol, &row.bid_px)?;
         let ask_px = ticks.price_str_to_ticks(&row.symbol, &row.ask_px)?;
-        out.push(PendingEvent {
+        on_event(PendingEvent {
             timestamp_ns: ts,
             venue: venue.to_string(),
             symbol: row.symbol,
@@ -66,9 +94,10 @@ pub fn parse_csv_a(
                 ask_sz: row.ask_sz,
             },
             ingest_order: idx as u64,
-        });
+            source_sequence: None,
+        })?;
     }
-    Ok(out)
+    Ok(())
 }
 
 pub fn parse_csv_b(
@@ -76,18 +105,48 @@ pub fn parse_csv_b(
     venue: &str,
     ticks: &TickTable,
 ) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for_each_csv_b(path, venue, ticks, |event| {
+        out.push(event);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+/// Hands rows to `on_event` one at a time instead of collecting them.
+pub fn for_each_csv_b(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
+    for_each_csv_b_with(
+        path,
+        venue,
+        ticks,
+        &TimestampSpec::epoch(1_000_000),
+        on_event,
+    )
+}
+
+/// [`for_each_csv_b`] reading the timestamp column as `timestamps`.
+pub fn for_each_csv_b_with(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    timestamps: &TimestampSpec,
+    mut on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
     let mut rdr = csv::ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_path(path)?;
-    let mut out = Vec::new();
     for (idx, row) in rdr.deserialize::<RowB>().enumerate() {
         let row = row?;
-        let ts = row
-            .timestamp_ms
-            .checked_mul(1_000_000)
-            .ok_or_else(|| IngestError::Parse(format!("timestamp overflow at row {}", idx + 1)))?;
+        let ts = timestamps
+            .parse(&row.timestamp_ms)
+            .map_err(|e| IngestError::Parse(format!("row {}: {e}", idx + 1)))?;
         let price_ticks = ticks.price_str_to_ticks(&row.symbol, &row.price)?;
-        out.push(PendingEvent {
+        on_event(PendingEvent {
             timestamp_ns: ts,
             venue: venue.to_string(),
             symbol: row.symbol,
@@ -96,9 +155,10 @@ pub fn parse_csv_b(
                 size: row.size,
             },
             ingest_order: idx as u64,
-        });
+            source_sequence: None,
+        })?;
     }
-    Ok(out)
+    Ok(())
 }
 
 pub fn parse_csv_c(
@@ -106,13 +166,46 @@ pub fn parse_csv_c(
     venue: &str,
     ticks: &TickTable,
 ) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for_each_csv_c(path, venue, ticks, |event| {
+        out.push(event);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+/// Hands rows to `on_event` one at a time instead of collecting them.
+pub fn for_each_csv_c(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
+    for_each_csv_c_with(
+        path,
+        venue,
+        ticks,
+        &TimestampSpec::new(CsvTimestampFormat::Mixed, None)?,
+        on_event,
+    )
+}
+
+/// [`for_each_csv_c`] reading the timestamp column as `timestamps`.
+pub fn for_each_csv_c_with(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    timestamps: &TimestampSpec,
+    mut on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
     let mut rdr = csv::ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_path(path)?;
-    let mut out = Vec::new();
     for (idx, row) in rdr.deserialize::<RowC>().enumerate() {
         let row = row?;
-        let ts = parse_mixed_ts_ns(&row.timestamp)?;
+        let ts = timestamps
+            .parse(&row.timestamp)
+            .map_err(|e| IngestError::Parse(format!("row {}: {e}", idx + 1)))?;
         let payload = match row.r#type.as_str() {
             "trade" | "Trade" | "TRADE" => {
                 let price_ticks = ticks.price_str_to_ticks(&row.symbol, &row.price)?;
@@ -138,35 +231,16 @@ pub fn parse_csv_c(
                 )))
             }
         };
-        out.push(PendingEvent {
+        on_event(PendingEvent {
             timestamp_ns: ts,
             venue: venue.to_string(),
             symbol: row.symbol,
             payload,
             ingest_order: idx as u64,
-        });
+            source_sequence: None,
+        })?;
     }
-    Ok(out)
-}
-
-fn parse_rfc3339_ns(raw: &str) -> Result<u64, IngestError> {
-    let dt = DateTime::parse_from_rfc3339(raw)?;
-    let ns = dt
-        .timestamp_nanos_opt()
-        .ok_or_else(|| IngestError::Parse(format!("timestamp out of range: {raw}")))?;
-    u64::try_from(ns).map_err(|_| IngestError::Parse(format!("negative timestamp: {raw}")))
-}
-
-fn parse_mixed_ts_ns(raw: &str) -> Result<u64, IngestError> {
-    if raw.contains('T') {
-        return parse_rfc3339_ns(raw);
-    }
-    let value = raw
-        .parse::<u64>()
-        .map_err(|_| IngestError::Parse(format!("invalid timestamp: {raw}")))?;
-    value
-        .checked_mul(1_000_000)
-        .ok_or_else(|| IngestError::Parse(format!("timestamp overflow: {raw}")))
+    Ok(())
 }
 
 fn parse_i64_or_zero(raw: &str) -> Result<i64, IngestError> {
@@ -237,6 +311,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn csv_b_reads_other_epoch_units() {
+        let path = write_temp(
+            "timestamp_ms,symbol,price,size\n1700000000.5,MSFT,200.10,5\n",
+            "b_secs",
+        );
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let mut events = Vec::new();
+        for_each_csv_b_with(
+            &path,
+            "X",
+            &ticks,
+            &TimestampSpec::epoch(1_000_000_000),
+            |event| {
+                events.push(event);
+                Ok(())
+            },
+        )
+        .expect("parse csv b");
+        assert_eq!(events[0].timestamp_ns.as_nanos(), 1_700_000_000_500_000_000);
+        let default = parse_csv_b(&path, "X", &ticks).expect("parse as ms");
+        assert_eq!(default[0].timestamp_ns.as_nanos(), 1_700_000_000_500_000);
+    }
+
     #[test]
     fn csv_c_handles_mixed() {
         let path = write_temp(
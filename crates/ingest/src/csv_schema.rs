@@ -0,0 +1,621 @@
+//! Vendor CSVs described by a TOML mapping instead of a fixed layout.
+//!
+//! ```toml
+//! delimiter = ";"
+//!
+//! [timestamp]
+//! column = "Time"
+//! format = "%Y-%m-%d %H:%M:%S%.f"
+//! tz = "America/New_York"
+//!
+//! [columns]
+//! symbol = "Ticker"
+//! price = "Px"
+//! size = "Qty"
+//! bid_px = 5
+//!
+//! [kind]
+//! column = "Type"
+//! trade = ["T"]
+//! quote = ["Q"]
+//! ```
+//!
+//! Columns are header names or zero-based positions.
+
+use crate::IngestError;
+use chrono::{NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use md_core::{Payload, PendingEvent, TickTable, Timestamp};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+/// How the timestamp column is written. Epoch values may carry a
+/// fractional part (`1700000000.25` in seconds).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CsvTimestampFormat {
+    #[default]
+    Rfc3339,
+    /// Epoch in units of this many nanoseconds (`s`, `ms`, `us`, `ns`).
+    Epoch(i64),
+    /// RFC 3339 when the value contains a `T`, epoch milliseconds otherwise,
+    /// as in CSV C.
+    Mixed,
+    /// A `chrono` pattern such as `%Y-%m-%d %H:%M:%S%.f`, read as UTC
+    /// unless a time zone is given.
+    Pattern(String),
+}
+
+impl TryFrom<String> for CsvTimestampFormat {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Ok(match raw.as_str() {
+            "rfc3339" => Self::Rfc3339,
+            "s" => Self::Epoch(1_000_000_000),
+            "ms" => Self::Epoch(1_000_000),
+            "us" => Self::Epoch(1_000),
+            "ns" => Self::Epoch(1),
+            "mixed" => Self::Mixed,
+            _ if raw.contains('%') => Self::Pattern(raw),
+            _ => {
+                return Err(format!(
+                    "unknown timestamp format {raw}, expected rfc3339, s, ms, us, ns, mixed or a %-pattern"
+                ))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvTimestamp {
+    pub column: CsvColumn,
+    #[serde(default)]
+    pub format: CsvTimestampFormat,
+    /// IANA zone of naive wall-clock times, e.g. `America/New_York`.
+    #[serde(default)]
+    pub tz: Option<String>,
+}
+
+/// A timestamp format and the zone naive wall-clock times are in, UTC
+/// when unset. Values carrying an offset keep it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TimestampSpec {
+    pub format: CsvTimestampFormat,
+    pub tz: Option<Tz>,
+}
+
+impl TimestampSpec {
+    /// Epochs are UTC by definition, so they take no zone.
+    pub fn new(format: CsvTimestampFormat, tz: Option<&str>) -> Result<Self, IngestError> {
+        let tz = tz
+            .map(|name| {
+                name.parse::<Tz>()
+                    .map_err(|_| IngestError::Parse(format!("unknown time zone {name}")))
+            })
+            .transpose()?;
+        if tz.is_some() && matches!(format, CsvTimestampFormat::Epoch(_)) {
+            return Err(IngestError::Parse(String::from(
+                "epoch timestamps are UTC, a time zone needs rfc3339, mixed or a %-pattern",
+            )));
+        }
+        Ok(Self { format, tz })
+    }
+
+    pub fn epoch(scale: i64) -> Self {
+        Self {
+            format: CsvTimestampFormat::Epoch(scale),
+            tz: None,
+        }
+    }
+
+    /// With a zone, RFC 3339 values lacking an offset are read as local
+    /// times too, with a `T` or a space between date and time. A local
+    /// time that occurs twice on a DST change takes the earlier instant.
+    pub fn parse(&self, raw: &str) -> Result<Timestamp, IngestError> {
+        let invalid = || IngestError::Parse(format!("invalid timestamp: {raw}"));
+        match &self.format {
+            CsvTimestampFormat::Rfc3339 => self.parse_rfc3339(raw),
+            CsvTimestampFormat::Mixed if raw.contains('T') => self.parse_rfc3339(raw),
+            CsvTimestampFormat::Mixed => Self::epoch(1_000_000).parse(raw),
+            CsvTimestampFormat::Epoch(scale) => Decimal::from_str(raw)
+                .ok()
+                .and_then(|v| v.checked_mul(Decimal::from(*scale)))
+                .and_then(|ns| ns.trunc().to_i64())
+                .map(Timestamp::from_nanos)
+                .ok_or_else(invalid),
+            CsvTimestampFormat::Pattern(pattern) => NaiveDateTime::parse_from_str(raw, pattern)
+                .map_err(|_| invalid())
+                .and_then(|naive| self.local(raw, naive)),
+        }
+    }
+
+    fn parse_rfc3339(&self, raw: &str) -> Result<Timestamp, IngestError> {
+        let invalid = || IngestError::Parse(format!("invalid timestamp: {raw}"));
+        match Timestamp::from_rfc3339(raw) {
+            Ok(ts) => Ok(ts),
+            Err(_) if self.tz.is_some() => ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+                .iter()
+                .find_map(|pattern| NaiveDateTime::parse_from_str(raw, pattern).ok())
+                .ok_or_else(invalid)
+                .and_then(|naive| self.local(raw, naive)),
+            Err(_) => Err(invalid()),
+        }
+    }
+
+    fn local(&self, raw: &str, naive: NaiveDateTime) -> Result<Timestamp, IngestError> {
+        let nanos = match self.tz {
+            Some(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .and_then(|t| t.timestamp_nanos_opt())
+                .ok_or_else(|| IngestError::Parse(format!("{raw} does not exist in {tz}")))?,
+            None => naive
+                .and_utc()
+                .timestamp_nanos_opt()
+                .ok_or_else(|| IngestError::Parse(format!("invalid timestamp: {raw}")))?,
+        };
+        Ok(Timestamp::from_nanos(nanos))
+    }
+}
+
+/// Without `venue`, rows take the `--venue` of the ingest. Empty sizes
+/// read as zero. `source_sequence` maps the feed's own sequence number,
+/// kept beside the replay sequence; empty cells read as none.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvColumns {
+    pub symbol: CsvColumn,
+    pub venue: Option<CsvColumn>,
+    pub price: Option<CsvColumn>,
+    pub size: Option<CsvColumn>,
+    pub bid_px: Option<CsvColumn>,
+    pub bid_sz: Option<CsvColumn>,
+    pub ask_px: Option<CsvColumn>,
+    pub ask_sz: Option<CsvColumn>,
+    pub source_sequence: Option<CsvColumn>,
+}
+
+/// Picks trade or quote per row. Optional when the columns map only one
+/// of the two.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvKind {
+    pub column: CsvColumn,
+    #[serde(default = "default_trade_values")]
+    pub trade: Vec<String>,
+    #[serde(default = "default_quote_values")]
+    pub quote: Vec<String>,
+    /// Drop rows whose kind matches neither list instead of failing.
+    #[serde(default)]
+    pub skip_unknown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvSchema {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_has_headers")]
+    pub has_headers: bool,
+    pub timestamp: CsvTimestamp,
+    pub columns: CsvColumns,
+    pub kind: Option<CsvKind>,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_has_headers() -> bool {
+    true
+}
+
+fn default_trade_values() -> Vec<String> {
+    vec![
+        String::from("trade"),
+        String::from("Trade"),
+        String::from("TRADE"),
+    ]
+}
+
+fn default_quote_values() -> Vec<String> {
+    vec![
+        String::from("quote"),
+        String::from("Quote"),
+        String::from("QUOTE"),
+    ]
+}
+
+impl CsvSchema {
+    pub fn from_toml_str(raw: &str) -> Result<Self, IngestError> {
+        toml::from_str(raw).map_err(|err| IngestError::Parse(format!("csv schema: {err}")))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowKind {
+    Trade,
+    Quote,
+}
+
+/// `CsvSchema` with every column resolved to a position.
+struct Resolved {
+    timestamp: usize,
+    symbol: usize,
+    venue: Option<usize>,
+    trade: Option<(usize, Option<usize>)>,
+    quote: Option<(usize, Option<usize>, usize, Option<usize>)>,
+    kind: Option<usize>,
+    source_sequence: Option<usize>,
+}
+
+impl Resolved {
+    fn new(schema: &CsvSchema, headers: Option<&csv::StringRecord>) -> Result<Self, IngestError> {
+        let find = |column: &CsvColumn| -> Result<usize, IngestError> {
+            match (column, headers) {
+                (CsvColumn::Index(index), _) => Ok(*index),
+                (CsvColumn::Name(name), Some(headers)) => {
+                    headers.iter().position(|h| h == name).ok_or_else(|| {
+                        IngestError::Parse(format!("csv schema: no column named {name}"))
+                    })
+                }
+                (CsvColumn::Name(name), None) => Err(IngestError::Parse(format!(
+                    "csv schema: column {name} named, but has_headers is false"
+                ))),
+            }
+        };
+        let find_opt = |column: &Option<CsvColumn>| column.as_ref().map(find).transpose();
+        let columns = &schema.columns;
+        let trade = find_opt(&columns.price)?
+            .map(|price| Ok::<_, IngestError>((price, find_opt(&columns.size)?)));
+        let quote = match (find_opt(&columns.bid_px)?, find_opt(&columns.ask_px)?) {
+            (Some(bid), Some(ask)) => Some((
+                bid,
+                find_opt(&columns.bid_sz)?,
+                ask,
+                find_opt(&columns.ask_sz)?,
+            )),
+            (None, None) => None,
+            _ => {
+                return Err(IngestError::Parse(String::from(
+                    "csv schema: quotes need both bid_px and ask_px",
+                )))
+            }
+        };
+        let resolved = Self {
+            timestamp: find(&schema.timestamp.column)?,
+            symbol: find(&columns.symbol)?,
+            venue: find_opt(&columns.venue)?,
+            trade: trade.transpose()?,
+            quote,
+            kind: schema.kind.as_ref().map(|k| find(&k.column)).transpose()?,
+            source_sequence: find_opt(&columns.source_sequence)?,
+        };
+        match (&resolved.trade, &resolved.quote, resolved.kind) {
+            (None, None, _) => Err(IngestError::Parse(String::from(
+                "csv schema: map price, or bid_px and ask_px",
+            ))),
+            (Some(_), Some(_), None) => Err(IngestError::Parse(String::from(
+                "csv schema: a [kind] column is needed when both trades and quotes are mapped",
+            ))),
+            _ => Ok(resolved),
+        }
+    }
+}
+
+pub fn parse_csv_with_schema(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    schema: &CsvSchema,
+) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for_each_csv_with_schema(path, venue, ticks, schema, |event| {
+        out.push(event);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+/// Hands rows to `on_event` one at a time instead of collecting them.
+pub fn for_each_csv_with_schema(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    schema: &CsvSchema,
+    mut on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
+    let delimiter = u8::try_from(schema.delimiter)
+        .map_err(|_| IngestError::Parse(String::from("csv schema: delimiter must be ASCII")))?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(schema.has_headers)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_path(path)?;
+    let headers = if schema.has_headers {
+        Some(rdr.headers()?.clone())
+    } else {
+        None
+    };
+    let columns = Resolved::new(schema, headers.as_ref())?;
+    let timestamps = TimestampSpec::new(
+        schema.timestamp.format.clone(),
+        schema.timestamp.tz.as_deref(),
+    )?;
+    let mut ingest_order = 0;
+    for record in rdr.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line());
+        let at = |err: IngestError| match err {
+            IngestError::Parse(detail) => IngestError::Parse(format!("line {line}: {detail}")),
+            other => IngestError::Parse(format!("line {line}: {other}")),
+        };
+        let field = |index: usize| {
+            record
+                .get(index)
+                .ok_or_else(|| IngestError::Parse(format!("line {line}: no column {index}")))
+        };
+        let kind = match (columns.kind, &schema.kind) {
+            (Some(index), Some(kind)) => {
+                let raw = field(index)?;
+                if kind.trade.iter().any(|v| v == raw) {
+                    RowKind::Trade
+                } else if kind.quote.iter().any(|v| v == raw) {
+                    RowKind::Quote
+                } else if kind.skip_unknown {
+                    continue;
+                } else {
+                    return Err(IngestError::Parse(format!(
+                        "line {line}: unknown row kind '{raw}'"
+                    )));
+                }
+            }
+            _ if columns.trade.is_some() => RowKind::Trade,
+            _ => RowKind::Quote,
+        };
+        let symbol = field(columns.symbol)?;
+        let price = |index: usize| -> Result<i64, IngestError> {
+            ticks
+                .price_str_to_ticks(symbol, field(index)?)
+                .map_err(|e| at(e.into()))
+        };
+        let size = |index: Option<usize>| -> Result<i64, IngestError> {
+            index.map_or(Ok(0), |i| parse_size(field(i)?).map_err(at))
+        };
+        let payload = match (kind, columns.trade, columns.quote) {
+            (RowKind::Trade, Some((price_at, size_at)), _) => Payload::Trade {
+                price_ticks: price(price_at)?,
+                size: size(size_at)?,
+            },
+            (RowKind::Quote, _, Some((bid_at, bid_sz_at, ask_at, ask_sz_at))) => Payload::Quote {
+                bid_px: price(bid_at)?,
+                bid_sz: size(bid_sz_at)?,
+                ask_px: price(ask_at)?,
+                ask_sz: size(ask_sz_at)?,
+            },
+            (RowKind::Trade, None, _) => {
+                return Err(IngestError::Parse(format!(
+                    "line {line}: trade row, but no price column is mapped"
+                )))
+            }
+            (RowKind::Quote, _, None) => {
+                return Err(IngestError::Parse(format!(
+                    "line {line}: quote row, but no bid_px/ask_px columns are mapped"
+                )))
+            }
+        };
+        let timestamp_ns = timestamps.parse(field(columns.timestamp)?).map_err(at)?;
+        let venue = match columns.venue.map(field).transpose()? {
+            Some(v) if !v.is_empty() => v.to_string(),
+            _ => venue.to_string(),
+        };
+        let source_sequence = match columns.source_sequence.map(field).transpose()? {
+            Some(raw) if !raw.is_empty() => Some(raw.parse::<u64>().map_err(|_| {
+                IngestError::Parse(format!("line {line}: invalid source sequence: {raw}"))
+            })?),
+            _ => None,
+        };
+        on_event(PendingEvent {
+            timestamp_ns,
+            venue,
+            symbol: symbol.to_string(),
+            payload,
+            ingest_order,
+            source_sequence,
+        })?;
+        ingest_order += 1;
+    }
+    Ok(())
+}
+
+fn parse_size(raw: &str) -> Result<i64, IngestError> {
+    if raw.is_empty() {
+        return Ok(0);
+    }
+    raw.parse::<i64>()
+        .map_err(|_| IngestError::Parse(format!("invalid size: {raw}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(content: &str, name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "md_replay_csv_schema_{name}_{}.csv",
+            std::process::id()
+        ));
+        std::fs::write(&path, content).expect("write temp csv");
+        path
+    }
+
+    #[test]
+    fn maps_vendor_columns_and_kinds() {
+        let schema = CsvSchema::from_toml_str(
+            r#"
+            delimiter = ";"
+
+            [timestamp]
+            column = "Time"
+            format = "%Y-%m-%d %H:%M:%S%.f"
+
+            [columns]
+            symbol = "Ticker"
+            venue = "Mic"
+            price = "Px"
+            size = "Qty"
+            bid_px = 5
+            bid_sz = 6
+            ask_px = 7
+            ask_sz = 8
+
+            [kind]
+            column = "Type"
+            trade = ["T"]
+            quote = ["Q"]
+            skip_unknown = true
+            "#,
+        )
+        .expect("schema");
+        let path = write_temp(
+            "Time;Ticker;Type;Px;Qty;Bid;BidQty;Ask;AskQty;Mic\n\
+             2024-01-02 14:30:00.5;AAPL;T;185.64;100;;;;;\n\
+             2024-01-02 14:30:01;AAPL;X;;;;;;;\n\
+             2024-01-02 14:30:02;AAPL;Q;;;185.63;3;185.65;;XNAS\n",
+            "vendor",
+        );
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let events = parse_csv_with_schema(&path, "X", &ticks, &schema).expect("parse");
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].timestamp_ns,
+            Timestamp::from_rfc3339("2024-01-02T14:30:00.5Z").expect("ts")
+        );
+        assert_eq!(
+            events[0].payload,
+            Payload::Trade {
+                price_ticks: 18_564,
+                size: 100
+            }
+        );
+        assert_eq!(events[0].venue, "X");
+        assert_eq!(
+            events[1].payload,
+            Payload::Quote {
+                bid_px: 18_563,
+                bid_sz: 3,
+                ask_px: 18_565,
+                ask_sz: 0
+            }
+        );
+        assert_eq!(
+            (events[1].venue.as_str(), events[1].ingest_order),
+            ("XNAS", 1)
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn positional_columns_and_epoch_units() {
+        let schema = CsvSchema::from_toml_str(
+            r#"
+            has_headers = false
+            timestamp = { column = 0, format = "s" }
+            columns = { symbol = 1, price = 2, size = 3, source_sequence = 4 }
+            "#,
+        )
+        .expect("schema");
+        let path = write_temp(
+            "1700000000.25,BTCUSD,37000.5,2,9001\n1700000001,BTCUSD,37000.5,1,\n",
+            "positional",
+        );
+        let ticks = TickTable::uniform(Decimal::new(1, 1)).expect("tick table");
+        let events = parse_csv_with_schema(&path, "X", &ticks, &schema).expect("parse");
+        assert_eq!(events[0].timestamp_ns.as_nanos(), 1_700_000_000_250_000_000);
+        assert_eq!(
+            events[0].payload,
+            Payload::Trade {
+                price_ticks: 370_005,
+                size: 2
+            }
+        );
+        assert_eq!(events[0].source_sequence, Some(9001));
+        assert_eq!(events[1].source_sequence, None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn naive_times_read_in_the_given_zone() {
+        let utc = |raw: &str| Timestamp::from_rfc3339(raw).expect("utc");
+        let pattern = CsvTimestampFormat::Pattern(String::from("%Y-%m-%d %H:%M:%S"));
+        let ny = TimestampSpec::new(pattern, Some("America/New_York")).expect("spec");
+        assert_eq!(
+            ny.parse("2024-01-02 09:30:00").expect("est"),
+            utc("2024-01-02T14:30:00Z")
+        );
+        // Falling back repeats 01:30; the first one, still EDT, wins.
+        assert_eq!(
+            ny.parse("2024-11-03 01:30:00").expect("ambiguous"),
+            utc("2024-11-03T05:30:00Z")
+        );
+        assert!(ny.parse("2024-03-10 02:30:00").is_err());
+
+        let rfc = TimestampSpec::new(CsvTimestampFormat::Rfc3339, Some("America/New_York"))
+            .expect("spec");
+        assert_eq!(
+            rfc.parse("2024-07-01T09:30:00.5").expect("edt"),
+            utc("2024-07-01T13:30:00.5Z")
+        );
+        assert_eq!(
+            rfc.parse("2024-07-01T09:30:00Z").expect("offset kept"),
+            utc("2024-07-01T09:30:00Z")
+        );
+        assert!(TimestampSpec::default()
+            .parse("2024-07-01T09:30:00")
+            .is_err());
+        assert!(TimestampSpec::new(CsvTimestampFormat::Epoch(1), Some("UTC")).is_err());
+        assert!(TimestampSpec::new(CsvTimestampFormat::Rfc3339, Some("Mars/Base")).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_schemas_and_rows() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let path = write_temp("ts,sym,price,bid,ask\n1,AAPL,1.00,1,2\n", "bad");
+        let parse = |raw: &str| {
+            let schema = CsvSchema::from_toml_str(raw).expect("schema");
+            parse_csv_with_schema(&path, "X", &ticks, &schema)
+                .expect_err("rejected")
+                .to_string()
+        };
+        assert!(parse(
+            "timestamp = { column = \"ts\", format = \"ns\" }\ncolumns = { symbol = \"missing\", price = \"price\" }"
+        )
+        .contains("no column named missing"));
+        assert!(parse(
+            "timestamp = { column = \"ts\", format = \"ns\" }\ncolumns = { symbol = \"sym\", price = \"price\", bid_px = \"bid\", ask_px = \"ask\" }"
+        )
+        .contains("[kind] column is needed"));
+        assert!(parse(
+            "timestamp = { column = \"ts\" }\ncolumns = { symbol = \"sym\", price = \"price\" }"
+        )
+        .contains("line 2: invalid timestamp"));
+        assert!(CsvSchema::from_toml_str(
+            "timestamp = { column = 0, format = \"minutes\" }\ncolumns = { symbol = 1 }"
+        )
+        .is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,111 @@
+//! Dropping duplicate events, as multicast captures and merged A/B feeds
+//! produce.
+
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DedupReport {
+    pub kept: u64,
+    pub dropped: u64,
+    pub by_symbol: BTreeMap<String, u64>,
+}
+
+/// Drops events that repeat an earlier one's timestamp, venue, symbol and
+/// payload, and renumbers sequences to stay contiguous from the first
+/// event's. Input must be in timestamp order, as ingest produces it, and
+/// may arrive in batches: a timestamp split across batches is still
+/// deduplicated as one. Two genuine events identical in all of those (two
+/// equal fills in the same nanosecond) are indistinguishable from a
+/// duplicate and are dropped too.
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    timestamp: Option<Timestamp>,
+    seen: HashSet<(String, String, Payload)>,
+    next_sequence: Option<u64>,
+    report: DedupReport,
+}
+
+impl Deduplicator {
+    pub fn filter(&mut self, events: Vec<Event>) -> Vec<Event> {
+        let mut kept = Vec::with_capacity(events.len());
+        for mut event in events {
+            if self.timestamp != Some(event.timestamp_ns) {
+                self.timestamp = Some(event.timestamp_ns);
+                self.seen.clear();
+            }
+            let key = (
+                event.venue.clone(),
+                event.symbol.clone(),
+                event.payload.clone(),
+            );
+            if !self.seen.insert(key) {
+                self.report.dropped += 1;
+                *self.report.by_symbol.entry(event.symbol).or_default() += 1;
+                continue;
+            }
+            let sequence = *self.next_sequence.get_or_insert(event.sequence);
+            event.sequence = sequence;
+            self.next_sequence = Some(sequence + 1);
+            self.report.kept += 1;
+            kept.push(event);
+        }
+        kept
+    }
+
+    pub fn report(&self) -> &DedupReport {
+        &self.report
+    }
+}
+
+/// [`Deduplicator`] over one batch.
+pub fn drop_duplicates(events: &mut Vec<Event>) -> DedupReport {
+    let mut dedup = Deduplicator::default();
+    *events = dedup.filter(std::mem::take(events));
+    dedup.report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_repeats_within_a_timestamp_across_batches() {
+        let mut events = vec![
+            Event::trade(10, 1, "X", "AAPL", 100, 5),
+            Event::trade(10, 2, "X", "AAPL", 100, 5),
+            Event::trade(10, 3, "Y", "AAPL", 100, 5),
+            Event::trade(10, 4, "X", "AAPL", 101, 5),
+            Event::trade(20, 5, "X", "AAPL", 100, 5),
+            Event::trade(20, 6, "X", "MSFT", 300, 1),
+            Event::trade(20, 7, "X", "MSFT", 300, 1),
+        ];
+        let mut dedup = Deduplicator::default();
+        let later = events.split_off(5);
+        let mut kept = dedup.filter(events);
+        kept.extend(dedup.filter(later));
+        let summary = kept
+            .iter()
+            .map(|e| (e.timestamp_ns.as_nanos(), e.sequence, e.venue.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (10, 1, "X"),
+                (10, 2, "Y"),
+                (10, 3, "X"),
+                (20, 4, "X"),
+                (20, 5, "X")
+            ]
+        );
+        let report = dedup.report();
+        assert_eq!((report.kept, report.dropped), (5, 2));
+        assert_eq!(report.by_symbol["AAPL"], 1);
+        assert_eq!(report.by_symbol["MSFT"], 1);
+
+        let mut again = kept.clone();
+        assert_eq!(drop_duplicates(&mut again).dropped, 0);
+        assert_eq!(again, kept);
+    }
+}
@@ -0,0 +1,121 @@
+//! Several input files, or glob patterns, ingested as one tape.
+
+use crate::IngestError;
+use md_core::{assign_sequences, Event, PendingEvent};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Expands each input into paths. Inputs containing `*`, `?` or `[` are
+/// glob patterns whose matches are taken in sorted order; a pattern that
+/// matches nothing is an error. Other inputs are taken as they are.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, IngestError> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if !input.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(input));
+            continue;
+        }
+        let pattern = glob::glob(input)
+            .map_err(|err| IngestError::Parse(format!("input pattern {input}: {err}")))?;
+        let mut matched = pattern
+            .map(|entry| entry.map_err(|err| IngestError::Io(err.into())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if matched.is_empty() {
+            return Err(IngestError::Parse(format!("no files match {input}")));
+        }
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Parses `paths` in parallel and sequences the rows as one tape. Events
+/// with equal timestamps keep input order: earlier files first, then row
+/// order within a file.
+pub fn ingest_files<F>(paths: &[PathBuf], parse: F) -> Result<Vec<Event>, IngestError>
+where
+    F: Fn(&Path) -> Result<Vec<PendingEvent>, IngestError> + Sync,
+{
+    let parsed = paths
+        .par_iter()
+        .map(|path| parse(path).map_err(|err| in_file(path, err)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut pending = Vec::with_capacity(parsed.iter().map(Vec::len).sum());
+    for mut event in parsed.into_iter().flatten() {
+        event.ingest_order = pending.len() as u64;
+        pending.push(event);
+    }
+    Ok(assign_sequences(pending))
+}
+
+pub(crate) fn in_file(path: &Path, err: IngestError) -> IngestError {
+    match err {
+        IngestError::Parse(detail) => IngestError::Parse(format!("{}: {detail}", path.display())),
+        other => IngestError::Parse(format!("{}: {other}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_csv_b;
+    use md_core::TickTable;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn merges_files_by_timestamp_in_input_order() {
+        let dir = std::env::temp_dir().join(format!("md_ingest_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let header = "timestamp_ms,symbol,price,size\n";
+        std::fs::write(
+            dir.join("day-02.csv"),
+            format!("{header}1000,MSFT,2.00,1\n3000,MSFT,2.01,1\n"),
+        )
+        .expect("write");
+        std::fs::write(
+            dir.join("day-01.csv"),
+            format!("{header}1000,AAPL,1.00,1\n2000,AAPL,1.01,1\n"),
+        )
+        .expect("write");
+        std::fs::write(dir.join("notes.txt"), "skip").expect("write");
+
+        let pattern = format!("{}/day-*.csv", dir.display());
+        let paths = expand_inputs(&[pattern]).expect("expand");
+        assert_eq!(
+            paths
+                .iter()
+                .filter_map(|p| p.file_name()?.to_str())
+                .collect::<Vec<_>>(),
+            vec!["day-01.csv", "day-02.csv"]
+        );
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let events = ingest_files(&paths, |p| parse_csv_b(p, "X", &ticks)).expect("ingest");
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (
+                    e.timestamp_ns.as_nanos() / 1_000_000,
+                    e.symbol.as_str(),
+                    e.sequence
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (1000, "AAPL", 1),
+                (1000, "MSFT", 2),
+                (2000, "AAPL", 3),
+                (3000, "MSFT", 4)
+            ]
+        );
+
+        let missing = format!("{}/nothing-*.csv", dir.display());
+        assert!(expand_inputs(&[missing]).is_err());
+        std::fs::write(dir.join("day-03.csv"), format!("{header}x,AAPL,1,1\n")).expect("write");
+        let err = ingest_files(
+            &expand_inputs(&[format!("{}/day-*.csv", dir.display())]).expect("expand"),
+            |p| parse_csv_b(p, "X", &ticks),
+        )
+        .expect_err("bad row");
+        assert!(err.to_string().contains("day-03.csv"), "{err}");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,466 @@
+//! FIX 4.2/4.4 tag=value logs, one message per line. Anything before
+//! `8=FIX` (log prefixes) is ignored and fields are split on SOH, or on
+//! `|` when the line has no SOH. Market data snapshots (`35=W`) and
+//! incremental refreshes (`35=X`) keep top of book per venue and symbol
+//! and become quotes and trades; execution reports (`35=8`) that carry a
+//! fill become trades at `LastPx`/`LastQty`.
+
+use crate::frames::{warn_issue, IssueKind, ParseIssue, PcapIngestOutput};
+use crate::IngestError;
+use md_core::{assign_sequences, Payload, PendingEvent, TickTable, Timestamp};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Tags and codes read from each message, for venues that stray from the
+/// standard. Timestamp and venue tags are tried in order.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixTagMap {
+    pub msg_type: u32,
+    /// `MsgSeqNum`, kept as each event's source sequence.
+    pub msg_seq_num: u32,
+    pub symbol: u32,
+    pub no_md_entries: u32,
+    pub md_entry_type: u32,
+    pub md_entry_px: u32,
+    pub md_entry_size: u32,
+    pub md_update_action: u32,
+    pub md_price_level: u32,
+    pub exec_type: u32,
+    pub last_px: u32,
+    pub last_qty: u32,
+    pub timestamp: Vec<u32>,
+    pub venue: Vec<u32>,
+    pub bid: String,
+    pub offer: String,
+    pub trade: String,
+    /// `ExecType` values that mean a fill (`F` in 4.4, `1`/`2` in 4.2).
+    pub fill_exec_types: Vec<String>,
+}
+
+impl Default for FixTagMap {
+    fn default() -> Self {
+        Self {
+            msg_type: 35,
+            msg_seq_num: 34,
+            symbol: 55,
+            no_md_entries: 268,
+            md_entry_type: 269,
+            md_entry_px: 270,
+            md_entry_size: 271,
+            md_update_action: 279,
+            md_price_level: 1023,
+            exec_type: 150,
+            last_px: 31,
+            last_qty: 32,
+            timestamp: vec![60, 52],
+            venue: vec![207, 30],
+            bid: String::from("0"),
+            offer: String::from("1"),
+            trade: String::from("2"),
+            fill_exec_types: vec![String::from("F"), String::from("1"), String::from("2")],
+        }
+    }
+}
+
+impl FixTagMap {
+    pub fn from_toml_str(raw: &str) -> Result<Self, IngestError> {
+        toml::from_str(raw).map_err(|err| IngestError::Parse(format!("fix tag config: {err}")))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct TopBook {
+    bid_px: i64,
+    bid_sz: i64,
+    ask_px: i64,
+    ask_sz: i64,
+}
+
+/// Converts a FIX `UTCTimestamp` (`YYYYMMDD-HH:MM:SS[.fff...]`).
+fn parse_utc_timestamp(raw: &str) -> Option<Timestamp> {
+    let (date, time) = raw.split_once('-')?;
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let rfc3339 = format!("{}-{}-{}T{time}Z", &date[..4], &date[4..6], &date[6..]);
+    Timestamp::from_rfc3339(&rfc3339).ok()
+}
+
+/// Quantities are integers, sometimes written with a zero fraction.
+fn parse_qty(raw: &str) -> Option<i64> {
+    raw.parse::<i64>().ok().or_else(|| {
+        let qty = Decimal::from_str(raw).ok()?;
+        qty.fract().is_zero().then(|| i64::try_from(qty).ok())?
+    })
+}
+
+type Fields<'a> = Vec<(u32, &'a str)>;
+
+fn split_fields(message: &str) -> Result<Fields<'_>, String> {
+    let delimiter = if message.contains('\x01') {
+        '\x01'
+    } else {
+        '|'
+    };
+    message
+        .split(delimiter)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let (tag, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("field without '=': {field}"))?;
+            let tag = tag
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("bad tag {tag}"))?;
+            Ok((tag, value))
+        })
+        .collect()
+}
+
+fn first<'a>(fields: &[(u32, &'a str)], tag: u32) -> Option<&'a str> {
+    fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v)
+}
+
+struct FixDecoder<'a> {
+    venue: &'a str,
+    ticks: &'a TickTable,
+    tags: &'a FixTagMap,
+    books: HashMap<(String, String), TopBook>,
+    ingest_order: u64,
+    /// `MsgSeqNum` of the message being decoded.
+    seq_num: Option<u64>,
+}
+
+impl<'a> FixDecoder<'a> {
+    /// Decodes one message; lines that are not FIX messages yield nothing.
+    fn decode(&mut self, message: &str) -> Result<Vec<PendingEvent>, String> {
+        let fields = split_fields(message)?;
+        let tags = self.tags;
+        let msg_type = first(&fields, tags.msg_type).ok_or("missing MsgType")?;
+        self.seq_num = first(&fields, tags.msg_seq_num).and_then(|raw| raw.parse().ok());
+        match msg_type {
+            "W" | "X" => self.decode_market_data(&fields, msg_type == "W"),
+            "8" => self.decode_execution(&fields),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn timestamp(&self, fields: &[(u32, &str)]) -> Result<Timestamp, String> {
+        let raw = self
+            .tags
+            .timestamp
+            .iter()
+            .find_map(|tag| first(fields, *tag))
+            .ok_or("missing timestamp")?;
+        parse_utc_timestamp(raw).ok_or_else(|| format!("bad timestamp {raw}"))
+    }
+
+    fn venue(&self, fields: &[(u32, &str)]) -> String {
+        self.tags
+            .venue
+            .iter()
+            .find_map(|tag| first(fields, *tag))
+            .unwrap_or(self.venue)
+            .to_string()
+    }
+
+    fn price(&self, symbol: &str, raw: Option<&str>) -> Result<i64, String> {
+        let raw = raw.ok_or("missing price")?;
+        self.ticks
+            .price_str_to_ticks(symbol, raw)
+            .map_err(|err| format!("price {raw}: {err}"))
+    }
+
+    fn event(
+        &mut self,
+        ts: Timestamp,
+        venue: &str,
+        symbol: &str,
+        payload: Payload,
+    ) -> PendingEvent {
+        self.ingest_order += 1;
+        PendingEvent {
+            timestamp_ns: ts,
+            venue: venue.to_string(),
+            symbol: symbol.to_string(),
+            payload,
+            ingest_order: self.ingest_order,
+            source_sequence: self.seq_num,
+        }
+    }
+
+    /// Splits the `NoMDEntries` group on its first tag and applies each
+    /// entry. A snapshot replaces the book; only level-1 bids and offers
+    /// move an incremental book.
+    fn decode_market_data(
+        &mut self,
+        fields: &[(u32, &str)],
+        snapshot: bool,
+    ) -> Result<Vec<PendingEvent>, String> {
+        let tags = self.tags;
+        let ts = self.timestamp(fields)?;
+        let venue = self.venue(fields);
+        let message_symbol = first(fields, tags.symbol);
+
+        let start = fields
+            .iter()
+            .position(|(tag, _)| *tag == tags.no_md_entries)
+            .ok_or("missing NoMDEntries")?;
+        let group = &fields[start + 1..];
+        let mut entries = Vec::new();
+        if let Some((delimiter, _)) = group.first() {
+            for (i, (tag, _)) in group.iter().enumerate() {
+                if tag == delimiter {
+                    entries.push(i);
+                }
+            }
+            entries.push(group.len());
+        }
+
+        let mut out = Vec::new();
+        let mut touched = Vec::<String>::new();
+        if snapshot {
+            if let Some(symbol) = message_symbol {
+                self.books
+                    .insert((venue.clone(), symbol.to_string()), TopBook::default());
+                touched.push(symbol.to_string());
+            }
+        }
+        for bounds in entries.windows(2) {
+            let entry = &group[bounds[0]..bounds[1]];
+            let symbol = first(entry, tags.symbol)
+                .or(message_symbol)
+                .ok_or("missing Symbol")?;
+            let kind = first(entry, tags.md_entry_type).ok_or("missing MDEntryType")?;
+            if kind == tags.trade {
+                let price_ticks = self.price(symbol, first(entry, tags.md_entry_px))?;
+                let size = first(entry, tags.md_entry_size)
+                    .and_then(parse_qty)
+                    .ok_or("bad trade size")?;
+                let payload = Payload::Trade { price_ticks, size };
+                out.push(self.event(ts, &venue, symbol, payload));
+                continue;
+            }
+            let is_bid = kind == tags.bid;
+            if !is_bid && kind != tags.offer {
+                continue;
+            }
+            let level = first(entry, tags.md_price_level).unwrap_or("1");
+            let action = first(entry, tags.md_update_action).unwrap_or("0");
+            let key = (venue.clone(), symbol.to_string());
+            // Snapshots list levels best first; later ones are depth.
+            let side_set = self.books.get(&key).is_some_and(|book| {
+                if is_bid {
+                    book.bid_px != 0 || book.bid_sz != 0
+                } else {
+                    book.ask_px != 0 || book.ask_sz != 0
+                }
+            });
+            if level != "1" || (snapshot && side_set && touched.contains(&key.1)) {
+                continue;
+            }
+            let (px, sz) = if action == "2" {
+                (0, 0)
+            } else {
+                let px = self.price(symbol, first(entry, tags.md_entry_px))?;
+                let sz = first(entry, tags.md_entry_size)
+                    .and_then(parse_qty)
+                    .ok_or("bad entry size")?;
+                (px, sz)
+            };
+            let book = self.books.entry(key).or_default();
+            if is_bid {
+                book.bid_px = px;
+                book.bid_sz = sz;
+            } else {
+                book.ask_px = px;
+                book.ask_sz = sz;
+            }
+            if !touched.iter().any(|s| s == symbol) {
+                touched.push(symbol.to_string());
+            }
+        }
+
+        for symbol in touched {
+            let book = self
+                .books
+                .get(&(venue.clone(), symbol.clone()))
+                .copied()
+                .unwrap_or_default();
+            let payload = Payload::Quote {
+                bid_px: book.bid_px,
+                bid_sz: book.bid_sz,
+                ask_px: book.ask_px,
+                ask_sz: book.ask_sz,
+            };
+            out.push(self.event(ts, &venue, &symbol, payload));
+        }
+        Ok(out)
+    }
+
+    fn decode_execution(&mut self, fields: &[(u32, &str)]) -> Result<Vec<PendingEvent>, String> {
+        let tags = self.tags;
+        let is_fill = first(fields, tags.exec_type)
+            .is_some_and(|t| tags.fill_exec_types.iter().any(|f| f == t));
+        let qty = first(fields, tags.last_qty)
+            .and_then(parse_qty)
+            .unwrap_or(0);
+        if !is_fill || qty <= 0 {
+            return Ok(Vec::new());
+        }
+        let symbol = first(fields, tags.symbol).ok_or("missing Symbol")?;
+        let ts = self.timestamp(fields)?;
+        let venue = self.venue(fields);
+        let price_ticks = self.price(symbol, first(fields, tags.last_px))?;
+        let payload = Payload::Trade {
+            price_ticks,
+            size: qty,
+        };
+        Ok(vec![self.event(ts, &venue, symbol, payload)])
+    }
+}
+
+/// Ingests a FIX message log. Venues come from the message (see
+/// [`FixTagMap::venue`]) or default to `venue`. Lines that fail to decode
+/// are reported as issues with `packet_index` set to the line number.
+pub fn ingest_fix(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    tags: &FixTagMap,
+) -> Result<PcapIngestOutput, IngestError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut decoder = FixDecoder {
+        venue,
+        ticks,
+        tags,
+        books: HashMap::new(),
+        ingest_order: 0,
+        seq_num: None,
+    };
+    let mut pending = Vec::new();
+    let mut issues = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let Some(start) = line.find("8=FIX") else {
+            continue;
+        };
+        match decoder.decode(line[start..].trim_end()) {
+            Ok(events) => pending.extend(events),
+            Err(detail) => issues.push(ParseIssue {
+                packet_index: idx as u64 + 1,
+                offset: start,
+                kind: IssueKind::Malformed,
+                detail,
+                gap: None,
+            }),
+        }
+    }
+
+    for issue in &issues {
+        warn_issue(issue);
+    }
+    Ok(PcapIngestOutput {
+        events: assign_sequences(pending),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks() -> TickTable {
+        TickTable::uniform(Decimal::new(1, 2)).expect("tick table")
+    }
+
+    fn ingest(content: &str, tags: &FixTagMap) -> PcapIngestOutput {
+        let path = std::env::temp_dir().join(format!(
+            "md_replay_ingest_fix_{}_{}.log",
+            std::process::id(),
+            content.len()
+        ));
+        std::fs::write(&path, content).expect("write fix log");
+        let out = ingest_fix(&path, "X", &ticks(), tags).expect("ingest");
+        let _ = std::fs::remove_file(&path);
+        out
+    }
+
+    fn quote(out: &PcapIngestOutput, i: usize) -> (i64, i64, i64, i64) {
+        match out.events[i].payload {
+            Payload::Quote {
+                bid_px,
+                bid_sz,
+                ask_px,
+                ask_sz,
+            } => (bid_px, bid_sz, ask_px, ask_sz),
+            Payload::Trade { .. } => panic!("event {i} is a trade"),
+        }
+    }
+
+    #[test]
+    fn market_data_and_fills_become_events() {
+        let log = concat!(
+            "2024-01-02 14:30:00.000 IN 8=FIX.4.4|35=W|34=101|52=20240102-14:30:00.000|55=AAPL|268=3|",
+            "269=0|270=185.20|271=100|269=0|270=185.10|271=300|269=1|270=185.25|271=200|10=000|\n",
+            "8=FIX.4.4\x0135=X\x0134=102\x0152=20240102-14:30:00.500\x01268=2\x01",
+            "279=1\x01269=1\x0155=AAPL\x01270=185.24\x01271=50\x01",
+            "279=0\x01269=2\x0155=AAPL\x01270=185.24\x01271=10.0\x0110=000\x01\n",
+            "heartbeat noise\n",
+            "8=FIX.4.2|35=8|60=20240102-14:30:01|55=AAPL|30=XNAS|150=2|31=185.23|32=25|10=000|\n",
+            "8=FIX.4.2|35=8|60=20240102-14:30:02|55=AAPL|150=0|31=0|32=0|10=000|\n",
+            "8=FIX.4.4|35=X|52=bad|268=0|10=000|\n",
+        );
+        let out = ingest(log, &FixTagMap::default());
+        assert_eq!(out.events.len(), 4, "{:?}", out.events);
+        assert_eq!(quote(&out, 0), (18_520, 100, 18_525, 200));
+        assert_eq!(
+            out.events[1].payload,
+            Payload::Trade {
+                price_ticks: 18_524,
+                size: 10
+            }
+        );
+        assert_eq!(quote(&out, 2), (18_520, 100, 18_524, 50));
+        assert_eq!(out.events[3].venue, "XNAS");
+        assert_eq!(
+            out.events[3].payload,
+            Payload::Trade {
+                price_ticks: 18_523,
+                size: 25
+            }
+        );
+        assert_eq!(
+            out.events[0].timestamp_ns,
+            parse_utc_timestamp("20240102-14:30:00").expect("ts")
+        );
+        let seqs = out.events.iter().map(|e| e.source_sequence);
+        assert_eq!(
+            seqs.collect::<Vec<_>>(),
+            [Some(101), Some(102), Some(102), None]
+        );
+        assert_eq!(out.issues.len(), 1);
+        assert_eq!(out.issues[0].packet_index, 6);
+    }
+
+    #[test]
+    fn tag_map_handles_venue_quirks() {
+        let tags = FixTagMap::from_toml_str(
+            "md_entry_px = 9270\nvenue = [9207]\nbid = \"B\"\noffer = \"S\"\n",
+        )
+        .expect("tag config");
+        let log = "8=FIX.4.4|35=X|52=20240102-14:30:00|9207=ARCX|268=1|279=0|269=B|55=MSFT|9270=410.5|271=7|10=000|\n";
+        let out = ingest(log, &tags);
+        assert!(out.issues.is_empty(), "{:?}", out.issues);
+        assert_eq!(out.events[0].venue, "ARCX");
+        assert_eq!(quote(&out, 0), (41_050, 7, 0, 0));
+        assert!(FixTagMap::from_toml_str("nonsense = 1").is_err());
+    }
+}
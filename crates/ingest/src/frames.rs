@@ -0,0 +1,505 @@
+use crate::gaps::GapRange;
+use crate::itch::{parse_message, MockItchMessage, Side};
+use crate::mold::{MoldHeader, MoldSequencer, HEADER_LEN};
+use md_core::{Event, Payload, PendingEvent, Timestamp};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// How ITCH messages are packed into UDP payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per datagram.
+    #[default]
+    Raw,
+    /// MoldUDP64 downstream packets, possibly several messages each.
+    MoldUdp64,
+}
+
+impl std::str::FromStr for Framing {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_ascii_lowercase().as_str() {
+            "raw" => Ok(Self::Raw),
+            "mold" | "moldudp64" => Ok(Self::MoldUdp64),
+            other => Err(format!("unknown framing {other}, expected raw|mold")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The packet or message could not be decoded.
+    #[default]
+    Malformed,
+    /// Part of the feed never arrived: skipped MoldUDP64 sequence numbers or
+    /// a hole in a reassembled TCP stream. `detail` names the range.
+    SequenceGap,
+    /// A live event arrived too late to be kept in timestamp order.
+    Late,
+}
+
+impl IssueKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::SequenceGap => "sequence_gap",
+            Self::Late => "late",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIssue {
+    pub packet_index: u64,
+    pub offset: usize,
+    pub kind: IssueKind,
+    pub detail: String,
+    /// The missing sequence numbers, for gaps in a natively sequenced feed.
+    pub gap: Option<GapRange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PcapIngestOutput {
+    pub events: Vec<Event>,
+    pub issues: Vec<ParseIssue>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct TopBook {
+    bid_px: i64,
+    bid_sz: i64,
+    ask_px: i64,
+    ask_sz: i64,
+}
+
+pub(crate) fn warn_issue(issue: &ParseIssue) {
+    warn!(
+        packet = issue.packet_index,
+        offset = issue.offset,
+        kind = issue.kind.as_str(),
+        detail = %issue.detail,
+        "pcap parse error"
+    );
+}
+
+/// Decodes mock ITCH frames, keeping top of book per symbol so add-orders
+/// become full quotes.
+pub(crate) struct ItchDecoder<'a> {
+    venue: &'a str,
+    framing: Framing,
+    mold: MoldSequencer,
+    books: HashMap<String, TopBook>,
+    pub(crate) packet_index: u64,
+    ingest_order: u64,
+}
+
+impl<'a> ItchDecoder<'a> {
+    pub(crate) fn new(venue: &'a str, framing: Framing) -> Self {
+        Self {
+            venue,
+            framing,
+            mold: MoldSequencer::default(),
+            books: HashMap::new(),
+            packet_index: 0,
+            ingest_order: 0,
+        }
+    }
+
+    pub(crate) fn issue(&self, offset: usize, kind: IssueKind, detail: String) -> ParseIssue {
+        ParseIssue {
+            packet_index: self.packet_index,
+            offset,
+            kind,
+            detail,
+            gap: None,
+        }
+    }
+
+    /// Decodes one captured frame into its events and issues, in message
+    /// order. Issue offsets are relative to the UDP payload.
+    pub(crate) fn decode(&mut self, frame: &[u8]) -> Vec<Result<PendingEvent, ParseIssue>> {
+        self.packet_index += 1;
+        let udp_payload = match extract_udp_payload(frame) {
+            Ok(payload) => payload,
+            Err((offset, detail)) => {
+                return vec![Err(self.issue(offset, IssueKind::Malformed, detail))]
+            }
+        };
+        match self.framing {
+            Framing::Raw => vec![self.decode_message(udp_payload, 0)],
+            Framing::MoldUdp64 => self.decode_mold(udp_payload),
+        }
+    }
+
+    fn decode_mold(&mut self, packet: &[u8]) -> Vec<Result<PendingEvent, ParseIssue>> {
+        let header = match MoldHeader::parse(packet) {
+            Ok(header) => header,
+            Err((offset, detail)) => {
+                return vec![Err(self.issue(offset, IssueKind::Malformed, detail))]
+            }
+        };
+        let mut out = Vec::with_capacity(usize::from(header.message_count()) + 1);
+        let (gap, seen) = self.mold.observe(&header);
+        if let Some(gap) = gap {
+            let range = GapRange {
+                stream: header.session_name(),
+                first: gap.first,
+                last: gap.last,
+                estimated_messages: gap.last - gap.first + 1,
+            };
+            let detail = format!(
+                "session {} missing sequence {}..={} ({} messages)",
+                range.stream, range.first, range.last, range.estimated_messages
+            );
+            out.push(Err(ParseIssue {
+                gap: Some(range),
+                ..self.issue(10, IssueKind::SequenceGap, detail)
+            }));
+        }
+
+        let mut offset = HEADER_LEN;
+        for i in 0..usize::from(header.message_count()) {
+            let Some(len) = packet.get(offset..offset + 2) else {
+                let detail = format!("truncated moldudp64 packet at message {i}");
+                out.push(Err(self.issue(offset, IssueKind::Malformed, detail)));
+                break;
+            };
+            let len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+            let start = offset + 2;
+            let Some(message) = packet.get(start..start + len) else {
+                let detail = format!("truncated moldudp64 message {i}");
+                out.push(Err(self.issue(offset, IssueKind::Malformed, detail)));
+                break;
+            };
+            offset = start + len;
+            if i >= seen {
+                let sequence = header.sequence + i as u64;
+                out.push(
+                    self.decode_message(message, start)
+                        .map(|event| PendingEvent {
+                            source_sequence: Some(sequence),
+                            ..event
+                        }),
+                );
+            }
+        }
+        out
+    }
+
+    /// Decodes one bare ITCH message; issues carry the current
+    /// `packet_index` and offsets from `base`.
+    pub(crate) fn decode_message(
+        &mut self,
+        data: &[u8],
+        base: usize,
+    ) -> Result<PendingEvent, ParseIssue> {
+        let msg = parse_message(data)
+            .map_err(|err| self.issue(base + err.offset, IssueKind::Malformed, err.detail))?;
+        let timestamp_ns = Timestamp::from_unsigned_nanos(msg.timestamp_ns()).map_err(|_| {
+            self.issue(
+                base,
+                IssueKind::Malformed,
+                String::from("timestamp out of range"),
+            )
+        })?;
+        self.ingest_order += 1;
+        let ingest_order = self.ingest_order;
+        Ok(match msg {
+            MockItchMessage::Trade {
+                symbol,
+                price_i64,
+                size_i64,
+                ..
+            } => PendingEvent {
+                timestamp_ns,
+                venue: self.venue.to_string(),
+                symbol,
+                payload: Payload::Trade {
+                    price_ticks: price_i64,
+                    size: size_i64,
+                },
+                ingest_order,
+                source_sequence: None,
+            },
+            MockItchMessage::AddOrder {
+                symbol,
+                side,
+                price_i64,
+                size_i64,
+                ..
+            } => {
+                let book = self.books.entry(symbol.clone()).or_default();
+                match side {
+                    Side::Bid => {
+                        book.bid_px = price_i64;
+                        book.bid_sz = size_i64;
+                    }
+                    Side::Ask => {
+                        book.ask_px = price_i64;
+                        book.ask_sz = size_i64;
+                    }
+                }
+                PendingEvent {
+                    timestamp_ns,
+                    venue: self.venue.to_string(),
+                    symbol,
+                    payload: Payload::Quote {
+                        bid_px: book.bid_px,
+                        bid_sz: book.bid_sz,
+                        ask_px: book.ask_px,
+                        ask_sz: book.ask_sz,
+                    },
+                    ingest_order,
+                    source_sequence: None,
+                }
+            }
+        })
+    }
+}
+
+/// IPv4 packet inside an Ethernet frame. `l4_offset` is where the
+/// transport header starts within the frame.
+struct Ipv4<'a> {
+    proto: u8,
+    src: [u8; 4],
+    dst: [u8; 4],
+    l4_offset: usize,
+    /// Transport header and payload, bounded by the IP total length unless
+    /// that is zero (segmentation offload) or longer than the capture.
+    l4: &'a [u8],
+}
+
+fn ipv4_packet(data: &[u8]) -> Result<Ipv4<'_>, (usize, String)> {
+    if data.len() < 14 {
+        return Err((0, String::from("short ethernet header")));
+    }
+    let ethertype = u16::from_be_bytes([data[12], data[13]]);
+    if ethertype != 0x0800 {
+        return Err((12, format!("unsupported ethertype 0x{ethertype:04x}")));
+    }
+
+    let ip_offset = 14;
+    if data.len() < ip_offset + 20 {
+        return Err((ip_offset, String::from("short ipv4 header")));
+    }
+
+    let version_ihl = data[ip_offset];
+    let version = version_ihl >> 4;
+    let ihl = (version_ihl & 0x0f) as usize * 4;
+    if version != 4 {
+        return Err((ip_offset, format!("unsupported ip version {version}")));
+    }
+    if ihl < 20 {
+        return Err((ip_offset, String::from("invalid ipv4 ihl")));
+    }
+    if data.len() < ip_offset + ihl {
+        return Err((ip_offset, String::from("truncated ipv4 header")));
+    }
+
+    let total_len = usize::from(u16::from_be_bytes([
+        data[ip_offset + 2],
+        data[ip_offset + 3],
+    ]));
+    let end = if total_len < ihl || ip_offset + total_len > data.len() {
+        data.len()
+    } else {
+        ip_offset + total_len
+    };
+    let mut src = [0u8; 4];
+    src.copy_from_slice(&data[ip_offset + 12..ip_offset + 16]);
+    let mut dst = [0u8; 4];
+    dst.copy_from_slice(&data[ip_offset + 16..ip_offset + 20]);
+    Ok(Ipv4 {
+        proto: data[ip_offset + 9],
+        src,
+        dst,
+        l4_offset: ip_offset + ihl,
+        l4: &data[ip_offset + ihl..end],
+    })
+}
+
+pub(crate) fn extract_udp_payload(data: &[u8]) -> Result<&[u8], (usize, String)> {
+    let ip = ipv4_packet(data)?;
+    if ip.proto != 17 {
+        return Err((14 + 9, format!("non-udp protocol {}", ip.proto)));
+    }
+
+    let udp_offset = ip.l4_offset;
+    if data.len() < udp_offset + 8 {
+        return Err((udp_offset, String::from("short udp header")));
+    }
+
+    let udp_len = u16::from_be_bytes([data[udp_offset + 4], data[udp_offset + 5]]) as usize;
+    if udp_len < 8 {
+        return Err((udp_offset + 4, String::from("invalid udp length")));
+    }
+    if data.len() < udp_offset + udp_len {
+        return Err((udp_offset + 4, String::from("truncated udp payload")));
+    }
+
+    Ok(&data[udp_offset + 8..udp_offset + udp_len])
+}
+
+/// One direction of a TCP connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct TcpFlow {
+    pub(crate) src: ([u8; 4], u16),
+    pub(crate) dst: ([u8; 4], u16),
+}
+
+impl std::fmt::Display for TcpFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d] = self.src.0;
+        let [w, x, y, z] = self.dst.0;
+        write!(
+            f,
+            "{a}.{b}.{c}.{d}:{}->{w}.{x}.{y}.{z}:{}",
+            self.src.1, self.dst.1
+        )
+    }
+}
+
+pub(crate) struct TcpSegment<'a> {
+    pub(crate) flow: TcpFlow,
+    pub(crate) seq: u32,
+    pub(crate) syn: bool,
+    pub(crate) payload: &'a [u8],
+}
+
+pub(crate) fn extract_tcp_segment(data: &[u8]) -> Result<TcpSegment<'_>, (usize, String)> {
+    let ip = ipv4_packet(data)?;
+    if ip.proto != 6 {
+        return Err((14 + 9, format!("non-tcp protocol {}", ip.proto)));
+    }
+    let tcp = ip.l4;
+    if tcp.len() < 20 {
+        return Err((ip.l4_offset, String::from("short tcp header")));
+    }
+    let header_len = usize::from(tcp[12] >> 4) * 4;
+    if header_len < 20 || tcp.len() < header_len {
+        return Err((ip.l4_offset + 12, String::from("invalid tcp data offset")));
+    }
+    Ok(TcpSegment {
+        flow: TcpFlow {
+            src: (ip.src, u16::from_be_bytes([tcp[0], tcp[1]])),
+            dst: (ip.dst, u16::from_be_bytes([tcp[2], tcp[3]])),
+        },
+        seq: u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]),
+        syn: tcp[13] & 0x02 != 0,
+        payload: &tcp[header_len..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaps::GapReport;
+    use crate::gen_pcap::{build_udp_frame, trade_payload};
+    use crate::mold::encode_packet;
+
+    #[test]
+    fn rejects_short_ethernet() {
+        let err = extract_udp_payload(&[1, 2, 3]).expect_err("must fail");
+        assert_eq!(err.0, 0);
+    }
+
+    fn mold_frame(sequence: u64, prices: &[i64]) -> Vec<u8> {
+        let messages = prices
+            .iter()
+            .map(|&px| trade_payload(1_700_000_000_000_000_000, "AAPL", px, 1))
+            .collect::<Vec<_>>();
+        build_udp_frame(0, &encode_packet(b"S1        ", sequence, &messages))
+    }
+
+    fn prices(items: &[Result<PendingEvent, ParseIssue>]) -> Vec<i64> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                Ok(PendingEvent {
+                    payload: Payload::Trade { price_ticks, .. },
+                    ..
+                }) => Some(*price_ticks),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mold_packets_unwrap_and_report_gaps() {
+        let mut decoder = ItchDecoder::new("X", Framing::MoldUdp64);
+        let first = decoder.decode(&mold_frame(1, &[100, 101, 102]));
+        assert_eq!(prices(&first), vec![100, 101, 102]);
+
+        let after_gap = decoder.decode(&mold_frame(6, &[106]));
+        assert_eq!(prices(&after_gap), vec![106]);
+        let issue = after_gap[0].as_ref().expect_err("gap");
+        assert_eq!(issue.kind, IssueKind::SequenceGap);
+        assert_eq!(issue.packet_index, 2);
+        assert!(issue.detail.contains("4..=5"), "{}", issue.detail);
+        let report = GapReport::from_issues(std::slice::from_ref(issue));
+        assert_eq!((report.gaps, report.estimated_messages), (1, 2));
+        assert_eq!(report.ranges[0].stream, "S1");
+        assert_eq!(report.ranges[0].sequences(), 2);
+
+        // 6 was already delivered; only 7 is new.
+        let retransmit = decoder.decode(&mold_frame(6, &[106, 107]));
+        assert_eq!(prices(&retransmit), vec![107]);
+        let source = retransmit[0].as_ref().map(|e| e.source_sequence);
+        assert_eq!(source.expect("event"), Some(7));
+        assert!(retransmit.iter().all(Result::is_ok));
+
+        let mut truncated = mold_frame(8, &[108, 109]);
+        truncated.truncate(truncated.len() - 4);
+        let frame = build_udp_frame(0, &truncated[42..]);
+        let items = decoder.decode(&frame);
+        assert_eq!(prices(&items), vec![108]);
+        let issue = items[1].as_ref().expect_err("truncated");
+        assert_eq!(issue.kind, IssueKind::Malformed);
+    }
+
+    #[test]
+    fn generated_mold_capture_matches_raw_feed() {
+        use crate::gen_pcap::{generate_capture, CaptureFormat, ClockModel};
+
+        let dir = std::env::temp_dir();
+        let symbols = [String::from("AAPL"), String::from("MSFT")];
+        let mut outputs = Vec::new();
+        for framing in [Framing::Raw, Framing::MoldUdp64] {
+            let path = dir.join(format!(
+                "md_replay_mold_{}_{:?}.pcapng",
+                std::process::id(),
+                framing
+            ));
+            generate_capture(
+                &path,
+                &symbols,
+                400,
+                11,
+                &ClockModel::default(),
+                CaptureFormat::Pcapng,
+                framing,
+            )
+            .expect("generate");
+            outputs.push(crate::ingest_pcap_with(&path, "X", framing).expect("ingest"));
+        }
+        let (raw, mold) = (&outputs[0], &outputs[1]);
+        // Only the Mold framing numbers its messages.
+        assert!(raw.events.iter().all(|e| e.source_sequence.is_none()));
+        assert!(mold.events.iter().all(|e| e.source_sequence.is_some()));
+        let unsequenced = mold
+            .events
+            .iter()
+            .map(|e| Event {
+                source_sequence: None,
+                ..e.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(raw.events, unsequenced);
+        assert_eq!(raw.issues.len(), mold.issues.len());
+        assert!(mold
+            .issues
+            .iter()
+            .all(|issue| issue.kind == IssueKind::Malformed));
+    }
+}
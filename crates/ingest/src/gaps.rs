@@ -0,0 +1,46 @@
+//! Machine-readable report of the native sequence numbers a capture is
+//! missing, for feeds that carry them (MoldUDP64, MDP3).
+
+use crate::frames::ParseIssue;
+use serde::Serialize;
+
+/// Sequence numbers `first..=last` of `stream` never arrived.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GapRange {
+    /// MoldUDP64 session name, or `mdp3` for an MDP3 channel.
+    pub stream: String,
+    pub first: u64,
+    pub last: u64,
+    /// Messages lost. Exact for MoldUDP64, which numbers messages; MDP3
+    /// numbers packets, so this scales by the messages per packet seen so
+    /// far.
+    pub estimated_messages: u64,
+}
+
+impl GapRange {
+    pub fn sequences(&self) -> u64 {
+        self.last.saturating_sub(self.first) + 1
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GapReport {
+    pub gaps: u64,
+    pub estimated_messages: u64,
+    pub ranges: Vec<GapRange>,
+}
+
+impl GapReport {
+    /// Collects the gap ranges among `issues`, in capture order.
+    pub fn from_issues(issues: &[ParseIssue]) -> Self {
+        let ranges = issues
+            .iter()
+            .filter_map(|issue| issue.gap.clone())
+            .collect::<Vec<_>>();
+        Self {
+            gaps: ranges.len() as u64,
+            estimated_messages: ranges.iter().map(|r| r.estimated_messages).sum(),
+            ranges,
+        }
+    }
+}
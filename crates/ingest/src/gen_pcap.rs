@@ -1,3 +1,5 @@
+use crate::mold;
+use crate::Framing;
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 use std::fs::File;
@@ -5,6 +7,10 @@ use std::io::{BufWriter, Write};
 use std::path::Path;
 use thiserror::Error;
 
+const CLOCK_SEED_SALT: u64 = 0x636c_6f63_6b5f_6d64;
+const BATCH_SEED_SALT: u64 = 0x6d6f_6c64_5f62_6174;
+const MOLD_SESSION: &[u8; 10] = b"MDREPLAY01";
+
 #[derive(Debug, Error)]
 pub enum GenPcapError {
     #[error("io error: {0}")]
@@ -13,23 +19,114 @@ pub enum GenPcapError {
     EmptySymbols,
 }
 
+/// Capture-clock model applied to pcap record timestamps. The exchange
+/// timestamp inside each ITCH payload is left untouched, so the two clocks
+/// diverge by `offset + drift * elapsed + jitter`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClockModel {
+    pub offset_ns: i64,
+    pub drift_ppm: f64,
+    pub jitter_ns: u64,
+}
+
+impl ClockModel {
+    fn capture_ts(&self, exchange_ts: u64, first_ts: u64, rng: &mut ChaCha8Rng) -> u64 {
+        let elapsed = exchange_ts.saturating_sub(first_ts) as f64;
+        let drift = (elapsed * self.drift_ppm / 1_000_000.0) as i128;
+        let jitter = if self.jitter_ns == 0 {
+            0
+        } else {
+            rng.gen_range(0..=self.jitter_ns) as i128
+        };
+        let ts = exchange_ts as i128 + self.offset_ns as i128 + drift + jitter;
+        ts.clamp(0, u64::MAX as i128) as u64
+    }
+}
+
+/// Container written by the generator. pcapng keeps nanosecond capture
+/// timestamps; classic pcap truncates them to microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptureFormat {
+    #[default]
+    Pcap,
+    Pcapng,
+}
+
+impl std::str::FromStr for CaptureFormat {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_ascii_lowercase().as_str() {
+            "pcap" => Ok(Self::Pcap),
+            "pcapng" => Ok(Self::Pcapng),
+            other => Err(format!(
+                "unknown capture format {other}, expected pcap|pcapng"
+            )),
+        }
+    }
+}
+
 pub fn generate_pcap(
     out: &Path,
     symbols: &[String],
     events: usize,
     seed: u64,
+) -> Result<(), GenPcapError> {
+    generate_pcap_with_clock(out, symbols, events, seed, &ClockModel::default())
+}
+
+pub fn generate_pcap_with_clock(
+    out: &Path,
+    symbols: &[String],
+    events: usize,
+    seed: u64,
+    clock: &ClockModel,
+) -> Result<(), GenPcapError> {
+    generate_capture(
+        out,
+        symbols,
+        events,
+        seed,
+        clock,
+        CaptureFormat::Pcap,
+        Framing::Raw,
+    )
+}
+
+/// Same feed in either container; the packets do not depend on `format`.
+/// With MoldUDP64 framing the same messages go out one to four per packet,
+/// sequenced from 1 without gaps.
+pub fn generate_capture(
+    out: &Path,
+    symbols: &[String],
+    events: usize,
+    seed: u64,
+    clock: &ClockModel,
+    format: CaptureFormat,
+    framing: Framing,
 ) -> Result<(), GenPcapError> {
     if symbols.is_empty() {
         return Err(GenPcapError::EmptySymbols);
     }
 
     let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    // Separate stream so the clock model never perturbs the generated events.
+    let mut clock_rng = ChaCha8Rng::seed_from_u64(seed ^ CLOCK_SEED_SALT);
+    let mut batch_rng = ChaCha8Rng::seed_from_u64(seed ^ BATCH_SEED_SALT);
     let file = File::create(out)?;
     let mut w = BufWriter::new(file);
 
-    write_global_header(&mut w)?;
+    match format {
+        CaptureFormat::Pcap => write_global_header(&mut w)?,
+        CaptureFormat::Pcapng => write_pcapng_header(&mut w)?,
+    }
 
-    let mut ts_ns = 1_700_000_000_000_000_000u64;
+    let first_ts = 1_700_000_000_000_000_000u64;
+    let mut ts_ns = first_ts;
+    let mut batch = Vec::new();
+    let mut batch_size = batch_rng.gen_range(1usize..=4usize);
+    let mut next_sequence = 1u64;
+    let mut packets = 0u16;
     for i in 0..events {
         ts_ns = ts_ns.saturating_add(rng.gen_range(200u64..5_000u64));
         if i % 97 == 0 {
@@ -57,8 +154,27 @@ pub fn generate_pcap(
             )
         };
 
-        let frame = build_udp_frame(i as u16, &payload);
-        write_packet(&mut w, ts_ns, &frame)?;
+        let capture_ts = clock.capture_ts(ts_ns, first_ts, &mut clock_rng);
+        let datagram = match framing {
+            Framing::Raw => payload,
+            Framing::MoldUdp64 => {
+                batch.push(payload);
+                if batch.len() < batch_size && i + 1 < events {
+                    continue;
+                }
+                let packet = mold::encode_packet(MOLD_SESSION, next_sequence, &batch);
+                next_sequence += batch.len() as u64;
+                batch.clear();
+                batch_size = batch_rng.gen_range(1usize..=4usize);
+                packet
+            }
+        };
+        let frame = build_udp_frame(packets, &datagram);
+        packets = packets.wrapping_add(1);
+        match format {
+            CaptureFormat::Pcap => write_packet(&mut w, capture_ts, &frame)?,
+            CaptureFormat::Pcapng => write_pcapng_packet(&mut w, capture_ts, &frame)?,
+        }
     }
 
     w.flush()?;
@@ -88,6 +204,49 @@ fn write_packet<W: Write>(w: &mut W, ts_ns: u64, data: &[u8]) -> Result<(), std:
     Ok(())
 }
 
+/// Section header plus one Ethernet interface with `if_tsresol = 9`.
+pub(crate) fn write_pcapng_header<W: Write>(w: &mut W) -> Result<(), std::io::Error> {
+    w.write_all(&0x0A0D_0D0Au32.to_le_bytes())?;
+    w.write_all(&28u32.to_le_bytes())?;
+    w.write_all(&0x1A2B_3C4Du32.to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())?;
+    w.write_all(&(-1i64).to_le_bytes())?;
+    w.write_all(&28u32.to_le_bytes())?;
+
+    w.write_all(&1u32.to_le_bytes())?;
+    w.write_all(&32u32.to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())?;
+    w.write_all(&65_535u32.to_le_bytes())?;
+    w.write_all(&9u16.to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?;
+    w.write_all(&[9, 0, 0, 0])?;
+    w.write_all(&0u32.to_le_bytes())?;
+    w.write_all(&32u32.to_le_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_pcapng_packet<W: Write>(
+    w: &mut W,
+    ts_ns: u64,
+    data: &[u8],
+) -> Result<(), std::io::Error> {
+    let padded = data.len().next_multiple_of(4);
+    let total = (32 + padded) as u32;
+    w.write_all(&6u32.to_le_bytes())?;
+    w.write_all(&total.to_le_bytes())?;
+    w.write_all(&0u32.to_le_bytes())?;
+    w.write_all(&((ts_ns >> 32) as u32).to_le_bytes())?;
+    w.write_all(&(ts_ns as u32).to_le_bytes())?;
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(data)?;
+    w.write_all(&vec![0u8; padded - data.len()])?;
+    w.write_all(&total.to_le_bytes())?;
+    Ok(())
+}
+
 fn add_order_payload(ts_ns: u64, symbol: &str, side: u8, price: i64, size: i64) -> Vec<u8> {
     let mut v = Vec::with_capacity(37);
     v.extend_from_slice(&ts_ns.to_be_bytes());
@@ -99,7 +258,7 @@ fn add_order_payload(ts_ns: u64, symbol: &str, side: u8, price: i64, size: i64)
     v
 }
 
-fn trade_payload(ts_ns: u64, symbol: &str, price: i64, size: i64) -> Vec<u8> {
+pub(crate) fn trade_payload(ts_ns: u64, symbol: &str, price: i64, size: i64) -> Vec<u8> {
     let mut v = Vec::with_capacity(36);
     v.extend_from_slice(&ts_ns.to_be_bytes());
     v.extend_from_slice(&2u32.to_be_bytes());
@@ -124,7 +283,7 @@ fn pack_symbol(symbol: &str) -> [u8; 8] {
     out
 }
 
-fn build_udp_frame(ident: u16, payload: &[u8]) -> Vec<u8> {
+pub(crate) fn build_udp_frame(ident: u16, payload: &[u8]) -> Vec<u8> {
     let eth_len = 14usize;
     let ip_len = 20usize;
     let udp_len = 8usize;
@@ -172,3 +331,48 @@ fn ipv4_checksum(header: &[u8]) -> u16 {
     }
     !(sum as u16)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture_and_exchange_ts(path: &Path) -> Vec<(u64, u64)> {
+        let bytes = std::fs::read(path).expect("read pcap");
+        let mut out = Vec::new();
+        let mut pos = 24;
+        while pos + 16 <= bytes.len() {
+            let sec = u32::from_le_bytes(bytes[pos..pos + 4].try_into().expect("sec")) as u64;
+            let usec = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().expect("usec")) as u64;
+            let len =
+                u32::from_le_bytes(bytes[pos + 8..pos + 12].try_into().expect("len")) as usize;
+            let data = &bytes[pos + 16..pos + 16 + len];
+            pos += 16 + len;
+            let payload = &data[42..];
+            if payload.len() < 36 {
+                continue;
+            }
+            let exchange = u64::from_be_bytes(payload[..8].try_into().expect("ts"));
+            out.push((sec * 1_000_000_000 + usec * 1_000, exchange));
+        }
+        out
+    }
+
+    #[test]
+    fn clock_offset_shifts_capture_timestamps() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("md_replay_gen_clock_{}.pcap", std::process::id()));
+        let clock = ClockModel {
+            offset_ns: 250_000,
+            drift_ppm: 0.0,
+            jitter_ns: 0,
+        };
+        generate_pcap_with_clock(&path, &[String::from("AAPL")], 50, 3, &clock).expect("generate");
+
+        let pairs = capture_and_exchange_ts(&path);
+        assert!(!pairs.is_empty());
+        for (capture, exchange) in pairs {
+            let skew = capture as i64 - (exchange - exchange % 1_000) as i64;
+            assert_eq!(skew, 250_000);
+        }
+    }
+}
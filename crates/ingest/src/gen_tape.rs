@@ -0,0 +1,360 @@
+use md_core::{assign_sequences, Event, Payload, PendingEvent, Timestamp};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::str::FromStr;
+use thiserror::Error;
+
+const FIRST_TS: i64 = 1_700_000_000_000_000_000;
+
+#[derive(Debug, Error)]
+pub enum GenTapeError {
+    #[error("symbols list is empty")]
+    EmptySymbols,
+    #[error("venues list is empty")]
+    EmptyVenues,
+    #[error("invalid venue spec {0:?}, expected VENUE or VENUE:LATENCY_NS")]
+    InvalidVenue(String),
+    #[error("{name} must be within 0..=1, got {value}")]
+    InvalidRate { name: &'static str, value: f64 },
+}
+
+/// One venue of the tape. Every price change reaches the venue
+/// `latency_ns` after it happened, so slower venues quote stale prices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueFeed {
+    pub venue: String,
+    pub latency_ns: u64,
+}
+
+impl FromStr for VenueFeed {
+    type Err = GenTapeError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || GenTapeError::InvalidVenue(raw.to_string());
+        let (venue, latency_ns) = match raw.split_once(':') {
+            Some((venue, latency)) => (venue, latency.trim().parse().map_err(|_| invalid())?),
+            None => (raw, 0),
+        };
+        let venue = venue.trim();
+        if venue.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            venue: venue.to_string(),
+            latency_ns,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TapeConfig {
+    pub symbols: Vec<String>,
+    pub venues: Vec<VenueFeed>,
+    /// Number of underlying price changes; each one is quoted on every venue.
+    pub updates: usize,
+    pub seed: u64,
+    /// Chance per venue and update that a crossed episode starts.
+    pub crossed_rate: f64,
+    /// Quotes each crossed episode lasts on the offending venue.
+    pub crossed_quotes: usize,
+    /// Chance per update that one venue prints a trade.
+    pub trade_rate: f64,
+    /// Extra uniform delay on top of each venue's latency.
+    pub jitter_ns: u64,
+}
+
+impl Default for TapeConfig {
+    fn default() -> Self {
+        Self {
+            symbols: vec![String::from("AAPL")],
+            venues: vec![
+                VenueFeed {
+                    venue: String::from("XNAS"),
+                    latency_ns: 0,
+                },
+                VenueFeed {
+                    venue: String::from("ARCX"),
+                    latency_ns: 150_000,
+                },
+                VenueFeed {
+                    venue: String::from("BATS"),
+                    latency_ns: 400_000,
+                },
+            ],
+            updates: 1_000,
+            seed: 42,
+            crossed_rate: 0.005,
+            crossed_quotes: 3,
+            trade_rate: 0.2,
+            jitter_ns: 0,
+        }
+    }
+}
+
+/// Ground truth for a deliberately crossed stretch: from `start_ns` to
+/// `end_ns` inclusive the venue's bid sat above any undisturbed ask of the
+/// other venues (or its ask below their bids).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossedEpisode {
+    pub venue: String,
+    pub symbol: String,
+    pub start_ns: Timestamp,
+    pub end_ns: Timestamp,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntheticTape {
+    pub events: Vec<Event>,
+    pub crossed: Vec<CrossedEpisode>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Crossing {
+    remaining: usize,
+    up: bool,
+    start_ns: i64,
+}
+
+/// Generates a multi-venue tape where all venues quote the same per-symbol
+/// fair price, each with its own spread and feed latency. Same config,
+/// same tape.
+pub fn generate_tape(cfg: &TapeConfig) -> Result<SyntheticTape, GenTapeError> {
+    if cfg.symbols.is_empty() {
+        return Err(GenTapeError::EmptySymbols);
+    }
+    if cfg.venues.is_empty() {
+        return Err(GenTapeError::EmptyVenues);
+    }
+    for (name, value) in [
+        ("crossed_rate", cfg.crossed_rate),
+        ("trade_rate", cfg.trade_rate),
+    ] {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(GenTapeError::InvalidRate { name, value });
+        }
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed);
+    let mut fair = cfg
+        .symbols
+        .iter()
+        .map(|_| rng.gen_range(10_000i64..50_000i64))
+        .collect::<Vec<_>>();
+    let base_half_spread = cfg
+        .venues
+        .iter()
+        .map(|_| rng.gen_range(1i64..=3i64))
+        .collect::<Vec<_>>();
+    let mut crossings = vec![vec![Crossing::default(); cfg.symbols.len()]; cfg.venues.len()];
+    // Quotes only cross if someone else is quoting.
+    let crossed_rate = if cfg.venues.len() > 1 {
+        cfg.crossed_rate
+    } else {
+        0.0
+    };
+
+    let mut pending = Vec::with_capacity(cfg.updates * (cfg.venues.len() + 1));
+    let mut crossed = Vec::new();
+    let mut ingest_order = 0u64;
+    let mut ts = FIRST_TS;
+    for _ in 0..cfg.updates {
+        ts = ts.saturating_add(rng.gen_range(200i64..5_000i64));
+        let s = rng.gen_range(0..cfg.symbols.len());
+        fair[s] = (fair[s] + rng.gen_range(-3i64..=3i64)).max(10);
+        let symbol = &cfg.symbols[s];
+
+        let mut quoted = Vec::with_capacity(cfg.venues.len());
+        for (v, feed) in cfg.venues.iter().enumerate() {
+            let jitter = if cfg.jitter_ns == 0 {
+                0
+            } else {
+                rng.gen_range(0..=cfg.jitter_ns)
+            };
+            let seen_ns = ts.saturating_add(feed.latency_ns.saturating_add(jitter) as i64);
+            let half_spread = base_half_spread[v] + rng.gen_range(0i64..=1i64);
+
+            let crossing = &mut crossings[v][s];
+            if crossing.remaining == 0 && rng.gen_bool(crossed_rate) {
+                *crossing = Crossing {
+                    remaining: cfg.crossed_quotes.max(1),
+                    up: rng.gen_bool(0.5),
+                    start_ns: seen_ns,
+                };
+            }
+            let (bid_px, ask_px) = if crossing.remaining > 0 {
+                // Clear the widest possible spread of every other venue.
+                let shift = 5 + rng.gen_range(0i64..=2i64);
+                let mid = if crossing.up {
+                    fair[s] + shift + half_spread
+                } else {
+                    fair[s] - shift - half_spread
+                };
+                crossing.remaining -= 1;
+                if crossing.remaining == 0 {
+                    crossed.push(CrossedEpisode {
+                        venue: feed.venue.clone(),
+                        symbol: symbol.clone(),
+                        start_ns: Timestamp::from_nanos(crossing.start_ns),
+                        end_ns: Timestamp::from_nanos(seen_ns),
+                    });
+                }
+                (mid - half_spread, mid + half_spread)
+            } else {
+                (fair[s] - half_spread, fair[s] + half_spread)
+            };
+
+            ingest_order += 1;
+            pending.push(PendingEvent {
+                timestamp_ns: Timestamp::from_nanos(seen_ns),
+                venue: feed.venue.clone(),
+                symbol: symbol.clone(),
+                payload: Payload::Quote {
+                    bid_px,
+                    bid_sz: rng.gen_range(1i64..500i64),
+                    ask_px,
+                    ask_sz: rng.gen_range(1i64..500i64),
+                },
+                ingest_order,
+                source_sequence: None,
+            });
+            quoted.push((seen_ns, bid_px, ask_px));
+        }
+
+        if rng.gen_bool(cfg.trade_rate) {
+            let v = rng.gen_range(0..cfg.venues.len());
+            let (seen_ns, bid_px, ask_px) = quoted[v];
+            ingest_order += 1;
+            pending.push(PendingEvent {
+                timestamp_ns: Timestamp::from_nanos(seen_ns),
+                venue: cfg.venues[v].venue.clone(),
+                symbol: symbol.clone(),
+                payload: Payload::Trade {
+                    price_ticks: if rng.gen_bool(0.5) { bid_px } else { ask_px },
+                    size: rng.gen_range(1i64..500i64),
+                },
+                ingest_order,
+                source_sequence: None,
+            });
+        }
+    }
+
+    Ok(SyntheticTape {
+        events: assign_sequences(pending),
+        crossed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venues(specs: &[&str]) -> Vec<VenueFeed> {
+        specs
+            .iter()
+            .map(|s| s.parse().expect("venue spec"))
+            .collect()
+    }
+
+    fn quotes_of(events: &[Event], venue: &str) -> Vec<(i64, i64, i64)> {
+        events
+            .iter()
+            .filter(|e| e.venue == venue)
+            .filter_map(|e| match e.payload {
+                Payload::Quote { bid_px, ask_px, .. } => {
+                    Some((e.timestamp_ns.as_nanos(), bid_px, ask_px))
+                }
+                Payload::Trade { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn venue_spec_parses_latency() {
+        assert_eq!(
+            "ARCX:1500".parse::<VenueFeed>().expect("spec"),
+            VenueFeed {
+                venue: String::from("ARCX"),
+                latency_ns: 1500
+            }
+        );
+        assert_eq!("XNAS".parse::<VenueFeed>().expect("spec").latency_ns, 0);
+        assert!("XNAS:soon".parse::<VenueFeed>().is_err());
+        assert!(":10".parse::<VenueFeed>().is_err());
+    }
+
+    #[test]
+    fn same_seed_same_tape() {
+        let cfg = TapeConfig {
+            updates: 200,
+            ..TapeConfig::default()
+        };
+        let a = generate_tape(&cfg).expect("tape");
+        let b = generate_tape(&cfg).expect("tape");
+        assert_eq!(a.events, b.events);
+        assert_eq!(a.crossed, b.crossed);
+    }
+
+    #[test]
+    fn latency_offsets_delay_each_venue() {
+        let cfg = TapeConfig {
+            venues: venues(&["A", "B:1000"]),
+            updates: 100,
+            crossed_rate: 0.0,
+            trade_rate: 0.0,
+            ..TapeConfig::default()
+        };
+        let tape = generate_tape(&cfg).expect("tape");
+        let a = quotes_of(&tape.events, "A");
+        let b = quotes_of(&tape.events, "B");
+        assert_eq!(a.len(), 100);
+        assert_eq!(b.len(), 100);
+        for (qa, qb) in a.iter().zip(&b) {
+            assert_eq!(qb.0 - qa.0, 1000);
+        }
+        assert!(tape
+            .events
+            .windows(2)
+            .all(|w| w[0].timestamp_ns <= w[1].timestamp_ns));
+    }
+
+    #[test]
+    fn crossed_episodes_cross_the_other_venue() {
+        let cfg = TapeConfig {
+            venues: venues(&["A", "B"]),
+            updates: 400,
+            crossed_rate: 0.05,
+            crossed_quotes: 2,
+            trade_rate: 0.0,
+            ..TapeConfig::default()
+        };
+        let tape = generate_tape(&cfg).expect("tape");
+        assert!(!tape.crossed.is_empty());
+
+        let a = quotes_of(&tape.events, "A");
+        let b = quotes_of(&tape.events, "B");
+        let crossing = |venue: &str, ts: i64| {
+            tape.crossed.iter().any(|e| {
+                e.venue == venue && e.start_ns.as_nanos() <= ts && ts <= e.end_ns.as_nanos()
+            })
+        };
+        let mut checked = 0;
+        for episode in &tape.crossed {
+            let (own, other, other_venue) = if episode.venue == "A" {
+                (&a, &b, "B")
+            } else {
+                (&b, &a, "A")
+            };
+            let start = episode.start_ns.as_nanos();
+            let i = own.iter().position(|q| q.0 == start).expect("start quote");
+            // Both venues crossing the same way at once need not cross each other.
+            if crossing(other_venue, other[i].0) {
+                continue;
+            }
+            let (_, bid, ask) = own[i];
+            let (_, other_bid, other_ask) = other[i];
+            assert!(bid > other_ask || ask < other_bid);
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::IngestError;
+use reqwest::{Certificate, Client, Proxy, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+const DEFAULT_USER_AGENT: &str = "md-replay/0.1";
+
+/// HTTP client settings shared by the network providers. Loaded from a TOML
+/// file and then overridden by `MD_REPLAY_HTTP_PROXY`, `MD_REPLAY_CA_CERT`,
+/// `MD_REPLAY_HTTP_TIMEOUT_SECS` and `MD_REPLAY_USER_AGENT`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpOptions {
+    pub proxy: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    pub timeout_secs: Option<u64>,
+    pub user_agent: Option<String>,
+}
+
+impl HttpOptions {
+    pub fn from_toml_str(raw: &str) -> Result<Self, IngestError> {
+        toml::from_str(raw).map_err(|e| IngestError::Parse(format!("http config: {e}")))
+    }
+
+    pub fn with_env(self) -> Result<Self, IngestError> {
+        self.with_env_from(|key| std::env::var(key).ok())
+    }
+
+    fn with_env_from(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, IngestError> {
+        if let Some(proxy) = lookup("MD_REPLAY_HTTP_PROXY") {
+            self.proxy = Some(proxy);
+        }
+        if let Some(path) = lookup("MD_REPLAY_CA_CERT") {
+            self.ca_cert = Some(PathBuf::from(path));
+        }
+        if let Some(raw) = lookup("MD_REPLAY_HTTP_TIMEOUT_SECS") {
+            let secs = raw.trim().parse().map_err(|_| {
+                IngestError::Parse(format!("invalid MD_REPLAY_HTTP_TIMEOUT_SECS {raw:?}"))
+            })?;
+            self.timeout_secs = Some(secs);
+        }
+        if let Some(agent) = lookup("MD_REPLAY_USER_AGENT") {
+            self.user_agent = Some(agent);
+        }
+        Ok(self)
+    }
+
+    pub fn build_client(&self) -> Result<Client, IngestError> {
+        let mut builder =
+            Client::builder().user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        if let Some(path) = &self.ca_cert {
+            let pem = std::fs::read(path)?;
+            for cert in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(secs) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        builder.build().map_err(IngestError::from)
+    }
+}
+
+/// Retries for providers that throttle: 429 and 5xx responses, timeouts
+/// and dropped connections are retried after a delay that doubles each
+/// time, or after the server's `Retry-After` when it sends one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry `attempt`, counted from 0.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// Allows at most `limit` acquisitions in any `period`.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    provider: &'static str,
+    limit: usize,
+    period: Duration,
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(provider: &'static str, limit: u32, period: Duration) -> Self {
+        Self {
+            provider,
+            limit: limit.max(1) as usize,
+            period,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Spaces acquisitions evenly, `per_second` of them a second.
+    pub(crate) fn per_second(provider: &'static str, per_second: f64) -> Self {
+        Self::new(provider, 1, Duration::from_secs_f64(1.0 / per_second))
+    }
+
+    pub(crate) async fn acquire(&mut self) {
+        if self.recent.len() >= self.limit {
+            if let Some(oldest) = self.recent.pop_front() {
+                let ready = oldest + self.period;
+                if ready > Instant::now() {
+                    info!(
+                        provider = self.provider,
+                        wait_ms = (ready - Instant::now()).as_millis() as u64,
+                        "rate limit, waiting"
+                    );
+                    tokio::time::sleep_until(ready).await;
+                }
+            }
+        }
+        self.recent.push_back(Instant::now());
+    }
+}
+
+/// GETs `url` and returns the body, retrying as `policy` allows. Every
+/// attempt, retries included, waits its turn on `limiter`.
+pub(crate) async fn get_text(
+    client: &Client,
+    url: Url,
+    policy: &RetryPolicy,
+    limiter: Option<&tokio::sync::Mutex<RateLimiter>>,
+) -> Result<String, IngestError> {
+    let mut attempt = 0;
+    loop {
+        if let Some(limiter) = limiter {
+            limiter.lock().await.acquire().await;
+        }
+        let (err, retry_after) = match client.get(url.clone()).send().await {
+            Ok(response) => {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok())
+                    .map(Duration::from_secs);
+                match response.error_for_status() {
+                    Ok(response) => return response.text().await.map_err(IngestError::from),
+                    Err(err) if err.status().is_some_and(retryable) => (err, retry_after),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Err(err) if err.is_timeout() || err.is_connect() => (err, None),
+            Err(err) => return Err(err.into()),
+        };
+        if attempt >= policy.max_retries {
+            return Err(err.into());
+        }
+        let delay = retry_after.unwrap_or_else(|| policy.backoff(attempt));
+        warn!(
+            url = %url,
+            error = %err,
+            retry_in_ms = delay.as_millis() as u64,
+            "request failed, retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides_file_settings() {
+        let file = HttpOptions::from_toml_str(
+            "proxy = \"http://file:3128\"\ntimeout_secs = 30\nuser_agent = \"ua\"\n",
+        )
+        .expect("parse");
+        let opts = file
+            .with_env_from(|key| match key {
+                "MD_REPLAY_HTTP_PROXY" => Some(String::from("http://env:8080")),
+                "MD_REPLAY_HTTP_TIMEOUT_SECS" => Some(String::from("5")),
+                _ => None,
+            })
+            .expect("env");
+        assert_eq!(opts.proxy.as_deref(), Some("http://env:8080"));
+        assert_eq!(opts.timeout_secs, Some(5));
+        assert_eq!(opts.user_agent.as_deref(), Some("ua"));
+        assert!(opts.build_client().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_timeout() {
+        assert!(HttpOptions::from_toml_str("proxi = \"x\"\n").is_err());
+        let err = HttpOptions::default()
+            .with_env_from(|key| (key == "MD_REPLAY_HTTP_TIMEOUT_SECS").then(|| String::from("x")));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_retries: 8,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let delays = (0..5)
+            .map(|n| policy.backoff(n).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limiter_spaces_requests_per_period() {
+        let mut limiter = RateLimiter::new("test", 2, Duration::from_secs(60));
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+
+        let mut limiter = RateLimiter::per_second("test", 4.0);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+}
@@ -23,6 +23,15 @@ pub enum MockItchMessage {
     },
 }
 
+impl MockItchMessage {
+    pub fn timestamp_ns(&self) -> u64 {
+        match self {
+            MockItchMessage::AddOrder { timestamp_ns, .. }
+            | MockItchMessage::Trade { timestamp_ns, .. } => *timestamp_ns,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Error, PartialEq, Eq)]
 #[error("{detail} at byte offset {offset}")]
 pub struct ItchParseError {
@@ -117,29 +126,29 @@ impl<'a> Reader<'a> {
         Ok(out)
     }
 
+    fn take_array<const N: usize>(
+        &mut self,
+        field_offset: usize,
+    ) -> Result<[u8; N], ItchParseError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N, field_offset)?);
+        Ok(out)
+    }
+
     fn read_u8(&mut self, field_offset: usize) -> Result<u8, ItchParseError> {
         Ok(self.take(1, field_offset)?[0])
     }
 
     fn read_u32_be(&mut self, field_offset: usize) -> Result<u32, ItchParseError> {
-        let bytes = self.take(4, field_offset)?;
-        Ok(u32::from_be_bytes(
-            bytes.try_into().expect("len checked for u32"),
-        ))
+        Ok(u32::from_be_bytes(self.take_array(field_offset)?))
     }
 
     fn read_u64_be(&mut self, field_offset: usize) -> Result<u64, ItchParseError> {
-        let bytes = self.take(8, field_offset)?;
-        Ok(u64::from_be_bytes(
-            bytes.try_into().expect("len checked for u64"),
-        ))
+        Ok(u64::from_be_bytes(self.take_array(field_offset)?))
     }
 
     fn read_i64_be(&mut self, field_offset: usize) -> Result<i64, ItchParseError> {
-        let bytes = self.take(8, field_offset)?;
-        Ok(i64::from_be_bytes(
-            bytes.try_into().expect("len checked for i64"),
-        ))
+        Ok(i64::from_be_bytes(self.take_array(field_offset)?))
     }
 
     fn read_symbol(&mut self, field_offset: usize) -> Result<String, ItchParseError> {
@@ -0,0 +1,107 @@
+use crate::IngestError;
+use md_core::{Payload, PendingEvent, Timestamp};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Accepts the objects written by `export --format jsonl`. `sequence` and
+/// `event_type` are ignored and rebuilt on ingest; `venue` is optional and
+/// falls back to the command-line venue. `source_sequence` is kept when
+/// present.
+#[derive(Debug, Deserialize)]
+struct JsonlRow {
+    timestamp_ns: Timestamp,
+    #[serde(default)]
+    venue: Option<String>,
+    symbol: String,
+    payload: Payload,
+    #[serde(default)]
+    source_sequence: Option<u64>,
+}
+
+pub fn parse_jsonl(path: &Path, venue: &str) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for_each_jsonl(path, venue, |event| {
+        out.push(event);
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+/// Hands rows to `on_event` one at a time instead of collecting them.
+pub fn for_each_jsonl(
+    path: &Path,
+    venue: &str,
+    mut on_event: impl FnMut(PendingEvent) -> Result<(), IngestError>,
+) -> Result<(), IngestError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut ingest_order = 0;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row: JsonlRow = serde_json::from_str(&line)
+            .map_err(|err| IngestError::Parse(format!("line {}: {err}", idx + 1)))?;
+        on_event(PendingEvent {
+            timestamp_ns: row.timestamp_ns,
+            venue: row.venue.unwrap_or_else(|| venue.to_string()),
+            symbol: row.symbol,
+            payload: row.payload,
+            ingest_order,
+            source_sequence: row.source_sequence,
+        })?;
+        ingest_order += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(content: &str, suffix: &str) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("clock")
+            .as_nanos();
+        path.push(format!(
+            "md_replay_ingest_{}_{}_{}.jsonl",
+            suffix,
+            std::process::id(),
+            ts
+        ));
+        std::fs::write(&path, content).expect("write temp jsonl");
+        path
+    }
+
+    #[test]
+    fn jsonl_parses_exported_and_minimal_rows() {
+        let path = write_temp(
+            concat!(
+                r#"{"timestamp_ns":5,"sequence":9,"venue":"Y","symbol":"AAPL","event_type":"Trade","payload":{"Trade":{"price_ticks":100,"size":2}},"source_sequence":77}"#,
+                "\n\n",
+                r#"{"timestamp_ns":-1,"symbol":"MSFT","payload":{"Quote":{"bid_px":1,"bid_sz":2,"ask_px":3,"ask_sz":4}}}"#,
+                "\n"
+            ),
+            "rows",
+        );
+        let rows = parse_jsonl(&path, "X").expect("parse jsonl");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].venue, "Y");
+        assert_eq!(rows[0].source_sequence, Some(77));
+        assert_eq!(rows[1].source_sequence, None);
+        assert_eq!(rows[1].venue, "X");
+        assert_eq!(rows[1].timestamp_ns, Timestamp::from_nanos(-1));
+        assert_eq!(rows[1].ingest_order, 1);
+    }
+
+    #[test]
+    fn jsonl_reports_line_number() {
+        let path = write_temp("\n{\"symbol\":\"A\"}\n", "bad");
+        let err = parse_jsonl(&path, "X").expect_err("must fail");
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
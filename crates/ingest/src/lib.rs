@@ -1,22 +1,93 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+mod alerts;
+pub mod alphavantage;
+pub mod binance;
+mod cache;
+mod checkpoint;
+mod clock;
+pub mod coinbase;
 mod csv;
+mod csv_schema;
+mod dedup;
+mod files;
+mod fix;
+mod frames;
+mod gaps;
 pub mod gen_pcap;
+pub mod gen_tape;
+pub mod http;
 pub mod itch;
+mod jsonl;
+mod live;
+mod mdp3;
+pub mod mold;
+mod notify;
+mod orders;
 #[cfg(feature = "pcap")]
 mod pcap_ingest;
 #[cfg(not(feature = "pcap"))]
 mod pcap_stub;
+pub mod pcapng;
+pub mod polygon;
+mod report;
+mod soup;
+pub mod stooq;
+mod stream;
+pub mod tiingo;
+mod ws;
 pub mod yahoo;
 
-use md_core::{assign_sequences, Event, TickError, TickTable};
+use md_core::{assign_sequences, Event, TickError, TickTable, TimestampError};
+use std::io::Read;
 use std::path::Path;
 use thiserror::Error;
 
-pub use csv::{parse_csv_a, parse_csv_b, parse_csv_c};
+pub use alerts::{Alert, AlertKind, AlertMonitor, AlertThresholds};
+pub use alphavantage::{ingest_alphavantage, AlphaVantageOptions};
+pub use cache::{CacheKey, ResponseCache, DEFAULT_CACHE_DIR};
+pub use checkpoint::{checkpoint_path_for, IngestCheckpoint, InputFingerprint};
+pub use clock::{
+    apply_clock_offsets, estimate_clock_offsets, parse_clock_offset, ClockEstimate, ClockOffsets,
+};
+pub use csv::{
+    for_each_csv_a, for_each_csv_a_with, for_each_csv_b, for_each_csv_b_with, for_each_csv_c,
+    for_each_csv_c_with, parse_csv_a, parse_csv_b, parse_csv_c,
+};
+pub use csv_schema::{
+    for_each_csv_with_schema, parse_csv_with_schema, CsvColumn, CsvColumns, CsvKind, CsvSchema,
+    CsvTimestamp, CsvTimestampFormat, TimestampSpec,
+};
+pub use dedup::{drop_duplicates, DedupReport, Deduplicator};
+pub use files::{expand_inputs, ingest_files};
+pub use fix::{ingest_fix, FixTagMap};
+pub use frames::{Framing, IssueKind, ParseIssue, PcapIngestOutput};
+pub use gaps::{GapRange, GapReport};
+pub use http::{HttpOptions, RetryPolicy};
+pub use jsonl::{for_each_jsonl, parse_jsonl};
+pub use live::{LiveItem, LiveOptions, LiveSummary};
+pub use mdp3::{ingest_mdp3, load_security_map};
+pub use notify::{Notification, Notifier};
+pub use orders::{ingest_orders, GatewayFormat};
+#[cfg(feature = "pcap")]
+pub use pcap_ingest::capture_live;
 #[cfg(feature = "pcap")]
-pub use pcap_ingest::{ingest_pcap, ParseIssue, PcapIngestOutput};
+use pcap_ingest::{for_each_classic_frame, ingest_classic_pcap};
+#[cfg(not(feature = "pcap"))]
+pub use pcap_stub::capture_live;
 #[cfg(not(feature = "pcap"))]
-pub use pcap_stub::{ingest_pcap, ParseIssue, PcapIngestOutput};
-pub use yahoo::ingest_yahoo;
+use pcap_stub::{for_each_classic_frame, ingest_classic_pcap};
+pub use polygon::{ingest_polygon, PolygonOptions};
+pub use report::{IngestReport, Provenance};
+pub use soup::ingest_soup;
+pub use stooq::{ingest_stooq, StooqOptions};
+pub use stream::{scan_symbols, stream_files, stream_files_from, RowVisitor, StreamPosition};
+pub use tiingo::{ingest_tiingo, TiingoOptions};
+pub use ws::{live_provider, stream_provider, LiveProvider, ProviderEvent, ProviderOptions};
+pub use yahoo::{ingest_yahoo, ingest_yahoo_with, BarExpansion, YahooOptions};
 
 #[derive(Debug, Error)]
 pub enum IngestError {
@@ -24,8 +95,8 @@ pub enum IngestError {
     Io(#[from] std::io::Error),
     #[error("csv error: {0}")]
     Csv(#[from] ::csv::Error),
-    #[error("time parse error: {0}")]
-    Time(#[from] chrono::ParseError),
+    #[error("timestamp error: {0}")]
+    Timestamp(#[from] TimestampError),
     #[error("http error: {0}")]
     Http(#[from] reqwest::Error),
     #[error("json error: {0}")]
@@ -39,6 +110,10 @@ pub enum IngestError {
     PcapUnavailable,
     #[error("parse error: {0}")]
     Parse(String),
+    #[error("websocket error: {0}")]
+    WebSocket(String),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(String),
 }
 
 pub fn ingest_csv_a(
@@ -67,3 +142,65 @@ pub fn ingest_csv_c(
     let pending = parse_csv_c(path, venue, ticks)?;
     Ok(assign_sequences(pending))
 }
+
+/// Ingests a CSV laid out as `schema` describes.
+pub fn ingest_csv_with_schema(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    schema: &CsvSchema,
+) -> Result<Vec<Event>, IngestError> {
+    let pending = parse_csv_with_schema(path, venue, ticks, schema)?;
+    Ok(assign_sequences(pending))
+}
+
+/// Ingests a mock ITCH capture with one message per datagram.
+pub fn ingest_pcap(path: &Path, venue: &str) -> Result<PcapIngestOutput, IngestError> {
+    ingest_pcap_with(path, venue, Framing::Raw)
+}
+
+/// Ingests a mock ITCH capture. pcapng files are read natively; classic
+/// pcap goes through libpcap and needs the `pcap` feature.
+pub fn ingest_pcap_with(
+    path: &Path,
+    venue: &str,
+    framing: Framing,
+) -> Result<PcapIngestOutput, IngestError> {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)?.read_exact(&mut magic)?;
+    if u32::from_le_bytes(magic) == pcapng::SECTION_HEADER {
+        pcapng::ingest_pcapng(path, venue, framing)
+    } else {
+        ingest_classic_pcap(path, venue, framing)
+    }
+}
+
+/// Magic numbers of classic pcap files: micro- and nanosecond resolution,
+/// in either byte order.
+pub(crate) const PCAP_MAGICS: [u32; 4] = [0xa1b2_c3d4, 0xd4c3_b2a1, 0xa1b2_3c4d, 0x4d3c_b2a1];
+
+/// Hands every frame of a pcap or pcapng capture to `on_frame` with its
+/// link type. Classic pcap goes through libpcap, needs the `pcap` feature
+/// and is taken to be Ethernet.
+pub(crate) fn for_each_capture_frame(
+    path: &Path,
+    mut on_frame: impl FnMut(u16, &[u8]),
+) -> Result<(), IngestError> {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)?.read_exact(&mut magic)?;
+    if u32::from_le_bytes(magic) == pcapng::SECTION_HEADER {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut reader = pcapng::PcapngReader::new(file);
+        while let Some(packet) = reader.next_packet()? {
+            on_frame(packet.link_type, &packet.data);
+        }
+        Ok(())
+    } else {
+        for_each_classic_frame(path, |frame| on_frame(pcapng::LINKTYPE_ETHERNET, frame))
+    }
+}
+
+pub fn ingest_jsonl(path: &Path, venue: &str) -> Result<Vec<Event>, IngestError> {
+    let pending = parse_jsonl(path, venue)?;
+    Ok(assign_sequences(pending))
+}
@@ -0,0 +1,189 @@
+use crate::{Framing, ParseIssue};
+use md_core::{Event, PendingEvent, Timestamp};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct LiveOptions {
+    pub iface: String,
+    pub venue: String,
+    pub framing: Framing,
+    /// Optional BPF expression applied by libpcap, e.g. `udp port 50000`.
+    pub bpf_filter: Option<String>,
+    pub snaplen: i32,
+    pub promisc: bool,
+    /// How long to wait for a packet before reporting `LiveItem::Idle`.
+    pub poll_timeout_ms: i32,
+    /// Events are held back until the feed has moved this far past them so
+    /// small out-of-order bursts still land in timestamp order.
+    pub reorder_window_ns: u64,
+}
+
+impl Default for LiveOptions {
+    fn default() -> Self {
+        Self {
+            iface: String::new(),
+            venue: String::new(),
+            framing: Framing::Raw,
+            bpf_filter: None,
+            snaplen: 65_535,
+            promisc: true,
+            poll_timeout_ms: 200,
+            reorder_window_ns: 1_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LiveItem {
+    /// A sequenced event, in timestamp order.
+    Event(Event),
+    Issue(ParseIssue),
+    /// No packet arrived within the poll timeout.
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiveSummary {
+    pub packets: u64,
+    pub events: u64,
+    pub issues: u64,
+    /// MoldUDP64 sequence gaps, also counted in `issues`.
+    pub gaps: u64,
+    /// Events that arrived after newer ones had already been released and
+    /// were dropped to keep the log in timestamp order.
+    pub late: u64,
+}
+
+/// Turns arrival-ordered events into timestamp-ordered, sequenced ones
+/// using a fixed reorder window.
+#[derive(Debug, Default)]
+pub(crate) struct Resequencer {
+    window_ns: i64,
+    pending: BTreeMap<(Timestamp, u64), PendingEvent>,
+    newest: Option<Timestamp>,
+    released: Option<Timestamp>,
+    /// Timestamp and ingest order of the last event released.
+    last_key: Option<(Timestamp, u64)>,
+    next_sequence: u64,
+}
+
+impl Resequencer {
+    pub(crate) fn new(window_ns: u64) -> Self {
+        Self {
+            window_ns: i64::try_from(window_ns).unwrap_or(i64::MAX),
+            ..Self::default()
+        }
+    }
+
+    /// Carries on as if `last` had just been released as `sequence`: older
+    /// events are late and numbering continues from `sequence + 1`.
+    pub(crate) fn resume_after(&mut self, last: Option<(Timestamp, u64)>, sequence: u64) {
+        self.released = last.map(|(ts, _)| ts);
+        self.last_key = last;
+        self.next_sequence = sequence;
+    }
+
+    pub(crate) fn last_key(&self) -> Option<(Timestamp, u64)> {
+        self.last_key
+    }
+
+    pub(crate) fn last_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Buffers `event`, or hands it back if it is older than something
+    /// already released.
+    pub(crate) fn push(&mut self, event: PendingEvent) -> Result<(), PendingEvent> {
+        if self.released.is_some_and(|r| event.timestamp_ns < r) {
+            return Err(event);
+        }
+        self.newest = Some(
+            self.newest
+                .map_or(event.timestamp_ns, |n| n.max(event.timestamp_ns)),
+        );
+        self.pending
+            .insert((event.timestamp_ns, event.ingest_order), event);
+        Ok(())
+    }
+
+    /// Events that have fallen out of the reorder window.
+    pub(crate) fn ready(&mut self) -> Vec<Event> {
+        let Some(newest) = self.newest else {
+            return Vec::new();
+        };
+        let cutoff = newest.saturating_add_nanos(-self.window_ns);
+        self.release(|ts| ts <= cutoff)
+    }
+
+    /// Everything still buffered, e.g. once the feed has gone quiet.
+    pub(crate) fn drain(&mut self) -> Vec<Event> {
+        self.release(|_| true)
+    }
+
+    pub(crate) fn late_by(&self, event: &PendingEvent) -> i64 {
+        self.released
+            .map_or(0, |r| r.nanos_since(event.timestamp_ns))
+    }
+
+    fn release(&mut self, due: impl Fn(Timestamp) -> bool) -> Vec<Event> {
+        let mut out = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            if !due(entry.key().0) {
+                break;
+            }
+            let event = entry.remove();
+            self.released = Some(event.timestamp_ns);
+            self.last_key = Some((event.timestamp_ns, event.ingest_order));
+            self.next_sequence += 1;
+            out.push(event.into_event(self.next_sequence));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::Payload;
+
+    fn pending(ts: i64, ingest_order: u64) -> PendingEvent {
+        PendingEvent {
+            timestamp_ns: Timestamp::from_nanos(ts),
+            venue: String::from("X"),
+            symbol: String::from("AAPL"),
+            payload: Payload::Trade {
+                price_ticks: 100,
+                size: 1,
+            },
+            ingest_order,
+            source_sequence: None,
+        }
+    }
+
+    fn stamps(events: &[Event]) -> Vec<(i64, u64)> {
+        events
+            .iter()
+            .map(|e| (e.timestamp_ns.as_nanos(), e.sequence))
+            .collect()
+    }
+
+    #[test]
+    fn reorders_within_window_and_drops_late_events() {
+        let mut seq = Resequencer::new(10);
+        seq.push(pending(100, 1)).expect("push");
+        seq.push(pending(95, 2)).expect("push");
+        assert!(seq.ready().is_empty());
+
+        seq.push(pending(108, 3)).expect("push");
+        assert_eq!(stamps(&seq.ready()), vec![(95, 1)]);
+
+        seq.push(pending(120, 4)).expect("push");
+        assert_eq!(stamps(&seq.ready()), vec![(100, 2), (108, 3)]);
+
+        let late = seq.push(pending(101, 5)).expect_err("late");
+        assert_eq!(seq.late_by(&late), 7);
+
+        assert_eq!(stamps(&seq.drain()), vec![(120, 4)]);
+        assert!(seq.drain().is_empty());
+    }
+}
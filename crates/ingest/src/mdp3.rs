@@ -0,0 +1,722 @@
+//! CME MDP 3.0 incremental feed: SBE messages behind a 12-byte packet
+//! header (`MsgSeqNum` u32, `SendingTime` u64, little-endian). Each message
+//! is a `u16` size covering itself, the 8-byte SBE header and the body.
+//! Only what trades and top of book need is decoded: book refresh
+//! (template 46), trade summary (48) and future definitions (54), which
+//! name the instruments. Everything else is skipped by its size.
+
+use crate::frames::{extract_udp_payload, warn_issue, IssueKind, ParseIssue, PcapIngestOutput};
+use crate::gaps::GapRange;
+use crate::pcapng::LINKTYPE_ETHERNET;
+use crate::IngestError;
+use md_core::{assign_sequences, Payload, PendingEvent, TickTable, Timestamp};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const PACKET_HEADER_LEN: usize = 12;
+const SBE_HEADER_LEN: usize = 8;
+const BOOK_REFRESH: u16 = 46;
+const TRADE_SUMMARY: u16 = 48;
+const FUTURE_DEFINITION: u16 = 54;
+/// Levels kept per side; outright futures are published ten deep.
+const BOOK_DEPTH: usize = 10;
+/// Null value of the optional `PRICE9` fields.
+const PRICE_NULL: i64 = i64::MAX;
+/// `PRICE9` mantissas carry nine implied decimals.
+const PRICE_SCALE: u32 = 9;
+
+type Decoded = Vec<Result<PendingEvent, ParseIssue>>;
+
+fn bytes_at<const N: usize>(buf: &[u8], at: usize) -> Option<[u8; N]> {
+    buf.get(at..at.checked_add(N)?)?.try_into().ok()
+}
+
+fn u16_at(buf: &[u8], at: usize) -> Result<u16, (usize, String)> {
+    bytes_at(buf, at)
+        .map(u16::from_le_bytes)
+        .ok_or_else(|| (at, String::from("truncated field")))
+}
+
+fn i32_at(buf: &[u8], at: usize) -> Result<i32, (usize, String)> {
+    bytes_at(buf, at)
+        .map(i32::from_le_bytes)
+        .ok_or_else(|| (at, String::from("truncated field")))
+}
+
+fn u64_at(buf: &[u8], at: usize) -> Result<u64, (usize, String)> {
+    bytes_at(buf, at)
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| (at, String::from("truncated field")))
+}
+
+fn i64_at(buf: &[u8], at: usize) -> Result<i64, (usize, String)> {
+    bytes_at(buf, at)
+        .map(i64::from_le_bytes)
+        .ok_or_else(|| (at, String::from("truncated field")))
+}
+
+/// Reads a `groupSize` header (`u16` block length, `u8` count) at `at` and
+/// returns where the entries start, their block length and count. Entries
+/// must be at least `min_block` bytes and fit in `body`.
+fn group(
+    body: &[u8],
+    at: usize,
+    min_block: usize,
+) -> Result<(usize, usize, usize), (usize, String)> {
+    let block = usize::from(u16_at(body, at)?);
+    let count = usize::from(
+        *body
+            .get(at + 2)
+            .ok_or((at, String::from("truncated group")))?,
+    );
+    if block < min_block {
+        return Err((at, format!("group block length {block} below {min_block}")));
+    }
+    let start = at + 3;
+    if body.len() < start + block * count {
+        return Err((at, format!("truncated group of {count} entries")));
+    }
+    Ok((start, block, count))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpdateAction {
+    New,
+    Change,
+    Delete,
+    DeleteThru,
+    DeleteFrom,
+    Overlay,
+}
+
+impl UpdateAction {
+    fn from_u8(raw: u8) -> Option<Self> {
+        Some(match raw {
+            0 => Self::New,
+            1 => Self::Change,
+            2 => Self::Delete,
+            3 => Self::DeleteThru,
+            4 => Self::DeleteFrom,
+            5 => Self::Overlay,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Level {
+    price_ticks: i64,
+    size: i64,
+}
+
+/// Price-level book for one instrument; only the top is published.
+#[derive(Debug, Default)]
+struct Book {
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+    published: Option<(Level, Level)>,
+}
+
+impl Book {
+    /// Applies a level update; `level` is 1-based as on the wire.
+    fn apply(&mut self, side: Side, action: UpdateAction, level: usize, entry: Level) {
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        let idx = level.saturating_sub(1);
+        match action {
+            UpdateAction::New => {
+                if idx <= levels.len() {
+                    levels.insert(idx, entry);
+                    levels.truncate(BOOK_DEPTH);
+                }
+            }
+            UpdateAction::Change | UpdateAction::Overlay => {
+                if idx < levels.len() {
+                    levels[idx] = entry;
+                } else if idx == levels.len() && idx < BOOK_DEPTH {
+                    levels.push(entry);
+                }
+            }
+            UpdateAction::Delete => {
+                if idx < levels.len() {
+                    levels.remove(idx);
+                }
+            }
+            UpdateAction::DeleteThru => levels.clear(),
+            UpdateAction::DeleteFrom => {
+                levels.drain(..level.min(levels.len()));
+            }
+        }
+    }
+
+    fn top(&self) -> (Level, Level) {
+        (
+            self.bids.first().copied().unwrap_or_default(),
+            self.asks.first().copied().unwrap_or_default(),
+        )
+    }
+}
+
+/// Decodes MDP 3.0 packets of one channel. Packets at or below the last
+/// sequence number seen are dropped, which arbitrates the A and B feeds
+/// when both are in the capture.
+pub(crate) struct Mdp3Decoder<'a> {
+    venue: &'a str,
+    ticks: &'a TickTable,
+    symbols: HashMap<i32, String>,
+    books: HashMap<i32, Book>,
+    next_seq: Option<u64>,
+    /// Packets and messages decoded, for estimating what a gap lost.
+    packets: u64,
+    messages: u64,
+    pub(crate) packet_index: u64,
+    ingest_order: u64,
+}
+
+impl<'a> Mdp3Decoder<'a> {
+    pub(crate) fn new(
+        venue: &'a str,
+        ticks: &'a TickTable,
+        securities: &HashMap<i32, String>,
+    ) -> Self {
+        Self {
+            venue,
+            ticks,
+            symbols: securities.clone(),
+            books: HashMap::new(),
+            next_seq: None,
+            packets: 0,
+            messages: 0,
+            packet_index: 0,
+            ingest_order: 0,
+        }
+    }
+
+    pub(crate) fn issue(&self, offset: usize, kind: IssueKind, detail: String) -> ParseIssue {
+        ParseIssue {
+            packet_index: self.packet_index,
+            offset,
+            kind,
+            detail,
+            gap: None,
+        }
+    }
+
+    /// Decodes one captured frame. Issue offsets are relative to the UDP
+    /// payload.
+    pub(crate) fn decode(&mut self, frame: &[u8]) -> Decoded {
+        self.packet_index += 1;
+        match extract_udp_payload(frame) {
+            Ok(packet) => self.decode_packet(packet),
+            Err((offset, detail)) => vec![Err(self.issue(offset, IssueKind::Malformed, detail))],
+        }
+    }
+
+    fn decode_packet(&mut self, packet: &[u8]) -> Decoded {
+        let seq = match bytes_at(packet, 0) {
+            Some(seq) if packet.len() >= PACKET_HEADER_LEN => u64::from(u32::from_le_bytes(seq)),
+            _ => {
+                let detail = String::from("short mdp3 packet header");
+                return vec![Err(self.issue(0, IssueKind::Malformed, detail))];
+            }
+        };
+        let mut out = Vec::new();
+        match self.next_seq {
+            Some(next) if seq < next => return out,
+            Some(next) if seq > next => {
+                // At least one message per packet, even before any decoded.
+                let per_packet = (self.messages as f64 / self.packets.max(1) as f64).max(1.0);
+                let range = GapRange {
+                    stream: String::from("mdp3"),
+                    first: next,
+                    last: seq - 1,
+                    estimated_messages: ((seq - next) as f64 * per_packet).round() as u64,
+                };
+                let detail = format!(
+                    "missing packets {next}..={} ({} packets, ~{} messages)",
+                    range.last,
+                    seq - next,
+                    range.estimated_messages
+                );
+                out.push(Err(ParseIssue {
+                    gap: Some(range),
+                    ..self.issue(0, IssueKind::SequenceGap, detail)
+                }));
+            }
+            _ => {}
+        }
+        self.next_seq = Some(seq + 1);
+        self.packets += 1;
+
+        let mut at = PACKET_HEADER_LEN;
+        while at < packet.len() {
+            let size = match u16_at(packet, at) {
+                Ok(size) => usize::from(size),
+                Err((offset, detail)) => {
+                    out.push(Err(self.issue(offset, IssueKind::Malformed, detail)));
+                    break;
+                }
+            };
+            if size < 2 + SBE_HEADER_LEN || at + size > packet.len() {
+                let detail = format!("bad message size {size}");
+                out.push(Err(self.issue(at, IssueKind::Malformed, detail)));
+                break;
+            }
+            let base = at + 2;
+            self.messages += 1;
+            if let Err((offset, detail)) = self.decode_message(&packet[base..at + size], &mut out) {
+                out.push(Err(self.issue(base + offset, IssueKind::Malformed, detail)));
+            }
+            at += size;
+        }
+        out
+    }
+
+    fn decode_message(&mut self, message: &[u8], out: &mut Decoded) -> Result<(), (usize, String)> {
+        let block = usize::from(u16_at(message, 0)?);
+        let template = u16_at(message, 2)?;
+        let body = &message[SBE_HEADER_LEN..];
+        let at = |(offset, detail): (usize, String)| (SBE_HEADER_LEN + offset, detail);
+        match template {
+            BOOK_REFRESH => self.decode_book(body, block, out).map_err(at),
+            TRADE_SUMMARY => self.decode_trades(body, block, out).map_err(at),
+            FUTURE_DEFINITION => self.decode_definition(body, block).map_err(at),
+            _ => Ok(()),
+        }
+    }
+
+    /// `MDIncrementalRefreshBook46`: entries carry price, size, security,
+    /// price level, update action and entry type. Implied prices are
+    /// skipped; one quote goes out per instrument whose top changed.
+    fn decode_book(
+        &mut self,
+        body: &[u8],
+        block: usize,
+        out: &mut Decoded,
+    ) -> Result<(), (usize, String)> {
+        let timestamp_ns = transact_time(body)?;
+        let (start, entry_len, count) = group(body, block, 27)?;
+        let mut touched = Vec::new();
+        for i in 0..count {
+            let at = start + i * entry_len;
+            let side = match body[at + 26] {
+                b'0' => Side::Bid,
+                b'1' => Side::Ask,
+                _ => continue,
+            };
+            let security = i32_at(body, at + 12)?;
+            let action = UpdateAction::from_u8(body[at + 25])
+                .ok_or_else(|| (at + 25, format!("unknown update action {}", body[at + 25])))?;
+            let price = i64_at(body, at)?;
+            let price_ticks = if price == PRICE_NULL {
+                0
+            } else {
+                self.price_ticks(security, price)
+                    .map_err(|detail| (at, detail))?
+            };
+            let entry = Level {
+                price_ticks,
+                size: i64::from(i32_at(body, at + 8)?),
+            };
+            let level = usize::from(body[at + 24]);
+            self.books
+                .entry(security)
+                .or_default()
+                .apply(side, action, level, entry);
+            if !touched.contains(&security) {
+                touched.push(security);
+            }
+        }
+
+        for security in touched {
+            let Some(book) = self.books.get_mut(&security) else {
+                continue;
+            };
+            let top = book.top();
+            if book.published == Some(top) {
+                continue;
+            }
+            book.published = Some(top);
+            let (bid, ask) = top;
+            let payload = Payload::Quote {
+                bid_px: bid.price_ticks,
+                bid_sz: bid.size,
+                ask_px: ask.price_ticks,
+                ask_sz: ask.size,
+            };
+            out.push(Ok(self.event(timestamp_ns, security, payload)));
+        }
+        Ok(())
+    }
+
+    /// `MDIncrementalRefreshTradeSummary48`. Busted trades (update action
+    /// delete) are skipped.
+    fn decode_trades(
+        &mut self,
+        body: &[u8],
+        block: usize,
+        out: &mut Decoded,
+    ) -> Result<(), (usize, String)> {
+        let timestamp_ns = transact_time(body)?;
+        let (start, entry_len, count) = group(body, block, 26)?;
+        for i in 0..count {
+            let at = start + i * entry_len;
+            if body[at + 25] != 0 {
+                continue;
+            }
+            let security = i32_at(body, at + 12)?;
+            let price_ticks = self
+                .price_ticks(security, i64_at(body, at)?)
+                .map_err(|detail| (at, detail))?;
+            let payload = Payload::Trade {
+                price_ticks,
+                size: i64::from(i32_at(body, at + 8)?),
+            };
+            out.push(Ok(self.event(timestamp_ns, security, payload)));
+        }
+        Ok(())
+    }
+
+    /// `MDInstrumentDefinitionFuture54`: learns the symbol of a security
+    /// unless the caller already named it.
+    fn decode_definition(&mut self, body: &[u8], block: usize) -> Result<(), (usize, String)> {
+        if block < 59 || body.len() < 59 {
+            return Err((0, String::from("short future definition")));
+        }
+        let raw = &body[35..55];
+        let symbol = raw
+            .iter()
+            .take_while(|b| **b != 0)
+            .map(|b| char::from(*b))
+            .collect::<String>();
+        let symbol = symbol.trim_end();
+        if symbol.is_empty() || !symbol.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err((35, String::from("invalid symbol")));
+        }
+        let security = i32_at(body, 55)?;
+        self.symbols
+            .entry(security)
+            .or_insert_with(|| symbol.to_string());
+        Ok(())
+    }
+
+    fn symbol(&self, security: i32) -> String {
+        self.symbols
+            .get(&security)
+            .cloned()
+            .unwrap_or_else(|| security.to_string())
+    }
+
+    fn price_ticks(&self, security: i32, mantissa: i64) -> Result<i64, String> {
+        let price = Decimal::new(mantissa, PRICE_SCALE);
+        self.ticks
+            .price_to_ticks(&self.symbol(security), price)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Events carry the sequence number of the packet they came in.
+    fn event(&mut self, timestamp_ns: Timestamp, security: i32, payload: Payload) -> PendingEvent {
+        self.ingest_order += 1;
+        PendingEvent {
+            timestamp_ns,
+            venue: self.venue.to_string(),
+            symbol: self.symbol(security),
+            payload,
+            ingest_order: self.ingest_order,
+            source_sequence: self.next_seq.map(|next| next - 1),
+        }
+    }
+}
+
+fn transact_time(body: &[u8]) -> Result<Timestamp, (usize, String)> {
+    Timestamp::from_unsigned_nanos(u64_at(body, 0)?)
+        .map_err(|_| (0, String::from("timestamp out of range")))
+}
+
+#[derive(Debug, Deserialize)]
+struct SecurityRow {
+    security_id: i32,
+    symbol: String,
+}
+
+/// Reads a `security_id,symbol` CSV naming instruments whose definitions
+/// are not in the capture.
+pub fn load_security_map(path: &Path) -> Result<HashMap<i32, String>, IngestError> {
+    let mut reader = ::csv::Reader::from_path(path)?;
+    let mut out = HashMap::new();
+    for row in reader.deserialize() {
+        let row: SecurityRow = row?;
+        out.insert(row.security_id, row.symbol);
+    }
+    Ok(out)
+}
+
+/// Ingests a CME MDP 3.0 incremental capture of one channel into trades
+/// and top-of-book quotes. Instruments are named by `securities`, then by
+/// definitions seen in the capture, then by their numeric `SecurityID`;
+/// prices go through `ticks` under that name. Classic pcap needs the
+/// `pcap` feature.
+pub fn ingest_mdp3(
+    path: &Path,
+    venue: &str,
+    ticks: &TickTable,
+    securities: &HashMap<i32, String>,
+) -> Result<PcapIngestOutput, IngestError> {
+    let mut decoder = Mdp3Decoder::new(venue, ticks, securities);
+    let mut pending = Vec::new();
+    let mut issues = Vec::new();
+    crate::for_each_capture_frame(path, |link_type, frame| {
+        if link_type != LINKTYPE_ETHERNET {
+            decoder.packet_index += 1;
+            let detail = format!("unsupported link type {link_type}");
+            issues.push(decoder.issue(0, IssueKind::Malformed, detail));
+            return;
+        }
+        for item in decoder.decode(frame) {
+            match item {
+                Ok(evt) => pending.push(evt),
+                Err(issue) => issues.push(issue),
+            }
+        }
+    })?;
+
+    for issue in &issues {
+        warn_issue(issue);
+    }
+    Ok(PcapIngestOutput {
+        events: assign_sequences(pending),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_pcap::{build_udp_frame, write_pcapng_header, write_pcapng_packet};
+
+    const TS: u64 = 1_700_000_000_000_000_000;
+
+    fn price(px: i64) -> i64 {
+        px * 10_i64.pow(PRICE_SCALE) / 100
+    }
+
+    /// Wraps a root block and its entry group (plus the trailing, empty
+    /// order-id group) in a size-prefixed SBE message.
+    fn message(template: u16, root: &[u8], entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = root.to_vec();
+        if !entries.is_empty() {
+            body.extend_from_slice(&32u16.to_le_bytes());
+            body.push(entries.len() as u8);
+            for entry in entries {
+                body.extend_from_slice(entry);
+            }
+            body.extend_from_slice(&24u16.to_le_bytes());
+            body.extend_from_slice(&[0; 6]);
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(&((2 + SBE_HEADER_LEN + body.len()) as u16).to_le_bytes());
+        out.extend_from_slice(&(root.len() as u16).to_le_bytes());
+        out.extend_from_slice(&template.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&9u16.to_le_bytes());
+        out.extend(body);
+        out
+    }
+
+    fn root(ts: u64) -> Vec<u8> {
+        let mut root = ts.to_le_bytes().to_vec();
+        root.extend_from_slice(&[0x84, 0, 0]);
+        root
+    }
+
+    fn entry(px: i64, size: i32, security: i32, level: u8, action: u8, kind: u8) -> Vec<u8> {
+        let mut out = vec![0u8; 32];
+        out[..8].copy_from_slice(&px.to_le_bytes());
+        out[8..12].copy_from_slice(&size.to_le_bytes());
+        out[12..16].copy_from_slice(&security.to_le_bytes());
+        out[24] = level;
+        out[25] = action;
+        out[26] = kind;
+        out
+    }
+
+    fn book(ts: u64, entries: &[Vec<u8>]) -> Vec<u8> {
+        message(BOOK_REFRESH, &root(ts), entries)
+    }
+
+    fn trade(ts: u64, px: i64, size: i32, security: i32) -> Vec<u8> {
+        message(
+            TRADE_SUMMARY,
+            &root(ts),
+            &[entry(px, size, security, 0, 0, 1)],
+        )
+    }
+
+    fn definition(security: i32, symbol: &str) -> Vec<u8> {
+        let mut root = vec![0u8; 224];
+        root[35..35 + symbol.len()].copy_from_slice(symbol.as_bytes());
+        root[55..59].copy_from_slice(&security.to_le_bytes());
+        message(FUTURE_DEFINITION, &root, &[])
+    }
+
+    fn packet(seq: u32, messages: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = seq.to_le_bytes().to_vec();
+        out.extend_from_slice(&TS.to_le_bytes());
+        for message in messages {
+            out.extend_from_slice(message);
+        }
+        out
+    }
+
+    fn ticks() -> TickTable {
+        TickTable::uniform(Decimal::new(25, 2)).expect("tick table")
+    }
+
+    fn quotes(items: &Decoded) -> Vec<(i64, i64, i64, i64)> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                Ok(PendingEvent {
+                    payload:
+                        Payload::Quote {
+                            bid_px,
+                            bid_sz,
+                            ask_px,
+                            ask_sz,
+                        },
+                    ..
+                }) => Some((*bid_px, *bid_sz, *ask_px, *ask_sz)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn book_updates_publish_top_of_book_changes() {
+        let ticks = ticks();
+        let mut decoder = Mdp3Decoder::new("CME", &ticks, &HashMap::new());
+        let items = decoder.decode_packet(&packet(
+            1,
+            &[
+                definition(42, "ESZ4"),
+                book(
+                    TS,
+                    &[
+                        entry(price(500_000), 5, 42, 1, 0, b'0'),
+                        entry(price(500_025), 7, 42, 1, 0, b'1'),
+                        // Implied bid: not part of the outright book.
+                        entry(price(500_050), 1, 42, 1, 0, b'E'),
+                    ],
+                ),
+                // A new second level leaves the top alone.
+                book(TS + 1, &[entry(price(499_975), 3, 42, 2, 0, b'0')]),
+                book(TS + 2, &[entry(PRICE_NULL, 0, 42, 1, 2, b'0')]),
+                book(TS + 3, &[entry(price(500_025), 9, 42, 1, 1, b'1')]),
+            ],
+        ));
+        assert!(items.iter().all(Result::is_ok), "{items:?}");
+        assert_eq!(
+            quotes(&items),
+            vec![
+                (20_000, 5, 20_001, 7),
+                (19_999, 3, 20_001, 7),
+                (19_999, 3, 20_001, 9)
+            ]
+        );
+        let Some(Ok(first)) = items.first() else {
+            panic!("no events");
+        };
+        assert_eq!(first.symbol, "ESZ4");
+        assert_eq!(first.timestamp_ns.to_unsigned_nanos(), Some(TS));
+    }
+
+    #[test]
+    fn trades_are_decoded_and_packet_gaps_reported() {
+        let ticks = ticks();
+        let names = HashMap::from([(7, String::from("NQZ4"))]);
+        let mut decoder = Mdp3Decoder::new("CME", &ticks, &names);
+        let first = decoder.decode_packet(&packet(
+            10,
+            &[definition(7, "IGNORED"), trade(TS, price(2_000_050), 2, 7)],
+        ));
+        let Some(Ok(evt)) = first.first() else {
+            panic!("no trade: {first:?}");
+        };
+        assert_eq!(evt.symbol, "NQZ4");
+        assert_eq!(
+            evt.payload,
+            Payload::Trade {
+                price_ticks: 80_002,
+                size: 2
+            }
+        );
+        // The B feed copy of the same packet is dropped.
+        assert!(decoder
+            .decode_packet(&packet(10, &[trade(TS, price(2_000_050), 2, 7)]))
+            .is_empty());
+
+        let later = decoder.decode_packet(&packet(13, &[trade(TS + 5, price(2_000_000), 1, 8)]));
+        let Some(Err(gap)) = later.first() else {
+            panic!("no gap: {later:?}");
+        };
+        assert_eq!(gap.kind, IssueKind::SequenceGap);
+        assert!(gap.detail.contains("11..=12"), "{}", gap.detail);
+        let range = gap.gap.as_ref().expect("gap range");
+        assert_eq!((range.first, range.last), (11, 12));
+        // Two packets at the two messages per packet seen so far.
+        assert_eq!(range.estimated_messages, 4);
+        let Some(Ok(evt)) = later.get(1) else {
+            panic!("no trade after gap");
+        };
+        assert_eq!(evt.symbol, "8");
+        assert_eq!(evt.source_sequence, Some(13));
+
+        let bad = decoder.decode_packet(&[packet(14, &[]), vec![40, 0, 1]].concat());
+        assert!(matches!(bad.as_slice(), [Err(issue)] if issue.offset == 12));
+    }
+
+    #[test]
+    fn ingests_a_pcapng_capture_with_a_security_map() {
+        let dir = std::env::temp_dir().join(format!("mdp3-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let map_path = dir.join("securities.csv");
+        std::fs::write(&map_path, "security_id,symbol\n42,ESZ4\n").expect("map");
+        let securities = load_security_map(&map_path).expect("load map");
+
+        let packets = [
+            packet(1, &[book(TS, &[entry(price(500_000), 5, 42, 1, 0, b'0')])]),
+            packet(2, &[trade(TS + 10, price(500_000), 1, 42)]),
+        ];
+        let mut capture = Vec::new();
+        write_pcapng_header(&mut capture).expect("header");
+        for (i, p) in packets.iter().enumerate() {
+            let frame = build_udp_frame(i as u16, p);
+            write_pcapng_packet(&mut capture, TS + i as u64, &frame).expect("packet");
+        }
+        let path = dir.join("cme.pcapng");
+        std::fs::write(&path, capture).expect("capture");
+
+        let out = ingest_mdp3(&path, "CME", &ticks(), &securities).expect("ingest");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(out.issues.is_empty(), "{:?}", out.issues);
+        assert_eq!(out.events.len(), 2);
+        assert!(out.events.iter().all(|e| e.symbol == "ESZ4"));
+        assert_eq!(
+            out.events[1].payload,
+            Payload::Trade {
+                price_ticks: 20_000,
+                size: 1
+            }
+        );
+    }
+}
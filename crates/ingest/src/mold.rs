@@ -0,0 +1,147 @@
+//! MoldUDP64 downstream packets: a 20-byte header (10-byte session,
+//! big-endian sequence number of the first message, message count)
+//! followed by length-prefixed messages.
+
+pub const HEADER_LEN: usize = 20;
+/// Message count announcing the end of a session.
+pub const END_OF_SESSION: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoldHeader {
+    pub session: [u8; 10],
+    pub sequence: u64,
+    pub count: u16,
+}
+
+impl MoldHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, (usize, String)> {
+        if data.len() < HEADER_LEN {
+            return Err((0, String::from("short moldudp64 header")));
+        }
+        let mut session = [0u8; 10];
+        session.copy_from_slice(&data[..10]);
+        let mut sequence = [0u8; 8];
+        sequence.copy_from_slice(&data[10..18]);
+        Ok(Self {
+            session,
+            sequence: u64::from_be_bytes(sequence),
+            count: u16::from_be_bytes([data[18], data[19]]),
+        })
+    }
+
+    pub fn session_name(&self) -> String {
+        String::from_utf8_lossy(&self.session)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Number of sequenced messages carried; heartbeats and end-of-session
+    /// packets carry none.
+    pub fn message_count(&self) -> u16 {
+        if self.count == END_OF_SESSION {
+            0
+        } else {
+            self.count
+        }
+    }
+}
+
+/// Encodes one downstream packet whose first message gets `sequence`.
+pub fn encode_packet(session: &[u8; 10], sequence: u64, messages: &[Vec<u8>]) -> Vec<u8> {
+    let body = messages.iter().map(|m| 2 + m.len()).sum::<usize>();
+    let mut out = Vec::with_capacity(HEADER_LEN + body);
+    out.extend_from_slice(session);
+    out.extend_from_slice(&sequence.to_be_bytes());
+    out.extend_from_slice(&(messages.len() as u16).to_be_bytes());
+    for message in messages {
+        out.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        out.extend_from_slice(message);
+    }
+    out
+}
+
+/// Messages a packet skipped over: `first..=last` never arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceGap {
+    pub first: u64,
+    pub last: u64,
+}
+
+/// Tracks the next expected downstream sequence number per session. Late
+/// fills of a reported gap are treated like any other repeat and dropped,
+/// so events stay in arrival order.
+#[derive(Debug, Default)]
+pub(crate) struct MoldSequencer {
+    session: Option<[u8; 10]>,
+    next: u64,
+}
+
+impl MoldSequencer {
+    /// Returns the gap in front of `header`, if any, and how many of its
+    /// leading messages were already seen (retransmissions).
+    pub(crate) fn observe(&mut self, header: &MoldHeader) -> (Option<SequenceGap>, usize) {
+        if self.session != Some(header.session) {
+            // Joining mid-session is normal; only count from here on.
+            self.session = Some(header.session);
+            self.next = header.sequence;
+        }
+        let gap = (header.sequence > self.next).then(|| SequenceGap {
+            first: self.next,
+            last: header.sequence - 1,
+        });
+        let seen = self.next.saturating_sub(header.sequence);
+        let end = header
+            .sequence
+            .saturating_add(u64::from(header.message_count()));
+        self.next = self.next.max(end);
+        (gap, usize::try_from(seen).unwrap_or(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(session: &[u8; 10], sequence: u64, count: u16) -> MoldHeader {
+        MoldHeader {
+            session: *session,
+            sequence,
+            count,
+        }
+    }
+
+    #[test]
+    fn encoded_header_round_trips() {
+        let packet = encode_packet(b"SESSION001", 42, &[vec![1, 2, 3], vec![4]]);
+        assert_eq!(packet.len(), HEADER_LEN + 5 + 3);
+        let parsed = MoldHeader::parse(&packet).expect("header");
+        assert_eq!(parsed, header(b"SESSION001", 42, 2));
+        assert_eq!(parsed.session_name(), "SESSION001");
+        assert!(MoldHeader::parse(&packet[..19]).is_err());
+    }
+
+    #[test]
+    fn sequencer_reports_gaps_and_skips_retransmissions() {
+        let mut seq = MoldSequencer::default();
+        assert_eq!(seq.observe(&header(b"A         ", 10, 3)), (None, 0));
+        assert_eq!(seq.observe(&header(b"A         ", 13, 0)), (None, 0));
+        assert_eq!(
+            seq.observe(&header(b"A         ", 16, 2)),
+            (
+                Some(SequenceGap {
+                    first: 13,
+                    last: 15
+                }),
+                0
+            )
+        );
+        // Retransmission overlapping what was already delivered.
+        assert_eq!(seq.observe(&header(b"A         ", 17, 3)), (None, 1));
+        assert_eq!(
+            seq.observe(&header(b"A         ", 20, END_OF_SESSION)),
+            (None, 0)
+        );
+        // A new session restarts the count.
+        assert_eq!(seq.observe(&header(b"B         ", 1, 1)), (None, 0));
+    }
+}
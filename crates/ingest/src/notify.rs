@@ -0,0 +1,99 @@
+//! Failure notifications posted to a webhook. The JSON body carries a
+//! `text` line, so Slack-compatible incoming webhooks display it as is,
+//! next to structured fields for anything that parses it.
+
+use crate::http::HttpOptions;
+use crate::IngestError;
+use reqwest::Client;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Notification {
+    /// What was checked, usually a log or capture path.
+    pub source: String,
+    /// Name of the check, such as `verify`, `gaps` or `rate_collapse`.
+    pub check: String,
+    /// `false` when a check that was failing has recovered.
+    pub firing: bool,
+    pub summary: String,
+    /// Check-specific data.
+    pub details: serde_json::Value,
+}
+
+impl Notification {
+    /// The human-readable line sent as `text`.
+    pub fn text(&self) -> String {
+        let state = if self.firing { "FAILED" } else { "resolved" };
+        format!(
+            "md-replay {} {state} on {}: {}",
+            self.check, self.source, self.summary
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct Body<'a> {
+    text: String,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    client: Client,
+    url: String,
+}
+
+impl Notifier {
+    pub fn new(url: &str, http: &HttpOptions) -> Result<Self, IngestError> {
+        Ok(Self {
+            client: http.build_client()?,
+            url: url.to_string(),
+        })
+    }
+
+    pub async fn send(&self, notification: &Notification) -> Result<(), IngestError> {
+        self.client
+            .post(&self.url)
+            .json(&body(notification))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+fn body(notification: &Notification) -> Body<'_> {
+    Body {
+        text: notification.text(),
+        notification,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_has_slack_text_and_structured_fields() {
+        let notification = Notification {
+            source: String::from("data/norm.eventlog"),
+            check: String::from("gaps"),
+            firing: true,
+            summary: String::from("2 gaps longer than 1000000000 ns"),
+            details: serde_json::json!({ "gaps": 2 }),
+        };
+        let json = serde_json::to_value(body(&notification)).expect("json");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "text": "md-replay gaps FAILED on data/norm.eventlog: 2 gaps longer than 1000000000 ns",
+                "source": "data/norm.eventlog",
+                "check": "gaps",
+                "firing": true,
+                "summary": "2 gaps longer than 1000000000 ns",
+                "details": { "gaps": 2 },
+            })
+        );
+    }
+}
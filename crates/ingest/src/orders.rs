@@ -0,0 +1,232 @@
+//! Order-gateway logs: CSV or JSON lines of order submissions and gateway
+//! responses, stamped with the client clock. Columns are `timestamp`
+//! (integer nanoseconds or RFC3339), `order_id`, `symbol`, `side`,
+//! `action`, `price` (empty for market orders and cancels), `size` and an
+//! optional `venue`.
+
+use crate::IngestError;
+use md_core::{OrderEvent, TickTable, Timestamp};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GatewayFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl FromStr for GatewayFormat {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "json" => Ok(Self::Jsonl),
+            other => Err(format!(
+                "unknown gateway format {other}, expected csv|jsonl"
+            )),
+        }
+    }
+}
+
+/// A cell that JSON may carry as a number and CSV always carries as text.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Scalar {
+    fn text(&self) -> String {
+        match self {
+            Self::Int(v) => v.to_string(),
+            Self::Float(v) => v.to_string(),
+            Self::Text(v) => v.trim().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayRow {
+    timestamp: Scalar,
+    order_id: Scalar,
+    symbol: String,
+    side: String,
+    action: String,
+    #[serde(default)]
+    price: Option<Scalar>,
+    size: Scalar,
+    #[serde(default)]
+    venue: Option<String>,
+}
+
+impl GatewayRow {
+    fn into_order(self, venue: &str, ticks: &TickTable) -> Result<OrderEvent, String> {
+        let timestamp_ns = match &self.timestamp {
+            Scalar::Int(ns) => Timestamp::from_nanos(*ns),
+            other => Timestamp::from_str(&other.text()).map_err(|err| err.to_string())?,
+        };
+        let size = match &self.size {
+            Scalar::Int(size) => *size,
+            other => other
+                .text()
+                .parse::<i64>()
+                .map_err(|_| format!("invalid size {}", other.text()))?,
+        };
+        let price_ticks = match self.price.map(|p| p.text()) {
+            Some(price) if !price.is_empty() => Some(
+                ticks
+                    .price_str_to_ticks(&self.symbol, &price)
+                    .map_err(|err| err.to_string())?,
+            ),
+            _ => None,
+        };
+        Ok(OrderEvent {
+            timestamp_ns,
+            sequence: 0,
+            venue: self
+                .venue
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| venue.to_string()),
+            symbol: self.symbol,
+            order_id: self.order_id.text(),
+            action: self.action.parse()?,
+            side: self.side.parse()?,
+            price_ticks,
+            size,
+        })
+    }
+}
+
+/// Reads a gateway log into order events sorted by client timestamp (file
+/// order breaks ties) and numbered from 1. `venue` applies to rows
+/// without one.
+pub fn ingest_orders(
+    path: &Path,
+    format: GatewayFormat,
+    venue: &str,
+    ticks: &TickTable,
+) -> Result<Vec<OrderEvent>, IngestError> {
+    let parse = |row: usize, raw: GatewayRow| {
+        raw.into_order(venue, ticks)
+            .map_err(|err| IngestError::Parse(format!("row {row}: {err}")))
+    };
+    let mut orders = Vec::new();
+    match format {
+        GatewayFormat::Csv => {
+            let mut reader = ::csv::Reader::from_path(path)?;
+            for (idx, row) in reader.deserialize::<GatewayRow>().enumerate() {
+                orders.push(parse(idx + 1, row?)?);
+            }
+        }
+        GatewayFormat::Jsonl => {
+            let reader = BufReader::new(File::open(path)?);
+            for (idx, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row = serde_json::from_str(&line)
+                    .map_err(|err| IngestError::Parse(format!("line {}: {err}", idx + 1)))?;
+                orders.push(parse(idx + 1, row)?);
+            }
+        }
+    }
+
+    orders.sort_by_key(|order| order.timestamp_ns);
+    for (i, order) in orders.iter_mut().enumerate() {
+        order.sequence = i as u64 + 1;
+    }
+    Ok(orders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::{OrderAction, OrderSide};
+    use rust_decimal::Decimal;
+
+    fn write_temp(content: &str, name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "md_replay_ingest_orders_{}_{name}",
+            std::process::id()
+        ));
+        std::fs::write(&path, content).expect("write temp gateway log");
+        path
+    }
+
+    fn ticks() -> TickTable {
+        TickTable::uniform(Decimal::new(1, 2)).expect("tick table")
+    }
+
+    #[test]
+    fn csv_rows_are_sorted_by_client_time() {
+        let path = write_temp(
+            "timestamp,order_id,symbol,side,action,price,size,venue\n\
+             2024-01-02T14:30:00.000000200Z,7,AAPL,sell,fill,185.25,40,\n\
+             1704205800000000100,7,AAPL,S,new,185.25,100,XNAS\n\
+             1704205800000000300,7,AAPL,sell,cancel,,60,\n",
+            "rows.csv",
+        );
+        let orders = ingest_orders(&path, GatewayFormat::Csv, "X", &ticks()).expect("ingest");
+        let _ = std::fs::remove_file(&path);
+        let summary = orders
+            .iter()
+            .map(|o| {
+                (
+                    o.sequence,
+                    o.action,
+                    o.price_ticks,
+                    o.size,
+                    o.venue.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, OrderAction::New, Some(18_525), 100, "XNAS"),
+                (2, OrderAction::Fill, Some(18_525), 40, "X"),
+                (3, OrderAction::Cancel, None, 60, "X"),
+            ]
+        );
+        assert!(orders.iter().all(|o| o.side == OrderSide::Sell));
+        assert_eq!(orders[0].order_id, "7");
+    }
+
+    #[test]
+    fn jsonl_accepts_numbers_and_reports_bad_rows() {
+        let path = write_temp(
+            concat!(
+                r#"{"timestamp":5,"order_id":"a1","symbol":"MSFT","side":"buy","action":"new","price":410.5,"size":3}"#,
+                "\n\n",
+                r#"{"timestamp":"9","order_id":"a1","symbol":"MSFT","side":"buy","action":"fill","price":"410.49","size":"3"}"#,
+                "\n"
+            ),
+            "rows.jsonl",
+        );
+        let orders = ingest_orders(&path, GatewayFormat::Jsonl, "X", &ticks()).expect("ingest");
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].price_ticks, Some(41_050));
+        assert_eq!(orders[1].timestamp_ns, Timestamp::from_nanos(9));
+        assert_eq!(orders[1].price_ticks, Some(41_049));
+
+        std::fs::write(
+            &path,
+            r#"{"timestamp":5,"order_id":"a1","symbol":"MSFT","side":"short","action":"new","size":3}"#,
+        )
+        .expect("rewrite");
+        let err = ingest_orders(&path, GatewayFormat::Jsonl, "X", &ticks()).expect_err("bad side");
+        let _ = std::fs::remove_file(&path);
+        assert!(
+            err.to_string().contains("row 1: unknown side short"),
+            "{err}"
+        );
+    }
+}
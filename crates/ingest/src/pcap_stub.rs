@@ -1,20 +1,27 @@
+use crate::frames::{Framing, PcapIngestOutput};
+use crate::live::{LiveItem, LiveOptions, LiveSummary};
 use crate::IngestError;
-use md_core::Event;
+use std::ops::ControlFlow;
 use std::path::Path;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ParseIssue {
-    pub packet_index: u64,
-    pub offset: usize,
-    pub detail: String,
+pub(crate) fn ingest_classic_pcap(
+    _path: &Path,
+    _venue: &str,
+    _framing: Framing,
+) -> Result<PcapIngestOutput, IngestError> {
+    Err(IngestError::PcapUnavailable)
 }
 
-#[derive(Debug, Clone)]
-pub struct PcapIngestOutput {
-    pub events: Vec<Event>,
-    pub issues: Vec<ParseIssue>,
+pub(crate) fn for_each_classic_frame(
+    _path: &Path,
+    _on_frame: impl FnMut(&[u8]),
+) -> Result<(), IngestError> {
+    Err(IngestError::PcapUnavailable)
 }
 
-pub fn ingest_pcap(_path: &Path, _venue: &str) -> Result<PcapIngestOutput, IngestError> {
+pub fn capture_live(
+    _opts: &LiveOptions,
+    _on_item: impl FnMut(LiveItem) -> ControlFlow<()>,
+) -> Result<LiveSummary, IngestError> {
     Err(IngestError::PcapUnavailable)
 }
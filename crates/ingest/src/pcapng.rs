@@ -0,0 +1,478 @@
+//! Pure-Rust pcapng reader. Needs no libpcap, so pcapng captures ingest
+//! even without the `pcap` feature.
+
+use crate::frames::{warn_issue, Framing, IssueKind, ItchDecoder, PcapIngestOutput};
+use crate::IngestError;
+use md_core::assign_sequences;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+pub const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const OBSOLETE_PACKET: u32 = 0x0000_0002;
+const SIMPLE_PACKET: u32 = 0x0000_0003;
+const ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPT_END: u16 = 0;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_IF_TSOFFSET: u16 = 14;
+pub(crate) const LINKTYPE_ETHERNET: u16 = 1;
+const MAX_BLOCK_LEN: u32 = 1 << 24;
+
+/// Units of one interface's raw timestamps, from `if_tsresol`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsResolution {
+    /// 10^-n seconds; pcapng's default is microseconds (n = 6).
+    Decimal(u8),
+    /// 2^-n seconds.
+    Binary(u8),
+}
+
+impl TsResolution {
+    fn from_option(raw: u8) -> Self {
+        if raw & 0x80 == 0 {
+            Self::Decimal(raw)
+        } else {
+            Self::Binary(raw & 0x7f)
+        }
+    }
+
+    fn to_nanos(self, units: u64) -> Option<u128> {
+        let units = units as u128;
+        match self {
+            Self::Decimal(n) if n <= 9 => units.checked_mul(10u128.pow(9 - n as u32)),
+            Self::Decimal(n) => Some(units / 10u128.checked_pow(n as u32 - 9)?),
+            Self::Binary(n) => Some(units.checked_mul(1_000_000_000)? >> n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    pub link_type: u16,
+    pub resolution: TsResolution,
+    /// `if_tsoffset`, seconds added to every timestamp.
+    pub offset_secs: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub interface_id: u32,
+    pub link_type: u16,
+    /// Capture time in nanoseconds since the epoch, honouring the
+    /// interface's resolution and offset. `None` for simple packet blocks,
+    /// which carry no timestamp.
+    pub timestamp_ns: Option<i64>,
+    pub data: Vec<u8>,
+}
+
+pub struct PcapngReader<R: Read> {
+    r: R,
+    big_endian: bool,
+    interfaces: Vec<InterfaceInfo>,
+    offset: u64,
+    block_start: u64,
+}
+
+impl<R: Read> PcapngReader<R> {
+    pub fn new(r: R) -> Self {
+        Self {
+            r,
+            big_endian: false,
+            interfaces: Vec::new(),
+            offset: 0,
+            block_start: 0,
+        }
+    }
+
+    pub fn interfaces(&self) -> &[InterfaceInfo] {
+        &self.interfaces
+    }
+
+    /// Next packet block, skipping every other block type. `Ok(None)` at a
+    /// clean end of file.
+    pub fn next_packet(&mut self) -> Result<Option<CapturedPacket>, IngestError> {
+        loop {
+            let Some((block_type, body)) = self.next_block()? else {
+                return Ok(None);
+            };
+            match block_type {
+                SECTION_HEADER => self.interfaces.clear(),
+                INTERFACE_DESCRIPTION => {
+                    let info = self.parse_interface(&body)?;
+                    self.interfaces.push(info);
+                }
+                ENHANCED_PACKET => return self.parse_enhanced(&body).map(Some),
+                OBSOLETE_PACKET => return self.parse_obsolete(&body).map(Some),
+                SIMPLE_PACKET => return self.parse_simple(&body).map(Some),
+                _ => {}
+            }
+        }
+    }
+
+    fn next_block(&mut self) -> Result<Option<(u32, Vec<u8>)>, IngestError> {
+        let block_offset = self.offset;
+        self.block_start = block_offset;
+        let mut head = [0u8; 8];
+        match self.r.read_exact(&mut head[..4]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        self.r.read_exact(&mut head[4..])?;
+        let raw_type = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+        if raw_type == SECTION_HEADER {
+            // The section header decides the byte order of everything that
+            // follows, including its own length field.
+            let mut bom = [0u8; 4];
+            self.r.read_exact(&mut bom)?;
+            self.big_endian = match u32::from_le_bytes(bom) {
+                BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => return Err(self.malformed(block_offset, "bad byte-order magic")),
+            };
+            let total = self.u32_at(&head, 4);
+            let body = self.read_body(block_offset, total, 4)?;
+            return Ok(Some((SECTION_HEADER, [bom.to_vec(), body].concat())));
+        }
+        let block_type = self.u32_at(&head, 0);
+        let total = self.u32_at(&head, 4);
+        let body = self.read_body(block_offset, total, 0)?;
+        Ok(Some((block_type, body)))
+    }
+
+    /// Reads the rest of a block whose total length is `total`, `already`
+    /// body bytes having been consumed, and checks the trailing length.
+    fn read_body(
+        &mut self,
+        block_offset: u64,
+        total: u32,
+        already: usize,
+    ) -> Result<Vec<u8>, IngestError> {
+        if total < 12 || !total.is_multiple_of(4) || total > MAX_BLOCK_LEN {
+            return Err(self.malformed(block_offset, "bad block length"));
+        }
+        let mut rest = vec![0u8; total as usize - 8 - already];
+        self.r.read_exact(&mut rest).map_err(|err| {
+            if err.kind() == std::io::ErrorKind::UnexpectedEof {
+                self.malformed(block_offset, "truncated block")
+            } else {
+                err.into()
+            }
+        })?;
+        let trailer_at = rest.len() - 4;
+        if self.u32_at(&rest, trailer_at) != total {
+            return Err(self.malformed(block_offset, "block length mismatch"));
+        }
+        rest.truncate(trailer_at);
+        self.offset += total as u64;
+        Ok(rest)
+    }
+
+    fn parse_interface(&self, body: &[u8]) -> Result<InterfaceInfo, IngestError> {
+        if body.len() < 8 {
+            return Err(self.malformed(self.block_start, "short interface block"));
+        }
+        let mut info = InterfaceInfo {
+            link_type: self.u16_at(body, 0),
+            resolution: TsResolution::Decimal(6),
+            offset_secs: 0,
+        };
+        let mut pos = 8;
+        while pos + 4 <= body.len() {
+            let code = self.u16_at(body, pos);
+            let len = self.u16_at(body, pos + 2) as usize;
+            let value = body
+                .get(pos + 4..pos + 4 + len)
+                .ok_or_else(|| self.malformed(self.block_start, "truncated option"))?;
+            match code {
+                OPT_END => break,
+                OPT_IF_TSRESOL if len == 1 => {
+                    info.resolution = TsResolution::from_option(value[0]);
+                }
+                OPT_IF_TSOFFSET if len == 8 => {
+                    info.offset_secs = self.u64_at(value, 0) as i64;
+                }
+                _ => {}
+            }
+            pos += 4 + len.next_multiple_of(4);
+        }
+        Ok(info)
+    }
+
+    fn parse_enhanced(&self, body: &[u8]) -> Result<CapturedPacket, IngestError> {
+        if body.len() < 20 {
+            return Err(self.malformed(self.block_start, "short enhanced packet block"));
+        }
+        let interface_id = self.u32_at(body, 0);
+        let units = ((self.u32_at(body, 4) as u64) << 32) | self.u32_at(body, 8) as u64;
+        let cap_len = self.u32_at(body, 12) as usize;
+        self.packet(interface_id, Some(units), body, 20, cap_len)
+    }
+
+    fn parse_obsolete(&self, body: &[u8]) -> Result<CapturedPacket, IngestError> {
+        if body.len() < 20 {
+            return Err(self.malformed(self.block_start, "short packet block"));
+        }
+        let interface_id = self.u16_at(body, 0) as u32;
+        let units = ((self.u32_at(body, 4) as u64) << 32) | self.u32_at(body, 8) as u64;
+        let cap_len = self.u32_at(body, 12) as usize;
+        self.packet(interface_id, Some(units), body, 20, cap_len)
+    }
+
+    fn parse_simple(&self, body: &[u8]) -> Result<CapturedPacket, IngestError> {
+        if body.len() < 4 {
+            return Err(self.malformed(self.block_start, "short simple packet block"));
+        }
+        let orig_len = self.u32_at(body, 0) as usize;
+        let cap_len = orig_len.min(body.len() - 4);
+        self.packet(0, None, body, 4, cap_len)
+    }
+
+    fn packet(
+        &self,
+        interface_id: u32,
+        units: Option<u64>,
+        body: &[u8],
+        data_at: usize,
+        cap_len: usize,
+    ) -> Result<CapturedPacket, IngestError> {
+        let iface = self
+            .interfaces
+            .get(interface_id as usize)
+            .ok_or_else(|| self.malformed(self.block_start, "packet for undeclared interface"))?;
+        let data = body
+            .get(data_at..data_at + cap_len)
+            .ok_or_else(|| self.malformed(self.block_start, "captured length exceeds block"))?;
+        let timestamp_ns = units.and_then(|units| {
+            let ns = iface.resolution.to_nanos(units)?;
+            let ns = i64::try_from(ns).ok()?;
+            ns.checked_add(iface.offset_secs.checked_mul(1_000_000_000)?)
+        });
+        Ok(CapturedPacket {
+            interface_id,
+            link_type: iface.link_type,
+            timestamp_ns,
+            data: data.to_vec(),
+        })
+    }
+
+    fn malformed(&self, offset: u64, detail: &str) -> IngestError {
+        IngestError::Parse(format!("pcapng: {detail} at byte offset {offset}"))
+    }
+
+    fn u16_at(&self, buf: &[u8], at: usize) -> u16 {
+        let bytes = [buf[at], buf[at + 1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32_at(&self, buf: &[u8], at: usize) -> u32 {
+        let bytes = [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn u64_at(&self, buf: &[u8], at: usize) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf[at..at + 8]);
+        if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        }
+    }
+}
+
+pub(crate) fn ingest_pcapng(
+    path: &Path,
+    venue: &str,
+    framing: Framing,
+) -> Result<PcapIngestOutput, IngestError> {
+    let mut reader = PcapngReader::new(BufReader::new(File::open(path)?));
+    let mut decoder = ItchDecoder::new(venue, framing);
+    let mut pending = Vec::new();
+    let mut issues = Vec::new();
+
+    while let Some(packet) = reader.next_packet()? {
+        if packet.link_type != LINKTYPE_ETHERNET {
+            decoder.packet_index += 1;
+            issues.push(decoder.issue(
+                0,
+                IssueKind::Malformed,
+                format!("unsupported link type {}", packet.link_type),
+            ));
+            continue;
+        }
+        for item in decoder.decode(&packet.data) {
+            match item {
+                Ok(evt) => pending.push(evt),
+                Err(issue) => issues.push(issue),
+            }
+        }
+    }
+
+    for issue in &issues {
+        warn_issue(issue);
+    }
+
+    Ok(PcapIngestOutput {
+        events: assign_sequences(pending),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(big_endian: bool, block_type: u32, body: &[u8]) -> Vec<u8> {
+        let u32b = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let mut padded = body.to_vec();
+        padded.resize(body.len().next_multiple_of(4), 0);
+        let total = 12 + padded.len() as u32;
+        [&u32b(block_type)[..], &u32b(total), &padded, &u32b(total)].concat()
+    }
+
+    fn capture(big_endian: bool, tsresol: Option<u8>, units: u64, frame: &[u8]) -> Vec<u8> {
+        let u16b = |v: u16| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let u32b = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let shb = [
+            &u32b(BYTE_ORDER_MAGIC)[..],
+            &u16b(1),
+            &u16b(0),
+            &(-1i64).to_le_bytes(),
+        ]
+        .concat();
+        let mut idb = [&u16b(LINKTYPE_ETHERNET)[..], &u16b(0), &u32b(65_535)].concat();
+        if let Some(res) = tsresol {
+            idb.extend_from_slice(&u16b(OPT_IF_TSRESOL));
+            idb.extend_from_slice(&u16b(1));
+            idb.extend_from_slice(&[res, 0, 0, 0]);
+        }
+        idb.extend_from_slice(&[0; 4]);
+        let epb = [
+            &u32b(0)[..],
+            &u32b((units >> 32) as u32),
+            &u32b(units as u32),
+            &u32b(frame.len() as u32),
+            &u32b(frame.len() as u32),
+            frame,
+        ]
+        .concat();
+        [
+            block(big_endian, SECTION_HEADER, &shb),
+            block(big_endian, INTERFACE_DESCRIPTION, &idb),
+            block(big_endian, 0x0000_0005, &[0; 8]),
+            block(big_endian, ENHANCED_PACKET, &epb),
+        ]
+        .concat()
+    }
+
+    fn only_packet(bytes: &[u8]) -> CapturedPacket {
+        let mut reader = PcapngReader::new(bytes);
+        let packet = reader.next_packet().expect("read").expect("packet");
+        assert!(reader.next_packet().expect("read").is_none());
+        packet
+    }
+
+    #[test]
+    fn honours_timestamp_resolution() {
+        let ts = 1_700_000_000_123_456_789u64;
+        let nanos = only_packet(&capture(false, Some(9), ts, &[1, 2, 3]));
+        assert_eq!(nanos.timestamp_ns, Some(ts as i64));
+        assert_eq!(nanos.data, vec![1, 2, 3]);
+
+        let micros = only_packet(&capture(false, None, ts / 1_000, &[1]));
+        assert_eq!(micros.timestamp_ns, Some(1_700_000_000_123_456_000));
+
+        let binary = only_packet(&capture(false, Some(0x80 | 10), 3 << 10, &[1]));
+        assert_eq!(binary.timestamp_ns, Some(3_000_000_000));
+    }
+
+    #[test]
+    fn reads_big_endian_sections() {
+        let packet = only_packet(&capture(true, Some(9), 42, &[7, 8, 9, 10, 11]));
+        assert_eq!(packet.timestamp_ns, Some(42));
+        assert_eq!(packet.data, vec![7, 8, 9, 10, 11]);
+        assert_eq!(packet.link_type, LINKTYPE_ETHERNET);
+    }
+
+    #[test]
+    fn truncated_block_is_an_error() {
+        let bytes = capture(false, Some(9), 42, &[1, 2, 3]);
+        let mut reader = PcapngReader::new(&bytes[..bytes.len() - 6]);
+        assert!(matches!(reader.next_packet(), Err(IngestError::Parse(_))));
+    }
+
+    #[test]
+    fn ingests_generated_pcapng_with_nanosecond_capture_clock() {
+        use crate::gen_pcap::{generate_capture, CaptureFormat, ClockModel};
+        use crate::Framing;
+
+        let path =
+            std::env::temp_dir().join(format!("md_replay_pcapng_{}.pcapng", std::process::id()));
+        let clock = ClockModel {
+            offset_ns: 1_234,
+            drift_ppm: 0.0,
+            jitter_ns: 0,
+        };
+        let symbols = [String::from("AAPL"), String::from("MSFT")];
+        generate_capture(
+            &path,
+            &symbols,
+            300,
+            7,
+            &clock,
+            CaptureFormat::Pcapng,
+            Framing::Raw,
+        )
+        .expect("generate");
+
+        let mut reader = PcapngReader::new(BufReader::new(File::open(&path).expect("open")));
+        let mut packets = 0;
+        while let Some(packet) = reader.next_packet().expect("read") {
+            packets += 1;
+            let payload = &packet.data[42..];
+            if payload.len() < 36 {
+                continue;
+            }
+            let exchange = u64::from_be_bytes(payload[..8].try_into().expect("ts"));
+            assert_eq!(packet.timestamp_ns, Some(exchange as i64 + 1_234));
+        }
+        assert_eq!(packets, 300);
+
+        let out = ingest_pcapng(&path, "X", Framing::Raw).expect("ingest");
+        assert_eq!(out.events.len() + out.issues.len(), 300);
+        assert!(!out.issues.is_empty());
+        assert!(out
+            .events
+            .windows(2)
+            .all(|w| w[0].timestamp_ns <= w[1].timestamp_ns));
+    }
+}
@@ -0,0 +1,349 @@
+//! Polygon.io historical trades (`/v3/trades`) and NBBO quotes
+//! (`/v3/quotes`). Events are stamped with the SIP timestamp, which Polygon
+//! reports in nanoseconds.
+
+use crate::cache::{fetch_cached, CacheKey, ResponseCache};
+use crate::{HttpOptions, IngestError};
+use md_core::{assign_sequences, Event, Payload, PendingEvent, TickTable, Timestamp};
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_URL: &str = "https://api.polygon.io";
+/// Largest page the v3 endpoints return.
+const PAGE_LIMIT: u32 = 50_000;
+
+/// Events are fetched for `[from, to)`. Symbols present in `since` start
+/// just after that instant instead, for incremental updates. Each result
+/// page is read from and saved to `cache` when set.
+#[derive(Debug, Clone)]
+pub struct PolygonOptions {
+    pub api_key: String,
+    pub base_url: String,
+    pub http: HttpOptions,
+    pub from: Timestamp,
+    pub to: Timestamp,
+    pub trades: bool,
+    pub quotes: bool,
+    pub since: BTreeMap<String, Timestamp>,
+    pub cache: Option<ResponseCache>,
+}
+
+impl Default for PolygonOptions {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: String::from(DEFAULT_URL),
+            http: HttpOptions::default(),
+            from: Timestamp::UNIX_EPOCH,
+            to: Timestamp::UNIX_EPOCH,
+            trades: true,
+            quotes: true,
+            since: BTreeMap::new(),
+            cache: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+    next_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeRow {
+    sip_timestamp: i64,
+    #[serde(default)]
+    sequence_number: i64,
+    price: f64,
+    size: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteRow {
+    sip_timestamp: i64,
+    #[serde(default)]
+    sequence_number: i64,
+    #[serde(default)]
+    bid_price: f64,
+    #[serde(default)]
+    bid_size: f64,
+    #[serde(default)]
+    ask_price: f64,
+    #[serde(default)]
+    ask_size: f64,
+}
+
+pub async fn ingest_polygon(
+    symbols: &[String],
+    venue: &str,
+    ticks: &TickTable,
+    options: &PolygonOptions,
+) -> Result<Vec<Event>, IngestError> {
+    if symbols.is_empty() {
+        return Err(IngestError::Parse(String::from("empty symbols list")));
+    }
+    if options.api_key.is_empty() {
+        return Err(IngestError::Parse(String::from("polygon needs an API key")));
+    }
+    let client = options.http.build_client()?;
+    let mut pending = Vec::new();
+    for symbol in symbols {
+        let from = match options.since.get(symbol) {
+            Some(since) => (*since).max(options.from).saturating_add_nanos(1),
+            None => options.from,
+        };
+        if from >= options.to {
+            continue;
+        }
+        // (timestamp, SIP sequence, event) per symbol, merged below.
+        let mut rows = Vec::<(Timestamp, i64, Payload)>::new();
+        if options.trades {
+            for row in fetch_all::<TradeRow>(&client, options, "trades", symbol, from).await? {
+                rows.push((
+                    Timestamp::from_nanos(row.sip_timestamp),
+                    row.sequence_number,
+                    Payload::Trade {
+                        price_ticks: price_ticks(ticks, symbol, row.price)?,
+                        size: row.size.round() as i64,
+                    },
+                ));
+            }
+        }
+        if options.quotes {
+            for row in fetch_all::<QuoteRow>(&client, options, "quotes", symbol, from).await? {
+                rows.push((
+                    Timestamp::from_nanos(row.sip_timestamp),
+                    row.sequence_number,
+                    Payload::Quote {
+                        bid_px: price_ticks(ticks, symbol, row.bid_price)?,
+                        bid_sz: row.bid_size.round() as i64,
+                        ask_px: price_ticks(ticks, symbol, row.ask_price)?,
+                        ask_sz: row.ask_size.round() as i64,
+                    },
+                ));
+            }
+        }
+        rows.sort_by_key(|(ts, seq, _)| (*ts, *seq));
+        for (timestamp_ns, seq, payload) in rows {
+            let ingest_order = pending.len() as u64;
+            pending.push(PendingEvent {
+                timestamp_ns,
+                venue: venue.to_string(),
+                symbol: symbol.clone(),
+                payload,
+                ingest_order,
+                source_sequence: u64::try_from(seq).ok().filter(|seq| *seq > 0),
+            });
+        }
+    }
+    if pending.is_empty() && options.since.is_empty() {
+        return Err(IngestError::Parse(String::from("no events returned")));
+    }
+    Ok(assign_sequences(pending))
+}
+
+fn price_ticks(ticks: &TickTable, symbol: &str, value: f64) -> Result<i64, IngestError> {
+    if !value.is_finite() {
+        return Err(IngestError::Parse(format!(
+            "{symbol}: non-finite price {value}"
+        )));
+    }
+    // `Display` gives the shortest string that round-trips, so 187.15
+    // stays 187.15 rather than picking up binary noise.
+    ticks
+        .price_str_to_ticks(symbol, &value.to_string())
+        .map_err(IngestError::from)
+}
+
+/// Follows `next_url` until the last page of `kind` for `symbol`.
+async fn fetch_all<T: for<'de> Deserialize<'de>>(
+    client: &Client,
+    options: &PolygonOptions,
+    kind: &str,
+    symbol: &str,
+    from: Timestamp,
+) -> Result<Vec<T>, IngestError> {
+    let mut url = Url::parse(&options.base_url).map_err(|e| IngestError::Parse(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| IngestError::Parse(String::from("invalid polygon url")))?
+        .pop_if_empty()
+        .extend(["v3", kind, symbol]);
+    url.query_pairs_mut()
+        .append_pair("timestamp.gte", &from.as_nanos().to_string())
+        .append_pair("timestamp.lt", &options.to.as_nanos().to_string())
+        .append_pair("order", "asc")
+        .append_pair("sort", "timestamp")
+        .append_pair("limit", &PAGE_LIMIT.to_string());
+
+    let mut rows = Vec::new();
+    let mut next = Some(url);
+    let mut pages = 0;
+    while let Some(url) = next.take() {
+        pages += 1;
+        // The window is absolute, so incremental fetches are cached too.
+        let range = format!("{}..{}.p{pages}", from.as_nanos(), options.to.as_nanos());
+        let key = CacheKey {
+            provider: "polygon",
+            symbol,
+            range: &range,
+            interval: kind,
+        };
+        let fetch = async {
+            let response = client.get(url).bearer_auth(&options.api_key).send().await?;
+            let status = response.status();
+            let body = response.text().await?;
+            if !status.is_success() {
+                return Err(api_error(symbol, kind, status, &body));
+            }
+            Ok(body)
+        };
+        let page: Page<T> = fetch_cached(options.cache.as_ref(), &key, fetch, |raw| {
+            serde_json::from_str(raw).map_err(IngestError::from)
+        })
+        .await?;
+        rows.extend(page.results);
+        next = page
+            .next_url
+            .map(|raw| Url::parse(&raw).map_err(|e| IngestError::Parse(e.to_string())))
+            .transpose()?;
+    }
+    Ok(rows)
+}
+
+fn api_error(symbol: &str, kind: &str, status: StatusCode, body: &str) -> IngestError {
+    let detail = serde_json::from_str::<ErrorBody>(body)
+        .ok()
+        .and_then(|e| e.error.or(e.message))
+        .unwrap_or_else(|| body.chars().take(200).collect());
+    IngestError::Parse(format!(
+        "{symbol} {kind}: polygon returned {status}: {detail}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers each request with the first body whose key prefixes the
+    /// request target, after checking the bearer token. `{base}` in a body
+    /// becomes the server's own address, for `next_url`.
+    async fn serve(pages: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        let own = base.clone();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 8192];
+                let n = conn.read(&mut buf).await.expect("read");
+                let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                let target = head.split_whitespace().nth(1).unwrap_or("").to_string();
+                let (status, body) = if head.contains("authorization: Bearer key\r\n") {
+                    let body = pages
+                        .iter()
+                        .find(|(prefix, _)| target.starts_with(prefix))
+                        .map_or(r#"{"results":[]}"#, |(_, body)| body);
+                    ("200 OK", body.replace("{base}", &own))
+                } else {
+                    (
+                        "401 Unauthorized",
+                        String::from(r#"{"status":"ERROR","error":"Unknown API Key"}"#),
+                    )
+                };
+                let reply = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                conn.write_all(reply.as_bytes()).await.expect("write");
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn merges_paged_trades_and_quotes_by_sip_time() {
+        let base = serve(vec![
+            (
+                "/v3/trades/AAPL?cursor=p2",
+                r#"{"results":[{"sip_timestamp":1704204000000000300,"sequence_number":9,"price":187.16,"size":5}],"status":"OK"}"#,
+            ),
+            (
+                "/v3/trades/AAPL?timestamp.gte=1704204000000000000&timestamp.lt=1704204001000000000",
+                r#"{"results":[{"sip_timestamp":1704204000000000100,"sequence_number":3,"price":187.15,"size":100}],"next_url":"{base}/v3/trades/AAPL?cursor=p2"}"#,
+            ),
+            (
+                "/v3/quotes/AAPL?",
+                r#"{"results":[{"sip_timestamp":1704204000000000200,"sequence_number":4,"bid_price":187.14,"bid_size":2,"ask_price":187.17,"ask_size":3}],"status":"OK"}"#,
+            ),
+        ])
+        .await;
+
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let mut options = PolygonOptions {
+            api_key: String::from("key"),
+            base_url: base,
+            from: Timestamp::from_nanos(1_704_204_000_000_000_000),
+            to: Timestamp::from_nanos(1_704_204_001_000_000_000),
+            ..PolygonOptions::default()
+        };
+        let symbols = [String::from("AAPL")];
+        let events = ingest_polygon(&symbols, "XNAS", &ticks, &options)
+            .await
+            .expect("ingest");
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (
+                    e.timestamp_ns.as_nanos() % 1_000,
+                    e.sequence,
+                    e.source_sequence
+                ))
+                .collect::<Vec<_>>(),
+            vec![(100, 1, Some(3)), (200, 2, Some(4)), (300, 3, Some(9))]
+        );
+        assert_eq!(
+            events[0].payload,
+            Payload::Trade {
+                price_ticks: 18_715,
+                size: 100
+            }
+        );
+        assert_eq!(
+            events[1].payload,
+            Payload::Quote {
+                bid_px: 18_714,
+                bid_sz: 2,
+                ask_px: 18_717,
+                ask_sz: 3
+            }
+        );
+
+        // Incremental: only events after the last stored one are kept.
+        options.since = BTreeMap::from([(
+            String::from("AAPL"),
+            Timestamp::from_nanos(1_704_204_001_000_000_000),
+        )]);
+        assert!(ingest_polygon(&symbols, "XNAS", &ticks, &options)
+            .await
+            .expect("up to date")
+            .is_empty());
+
+        options.since.clear();
+        options.api_key = String::from("wrong");
+        let err = ingest_polygon(&symbols, "XNAS", &ticks, &options)
+            .await
+            .expect_err("unauthorized");
+        assert!(err.to_string().contains("Unknown API Key"), "{err}");
+    }
+}
@@ -0,0 +1,141 @@
+//! One summary shape for every ingest, so automation reads the same JSON
+//! whatever the source was.
+
+use crate::frames::{IssueKind, ParseIssue};
+use md_core::{Event, Payload, Timestamp};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where a log came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// Ingest kind: `csv_a`, `jsonl`, `pcap`, a provider name, ...
+    pub source: String,
+    /// Input files, or the symbols requested from a provider.
+    pub inputs: Vec<String>,
+    pub venue: String,
+    pub out: String,
+    /// Version of the ingest that wrote the log.
+    pub version: String,
+}
+
+impl Provenance {
+    pub fn new(source: &str, inputs: Vec<String>, venue: &str, out: &Path) -> Self {
+        Self {
+            source: source.to_string(),
+            inputs,
+            venue: venue.to_string(),
+            out: out.display().to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IngestReport {
+    pub provenance: Provenance,
+    /// Events written.
+    pub events: u64,
+    pub trades: u64,
+    pub quotes: u64,
+    /// Events written per symbol.
+    pub symbols: BTreeMap<String, u64>,
+    /// Read but not written: malformed or late messages, duplicates,
+    /// events older than an appended log.
+    pub skipped: u64,
+    /// Parse issues by kind.
+    pub issues: BTreeMap<String, u64>,
+    pub first_timestamp_ns: Option<Timestamp>,
+    pub last_timestamp_ns: Option<Timestamp>,
+}
+
+impl IngestReport {
+    pub fn new(provenance: Provenance) -> Self {
+        Self {
+            provenance,
+            ..Self::default()
+        }
+    }
+
+    /// Counts `event` as written.
+    pub fn record(&mut self, event: &Event) {
+        self.events += 1;
+        match event.payload {
+            Payload::Trade { .. } => self.trades += 1,
+            Payload::Quote { .. } => self.quotes += 1,
+        }
+        *self.symbols.entry(event.symbol.clone()).or_default() += 1;
+        let ts = event.timestamp_ns;
+        self.first_timestamp_ns = Some(self.first_timestamp_ns.map_or(ts, |first| first.min(ts)));
+        self.last_timestamp_ns = Some(self.last_timestamp_ns.map_or(ts, |last| last.max(ts)));
+    }
+
+    pub fn record_all(&mut self, events: &[Event]) {
+        for event in events {
+            self.record(event);
+        }
+    }
+
+    /// Malformed and late messages count as skipped; a sequence gap is
+    /// data that never arrived, so it does not.
+    pub fn record_issue(&mut self, issue: &ParseIssue) {
+        *self
+            .issues
+            .entry(issue.kind.as_str().to_string())
+            .or_default() += 1;
+        if issue.kind != IssueKind::SequenceGap {
+            self.skipped += 1;
+        }
+    }
+
+    pub fn record_issues(&mut self, issues: &[ParseIssue]) {
+        for issue in issues {
+            self.record_issue(issue);
+        }
+    }
+
+    pub fn skip(&mut self, count: u64) {
+        self.skipped += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    #[test]
+    fn counts_events_issues_and_time_range() {
+        let out = Path::new("data/x.eventlog");
+        let mut report = IngestReport::new(Provenance::new("pcap", vec![], "XNAS", out));
+        let quote = QuoteTicks {
+            bid_px: 1,
+            bid_sz: 1,
+            ask_px: 2,
+            ask_sz: 1,
+        };
+        report.record_all(&[
+            Event::trade(20, 1, "XNAS", "AAPL", 100, 1),
+            Event::quote(10, 2, "XNAS", "MSFT", quote),
+            Event::trade(30, 3, "XNAS", "AAPL", 101, 1),
+        ]);
+        let issue = |kind| ParseIssue {
+            packet_index: 0,
+            offset: 0,
+            kind,
+            detail: String::new(),
+            gap: None,
+        };
+        report.record_issues(&[issue(IssueKind::Malformed), issue(IssueKind::SequenceGap)]);
+        report.skip(2);
+
+        assert_eq!((report.events, report.trades, report.quotes), (3, 2, 1));
+        assert_eq!(report.symbols.get("AAPL"), Some(&2));
+        assert_eq!(report.skipped, 3);
+        assert_eq!(report.issues.get("sequence_gap"), Some(&1));
+        assert_eq!(report.first_timestamp_ns, Some(Timestamp::from_nanos(10)));
+        assert_eq!(report.last_timestamp_ns, Some(Timestamp::from_nanos(30)));
+        assert_eq!(report.provenance.version, env!("CARGO_PKG_VERSION"));
+    }
+}
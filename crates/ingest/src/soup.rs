@@ -0,0 +1,423 @@
+//! SoupBinTCP, the TCP session protocol ITCH is served over for recovery.
+//! Each packet is a big-endian `u16` length covering the type byte and
+//! payload, then the type byte, then the payload. Only sequenced data
+//! (`S`) packets carry ITCH messages.
+
+use crate::frames::{
+    extract_tcp_segment, warn_issue, Framing, IssueKind, ItchDecoder, ParseIssue, PcapIngestOutput,
+    TcpFlow,
+};
+use crate::pcapng::{LINKTYPE_ETHERNET, SECTION_HEADER};
+use crate::{IngestError, PCAP_MAGICS};
+use md_core::{assign_sequences, PendingEvent};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const SEQUENCED_DATA: u8 = b'S';
+/// Packet types that carry nothing to ingest: debug, login accepted and
+/// rejected, heartbeats, end of session, and the client-side packets.
+const CONTROL_TYPES: &[u8] = b"+AJHZLURO";
+/// Out-of-order bytes buffered per TCP stream before the missing data is
+/// given up on.
+const MAX_PENDING_BYTES: usize = 64 << 20;
+
+/// Splits a byte stream into SoupBinTCP packets, carrying partial packets
+/// over between pushes.
+#[derive(Debug, Default)]
+struct SoupStream {
+    buf: Vec<u8>,
+    /// Stream offset of `buf[0]`.
+    offset: u64,
+}
+
+impl SoupStream {
+    fn push(&mut self, data: &[u8], mut on_packet: impl FnMut(u64, u8, &[u8])) {
+        self.buf.extend_from_slice(data);
+        let mut at = 0;
+        while let Some(len) = self.buf.get(at..at + 2) {
+            let len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+            let Some(packet) = self.buf.get(at + 2..at + 2 + len) else {
+                break;
+            };
+            match packet.split_first() {
+                Some((&packet_type, payload)) => {
+                    on_packet(self.offset + at as u64, packet_type, payload)
+                }
+                None => on_packet(self.offset + at as u64, 0, &[]),
+            }
+            at += 2 + len;
+        }
+        self.buf.drain(..at);
+        self.offset += at as u64;
+    }
+}
+
+#[derive(Debug, Default)]
+struct TcpStream {
+    /// Sequence number of the first payload byte.
+    base: Option<u32>,
+    delivered: u64,
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_bytes: usize,
+    stalled: bool,
+    soup: SoupStream,
+}
+
+/// Decodes SoupBinTCP sessions from a raw stream or from the TCP segments
+/// of a capture.
+struct SoupIngest<'a> {
+    decoder: ItchDecoder<'a>,
+    streams: BTreeMap<TcpFlow, TcpStream>,
+    pending: Vec<PendingEvent>,
+    issues: Vec<ParseIssue>,
+}
+
+impl<'a> SoupIngest<'a> {
+    fn new(venue: &'a str) -> Self {
+        Self {
+            decoder: ItchDecoder::new(venue, Framing::Raw),
+            streams: BTreeMap::new(),
+            pending: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    fn decode(
+        decoder: &mut ItchDecoder<'_>,
+        soup: &mut SoupStream,
+        data: &[u8],
+        pending: &mut Vec<PendingEvent>,
+        issues: &mut Vec<ParseIssue>,
+    ) {
+        soup.push(data, |offset, packet_type, payload| {
+            let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+            if packet_type == SEQUENCED_DATA {
+                match decoder.decode_message(payload, offset.saturating_add(3)) {
+                    Ok(evt) => pending.push(evt),
+                    Err(issue) => issues.push(issue),
+                }
+            } else if !CONTROL_TYPES.contains(&packet_type) {
+                issues.push(decoder.issue(
+                    offset,
+                    IssueKind::Malformed,
+                    format!("unknown soupbintcp packet type 0x{packet_type:02x}"),
+                ));
+            }
+        });
+    }
+
+    fn push_raw(&mut self, soup: &mut SoupStream, data: &[u8]) {
+        Self::decode(
+            &mut self.decoder,
+            soup,
+            data,
+            &mut self.pending,
+            &mut self.issues,
+        );
+    }
+
+    fn push_frame(&mut self, frame: &[u8]) {
+        self.decoder.packet_index += 1;
+        let segment = match extract_tcp_segment(frame) {
+            Ok(segment) => segment,
+            Err((offset, detail)) => {
+                let issue = self.decoder.issue(offset, IssueKind::Malformed, detail);
+                self.issues.push(issue);
+                return;
+            }
+        };
+        let stream = self.streams.entry(segment.flow).or_default();
+        if stream.stalled {
+            return;
+        }
+        let start = if segment.syn {
+            segment.seq.wrapping_add(1)
+        } else {
+            segment.seq
+        };
+        let base = *stream.base.get_or_insert(start);
+        if segment.payload.is_empty() {
+            return;
+        }
+
+        // Relative offsets wrap after 4 GiB, far beyond any session dump.
+        let rel = u64::from(start.wrapping_sub(base));
+        let end = rel + segment.payload.len() as u64;
+        if end <= stream.delivered {
+            return;
+        }
+        if rel > stream.delivered {
+            let kept = stream.pending.entry(rel).or_default();
+            if kept.len() < segment.payload.len() {
+                stream.pending_bytes += segment.payload.len() - kept.len();
+                *kept = segment.payload.to_vec();
+            }
+            if stream.pending_bytes > MAX_PENDING_BYTES {
+                let issue = stream_gap(&self.decoder, &segment.flow, stream);
+                self.issues.push(issue);
+                stream.stalled = true;
+                stream.pending.clear();
+            }
+            return;
+        }
+
+        let mut ready = vec![segment.payload[(stream.delivered - rel) as usize..].to_vec()];
+        stream.delivered = end;
+        while let Some(entry) = stream.pending.first_entry() {
+            let rel = *entry.key();
+            if rel > stream.delivered {
+                break;
+            }
+            let data = entry.remove();
+            stream.pending_bytes -= data.len();
+            let end = rel + data.len() as u64;
+            if end > stream.delivered {
+                ready.push(data[(stream.delivered - rel) as usize..].to_vec());
+                stream.delivered = end;
+            }
+        }
+        for data in ready {
+            Self::decode(
+                &mut self.decoder,
+                &mut stream.soup,
+                &data,
+                &mut self.pending,
+                &mut self.issues,
+            );
+        }
+    }
+
+    fn finish(mut self) -> PcapIngestOutput {
+        for (flow, stream) in &self.streams {
+            if stream.stalled {
+                continue;
+            }
+            if !stream.pending.is_empty() {
+                self.issues.push(stream_gap(&self.decoder, flow, stream));
+            } else if !stream.soup.buf.is_empty() {
+                self.issues
+                    .push(truncated(&self.decoder, &flow.to_string(), &stream.soup));
+            }
+        }
+        for issue in &self.issues {
+            warn_issue(issue);
+        }
+        PcapIngestOutput {
+            events: assign_sequences(self.pending),
+            issues: self.issues,
+        }
+    }
+}
+
+fn stream_gap(decoder: &ItchDecoder<'_>, flow: &TcpFlow, stream: &TcpStream) -> ParseIssue {
+    let resume = stream
+        .pending
+        .keys()
+        .next()
+        .copied()
+        .unwrap_or(stream.delivered);
+    decoder.issue(
+        usize::try_from(stream.delivered).unwrap_or(usize::MAX),
+        IssueKind::SequenceGap,
+        format!(
+            "tcp stream {flow} missing bytes {}..{resume}, rest of stream dropped",
+            stream.delivered
+        ),
+    )
+}
+
+fn truncated(decoder: &ItchDecoder<'_>, stream_name: &str, soup: &SoupStream) -> ParseIssue {
+    decoder.issue(
+        usize::try_from(soup.offset).unwrap_or(usize::MAX),
+        IssueKind::Malformed,
+        format!(
+            "{stream_name} ends inside a soupbintcp packet ({} bytes)",
+            soup.buf.len()
+        ),
+    )
+}
+
+/// Ingests a SoupBinTCP session dump carrying mock ITCH. pcap and pcapng
+/// captures are recognised by their magic and every TCP stream in them is
+/// reassembled and decoded; anything else is read as the raw server byte
+/// stream. Issues from captures carry the frame that completed the bytes
+/// as `packet_index`; for raw streams `offset` is the byte offset of the
+/// SoupBinTCP packet. Classic pcap needs the `pcap` feature.
+pub fn ingest_soup(path: &Path, venue: &str) -> Result<PcapIngestOutput, IngestError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut head = Vec::with_capacity(4);
+    file.by_ref().take(4).read_to_end(&mut head)?;
+    let magic = <[u8; 4]>::try_from(head.as_slice()).map(u32::from_le_bytes);
+    let mut ingest = SoupIngest::new(venue);
+
+    match magic {
+        Ok(magic) if magic == SECTION_HEADER || PCAP_MAGICS.contains(&magic) => {
+            crate::for_each_capture_frame(path, |link_type, frame| {
+                if link_type == LINKTYPE_ETHERNET {
+                    ingest.push_frame(frame);
+                } else {
+                    ingest.decoder.packet_index += 1;
+                    let issue = ingest.decoder.issue(
+                        0,
+                        IssueKind::Malformed,
+                        format!("unsupported link type {link_type}"),
+                    );
+                    ingest.issues.push(issue);
+                }
+            })?;
+        }
+        _ => {
+            let mut soup = SoupStream::default();
+            ingest.push_raw(&mut soup, &head);
+            let mut chunk = vec![0u8; 64 * 1024];
+            loop {
+                let n = file.read(&mut chunk)?;
+                if n == 0 {
+                    break;
+                }
+                ingest.push_raw(&mut soup, &chunk[..n]);
+            }
+            if !soup.buf.is_empty() {
+                let issue = truncated(&ingest.decoder, "stream", &soup);
+                ingest.issues.push(issue);
+            }
+        }
+    }
+    Ok(ingest.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_pcap::trade_payload;
+    use md_core::Payload;
+
+    fn soup_packet(packet_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 1) as u16).to_be_bytes().to_vec();
+        out.push(packet_type);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn session(prices: &[i64]) -> Vec<u8> {
+        // Login accepted: 10-byte session, 20-byte next sequence number.
+        let login = format!("{:<10}{:>20}", "SESSION1", 1);
+        let mut out = soup_packet(b'A', login.as_bytes());
+        for (i, &px) in prices.iter().enumerate() {
+            let ts = 1_700_000_000_000_000_000 + i as u64;
+            out.extend(soup_packet(b'S', &trade_payload(ts, "AAPL", px, 1)));
+            out.extend(soup_packet(b'H', &[]));
+        }
+        out.extend(soup_packet(b'Z', &[]));
+        out
+    }
+
+    fn prices(out: &PcapIngestOutput) -> Vec<i64> {
+        out.events
+            .iter()
+            .filter_map(|e| match e.payload {
+                Payload::Trade { price_ticks, .. } => Some(price_ticks),
+                Payload::Quote { .. } => None,
+            })
+            .collect()
+    }
+
+    fn tcp_frame(seq: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 14 + 20 + 20];
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        frame[14] = 0x45;
+        frame[16..18].copy_from_slice(&((40 + payload.len()) as u16).to_be_bytes());
+        frame[23] = 6;
+        frame[26..30].copy_from_slice(&[10, 0, 0, 1]);
+        frame[30..34].copy_from_slice(&[10, 0, 0, 2]);
+        frame[34..36].copy_from_slice(&26_400u16.to_be_bytes());
+        frame[36..38].copy_from_slice(&50_123u16.to_be_bytes());
+        frame[38..42].copy_from_slice(&seq.to_be_bytes());
+        frame[46] = 5 << 4;
+        frame[47] = if syn { 0x12 } else { 0x18 };
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn raw_stream_decodes_across_arbitrary_chunks() {
+        let mut bytes = session(&[100, 101, 102]);
+        bytes.extend(soup_packet(b'?', &[1, 2]));
+        bytes.extend(&soup_packet(b'S', &[0; 10])[..5]);
+
+        let mut ingest = SoupIngest::new("X");
+        let mut soup = SoupStream::default();
+        for chunk in bytes.chunks(7) {
+            ingest.push_raw(&mut soup, chunk);
+        }
+        assert_eq!(soup.buf.len(), 5);
+        let out = ingest.finish();
+        assert_eq!(prices(&out), vec![100, 101, 102]);
+        assert_eq!(out.issues.len(), 1);
+        assert!(out.issues[0].detail.contains("0x3f"));
+    }
+
+    #[test]
+    fn reassembles_out_of_order_and_retransmitted_segments() {
+        let bytes = session(&[100, 101, 102, 103]);
+        let isn = u32::MAX - 20;
+        let at = |i: usize| isn.wrapping_add(1).wrapping_add(i as u32);
+        let (a, rest) = bytes.split_at(30);
+        let (b, c) = rest.split_at(40);
+
+        let mut ingest = SoupIngest::new("X");
+        ingest.push_frame(&tcp_frame(isn, true, &[]));
+        ingest.push_frame(&tcp_frame(at(0), false, a));
+        ingest.push_frame(&tcp_frame(at(70), false, c));
+        ingest.push_frame(&tcp_frame(at(0), false, a));
+        ingest.push_frame(&tcp_frame(at(30), false, b));
+        ingest.push_frame(&tcp_frame(at(10), false, &bytes[10..50]));
+        let out = ingest.finish();
+        assert_eq!(prices(&out), vec![100, 101, 102, 103]);
+        assert!(out.issues.is_empty(), "{:?}", out.issues);
+    }
+
+    #[test]
+    fn missing_segment_is_reported_as_a_gap() {
+        let bytes = session(&[100, 101, 102]);
+        let mut ingest = SoupIngest::new("X");
+        // Login is 33 bytes and each trade plus heartbeat 42, so the first
+        // trade is complete at 80 and the hole swallows the second.
+        ingest.push_frame(&tcp_frame(1000, false, &bytes[..80]));
+        ingest.push_frame(&tcp_frame(1000 + 100, false, &bytes[100..]));
+        let out = ingest.finish();
+        assert_eq!(prices(&out), vec![100]);
+        let gaps = out
+            .issues
+            .iter()
+            .filter(|i| i.kind == IssueKind::SequenceGap)
+            .collect::<Vec<_>>();
+        assert_eq!(gaps.len(), 1);
+        assert!(gaps[0].detail.contains("missing bytes 80..100"));
+    }
+
+    #[test]
+    fn ingests_pcapng_session_capture() {
+        use crate::gen_pcap::{write_pcapng_header, write_pcapng_packet};
+
+        let path =
+            std::env::temp_dir().join(format!("md_replay_soup_{}.pcapng", std::process::id()));
+        let bytes = session(&[100, 101]);
+        let mut file = Vec::new();
+        write_pcapng_header(&mut file).expect("header");
+        write_pcapng_packet(&mut file, 1, &tcp_frame(7, true, &[])).expect("syn");
+        write_pcapng_packet(&mut file, 2, &tcp_frame(8, false, &bytes)).expect("data");
+        std::fs::write(&path, file).expect("write");
+
+        let out = ingest_soup(&path, "X").expect("ingest");
+        assert_eq!(prices(&out), vec![100, 101]);
+        assert!(out.issues.is_empty());
+
+        let raw = std::env::temp_dir().join(format!("md_replay_soup_{}.soup", std::process::id()));
+        std::fs::write(&raw, &bytes).expect("write raw");
+        let out = ingest_soup(&raw, "X").expect("ingest raw");
+        assert_eq!(prices(&out), vec![100, 101]);
+    }
+}
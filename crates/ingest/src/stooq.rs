@@ -0,0 +1,205 @@
+//! Stooq daily bars from its CSV download endpoint. No key is needed.
+//! Symbols use Stooq's market suffixes (`AAPL.US`, `VOD.UK`, `^SPX`).
+
+use crate::cache::{fetch_cached, CacheKey, ResponseCache};
+use crate::yahoo::{bars_to_events, Bar, BarExpansion};
+use crate::{HttpOptions, IngestError};
+use chrono::NaiveDate;
+use md_core::{Event, TickTable, Timestamp};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_URL: &str = "https://stooq.com";
+pub(crate) const DAY_NS: i64 = 86_400_000_000_000;
+
+/// `from`/`to` bound the dates fetched (both inclusive); the whole history
+/// is fetched when unset. Symbols present in `since` keep only bars
+/// strictly after it. Responses are read from and saved to `cache` when
+/// set, except for symbols in `since`.
+#[derive(Debug, Clone)]
+pub struct StooqOptions {
+    pub base_url: String,
+    pub http: HttpOptions,
+    pub from: Option<Timestamp>,
+    pub to: Option<Timestamp>,
+    pub expansion: BarExpansion,
+    pub since: BTreeMap<String, Timestamp>,
+    pub cache: Option<ResponseCache>,
+}
+
+impl Default for StooqOptions {
+    fn default() -> Self {
+        Self {
+            base_url: String::from(DEFAULT_URL),
+            http: HttpOptions::default(),
+            from: None,
+            to: None,
+            expansion: BarExpansion::default(),
+            since: BTreeMap::new(),
+            cache: None,
+        }
+    }
+}
+
+pub async fn ingest_stooq(
+    symbols: &[String],
+    venue: &str,
+    ticks: &TickTable,
+    options: &StooqOptions,
+) -> Result<Vec<Event>, IngestError> {
+    if symbols.is_empty() {
+        return Err(IngestError::Parse(String::from("empty symbols list")));
+    }
+    let client = options.http.build_client()?;
+    let mut per_symbol = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let from = match (options.from, options.since.get(symbol)) {
+            (Some(from), Some(since)) => Some(from.max(*since)),
+            (from, since) => from.or(since.copied()),
+        };
+        let range = format!(
+            "{}..{}",
+            from.map(utc_date).unwrap_or_default(),
+            options.to.map(utc_date).unwrap_or_default()
+        );
+        let key = CacheKey {
+            provider: "stooq",
+            symbol,
+            range: &range,
+            interval: "1d",
+        };
+        let cache = options
+            .cache
+            .as_ref()
+            .filter(|_| !options.since.contains_key(symbol));
+        let fetch = fetch_daily(&client, options, symbol, from);
+        let bars = fetch_cached(cache, &key, fetch, |raw| parse_daily_csv(raw, symbol)).await?;
+        per_symbol.push((symbol.clone(), bars));
+    }
+    bars_to_events(
+        &per_symbol,
+        venue,
+        ticks,
+        DAY_NS,
+        &options.expansion,
+        &options.since,
+    )
+}
+
+/// `YYYY-MM-DD` of a timestamp, in UTC.
+pub(crate) fn utc_date(ts: Timestamp) -> String {
+    chrono::DateTime::from_timestamp_nanos(ts.as_nanos())
+        .date_naive()
+        .to_string()
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` date, the time daily bars are stamped at.
+pub(crate) fn date_start(symbol: &str, raw: &str) -> Result<Timestamp, IngestError> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|t| t.and_utc().timestamp_nanos_opt())
+        .map(Timestamp::from_nanos)
+        .ok_or_else(|| IngestError::Parse(format!("{symbol}: bad date {raw}")))
+}
+
+async fn fetch_daily(
+    client: &Client,
+    options: &StooqOptions,
+    symbol: &str,
+    from: Option<Timestamp>,
+) -> Result<String, IngestError> {
+    let mut url = Url::parse(&options.base_url).map_err(|e| IngestError::Parse(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| IngestError::Parse(String::from("invalid stooq url")))?
+        .pop_if_empty()
+        .extend(["q", "d", "l", ""]);
+    {
+        let compact = |ts: Timestamp| utc_date(ts).replace('-', "");
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("s", &symbol.to_ascii_lowercase())
+            .append_pair("i", "d");
+        if let Some(from) = from {
+            query.append_pair("d1", &compact(from));
+        }
+        if let Some(to) = options.to {
+            query.append_pair("d2", &compact(to));
+        }
+    }
+    let response = client.get(url).send().await?.error_for_status()?;
+    response.text().await.map_err(IngestError::from)
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyRow {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Open")]
+    open: Option<f64>,
+    #[serde(rename = "High")]
+    high: Option<f64>,
+    #[serde(rename = "Low")]
+    low: Option<f64>,
+    #[serde(rename = "Close")]
+    close: Option<f64>,
+    /// Absent for indices and FX.
+    #[serde(rename = "Volume", default)]
+    volume: Option<f64>,
+}
+
+/// Bars in file order, which is ascending by date.
+fn parse_daily_csv(raw: &str, symbol: &str) -> Result<Vec<Bar>, IngestError> {
+    // Unknown symbols answer "No data" instead of a CSV.
+    if !raw.starts_with("Date,") {
+        let detail = raw.lines().next().unwrap_or("empty response").trim();
+        return Err(IngestError::Parse(format!("{symbol}: {detail}")));
+    }
+    let mut reader = ::csv::Reader::from_reader(raw.as_bytes());
+    let mut bars = Vec::new();
+    for row in reader.deserialize::<DailyRow>() {
+        let row = row?;
+        let finite = |v: Option<f64>| v.filter(|v| v.is_finite());
+        bars.push(Bar {
+            start: date_start(symbol, &row.date)?,
+            open: finite(row.open),
+            high: finite(row.high),
+            low: finite(row.low),
+            close: finite(row.close),
+            volume: finite(row.volume).map(|v| v.round() as i64),
+        });
+    }
+    Ok(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_daily_csv() {
+        let raw = "Date,Open,High,Low,Close,Volume\n\
+                   2024-01-02,187.15,188.44,183.885,185.64,82488674\n\
+                   2024-01-03,184.22,185.88,183.43,184.25,58414460\n";
+        let bars = parse_daily_csv(raw, "AAPL.US").expect("parse");
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            bars[0].start,
+            Timestamp::from_rfc3339("2024-01-02T00:00:00Z").expect("ts")
+        );
+        assert_eq!(bars[1].close, Some(184.25));
+        assert_eq!(bars[0].volume, Some(82_488_674));
+
+        let index = parse_daily_csv("Date,Open,High,Low,Close\n2024-01-02,1,2,0.5,1.5\n", "^SPX")
+            .expect("no volume column");
+        assert_eq!(index[0].volume, None);
+
+        let err = parse_daily_csv("No data", "NOPE.US").expect_err("no data");
+        assert!(err.to_string().contains("NOPE.US: No data"), "{err}");
+        assert_eq!(
+            utc_date(Timestamp::from_rfc3339("2024-03-05T23:59:59Z").expect("ts")),
+            "2024-03-05"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Ingest that holds only a reorder window in memory, for tapes larger than
+//! RAM.
+
+use crate::files::in_file;
+use crate::live::Resequencer;
+use crate::IngestError;
+use md_core::{Event, PendingEvent, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Parses one file, handing each row to the callback.
+pub type RowVisitor<'a> = dyn Fn(&Path, &mut dyn FnMut(PendingEvent) -> Result<(), IngestError>) -> Result<(), IngestError>
+    + Sync
+    + 'a;
+
+/// Symbols of every row in `paths`, for the header of a log written by
+/// [`stream_files`]. This is a full parse, so bad rows fail here, before
+/// anything is written.
+pub fn scan_symbols(paths: &[PathBuf], visit: &RowVisitor) -> Result<Vec<String>, IngestError> {
+    let mut symbols = BTreeSet::new();
+    for path in paths {
+        visit(path, &mut |event| {
+            symbols.insert(event.symbol);
+            Ok(())
+        })
+        .map_err(|err| in_file(path, err))?;
+    }
+    Ok(symbols.into_iter().collect())
+}
+
+/// Reads `paths` one after another and hands `sink` sequenced events in
+/// timestamp order, in batches. A row is held until the input has moved
+/// `window_ns` past it, so rows may arrive up to that much out of order,
+/// across file boundaries too. An older row is an error rather than being
+/// dropped. Within the window the result matches
+/// [`ingest_files`](crate::ingest_files). Returns the number of events.
+pub fn stream_files<S, E>(
+    paths: &[PathBuf],
+    window_ns: u64,
+    visit: &RowVisitor,
+    mut sink: S,
+) -> Result<u64, E>
+where
+    S: FnMut(Vec<Event>) -> Result<(), E>,
+    E: From<IngestError>,
+{
+    stream_files_from(paths, window_ns, visit, None, |batch, _| sink(batch))
+}
+
+/// How far a [`stream_files_from`] run has got. Events leave in order of
+/// timestamp, then row, so every row up to the last one handed to the
+/// sink has been handed over and no row after it has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamPosition {
+    /// Leading inputs whose rows have all been handed over; a resume does
+    /// not read them again.
+    pub inputs_done: usize,
+    /// Rows in those inputs.
+    pub rows_done: u64,
+    /// Rows read so far, counted across inputs.
+    pub rows_read: u64,
+    /// Timestamp and row number, counted across inputs, of the last row
+    /// handed over.
+    pub last_timestamp_ns: Option<Timestamp>,
+    pub last_ingest_order: u64,
+    pub last_sequence: u64,
+}
+
+impl StreamPosition {
+    fn last_key(&self) -> Option<(Timestamp, u64)> {
+        self.last_timestamp_ns
+            .map(|ts| (ts, self.last_ingest_order))
+    }
+}
+
+/// [`stream_files`], resuming after `resume` when given and telling the
+/// sink the position after each batch. Inputs must be the ones the
+/// position came from: rows are skipped by their place in them, and
+/// sequences continue from its last one.
+pub fn stream_files_from<S, E>(
+    paths: &[PathBuf],
+    window_ns: u64,
+    visit: &RowVisitor,
+    resume: Option<&StreamPosition>,
+    mut sink: S,
+) -> Result<u64, E>
+where
+    S: FnMut(Vec<Event>, &StreamPosition) -> Result<(), E>,
+    E: From<IngestError>,
+{
+    let mut position = resume.copied().unwrap_or_default();
+    let skip_through = position.last_key();
+    let mut resequencer = Resequencer::new(window_ns);
+    resequencer.resume_after(skip_through, position.last_sequence);
+    let rows_read = position.rows_read;
+    // Row count at the end of, and newest row of, each input read in full
+    // but not yet known to be handed over in full.
+    let mut read = VecDeque::<(u64, Option<(Timestamp, u64)>)>::new();
+    let mut ingest_order = position.rows_done;
+    let mut written = 0u64;
+    for path in paths.iter().skip(position.inputs_done) {
+        let mut failure = None;
+        let mut newest = None;
+        let parsed = visit(path, &mut |mut event| {
+            event.ingest_order = ingest_order;
+            ingest_order += 1;
+            let key = (event.timestamp_ns, event.ingest_order);
+            newest = newest.max(Some(key));
+            // Rows read before the position up to its last row were handed
+            // over; any later ones that old are late, as they were then.
+            if event.ingest_order < rows_read && skip_through.is_some_and(|done| key <= done) {
+                return Ok(());
+            }
+            if let Err(late) = resequencer.push(event) {
+                return Err(IngestError::Parse(format!(
+                    "{} row at {} ns is {} ns behind rows already written; widen the reorder window",
+                    late.symbol,
+                    late.timestamp_ns.as_nanos(),
+                    resequencer.late_by(&late)
+                )));
+            }
+            let ready = resequencer.ready();
+            if ready.is_empty() {
+                return Ok(());
+            }
+            written += ready.len() as u64;
+            position.rows_read = ingest_order;
+            advance(&mut position, &resequencer, &mut read);
+            sink(ready, &position).map_err(|err| {
+                failure = Some(err);
+                IngestError::Parse(String::from("sink failed"))
+            })
+        });
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        parsed.map_err(|err| in_file(path, err))?;
+        read.push_back((ingest_order, newest));
+    }
+    let rest = resequencer.drain();
+    written += rest.len() as u64;
+    position.rows_read = ingest_order;
+    advance(&mut position, &resequencer, &mut read);
+    if !rest.is_empty() {
+        sink(rest, &position)?;
+    }
+    Ok(written)
+}
+
+fn advance(
+    position: &mut StreamPosition,
+    resequencer: &Resequencer,
+    read: &mut VecDeque<(u64, Option<(Timestamp, u64)>)>,
+) {
+    if let Some((ts, order)) = resequencer.last_key() {
+        position.last_timestamp_ns = Some(ts);
+        position.last_ingest_order = order;
+    }
+    position.last_sequence = resequencer.last_sequence();
+    let done = position.last_key();
+    while let Some(&(rows, newest)) = read.front() {
+        if newest.is_some_and(|newest| done.is_none_or(|done| newest > done)) {
+            break;
+        }
+        read.pop_front();
+        position.inputs_done += 1;
+        position.rows_done = rows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{for_each_csv_b, ingest_files};
+    use md_core::TickTable;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn streams_in_batches_matching_batch_ingest() {
+        let dir = std::env::temp_dir().join(format!("md_ingest_stream_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let header = "timestamp_ms,symbol,price,size\n";
+        let first = dir.join("1.csv");
+        let second = dir.join("2.csv");
+        std::fs::write(
+            &first,
+            format!("{header}1000,AAPL,1.00,1\n1003,MSFT,2.00,1\n1001,AAPL,1.01,1\n"),
+        )
+        .expect("write");
+        std::fs::write(
+            &second,
+            format!("{header}1002,SPY,3.00,1\n1010,AAPL,1.02,1\n"),
+        )
+        .expect("write");
+        let paths = vec![first, second];
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let visit =
+            |path: &Path, on_event: &mut dyn FnMut(PendingEvent) -> Result<(), IngestError>| {
+                for_each_csv_b(path, "X", &ticks, on_event)
+            };
+
+        assert_eq!(
+            scan_symbols(&paths, &visit).expect("scan"),
+            ["AAPL", "MSFT", "SPY"]
+        );
+        let mut batches = Vec::new();
+        let count = stream_files(&paths, 2_000_000, &visit, |batch| {
+            batches.push(batch);
+            Ok::<_, IngestError>(())
+        })
+        .expect("stream");
+        assert_eq!(count, 5);
+        assert!(batches.len() > 1);
+        let streamed = batches.concat();
+        let batch =
+            ingest_files(&paths, |path| crate::parse_csv_b(path, "X", &ticks)).expect("ingest");
+        assert_eq!(streamed, batch);
+
+        let err =
+            stream_files(&paths, 0, &visit, |_| Ok::<_, IngestError>(())).expect_err("late row");
+        assert!(err.to_string().contains("1.csv"), "{err}");
+        assert!(err.to_string().contains("reorder window"), "{err}");
+
+        // Stopping after any batch and resuming from its position gives the
+        // same events as one run.
+        let mut positions = Vec::new();
+        stream_files_from(&paths, 2_000_000, &visit, None, |batch, position| {
+            positions.push((batch.len(), *position));
+            Ok::<_, IngestError>(())
+        })
+        .expect("stream");
+        assert_eq!(positions.last().map(|(_, p)| p.inputs_done), Some(2));
+        let mut done = 0;
+        for (len, position) in positions {
+            done += len;
+            let mut rest = Vec::new();
+            stream_files_from(&paths, 2_000_000, &visit, Some(&position), |batch, _| {
+                rest.extend(batch);
+                Ok::<_, IngestError>(())
+            })
+            .expect("resume");
+            assert_eq!(rest, streamed[done..], "{position:?}");
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,200 @@
+//! Tiingo end-of-day prices (`/tiingo/daily/{ticker}/prices`). Prices are
+//! the unadjusted ones; bars are stamped at midnight UTC of their date.
+
+use crate::cache::{fetch_cached, CacheKey, ResponseCache};
+use crate::stooq::{date_start, utc_date, DAY_NS};
+use crate::yahoo::{bars_to_events, Bar, BarExpansion};
+use crate::{HttpOptions, IngestError};
+use md_core::{Event, TickTable, Timestamp};
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_URL: &str = "https://api.tiingo.com";
+
+/// `from`/`to` bound the dates fetched (both inclusive). Without `from`,
+/// Tiingo returns only the latest day. Symbols present in `since` keep
+/// only bars strictly after it. Responses are read from and saved to
+/// `cache` when set, except for symbols in `since`.
+#[derive(Debug, Clone)]
+pub struct TiingoOptions {
+    pub api_key: String,
+    pub base_url: String,
+    pub http: HttpOptions,
+    pub from: Option<Timestamp>,
+    pub to: Option<Timestamp>,
+    pub expansion: BarExpansion,
+    pub since: BTreeMap<String, Timestamp>,
+    pub cache: Option<ResponseCache>,
+}
+
+impl Default for TiingoOptions {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: String::from(DEFAULT_URL),
+            http: HttpOptions::default(),
+            from: None,
+            to: None,
+            expansion: BarExpansion::default(),
+            since: BTreeMap::new(),
+            cache: None,
+        }
+    }
+}
+
+pub async fn ingest_tiingo(
+    symbols: &[String],
+    venue: &str,
+    ticks: &TickTable,
+    options: &TiingoOptions,
+) -> Result<Vec<Event>, IngestError> {
+    if symbols.is_empty() {
+        return Err(IngestError::Parse(String::from("empty symbols list")));
+    }
+    if options.api_key.is_empty() {
+        return Err(IngestError::Parse(String::from("tiingo needs an API key")));
+    }
+    let client = options.http.build_client()?;
+    let mut per_symbol = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let from = match (options.from, options.since.get(symbol)) {
+            (Some(from), Some(since)) => Some(from.max(*since)),
+            (from, since) => from.or(since.copied()),
+        };
+        let range = format!(
+            "{}..{}",
+            from.map(utc_date).unwrap_or_default(),
+            options.to.map(utc_date).unwrap_or_default()
+        );
+        let key = CacheKey {
+            provider: "tiingo",
+            symbol,
+            range: &range,
+            interval: "1d",
+        };
+        let cache = options
+            .cache
+            .as_ref()
+            .filter(|_| !options.since.contains_key(symbol));
+        let fetch = fetch_daily(&client, options, symbol, from);
+        let bars = fetch_cached(cache, &key, fetch, |raw| parse_daily(raw, symbol)).await?;
+        per_symbol.push((symbol.clone(), bars));
+    }
+    bars_to_events(
+        &per_symbol,
+        venue,
+        ticks,
+        DAY_NS,
+        &options.expansion,
+        &options.since,
+    )
+}
+
+async fn fetch_daily(
+    client: &Client,
+    options: &TiingoOptions,
+    symbol: &str,
+    from: Option<Timestamp>,
+) -> Result<String, IngestError> {
+    let mut url = Url::parse(&options.base_url).map_err(|e| IngestError::Parse(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| IngestError::Parse(String::from("invalid tiingo url")))?
+        .pop_if_empty()
+        .extend(["tiingo", "daily", &symbol.to_ascii_lowercase(), "prices"]);
+    {
+        let mut query = url.query_pairs_mut();
+        if let Some(from) = from {
+            query.append_pair("startDate", &utc_date(from));
+        }
+        if let Some(to) = options.to {
+            query.append_pair("endDate", &utc_date(to));
+        }
+    }
+    let response = client
+        .get(url)
+        .header(
+            reqwest::header::AUTHORIZATION,
+            format!("Token {}", options.api_key),
+        )
+        .send()
+        .await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(api_error(symbol, status, &body));
+    }
+    Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    detail: Option<String>,
+}
+
+fn api_error(symbol: &str, status: StatusCode, body: &str) -> IngestError {
+    let detail = serde_json::from_str::<ErrorBody>(body)
+        .ok()
+        .and_then(|e| e.detail)
+        .unwrap_or_else(|| body.chars().take(200).collect());
+    IngestError::Parse(format!("{symbol}: tiingo returned {status}: {detail}"))
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyRow {
+    /// `2024-01-02T00:00:00.000Z`; only the date part is meaningful.
+    date: String,
+    open: Option<f64>,
+    high: Option<f64>,
+    low: Option<f64>,
+    close: Option<f64>,
+    volume: Option<f64>,
+}
+
+fn parse_daily(raw: &str, symbol: &str) -> Result<Vec<Bar>, IngestError> {
+    let rows: Vec<DailyRow> = serde_json::from_str(raw)?;
+    let finite = |v: Option<f64>| v.filter(|v| v.is_finite());
+    rows.into_iter()
+        .map(|row| {
+            let date = row.date.get(..10).unwrap_or(&row.date);
+            Ok(Bar {
+                start: date_start(symbol, date)?,
+                open: finite(row.open),
+                high: finite(row.high),
+                low: finite(row.low),
+                close: finite(row.close),
+                volume: finite(row.volume).map(|v| v.round() as i64),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_daily_prices_and_errors() {
+        let raw = r#"[
+            {"date":"2024-01-02T00:00:00.000Z","open":187.15,"high":188.44,"low":183.885,
+             "close":185.64,"volume":82488674,"adjClose":184.94,"divCash":0.0,"splitFactor":1.0},
+            {"date":"2024-01-03T00:00:00.000Z","open":184.22,"high":185.88,"low":183.43,
+             "close":184.25,"volume":58414460}
+        ]"#;
+        let bars = parse_daily(raw, "AAPL").expect("parse");
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            bars[1].start,
+            Timestamp::from_rfc3339("2024-01-03T00:00:00Z").expect("ts")
+        );
+        assert_eq!(bars[0].close, Some(185.64));
+        assert_eq!(bars[0].volume, Some(82_488_674));
+
+        let err = api_error(
+            "NOPE",
+            StatusCode::NOT_FOUND,
+            r#"{"detail":"Error: Ticker 'NOPE' not found"}"#,
+        );
+        assert!(err.to_string().contains("Ticker 'NOPE' not found"), "{err}");
+    }
+}
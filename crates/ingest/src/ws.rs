@@ -0,0 +1,304 @@
+//! Websocket market data providers. A [`LiveProvider`] says where to
+//! connect, what to send once connected and how to decode each message;
+//! [`stream_provider`] does the rest: reconnects, resequencing and issue
+//! reporting, the same way `capture_live` does for packets.
+
+use crate::binance::Binance;
+use crate::coinbase::Coinbase;
+use crate::frames::{warn_issue, IssueKind, ParseIssue};
+use crate::live::{LiveItem, LiveSummary, Resequencer};
+use crate::IngestError;
+use futures::{SinkExt, StreamExt};
+use md_core::{Payload, PendingEvent, TickTable, Timestamp};
+use std::ops::ControlFlow;
+use std::time::Duration;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn};
+
+/// One decoded market data update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderEvent {
+    pub timestamp_ns: Timestamp,
+    pub symbol: String,
+    pub payload: Payload,
+    /// The exchange's own sequence number or update id, if it sends one.
+    pub source_sequence: Option<u64>,
+}
+
+pub trait LiveProvider: Send + Sync {
+    /// Name accepted by `--provider`.
+    fn name(&self) -> &'static str;
+
+    fn default_url(&self) -> &'static str;
+
+    /// The exchange's spelling of a user-supplied symbol.
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        symbol.to_ascii_uppercase()
+    }
+
+    /// Address to connect to for `symbols`, given the configured base URL.
+    fn connect_url(&self, base: &str, _symbols: &[String]) -> String {
+        base.to_string()
+    }
+
+    /// Text frames sent after every (re)connect.
+    fn subscribe_messages(&self, _symbols: &[String]) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Decodes one text message. `received` stamps updates the exchange
+    /// does not time itself. Messages that carry no market data (heartbeats,
+    /// subscription acks) decode to nothing.
+    fn decode(
+        &self,
+        text: &str,
+        ticks: &TickTable,
+        received: Timestamp,
+    ) -> Result<Vec<ProviderEvent>, String>;
+}
+
+/// Looks up a provider by its `--provider` name.
+pub fn live_provider(name: &str) -> Option<Box<dyn LiveProvider>> {
+    match name {
+        "binance" => Some(Box::new(Binance)),
+        "coinbase" => Some(Box::new(Coinbase)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderOptions {
+    /// Base URL; the provider's default when unset.
+    pub url: Option<String>,
+    pub venue: String,
+    /// Events are held back until the feed has moved this far past them,
+    /// which absorbs the gap between exchange and arrival timestamps.
+    pub reorder_window_ns: u64,
+    /// How long to wait for a message before reporting `LiveItem::Idle`.
+    pub idle_timeout: Duration,
+    /// Pause before reconnecting after the server drops the stream.
+    pub reconnect_delay: Duration,
+}
+
+impl Default for ProviderOptions {
+    fn default() -> Self {
+        Self {
+            url: None,
+            venue: String::new(),
+            reorder_window_ns: 500_000_000,
+            idle_timeout: Duration::from_secs(1),
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Parses a decimal price into ticks.
+pub(crate) fn price_ticks(ticks: &TickTable, symbol: &str, raw: &str) -> Result<i64, String> {
+    ticks
+        .price_str_to_ticks(symbol, raw)
+        .map_err(|err| format!("price {raw}: {err}"))
+}
+
+/// Parses a decimal quantity into the symbol's lots, rounding to whole
+/// lots (whole units unless the tick config sets a lot size).
+pub(crate) fn quantity(ticks: &TickTable, symbol: &str, raw: &str) -> Result<i64, String> {
+    ticks
+        .quantity_str_to_lots(symbol, raw)
+        .map_err(|err| format!("quantity {raw}: {err}"))
+}
+
+/// Streams `symbols` from `provider` into `on_item` until it breaks,
+/// reconnecting whenever the server closes the stream. Only the first
+/// connection failing is an error. Events reach `on_item` sequenced and in
+/// timestamp order.
+pub async fn stream_provider(
+    provider: &dyn LiveProvider,
+    symbols: &[String],
+    ticks: &TickTable,
+    options: &ProviderOptions,
+    mut on_item: impl FnMut(LiveItem) -> ControlFlow<()>,
+) -> Result<LiveSummary, IngestError> {
+    let name = provider.name();
+    let base = options.url.as_deref().unwrap_or(provider.default_url());
+    let url = provider.connect_url(base, symbols);
+    let subscribe = provider.subscribe_messages(symbols);
+    let mut resequencer = Resequencer::new(options.reorder_window_ns);
+    let mut summary = LiveSummary::default();
+    let mut connected_once = false;
+    let mut ingest_order = 0u64;
+
+    'session: loop {
+        let connected = match connect_async(url.as_str()).await {
+            Ok((mut ws, _)) => {
+                let mut sent = Ok(());
+                for message in &subscribe {
+                    sent = ws.send(Message::text(message.as_str())).await;
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                sent.map(|()| ws)
+            }
+            Err(err) => Err(err),
+        };
+        let mut ws = match connected {
+            Ok(ws) => ws,
+            Err(err) if connected_once => {
+                warn!(provider = name, error = %err, "reconnect failed");
+                tokio::time::sleep(options.reconnect_delay).await;
+                continue;
+            }
+            Err(err) => return Err(IngestError::WebSocket(err.to_string())),
+        };
+        connected_once = true;
+        info!(provider = name, %url, "stream connected");
+
+        loop {
+            let ready = match tokio::time::timeout(options.idle_timeout, ws.next()).await {
+                Err(_) => {
+                    let ready = resequencer.drain();
+                    if ready.is_empty() && on_item(LiveItem::Idle).is_break() {
+                        break 'session;
+                    }
+                    ready
+                }
+                Ok(None) | Ok(Some(Ok(Message::Close(_)))) => break,
+                Ok(Some(Err(err))) => {
+                    warn!(provider = name, error = %err, "stream failed");
+                    break;
+                }
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    summary.packets += 1;
+                    let mut issues = Vec::new();
+                    match provider.decode(&text, ticks, Timestamp::now()) {
+                        Ok(decoded) => {
+                            for update in decoded {
+                                ingest_order += 1;
+                                let event = PendingEvent {
+                                    timestamp_ns: update.timestamp_ns,
+                                    venue: options.venue.clone(),
+                                    symbol: update.symbol,
+                                    payload: update.payload,
+                                    ingest_order,
+                                    source_sequence: update.source_sequence,
+                                };
+                                if let Err(late) = resequencer.push(event) {
+                                    summary.late += 1;
+                                    let detail = format!(
+                                        "late by {} ns, dropped",
+                                        resequencer.late_by(&late)
+                                    );
+                                    issues.push((IssueKind::Late, detail));
+                                }
+                            }
+                        }
+                        Err(detail) => {
+                            summary.issues += 1;
+                            issues.push((IssueKind::Malformed, detail));
+                        }
+                    }
+                    for (kind, detail) in issues {
+                        let issue = ParseIssue {
+                            packet_index: summary.packets,
+                            offset: 0,
+                            kind,
+                            detail,
+                            gap: None,
+                        };
+                        warn_issue(&issue);
+                        if on_item(LiveItem::Issue(issue)).is_break() {
+                            break 'session;
+                        }
+                    }
+                    resequencer.ready()
+                }
+                Ok(Some(Ok(_))) => continue,
+            };
+            for event in ready {
+                summary.events += 1;
+                if on_item(LiveItem::Event(event)).is_break() {
+                    break 'session;
+                }
+            }
+        }
+        warn!(provider = name, "stream closed, reconnecting");
+        tokio::time::sleep(options.reconnect_delay).await;
+    }
+
+    for event in resequencer.drain() {
+        summary.events += 1;
+        let _ = on_item(LiveItem::Event(event));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::Event;
+    use rust_decimal::Decimal;
+    use tokio::net::TcpListener;
+
+    const MATCH: &str = r#"{"type":"match","trade_id":10,"sequence":50,"time":"2024-01-02T10:00:00.000001Z","product_id":"BTC-USD","size":"2","price":"42000.01","side":"sell"}"#;
+
+    #[tokio::test]
+    async fn subscribes_and_streams_until_the_callback_stops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let server = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.expect("accept");
+            let mut ws = tokio_tungstenite::accept_async(tcp)
+                .await
+                .expect("handshake");
+            let subscribe = ws.next().await.expect("subscribe").expect("frame");
+            for text in [MATCH, "not json", MATCH] {
+                ws.send(Message::text(text)).await.expect("send");
+            }
+            // Hold the connection open; the client stops on its own.
+            let _ = ws.next().await;
+            subscribe.into_text().expect("text").to_string()
+        });
+
+        let options = ProviderOptions {
+            url: Some(format!("ws://{addr}")),
+            venue: String::from("COINBASE"),
+            reorder_window_ns: 0,
+            idle_timeout: Duration::from_millis(200),
+            ..ProviderOptions::default()
+        };
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let provider = live_provider("coinbase").expect("provider");
+        let mut events = Vec::<Event>::new();
+        let mut issues = 0;
+        let summary = stream_provider(
+            provider.as_ref(),
+            &[String::from("BTC-USD")],
+            &ticks,
+            &options,
+            |item| {
+                match item {
+                    LiveItem::Event(event) => events.push(event),
+                    LiveItem::Issue(_) => issues += 1,
+                    LiveItem::Idle => return ControlFlow::Break(()),
+                }
+                ControlFlow::Continue(())
+            },
+        )
+        .await
+        .expect("stream");
+        assert_eq!(summary.packets, 3);
+        assert_eq!(issues, 1);
+        assert_eq!(
+            events.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(events.iter().all(|e| e.venue == "COINBASE"));
+
+        let subscribe = server.await.expect("server");
+        assert!(
+            subscribe.contains(r#""product_ids":["BTC-USD"]"#),
+            "{subscribe}"
+        );
+    }
+}
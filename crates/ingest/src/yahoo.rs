@@ -1,9 +1,62 @@
-use crate::IngestError;
-use md_core::{assign_sequences, Event, Payload, PendingEvent, TickTable};
+use crate::cache::{fetch_cached, CacheKey, ResponseCache};
+use crate::http::{get_text, RateLimiter, RetryPolicy};
+use crate::{HttpOptions, IngestError};
+use futures::stream::{self, StreamExt};
+use md_core::{assign_sequences, Event, Payload, PendingEvent, TickTable, Timestamp};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use reqwest::{Client, Url};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 
-const BASE_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+pub const DEFAULT_URL: &str = "https://query1.finance.yahoo.com";
+const DEFAULT_BAR_NS: i64 = 60_000_000_000;
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Expands each bar into `steps` trade/quote pairs spread evenly over the bar.
+/// Prices follow a seeded Brownian bridge from open to close that touches the
+/// bar's high and low. `steps <= 1` keeps the one-event-per-bar behaviour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BarExpansion {
+    pub steps: usize,
+    pub seed: u64,
+}
+
+/// `concurrency` bounds in-flight chart requests. Responses are consumed in
+/// symbol order, so the resulting tape does not depend on completion order.
+/// Symbols present in `since` are fetched from that instant instead of
+/// `range`, and only events strictly after it are kept. Throttled requests
+/// are retried per `retry`, and `requests_per_second` caps how often any
+/// request, retries included, is sent. Responses are read from and saved
+/// to `cache` when set, except for symbols in `since`. Spans longer than
+/// Yahoo serves in one request at the interval (7 days of 1m bars) are
+/// fetched in windows and stitched back together.
+#[derive(Debug, Clone)]
+pub struct YahooOptions {
+    pub base_url: String,
+    pub expansion: BarExpansion,
+    pub http: HttpOptions,
+    pub concurrency: usize,
+    pub since: BTreeMap<String, Timestamp>,
+    pub retry: RetryPolicy,
+    pub requests_per_second: Option<f64>,
+    pub cache: Option<ResponseCache>,
+}
+
+impl Default for YahooOptions {
+    fn default() -> Self {
+        Self {
+            base_url: String::from(DEFAULT_URL),
+            expansion: BarExpansion::default(),
+            http: HttpOptions::default(),
+            concurrency: DEFAULT_CONCURRENCY,
+            since: BTreeMap::new(),
+            retry: RetryPolicy::default(),
+            requests_per_second: None,
+            cache: None,
+        }
+    }
+}
 
 pub async fn ingest_yahoo(
     symbols: &[String],
@@ -11,54 +64,307 @@ pub async fn ingest_yahoo(
     ticks: &TickTable,
     interval: &str,
     range: &str,
+) -> Result<Vec<Event>, IngestError> {
+    ingest_yahoo_with(
+        symbols,
+        venue,
+        ticks,
+        interval,
+        range,
+        &YahooOptions::default(),
+    )
+    .await
+}
+
+pub async fn ingest_yahoo_with(
+    symbols: &[String],
+    venue: &str,
+    ticks: &TickTable,
+    interval: &str,
+    range: &str,
+    options: &YahooOptions,
 ) -> Result<Vec<Event>, IngestError> {
     if symbols.is_empty() {
         return Err(IngestError::Parse(String::from("empty symbols list")));
     }
-    let client = Client::builder().user_agent("md-replay/0.1").build()?;
+    let limiter = match options.requests_per_second {
+        Some(rate) if !(rate.is_finite() && rate > 0.0) => {
+            return Err(IngestError::Parse(format!(
+                "requests per second must be positive, got {rate}"
+            )))
+        }
+        Some(rate) => Some(tokio::sync::Mutex::new(RateLimiter::per_second(
+            "yahoo", rate,
+        ))),
+        None => None,
+    };
+    let client = options.http.build_client()?;
+    let expansion = &options.expansion;
     let mut pending = Vec::new();
     let mut ingest_order = 0u64;
 
+    let mut responses = stream::iter(symbols)
+        .map(|symbol| {
+            let since = options.since.get(symbol).copied();
+            // Incremental fetches run up to now, so they are never cached.
+            let cache = options.cache.as_ref().filter(|_| since.is_none());
+            let spans = chart_spans(interval, range, since, Timestamp::now());
+            let client = &client;
+            let limiter = limiter.as_ref();
+            async move {
+                let mut bars = Vec::new();
+                for span in &spans {
+                    let key = CacheKey {
+                        provider: "yahoo",
+                        symbol,
+                        range: &span.key(),
+                        interval,
+                    };
+                    let fetch =
+                        fetch_symbol_chart(client, options, limiter, symbol, interval, span);
+                    bars.append(
+                        &mut fetch_cached(cache, &key, fetch, |raw| chart_series(raw, symbol))
+                            .await?,
+                    );
+                }
+                if spans.len() > 1 {
+                    stitch_bars(&mut bars);
+                }
+                Ok::<_, IngestError>(bars)
+            }
+        })
+        .buffered(options.concurrency.max(1));
+
     for symbol in symbols {
-        let raw = fetch_symbol_chart(&client, symbol, interval, range).await?;
-        let mut items = parse_symbol_payload(&raw, symbol, venue, ticks, ingest_order)?;
+        let Some(bars) = responses.next().await else {
+            break;
+        };
+        let bars = bars?;
+        let mut items = if expansion.steps > 1 {
+            let bar_ns = interval_ns(interval).unwrap_or(DEFAULT_BAR_NS);
+            expand_bars(&bars, symbol, venue, ticks, bar_ns, expansion, ingest_order)?
+        } else {
+            bar_events(&bars, symbol, venue, ticks, ingest_order)?
+        };
         ingest_order += items.len() as u64;
+        if let Some(since) = options.since.get(symbol) {
+            items.retain(|e| e.timestamp_ns > *since);
+        }
         pending.append(&mut items);
     }
 
-    if pending.is_empty() {
+    if pending.is_empty() && options.since.is_empty() {
         return Err(IngestError::Parse(String::from("no events returned")));
     }
     Ok(assign_sequences(pending))
 }
 
+/// What one chart request asks for: a named range, or a window of Unix
+/// seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ChartSpan {
+    Range(String),
+    Period(i64, i64),
+}
+
+impl ChartSpan {
+    /// The range part of the span's cache key.
+    fn key(&self) -> String {
+        match self {
+            ChartSpan::Range(range) => range.clone(),
+            ChartSpan::Period(start, end) => format!("{start}..{end}"),
+        }
+    }
+}
+
+/// The requests that cover `range`, or everything after `since`. A span
+/// longer than [`max_window_secs`] allows is split into windows; those of a
+/// range end at the next UTC midnight, so they stay put (and cached) for
+/// the rest of the day.
+fn chart_spans(
+    interval: &str,
+    range: &str,
+    since: Option<Timestamp>,
+    now: Timestamp,
+) -> Vec<ChartSpan> {
+    const DAY: i64 = 86_400;
+    let now_secs = now.as_nanos().div_euclid(1_000_000_000);
+    let max = max_window_secs(interval);
+    let (start, end) = match (since, span_secs(range)) {
+        (Some(since), _) => (since.as_nanos().div_euclid(1_000_000_000), now_secs + 1),
+        (None, Some(len)) if max.is_some_and(|max| len > max) => {
+            let end = (now_secs.div_euclid(DAY) + 1) * DAY;
+            (end - len, end)
+        }
+        (None, _) => return vec![ChartSpan::Range(range.to_string())],
+    };
+    let step = max.unwrap_or(i64::MAX).max(1);
+    let mut spans = Vec::new();
+    let mut from = start;
+    loop {
+        let to = from.saturating_add(step).min(end);
+        spans.push(ChartSpan::Period(from, to));
+        if to >= end {
+            return spans;
+        }
+        from = to;
+    }
+}
+
+/// Longest span Yahoo serves in one request at `interval`, for intervals
+/// that have such a limit.
+fn max_window_secs(interval: &str) -> Option<i64> {
+    const DAY: i64 = 86_400;
+    match interval {
+        "1m" => Some(7 * DAY),
+        "2m" | "5m" | "15m" | "30m" | "90m" => Some(60 * DAY),
+        "60m" | "1h" => Some(730 * DAY),
+        _ => None,
+    }
+}
+
+/// Orders bars from consecutive windows and drops the copies of bars at
+/// the boundaries, keeping the one from the later window.
+fn stitch_bars(bars: &mut Vec<Bar>) {
+    bars.sort_by_key(|bar| bar.start);
+    let mut stitched: Vec<Bar> = Vec::with_capacity(bars.len());
+    for bar in bars.drain(..) {
+        match stitched.last_mut() {
+            Some(last) if last.start == bar.start => *last = bar,
+            _ => stitched.push(bar),
+        }
+    }
+    *bars = stitched;
+}
+
 async fn fetch_symbol_chart(
     client: &Client,
+    options: &YahooOptions,
+    limiter: Option<&tokio::sync::Mutex<RateLimiter>>,
     symbol: &str,
     interval: &str,
-    range: &str,
+    span: &ChartSpan,
 ) -> Result<String, IngestError> {
-    let mut url = Url::parse(BASE_URL).map_err(|e| IngestError::Parse(e.to_string()))?;
+    let mut url = Url::parse(&options.base_url).map_err(|e| IngestError::Parse(e.to_string()))?;
     url.path_segments_mut()
         .map_err(|_| IngestError::Parse(String::from("invalid yahoo url")))?
-        .push(symbol);
-    url.query_pairs_mut()
-        .append_pair("interval", interval)
-        .append_pair("range", range)
-        .append_pair("includePrePost", "false")
-        .append_pair("events", "history");
+        .pop_if_empty()
+        .extend(["v8", "finance", "chart", symbol]);
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("interval", interval);
+        match span {
+            ChartSpan::Period(period1, period2) => {
+                query
+                    .append_pair("period1", &period1.to_string())
+                    .append_pair("period2", &period2.to_string());
+            }
+            ChartSpan::Range(range) => {
+                query.append_pair("range", range);
+            }
+        }
+        query
+            .append_pair("includePrePost", "false")
+            .append_pair("events", "history");
+    }
+
+    get_text(client, url, &options.retry, limiter).await
+}
 
-    let response = client.get(url).send().await?.error_for_status()?;
-    response.text().await.map_err(IngestError::from)
+/// One OHLCV bar. Non-finite prices are already dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Bar {
+    pub start: Timestamp,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub volume: Option<i64>,
 }
 
-fn parse_symbol_payload(
-    raw: &str,
+/// A trade at the close and a quote spanning low to high per bar, both
+/// sized by the bar volume.
+pub(crate) fn bar_events(
+    bars: &[Bar],
     symbol: &str,
     venue: &str,
     ticks: &TickTable,
     ingest_order_start: u64,
 ) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut out = Vec::new();
+    for bar in bars {
+        let timestamp_ns = bar.start;
+        let volume = bar.volume.unwrap_or(1).max(1);
+
+        if let Some(close) = bar.close {
+            let price_ticks = f64_to_ticks(ticks, symbol, close)?;
+            out.push(PendingEvent {
+                timestamp_ns,
+                venue: venue.to_string(),
+                symbol: symbol.to_string(),
+                payload: Payload::Trade {
+                    price_ticks,
+                    size: volume,
+                },
+                ingest_order: ingest_order_start + out.len() as u64,
+                source_sequence: None,
+            });
+        }
+
+        if let (Some(low), Some(high)) = (bar.low, bar.high) {
+            let bid_px = f64_to_ticks(ticks, symbol, low.min(high))?;
+            let ask_px = f64_to_ticks(ticks, symbol, high.max(low))?;
+            out.push(PendingEvent {
+                timestamp_ns,
+                venue: venue.to_string(),
+                symbol: symbol.to_string(),
+                payload: Payload::Quote {
+                    bid_px,
+                    bid_sz: volume,
+                    ask_px,
+                    ask_sz: volume,
+                },
+                ingest_order: ingest_order_start + out.len() as u64,
+                source_sequence: None,
+            });
+        }
+    }
+
+    Ok(out)
+}
+
+/// Turns each symbol's bars into events, expanded when `expansion.steps >
+/// 1`, and sequences them into one tape. Events at or before a symbol's
+/// `since` entry are dropped.
+pub(crate) fn bars_to_events(
+    per_symbol: &[(String, Vec<Bar>)],
+    venue: &str,
+    ticks: &TickTable,
+    bar_ns: i64,
+    expansion: &BarExpansion,
+    since: &BTreeMap<String, Timestamp>,
+) -> Result<Vec<Event>, IngestError> {
+    let mut pending = Vec::new();
+    let mut ingest_order = 0u64;
+    for (symbol, bars) in per_symbol {
+        let mut items = if expansion.steps > 1 {
+            expand_bars(bars, symbol, venue, ticks, bar_ns, expansion, ingest_order)?
+        } else {
+            bar_events(bars, symbol, venue, ticks, ingest_order)?
+        };
+        ingest_order += items.len() as u64;
+        if let Some(since) = since.get(symbol) {
+            items.retain(|e| e.timestamp_ns > *since);
+        }
+        pending.append(&mut items);
+    }
+    if pending.is_empty() && since.is_empty() {
+        return Err(IngestError::Parse(String::from("no events returned")));
+    }
+    Ok(assign_sequences(pending))
+}
+
+fn chart_series(raw: &str, symbol: &str) -> Result<Vec<Bar>, IngestError> {
     let payload: ChartEnvelope = serde_json::from_str(raw)?;
     if let Some(err) = payload.chart.error {
         let msg = err
@@ -78,51 +384,84 @@ fn parse_symbol_payload(
         .quote
         .and_then(|list| list.into_iter().next())
         .ok_or_else(|| IngestError::Parse(format!("{symbol}: missing quote payload")))?;
+    let bars = timestamps
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, ts)| {
+            Some(Bar {
+                start: Timestamp::from_secs(ts).ok()?,
+                open: value_f64_at(&quote.open, idx),
+                high: value_f64_at(&quote.high, idx),
+                low: value_f64_at(&quote.low, idx),
+                close: value_f64_at(&quote.close, idx),
+                volume: value_i64_at(&quote.volume, idx),
+            })
+        })
+        .collect();
+    Ok(bars)
+}
+
+/// Spreads each bar over `expansion.steps` trade/quote pairs. A bar lasts
+/// until the next one starts, capped at `bar_ns`.
+pub(crate) fn expand_bars(
+    bars: &[Bar],
+    symbol: &str,
+    venue: &str,
+    ticks: &TickTable,
+    bar_ns: i64,
+    expansion: &BarExpansion,
+    ingest_order_start: u64,
+) -> Result<Vec<PendingEvent>, IngestError> {
+    let mut rng = ChaCha8Rng::seed_from_u64(expansion.seed ^ symbol_salt(symbol));
+    let steps = expansion.steps.max(1);
 
     let mut out = Vec::new();
-    for (idx, ts) in timestamps.into_iter().enumerate() {
-        let ts = match u64::try_from(ts) {
-            Ok(v) => v,
-            Err(_) => continue,
+    for (idx, bar) in bars.iter().enumerate() {
+        let bar_start = bar.start;
+        let Some(close) = bar.close else {
+            continue;
         };
-        let timestamp_ns = match ts.checked_mul(1_000_000_000) {
-            Some(v) => v,
-            None => continue,
+        let open = bar.open.unwrap_or(close);
+        let high = bar.high.unwrap_or(open.max(close));
+        let low = bar.low.unwrap_or(open.min(close));
+        let bar_ticks = BarTicks {
+            open: f64_to_ticks(ticks, symbol, open)?,
+            high: f64_to_ticks(ticks, symbol, high.max(open).max(close))?,
+            low: f64_to_ticks(ticks, symbol, low.min(open).min(close))?,
+            close: f64_to_ticks(ticks, symbol, close)?,
         };
+        let volume = bar.volume.unwrap_or(1).max(1);
+        let span = bars
+            .get(idx + 1)
+            .map(|next| next.start.nanos_since(bar_start))
+            .filter(|gap| *gap > 0)
+            .map_or(bar_ns, |gap| gap.min(bar_ns));
 
-        let volume = value_i64_at(&quote.volume, idx).unwrap_or(1).max(1);
-
-        if let Some(close) = value_f64_at(&quote.close, idx) {
-            let price_ticks = f64_to_ticks(ticks, symbol, close)?;
+        let path = bridge_path(&bar_ticks, steps, &mut rng);
+        for (step, price_ticks) in path.into_iter().enumerate() {
+            let offset = (span as i128 * step as i128 / steps as i128) as i64;
+            let timestamp_ns = bar_start.saturating_add_nanos(offset);
+            let size = split_volume(volume, steps, step);
             out.push(PendingEvent {
                 timestamp_ns,
                 venue: venue.to_string(),
                 symbol: symbol.to_string(),
-                payload: Payload::Trade {
-                    price_ticks,
-                    size: volume,
-                },
+                payload: Payload::Trade { price_ticks, size },
                 ingest_order: ingest_order_start + out.len() as u64,
+                source_sequence: None,
             });
-        }
-
-        if let (Some(low), Some(high)) = (
-            value_f64_at(&quote.low, idx),
-            value_f64_at(&quote.high, idx),
-        ) {
-            let bid_px = f64_to_ticks(ticks, symbol, low.min(high))?;
-            let ask_px = f64_to_ticks(ticks, symbol, high.max(low))?;
             out.push(PendingEvent {
                 timestamp_ns,
                 venue: venue.to_string(),
                 symbol: symbol.to_string(),
                 payload: Payload::Quote {
-                    bid_px,
-                    bid_sz: volume,
-                    ask_px,
-                    ask_sz: volume,
+                    bid_px: price_ticks - 1,
+                    bid_sz: size,
+                    ask_px: price_ticks + 1,
+                    ask_sz: size,
                 },
                 ingest_order: ingest_order_start + out.len() as u64,
+                source_sequence: None,
             });
         }
     }
@@ -130,6 +469,105 @@ fn parse_symbol_payload(
     Ok(out)
 }
 
+#[derive(Debug, Clone, Copy)]
+struct BarTicks {
+    open: i64,
+    high: i64,
+    low: i64,
+    close: i64,
+}
+
+/// Brownian bridge pinned at open and close, scaled to the bar range and
+/// clamped into `[low, high]`. The highest and lowest interior points are
+/// snapped onto the bar's high and low so the extremes actually trade.
+fn bridge_path(bar: &BarTicks, steps: usize, rng: &mut ChaCha8Rng) -> Vec<i64> {
+    if steps == 1 {
+        return vec![bar.close];
+    }
+    let last = (steps - 1) as f64;
+    let mut walk = vec![0.0f64; steps];
+    for k in 1..steps {
+        walk[k] = walk[k - 1] + standard_normal(rng);
+    }
+    let end = walk[steps - 1];
+    let noise: Vec<f64> = walk
+        .iter()
+        .enumerate()
+        .map(|(k, w)| w - end * k as f64 / last)
+        .collect();
+    let noise_range = noise.iter().cloned().fold(f64::MIN, f64::max)
+        - noise.iter().cloned().fold(f64::MAX, f64::min);
+    let scale = if noise_range > 0.0 {
+        (bar.high - bar.low) as f64 / noise_range
+    } else {
+        0.0
+    };
+
+    let mut path: Vec<i64> = noise
+        .iter()
+        .enumerate()
+        .map(|(k, b)| {
+            let line = bar.open as f64 + (bar.close - bar.open) as f64 * k as f64 / last;
+            ((line + scale * b).round() as i64).clamp(bar.low, bar.high)
+        })
+        .collect();
+    path[0] = bar.open;
+    path[steps - 1] = bar.close;
+
+    if steps >= 4 {
+        let interior = 1..steps - 1;
+        let hi = interior.clone().max_by_key(|&k| path[k]).unwrap_or(1);
+        let lo = interior
+            .filter(|&k| k != hi)
+            .min_by_key(|&k| path[k])
+            .unwrap_or(1);
+        path[hi] = bar.high;
+        path[lo] = bar.low;
+    }
+    path
+}
+
+fn standard_normal(rng: &mut ChaCha8Rng) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+fn split_volume(volume: i64, steps: usize, step: usize) -> i64 {
+    let steps = steps as i64;
+    let base = volume / steps;
+    let extra = i64::from((step as i64) < volume % steps);
+    (base + extra).max(1)
+}
+
+fn symbol_salt(symbol: &str) -> u64 {
+    symbol.bytes().fold(0xcbf2_9ce4_8422_2325, |acc, b| {
+        (acc ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn interval_ns(interval: &str) -> Option<i64> {
+    span_secs(interval)?.checked_mul(1_000_000_000)
+}
+
+/// Seconds in an interval or range such as `15m`, `60d` or `2y`; months
+/// count 30 days and years 365.
+fn span_secs(raw: &str) -> Option<i64> {
+    let split = raw.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = raw.split_at(split);
+    let count: i64 = count.parse().ok()?;
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "wk" => 604_800,
+        "mo" => 2_592_000,
+        "y" => 31_536_000,
+        _ => return None,
+    };
+    count.checked_mul(unit_secs)
+}
+
 fn value_f64_at(series: &Option<Vec<Option<f64>>>, index: usize) -> Option<f64> {
     let v = series.as_ref()?.get(index).copied().flatten()?;
     if v.is_finite() {
@@ -143,7 +581,11 @@ fn value_i64_at(series: &Option<Vec<Option<i64>>>, index: usize) -> Option<i64>
     series.as_ref()?.get(index).copied().flatten()
 }
 
-fn f64_to_ticks(ticks: &TickTable, symbol: &str, value: f64) -> Result<i64, IngestError> {
+pub(crate) fn f64_to_ticks(
+    ticks: &TickTable,
+    symbol: &str,
+    value: f64,
+) -> Result<i64, IngestError> {
     if !value.is_finite() {
         return Err(IngestError::Parse(format!(
             "{symbol}: non-finite price {value}"
@@ -183,6 +625,7 @@ struct ChartIndicators {
 
 #[derive(Debug, Deserialize)]
 struct QuoteSet {
+    open: Option<Vec<Option<f64>>>,
     close: Option<Vec<Option<f64>>>,
     high: Option<Vec<Option<f64>>>,
     low: Option<Vec<Option<f64>>>,
@@ -194,6 +637,43 @@ mod tests {
     use super::*;
     use md_core::TickTable;
     use rust_decimal::Decimal;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn parse_symbol_payload(
+        raw: &str,
+        symbol: &str,
+        venue: &str,
+        ticks: &TickTable,
+        ingest_order_start: u64,
+    ) -> Result<Vec<PendingEvent>, IngestError> {
+        let bars = chart_series(raw, symbol)?;
+        bar_events(&bars, symbol, venue, ticks, ingest_order_start)
+    }
+
+    fn expand_symbol_payload(
+        raw: &str,
+        symbol: &str,
+        venue: &str,
+        ticks: &TickTable,
+        bar_ns: i64,
+        expansion: &BarExpansion,
+        ingest_order_start: u64,
+    ) -> Result<Vec<PendingEvent>, IngestError> {
+        let bars = chart_series(raw, symbol)?;
+        expand_bars(
+            &bars,
+            symbol,
+            venue,
+            ticks,
+            bar_ns,
+            expansion,
+            ingest_order_start,
+        )
+    }
 
     #[test]
     fn parses_trade_and_quote() {
@@ -261,4 +741,268 @@ mod tests {
         let events = parse_symbol_payload(raw, "MSFT", "X", &ticks, 0).expect("parse");
         assert_eq!(events.len(), 2);
     }
+
+    #[test]
+    fn expands_bar_along_bridge() {
+        let raw = r#"{
+          "chart": {
+            "result": [{
+              "timestamp": [1700000000, 1700000060],
+              "indicators": {
+                "quote": [{
+                  "open": [100.00, 100.20],
+                  "close": [100.20, 100.10],
+                  "high": [100.50, 100.30],
+                  "low": [99.80, 100.00],
+                  "volume": [103, 10]
+                }]
+              }
+            }],
+            "error": null
+          }
+        }"#;
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let expansion = BarExpansion { steps: 10, seed: 7 };
+        let events = expand_symbol_payload(raw, "AAPL", "X", &ticks, 60_000_000_000, &expansion, 0)
+            .expect("expand");
+        assert_eq!(events.len(), 40);
+
+        let trades: Vec<(i64, i64, i64)> = events[..20]
+            .iter()
+            .filter_map(|e| match e.payload {
+                Payload::Trade { price_ticks, size } => {
+                    Some((e.timestamp_ns.as_nanos(), price_ticks, size))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(trades.len(), 10);
+        assert_eq!(trades[0].1, 10000);
+        assert_eq!(trades[9].1, 10020);
+        assert!(trades.iter().all(|t| (9980..=10050).contains(&t.1)));
+        assert!(trades.iter().any(|t| t.1 == 10050));
+        assert!(trades.iter().any(|t| t.1 == 9980));
+        assert_eq!(trades.iter().map(|t| t.2).sum::<i64>(), 103);
+        assert!(trades.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(trades[9].0 < 1_700_000_060_000_000_000);
+
+        let again = expand_symbol_payload(raw, "AAPL", "X", &ticks, 60_000_000_000, &expansion, 0)
+            .expect("expand");
+        assert_eq!(events, again);
+    }
+
+    #[test]
+    fn parses_interval_lengths() {
+        assert_eq!(interval_ns("1m"), Some(60_000_000_000));
+        assert_eq!(interval_ns("15m"), Some(900_000_000_000));
+        assert_eq!(interval_ns("1wk"), Some(604_800_000_000_000));
+        assert_eq!(interval_ns("max"), None);
+        assert_eq!(span_secs("2y"), Some(2 * 31_536_000));
+    }
+
+    #[test]
+    fn long_ranges_split_into_windows() {
+        const DAY: i64 = 86_400;
+        let now = Timestamp::from_secs(100 * DAY + 3_600).expect("ts");
+        let range = |r: &str| ChartSpan::Range(r.to_string());
+        assert_eq!(chart_spans("1m", "5d", None, now), [range("5d")]);
+        assert_eq!(chart_spans("1d", "10y", None, now), [range("10y")]);
+        assert_eq!(chart_spans("1m", "ytd", None, now), [range("ytd")]);
+
+        let spans = chart_spans("1m", "20d", None, now);
+        assert_eq!(
+            spans,
+            [
+                ChartSpan::Period(81 * DAY, 88 * DAY),
+                ChartSpan::Period(88 * DAY, 95 * DAY),
+                ChartSpan::Period(95 * DAY, 101 * DAY),
+            ]
+        );
+        assert_eq!(spans[0].key(), format!("{}..{}", 81 * DAY, 88 * DAY));
+
+        let since = Timestamp::from_secs(90 * DAY).expect("ts");
+        assert_eq!(
+            chart_spans("1m", "20d", Some(since), now),
+            [
+                ChartSpan::Period(90 * DAY, 97 * DAY),
+                ChartSpan::Period(97 * DAY, 100 * DAY + 3_601),
+            ]
+        );
+        assert_eq!(
+            chart_spans("1d", "1d", Some(since), now),
+            [ChartSpan::Period(90 * DAY, 100 * DAY + 3_601)]
+        );
+    }
+
+    /// Answers each chart request with one-minute bars at its `period1` and
+    /// `period2`, so consecutive windows share a boundary bar; counts the
+    /// requests.
+    async fn window_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        let count = Arc::new(AtomicUsize::new(0));
+        let seen = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = conn.read(&mut buf).await.expect("read");
+                let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                let param = |name: &str| {
+                    let at = head.find(&format!("{name}=")).expect("param") + name.len() + 1;
+                    head[at..]
+                        .split(['&', ' '])
+                        .next()
+                        .and_then(|v| v.parse::<i64>().ok())
+                        .expect("number")
+                };
+                let (period1, period2) = (param("period1"), param("period2"));
+                seen.fetch_add(1, Ordering::SeqCst);
+                let body = format!(
+                    r#"{{"chart":{{"result":[{{"timestamp":[{period1},{period2}],"indicators":{{"quote":[{{"close":[1.5,1.6],"high":[1.6,1.7],"low":[1.4,1.5],"volume":[3,4]}}]}}}}],"error":null}}}}"#
+                );
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                conn.write_all(reply.as_bytes()).await.expect("write");
+            }
+        });
+        (base, count)
+    }
+
+    #[tokio::test]
+    async fn long_ranges_are_fetched_in_windows_and_stitched() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let symbols = [String::from("AAPL")];
+        let (base, count) = window_server().await;
+        let options = YahooOptions {
+            base_url: base,
+            ..YahooOptions::default()
+        };
+        let events = ingest_yahoo_with(&symbols, "X", &ticks, "1m", "20d", &options)
+            .await
+            .expect("ingest");
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        // Three windows, two bars each, two of them shared.
+        let trades = events
+            .iter()
+            .filter(|e| matches!(e.payload, Payload::Trade { .. }))
+            .map(|e| e.timestamp_ns)
+            .collect::<Vec<_>>();
+        assert_eq!(trades.len(), 4);
+        assert!(trades.windows(2).all(|w| w[0] < w[1]));
+        // Boundary bars come from the later window, which opens on them.
+        let first_boundary = events
+            .iter()
+            .find(|e| e.timestamp_ns == trades[1])
+            .expect("boundary");
+        assert_eq!(
+            first_boundary.payload,
+            Payload::Trade {
+                price_ticks: 150,
+                size: 3
+            }
+        );
+    }
+
+    /// Answers `/v8/finance/chart/...` with 429 `throttled` times, then with
+    /// a one-bar chart; returns the base URL and the request count.
+    async fn throttling_server(throttled: usize) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        let count = Arc::new(AtomicUsize::new(0));
+        let seen = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = conn.read(&mut buf).await.expect("read");
+                let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                assert!(head.starts_with("GET /v8/finance/chart/AAPL?"), "{head}");
+                let (status, body) = if seen.fetch_add(1, Ordering::SeqCst) < throttled {
+                    ("429 Too Many Requests", String::new())
+                } else {
+                    (
+                        "200 OK",
+                        String::from(
+                            r#"{"chart":{"result":[{"timestamp":[1700000000],"indicators":{"quote":[{"close":[1.5],"high":[1.6],"low":[1.4],"volume":[3]}]}}],"error":null}}"#,
+                        ),
+                    )
+                };
+                let reply = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                conn.write_all(reply.as_bytes()).await.expect("write");
+            }
+        });
+        (base, count)
+    }
+
+    #[tokio::test]
+    async fn retries_throttled_requests_with_backoff() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let symbols = [String::from("AAPL")];
+        let retry = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        };
+        let (base, count) = throttling_server(2).await;
+        let options = YahooOptions {
+            base_url: base,
+            retry,
+            requests_per_second: Some(1_000.0),
+            ..YahooOptions::default()
+        };
+        let events = ingest_yahoo_with(&symbols, "X", &ticks, "1m", "1d", &options)
+            .await
+            .expect("ingest");
+        assert_eq!(events.len(), 2);
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+
+        let (base, count) = throttling_server(3).await;
+        let options = YahooOptions {
+            base_url: base,
+            retry,
+            ..YahooOptions::default()
+        };
+        let err = ingest_yahoo_with(&symbols, "X", &ticks, "1m", "1d", &options)
+            .await
+            .expect_err("retries exhausted");
+        assert!(err.to_string().contains("429"), "{err}");
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn repeated_ingests_are_served_from_the_cache() {
+        let ticks = TickTable::uniform(Decimal::new(1, 2)).expect("tick table");
+        let symbols = [String::from("AAPL")];
+        let dir = std::env::temp_dir().join(format!("md_yahoo_cache_{}", std::process::id()));
+        let (base, count) = throttling_server(0).await;
+        let mut options = YahooOptions {
+            base_url: base,
+            cache: Some(ResponseCache::new(&dir)),
+            ..YahooOptions::default()
+        };
+        let first = ingest_yahoo_with(&symbols, "X", &ticks, "1m", "1d", &options)
+            .await
+            .expect("ingest");
+        let again = ingest_yahoo_with(&symbols, "X", &ticks, "1m", "1d", &options)
+            .await
+            .expect("cached ingest");
+        assert_eq!(first, again);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // A different range, and any incremental fetch, go to the server.
+        ingest_yahoo_with(&symbols, "X", &ticks, "1m", "5d", &options)
+            .await
+            .expect("ingest");
+        let since = Timestamp::now().saturating_add_nanos(-3_600_000_000_000);
+        options.since.insert(String::from("AAPL"), since);
+        ingest_yahoo_with(&symbols, "X", &ticks, "1m", "1d", &options)
+            .await
+            .expect("incremental ingest");
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -1,9 +1,30 @@
-fn main() {
-    let protoc = protoc_bin_vendored::protoc_bin_path().expect("protoc");
-    std::env::set_var("PROTOC", protoc);
+//! The generated gRPC code is checked in under `src/pb/`, so a normal build
+//! needs neither protoc nor network access. After editing
+//! `proto/replay.proto` or `proto/flight.proto`, build once with
+//! `--features regen-proto` to refresh it and the descriptor set the
+//! reflection service serves.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/replay.proto");
+    println!("cargo:rerun-if-changed=proto/flight.proto");
+    #[cfg(feature = "regen-proto")]
+    regen_proto()?;
+    Ok(())
+}
+
+#[cfg(feature = "regen-proto")]
+fn regen_proto() -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = prost_build::Config::new();
+    config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
-        .compile_protos(&["proto/replay.proto"], &["proto"])
-        .expect("compile replay proto");
+        .out_dir("src/pb")
+        .file_descriptor_set_path("src/pb/replay_descriptor.bin")
+        .compile_protos_with_config(
+            config,
+            &["proto/replay.proto", "proto/flight.proto"],
+            &["proto"],
+        )?;
+    Ok(())
 }
@@ -0,0 +1,938 @@
+//! Audit log of the requests a server receives, and a client that issues
+//! a recorded set again against another server.
+//!
+//! The log is JSON lines, one [`AuditRecord`] per call, with each
+//! multiplexed or control command recorded under its stream's connection. Clients are named by a
+//! fingerprint of their bearer token so the token itself never lands on
+//! disk; [`replay_requests`] is handed the real tokens and sends each call
+//! with the one whose fingerprint was recorded for it.
+
+use crate::engine::ReplayError;
+use crate::pb::replay_service_client::ReplayServiceClient;
+use crate::pb::{self, ControlCommand, StreamRequest, SubscriptionCommand};
+use md_core::Timestamp;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::warn;
+
+pub const ANONYMOUS_CLIENT: &str = "anonymous";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Name recorded for a bearer token; empty tokens are anonymous.
+pub fn client_fingerprint(token: &str) -> String {
+    if token.is_empty() {
+        String::from(ANONYMOUS_CLIENT)
+    } else {
+        format!("{:016x}", fnv1a(FNV_OFFSET, token.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Wall-clock arrival time.
+    pub received_ns: Timestamp,
+    /// Numbers each call; commands share their `Multiplex` stream's.
+    pub connection: u64,
+    pub client: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    #[serde(flatten)]
+    pub call: AuditCall,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rpc", rename_all = "snake_case")]
+pub enum AuditCall {
+    StreamEvents {
+        request: AuditRequest,
+    },
+    /// A `Multiplex` stream was opened.
+    Multiplex,
+    Subscribe {
+        subscription_id: u64,
+        request: AuditRequest,
+    },
+    Unsubscribe {
+        subscription_id: u64,
+    },
+    /// One line of an A/B pair, with the seeds it drew its jitter and
+    /// drops from; line B's are the complement of the request's.
+    StreamFeedLine {
+        pair: String,
+        line: AuditFeedLine,
+        request: AuditRequest,
+        jitter_seed: u64,
+        drop_seed: u64,
+    },
+    /// A `Control` stream was started with `request`.
+    Control {
+        request: AuditRequest,
+    },
+    /// A later command on that stream.
+    ControlCommand {
+        command: AuditControl,
+    },
+    CreateSession {
+        name: String,
+        request: AuditRequest,
+    },
+    ListSessions,
+    CloseSession {
+        name: String,
+    },
+    GetLogInfo,
+    GetRange {
+        from_seq: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to_seq: Option<u64>,
+        #[serde(default)]
+        limit: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditFeedLine {
+    A,
+    B,
+}
+
+impl From<pb::FeedLine> for AuditFeedLine {
+    fn from(line: pb::FeedLine) -> Self {
+        match line {
+            pb::FeedLine::A => Self::A,
+            pb::FeedLine::B => Self::B,
+        }
+    }
+}
+
+impl From<AuditFeedLine> for pb::FeedLine {
+    fn from(line: AuditFeedLine) -> Self {
+        match line {
+            AuditFeedLine::A => Self::A,
+            AuditFeedLine::B => Self::B,
+        }
+    }
+}
+
+/// A `ControlCommand` after the first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditControl {
+    Pause,
+    Resume,
+    Speed(f64),
+    SeekNs(i64),
+    SeekSequence(u64),
+    Step { count: u32, by_timestamp: bool },
+}
+
+impl AuditControl {
+    /// `None` for `start`, which only opens a stream.
+    pub(crate) fn from_command(command: &pb::control_command::Command) -> Option<Self> {
+        use pb::control_command::Command;
+        Some(match command {
+            Command::Start(_) => return None,
+            Command::Pause(_) => Self::Pause,
+            Command::Resume(_) => Self::Resume,
+            Command::Speed(speed) => Self::Speed(*speed),
+            Command::SeekNs(ns) => Self::SeekNs(*ns),
+            Command::SeekSequence(seq) => Self::SeekSequence(*seq),
+            Command::Step(step) => Self::Step {
+                count: step.count,
+                by_timestamp: step.by_timestamp,
+            },
+        })
+    }
+}
+
+impl From<AuditControl> for pb::control_command::Command {
+    fn from(control: AuditControl) -> Self {
+        match control {
+            AuditControl::Pause => Self::Pause(true),
+            AuditControl::Resume => Self::Resume(true),
+            AuditControl::Speed(speed) => Self::Speed(speed),
+            AuditControl::SeekNs(ns) => Self::SeekNs(ns),
+            AuditControl::SeekSequence(seq) => Self::SeekSequence(seq),
+            AuditControl::Step {
+                count,
+                by_timestamp,
+            } => Self::Step(pb::Step {
+                count,
+                by_timestamp,
+            }),
+        }
+    }
+}
+
+/// A `StreamRequest` as the client sent it, before server defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuditRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_ns: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_ns: Option<i64>,
+    #[serde(default)]
+    pub speed: f64,
+    #[serde(default)]
+    pub max_speed: bool,
+    #[serde(default)]
+    pub step_mode: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gap_ns: Option<i64>,
+    #[serde(default)]
+    pub enrich: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symbol_starts_ns: BTreeMap<String, i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closing_cross_ns: Option<i64>,
+    #[serde(default)]
+    pub batch_closing_cross: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub venues: Vec<String>,
+    #[serde(default)]
+    pub venue_channels: bool,
+    #[serde(default)]
+    pub max_batch: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ns: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_ns: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_burst: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_seed: Option<u64>,
+    #[serde(default)]
+    pub wall_clock: bool,
+    #[serde(default)]
+    pub checkpoint_every: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub resume_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_events_per_sec: Option<f64>,
+    #[serde(default)]
+    pub buffer: u32,
+    #[serde(default)]
+    pub backpressure: i32,
+}
+
+impl From<&StreamRequest> for AuditRequest {
+    fn from(req: &StreamRequest) -> Self {
+        Self {
+            from_ns: req.from_ns,
+            to_ns: req.to_ns,
+            speed: req.speed,
+            max_speed: req.max_speed,
+            step_mode: req.step_mode,
+            max_gap_ns: req.max_gap_ns,
+            enrich: req.enrich,
+            symbols: req.symbols.clone(),
+            symbol_starts_ns: req
+                .symbol_starts_ns
+                .iter()
+                .map(|(symbol, ns)| (symbol.clone(), *ns))
+                .collect(),
+            closing_cross_ns: req.closing_cross_ns,
+            batch_closing_cross: req.batch_closing_cross,
+            venues: req.venues.clone(),
+            venue_channels: req.venue_channels,
+            max_batch: req.max_batch,
+            latency_ns: req.latency_ns,
+            jitter_ns: req.jitter_ns,
+            jitter_seed: req.jitter_seed,
+            drop_rate: req.drop_rate,
+            drop_burst: req.drop_burst,
+            drop_seed: req.drop_seed,
+            wall_clock: req.wall_clock,
+            checkpoint_every: req.checkpoint_every,
+            resume_token: req.resume_token.clone(),
+            max_events_per_sec: req.max_events_per_sec,
+            buffer: req.buffer,
+            backpressure: req.backpressure,
+        }
+    }
+}
+
+impl From<AuditRequest> for StreamRequest {
+    fn from(req: AuditRequest) -> Self {
+        Self {
+            from_ns: req.from_ns,
+            to_ns: req.to_ns,
+            speed: req.speed,
+            max_speed: req.max_speed,
+            step_mode: req.step_mode,
+            max_gap_ns: req.max_gap_ns,
+            enrich: req.enrich,
+            symbols: req.symbols,
+            symbol_starts_ns: req.symbol_starts_ns.into_iter().collect(),
+            closing_cross_ns: req.closing_cross_ns,
+            batch_closing_cross: req.batch_closing_cross,
+            venues: req.venues,
+            venue_channels: req.venue_channels,
+            max_batch: req.max_batch,
+            latency_ns: req.latency_ns,
+            jitter_ns: req.jitter_ns,
+            jitter_seed: req.jitter_seed,
+            drop_rate: req.drop_rate,
+            drop_burst: req.drop_burst,
+            drop_seed: req.drop_seed,
+            wall_clock: req.wall_clock,
+            checkpoint_every: req.checkpoint_every,
+            resume_token: req.resume_token,
+            max_events_per_sec: req.max_events_per_sec,
+            buffer: req.buffer,
+            backpressure: req.backpressure,
+        }
+    }
+}
+
+/// Appends records to a JSON-lines file shared by every session.
+#[derive(Clone)]
+pub struct AuditLog {
+    file: Arc<Mutex<File>>,
+    connections: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, ReplayError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            connections: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Failures are logged, not returned: a full disk should not stop
+    /// replay.
+    pub fn record(&self, record: &AuditRecord) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!(error = %err, "audit record not serializable");
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = file.write_all(&line) {
+            warn!(error = %err, "audit log write failed");
+        }
+    }
+
+    /// Starts recording one call from `token` at `peer`.
+    pub(crate) fn connection(&self, token: &str, peer: Option<SocketAddr>) -> AuditContext {
+        AuditContext {
+            log: self.clone(),
+            connection: self.connections.fetch_add(1, Ordering::Relaxed) + 1,
+            client: client_fingerprint(token),
+            peer: peer.map(|addr| addr.to_string()),
+        }
+    }
+}
+
+pub(crate) struct AuditContext {
+    log: AuditLog,
+    connection: u64,
+    client: String,
+    peer: Option<String>,
+}
+
+impl AuditContext {
+    pub(crate) fn record(&self, call: AuditCall) {
+        self.log.record(&AuditRecord {
+            received_ns: Timestamp::now(),
+            connection: self.connection,
+            client: self.client.clone(),
+            peer: self.peer.clone(),
+            call,
+        });
+    }
+}
+
+pub fn read_audit_log(path: &Path) -> Result<Vec<AuditRecord>, ReplayError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .map_err(|err| ReplayError::Audit(format!("line {}: {err}", i + 1)))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// What one replayed `StreamEvents` call or subscription received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestOutcome {
+    /// Position of the request in the audit log, from 0.
+    pub request: usize,
+    pub rpc: String,
+    pub events: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_sequence: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sequence: Option<u64>,
+    /// FNV-1a over every encoded event message, in arrival order.
+    pub digest: String,
+    /// `completed`, the subscription's end reason, or the error status.
+    pub end: String,
+}
+
+struct Tally {
+    request: usize,
+    rpc: &'static str,
+    events: u64,
+    first_sequence: Option<u64>,
+    last_sequence: Option<u64>,
+    hash: u64,
+}
+
+impl Tally {
+    fn new(request: usize, rpc: &'static str) -> Self {
+        Self {
+            request,
+            rpc,
+            events: 0,
+            first_sequence: None,
+            last_sequence: None,
+            hash: FNV_OFFSET,
+        }
+    }
+
+    /// Day markers and checkpoints go into the digest but are not events;
+    /// a closing cross or event batch counts the events inside it. Stream
+    /// stats depend on how fast the client read, so they are left out.
+    fn push(&mut self, msg: &pb::EventMessage) {
+        if let Some(pb::event_message::Payload::Stats(_)) = msg.payload {
+            return;
+        }
+        self.hash = fnv1a(self.hash, &msg.encode_to_vec());
+        let events = match &msg.payload {
+            Some(
+                pb::event_message::Payload::DayBoundary(_)
+                | pb::event_message::Payload::Checkpoint(_),
+            ) => return,
+            Some(pb::event_message::Payload::ClosingCross(cross)) => &cross.events[..],
+            Some(pb::event_message::Payload::Batch(batch)) => &batch.events[..],
+            _ => std::slice::from_ref(msg),
+        };
+        for event in events {
+            self.events += 1;
+            self.first_sequence.get_or_insert(event.sequence);
+            self.last_sequence = Some(event.sequence);
+        }
+    }
+
+    /// Folds a reply into the digest without counting events.
+    fn absorb(&mut self, msg: &impl Message) {
+        self.hash = fnv1a(self.hash, &msg.encode_to_vec());
+    }
+
+    fn finish(self, end: String) -> RequestOutcome {
+        RequestOutcome {
+            request: self.request,
+            rpc: self.rpc.to_string(),
+            events: self.events,
+            first_sequence: self.first_sequence,
+            last_sequence: self.last_sequence,
+            digest: format!("{:016x}", self.hash),
+            end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayRequestsOptions {
+    /// Issue every request immediately instead of at its recorded offset
+    /// from the first one.
+    pub no_wait: bool,
+    /// Bearer tokens to call with. A call recorded under a token's
+    /// fingerprint is sent with that token, and any other non-anonymous
+    /// call with the first one; anonymous calls go without.
+    pub tokens: Vec<String>,
+    /// PEM certificates to trust; the server is called over TLS when set.
+    pub ca_cert: Option<PathBuf>,
+}
+
+impl ReplayRequestsOptions {
+    /// The token to send for calls recorded from `client`.
+    fn tokens_by_client(&self) -> impl Fn(&str) -> Option<String> {
+        let by_client: HashMap<String, String> = self
+            .tokens
+            .iter()
+            .map(|token| (client_fingerprint(token), token.clone()))
+            .collect();
+        let fallback = self.tokens.first().cloned();
+        move |client| {
+            if client == ANONYMOUS_CLIENT {
+                return None;
+            }
+            by_client.get(client).cloned().or_else(|| fallback.clone())
+        }
+    }
+}
+
+type Scheduled = (usize, Instant, AuditRecord);
+
+/// Issues `records` against the server at `endpoint` (e.g.
+/// `http://127.0.0.1:50051`) and reports what each call and subscription
+/// received, ordered by request. Calls run concurrently, and multiplexed
+/// and control commands go out on one stream per recorded connection.
+pub async fn replay_requests(
+    endpoint: &str,
+    records: &[AuditRecord],
+    options: ReplayRequestsOptions,
+) -> Result<Vec<RequestOutcome>, ReplayError> {
+    let channel = match &options.ca_cert {
+        Some(ca) => crate::tls::connect(endpoint, ca).await?,
+        None => {
+            Endpoint::from_shared(endpoint.to_string())?
+                .connect()
+                .await?
+        }
+    };
+    let client = ReplayServiceClient::new(channel);
+    let token_for = options.tokens_by_client();
+
+    let start = Instant::now();
+    let base = records.iter().map(|r| r.received_ns).min();
+    let due = |record: &AuditRecord| match base {
+        Some(base) if !options.no_wait => {
+            let offset = record.received_ns.nanos_since(base).max(0);
+            start + Duration::from_nanos(offset as u64)
+        }
+        _ => start,
+    };
+
+    let mut tasks = JoinSet::new();
+    let mut connections = BTreeMap::<u64, Vec<Scheduled>>::new();
+    for (i, record) in records.iter().enumerate() {
+        match &record.call {
+            AuditCall::Multiplex
+            | AuditCall::Subscribe { .. }
+            | AuditCall::Unsubscribe { .. }
+            | AuditCall::Control { .. }
+            | AuditCall::ControlCommand { .. } => connections
+                .entry(record.connection)
+                .or_default()
+                .push((i, due(record), record.clone())),
+            call => {
+                tasks.spawn(call_once(
+                    client.clone(),
+                    i,
+                    token_for(&record.client),
+                    call.clone(),
+                    due(record),
+                ));
+            }
+        }
+    }
+    for commands in connections.into_values() {
+        let Some((_, _, first)) = commands.first() else {
+            continue;
+        };
+        let token = token_for(&first.client);
+        if matches!(first.call, AuditCall::Control { .. }) {
+            tasks.spawn(control_once(client.clone(), token, commands));
+        } else {
+            tasks.spawn(multiplex_once(client.clone(), token, commands));
+        }
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(done) = tasks.join_next().await {
+        match done {
+            Ok(mut batch) => outcomes.append(&mut batch),
+            Err(err) => warn!(error = %err, "replayed request task failed"),
+        }
+    }
+    outcomes.sort_by_key(|o| o.request);
+    Ok(outcomes)
+}
+
+fn authorized<T>(message: T, token: Option<&str>) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(token) = token {
+        if let Ok(value) = format!("Bearer {token}").parse() {
+            request.metadata_mut().insert("authorization", value);
+        }
+    }
+    request
+}
+
+fn status_text(status: &Status) -> String {
+    format!("{:?}: {}", status.code(), status.message())
+}
+
+/// Issues one call that stands on its own connection.
+async fn call_once(
+    mut client: ReplayServiceClient<Channel>,
+    request: usize,
+    token: Option<String>,
+    call: AuditCall,
+    at: Instant,
+) -> Vec<RequestOutcome> {
+    tokio::time::sleep_until(at).await;
+    let token = token.as_deref();
+    let outcome = match call {
+        AuditCall::StreamEvents { request: req } => {
+            let response = client
+                .stream_events(authorized(StreamRequest::from(req), token))
+                .await;
+            drain(Tally::new(request, "stream_events"), response).await
+        }
+        AuditCall::StreamFeedLine {
+            pair,
+            line,
+            request: req,
+            ..
+        } => {
+            let req = pb::FeedLineRequest {
+                pair,
+                line: pb::FeedLine::from(line).into(),
+                request: Some(req.into()),
+            };
+            let response = client.stream_feed_line(authorized(req, token)).await;
+            drain(Tally::new(request, "stream_feed_line"), response).await
+        }
+        AuditCall::CreateSession { name, request: req } => {
+            let req = pb::CreateSessionRequest {
+                name,
+                request: Some(req.into()),
+            };
+            let response = client.create_session(authorized(req, token)).await;
+            drain(Tally::new(request, "create_session"), response).await
+        }
+        AuditCall::ListSessions => {
+            // Only the names: progress and lag depend on timing.
+            let mut tally = Tally::new(request, "list_sessions");
+            match client
+                .list_sessions(authorized(pb::ListSessionsRequest {}, token))
+                .await
+            {
+                Ok(list) => {
+                    list.get_ref()
+                        .sessions
+                        .iter()
+                        .for_each(|session| tally.absorb(&session.name));
+                    tally.finish(String::from("completed"))
+                }
+                Err(status) => tally.finish(status_text(&status)),
+            }
+        }
+        AuditCall::CloseSession { name } => {
+            let tally = Tally::new(request, "close_session");
+            let req = pb::CloseSessionRequest { name };
+            match client.close_session(authorized(req, token)).await {
+                Ok(_) => tally.finish(String::from("completed")),
+                Err(status) => tally.finish(status_text(&status)),
+            }
+        }
+        AuditCall::GetLogInfo => {
+            let mut tally = Tally::new(request, "get_log_info");
+            match client
+                .get_log_info(authorized(pb::GetLogInfoRequest {}, token))
+                .await
+            {
+                Ok(info) => {
+                    tally.absorb(info.get_ref());
+                    tally.finish(String::from("completed"))
+                }
+                Err(status) => tally.finish(status_text(&status)),
+            }
+        }
+        AuditCall::GetRange {
+            from_seq,
+            to_seq,
+            limit,
+        } => {
+            let mut tally = Tally::new(request, "get_range");
+            let req = pb::GetRangeRequest {
+                from_seq,
+                to_seq,
+                limit,
+            };
+            match client.get_range(authorized(req, token)).await {
+                Ok(page) => {
+                    page.get_ref().events.iter().for_each(|e| tally.push(e));
+                    tally.finish(String::from("completed"))
+                }
+                Err(status) => tally.finish(status_text(&status)),
+            }
+        }
+        AuditCall::Multiplex
+        | AuditCall::Subscribe { .. }
+        | AuditCall::Unsubscribe { .. }
+        | AuditCall::Control { .. }
+        | AuditCall::ControlCommand { .. } => return Vec::new(),
+    };
+    vec![outcome]
+}
+
+/// Tallies a streamed reply until it ends.
+async fn drain(
+    mut tally: Tally,
+    response: Result<Response<Streaming<pb::EventMessage>>, Status>,
+) -> RequestOutcome {
+    let end = match response {
+        Err(status) => status_text(&status),
+        Ok(response) => {
+            let mut stream = response.into_inner();
+            loop {
+                match stream.next().await {
+                    Some(Ok(msg)) => tally.push(&msg),
+                    Some(Err(status)) => break status_text(&status),
+                    None => break String::from("completed"),
+                }
+            }
+        }
+    };
+    tally.finish(end)
+}
+
+/// Replays one recorded `Control` stream, sending each command at its
+/// recorded offset and half-closing after the last. A replay left paused
+/// stays open, as the server keeps it.
+async fn control_once(
+    mut client: ReplayServiceClient<Channel>,
+    token: Option<String>,
+    commands: Vec<Scheduled>,
+) -> Vec<RequestOutcome> {
+    use pb::control_command::Command;
+
+    let mut commands = commands.into_iter();
+    let Some((request, opened, record)) = commands.next() else {
+        return Vec::new();
+    };
+    let AuditCall::Control { request: req } = record.call else {
+        return Vec::new();
+    };
+    tokio::time::sleep_until(opened).await;
+    let (tx, rx) = mpsc::channel(16);
+    let start = ControlCommand {
+        command: Some(Command::Start(req.into())),
+    };
+    let _ = tx.send(start).await;
+    let response = client
+        .control(authorized(ReceiverStream::new(rx), token.as_deref()))
+        .await;
+    let send = async move {
+        for (_, at, record) in commands {
+            let AuditCall::ControlCommand { command } = record.call else {
+                continue;
+            };
+            tokio::time::sleep_until(at).await;
+            let command = ControlCommand {
+                command: Some(command.into()),
+            };
+            if tx.send(command).await.is_err() {
+                break;
+            }
+        }
+    };
+    let ((), outcome) = tokio::join!(send, drain(Tally::new(request, "control"), response));
+    vec![outcome]
+}
+
+/// Replays one recorded `Multiplex` stream. Events and end messages go to
+/// the oldest open subscription with their id.
+async fn multiplex_once(
+    mut client: ReplayServiceClient<Channel>,
+    token: Option<String>,
+    commands: Vec<Scheduled>,
+) -> Vec<RequestOutcome> {
+    use pb::multiplex_message::Body;
+    use pb::subscription_command::Command;
+
+    let Some((_, opened, _)) = commands.first() else {
+        return Vec::new();
+    };
+    tokio::time::sleep_until(*opened).await;
+
+    let mut outcomes = Vec::new();
+    let (tx, rx) = mpsc::channel(16);
+    let mut stream = match client
+        .multiplex(authorized(ReceiverStream::new(rx), token.as_deref()))
+        .await
+    {
+        Ok(response) => response.into_inner(),
+        Err(status) => {
+            for (request, _, record) in &commands {
+                if matches!(record.call, AuditCall::Subscribe { .. }) {
+                    outcomes.push(Tally::new(*request, "subscribe").finish(status_text(&status)));
+                }
+            }
+            return outcomes;
+        }
+    };
+
+    let mut queue = commands.into_iter().peekable();
+    let mut tx = Some(tx);
+    let mut open = HashMap::<u64, VecDeque<Tally>>::new();
+    loop {
+        let next_due = queue.peek().map(|(_, at, _)| *at);
+        if next_due.is_none() {
+            // Half-close; subscriptions already running carry on.
+            tx = None;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep_until(next_due.unwrap_or_else(Instant::now)), if next_due.is_some() => {
+                let Some((request, _, record)) = queue.next() else {
+                    continue;
+                };
+                let command = match record.call {
+                    AuditCall::Subscribe { subscription_id, request: req } => {
+                        open.entry(subscription_id)
+                            .or_default()
+                            .push_back(Tally::new(request, "subscribe"));
+                        SubscriptionCommand {
+                            subscription_id,
+                            command: Some(Command::Subscribe(req.into())),
+                        }
+                    }
+                    AuditCall::Unsubscribe { subscription_id } => SubscriptionCommand {
+                        subscription_id,
+                        command: Some(Command::Unsubscribe(true)),
+                    },
+                    _ => continue,
+                };
+                if let Some(tx) = &tx {
+                    let _ = tx.send(command).await;
+                }
+            }
+            msg = stream.next() => match msg {
+                Some(Ok(msg)) => {
+                    let queue = open.get_mut(&msg.subscription_id);
+                    match (msg.body, queue) {
+                        (Some(Body::Event(event)), Some(queue)) => {
+                            if let Some(tally) = queue.front_mut() {
+                                tally.push(&event);
+                            }
+                        }
+                        (Some(Body::End(end)), Some(queue)) => {
+                            if let Some(tally) = queue.pop_front() {
+                                outcomes.push(tally.finish(end.reason));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(status)) => {
+                    let end = status_text(&status);
+                    for tally in open.drain().flat_map(|(_, q)| q) {
+                        outcomes.push(tally.finish(end.clone()));
+                    }
+                    break;
+                }
+                None => break,
+            }
+        }
+    }
+    for tally in open.into_values().flatten() {
+        outcomes.push(tally.finish(String::from("stream closed")));
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip_without_tokens() {
+        let path =
+            std::env::temp_dir().join(format!("md_replay_audit_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = AuditLog::open(&path).expect("open");
+        let ctx = log.connection("secret-token", None);
+        ctx.record(AuditCall::Subscribe {
+            subscription_id: 7,
+            request: AuditRequest {
+                from_ns: Some(5),
+                speed: 2.0,
+                symbols: vec![String::from("AAPL")],
+                ..AuditRequest::default()
+            },
+        });
+        log.connection("", "127.0.0.1:9".parse().ok())
+            .record(AuditCall::Multiplex);
+
+        let raw = std::fs::read_to_string(&path).expect("read");
+        assert!(!raw.contains("secret-token"));
+        let records = read_audit_log(&path).expect("parse");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].connection, 1);
+        assert_eq!(records[0].client, client_fingerprint("secret-token"));
+        assert_eq!(
+            StreamRequest::from(match &records[0].call {
+                AuditCall::Subscribe { request, .. } => request.clone(),
+                other => panic!("unexpected {other:?}"),
+            }),
+            StreamRequest {
+                from_ns: Some(5),
+                speed: 2.0,
+                symbols: vec![String::from("AAPL")],
+                ..StreamRequest::default()
+            }
+        );
+        assert_eq!(records[1].client, ANONYMOUS_CLIENT);
+        assert_eq!(records[1].peer.as_deref(), Some("127.0.0.1:9"));
+        assert_eq!(records[1].call, AuditCall::Multiplex);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn calls_are_sent_with_the_token_recorded_for_them() {
+        let options = ReplayRequestsOptions {
+            tokens: vec![String::from("alpha"), String::from("beta")],
+            ..ReplayRequestsOptions::default()
+        };
+        let token_for = options.tokens_by_client();
+        assert_eq!(
+            token_for(&client_fingerprint("beta")).as_deref(),
+            Some("beta")
+        );
+        assert_eq!(
+            token_for(&client_fingerprint("gamma")).as_deref(),
+            Some("alpha")
+        );
+        assert_eq!(token_for(ANONYMOUS_CLIENT), None);
+        let token_for = ReplayRequestsOptions::default().tokens_by_client();
+        assert_eq!(token_for(&client_fingerprint("beta")), None);
+    }
+}
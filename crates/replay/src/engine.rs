@@ -1,20 +1,88 @@
 use crate::pb;
-use md_core::{Event, Payload};
-use md_storage::{EventLogReader, IndexReader, StorageError};
+use crate::rng::Rng;
+use crate::transform::Transforms;
+use md_core::{ErrorKind, Event, FeatureConfig, FeatureEngine, OrderCheck, Payload, Timestamp};
+use md_storage::{EventLogReader, IndexReader, RecordFilter, StorageError};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::ops::{ControlFlow, RangeInclusive};
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tonic::Status;
 
 #[derive(Debug, Clone)]
 pub struct ReplayConfig {
-    pub from_ns: Option<u64>,
-    pub to_ns: Option<u64>,
+    pub from_ns: Option<Timestamp>,
+    pub to_ns: Option<Timestamp>,
     pub speed: f64,
     pub max_speed: bool,
+    /// Hold every event until a `Control` client steps to it. Streams
+    /// nothing can step go out unpaced.
     pub step_mode: bool,
+    /// Longest pause between two events, in event time; longer ones are
+    /// shortened to it. Between stitched days it replaces the whole gap,
+    /// which is otherwise skipped.
+    pub max_gap_ns: Option<i64>,
+    /// Attach each symbol's book features to streamed trades and quotes.
+    pub enrich: bool,
+    /// Historical start of each listed symbol. The symbol's events before
+    /// it are dropped, and its start lines up with the earliest listed
+    /// start, so every listed symbol begins at the same wall-clock moment.
+    /// Other symbols play at their own times. Symbols and starts are as
+    /// the transforms leave them.
+    pub symbol_starts: BTreeMap<String, Timestamp>,
+    /// UTC time of day of the closing cross, in ns after midnight. Events
+    /// stamped exactly then go out together: only the first is paced, and
+    /// no other message is sent between them.
+    pub closing_cross: Option<i64>,
+    /// Wrap each closing cross in one `ClosingCross` message.
+    pub batch_closing_cross: bool,
+    /// Venues numbered by position. Each event's message carries its
+    /// venue's number as `channel_id`; empty leaves it unset.
+    pub venue_channels: Vec<String>,
+    /// Send events that are due together as `EventBatch` messages of up
+    /// to this many; 0 or 1 sends each on its own.
+    pub max_batch: usize,
+    /// Wall-clock delay added to every paced event's send time, in ns.
+    pub latency_ns: i64,
+    /// Most extra delay drawn for a paced event, uniformly from
+    /// `[0, jitter_ns]`. Events still go out in order, so a long draw
+    /// holds back the events behind it.
+    pub jitter_ns: i64,
+    /// Seeds the jitter draws; the same seed delays the same events alike.
+    pub jitter_seed: u64,
+    /// Chance, from 0 to 1, that an event starts a simulated loss of
+    /// `drop_burst` events. Lost events are paced but never sent.
+    pub drop_rate: f64,
+    /// Events lost together once a loss starts; 0 counts as 1.
+    pub drop_burst: usize,
+    /// Seeds the loss draws; the same seed loses the same events.
+    pub drop_seed: u64,
+    /// Pace at 1x against today's UTC clock: the first event is due at its
+    /// own time of day today and the rest follow at their offsets from it,
+    /// gaps between days included. Events already past go out at once.
+    /// Pausing or changing speed lets go of the anchor.
+    pub wall_clock: bool,
+    /// Send a checkpoint after about every this many events, counting
+    /// lost ones; 0 sends none.
+    pub checkpoint_every: usize,
+    /// Where a resumed stream continues; checked against the events with
+    /// [`ResumeToken::check`] before streaming.
+    pub resume: Option<ResumeToken>,
+    /// Run over the events before they are staggered and paced, after
+    /// any plugin and the time window. The server also applies them after
+    /// a request's filters, which still see the stored symbols and venues.
+    pub transforms: Transforms,
+    /// Most events sent per wall-clock second, at any speed and in max
+    /// speed too. Quiet periods are not saved up for a burst afterwards.
+    pub max_events_per_sec: Option<f64>,
 }
 
 impl Default for ReplayConfig {
@@ -25,63 +93,981 @@ impl Default for ReplayConfig {
             speed: 1.0,
             max_speed: false,
             step_mode: false,
+            max_gap_ns: None,
+            enrich: false,
+            symbol_starts: BTreeMap::new(),
+            closing_cross: None,
+            batch_closing_cross: false,
+            venue_channels: Vec::new(),
+            max_batch: 0,
+            latency_ns: 0,
+            jitter_ns: 0,
+            jitter_seed: 0,
+            drop_rate: 0.0,
+            drop_burst: 1,
+            drop_seed: 0,
+            wall_clock: false,
+            checkpoint_every: 0,
+            resume: None,
+            transforms: Transforms::new(),
+            max_events_per_sec: None,
         }
     }
 }
 
+impl ReplayConfig {
+    /// Nanoseconds each staggered symbol's clock is moved back so its
+    /// start lines up with the earliest one.
+    fn symbol_shifts(&self) -> HashMap<String, i64> {
+        let Some(&common) = self.symbol_starts.values().min() else {
+            return HashMap::new();
+        };
+        self.symbol_starts
+            .iter()
+            .map(|(symbol, start)| (symbol.clone(), start.nanos_since(common)))
+            .collect()
+    }
+}
+
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+
+/// Whether `event` is stamped at the `closing_cross` time of day.
+fn at_closing_cross(closing_cross: Option<i64>, event: &Event) -> bool {
+    closing_cross
+        .is_some_and(|cross| event.timestamp_ns.as_nanos().rem_euclid(NANOS_PER_DAY) == cross)
+}
+
+fn replay_time(event: &Event, shifts: &HashMap<String, i64>) -> Timestamp {
+    match shifts.get(&event.symbol) {
+        Some(&shift) => event.timestamp_ns.saturating_add_nanos(-shift),
+        None => event.timestamp_ns,
+    }
+}
+
+/// Applies `symbol_starts`: drops each staggered symbol's events before
+/// its start and puts the rest in the order they are paced, by shifted
+/// time and then sequence.
+pub(crate) fn stagger(events: &mut Vec<Event>, config: &ReplayConfig) {
+    if config.symbol_starts.is_empty() {
+        return;
+    }
+    let shifts = config.symbol_shifts();
+    events.retain(|e| {
+        config
+            .symbol_starts
+            .get(&e.symbol)
+            .is_none_or(|start| e.timestamp_ns >= *start)
+    });
+    events.sort_by_key(|e| (replay_time(e, &shifts), e.sequence));
+}
+
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum ReplayError {
     #[error("storage error: {0}")]
     Storage(#[from] StorageError),
     #[error("transport error: {0}")]
     Transport(#[from] tonic::transport::Error),
+    #[error("plugin error: {0}")]
+    Plugin(String),
+    #[error("wasm plugin support not enabled")]
+    PluginUnavailable,
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("audit log error: {0}")]
+    Audit(String),
+    #[error("cannot stitch logs: {0}")]
+    Stitch(String),
+    #[error("cannot merge logs: {0}")]
+    Merge(String),
+    #[error("cannot resume: {0}")]
+    Resume(String),
+    #[error("tls error: {0}")]
+    Tls(String),
+    #[error("reflection error: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
+}
+
+impl ReplayError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Storage(err) => err.kind(),
+            Self::Transport(err) => ErrorKind::from_source_chain(err),
+            Self::Plugin(_) | Self::PluginUnavailable => ErrorKind::Config,
+            Self::Io(err) => ErrorKind::from_io(err),
+            Self::Audit(_) => ErrorKind::Corrupt,
+            Self::Stitch(_) | Self::Merge(_) | Self::Resume(_) | Self::Tls(_) => ErrorKind::Config,
+            Self::Reflection(_) => ErrorKind::Corrupt,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    /// gRPC status carrying the classification: clients retry on
+    /// `UNAVAILABLE` and treat `DATA_LOSS` / `FAILED_PRECONDITION` as final.
+    pub fn to_status(&self) -> Status {
+        let message = self.to_string();
+        match self.kind() {
+            ErrorKind::Transient => Status::unavailable(message),
+            ErrorKind::Corrupt => Status::data_loss(message),
+            ErrorKind::Config => Status::failed_precondition(message),
+            _ => Status::internal(message),
+        }
+    }
 }
 
 pub fn read_events(
     log_path: &Path,
     index_path: Option<&Path>,
-    from_ns: Option<u64>,
-    to_ns: Option<u64>,
+    from_ns: Option<Timestamp>,
+    to_ns: Option<Timestamp>,
 ) -> Result<Vec<Event>, ReplayError> {
     let mut reader = EventLogReader::open(log_path)?;
-    match (from_ns, index_path) {
-        (Some(from), Some(idx_path)) if idx_path.exists() => {
-            let idx = IndexReader::open(idx_path)?;
-            if let Some(offset) = idx.seek_offset(from) {
-                reader.seek(offset)?;
-            } else {
-                reader.rewind_to_data()?;
-            }
+    reader.rewind_to_data()?;
+    let windowed = from_ns.is_some() || to_ns.is_some();
+    if let Some(idx_path) = index_path.filter(|p| windowed && p.exists()) {
+        let idx = IndexReader::open(idx_path)?;
+        if let Some(offset) = from_ns.and_then(|from| idx.seek_offset(from)) {
+            reader.seek(offset)?;
         }
-        _ => {
-            reader.rewind_to_data()?;
+        // Stop at the first stride past the window instead of relying on
+        // decoding a record stamped after `to_ns`.
+        if let Some(offset) = to_ns.and_then(|to| idx.end_offset(to)) {
+            reader.stop_at(offset);
         }
     }
 
+    let filter = RecordFilter {
+        from_ns,
+        to_ns,
+        ..RecordFilter::default()
+    };
     let mut out = Vec::new();
-    loop {
-        let next = reader.next_record()?;
-        let Some(record) = next else {
-            break;
+    while let Some(record) = reader.next_record_filtered(&filter)? {
+        out.push(record.event);
+    }
+
+    out.sort_by_key(|e| e.sequence);
+    Ok(out)
+}
+
+/// [`read_events`] run through `transforms`, as a replay sees the window
+/// before staggering and pacing. The window selects stored times.
+pub fn read_transformed(
+    log_path: &Path,
+    index_path: Option<&Path>,
+    from_ns: Option<Timestamp>,
+    to_ns: Option<Timestamp>,
+    transforms: &Transforms,
+) -> Result<Vec<Event>, ReplayError> {
+    let events = read_events(log_path, index_path, from_ns, to_ns)?;
+    Ok(transforms.apply_all(events))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    Completed { delivered: u64 },
+    Stopped { delivered: u64 },
+    Cancelled { delivered: u64 },
+}
+
+/// Start of one day's events in a stitched replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DayBoundary {
+    /// Position among the stitched logs, from 0.
+    pub day: u32,
+    /// File name of the day's log.
+    pub log: String,
+    pub first_ns: Timestamp,
+    /// The day's events are the ones from this sequence on.
+    pub first_sequence: u64,
+}
+
+impl DayBoundary {
+    pub fn to_proto(&self) -> pb::EventMessage {
+        pb::EventMessage {
+            timestamp_ns: self.first_ns.as_nanos(),
+            sequence: 0,
+            venue: String::new(),
+            symbol: String::new(),
+            payload: Some(pb::event_message::Payload::DayBoundary(pb::DayBoundary {
+                day: self.day,
+                log: self.log.clone(),
+            })),
+            features: None,
+            source_sequence: None,
+            channel_id: None,
+        }
+    }
+}
+
+/// Builds stream messages, with the symbol's book features attached when
+/// the replay is enriched and the venue's channel when it has them.
+pub(crate) struct Enricher {
+    engine: Option<FeatureEngine>,
+    channels: Vec<String>,
+}
+
+impl Enricher {
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        Self {
+            engine: config
+                .enrich
+                .then(|| FeatureEngine::new(FeatureConfig::default())),
+            channels: config.venue_channels.clone(),
+        }
+    }
+
+    pub(crate) fn message(&mut self, event: &Event) -> pb::EventMessage {
+        let mut msg = to_proto(event);
+        msg.channel_id = self
+            .channels
+            .iter()
+            .position(|v| v.eq_ignore_ascii_case(&event.venue))
+            .and_then(|i| u32::try_from(i).ok());
+        if let Some(engine) = &mut self.engine {
+            let f = engine.features(event);
+            msg.features = Some(pb::Features {
+                mid: f.mid,
+                rolling_mid: f.rolling_mid,
+                spread: f.spread,
+                imbalance: f.imbalance,
+                vol: f.vol,
+            });
+        }
+        msg
+    }
+}
+
+/// Messages built up to `depth` events before they are due, a batch at a
+/// time, so the paced loop only waits and sends.
+pub(crate) struct ReadAhead<'a> {
+    events: std::slice::Iter<'a, Event>,
+    enricher: Enricher,
+    depth: usize,
+    ready: VecDeque<(&'a Event, pb::EventMessage)>,
+    closing_cross: Option<i64>,
+}
+
+/// Events that go out together: one event, or a whole closing cross.
+pub(crate) struct Group<'a> {
+    pub(crate) items: Vec<(&'a Event, pb::EventMessage)>,
+    pub(crate) closing_cross: bool,
+}
+
+impl Group<'_> {
+    /// The group's messages, or one `ClosingCross` message wrapping them
+    /// when `batch` is set.
+    pub(crate) fn into_messages(self, batch: bool) -> Vec<pb::EventMessage> {
+        let first = self
+            .items
+            .first()
+            .map(|(event, _)| (event.timestamp_ns.as_nanos(), event.sequence));
+        let messages = self.items.into_iter().map(|(_, msg)| msg).collect();
+        match first {
+            Some((timestamp_ns, sequence)) if batch && self.closing_cross => {
+                vec![pb::EventMessage {
+                    timestamp_ns,
+                    sequence,
+                    venue: String::new(),
+                    symbol: String::new(),
+                    payload: Some(pb::event_message::Payload::ClosingCross(pb::ClosingCross {
+                        events: messages,
+                    })),
+                    features: None,
+                    source_sequence: None,
+                    channel_id: None,
+                }]
+            }
+            _ => messages,
+        }
+    }
+}
+
+/// Collects released events into `EventBatch` messages of up to `max`.
+/// Callers flush it before anything that must not overtake the held
+/// events: a wait, a day marker or a seek.
+pub(crate) struct Batcher {
+    max: usize,
+    held: Vec<pb::EventMessage>,
+}
+
+impl Batcher {
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        Self {
+            max: config.max_batch,
+            held: Vec::new(),
+        }
+    }
+
+    /// Messages ready to go out once `group` is released. Closing crosses
+    /// are never batched, so they flush whatever is held first.
+    pub(crate) fn add(&mut self, group: Group<'_>, batch_cross: bool) -> Vec<pb::EventMessage> {
+        if self.max <= 1 || group.closing_cross {
+            let mut out = self.flush().into_iter().collect::<Vec<_>>();
+            out.extend(group.into_messages(batch_cross));
+            return out;
+        }
+        self.held.extend(group.into_messages(batch_cross));
+        if self.held.len() >= self.max {
+            return self.flush().into_iter().collect();
+        }
+        Vec::new()
+    }
+
+    /// The held events as one message; a lone event goes out as itself.
+    pub(crate) fn flush(&mut self) -> Option<pb::EventMessage> {
+        if self.held.len() <= 1 {
+            return self.held.pop();
+        }
+        let events = std::mem::take(&mut self.held);
+        let (first_ns, sequence) = events.first().map(|m| (m.timestamp_ns, m.sequence))?;
+        let last_ns = events.last()?.timestamp_ns;
+        Some(pb::EventMessage {
+            timestamp_ns: first_ns,
+            sequence,
+            venue: String::new(),
+            symbol: String::new(),
+            payload: Some(pb::event_message::Payload::Batch(pb::EventBatch {
+                events,
+                first_ns,
+                last_ns,
+            })),
+            features: None,
+            source_sequence: None,
+            channel_id: None,
+        })
+    }
+}
+
+/// Simulated packet loss: each event starts a burst of lost events with
+/// the configured chance, drawn from the drop seed.
+pub(crate) struct Dropper {
+    rate: f64,
+    burst: usize,
+    /// Events the current burst has yet to lose.
+    left: usize,
+    rng: Rng,
+}
+
+impl Dropper {
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        Self {
+            rate: config.drop_rate.clamp(0.0, 1.0),
+            burst: config.drop_burst.max(1),
+            left: 0,
+            rng: Rng::new(config.drop_seed),
+        }
+    }
+
+    /// Draws for `count` events, as if they had gone by.
+    pub(crate) fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.drops();
+        }
+    }
+
+    /// Whether the next event is lost.
+    pub(crate) fn drops(&mut self) -> bool {
+        if self.left > 0 {
+            self.left -= 1;
+            return true;
+        }
+        if self.rate > 0.0 && self.rng.unit() < self.rate {
+            self.left = self.burst - 1;
+            return true;
+        }
+        false
+    }
+}
+
+/// Lowest events-per-second cap a stream keeps to; lower caps are raised
+/// to it, so no event waits more than 1,000 s for its turn.
+pub const MIN_EVENTS_PER_SEC: f64 = 0.001;
+
+/// How far ahead a throttle's next slot goes when the clock cannot
+/// represent the real one, as tokio does for timers that never fire.
+const FAR_FUTURE: Duration = Duration::from_secs(86_400 * 365 * 30);
+
+/// Spaces sends out to the configured events-per-second cap.
+pub(crate) struct Throttle {
+    /// Wall-clock time each event takes up.
+    interval: Option<Duration>,
+    /// Earliest the next send may go.
+    next: Option<Instant>,
+}
+
+impl Throttle {
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        let interval = config
+            .max_events_per_sec
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .map(|rate| {
+                let longest = Duration::from_secs_f64(MIN_EVENTS_PER_SEC.recip());
+                Duration::try_from_secs_f64(rate.recip()).map_or(longest, |d| d.min(longest))
+            });
+        Self {
+            interval,
+            next: None,
+        }
+    }
+
+    /// Waits until `events` sent together fit under the cap.
+    pub(crate) async fn wait(&mut self, events: usize) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let now = Instant::now();
+        let slot = self.next.map_or(now, |next| next.max(now));
+        tokio::time::sleep_until(slot).await;
+        let taken = interval.saturating_mul(u32::try_from(events).unwrap_or(u32::MAX));
+        self.next = Some(slot.checked_add(taken).unwrap_or_else(|| slot + FAR_FUTURE));
+    }
+}
+
+/// Where a stream had got to at a checkpoint: how many of its events had
+/// gone by, and the sequence of the last of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumeToken {
+    pub offset: u64,
+    pub sequence: u64,
+}
+
+impl ResumeToken {
+    /// The index in `events` to continue from, if the token was taken from
+    /// a stream of the same events.
+    pub fn check(&self, events: &[Event]) -> Result<usize, ReplayError> {
+        let offset = usize::try_from(self.offset).unwrap_or(usize::MAX);
+        let matches = match offset.checked_sub(1) {
+            None => true,
+            Some(last) => events.get(last).map(|e| e.sequence) == Some(self.sequence),
+        };
+        if !matches {
+            return Err(ReplayError::Resume(format!(
+                "token {self} does not match this replay"
+            )));
+        }
+        Ok(offset)
+    }
+}
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.offset, self.sequence)
+    }
+}
+
+impl FromStr for ResumeToken {
+    type Err = ReplayError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || ReplayError::Resume(format!("invalid token {raw:?}"));
+        let (offset, sequence) = raw.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            offset: offset.parse().map_err(|_| invalid())?,
+            sequence: sequence.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Counts events going by a stream and says when a checkpoint is due.
+pub(crate) struct Checkpoints {
+    every: usize,
+    /// Index of the next event.
+    position: usize,
+    since: usize,
+}
+
+impl Checkpoints {
+    /// Starts at the config's resume point, which must have been checked.
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        let position = config.resume.map_or(0, |token| {
+            usize::try_from(token.offset).unwrap_or(usize::MAX)
+        });
+        Self {
+            every: config.checkpoint_every,
+            position,
+            since: 0,
+        }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Carries on from index `position` after a seek.
+    pub(crate) fn seek(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    /// Counts `passed` more events of `events`, sent or lost, and returns
+    /// a checkpoint message once enough have gone by.
+    pub(crate) fn pass(&mut self, events: &[Event], passed: usize) -> Option<pb::EventMessage> {
+        self.position += passed;
+        self.since += passed;
+        if self.every == 0 || self.since < self.every {
+            return None;
+        }
+        self.since = 0;
+        let last = events.get(self.position.checked_sub(1)?)?;
+        let token = ResumeToken {
+            offset: self.position as u64,
+            sequence: last.sequence,
+        };
+        Some(pb::EventMessage {
+            timestamp_ns: last.timestamp_ns.as_nanos(),
+            sequence: last.sequence,
+            venue: String::new(),
+            symbol: String::new(),
+            payload: Some(pb::event_message::Payload::Checkpoint(pb::Checkpoint {
+                resume_token: token.to_string(),
+            })),
+            features: None,
+            source_sequence: None,
+            channel_id: None,
+        })
+    }
+}
+
+/// The first of `days` to announce when a stream starts at `events[start]`:
+/// the day that event falls in.
+pub(crate) fn day_at(days: &[DayBoundary], events: &[Event], start: usize) -> usize {
+    events.get(start).map_or(days.len(), |e| {
+        days.partition_point(|d| d.first_sequence <= e.sequence)
+            .saturating_sub(1)
+    })
+}
+
+/// Adds `sequence` to `runs`, extending the last run when it follows on.
+pub(crate) fn extend_runs(runs: &mut Vec<RangeInclusive<u64>>, sequence: u64) {
+    match runs.last_mut() {
+        Some(run) if run.end().checked_add(1) == Some(sequence) => {
+            *run = *run.start()..=sequence;
+        }
+        _ => runs.push(sequence..=sequence),
+    }
+}
+
+pub(crate) fn to_ranges(runs: &[RangeInclusive<u64>]) -> Vec<pb::SequenceRange> {
+    runs.iter()
+        .map(|run| pb::SequenceRange {
+            first: *run.start(),
+            last: *run.end(),
+        })
+        .collect()
+}
+
+impl<'a> ReadAhead<'a> {
+    pub(crate) fn new(events: &'a [Event], config: &ReplayConfig, depth: usize) -> Self {
+        Self {
+            events: events.iter(),
+            enricher: Enricher::new(config),
+            depth: depth.max(1),
+            ready: VecDeque::new(),
+            closing_cross: config.closing_cross,
+        }
+    }
+
+    /// The next event, or every event of the closing cross it opens.
+    pub(crate) fn next_group(&mut self) -> Option<Group<'a>> {
+        let first = self.next()?;
+        let closing_cross = at_closing_cross(self.closing_cross, first.0);
+        let at = first.0.timestamp_ns;
+        let mut items = vec![first];
+        while closing_cross
+            && self
+                .peek()
+                .is_some_and(|(event, _)| event.timestamp_ns == at)
+        {
+            items.extend(self.ready.pop_front());
+        }
+        Some(Group {
+            items,
+            closing_cross,
+        })
+    }
+
+    fn peek(&mut self) -> Option<&(&'a Event, pb::EventMessage)> {
+        if self.ready.is_empty() {
+            self.fill();
+        }
+        self.ready.front()
+    }
+
+    fn fill(&mut self) {
+        for event in self.events.by_ref().take(self.depth) {
+            self.ready.push_back((event, self.enricher.message(event)));
+        }
+    }
+}
+
+impl<'a> Iterator for ReadAhead<'a> {
+    type Item = (&'a Event, pb::EventMessage);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.peek()?;
+        self.ready.pop_front()
+    }
+}
+
+/// Sends `messages` back to back. When they fit in the channel they are
+/// reserved together, so no other sender's message lands between them.
+/// False once the receiver is gone.
+pub(crate) async fn send_together<T>(tx: &mpsc::Sender<T>, messages: Vec<T>) -> bool {
+    if messages.len() > 1 && messages.len() <= tx.max_capacity() {
+        let Ok(permits) = tx.reserve_many(messages.len()).await else {
+            return false;
         };
+        for (permit, msg) in permits.zip(messages) {
+            permit.send(msg);
+        }
+        return true;
+    }
+    for msg in messages {
+        if tx.send(msg).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// A change to a running replay, sent over the `Control` RPC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Pause,
+    Resume,
+    /// New speed multiplier, which also ends max-speed replay. Ignored
+    /// unless positive.
+    Speed(f64),
+    /// Replay from the first event at or after this time.
+    SeekTime(Timestamp),
+    /// Replay from the first event at or after this sequence.
+    SeekSequence(u64),
+    /// Releases `count` more events in step mode, or `count` timestamps
+    /// with every event stamped at each when `by_timestamp` is set.
+    Step {
+        count: u32,
+        by_timestamp: bool,
+    },
+}
+
+/// Steps a client has asked for and not yet used.
+#[derive(Debug, Default)]
+struct Steps {
+    left: u32,
+    by_timestamp: bool,
+    /// Timestamp whose events the current step releases.
+    batch: Option<Timestamp>,
+}
+
+impl Steps {
+    fn add(&mut self, count: u32, by_timestamp: bool) {
+        self.left = self.left.saturating_add(count.max(1));
+        self.by_timestamp = by_timestamp;
+    }
+
+    /// Whether `event` may go out, using up a step if it starts one.
+    fn take(&mut self, event: &Event) -> bool {
+        if self.batch == Some(event.timestamp_ns) {
+            return true;
+        }
+        if self.left == 0 {
+            return false;
+        }
+        self.left -= 1;
+        self.batch = self.by_timestamp.then_some(event.timestamp_ns);
+        true
+    }
+}
+
+/// Sleeps until each event's scaled offset from the first event, measured
+/// from construction time. Time cut by the gap cap or skipped between
+/// days comes off every later offset. Staggered symbols are timed from
+/// their own start. Events after the first of a closing cross go out
+/// without waiting. Injected latency and jitter push each paced event
+/// back. Pausing or changing speed moves the clock's origin so the replay
+/// carries on from where it stood.
+pub(crate) struct Pacer {
+    config: ReplayConfig,
+    shifts: HashMap<String, i64>,
+    jitter: Rng,
+    start: Instant,
+    /// Offset reached at `start`.
+    base_ns: i64,
+    /// Offset the replay was paused at.
+    paused: Option<i64>,
+    first_ts: Option<Timestamp>,
+    last_ts: Option<Timestamp>,
+    skipped_ns: i64,
+    new_day: bool,
+    /// Timestamp of the closing cross being sent.
+    cross_at: Option<Timestamp>,
+}
+
+impl Pacer {
+    pub(crate) fn new(mut config: ReplayConfig) -> Self {
+        if config.wall_clock {
+            config.speed = 1.0;
+        }
+        Self {
+            shifts: config.symbol_shifts(),
+            jitter: Rng::new(config.jitter_seed),
+            config,
+            start: Instant::now(),
+            base_ns: 0,
+            paused: None,
+            first_ts: None,
+            last_ts: None,
+            skipped_ns: 0,
+            new_day: false,
+            cross_at: None,
+        }
+    }
+
+    /// The next event starts a stitched day, so the gap before it is
+    /// skipped, or shortened to the gap cap.
+    pub(crate) fn start_day(&mut self) {
+        self.new_day = true;
+    }
+
+    /// Offset of `event` from the first one in replay time, after gaps
+    /// were cut.
+    fn offset_ns(&mut self, event: &Event) -> i64 {
+        let ts = replay_time(event, &self.shifts);
+        if self.first_ts.is_none() && self.config.wall_clock && self.paused.is_none() {
+            self.anchor(ts, Timestamp::now());
+        }
+        let baseline = *self.first_ts.get_or_insert(ts);
+        if let Some(last) = self.last_ts {
+            let gap = ts.nanos_since(last).max(0);
+            let cap = match self.config.max_gap_ns {
+                _ if self.config.wall_clock => i64::MAX,
+                Some(cap) => cap.max(0),
+                None if self.new_day => 0,
+                None => i64::MAX,
+            };
+            self.skipped_ns = self.skipped_ns.saturating_add((gap - cap).max(0));
+        }
+        self.new_day = false;
+        self.last_ts = Some(self.last_ts.map_or(ts, |l| l.max(ts)));
+        ts.nanos_since(baseline)
+            .saturating_sub(self.skipped_ns)
+            .max(0)
+    }
+
+    /// Whether events go out in log order, which staggering gives up.
+    pub(crate) fn in_log_order(&self) -> bool {
+        self.shifts.is_empty()
+    }
+
+    pub(crate) async fn wait(&mut self, event: &Event) {
+        let due = self.schedule(event);
+        self.sleep(due).await;
+    }
+
+    /// Offset at which `event` is due, or `None` if it goes out at once.
+    pub(crate) fn schedule(&mut self, event: &Event) -> Option<i64> {
+        let dt = self.offset_ns(event);
+        let in_cross = self.cross_at == Some(event.timestamp_ns);
+        self.cross_at =
+            at_closing_cross(self.config.closing_cross, event).then_some(event.timestamp_ns);
+        let immediate = self.config.max_speed || self.config.step_mode || in_cross;
+        (!immediate).then(|| dt.saturating_add(self.delay_ns()))
+    }
+
+    /// Latency plus a jitter draw, in replay time at the current speed.
+    fn delay_ns(&mut self) -> i64 {
+        let mut wall_ns = self.config.latency_ns.max(0);
+        if self.config.jitter_ns > 0 {
+            let jitter = self.jitter.up_to(self.config.jitter_ns as u64);
+            wall_ns = wall_ns.saturating_add(jitter as i64);
+        }
+        (wall_ns as f64 * self.speed()) as i64
+    }
+
+    /// Whether an event due at `due` may go out without waiting.
+    pub(crate) fn is_due(&self, due: Option<i64>) -> bool {
+        self.paused.is_none() && due.is_none_or(|dt| dt <= self.position_ns())
+    }
 
-        if let Some(to) = to_ns {
-            if record.event.timestamp_ns > to {
-                break;
+    /// Wall-clock time an event due at `due` is already late by.
+    fn lateness_ns(&self, due: Option<i64>) -> i64 {
+        match due {
+            Some(dt) if self.paused.is_none() => {
+                (self.position_ns().saturating_sub(dt).max(0) as f64 / self.speed()) as i64
             }
+            _ => 0,
         }
+    }
 
-        if let Some(from) = from_ns {
-            if record.event.timestamp_ns < from {
-                continue;
+    /// Sleeps until `due`, as given by [`schedule`](Self::schedule), or for
+    /// as long as the replay is paused.
+    pub(crate) async fn sleep(&self, due: Option<i64>) {
+        if self.paused.is_some() {
+            return std::future::pending().await;
+        }
+        match due {
+            Some(dt) => {
+                let ahead = dt.saturating_sub(self.base_ns).max(0) as f64 / self.speed();
+                tokio::time::sleep_until(self.start + Duration::from_nanos(ahead as u64)).await;
             }
+            None if self.config.step_mode => tokio::task::yield_now().await,
+            None => {}
         }
+    }
 
-        out.push(record.event);
+    /// Applies a pause, resume or speed change; seeks are left to the
+    /// caller, which then calls [`restart`](Self::restart).
+    pub(crate) fn apply(&mut self, control: Control) {
+        match control {
+            Control::Pause => {
+                if self.paused.is_none() {
+                    self.paused = Some(self.position_ns());
+                }
+            }
+            Control::Resume => {
+                if let Some(at) = self.paused.take() {
+                    self.rebase(at);
+                }
+            }
+            Control::Speed(speed) if speed > 0.0 => {
+                let at = self.position_ns();
+                self.config.speed = speed;
+                self.config.max_speed = false;
+                if self.paused.is_none() {
+                    self.rebase(at);
+                }
+            }
+            Control::Speed(_)
+            | Control::SeekTime(_)
+            | Control::SeekSequence(_)
+            | Control::Step { .. } => {}
+        }
     }
 
-    out.sort_by_key(|e| e.sequence);
-    Ok(out)
+    /// Times the next event as if it were the first, keeping speed and
+    /// pause state.
+    pub(crate) fn restart(&mut self) {
+        self.first_ts = None;
+        self.last_ts = None;
+        self.skipped_ns = 0;
+        self.new_day = false;
+        self.cross_at = None;
+        match self.paused {
+            Some(_) => self.paused = Some(0),
+            None => self.rebase(0),
+        }
+    }
+
+    /// Sets the clock so that offset 0, the event at `first`, falls on its
+    /// time of day in the UTC day of `now`. Until then the position runs
+    /// negative.
+    fn anchor(&mut self, first: Timestamp, now: Timestamp) {
+        let now_ns = now.as_nanos();
+        let today = now_ns - now_ns.rem_euclid(NANOS_PER_DAY);
+        let due = today.saturating_add(first.as_nanos().rem_euclid(NANOS_PER_DAY));
+        self.rebase(now_ns.saturating_sub(due));
+    }
+
+    fn rebase(&mut self, at: i64) {
+        self.start = Instant::now();
+        self.base_ns = at;
+    }
+
+    /// Offset the replay clock stands at now.
+    fn position_ns(&self) -> i64 {
+        if let Some(at) = self.paused {
+            return at;
+        }
+        let elapsed = self.start.elapsed().as_nanos() as f64 * self.speed();
+        self.base_ns.saturating_add(elapsed as i64)
+    }
+
+    fn speed(&self) -> f64 {
+        if self.config.speed <= 0.0 {
+            1.0
+        } else {
+            self.config.speed
+        }
+    }
+}
+
+/// How far a streamed replay has got, readable while it runs.
+#[derive(Debug, Default)]
+pub struct Progress {
+    events: AtomicU64,
+    last_event_ns: AtomicI64,
+    lag_ns: AtomicI64,
+    queued: AtomicU64,
+    dropped: Mutex<Vec<RangeInclusive<u64>>>,
+    events_dropped: AtomicU64,
+    overflowed: AtomicU64,
+}
+
+impl Progress {
+    /// Events released to the stream so far.
+    pub fn events_sent(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Timestamp of the latest event released.
+    pub fn last_event(&self) -> Option<Timestamp> {
+        (self.events_sent() > 0)
+            .then(|| Timestamp::from_nanos(self.last_event_ns.load(Ordering::Relaxed)))
+    }
+
+    /// How late, in wall-clock ns, the latest event went out after its
+    /// due time; 0 for unpaced streams.
+    pub fn lag_ns(&self) -> i64 {
+        self.lag_ns.load(Ordering::Relaxed)
+    }
+
+    /// Messages the client had yet to read when the latest event was
+    /// released.
+    pub fn queued(&self) -> u64 {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Events lost to simulated drops so far.
+    pub fn events_dropped(&self) -> u64 {
+        self.events_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Messages discarded because the client fell behind a drop-oldest
+    /// stream.
+    pub fn overflowed(&self) -> u64 {
+        self.overflowed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_overflow(&self) {
+        self.overflowed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sequences lost to simulated drops so far, in runs.
+    pub fn dropped(&self) -> Vec<RangeInclusive<u64>> {
+        self.dropped
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn record_drop(&self, sequence: u64) {
+        let mut dropped = self.dropped.lock().unwrap_or_else(|e| e.into_inner());
+        extend_runs(&mut dropped, sequence);
+        self.events_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record<T>(&self, events: usize, last: Timestamp, lag_ns: i64, tx: &mpsc::Sender<T>) {
+        self.last_event_ns.store(last.as_nanos(), Ordering::Relaxed);
+        self.lag_ns.store(lag_ns, Ordering::Relaxed);
+        self.queued.store(
+            (tx.max_capacity() - tx.capacity()) as u64,
+            Ordering::Relaxed,
+        );
+        self.events.fetch_add(events as u64, Ordering::Relaxed);
+    }
 }
 
 pub async fn stream_with_pacing(
@@ -89,31 +1075,245 @@ pub async fn stream_with_pacing(
     config: ReplayConfig,
     tx: mpsc::Sender<Result<pb::EventMessage, Status>>,
 ) {
-    let mut first_ts = None;
-    let start = Instant::now();
+    stream_days(events, Vec::new(), config, 1, tx).await
+}
 
-    for event in events {
-        if !config.max_speed {
-            if config.step_mode {
-                tokio::task::yield_now().await;
-            } else {
-                let baseline = first_ts.get_or_insert(event.timestamp_ns);
-                let dt = event.timestamp_ns.saturating_sub(*baseline);
-                let speed = if config.speed <= 0.0 {
-                    1.0
-                } else {
-                    config.speed
-                };
-                let target = Duration::from_nanos((dt as f64 / speed) as u64);
-                let deadline = start + target;
-                tokio::time::sleep_until(deadline).await;
+/// [`stream_with_pacing`] for stitched days: each day's marker goes out
+/// ahead of its first event, and the gap before it is skipped. Messages
+/// are built `read_ahead` events at a time, ahead of pacing.
+pub async fn stream_days(
+    events: Vec<Event>,
+    days: Vec<DayBoundary>,
+    config: ReplayConfig,
+    read_ahead: usize,
+    tx: mpsc::Sender<Result<pb::EventMessage, Status>>,
+) {
+    let (_, controls) = mpsc::channel(1);
+    stream_controlled(events, days, config, read_ahead, controls, tx).await
+}
+
+/// [`stream_days`] steered by `controls`, which take effect from the next
+/// event on. A seek goes either way, replays from the first event at or
+/// after its target and repeats that day's marker; enrichment starts over
+/// there. Seeking past the last event ends the stream. In step mode each
+/// event waits for a step, until `controls` is closed.
+pub async fn stream_controlled(
+    events: Vec<Event>,
+    days: Vec<DayBoundary>,
+    config: ReplayConfig,
+    read_ahead: usize,
+    controls: mpsc::Receiver<Control>,
+    tx: mpsc::Sender<Result<pb::EventMessage, Status>>,
+) {
+    let progress = Arc::default();
+    let mut events = config.transforms.apply_all(events);
+    stagger(&mut events, &config);
+    stream_tracked(events, days, config, read_ahead, controls, progress, tx).await
+}
+
+/// [`stream_controlled`], recording into `progress` as events go out. The
+/// config's transforms and staggered starts are left to the caller, which
+/// applies them in that order.
+pub async fn stream_tracked(
+    events: Vec<Event>,
+    days: Vec<DayBoundary>,
+    config: ReplayConfig,
+    read_ahead: usize,
+    controls: mpsc::Receiver<Control>,
+    progress: Arc<Progress>,
+    tx: mpsc::Sender<Result<pb::EventMessage, Status>>,
+) {
+    let mut controls = Some(controls);
+    let mut checkpoints = Checkpoints::new(&config);
+    let resume_at = checkpoints.position().min(events.len());
+    let mut messages = ReadAhead::new(&events[resume_at..], &config, read_ahead);
+    let batch = config.batch_closing_cross;
+    let mut pacer = Pacer::new(config.clone());
+    let mut order = OrderCheck::new("stream");
+    let mut next_day = day_at(&days, &events, resume_at);
+    let step_mode = config.step_mode;
+    let mut steps = Steps::default();
+    let mut batcher = Batcher::new(&config);
+    let mut dropper = Dropper::new(&config);
+    dropper.skip(resume_at);
+    let mut throttle = Throttle::new(&config);
+    let mut lag_ns = 0;
+
+    'replay: while let Some(mut group) = messages.next_group() {
+        // Lost events keep their place in the timing, but not a step. The
+        // throttle waits with the last kept event, so controls still apply.
+        let lost = group
+            .items
+            .iter()
+            .map(|_| dropper.drops())
+            .collect::<Vec<_>>();
+        let kept = lost.iter().filter(|lost| !**lost).count();
+        let last_kept = lost.iter().rposition(|lost| !*lost);
+        for (i, (event, _)) in group.items.iter().enumerate() {
+            while let Some(day) = days
+                .get(next_day)
+                .filter(|d| d.first_sequence <= event.sequence)
+            {
+                next_day += 1;
+                pacer.start_day();
+                if !send_held(&mut batcher, &tx).await || tx.send(Ok(day.to_proto())).await.is_err()
+                {
+                    return;
+                }
+            }
+            let due = pacer.schedule(event);
+            if lost[i] {
+                progress.record_drop(event.sequence);
+                continue;
+            }
+            let throttled = (last_kept == Some(i)).then_some(kept);
+            let mut released = !(step_mode && controls.is_some()) || steps.take(event);
+            if !(released && pacer.is_due(due)) {
+                // Held events must not wait behind this one.
+                let sent = send_held(&mut batcher, &tx).await;
+                if !sent {
+                    return;
+                }
+            }
+            loop {
+                tokio::select! {
+                    biased;
+                    control = next_control(&mut controls) => {
+                        let target = match control {
+                            Some(Control::SeekTime(ts)) => {
+                                events.iter().position(|e| e.timestamp_ns >= ts)
+                            }
+                            Some(Control::SeekSequence(seq)) => {
+                                events.iter().position(|e| e.sequence >= seq)
+                            }
+                            Some(Control::Step { count, by_timestamp }) => {
+                                steps.add(count, by_timestamp);
+                                released = released || steps.take(event);
+                                continue;
+                            }
+                            Some(other) => {
+                                pacer.apply(other);
+                                continue;
+                            }
+                            // Nothing can step a closed stream.
+                            None => {
+                                released = true;
+                                continue;
+                            }
+                        };
+                        if !send_held(&mut batcher, &tx).await {
+                            return;
+                        }
+                        let start = target.unwrap_or(events.len());
+                        pacer.restart();
+                        steps.batch = None;
+                        order = OrderCheck::new("stream");
+                        messages = ReadAhead::new(&events[start..], &config, read_ahead);
+                        next_day = day_at(&days, &events, start);
+                        checkpoints.seek(start);
+                        continue 'replay;
+                    }
+                    _ = async {
+                        pacer.sleep(due).await;
+                        if let Some(events) = throttled {
+                            throttle.wait(events).await;
+                        }
+                    }, if released => break,
+                }
+            }
+            lag_ns = pacer.lateness_ns(due);
+            if pacer.in_log_order() {
+                order.observe(event);
             }
         }
+        let checkpoint = checkpoints.pass(&events, group.items.len());
+        let mut lost = lost.into_iter();
+        group.items.retain(|_| !lost.next().unwrap_or(false));
+        if let Some(last_ns) = group.items.last().map(|(e, _)| e.timestamp_ns) {
+            progress.record(group.items.len(), last_ns, lag_ns, &tx);
+            let messages = batcher.add(group, batch).into_iter().map(Ok).collect();
+            if !send_together(&tx, messages).await {
+                return;
+            }
+        }
+        if let Some(checkpoint) = checkpoint {
+            // Held events were released before it.
+            if !send_held(&mut batcher, &tx).await || tx.send(Ok(checkpoint)).await.is_err() {
+                return;
+            }
+        }
+    }
+    send_held(&mut batcher, &tx).await;
+}
 
-        if tx.send(Ok(to_proto(&event))).await.is_err() {
-            break;
+/// Sends what `batcher` holds, if anything. False once the receiver is
+/// gone.
+async fn send_held(
+    batcher: &mut Batcher,
+    tx: &mpsc::Sender<Result<pb::EventMessage, Status>>,
+) -> bool {
+    match batcher.flush() {
+        Some(msg) => tx.send(Ok(msg)).await.is_ok(),
+        None => true,
+    }
+}
+
+/// The next control, `None` once when its sender goes, then never.
+async fn next_control(controls: &mut Option<mpsc::Receiver<Control>>) -> Option<Control> {
+    let Some(rx) = controls else {
+        return std::future::pending().await;
+    };
+    let control = rx.recv().await;
+    if control.is_none() {
+        *controls = None;
+    }
+    control
+}
+
+/// Paced replay of a log into a callback. Returning `ControlFlow::Break`
+/// from `on_event` or cancelling `cancel` ends the replay early.
+pub async fn replay_into<F>(
+    log_path: &Path,
+    index_path: Option<&Path>,
+    config: &ReplayConfig,
+    cancel: &CancellationToken,
+    mut on_event: F,
+) -> Result<ReplayOutcome, ReplayError>
+where
+    F: FnMut(&Event) -> ControlFlow<()>,
+{
+    let mut events = read_transformed(
+        log_path,
+        index_path,
+        config.from_ns,
+        config.to_ns,
+        &config.transforms,
+    )?;
+    stagger(&mut events, config);
+    let mut pacer = Pacer::new(config.clone());
+    let mut throttle = Throttle::new(config);
+    let mut order = OrderCheck::new("replay");
+    let mut delivered = 0u64;
+
+    for event in &events {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Ok(ReplayOutcome::Cancelled { delivered }),
+            _ = async {
+                pacer.wait(event).await;
+                throttle.wait(1).await;
+            } => {}
+        }
+        delivered += 1;
+        if pacer.in_log_order() {
+            order.observe(event);
+        }
+        if on_event(event).is_break() {
+            return Ok(ReplayOutcome::Stopped { delivered });
         }
     }
+
+    Ok(ReplayOutcome::Completed { delivered })
 }
 
 pub fn to_proto(event: &Event) -> pb::EventMessage {
@@ -138,11 +1338,14 @@ pub fn to_proto(event: &Event) -> pb::EventMessage {
     };
 
     pb::EventMessage {
-        timestamp_ns: event.timestamp_ns,
+        timestamp_ns: event.timestamp_ns.as_nanos(),
         sequence: event.sequence,
         venue: event.venue.clone(),
         symbol: event.symbol.clone(),
         payload,
+        features: None,
+        source_sequence: event.source_sequence,
+        channel_id: None,
     }
 }
 
@@ -158,7 +1361,12 @@ pub fn from_proto(msg: &pb::EventMessage) -> Option<Event> {
             ask_px: q.ask_px,
             ask_sz: q.ask_sz,
         },
-        None => return None,
+        Some(pb::event_message::Payload::DayBoundary(_))
+        | Some(pb::event_message::Payload::Checkpoint(_))
+        | Some(pb::event_message::Payload::Stats(_))
+        | Some(pb::event_message::Payload::ClosingCross(_))
+        | Some(pb::event_message::Payload::Batch(_))
+        | None => return None,
     };
 
     let event_type = match &payload {
@@ -167,11 +1375,587 @@ pub fn from_proto(msg: &pb::EventMessage) -> Option<Event> {
     };
 
     Some(Event {
-        timestamp_ns: msg.timestamp_ns,
+        timestamp_ns: Timestamp::from_nanos(msg.timestamp_ns),
         sequence: msg.sequence,
         venue: msg.venue.clone(),
         symbol: msg.symbol.clone(),
         event_type,
         payload,
+        source_sequence: msg.source_sequence,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::testing::Tape;
+    use md_storage::testing::TapeLog;
+    use md_storage::{default_schema_hash, EventLogWriter, IndexWriter};
+    use proptest::prelude::*;
+
+    #[tokio::test]
+    async fn enriched_streams_carry_book_features() {
+        let events = Tape::starting_at(10)
+            .quote("AAPL", "1.00/1.02", 3, 1)
+            .at("+10ns")
+            .trade("AAPL", "1.01", 5)
+            .build();
+        let collect = |enrich| {
+            let events = events.clone();
+            async move {
+                let config = ReplayConfig {
+                    max_speed: true,
+                    enrich,
+                    ..ReplayConfig::default()
+                };
+                let (tx, mut rx) = mpsc::channel(8);
+                stream_with_pacing(events, config, tx).await;
+                let mut features = Vec::new();
+                while let Some(Ok(msg)) = rx.recv().await {
+                    features.push(msg.features);
+                }
+                features
+            }
+        };
+
+        assert_eq!(collect(false).await, [None, None]);
+        let enriched = collect(true).await;
+        let quote = enriched[0].expect("quote features");
+        assert_eq!((quote.mid, quote.spread, quote.imbalance), (101.0, 2, 0.5));
+        // A trade leaves the book as it was.
+        assert_eq!(enriched[1].map(|f| f.spread), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn controls_pause_speed_up_and_seek() {
+        let events = (0..6i64)
+            .map(|i| Event::trade(i * 1_000_000_000, i as u64 + 1, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let (controls, rx_controls) = mpsc::channel(4);
+        let (tx, mut rx) = mpsc::channel(8);
+        tokio::spawn(stream_controlled(
+            events,
+            Vec::new(),
+            ReplayConfig::default(),
+            1,
+            rx_controls,
+            tx,
+        ));
+        // Sequence of the next message and how long it took to arrive.
+        async fn next(
+            rx: &mut mpsc::Receiver<Result<pb::EventMessage, Status>>,
+        ) -> Option<(u64, u128)> {
+            let sent = Instant::now();
+            let msg = rx.recv().await?.expect("event");
+            Some((msg.sequence, sent.elapsed().as_millis()))
+        }
+
+        assert_eq!(next(&mut rx).await, Some((1, 0)));
+        controls.send(Control::Pause).await.expect("pause");
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(rx.try_recv().is_err());
+        controls.send(Control::Resume).await.expect("resume");
+        assert_eq!(next(&mut rx).await, Some((2, 1_000)));
+        controls.send(Control::Speed(4.0)).await.expect("speed");
+        assert_eq!(next(&mut rx).await, Some((3, 250)));
+        controls.send(Control::SeekSequence(1)).await.expect("seek");
+        assert_eq!(next(&mut rx).await, Some((1, 0)));
+        assert_eq!(next(&mut rx).await, Some((2, 250)));
+        controls
+            .send(Control::SeekTime(Timestamp::from_nanos(4_500_000_000)))
+            .await
+            .expect("seek");
+        assert_eq!(next(&mut rx).await, Some((6, 0)));
+        assert_eq!(next(&mut rx).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn event_rate_is_capped_even_at_max_speed() {
+        // Two bursts a quiet hour apart; the cross at 01:00 is one group.
+        let hour = 3_600_000_000_000i64;
+        let events = [0, 0, 0, hour, hour, hour + 1]
+            .into_iter()
+            .zip(1u64..)
+            .map(|(ts, seq)| Event::trade(ts, seq, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let config = ReplayConfig {
+            max_speed: true,
+            closing_cross: Some(hour),
+            max_events_per_sec: Some(10.0),
+            ..ReplayConfig::default()
+        };
+        let (tx, mut rx) = mpsc::channel(8);
+        let start = Instant::now();
+        tokio::spawn(stream_with_pacing(events, config, tx));
+        let mut arrivals = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            arrivals.push((msg.expect("event").sequence, start.elapsed().as_millis()));
+        }
+        // 100ms per event; the cross goes out together and pays for both.
+        assert_eq!(
+            arrivals,
+            [(1, 0), (2, 100), (3, 200), (4, 300), (5, 300), (6, 500)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttled_streams_still_take_controls() {
+        let events = (1..=3u64)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        // Far below the floor, so each event waits 1,000 s for its turn.
+        let config = ReplayConfig {
+            max_speed: true,
+            max_events_per_sec: Some(1e-19),
+            ..ReplayConfig::default()
+        };
+        let (controls, rx_controls) = mpsc::channel(4);
+        let (tx, mut rx) = mpsc::channel(8);
+        let start = Instant::now();
+        tokio::spawn(stream_controlled(
+            events,
+            Vec::new(),
+            config,
+            1,
+            rx_controls,
+            tx,
+        ));
+        let first = rx.recv().await.expect("first").expect("event");
+        assert_eq!(first.sequence, 1);
+        controls
+            .send(Control::SeekSequence(u64::MAX))
+            .await
+            .expect("control");
+        assert!(rx.recv().await.is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn step_mode_waits_for_each_step() {
+        let events = [0, 1, 1, 2]
+            .into_iter()
+            .zip(1u64..)
+            .map(|(ts, seq)| Event::trade(ts, seq, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let config = ReplayConfig {
+            step_mode: true,
+            ..ReplayConfig::default()
+        };
+        let (controls, rx_controls) = mpsc::channel(4);
+        let (tx, mut rx) = mpsc::channel(8);
+        tokio::spawn(stream_controlled(
+            events,
+            Vec::new(),
+            config,
+            1,
+            rx_controls,
+            tx,
+        ));
+        let mut received = |n: usize| {
+            let mut seqs = Vec::new();
+            while let Ok(msg) = rx.try_recv() {
+                seqs.push(msg.expect("event").sequence);
+            }
+            assert_eq!(seqs.len(), n, "{seqs:?}");
+            seqs
+        };
+        let settle = || tokio::time::sleep(Duration::from_secs(1));
+
+        settle().await;
+        assert!(received(0).is_empty());
+        let step = |count, by_timestamp| Control::Step {
+            count,
+            by_timestamp,
+        };
+        controls.send(step(1, false)).await.expect("step");
+        settle().await;
+        assert_eq!(received(1), [1]);
+        controls.send(step(1, true)).await.expect("step");
+        settle().await;
+        assert_eq!(received(2), [2, 3]);
+        // Once the client stops sending, the rest runs out.
+        drop(controls);
+        settle().await;
+        assert_eq!(received(1), [4]);
+    }
+
+    #[tokio::test]
+    async fn replay_into_stops_and_cancels() {
+        let tape = (0..5).fold(Tape::starting_at(10), |tape, _| {
+            tape.trade("AAPL", "1.00", 1).at("+10ns")
+        });
+        let dir = std::env::temp_dir().join(format!("md_replay_engine_{}", std::process::id()));
+        let path = tape.build_log(&dir).expect("log");
+        let config = ReplayConfig {
+            max_speed: true,
+            ..ReplayConfig::default()
+        };
+
+        let mut seen = Vec::new();
+        let outcome = replay_into(&path, None, &config, &CancellationToken::new(), |e| {
+            seen.push(e.sequence);
+            if seen.len() == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .await
+        .expect("replay");
+        assert_eq!(outcome, ReplayOutcome::Stopped { delivered: 2 });
+        assert_eq!(seen, vec![1, 2]);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let outcome = replay_into(&path, None, &config, &cancel, |_| ControlFlow::Continue(()))
+            .await
+            .expect("replay");
+        assert_eq!(outcome, ReplayOutcome::Cancelled { delivered: 0 });
+    }
+
+    #[test]
+    fn index_bounds_the_to_window() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_engine_bound_{}.eventlog",
+            std::process::id()
+        ));
+        let idx_path = md_storage::index_path_for(&path);
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        let mut idx = IndexWriter::create(&idx_path, 1).expect("index");
+        let mut offsets = Vec::new();
+        for i in 1..=6u64 {
+            let event = Event::trade(i as i64, i, "X", "AAPL", 100, 1);
+            let offset = writer.append(&event).expect("append");
+            idx.maybe_add(&event, offset).expect("index add");
+            offsets.push(offset);
+        }
+        writer.flush().expect("flush");
+        idx.flush().expect("index flush");
+
+        // Damage the body of the first record past the window. The index
+        // stops the read before it; without one, its intact timestamp still
+        // ends the read before it is decoded.
+        let mut bytes = std::fs::read(&path).expect("read");
+        bytes[offsets[4] as usize - 1] ^= 0xff;
+        std::fs::write(&path, bytes).expect("write");
+
+        for index in [Some(idx_path.as_path()), None] {
+            let events = read_events(&path, index, None, Some(Timestamp::from_nanos(3)))
+                .expect("bounded read");
+            assert_eq!(
+                events.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+                vec![1, 2, 3]
+            );
+        }
+        assert!(read_events(&path, None, None, None).is_err());
+    }
+
+    #[test]
+    fn reads_are_windowed_on_stored_times_then_transformed() {
+        use crate::transform::ShiftTimestamps;
+
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_engine_transformed_{}.eventlog",
+            std::process::id()
+        ));
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        for i in 1..=4u64 {
+            writer
+                .append(&Event::trade(i as i64 * 10, i, "X", "AAPL", 100, 1))
+                .expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let mut transforms = Transforms::new();
+        transforms.push(ShiftTimestamps { ns: 1_000 });
+        let events = read_transformed(
+            &path,
+            None,
+            Some(Timestamp::from_nanos(20)),
+            Some(Timestamp::from_nanos(30)),
+            &transforms,
+        )
+        .expect("read");
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.sequence, e.timestamp_ns.as_nanos()))
+                .collect::<Vec<_>>(),
+            vec![(2, 1_020), (3, 1_030)]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn pacer_caps_gaps_and_skips_day_breaks() {
+        let trade = |ts: i64| Event::trade(ts, 1, "X", "AAPL", 100, 1);
+        let offsets = |config: ReplayConfig| {
+            let mut pacer = Pacer::new(config);
+            let mut out = Vec::new();
+            for (ts, new_day) in [
+                (0, false),
+                (10, false),
+                (1_000, false),
+                (5_000, true),
+                (5_010, false),
+            ] {
+                if new_day {
+                    pacer.start_day();
+                }
+                out.push(pacer.offset_ns(&trade(ts)));
+            }
+            out
+        };
+        assert_eq!(
+            offsets(ReplayConfig::default()),
+            vec![0, 10, 1_000, 1_000, 1_010]
+        );
+        let capped = ReplayConfig {
+            max_gap_ns: Some(100),
+            ..ReplayConfig::default()
+        };
+        assert_eq!(offsets(capped), vec![0, 10, 110, 210, 220]);
+    }
+
+    #[test]
+    fn wall_clock_pacing_lines_events_up_with_their_time_of_day() {
+        let trade = |ts: i64| Event::trade(ts, 1, "X", "AAPL", 100, 1);
+        // 14:30 UTC on 2 January 2024, and a minute before that time of
+        // day some days later.
+        let open = 1_704_205_800_000_000_000i64;
+        let now = Timestamp::from_nanos(open + 3 * NANOS_PER_DAY - 60_000_000_000);
+        let mut pacer = Pacer::new(ReplayConfig {
+            wall_clock: true,
+            speed: 10.0,
+            max_gap_ns: Some(1_000),
+            ..ReplayConfig::default()
+        });
+        assert_eq!(pacer.schedule(&trade(open)), Some(0));
+        pacer.anchor(Timestamp::from_nanos(open), now);
+        let position = pacer.position_ns();
+        assert!((-60_000_000_000..-59_000_000_000).contains(&position));
+        assert!(!pacer.is_due(Some(0)));
+
+        // Speed is ignored, and neither the gap cap nor a new day cuts the
+        // night out of the schedule.
+        assert_eq!(pacer.schedule(&trade(open + 5_000)), Some(5_000));
+        pacer.start_day();
+        assert_eq!(
+            pacer.schedule(&trade(open + NANOS_PER_DAY)),
+            Some(NANOS_PER_DAY)
+        );
+        assert_eq!(pacer.speed(), 1.0);
+    }
+
+    #[test]
+    fn pacer_delays_paced_events_by_latency_and_seeded_jitter() {
+        let trade = |ts: i64| Event::trade(ts, 1, "X", "AAPL", 100, 1);
+        let dues = |config: ReplayConfig| {
+            let mut pacer = Pacer::new(config);
+            [0, 1_000, 2_000]
+                .map(|ts| pacer.schedule(&trade(ts)))
+                .to_vec()
+        };
+        let late = ReplayConfig {
+            latency_ns: 500,
+            speed: 2.0,
+            ..ReplayConfig::default()
+        };
+        // Wall-clock latency is twice as long in replay time at 2x.
+        assert_eq!(dues(late), vec![Some(1_000), Some(2_000), Some(3_000)]);
+
+        let jittery = ReplayConfig {
+            jitter_ns: 100,
+            jitter_seed: 7,
+            ..ReplayConfig::default()
+        };
+        let first = dues(jittery.clone());
+        assert_eq!(first, dues(jittery.clone()));
+        assert_ne!(
+            first,
+            dues(ReplayConfig {
+                jitter_seed: 8,
+                ..jittery.clone()
+            })
+        );
+        for (due, ts) in first.iter().zip([0, 1_000, 2_000]) {
+            let due = due.expect("paced");
+            assert!((ts..=ts + 100).contains(&due), "{due} outside {ts}+100");
+        }
+        let unpaced = ReplayConfig {
+            max_speed: true,
+            ..jittery
+        };
+        assert_eq!(dues(unpaced), vec![None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn drops_are_seeded_bursty_and_reported() {
+        let events = (0..200i64)
+            .map(|i| Event::trade(i, i as u64 + 1, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let config = ReplayConfig {
+            max_speed: true,
+            drop_rate: 0.1,
+            drop_burst: 3,
+            drop_seed: 11,
+            ..ReplayConfig::default()
+        };
+        let run = |config: ReplayConfig| {
+            let events = events.clone();
+            async move {
+                let progress = Arc::new(Progress::default());
+                let (tx, mut rx) = mpsc::channel(256);
+                let (_controls, controls) = mpsc::channel(1);
+                stream_tracked(
+                    events,
+                    Vec::new(),
+                    config,
+                    1,
+                    controls,
+                    progress.clone(),
+                    tx,
+                )
+                .await;
+                let mut sent = Vec::new();
+                while let Ok(msg) = rx.try_recv() {
+                    sent.push(msg.expect("event").sequence);
+                }
+                (sent, progress)
+            }
+        };
+        let (sent, progress) = run(config.clone()).await;
+        let runs = progress.dropped();
+        let lost = runs.iter().flat_map(|run| run.clone()).collect::<Vec<_>>();
+        assert!(!lost.is_empty());
+        assert_eq!(progress.events_dropped(), lost.len() as u64);
+        assert_eq!(progress.events_sent(), sent.len() as u64);
+        // A burst runs on into the next one, so runs are at least 3 long
+        // except where the log ends.
+        assert!(runs
+            .iter()
+            .all(|run| run.clone().count() >= 3 || *run.end() == 200));
+        let mut all = sent.iter().chain(&lost).copied().collect::<Vec<_>>();
+        all.sort_unstable();
+        assert_eq!(all, (1..=200).collect::<Vec<_>>());
+
+        let (again, _) = run(config.clone()).await;
+        assert_eq!(again, sent);
+        let (reseeded, _) = run(ReplayConfig {
+            drop_seed: 12,
+            ..config
+        })
+        .await;
+        assert_ne!(reseeded, sent);
+    }
+
+    #[test]
+    fn staggered_symbols_start_together() {
+        let events = vec![
+            Event::trade(100, 1, "X", "AAPL", 10, 1),
+            Event::trade(150, 2, "X", "SPY", 30, 1),
+            Event::trade(200, 3, "X", "MSFT", 20, 1),
+            Event::trade(250, 4, "X", "AAPL", 11, 1),
+            Event::trade(300, 5, "X", "MSFT", 21, 1),
+            Event::trade(320, 6, "X", "MSFT", 22, 1),
+        ];
+        let config = ReplayConfig {
+            symbol_starts: BTreeMap::from([
+                (String::from("AAPL"), Timestamp::from_nanos(100)),
+                (String::from("MSFT"), Timestamp::from_nanos(300)),
+            ]),
+            ..ReplayConfig::default()
+        };
+        let mut staggered = events.clone();
+        stagger(&mut staggered, &config);
+        let mut pacer = Pacer::new(config);
+        let paced = staggered
+            .iter()
+            .map(|e| (e.sequence, pacer.offset_ns(e)))
+            .collect::<Vec<_>>();
+        // MSFT's 300 lines up with AAPL's 100; its earlier trade is dropped.
+        assert_eq!(paced, vec![(1, 0), (5, 0), (6, 20), (2, 50), (4, 150)]);
+    }
+
+    /// Drains `rx`, stalling for `delays[i % len]` yields (or a short sleep
+    /// for 3) before message `i`, and returns the sequences received.
+    async fn slow_consumer(
+        mut rx: mpsc::Receiver<Result<pb::EventMessage, Status>>,
+        delays: Vec<u8>,
+    ) -> Vec<(u64, i64)> {
+        let mut seen = Vec::new();
+        while let Some(Ok(msg)) = rx.recv().await {
+            match delays[seen.len() % delays.len()] {
+                3 => tokio::time::sleep(Duration::from_micros(50)).await,
+                n => {
+                    for _ in 0..n {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            }
+            seen.push((msg.sequence, msg.timestamp_ns));
+        }
+        seen
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(24))]
+
+        #[test]
+        fn concurrent_streams_stay_ordered_for_slow_consumers(
+            gaps in prop::collection::vec(0i64..1_000, 1..300),
+            delays in prop::collection::vec(0u8..4, 1..16),
+            streams in 1usize..6,
+            capacity in 1usize..8,
+        ) {
+            let mut ts = 0;
+            let events = gaps
+                .iter()
+                .enumerate()
+                .map(|(i, gap)| {
+                    ts += gap;
+                    Event::trade(ts, i as u64 + 1, "X", "AAPL", 100, 1)
+                })
+                .collect::<Vec<_>>();
+            let expected = events
+                .iter()
+                .map(|e| (e.sequence, e.timestamp_ns.as_nanos()))
+                .collect::<Vec<_>>();
+            let config = ReplayConfig {
+                max_speed: true,
+                ..ReplayConfig::default()
+            };
+
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(4)
+                .enable_time()
+                .build()
+                .expect("runtime");
+            let received = runtime.block_on(async {
+                let mut consumers = Vec::new();
+                for stream in 0..streams {
+                    let (tx, rx) = mpsc::channel(capacity);
+                    tokio::spawn(stream_with_pacing(events.clone(), config.clone(), tx));
+                    let mut delays = delays.clone();
+                    let shift = stream % delays.len();
+                    delays.rotate_left(shift);
+                    consumers.push(tokio::spawn(slow_consumer(rx, delays)));
+                }
+                let mut out = Vec::new();
+                for consumer in consumers {
+                    out.push(consumer.await.expect("consumer"));
+                }
+                out
+            });
+            for seen in received {
+                prop_assert_eq!(&seen, &expected);
+            }
+        }
+    }
+}
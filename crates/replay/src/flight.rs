@@ -0,0 +1,369 @@
+//! Arrow Flight over the served log. `DoGet` returns a symbol and time
+//! window as record batches, at once and unpaced, in the columns
+//! `md-replay export` writes.
+
+use crate::engine::ReplayError;
+use crate::pb::flight::flight_descriptor::DescriptorType;
+use crate::pb::flight::flight_service_server::FlightService;
+use crate::pb::flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaResult, Ticket,
+};
+use crate::source::LogSource;
+use crate::WasmPlugin;
+use arrow::datatypes::Schema;
+use arrow::ipc::writer::{
+    write_message, DictionaryTracker, EncodedData, IpcDataGenerator, IpcWriteOptions,
+};
+use futures::TryStreamExt;
+use md_core::{Event, Timestamp};
+use md_export::{event_schema, events_to_arrow, ExportError};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status, Streaming};
+
+/// Rows per record batch, small enough for the 4 MiB message limit most
+/// gRPC clients decode by default.
+const BATCH_ROWS: usize = 8192;
+
+type FlightStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// What a ticket or `CMD` descriptor selects, as JSON such as
+/// `{"symbols":["AAPL"],"from_ns":0,"to_ns":1}`. Every field is optional
+/// and an empty ticket selects the whole log.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct FlightQuery {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_ns: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_ns: Option<i64>,
+}
+
+impl FlightQuery {
+    fn parse(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Self::default());
+        }
+        serde_json::from_slice(bytes)
+    }
+
+    /// A `CMD` descriptor holds the query; a `PATH` descriptor lists
+    /// symbols.
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self, serde_json::Error> {
+        match descriptor.r#type() {
+            DescriptorType::Path => Ok(Self {
+                symbols: descriptor.path.clone(),
+                ..Self::default()
+            }),
+            _ => Self::parse(&descriptor.cmd),
+        }
+    }
+
+    fn is_everything(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct FlightSvc {
+    pub source: LogSource,
+    pub plugin: Option<WasmPlugin>,
+    /// Most rows one `DoGet` returns.
+    pub max_events: Option<u64>,
+}
+
+impl FlightSvc {
+    fn events(&self, query: &FlightQuery) -> Result<Vec<Event>, ReplayError> {
+        let mut events = self
+            .source
+            .read(
+                query.from_ns.map(Timestamp::from_nanos),
+                query.to_ns.map(Timestamp::from_nanos),
+            )?
+            .events;
+        if !query.symbols.is_empty() {
+            events.retain(|e| query.symbols.contains(&e.symbol));
+        }
+        if let Some(plugin) = &self.plugin {
+            events = plugin.apply(events)?;
+        }
+        if let Some(max) = self.max_events {
+            events.truncate(usize::try_from(max).unwrap_or(usize::MAX));
+        }
+        Ok(events)
+    }
+
+    fn info(&self, query: FlightQuery) -> Result<FlightInfo, ReplayError> {
+        // Only the whole log has a count without reading it.
+        let total_records = if query.is_everything() && self.plugin.is_none() {
+            let count = self.source.info()?.summary.record_count;
+            let count = self.max_events.map_or(count, |max| count.min(max));
+            i64::try_from(count).unwrap_or(i64::MAX)
+        } else {
+            -1
+        };
+        let cmd = serde_json::to_vec(&query).unwrap_or_default();
+        Ok(FlightInfo {
+            schema: encapsulated_schema(&event_schema()),
+            flight_descriptor: Some(FlightDescriptor {
+                r#type: DescriptorType::Cmd.into(),
+                cmd: cmd.clone(),
+                path: Vec::new(),
+            }),
+            endpoint: vec![FlightEndpoint {
+                ticket: Some(Ticket { ticket: cmd }),
+                location: Vec::new(),
+                app_metadata: Vec::new(),
+            }],
+            total_records,
+            total_bytes: -1,
+            ordered: true,
+            app_metadata: Vec::new(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for FlightSvc {
+    type HandshakeStream = FlightStream<HandshakeResponse>;
+    type ListFlightsStream = FlightStream<FlightInfo>;
+    type DoGetStream = FlightStream<FlightData>;
+    type DoPutStream = FlightStream<PutResult>;
+    type DoExchangeStream = FlightStream<FlightData>;
+    type DoActionStream = FlightStream<crate::pb::flight::Result>;
+    type ListActionsStream = FlightStream<ActionType>;
+
+    /// Callers authenticate with a bearer token on every call instead.
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("send a bearer token with each call"))
+    }
+
+    /// One flight: the whole log.
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let info = self
+            .info(FlightQuery::default())
+            .map_err(|e| e.to_status())?;
+        Ok(Response::new(Box::pin(tokio_stream::once(Ok(info)))))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let query = FlightQuery::from_descriptor(request.get_ref()).map_err(invalid_query)?;
+        let info = self.info(query).map_err(|e| e.to_status())?;
+        Ok(Response::new(info))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Ok(Response::new(SchemaResult {
+            schema: encapsulated_schema(&event_schema()),
+        }))
+    }
+
+    /// The schema, then the selected events in batches of up to
+    /// [`BATCH_ROWS`].
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let query = FlightQuery::parse(&request.get_ref().ticket).map_err(invalid_query)?;
+        if query
+            .from_ns
+            .zip(query.to_ns)
+            .is_some_and(|(from, to)| to < from)
+        {
+            return Err(Status::invalid_argument("to_ns is before from_ns"));
+        }
+        let events = self.events(&query).map_err(|e| e.to_status())?;
+        let options = IpcWriteOptions::default();
+        let generator = IpcDataGenerator::default();
+        let mut tracker = DictionaryTracker::new(false);
+        let header = generator.schema_to_bytes_with_dictionary_tracker(
+            &event_schema(),
+            &mut tracker,
+            &options,
+        );
+        let batches = (0..events.len()).step_by(BATCH_ROWS).map(move |start| {
+            let end = (start + BATCH_ROWS).min(events.len());
+            let batch = events_to_arrow(&events[start..end])?;
+            let (_, encoded) = generator.encoded_batch(&batch, &mut tracker, &options)?;
+            Ok(flight_data(encoded))
+        });
+        let stream = futures::stream::iter(std::iter::once(Ok(flight_data(header))).chain(batches))
+            .map_err(internal);
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("the served log is read-only"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("the served log is read-only"))
+    }
+
+    async fn do_action(
+        &self,
+        request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented(format!(
+            "no action {}",
+            request.get_ref().r#type
+        )))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(Box::pin(tokio_stream::empty())))
+    }
+}
+
+fn invalid_query(err: serde_json::Error) -> Status {
+    Status::invalid_argument(format!("invalid flight query: {err}"))
+}
+
+fn internal(err: ExportError) -> Status {
+    Status::internal(err.to_string())
+}
+
+fn flight_data(encoded: EncodedData) -> FlightData {
+    FlightData {
+        flight_descriptor: None,
+        data_header: encoded.ipc_message,
+        app_metadata: Vec::new(),
+        data_body: encoded.arrow_data,
+    }
+}
+
+/// `schema` as an IPC message with its continuation marker and length,
+/// the form `FlightInfo` and `SchemaResult` carry.
+fn encapsulated_schema(schema: &Schema) -> Vec<u8> {
+    let options = IpcWriteOptions::default();
+    let mut tracker = DictionaryTracker::new(false);
+    let encoded = IpcDataGenerator::default().schema_to_bytes_with_dictionary_tracker(
+        schema,
+        &mut tracker,
+        &options,
+    );
+    let mut out = Vec::new();
+    // Writing into a Vec cannot fail.
+    let _ = write_message(&mut out, encoded, &options);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, RecordBatch};
+    use arrow::datatypes::{Int64Type, UInt64Type};
+    use arrow::ipc::reader::StreamReader;
+    use std::sync::Arc;
+    use tokio_stream::StreamExt;
+
+    fn service() -> FlightSvc {
+        FlightSvc {
+            source: LogSource::Events(Arc::from(vec![
+                Event::trade(100, 1, "X", "AAPL", 10, 1),
+                Event::trade(200, 2, "X", "MSFT", 20, 1),
+                Event::trade(300, 3, "X", "AAPL", 11, 2),
+                Event::trade(400, 4, "X", "AAPL", 12, 3),
+            ])),
+            plugin: None,
+            max_events: None,
+        }
+    }
+
+    /// Reassembles a `DoGet` reply as an IPC stream and reads it back.
+    async fn batches(svc: &FlightSvc, ticket: &str) -> Vec<RecordBatch> {
+        let request = Request::new(Ticket {
+            ticket: ticket.as_bytes().to_vec(),
+        });
+        let mut stream = svc.do_get(request).await.expect("do_get").into_inner();
+        let mut ipc = Vec::new();
+        let options = IpcWriteOptions::default();
+        while let Some(data) = stream.next().await {
+            let data = data.expect("data");
+            let encoded = EncodedData {
+                ipc_message: data.data_header,
+                arrow_data: data.data_body,
+            };
+            write_message(&mut ipc, encoded, &options).expect("write");
+        }
+        let reader = StreamReader::try_new(ipc.as_slice(), None).expect("reader");
+        reader.map(|batch| batch.expect("batch")).collect()
+    }
+
+    #[tokio::test]
+    async fn do_get_returns_the_selected_window_as_batches() {
+        let svc = service();
+        let all = batches(&svc, "").await;
+        assert_eq!(all.iter().map(RecordBatch::num_rows).sum::<usize>(), 4);
+        assert_eq!(all[0].schema(), event_schema());
+
+        let window = batches(&svc, r#"{"symbols":["AAPL"],"from_ns":150,"to_ns":400}"#).await;
+        let sequences = window[0].column(1).as_primitive::<UInt64Type>();
+        assert_eq!(sequences.values(), &[3, 4]);
+        let timestamps = window[0].column(0).as_primitive::<Int64Type>();
+        assert_eq!(timestamps.values(), &[300, 400]);
+
+        let err = svc
+            .do_get(Request::new(Ticket {
+                ticket: b"{\"symbol\":\"AAPL\"}".to_vec(),
+            }))
+            .await
+            .err()
+            .expect("unknown field");
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn flight_info_points_back_at_its_query() {
+        let svc = service();
+        let descriptor = FlightDescriptor {
+            r#type: DescriptorType::Path.into(),
+            cmd: Vec::new(),
+            path: vec![String::from("MSFT")],
+        };
+        let info = svc
+            .get_flight_info(Request::new(descriptor))
+            .await
+            .expect("info")
+            .into_inner();
+        let ticket = info.endpoint[0].ticket.clone().expect("ticket");
+        assert_eq!(ticket.ticket, br#"{"symbols":["MSFT"]}"#);
+        assert_eq!(info.total_records, -1);
+        let rows = batches(&svc, std::str::from_utf8(&ticket.ticket).expect("utf8")).await;
+        assert_eq!(rows[0].num_rows(), 1);
+
+        let mut flights = svc
+            .list_flights(Request::new(Criteria::default()))
+            .await
+            .expect("list")
+            .into_inner();
+        let whole = flights.next().await.expect("one flight").expect("info");
+        assert_eq!(whole.total_records, 4);
+        assert_eq!(&whole.schema[..4], &[0xFF; 4]);
+    }
+}
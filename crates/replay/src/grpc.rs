@@ -1,18 +1,283 @@
-use crate::engine::{read_events, stream_with_pacing, ReplayConfig, ReplayError};
+use crate::audit::{AuditCall, AuditContext, AuditControl, AuditLog};
+use crate::engine::{
+    day_at, extend_runs, send_together, stagger, stream_tracked, to_proto, to_ranges, Batcher,
+    Checkpoints, Control, DayBoundary, Dropper, Pacer, Progress, ReadAhead, ReplayConfig,
+    ReplayError, ResumeToken, Throttle, MIN_EVENTS_PER_SEC,
+};
+use crate::flight::FlightSvc;
+use crate::pb::flight::flight_service_server::{self, FlightServiceServer};
 use crate::pb::replay_service_server::{ReplayService, ReplayServiceServer};
-use crate::pb::{self, StreamRequest};
+use crate::pb::{
+    self, CloseSessionRequest, ControlCommand, CreateSessionRequest, FeedLine, FeedLineRequest,
+    GetLogInfoRequest, GetRangeRequest, ListSessionsRequest, StreamRequest, SubscriptionCommand,
+};
+use crate::source::{LogInfo, LogSource, SourceEvents};
+use crate::tls::{self, TlsIdentity};
+use crate::WasmPlugin;
+use md_core::{Event, OrderCheck, Timestamp};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::future::Future;
 use std::net::SocketAddr;
-use std::path::PathBuf;
-use tokio::sync::mpsc;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::MetadataMap;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
 use tonic::transport::Server;
-use tonic::{Request, Response, Status};
+use tonic::{Request, Response, Status, Streaming};
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+const ANONYMOUS_TOKEN: &str = "";
+/// Most events one `GetRange` call returns.
+const MAX_RANGE_EVENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionLimits {
+    pub max_events: Option<u64>,
+    pub max_duration: Option<Duration>,
+    pub max_sessions_per_token: Option<usize>,
+}
+
+/// How the server is protected once it listens beyond localhost. With
+/// `tls` unset it speaks plaintext; with `tokens` empty any caller is let
+/// in, as before, and bearer tokens only key the per-token limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerSecurity {
+    pub tls: Option<TlsIdentity>,
+    /// Bearer tokens a caller must present one of.
+    pub tokens: Vec<String>,
+}
+
+/// Turns away calls without one of the configured bearer tokens before
+/// they reach the service.
+#[derive(Debug, Clone)]
+struct BearerAuth {
+    tokens: Arc<[String]>,
+}
+
+impl Interceptor for BearerAuth {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.tokens.is_empty() {
+            return Ok(request);
+        }
+        let presented = request
+            .metadata()
+            .contains_key("authorization")
+            .then(|| session_token(request.metadata()));
+        match presented {
+            Some(token) if self.tokens.iter().any(|t| same_token(t, &token)) => Ok(request),
+            Some(_) => Err(Status::unauthenticated("invalid bearer token")),
+            None => Err(Status::unauthenticated("missing bearer token")),
+        }
+    }
+}
+
+/// Compares every byte whatever the first mismatch, so response timing
+/// gives no hint of how much of a guess was right.
+fn same_token(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Where a session's pacing loop runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpawnStrategy {
+    /// A task on the server's runtime, sharing its workers and timer.
+    #[default]
+    Task,
+    /// A thread of its own with a single-threaded runtime, so other
+    /// sessions and the transport never delay its wakeups.
+    Thread,
+}
+
+impl std::str::FromStr for SpawnStrategy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "task" => Ok(Self::Task),
+            "thread" => Ok(Self::Thread),
+            other => Err(format!(
+                "unsupported spawn strategy {other}, expected task|thread"
+            )),
+        }
+    }
+}
+
+/// What a session's stream does once its channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Pacing waits until the client reads.
+    #[default]
+    Block,
+    /// Pacing goes on and the oldest waiting message is discarded.
+    DropOldest,
+}
+
+impl std::str::FromStr for Backpressure {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            other => Err(format!(
+                "unsupported backpressure {other}, expected block|drop-oldest"
+            )),
+        }
+    }
+}
+
+/// Wire compression of gRPC messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn encoding(self) -> CompressionEncoding {
+        match self {
+            Self::Gzip => CompressionEncoding::Gzip,
+            Self::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(format!(
+                "unsupported compression {other}, expected gzip|zstd"
+            )),
+        }
+    }
+}
+
+/// How sessions are streamed. `channel_depth` is how many messages may
+/// wait for a slow client before `backpressure` applies, and `read_ahead`
+/// how many are built at once ahead of their send time. Requests may set
+/// their own depth and backpressure; multiplexed streams always block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTuning {
+    pub channel_depth: usize,
+    pub spawn: SpawnStrategy,
+    pub backpressure: Backpressure,
+    pub read_ahead: usize,
+    /// Compresses responses to clients that accept it. Compressed requests
+    /// are accepted either way.
+    pub compression: Option<Compression>,
+    /// Largest message sent or accepted, in bytes; tonic's 4 MiB
+    /// decoding limit when unset.
+    pub max_message_bytes: Option<usize>,
+}
+
+impl Default for StreamTuning {
+    fn default() -> Self {
+        Self {
+            channel_depth: 1024,
+            spawn: SpawnStrategy::Task,
+            backpressure: Backpressure::Block,
+            read_ahead: 1,
+            compression: None,
+            max_message_bytes: None,
+        }
+    }
+}
+
+/// Largest channel depth a request may ask for.
+const MAX_REQUEST_BUFFER: usize = 65_536;
+
+impl StreamTuning {
+    fn channel<T>(&self) -> (mpsc::Sender<T>, mpsc::Receiver<T>) {
+        mpsc::channel(self.channel_depth.max(1))
+    }
+
+    /// The server's tuning with `req`'s depth and backpressure, if set.
+    fn for_request(mut self, req: &StreamRequest) -> Self {
+        if req.buffer > 0 {
+            self.channel_depth = (req.buffer as usize).min(MAX_REQUEST_BUFFER);
+        }
+        match pb::Backpressure::try_from(req.backpressure) {
+            Ok(pb::Backpressure::Block) => self.backpressure = Backpressure::Block,
+            Ok(pb::Backpressure::DropOldest) => self.backpressure = Backpressure::DropOldest,
+            Ok(pb::Backpressure::Server) | Err(_) => {}
+        }
+        self
+    }
+
+    /// A session's channel under its backpressure. Dropping oldest runs a
+    /// relay that holds up to `channel_depth` messages and counts discards
+    /// into `progress`, so the sender never waits on the client.
+    fn session_channel(
+        &self,
+        progress: Arc<Progress>,
+    ) -> (mpsc::Sender<StreamItem>, mpsc::Receiver<StreamItem>) {
+        if self.backpressure == Backpressure::Block {
+            return self.channel();
+        }
+        let (tx, relayed) = self.channel();
+        let (client, rx) = mpsc::channel(1);
+        tokio::spawn(relay_dropping_oldest(
+            relayed,
+            client,
+            self.channel_depth.max(1),
+            progress,
+        ));
+        (tx, rx)
+    }
+
+    fn spawn(&self, session: impl Future<Output = ()> + Send + 'static) {
+        match self.spawn {
+            SpawnStrategy::Task => {
+                tokio::spawn(session);
+            }
+            SpawnStrategy::Thread => {
+                let spawned = std::thread::Builder::new()
+                    .name(String::from("md-replay-pacer"))
+                    .spawn(move || {
+                        match tokio::runtime::Builder::new_current_thread()
+                            .enable_time()
+                            .build()
+                        {
+                            Ok(runtime) => runtime.block_on(session),
+                            Err(err) => warn!(error = %err, "pacing runtime failed to start"),
+                        }
+                    });
+                if let Err(err) = spawned {
+                    warn!(error = %err, "pacing thread failed to start");
+                }
+            }
+        }
+    }
+}
 
 #[derive(Clone)]
 struct ServiceState {
-    log_path: PathBuf,
-    index_path: Option<PathBuf>,
+    source: LogSource,
     defaults: ReplayConfig,
+    limits: SessionLimits,
+    tuning: StreamTuning,
+    plugin: Option<WasmPlugin>,
+    audit: Option<AuditLog>,
+    sessions: SessionTable,
 }
 
 #[derive(Clone)]
@@ -20,64 +285,1062 @@ struct ReplaySvc {
     state: ServiceState,
 }
 
+#[derive(Clone, Default)]
+struct SessionTable {
+    active: Arc<Mutex<HashMap<String, usize>>>,
+    /// Sessions opened with `CreateSession`, by name.
+    named: Arc<Mutex<BTreeMap<String, NamedSession>>>,
+    /// Feed lines waiting for the other line of their pair, by pair.
+    feeds: Arc<Mutex<HashMap<String, WaitingLine>>>,
+}
+
+/// The first line of an A/B pair, until its partner connects.
+struct WaitingLine {
+    line: FeedLine,
+    /// Its request without the per-line fields.
+    request: StreamRequest,
+    start: oneshot::Sender<()>,
+}
+
+struct SessionSlot {
+    table: SessionTable,
+    token: String,
+    /// Name the session is listed under, until the slot is dropped.
+    name: Option<String>,
+}
+
+/// Where a session's stream reports its progress, and what closes it.
+#[derive(Clone, Default)]
+struct Tracking {
+    progress: Arc<Progress>,
+    close: CancellationToken,
+}
+
+struct NamedSession {
+    token: String,
+    request: StreamRequest,
+    created: Timestamp,
+    tracking: Tracking,
+}
+
+impl NamedSession {
+    fn info(&self, name: &str) -> pb::SessionInfo {
+        let progress = &self.tracking.progress;
+        pb::SessionInfo {
+            name: name.to_string(),
+            request: Some(self.request.clone()),
+            created_ns: self.created.as_nanos(),
+            events_sent: progress.events_sent(),
+            last_event_ns: progress.last_event().map(|ts| ts.as_nanos()),
+            lag_ns: progress.lag_ns(),
+            queued: u32::try_from(progress.queued()).unwrap_or(u32::MAX),
+            events_dropped: progress.events_dropped(),
+            dropped: to_ranges(&progress.dropped()),
+            overflowed: progress.overflowed(),
+        }
+    }
+}
+
+impl From<LogInfo> for pb::LogInfo {
+    fn from(info: LogInfo) -> Self {
+        let summary = info.summary;
+        let bounded = |ts: Timestamp| (summary.record_count > 0).then(|| ts.as_nanos());
+        Self {
+            version: u32::from(info.version),
+            schema_hash: info.schema_hash,
+            schema: info.schema.description().to_string(),
+            symbols: info.symbols,
+            record_count: summary.record_count,
+            first_ns: bounded(summary.first_timestamp_ns),
+            last_ns: bounded(summary.last_timestamp_ns),
+            first_sequence: summary.first_sequence,
+            last_sequence: summary.last_sequence,
+            counts: summary
+                .symbols
+                .into_iter()
+                .map(|(symbol, counts)| pb::SymbolCounts {
+                    symbol,
+                    trades: counts.trades,
+                    quotes: counts.quotes,
+                })
+                .collect(),
+            logs: info.logs,
+            price_units: info.price_units.into_iter().collect(),
+        }
+    }
+}
+
+impl SessionTable {
+    fn acquire(&self, token: &str, max: Option<usize>) -> Option<SessionSlot> {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let count = active.entry(token.to_string()).or_insert(0);
+        if max.is_some_and(|max| *count >= max) {
+            return None;
+        }
+        *count += 1;
+        Some(SessionSlot {
+            table: self.clone(),
+            token: token.to_string(),
+            name: None,
+        })
+    }
+
+    fn named(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, NamedSession>> {
+        self.named.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn feeds(&self) -> std::sync::MutexGuard<'_, HashMap<String, WaitingLine>> {
+        self.feeds.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionSlot {
+    /// Lists the slot's session under `name`; false if the name is taken.
+    fn register(&mut self, name: &str, session: NamedSession) -> bool {
+        let mut named = self.table.named();
+        if named.contains_key(name) {
+            return false;
+        }
+        named.insert(name.to_string(), session);
+        self.name = Some(name.to_string());
+        true
+    }
+}
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        let mut active = self.table.active.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = active.get_mut(&self.token) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                active.remove(&self.token);
+            }
+        }
+        drop(active);
+        if let Some(name) = &self.name {
+            self.table.named().remove(name);
+        }
+    }
+}
+
+/// One message of a session's stream.
+type StreamItem = Result<pb::EventMessage, Status>;
+
+/// Passes messages on to `client`, keeping at most `depth` waiting and
+/// discarding the oldest to make room. The running count of discards goes
+/// out as `StreamStats` ahead of the first message after any new ones, and
+/// before the stream ends. Ends once `client` is gone, or once `rx` closes
+/// and what is left has been passed on.
+async fn relay_dropping_oldest(
+    mut rx: mpsc::Receiver<StreamItem>,
+    client: mpsc::Sender<StreamItem>,
+    depth: usize,
+    progress: Arc<Progress>,
+) {
+    let stats = |overflowed| pb::EventMessage {
+        payload: Some(pb::event_message::Payload::Stats(pb::StreamStats {
+            overflowed,
+        })),
+        ..pb::EventMessage::default()
+    };
+    let mut reported = 0;
+    let mut waiting = VecDeque::with_capacity(depth.min(1024));
+    loop {
+        tokio::select! {
+            msg = rx.recv() => {
+                let Some(msg) = msg else {
+                    break;
+                };
+                if waiting.len() >= depth {
+                    waiting.pop_front();
+                    progress.record_overflow();
+                }
+                waiting.push_back(msg);
+            }
+            permit = client.reserve(), if !waiting.is_empty() => {
+                let Ok(permit) = permit else {
+                    return;
+                };
+                let overflowed = progress.overflowed();
+                if overflowed > reported {
+                    reported = overflowed;
+                    permit.send(Ok(stats(overflowed)));
+                } else if let Some(msg) = waiting.pop_front() {
+                    permit.send(msg);
+                }
+            }
+            _ = client.closed() => return,
+        }
+    }
+    let overflowed = progress.overflowed();
+    if overflowed > reported && client.send(Ok(stats(overflowed))).await.is_err() {
+        return;
+    }
+    for msg in waiting {
+        if client.send(msg).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// A session's events, and the stitched days they cover.
+struct Loaded {
+    events: Vec<Event>,
+    days: Vec<DayBoundary>,
+    /// Whether the per-session event cap cut it short.
+    truncated: bool,
+}
+
+impl ServiceState {
+    /// Starts the audit record of one call, when the server keeps a log.
+    fn audit<T>(&self, request: &Request<T>) -> Option<AuditContext> {
+        let log = self.audit.as_ref()?;
+        Some(log.connection(&session_token(request.metadata()), request.remote_addr()))
+    }
+
+    /// Reads the requested window, runs it through the plugin, applies the
+    /// symbol and venue filters, the configured transforms, staggered
+    /// starts and the per-session event cap, numbers the venues if `req`
+    /// asks for channels, and checks where a resumed stream continues.
+    fn load_events(
+        &self,
+        config: &mut ReplayConfig,
+        req: &StreamRequest,
+    ) -> Result<Loaded, ReplayError> {
+        let SourceEvents { mut events, days } = self.source.read(config.from_ns, config.to_ns)?;
+        if let Some(plugin) = &self.plugin {
+            events = plugin.apply(events)?;
+        }
+        let listed = |list: &[String], value: &str| {
+            list.is_empty() || list.iter().any(|s| s.eq_ignore_ascii_case(value))
+        };
+        events.retain(|e| listed(&req.symbols, &e.symbol) && listed(&req.venues, &e.venue));
+        if req.venue_channels {
+            config.venue_channels = if req.venues.is_empty() {
+                let venues = events
+                    .iter()
+                    .map(|e| e.venue.as_str())
+                    .collect::<BTreeSet<_>>();
+                venues.into_iter().map(String::from).collect()
+            } else {
+                req.venues.clone()
+            };
+        }
+        events = config.transforms.apply_all(events);
+        stagger(&mut events, config);
+        let truncated = match self.limits.max_events {
+            Some(max) if events.len() as u64 > max => {
+                events.truncate(max as usize);
+                true
+            }
+            _ => false,
+        };
+        if !req.resume_token.is_empty() {
+            let token = req.resume_token.parse::<ResumeToken>()?;
+            token.check(&events)?;
+            config.resume = Some(token);
+        }
+        Ok(Loaded {
+            events,
+            days,
+            truncated,
+        })
+    }
+
+    fn acquire_session(&self, metadata: &MetadataMap) -> Option<SessionSlot> {
+        let token = session_token(metadata);
+        self.sessions
+            .acquire(&token, self.limits.max_sessions_per_token)
+    }
+
+    /// Paces `loaded` onto a new stream under the session limits and
+    /// `tuning`, steered by `controls` if given, until it ends or
+    /// `tracking` closes it. With a `gate`, pacing starts once the gate
+    /// opens.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_stream(
+        &self,
+        slot: SessionSlot,
+        loaded: Loaded,
+        config: ReplayConfig,
+        tuning: StreamTuning,
+        controls: Option<mpsc::Receiver<Control>>,
+        tracking: Tracking,
+        gate: Option<oneshot::Receiver<()>>,
+    ) -> ReceiverStream<Result<pb::EventMessage, Status>> {
+        let Loaded {
+            events,
+            days,
+            truncated,
+        } = loaded;
+        let (tx, rx) = tuning.session_channel(tracking.progress.clone());
+        let max_duration = self.limits.max_duration;
+        tuning.spawn(async move {
+            let _slot = slot;
+            let Tracking { progress, close } = tracking;
+            if let Some(gate) = gate {
+                tokio::select! {
+                    opened = gate => if opened.is_err() {
+                        return;
+                    },
+                    _ = tx.closed() => return,
+                    _ = close.cancelled() => return,
+                }
+            }
+            let controls = controls.unwrap_or_else(|| mpsc::channel(1).1);
+            let paced = stream_tracked(
+                events,
+                days,
+                config,
+                tuning.read_ahead,
+                controls,
+                progress,
+                tx.clone(),
+            );
+            let finished = async {
+                match max_duration {
+                    Some(limit) => {
+                        let finished = tokio::time::timeout(limit, paced).await.is_ok();
+                        if !finished {
+                            info!(?limit, "session duration limit reached");
+                        }
+                        finished
+                    }
+                    None => {
+                        paced.await;
+                        true
+                    }
+                }
+            };
+            let end = tokio::select! {
+                _ = close.cancelled() => Some(Status::cancelled("session closed")),
+                finished = finished => match finished {
+                    false => Some(Status::deadline_exceeded("session duration limit reached")),
+                    true if truncated => Some(Status::resource_exhausted(
+                        "session event limit reached",
+                    )),
+                    true => None,
+                },
+            };
+            if let Some(status) = end {
+                let _ = tx.send(Err(status)).await;
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
 #[tonic::async_trait]
 impl ReplayService for ReplaySvc {
     type StreamEventsStream = ReceiverStream<Result<pb::EventMessage, Status>>;
+    type MultiplexStream = ReceiverStream<Result<pb::MultiplexMessage, Status>>;
+    type ControlStream = ReceiverStream<Result<pb::EventMessage, Status>>;
+    type CreateSessionStream = ReceiverStream<Result<pb::EventMessage, Status>>;
+    type StreamFeedLineStream = ReceiverStream<Result<pb::EventMessage, Status>>;
 
     async fn stream_events(
         &self,
         request: Request<StreamRequest>,
     ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            audit.record(AuditCall::StreamEvents {
+                request: request.get_ref().into(),
+            });
+        }
+        let slot = self
+            .state
+            .acquire_session(request.metadata())
+            .ok_or_else(too_many_sessions)?;
         let req = request.into_inner();
-        let config = merged_config(&self.state.defaults, &req);
+        let mut config = merged_config(&self.state.defaults, &req);
+        let loaded = self
+            .state
+            .load_events(&mut config, &req)
+            .map_err(|e| e.to_status())?;
+        let tuning = self.state.tuning.for_request(&req);
+        Ok(Response::new(self.state.spawn_stream(
+            slot,
+            loaded,
+            config,
+            tuning,
+            None,
+            Tracking::default(),
+            None,
+        )))
+    }
 
-        let events = read_events(
-            &self.state.log_path,
-            self.state.index_path.as_deref(),
-            config.from_ns,
-            config.to_ns,
-        )
-        .map_err(|e| Status::internal(e.to_string()))?;
+    /// A `StreamEvents` replay that `ListSessions` reports on and
+    /// `CloseSession` can end.
+    async fn create_session(
+        &self,
+        request: Request<CreateSessionRequest>,
+    ) -> Result<Response<Self::CreateSessionStream>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            let req = request.get_ref();
+            audit.record(AuditCall::CreateSession {
+                name: req.name.clone(),
+                request: req.request.as_ref().map(Into::into).unwrap_or_default(),
+            });
+        }
+        let token = session_token(request.metadata());
+        let mut slot = self
+            .state
+            .acquire_session(request.metadata())
+            .ok_or_else(too_many_sessions)?;
+        let CreateSessionRequest { name, request: req } = request.into_inner();
+        if name.is_empty() {
+            return Err(Status::invalid_argument("a session needs a name"));
+        }
+        let req = req.unwrap_or_default();
+        let mut config = merged_config(&self.state.defaults, &req);
+        let loaded = self
+            .state
+            .load_events(&mut config, &req)
+            .map_err(|e| e.to_status())?;
+        let tuning = self.state.tuning.for_request(&req);
+        let tracking = Tracking::default();
+        let session = NamedSession {
+            token,
+            request: req,
+            created: Timestamp::now(),
+            tracking: tracking.clone(),
+        };
+        if !slot.register(&name, session) {
+            return Err(Status::already_exists(format!("session {name} is open")));
+        }
+        Ok(Response::new(self.state.spawn_stream(
+            slot, loaded, config, tuning, None, tracking, None,
+        )))
+    }
+
+    /// One line of an A/B pair, held back until the other line connects
+    /// with the same replay.
+    async fn stream_feed_line(
+        &self,
+        request: Request<FeedLineRequest>,
+    ) -> Result<Response<Self::StreamFeedLineStream>, Status> {
+        let audit = self.state.audit(&request);
+        let slot = self
+            .state
+            .acquire_session(request.metadata())
+            .ok_or_else(too_many_sessions)?;
+        let FeedLineRequest {
+            pair,
+            line,
+            request: req,
+        } = request.into_inner();
+        if pair.is_empty() {
+            return Err(Status::invalid_argument("a feed line needs a pair name"));
+        }
+        let line = FeedLine::try_from(line)
+            .map_err(|_| Status::invalid_argument(format!("unknown feed line {line}")))?;
+        let req = req.unwrap_or_default();
+        let mut config = merged_config(&self.state.defaults, &req);
+        if line == FeedLine::B {
+            config.jitter_seed = !config.jitter_seed;
+            config.drop_seed = !config.drop_seed;
+        }
+        if let Some(audit) = &audit {
+            audit.record(AuditCall::StreamFeedLine {
+                pair: pair.clone(),
+                line: line.into(),
+                request: (&req).into(),
+                jitter_seed: config.jitter_seed,
+                drop_seed: config.drop_seed,
+            });
+        }
+        let loaded = self
+            .state
+            .load_events(&mut config, &req)
+            .map_err(|e| e.to_status())?;
+        let tuning = self.state.tuning.for_request(&req);
+        let shared = StreamRequest {
+            latency_ns: None,
+            jitter_ns: None,
+            jitter_seed: None,
+            drop_rate: None,
+            drop_burst: None,
+            drop_seed: None,
+            buffer: 0,
+            backpressure: 0,
+            ..req
+        };
+        let mut feeds = self.state.sessions.feeds();
+        let gate = match feeds.remove(&pair) {
+            Some(waiting) if !waiting.start.is_closed() => {
+                if waiting.line == line {
+                    feeds.insert(pair.clone(), waiting);
+                    return Err(Status::already_exists(format!(
+                        "line {line:?} of pair {pair} is connected"
+                    )));
+                }
+                if waiting.request != shared {
+                    feeds.insert(pair.clone(), waiting);
+                    return Err(Status::invalid_argument(format!(
+                        "pair {pair} replays a different request"
+                    )));
+                }
+                let _ = waiting.start.send(());
+                None
+            }
+            _ => {
+                let (start, gate) = oneshot::channel();
+                feeds.insert(
+                    pair,
+                    WaitingLine {
+                        line,
+                        request: shared,
+                        start,
+                    },
+                );
+                Some(gate)
+            }
+        };
+        drop(feeds);
+        Ok(Response::new(self.state.spawn_stream(
+            slot,
+            loaded,
+            config,
+            tuning,
+            None,
+            Tracking::default(),
+            gate,
+        )))
+    }
+
+    /// The named sessions opened with the caller's token.
+    async fn list_sessions(
+        &self,
+        request: Request<ListSessionsRequest>,
+    ) -> Result<Response<pb::ListSessionsResponse>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            audit.record(AuditCall::ListSessions);
+        }
+        let token = session_token(request.metadata());
+        let sessions = self
+            .state
+            .sessions
+            .named()
+            .iter()
+            .filter(|(_, session)| session.token == token)
+            .map(|(name, session)| session.info(name))
+            .collect();
+        Ok(Response::new(pb::ListSessionsResponse { sessions }))
+    }
+
+    async fn close_session(
+        &self,
+        request: Request<CloseSessionRequest>,
+    ) -> Result<Response<pb::SessionInfo>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            audit.record(AuditCall::CloseSession {
+                name: request.get_ref().name.clone(),
+            });
+        }
+        let token = session_token(request.metadata());
+        let name = request.into_inner().name;
+        let named = self.state.sessions.named();
+        // Someone else's session is as good as absent.
+        let session = named
+            .get(&name)
+            .filter(|session| session.token == token)
+            .ok_or_else(|| Status::not_found(format!("no open session {name}")))?;
+        session.tracking.close.cancel();
+        Ok(Response::new(session.info(&name)))
+    }
+
+    async fn get_log_info(
+        &self,
+        request: Request<GetLogInfoRequest>,
+    ) -> Result<Response<pb::LogInfo>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            audit.record(AuditCall::GetLogInfo);
+        }
+        let info = self.state.source.info().map_err(|e| e.to_status())?;
+        Ok(Response::new(info.into()))
+    }
+
+    /// Up to `limit` events from `from_seq` on, capped by the server and
+    /// by the per-session event limit.
+    async fn get_range(
+        &self,
+        request: Request<GetRangeRequest>,
+    ) -> Result<Response<pb::GetRangeResponse>, Status> {
+        if let Some(audit) = self.state.audit(&request) {
+            let req = request.get_ref();
+            audit.record(AuditCall::GetRange {
+                from_seq: req.from_seq,
+                to_seq: req.to_seq,
+                limit: req.limit,
+            });
+        }
+        let req = request.into_inner();
+        if req.to_seq.is_some_and(|to| to < req.from_seq) {
+            return Err(Status::invalid_argument("to_seq is before from_seq"));
+        }
+        let mut cap = MAX_RANGE_EVENTS;
+        if let Some(max) = self.state.limits.max_events {
+            cap = cap.min(usize::try_from(max).unwrap_or(usize::MAX));
+        }
+        let limit = match req.limit as usize {
+            0 => cap,
+            limit => limit.min(cap),
+        };
+        // One past the page says where the next one starts.
+        let source = self.state.source.clone();
+        let mut events = tokio::task::spawn_blocking(move || {
+            source.read_sequences(req.from_seq, req.to_seq, limit.saturating_add(1))
+        })
+        .await
+        .map_err(|e| Status::internal(format!("range read failed: {e}")))?
+        .map_err(|e| e.to_status())?;
+        let next_seq = events.get(limit).map(|e| e.sequence);
+        events.truncate(limit);
+        if let Some(plugin) = &self.state.plugin {
+            events = plugin.apply(events).map_err(|e| e.to_status())?;
+        }
+        Ok(Response::new(pb::GetRangeResponse {
+            events: events.iter().map(to_proto).collect(),
+            next_seq,
+        }))
+    }
+
+    /// A `StreamEvents` replay started by the first command, which later
+    /// commands pause, resume, speed up or seek.
+    async fn control(
+        &self,
+        request: Request<Streaming<ControlCommand>>,
+    ) -> Result<Response<Self::ControlStream>, Status> {
+        use pb::control_command::Command;
+
+        let audit = self.state.audit(&request);
+        let slot = self
+            .state
+            .acquire_session(request.metadata())
+            .ok_or_else(too_many_sessions)?;
+        let mut commands = request.into_inner();
+        let req = match commands.message().await? {
+            Some(ControlCommand {
+                command: Some(Command::Start(req)),
+            }) => req,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "the first control command must be start",
+                ))
+            }
+        };
+        if let Some(audit) = &audit {
+            audit.record(AuditCall::Control {
+                request: (&req).into(),
+            });
+        }
+        let mut config = merged_config(&self.state.defaults, &req);
+        let loaded = self
+            .state
+            .load_events(&mut config, &req)
+            .map_err(|e| e.to_status())?;
+        let tuning = self.state.tuning.for_request(&req);
+        let (controls_tx, controls) = mpsc::channel(16);
+        tokio::spawn(async move {
+            while let Some(Ok(cmd)) = commands.next().await {
+                if let (Some(audit), Some(command)) = (
+                    &audit,
+                    cmd.command.as_ref().and_then(AuditControl::from_command),
+                ) {
+                    audit.record(AuditCall::ControlCommand { command });
+                }
+                let control = match cmd.command {
+                    Some(Command::Pause(_)) => Control::Pause,
+                    Some(Command::Resume(_)) => Control::Resume,
+                    Some(Command::Speed(speed)) => Control::Speed(speed),
+                    Some(Command::SeekNs(ns)) => Control::SeekTime(Timestamp::from_nanos(ns)),
+                    Some(Command::SeekSequence(seq)) => Control::SeekSequence(seq),
+                    Some(Command::Step(step)) => Control::Step {
+                        count: step.count,
+                        by_timestamp: step.by_timestamp,
+                    },
+                    Some(Command::Start(_)) | None => continue,
+                };
+                if controls_tx.send(control).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(self.state.spawn_stream(
+            slot,
+            loaded,
+            config,
+            tuning,
+            Some(controls),
+            Tracking::default(),
+            None,
+        )))
+    }
 
-        let (tx, rx) = mpsc::channel(1024);
-        tokio::spawn(stream_with_pacing(events, config, tx));
+    async fn multiplex(
+        &self,
+        request: Request<Streaming<SubscriptionCommand>>,
+    ) -> Result<Response<Self::MultiplexStream>, Status> {
+        let audit = self.state.audit(&request);
+        if let Some(audit) = &audit {
+            audit.record(AuditCall::Multiplex);
+        }
+        let slot = self
+            .state
+            .acquire_session(request.metadata())
+            .ok_or_else(too_many_sessions)?;
+        let (tx, rx) = self.state.tuning.channel();
+        tokio::spawn(run_multiplex(
+            self.state.clone(),
+            request.into_inner(),
+            tx,
+            Arc::new(slot),
+            audit,
+        ));
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 }
 
+/// Drives one multiplexed connection: every `subscribe` starts an
+/// independently paced replay tagged with its subscription id, and
+/// `unsubscribe` cancels it. Subscriptions outlive a client half-close.
+async fn run_multiplex<S>(
+    state: ServiceState,
+    mut commands: S,
+    tx: mpsc::Sender<Result<pb::MultiplexMessage, Status>>,
+    slot: Arc<SessionSlot>,
+    audit: Option<AuditContext>,
+) where
+    S: Stream<Item = Result<SubscriptionCommand, Status>> + Unpin,
+{
+    use pb::subscription_command::Command;
+
+    let mut active = HashMap::<u64, CancellationToken>::new();
+    while let Some(Ok(cmd)) = commands.next().await {
+        let id = cmd.subscription_id;
+        if let Some(audit) = &audit {
+            match &cmd.command {
+                Some(Command::Subscribe(req)) => audit.record(AuditCall::Subscribe {
+                    subscription_id: id,
+                    request: req.into(),
+                }),
+                Some(Command::Unsubscribe(_)) => audit.record(AuditCall::Unsubscribe {
+                    subscription_id: id,
+                }),
+                None => {}
+            }
+        }
+        active.retain(|_, cancel| !cancel.is_cancelled());
+        match cmd.command {
+            Some(Command::Subscribe(req)) => {
+                if active.contains_key(&id) {
+                    let _ = tx
+                        .send(Ok(end_message(id, "duplicate subscription id", &[])))
+                        .await;
+                    continue;
+                }
+                let mut config = merged_config(&state.defaults, &req);
+                match state.load_events(&mut config, &req) {
+                    Ok(Loaded {
+                        events,
+                        days,
+                        truncated,
+                    }) => {
+                        let cancel = CancellationToken::new();
+                        active.insert(id, cancel.clone());
+                        let sub = Subscription {
+                            id,
+                            events,
+                            days,
+                            config,
+                            truncated,
+                            max_duration: state.limits.max_duration,
+                            read_ahead: state.tuning.read_ahead,
+                            cancel,
+                        };
+                        state
+                            .tuning
+                            .spawn(run_subscription(sub, tx.clone(), slot.clone()));
+                    }
+                    Err(err) => {
+                        let _ = tx.send(Ok(end_message(id, &err.to_string(), &[]))).await;
+                    }
+                }
+            }
+            Some(Command::Unsubscribe(_)) => {
+                if let Some(cancel) = active.remove(&id) {
+                    cancel.cancel();
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+struct Subscription {
+    id: u64,
+    events: Vec<Event>,
+    days: Vec<DayBoundary>,
+    config: ReplayConfig,
+    truncated: bool,
+    max_duration: Option<Duration>,
+    read_ahead: usize,
+    cancel: CancellationToken,
+}
+
+async fn run_subscription(
+    sub: Subscription,
+    tx: mpsc::Sender<Result<pb::MultiplexMessage, Status>>,
+    _slot: Arc<SessionSlot>,
+) {
+    let Subscription {
+        id,
+        events,
+        days,
+        config,
+        truncated,
+        max_duration,
+        read_ahead,
+        cancel,
+    } = sub;
+    let mut checkpoints = Checkpoints::new(&config);
+    let resume_at = checkpoints.position().min(events.len());
+    let mut messages = ReadAhead::new(&events[resume_at..], &config, read_ahead);
+    let batch = config.batch_closing_cross;
+    let mut batcher = Batcher::new(&config);
+    let mut dropper = Dropper::new(&config);
+    dropper.skip(resume_at);
+    let mut dropped = Vec::new();
+    let mut throttle = Throttle::new(&config);
+    let mut pacer = Pacer::new(config);
+    let mut order = OrderCheck::new("subscription");
+    let first_day = day_at(&days, &events, resume_at);
+    let mut days = days.into_iter().skip(first_day).peekable();
+    let deadline = max_duration.map(|d| Instant::now() + d);
+    let wrap = |msg| pb::MultiplexMessage {
+        subscription_id: id,
+        body: Some(pb::multiplex_message::Body::Event(msg)),
+    };
+    let reason = 'run: {
+        while let Some(mut group) = messages.next_group() {
+            let mut kept = Vec::with_capacity(group.items.len());
+            for (event, _) in &group.items {
+                while let Some(day) = days.next_if(|d| d.first_sequence <= event.sequence) {
+                    pacer.start_day();
+                    let messages = batcher
+                        .flush()
+                        .into_iter()
+                        .chain([day.to_proto()])
+                        .map(wrap)
+                        .map(Ok)
+                        .collect();
+                    if !send_together(&tx, messages).await {
+                        cancel.cancel();
+                        return;
+                    }
+                }
+                let due = pacer.schedule(event);
+                let lost = dropper.drops();
+                kept.push(!lost);
+                if lost {
+                    extend_runs(&mut dropped, event.sequence);
+                    continue;
+                }
+                if !pacer.is_due(due) {
+                    if let Some(held) = batcher.flush() {
+                        if tx.send(Ok(wrap(held))).await.is_err() {
+                            cancel.cancel();
+                            return;
+                        }
+                    }
+                }
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => break 'run "unsubscribed",
+                    _ = sleep_until_deadline(deadline) => break 'run "session duration limit reached",
+                    _ = pacer.sleep(due) => {}
+                }
+                if pacer.in_log_order() {
+                    order.observe(event);
+                }
+            }
+            let checkpoint = checkpoints.pass(&events, group.items.len());
+            let mut kept = kept.into_iter();
+            group.items.retain(|_| kept.next().unwrap_or(true));
+            if !group.items.is_empty() {
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => break 'run "unsubscribed",
+                    _ = sleep_until_deadline(deadline) => break 'run "session duration limit reached",
+                    _ = throttle.wait(group.items.len()) => {}
+                }
+            }
+            let mut messages = batcher.add(group, batch);
+            if let Some(checkpoint) = checkpoint {
+                messages.extend(batcher.flush());
+                messages.push(checkpoint);
+            }
+            let messages = messages.into_iter().map(wrap).map(Ok).collect();
+            if !send_together(&tx, messages).await {
+                cancel.cancel();
+                return;
+            }
+        }
+        if truncated {
+            "session event limit reached"
+        } else {
+            "completed"
+        }
+    };
+    cancel.cancel();
+    // Held events were released before the end, so they go out ahead of it.
+    let messages = batcher
+        .flush()
+        .into_iter()
+        .map(wrap)
+        .chain([end_message(id, reason, &dropped)])
+        .map(Ok)
+        .collect();
+    send_together(&tx, messages).await;
+}
+
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn too_many_sessions() -> Status {
+    Status::resource_exhausted("too many concurrent sessions for token")
+}
+
+fn end_message(id: u64, reason: &str, dropped: &[RangeInclusive<u64>]) -> pb::MultiplexMessage {
+    pb::MultiplexMessage {
+        subscription_id: id,
+        body: Some(pb::multiplex_message::Body::End(pb::SubscriptionEnd {
+            reason: reason.to_string(),
+            dropped: to_ranges(dropped),
+        })),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn serve_grpc(
-    log_path: PathBuf,
-    index_path: Option<PathBuf>,
+    source: LogSource,
     addr: SocketAddr,
     defaults: ReplayConfig,
+    limits: SessionLimits,
+    tuning: StreamTuning,
+    plugin: Option<WasmPlugin>,
+    audit: Option<AuditLog>,
+    security: ServerSecurity,
 ) -> Result<(), ReplayError> {
+    let flight = FlightSvc {
+        source: source.clone(),
+        plugin: plugin.clone(),
+        max_events: limits.max_events,
+    };
     let service = ReplaySvc {
         state: ServiceState {
-            log_path,
-            index_path,
+            source,
             defaults,
+            limits,
+            tuning,
+            plugin,
+            audit,
+            sessions: SessionTable::default(),
         },
     };
 
-    Server::builder()
-        .add_service(ReplayServiceServer::new(service))
-        .serve(addr)
-        .await?;
+    let auth = BearerAuth {
+        tokens: security.tokens.into(),
+    };
+    // Probes carry no token, so health stays open; reflection describes
+    // the API and is guarded like it.
+    let (mut health, health_service) = tonic_health::server::health_reporter();
+    health
+        .set_service_status(
+            pb::replay_service_server::SERVICE_NAME,
+            ServingStatus::Serving,
+        )
+        .await;
+    health
+        .set_service_status(flight_service_server::SERVICE_NAME, ServingStatus::Serving)
+        .await;
+    let reflection = |builder: tonic_reflection::server::Builder<'static>| {
+        builder.register_encoded_file_descriptor_set(pb::FILE_DESCRIPTOR_SET)
+    };
+    let reflection_v1 = reflection(tonic_reflection::server::Builder::configure()).build_v1()?;
+    let reflection_v1alpha =
+        reflection(tonic_reflection::server::Builder::configure()).build_v1alpha()?;
+    let router = Server::builder()
+        .add_service(health_service)
+        .add_service(InterceptedService::new(reflection_v1, auth.clone()))
+        .add_service(InterceptedService::new(reflection_v1alpha, auth.clone()))
+        .add_service(InterceptedService::new(
+            flight_server(flight, tuning),
+            auth.clone(),
+        ))
+        .add_service(InterceptedService::new(
+            replay_server(service, tuning),
+            auth,
+        ));
+    match security.tls {
+        Some(identity) => {
+            let acceptor = identity.acceptor()?;
+            let listener = TcpListener::bind(addr).await?;
+            router
+                .serve_with_incoming(tls::incoming(listener, acceptor))
+                .await?;
+        }
+        None => router.serve(addr).await?,
+    }
     Ok(())
 }
 
+fn replay_server(service: ReplaySvc, tuning: StreamTuning) -> ReplayServiceServer<ReplaySvc> {
+    let mut server = ReplayServiceServer::new(service)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    if let Some(compression) = tuning.compression {
+        server = server.send_compressed(compression.encoding());
+    }
+    if let Some(limit) = tuning.max_message_bytes {
+        server = server
+            .max_decoding_message_size(limit)
+            .max_encoding_message_size(limit);
+    }
+    server
+}
+
+fn flight_server(service: FlightSvc, tuning: StreamTuning) -> FlightServiceServer<FlightSvc> {
+    let mut server = FlightServiceServer::new(service)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    if let Some(compression) = tuning.compression {
+        server = server.send_compressed(compression.encoding());
+    }
+    if let Some(limit) = tuning.max_message_bytes {
+        server = server
+            .max_decoding_message_size(limit)
+            .max_encoding_message_size(limit);
+    }
+    server
+}
+
+fn session_token(metadata: &MetadataMap) -> String {
+    metadata
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.strip_prefix("Bearer ").unwrap_or(v).trim().to_string())
+        .unwrap_or_else(|| String::from(ANONYMOUS_TOKEN))
+}
+
 fn merged_config(defaults: &ReplayConfig, req: &StreamRequest) -> ReplayConfig {
     ReplayConfig {
-        from_ns: if req.from_ns == 0 {
-            defaults.from_ns
-        } else {
-            Some(req.from_ns)
-        },
-        to_ns: if req.to_ns == 0 {
-            defaults.to_ns
-        } else {
-            Some(req.to_ns)
-        },
+        from_ns: req.from_ns.map(Timestamp::from_nanos).or(defaults.from_ns),
+        to_ns: req.to_ns.map(Timestamp::from_nanos).or(defaults.to_ns),
         speed: if req.speed <= 0.0 {
             defaults.speed
         } else {
@@ -85,5 +1348,1089 @@ fn merged_config(defaults: &ReplayConfig, req: &StreamRequest) -> ReplayConfig {
         },
         max_speed: defaults.max_speed || req.max_speed,
         step_mode: defaults.step_mode || req.step_mode,
+        max_gap_ns: req.max_gap_ns.or(defaults.max_gap_ns),
+        enrich: defaults.enrich || req.enrich,
+        symbol_starts: if req.symbol_starts_ns.is_empty() {
+            defaults.symbol_starts.clone()
+        } else {
+            req.symbol_starts_ns
+                .iter()
+                .map(|(symbol, ns)| (symbol.clone(), Timestamp::from_nanos(*ns)))
+                .collect()
+        },
+        closing_cross: req.closing_cross_ns.or(defaults.closing_cross),
+        batch_closing_cross: defaults.batch_closing_cross || req.batch_closing_cross,
+        venue_channels: Vec::new(),
+        max_batch: if req.max_batch == 0 {
+            defaults.max_batch
+        } else {
+            req.max_batch as usize
+        },
+        latency_ns: req.latency_ns.unwrap_or(defaults.latency_ns),
+        jitter_ns: req.jitter_ns.unwrap_or(defaults.jitter_ns),
+        jitter_seed: req.jitter_seed.unwrap_or(defaults.jitter_seed),
+        drop_rate: req.drop_rate.unwrap_or(defaults.drop_rate),
+        drop_burst: req
+            .drop_burst
+            .map_or(defaults.drop_burst, |burst| burst as usize),
+        drop_seed: req.drop_seed.unwrap_or(defaults.drop_seed),
+        wall_clock: defaults.wall_clock || req.wall_clock,
+        checkpoint_every: if req.checkpoint_every == 0 {
+            defaults.checkpoint_every
+        } else {
+            req.checkpoint_every as usize
+        },
+        resume: None,
+        transforms: defaults.transforms.clone(),
+        max_events_per_sec: req
+            .max_events_per_sec
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| rate.max(MIN_EVENTS_PER_SEC))
+            .or(defaults.max_events_per_sec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::ReplayRequestsOptions;
+    use crate::source::LogFile;
+    use proptest::prelude::*;
+
+    #[test]
+    fn session_slots_are_capped_per_token() {
+        let table = SessionTable::default();
+        let first = table.acquire("a", Some(1)).expect("first slot");
+        assert!(table.acquire("a", Some(1)).is_none());
+        assert!(table.acquire("b", Some(1)).is_some());
+        drop(first);
+        assert!(table.acquire("a", Some(1)).is_some());
+    }
+
+    #[tokio::test]
+    async fn tuned_sessions_stream_the_same_messages() {
+        let events = (1..=50u64)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100 + i as i64 % 7, 1))
+            .collect::<Vec<_>>();
+        let stream = |tuning: StreamTuning| {
+            let service = ReplaySvc {
+                state: ServiceState {
+                    source: LogSource::Events(events.clone().into()),
+                    defaults: ReplayConfig {
+                        max_speed: true,
+                        enrich: true,
+                        ..ReplayConfig::default()
+                    },
+                    limits: SessionLimits::default(),
+                    tuning,
+                    plugin: None,
+                    audit: None,
+                    sessions: SessionTable::default(),
+                },
+            };
+            async move {
+                let response = service
+                    .stream_events(Request::new(StreamRequest::default()))
+                    .await
+                    .expect("stream");
+                response
+                    .into_inner()
+                    .map(|msg| msg.expect("message"))
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+
+        let baseline = stream(StreamTuning::default()).await;
+        assert_eq!(baseline.len(), 50);
+        let tuned = stream(StreamTuning {
+            channel_depth: 2,
+            spawn: SpawnStrategy::Thread,
+            read_ahead: 16,
+            ..StreamTuning::default()
+        })
+        .await;
+        assert_eq!(tuned, baseline);
+        assert_eq!("thread".parse(), Ok(SpawnStrategy::Thread));
+        assert!("fiber".parse::<SpawnStrategy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn closing_cross_goes_out_as_one_batch() {
+        // 20:00 UTC on the second day.
+        let cross = 20 * 3_600_000_000_000i64;
+        let at = 86_400_000_000_000 + cross;
+        let events = [at - 5, at, at, at, at + 5]
+            .into_iter()
+            .zip(1u64..)
+            .map(|(ts, i)| Event::trade(ts, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let mut config = ReplayConfig {
+            max_speed: true,
+            closing_cross: Some(cross),
+            ..ReplayConfig::default()
+        };
+        let mut read_ahead = ReadAhead::new(&events, &config, 2);
+        let groups = std::iter::from_fn(|| read_ahead.next_group())
+            .map(|g| g.items.iter().map(|(e, _)| e.sequence).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(groups, [vec![1], vec![2, 3, 4], vec![5]]);
+
+        config.batch_closing_cross = true;
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: config,
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let response = service
+            .stream_events(Request::new(StreamRequest::default()))
+            .await
+            .expect("stream");
+        let messages = response
+            .into_inner()
+            .map(|msg| msg.expect("message"))
+            .collect::<Vec<_>>()
+            .await;
+        let sequences = messages
+            .iter()
+            .map(|msg| match &msg.payload {
+                Some(pb::event_message::Payload::ClosingCross(cross)) => {
+                    cross.events.iter().map(|e| e.sequence).collect()
+                }
+                _ => vec![msg.sequence],
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sequences, [vec![1], vec![2, 3, 4], vec![5]]);
+        assert_eq!(messages[1].timestamp_ns, at);
+    }
+
+    #[tokio::test]
+    async fn feed_lines_start_together_with_their_own_losses() {
+        let events = (1..=200u64)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: ReplayConfig {
+                    max_speed: true,
+                    ..ReplayConfig::default()
+                },
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let line = |line: FeedLine, request: StreamRequest| FeedLineRequest {
+            pair: String::from("opra"),
+            line: line as i32,
+            request: Some(request),
+        };
+        let lossy = StreamRequest {
+            drop_rate: Some(0.1),
+            drop_seed: Some(7),
+            ..StreamRequest::default()
+        };
+        let mut a = service
+            .stream_feed_line(Request::new(line(FeedLine::A, lossy.clone())))
+            .await
+            .expect("line A")
+            .into_inner();
+        let waiting = tokio::time::timeout(Duration::from_millis(50), a.next()).await;
+        assert!(waiting.is_err(), "line A started alone");
+
+        let again = service
+            .stream_feed_line(Request::new(line(FeedLine::A, lossy.clone())))
+            .await
+            .expect_err("second line A");
+        assert_eq!(again.code(), tonic::Code::AlreadyExists);
+        let other = StreamRequest {
+            symbols: vec![String::from("MSFT")],
+            ..lossy.clone()
+        };
+        let mismatch = service
+            .stream_feed_line(Request::new(line(FeedLine::B, other)))
+            .await
+            .expect_err("different replay");
+        assert_eq!(mismatch.code(), tonic::Code::InvalidArgument);
+
+        let b = service
+            .stream_feed_line(Request::new(line(FeedLine::B, lossy)))
+            .await
+            .expect("line B")
+            .into_inner();
+        let sequences = |stream: ReceiverStream<Result<pb::EventMessage, Status>>| {
+            stream
+                .map(|msg| msg.expect("message").sequence)
+                .collect::<Vec<_>>()
+        };
+        let (a, b) = (sequences(a).await, sequences(b).await);
+        assert!(a.len() < 200 && b.len() < 200);
+        assert_ne!(a, b);
+        let mut both = a.iter().chain(&b).copied().collect::<Vec<_>>();
+        both.sort_unstable();
+        both.dedup();
+        assert!(both.len() > a.len().max(b.len()));
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_streams_keep_pacing_and_report_overflow() {
+        let events = (1..=20u64)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let state = ServiceState {
+            source: LogSource::Events(events.into()),
+            defaults: ReplayConfig {
+                max_speed: true,
+                ..ReplayConfig::default()
+            },
+            limits: SessionLimits::default(),
+            tuning: StreamTuning::default(),
+            plugin: None,
+            audit: None,
+            sessions: SessionTable::default(),
+        };
+        let req = StreamRequest {
+            buffer: 2,
+            backpressure: pb::Backpressure::DropOldest as i32,
+            ..StreamRequest::default()
+        };
+        let mut config = merged_config(&state.defaults, &req);
+        let loaded = state.load_events(&mut config, &req).expect("load");
+        let tuning = state.tuning.for_request(&req);
+        assert_eq!(tuning.channel_depth, 2);
+        let tracking = Tracking::default();
+        let slot = state.acquire_session(&MetadataMap::new()).expect("slot");
+        let stream = state.spawn_stream(slot, loaded, config, tuning, None, tracking.clone(), None);
+
+        // Nothing reads until the replay is over, so all but the first
+        // message and the newest two are pushed out, and the client hears
+        // of it before the newest two.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let received = stream
+            .map(|msg| match msg.expect("message") {
+                pb::EventMessage {
+                    payload: Some(pb::event_message::Payload::Stats(stats)),
+                    ..
+                } => (0, Some(stats.overflowed)),
+                msg => (msg.sequence, None),
+            })
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            received,
+            vec![(1, None), (0, Some(17)), (19, None), (20, None)]
+        );
+        assert_eq!(tracking.progress.overflowed(), 17);
+        assert_eq!(tracking.progress.events_sent(), 20);
+    }
+
+    #[tokio::test]
+    async fn resumed_streams_continue_after_their_checkpoint() {
+        let events = (1..=60u64)
+            .map(|i| Event::trade(i as i64, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: ReplayConfig {
+                    max_speed: true,
+                    ..ReplayConfig::default()
+                },
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let request = StreamRequest {
+            checkpoint_every: 25,
+            drop_rate: Some(0.2),
+            drop_seed: Some(3),
+            max_batch: 4,
+            ..StreamRequest::default()
+        };
+        let stream = |request: StreamRequest| async {
+            let messages = service
+                .stream_events(Request::new(request))
+                .await?
+                .into_inner()
+                .map(|msg| msg.expect("message"))
+                .collect::<Vec<_>>()
+                .await;
+            let mut sent = Vec::new();
+            let mut tokens = Vec::new();
+            for msg in messages {
+                match msg.payload {
+                    Some(pb::event_message::Payload::Checkpoint(checkpoint)) => {
+                        tokens.push((sent.len(), checkpoint.resume_token));
+                    }
+                    Some(pb::event_message::Payload::Batch(batch)) => {
+                        sent.extend(batch.events.iter().map(|e| e.sequence));
+                    }
+                    _ => sent.push(msg.sequence),
+                }
+            }
+            Ok::<_, Status>((sent, tokens))
+        };
+        let (full, tokens) = stream(request.clone()).await.expect("full");
+        assert_eq!(tokens.len(), 2);
+        let (at, token) = tokens[1].clone();
+        assert!(token.starts_with("50:"), "{token}");
+        assert!(full[..at].iter().all(|&seq| seq <= 50));
+        let (rest, _) = stream(StreamRequest {
+            resume_token: token,
+            ..request.clone()
+        })
+        .await
+        .expect("resumed");
+        // The same events are lost after the checkpoint as before.
+        assert_eq!(rest, full[at..]);
+
+        for token in ["50:7", "oops"] {
+            let err = stream(StreamRequest {
+                resume_token: token.to_string(),
+                ..request.clone()
+            })
+            .await
+            .expect_err("bad token");
+            assert_eq!(err.code(), tonic::Code::FailedPrecondition, "{token}");
+        }
+    }
+
+    #[tokio::test]
+    async fn symbol_starts_see_transformed_events_and_resume() {
+        use crate::transform::{AnonymizeSymbols, ShiftTimestamps, Transforms};
+
+        let events = vec![
+            Event::trade(100, 1, "X", "AAPL", 10, 1),
+            Event::trade(150, 2, "X", "SPY", 30, 1),
+            Event::trade(200, 3, "X", "MSFT", 20, 1),
+            Event::trade(250, 4, "X", "AAPL", 11, 1),
+            Event::trade(300, 5, "X", "MSFT", 21, 1),
+            Event::trade(320, 6, "X", "MSFT", 22, 1),
+        ];
+        let anonymize = AnonymizeSymbols { salt: 9 };
+        let mut transforms = Transforms::new();
+        transforms.push(ShiftTimestamps { ns: 1_000 });
+        transforms.push(anonymize);
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: ReplayConfig {
+                    max_speed: true,
+                    transforms,
+                    ..ReplayConfig::default()
+                },
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        // Starts name the stand-ins and the shifted times.
+        let request = StreamRequest {
+            symbol_starts_ns: HashMap::from([
+                (anonymize.anonymize("AAPL"), 1_100),
+                (anonymize.anonymize("MSFT"), 1_300),
+            ]),
+            checkpoint_every: 2,
+            ..StreamRequest::default()
+        };
+        let stream = |request: StreamRequest| async {
+            let messages = service
+                .stream_events(Request::new(request))
+                .await?
+                .into_inner()
+                .map(|msg| msg.expect("message"))
+                .collect::<Vec<_>>()
+                .await;
+            let mut sent = Vec::new();
+            let mut tokens = Vec::new();
+            for msg in messages {
+                match msg.payload {
+                    Some(pb::event_message::Payload::Checkpoint(checkpoint)) => {
+                        tokens.push((sent.len(), checkpoint.resume_token));
+                    }
+                    _ => sent.push(msg.sequence),
+                }
+            }
+            Ok::<_, Status>((sent, tokens))
+        };
+        let (full, tokens) = stream(request.clone()).await.expect("full");
+        // MSFT's 300 lines up with AAPL's 100; its earlier trade is dropped.
+        assert_eq!(full, [1, 5, 6, 2, 4]);
+        let (at, token) = tokens[0].clone();
+        let (rest, _) = stream(StreamRequest {
+            resume_token: token,
+            ..request
+        })
+        .await
+        .expect("resumed");
+        assert_eq!(rest, full[at..]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn due_events_go_out_in_batches() {
+        // Three events at once, a millisecond's pause, then four more.
+        let events = [0, 0, 0, 1, 1, 1, 1]
+            .into_iter()
+            .zip(1u64..)
+            .map(|(ms, i)| Event::trade(ms * 1_000_000, i, "X", "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: ReplayConfig::default(),
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let batches = |request| {
+            let service = &service;
+            async move {
+                let response = service
+                    .stream_events(Request::new(request))
+                    .await
+                    .expect("stream");
+                response
+                    .into_inner()
+                    .map(|msg| match msg.expect("message").payload {
+                        Some(pb::event_message::Payload::Batch(batch)) => {
+                            assert_eq!(batch.first_ns, batch.events[0].timestamp_ns);
+                            batch.events.iter().map(|e| e.sequence).collect()
+                        }
+                        _ => vec![0],
+                    })
+                    .collect::<Vec<Vec<u64>>>()
+                    .await
+            }
+        };
+
+        let fast = batches(StreamRequest {
+            max_speed: true,
+            max_batch: 3,
+            ..StreamRequest::default()
+        })
+        .await;
+        assert_eq!(fast, [vec![1, 2, 3], vec![4, 5, 6], vec![0]]);
+        // Paced, a batch never holds an event that is not yet due.
+        let paced = batches(StreamRequest {
+            max_batch: 3,
+            ..StreamRequest::default()
+        })
+        .await;
+        assert_eq!(paced, [vec![1, 2, 3], vec![4, 5, 6], vec![0]]);
+        let paced = batches(StreamRequest {
+            max_batch: 5,
+            ..StreamRequest::default()
+        })
+        .await;
+        assert_eq!(paced, [vec![1, 2, 3], vec![4, 5, 6, 7]]);
+    }
+
+    #[tokio::test]
+    async fn venues_are_filtered_and_numbered() {
+        let events = ["Y", "X", "Z", "X"]
+            .into_iter()
+            .zip(1u64..)
+            .map(|(venue, i)| Event::trade(i as i64, i, venue, "AAPL", 100, 1))
+            .collect::<Vec<_>>();
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Events(events.into()),
+                defaults: ReplayConfig {
+                    max_speed: true,
+                    ..ReplayConfig::default()
+                },
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let stream = |venues: &[&str], venue_channels| {
+            let request = StreamRequest {
+                venues: venues.iter().map(|v| v.to_string()).collect(),
+                venue_channels,
+                ..StreamRequest::default()
+            };
+            let service = service.clone();
+            async move {
+                let response = service
+                    .stream_events(Request::new(request))
+                    .await
+                    .expect("stream");
+                response
+                    .into_inner()
+                    .map(|msg| msg.expect("message"))
+                    .map(|msg| (msg.venue, msg.channel_id))
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+        let tagged = |pairs: &[(&str, Option<u32>)]| {
+            pairs
+                .iter()
+                .map(|(venue, id)| (venue.to_string(), *id))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            stream(&["z", "x"], true).await,
+            tagged(&[("X", Some(1)), ("Z", Some(0)), ("X", Some(1))])
+        );
+        assert_eq!(
+            stream(&[], true).await,
+            tagged(&[
+                ("Y", Some(1)),
+                ("X", Some(0)),
+                ("Z", Some(2)),
+                ("X", Some(0))
+            ])
+        );
+        assert_eq!(stream(&["Y"], false).await, tagged(&[("Y", None)]));
+    }
+
+    #[tokio::test]
+    async fn multiplex_runs_filtered_subscriptions() {
+        use md_storage::{default_schema_hash, EventLogWriter};
+        use pb::subscription_command::Command;
+
+        let mut log_path = std::env::temp_dir();
+        log_path.push(format!(
+            "md_replay_grpc_mux_{}.eventlog",
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&log_path, &symbols, default_schema_hash()).expect("writer");
+        for i in 1..=6u64 {
+            let symbol = &symbols[(i % 2) as usize];
+            writer
+                .append(&Event::trade(i as i64, i, "X", symbol.as_str(), 100, 1))
+                .expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let state = ServiceState {
+            source: LogSource::Single(LogFile {
+                log: log_path,
+                index: None,
+            }),
+            defaults: ReplayConfig {
+                max_speed: true,
+                ..ReplayConfig::default()
+            },
+            limits: SessionLimits::default(),
+            tuning: StreamTuning::default(),
+            plugin: None,
+            audit: None,
+            sessions: SessionTable::default(),
+        };
+        let subscribe = |id: u64, symbol: &str| SubscriptionCommand {
+            subscription_id: id,
+            command: Some(Command::Subscribe(StreamRequest {
+                symbols: vec![symbol.to_string()],
+                ..StreamRequest::default()
+            })),
+        };
+        let commands = tokio_stream::iter(vec![Ok(subscribe(1, "AAPL")), Ok(subscribe(2, "MSFT"))]);
+        let slot = state.sessions.acquire("", None).expect("slot");
+        let (tx, mut rx) = mpsc::channel(64);
+        tokio::spawn(run_multiplex(state, commands, tx, Arc::new(slot), None));
+
+        let mut seen = HashMap::<u64, Vec<String>>::new();
+        let mut ended = 0;
+        while let Some(Ok(msg)) = rx.recv().await {
+            match msg.body {
+                Some(pb::multiplex_message::Body::Event(ev)) => {
+                    seen.entry(msg.subscription_id).or_default().push(ev.symbol)
+                }
+                Some(pb::multiplex_message::Body::End(end)) => {
+                    assert_eq!(end.reason, "completed");
+                    ended += 1;
+                }
+                None => {}
+            }
+        }
+        assert_eq!(ended, 2);
+        assert_eq!(seen[&1], vec!["AAPL"; 3]);
+        assert_eq!(seen[&2], vec!["MSFT"; 3]);
+    }
+
+    #[tokio::test]
+    async fn stitched_days_are_marked_in_the_stream() {
+        use md_storage::{default_schema_hash, EventLogWriter};
+        use pb::event_message::Payload;
+        use pb::subscription_command::Command;
+
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let days = [("mon", 0i64), ("tue", 86_400_000_000_000)]
+            .iter()
+            .map(|(name, start)| {
+                let log = std::env::temp_dir().join(format!(
+                    "md_replay_grpc_stitch_{}_{name}.eventlog",
+                    std::process::id()
+                ));
+                let mut writer =
+                    EventLogWriter::create(&log, &symbols, default_schema_hash()).expect("writer");
+                for i in 1..=3u64 {
+                    let symbol = &symbols[(i % 2) as usize];
+                    writer
+                        .append(&Event::trade(start + i as i64, i, "X", symbol, 100, 1))
+                        .expect("append");
+                }
+                writer.flush().expect("flush");
+                LogFile::new(log)
+            })
+            .collect();
+        let state = ServiceState {
+            source: LogSource::Stitched(days),
+            defaults: ReplayConfig::default(),
+            limits: SessionLimits::default(),
+            tuning: StreamTuning::default(),
+            plugin: None,
+            audit: None,
+            sessions: SessionTable::default(),
+        };
+        let command = SubscriptionCommand {
+            subscription_id: 1,
+            command: Some(Command::Subscribe(StreamRequest {
+                symbols: vec![String::from("MSFT")],
+                ..StreamRequest::default()
+            })),
+        };
+        let slot = state.sessions.acquire("", None).expect("slot");
+        let (tx, mut rx) = mpsc::channel(64);
+        let started = Instant::now();
+        tokio::spawn(run_multiplex(
+            state,
+            tokio_stream::iter(vec![Ok(command)]),
+            tx,
+            Arc::new(slot),
+            None,
+        ));
+
+        let mut seen = Vec::new();
+        while let Some(Ok(msg)) = rx.recv().await {
+            match msg.body {
+                Some(pb::multiplex_message::Body::Event(ev)) => match ev.payload {
+                    Some(Payload::DayBoundary(day)) => seen.push(format!("day {}", day.day)),
+                    _ => seen.push(ev.sequence.to_string()),
+                },
+                Some(pb::multiplex_message::Body::End(end)) => seen.push(end.reason),
+                None => {}
+            }
+        }
+        // Real-time pacing, yet the overnight gap is skipped.
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            seen,
+            ["day 0", "1", "3", "day 1", "4", "6", "completed"].map(String::from)
+        );
+    }
+
+    #[tokio::test]
+    async fn audited_requests_replay_to_the_same_outcomes() {
+        use crate::audit::{
+            read_audit_log, replay_requests, AuditControl, AuditFeedLine, AuditRecord,
+            AuditRequest, RequestOutcome,
+        };
+        use md_storage::{default_schema_hash, EventLogWriter};
+
+        let dir = std::env::temp_dir();
+        let pid = std::process::id();
+        let log_path = dir.join(format!("md_replay_grpc_audit_{pid}.eventlog"));
+        let audit_path = dir.join(format!("md_replay_grpc_audit_{pid}.jsonl"));
+        let _ = std::fs::remove_file(&audit_path);
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&log_path, &symbols, default_schema_hash()).expect("writer");
+        for i in 1..=6u64 {
+            let symbol = &symbols[(i % 2) as usize];
+            writer
+                .append(&Event::trade(i as i64, i, "X", symbol.as_str(), 100, 1))
+                .expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Single(LogFile {
+                    log: log_path,
+                    index: None,
+                }),
+                defaults: ReplayConfig::default(),
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: Some(AuditLog::open(&audit_path).expect("audit log")),
+                sessions: SessionTable::default(),
+            },
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let endpoint = format!("http://{}", listener.local_addr().expect("addr"));
+        let incoming = futures::stream::unfold(listener, |listener| async move {
+            let conn = listener.accept().await.map(|(stream, _)| stream);
+            Some((conn, listener))
+        });
+        tokio::spawn(
+            Server::builder()
+                .add_service(ReplayServiceServer::new(service))
+                .serve_with_incoming(incoming),
+        );
+
+        let request = |symbols: &[&str]| AuditRequest {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            ..AuditRequest::default()
+        };
+        let record_at = |ms: i64, connection: u64, call: AuditCall| AuditRecord {
+            received_ns: Timestamp::from_nanos(ms * 1_000_000),
+            connection,
+            client: String::from("anonymous"),
+            peer: None,
+            call,
+        };
+        let record = |connection: u64, call: AuditCall| record_at(0, connection, call);
+        let feed_line = |connection: u64, line: AuditFeedLine| {
+            let call = AuditCall::StreamFeedLine {
+                pair: String::from("ab"),
+                line,
+                request: AuditRequest {
+                    drop_rate: Some(0.5),
+                    drop_seed: Some(7),
+                    ..request(&[])
+                },
+                // Drawn by the server; only the request goes back.
+                jitter_seed: 0,
+                drop_seed: 0,
+            };
+            record(connection, call)
+        };
+        let workload = vec![
+            record(
+                1,
+                AuditCall::StreamEvents {
+                    request: request(&["AAPL"]),
+                },
+            ),
+            record(2, AuditCall::Multiplex),
+            record(
+                2,
+                AuditCall::Subscribe {
+                    subscription_id: 1,
+                    request: request(&["MSFT"]),
+                },
+            ),
+            record(
+                2,
+                AuditCall::Subscribe {
+                    subscription_id: 2,
+                    request: request(&[]),
+                },
+            ),
+            record(3, AuditCall::GetLogInfo),
+            record(
+                4,
+                AuditCall::GetRange {
+                    from_seq: 2,
+                    to_seq: None,
+                    limit: 3,
+                },
+            ),
+            // A nanosecond apart at this speed, the events keep the
+            // session open until it is closed.
+            record(
+                5,
+                AuditCall::CreateSession {
+                    name: String::from("held"),
+                    request: AuditRequest {
+                        speed: 1e-8,
+                        ..request(&[])
+                    },
+                },
+            ),
+            record_at(50, 6, AuditCall::ListSessions),
+            record_at(
+                100,
+                7,
+                AuditCall::CloseSession {
+                    name: String::from("held"),
+                },
+            ),
+            // Two steps, a seek past 3 and 4, then the close releases the
+            // rest.
+            record(
+                8,
+                AuditCall::Control {
+                    request: AuditRequest {
+                        step_mode: true,
+                        ..request(&[])
+                    },
+                },
+            ),
+            record_at(
+                50,
+                8,
+                AuditCall::ControlCommand {
+                    command: AuditControl::Step {
+                        count: 2,
+                        by_timestamp: false,
+                    },
+                },
+            ),
+            record_at(
+                100,
+                8,
+                AuditCall::ControlCommand {
+                    command: AuditControl::SeekSequence(5),
+                },
+            ),
+            feed_line(9, AuditFeedLine::A),
+            feed_line(10, AuditFeedLine::B),
+        ];
+        let options = ReplayRequestsOptions::default();
+        let first = replay_requests(&endpoint, &workload, options.clone())
+            .await
+            .expect("first run");
+        assert_eq!(first.len(), 11);
+        assert_eq!(first[1].events, 3);
+        assert_eq!(first[2].events, 6);
+        assert_eq!(first[2].end, "completed");
+        assert_eq!(first[3].rpc, "get_log_info");
+        assert_eq!(first[3].end, "completed");
+        assert_eq!(
+            (
+                first[4].events,
+                first[4].first_sequence,
+                first[4].last_sequence
+            ),
+            (3, Some(2), Some(4))
+        );
+        assert_eq!(first[5].events, 1);
+        assert_eq!(first[5].end, "Cancelled: session closed");
+        assert_eq!(first[7].end, "completed");
+        // Closing digests nothing, while the listing saw the session.
+        assert_ne!(first[6].digest, first[7].digest);
+        assert_eq!(first[8].rpc, "control");
+        assert_eq!(
+            (
+                first[8].events,
+                first[8].first_sequence,
+                first[8].last_sequence
+            ),
+            (4, Some(1), Some(6))
+        );
+        assert!(first[9..].iter().all(|o| o.end == "completed"));
+        assert_ne!(first[9].digest, first[10].digest);
+
+        // The workload itself went through the audit log.
+        let recorded = read_audit_log(&audit_path).expect("audit");
+        assert_eq!(recorded.len(), 14);
+        assert!(recorded.iter().all(|r| r.peer.is_some()));
+        let seeds: HashMap<_, _> = recorded
+            .iter()
+            .filter_map(|r| match r.call {
+                AuditCall::StreamFeedLine {
+                    line, drop_seed, ..
+                } => Some((line, drop_seed)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(seeds[&AuditFeedLine::A], 7);
+        assert_eq!(seeds[&AuditFeedLine::B], !7);
+        let again = replay_requests(&endpoint, &recorded, options)
+            .await
+            .expect("second run");
+        // Concurrent calls reach the log in arrival order, so only the
+        // outcomes themselves must match.
+        let unordered = |outcomes: Vec<RequestOutcome>| {
+            let mut outcomes: Vec<_> = outcomes
+                .into_iter()
+                .map(|o| RequestOutcome { request: 0, ..o })
+                .collect();
+            outcomes.sort_by(|a, b| (&a.rpc, &a.digest).cmp(&(&b.rpc, &b.digest)));
+            outcomes
+        };
+        assert_eq!(unordered(again), unordered(first));
+    }
+
+    #[tokio::test]
+    async fn stress_sessions_stream_their_filters() {
+        use crate::stress::{run_stress, StressOptions, StressProfile};
+        use md_storage::{default_schema_hash, EventLogWriter};
+
+        let log_path = std::env::temp_dir().join(format!(
+            "md_replay_grpc_stress_{}.eventlog",
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&log_path, &symbols, default_schema_hash()).expect("writer");
+        for i in 1..=6u64 {
+            let symbol = &symbols[(i % 2) as usize];
+            writer
+                .append(&Event::trade(i as i64, i, "X", symbol.as_str(), 100, 1))
+                .expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let service = ReplaySvc {
+            state: ServiceState {
+                source: LogSource::Single(LogFile {
+                    log: log_path,
+                    index: None,
+                }),
+                defaults: ReplayConfig::default(),
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            },
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let endpoint = format!("http://{}", listener.local_addr().expect("addr"));
+        let incoming = futures::stream::unfold(listener, |listener| async move {
+            let conn = listener.accept().await.map(|(stream, _)| stream);
+            Some((conn, listener))
+        });
+        tokio::spawn(
+            Server::builder()
+                .add_service(ReplayServiceServer::new(service))
+                .serve_with_incoming(incoming),
+        );
+
+        let options = StressOptions {
+            sessions: 8,
+            profile: StressProfile::Flat,
+            symbols,
+            duration: Duration::from_secs(10),
+            ..StressOptions::default()
+        };
+        let report = run_stress(&endpoint, &options).await.expect("stress");
+        assert_eq!((report.sessions, report.completed), (8, 8));
+        assert!(report.errors.is_empty());
+        for outcome in &report.outcomes {
+            let expected = if outcome.symbols.len() == 1 { 3 } else { 6 };
+            assert_eq!(outcome.events, expected, "{outcome:?}");
+        }
+        assert_eq!(report.connect_ms.count, 8);
+        assert_eq!(report.first_event_ms.count, 8);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn multiplexed_subscriptions_stay_ordered_for_slow_consumers(
+            symbols in prop::collection::vec(0usize..3, 1..200),
+            subscriptions in 1u64..6,
+            capacity in 1usize..4,
+            stall_every in 1usize..7,
+        ) {
+            use md_storage::{default_schema_hash, EventLogWriter};
+            use pb::subscription_command::Command;
+
+            let names = ["AAPL", "MSFT", "NVDA"];
+            let log_path = std::env::temp_dir().join(format!(
+                "md_replay_grpc_mux_prop_{}.eventlog",
+                std::process::id()
+            ));
+            let header = names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let mut writer =
+                EventLogWriter::create(&log_path, &header, default_schema_hash()).expect("writer");
+            for (i, symbol) in symbols.iter().enumerate() {
+                let event = Event::trade(i as i64 / 3, i as u64 + 1, "X", names[*symbol], 100, 1);
+                writer.append(&event).expect("append");
+            }
+            writer.flush().expect("flush");
+
+            let state = ServiceState {
+                source: LogSource::Single(LogFile {
+                    log: log_path,
+                    index: None,
+                }),
+                defaults: ReplayConfig {
+                    max_speed: true,
+                    ..ReplayConfig::default()
+                },
+                limits: SessionLimits::default(),
+                tuning: StreamTuning::default(),
+                plugin: None,
+                audit: None,
+                sessions: SessionTable::default(),
+            };
+            // Subscription `id` follows one symbol, or everything when the
+            // id is past the symbol list.
+            let filter = |id: u64| names.get(id as usize).map(|s| s.to_string());
+            let commands = (0..subscriptions)
+                .map(|id| SubscriptionCommand {
+                    subscription_id: id,
+                    command: Some(Command::Subscribe(StreamRequest {
+                        symbols: filter(id).into_iter().collect(),
+                        ..StreamRequest::default()
+                    })),
+                })
+                .collect::<Vec<_>>();
+
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(4)
+                .enable_time()
+                .build()
+                .expect("runtime");
+            let seen = runtime.block_on(async move {
+                let slot = state.sessions.acquire("", None).expect("slot");
+                let (tx, mut rx) = mpsc::channel(capacity);
+                tokio::spawn(run_multiplex(
+                    state,
+                    tokio_stream::iter(commands.into_iter().map(Ok)),
+                    tx,
+                    Arc::new(slot),
+                    None,
+                ));
+                let mut seen = HashMap::<u64, Vec<u64>>::new();
+                let mut received = 0usize;
+                while let Some(Ok(msg)) = rx.recv().await {
+                    received += 1;
+                    if received.is_multiple_of(stall_every) {
+                        tokio::time::sleep(Duration::from_micros(20)).await;
+                    }
+                    if let Some(pb::multiplex_message::Body::Event(ev)) = msg.body {
+                        seen.entry(msg.subscription_id).or_default().push(ev.sequence);
+                    }
+                }
+                seen
+            });
+
+            for id in 0..subscriptions {
+                let expected = symbols
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| filter(id).is_none_or(|f| f == names[**s]))
+                    .map(|(i, _)| i as u64 + 1)
+                    .collect::<Vec<_>>();
+                prop_assert_eq!(seen.get(&id).cloned().unwrap_or_default(), expected);
+            }
+        }
     }
 }
@@ -1,9 +1,60 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+pub mod audit;
 pub mod engine;
+mod flight;
 pub mod grpc;
+#[cfg(feature = "wasm")]
+mod plugin;
+#[cfg(not(feature = "wasm"))]
+mod plugin_stub;
+mod rng;
+pub mod sink;
+pub mod source;
+pub mod stress;
+mod tls;
+pub mod transform;
 
+/// Generated from `proto/replay.proto`; see `build.rs` for regeneration.
+// Command oneofs carry a whole `StreamRequest` next to bare flags.
+#[allow(clippy::large_enum_variant)]
 pub mod pb {
-    tonic::include_proto!("replay");
+    include!("pb/replay.rs");
+
+    /// Generated from `proto/flight.proto`.
+    pub mod flight {
+        include!("pb/arrow.flight.protocol.rs");
+    }
+
+    /// Encoded descriptors of `replay.proto` and `flight.proto`, for the
+    /// reflection service.
+    pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("pb/replay_descriptor.bin");
 }
 
-pub use engine::{read_events, ReplayConfig, ReplayError};
-pub use grpc::serve_grpc;
+pub use audit::{
+    client_fingerprint, read_audit_log, replay_requests, AuditCall, AuditControl, AuditFeedLine,
+    AuditLog, AuditRecord, AuditRequest, ReplayRequestsOptions, RequestOutcome,
+};
+pub use engine::{
+    read_events, read_transformed, replay_into, Control, DayBoundary, ReplayConfig, ReplayError,
+    ReplayOutcome, ResumeToken, MIN_EVENTS_PER_SEC,
+};
+pub use grpc::{
+    serve_grpc, Backpressure, Compression, ServerSecurity, SessionLimits, SpawnStrategy,
+    StreamTuning,
+};
+#[cfg(feature = "wasm")]
+pub use plugin::{WasmPlugin, WasmTransform};
+#[cfg(not(feature = "wasm"))]
+pub use plugin_stub::WasmPlugin;
+pub use sink::{
+    publish, NatsOptions, NatsSink, RedisOptions, RedisSink, Sink, SinkError, ZmqOptions, ZmqSink,
+};
+pub use source::{LogFile, LogInfo, LogSource, SourceEvents};
+pub use stress::{run_stress, StressOptions, StressProfile, StressReport};
+pub use tls::TlsIdentity;
+pub use tokio_util::sync::CancellationToken;
+pub use transform::{AnonymizeSymbols, RescaleSizes, ShiftTimestamps, Transform, Transforms};
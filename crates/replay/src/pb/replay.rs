@@ -0,0 +1,1474 @@
+// This file is @generated by prost-build.
+/// Timestamps are signed nanoseconds since the Unix epoch. `int64` shares
+/// its wire encoding with the former `uint64` for every non-negative value.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StreamRequest {
+    #[prost(int64, optional, tag = "1")]
+    pub from_ns: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag = "2")]
+    pub to_ns: ::core::option::Option<i64>,
+    #[prost(double, tag = "3")]
+    pub speed: f64,
+    #[prost(bool, tag = "4")]
+    pub max_speed: bool,
+    /// Hold every event until a `Control` client steps to it. Streams
+    /// nothing can step go out unpaced.
+    #[prost(bool, tag = "5")]
+    pub step_mode: bool,
+    #[prost(string, repeated, tag = "6")]
+    pub symbols: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Longest pause between two events, in event time; longer ones are
+    /// shortened to it. In a stitched replay the gap between days is
+    /// skipped entirely unless this is set.
+    #[prost(int64, optional, tag = "7")]
+    pub max_gap_ns: ::core::option::Option<i64>,
+    /// Attach each symbol's book features to its trades and quotes.
+    #[prost(bool, tag = "8")]
+    pub enrich: bool,
+    /// Historical start of each listed symbol, lined up with the earliest
+    /// one so they all begin together. Replaces the server's own starts.
+    /// Symbols and starts are as the server's transforms leave them.
+    #[prost(map = "string, int64", tag = "9")]
+    pub symbol_starts_ns: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        i64,
+    >,
+    /// UTC time of day of the closing cross, in nanoseconds after midnight.
+    /// Every event stamped exactly then on any day goes out at once, with no
+    /// pacing or other subscription's message between them. Replaces the
+    /// server's own time.
+    #[prost(int64, optional, tag = "10")]
+    pub closing_cross_ns: ::core::option::Option<i64>,
+    /// Send each closing cross as one `ClosingCross` message.
+    #[prost(bool, tag = "11")]
+    pub batch_closing_cross: bool,
+    /// Only these venues; empty means every venue.
+    #[prost(string, repeated, tag = "12")]
+    pub venues: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Tag each event with its venue's `channel_id`: the venue's position in
+    /// `venues`, or else among the stream's venues in sorted order.
+    #[prost(bool, tag = "13")]
+    pub venue_channels: bool,
+    /// Send events that are due together as `EventBatch` messages of up to
+    /// this many. A batch goes out when its first event is due, so at max
+    /// speed batches fill up and paced streams keep their timing. 0 or 1
+    /// sends every event on its own.
+    #[prost(uint32, tag = "14")]
+    pub max_batch: u32,
+    /// Wall-clock delay added to every paced event, in nanoseconds.
+    /// Replaces the server's own latency.
+    #[prost(int64, optional, tag = "15")]
+    pub latency_ns: ::core::option::Option<i64>,
+    /// Most extra delay drawn per paced event, uniformly from zero up to
+    /// this. Events still arrive in order. Replaces the server's own jitter.
+    #[prost(int64, optional, tag = "16")]
+    pub jitter_ns: ::core::option::Option<i64>,
+    /// Seeds the jitter draws, so a replay can be repeated exactly.
+    #[prost(uint64, optional, tag = "17")]
+    pub jitter_seed: ::core::option::Option<u64>,
+    /// Chance, from 0 to 1, that an event starts a simulated loss. Lost
+    /// events are never sent; their sequences are reported beside the
+    /// stream. Replaces the server's own rate.
+    #[prost(double, optional, tag = "18")]
+    pub drop_rate: ::core::option::Option<f64>,
+    /// Events lost together once a loss starts; 0 counts as 1.
+    #[prost(uint32, optional, tag = "19")]
+    pub drop_burst: ::core::option::Option<u32>,
+    /// Seeds the loss draws, so a replay loses the same events every time.
+    #[prost(uint64, optional, tag = "20")]
+    pub drop_seed: ::core::option::Option<u64>,
+    /// Send each event at its UTC time of day on today's wall clock, keeping
+    /// the gaps between days, instead of from the moment the stream opens.
+    /// Events whose time has passed go out at once. Speed is ignored.
+    #[prost(bool, tag = "21")]
+    pub wall_clock: bool,
+    /// Send a `checkpoint` message after about every this many events.
+    /// Replaces the server's own interval; 0 keeps it.
+    #[prost(uint32, tag = "22")]
+    pub checkpoint_every: u32,
+    /// Continue after the checkpoint that carried this token. The rest of
+    /// the request must be the one that produced it.
+    #[prost(string, tag = "23")]
+    pub resume_token: ::prost::alloc::string::String,
+    /// Most events sent per second, however fast the replay runs, max speed
+    /// included; unset or not positive leaves it to the server's own cap.
+    #[prost(double, optional, tag = "24")]
+    pub max_events_per_sec: ::core::option::Option<f64>,
+    /// Messages the stream may queue for a slow client, up to 65,536; 0
+    /// keeps the server's depth.
+    #[prost(uint32, tag = "25")]
+    pub buffer: u32,
+    /// What the stream does once that queue is full.
+    #[prost(enumeration = "Backpressure", tag = "26")]
+    pub backpressure: i32,
+}
+/// Sequences `first` to `last`, inclusive.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SequenceRange {
+    #[prost(uint64, tag = "1")]
+    pub first: u64,
+    #[prost(uint64, tag = "2")]
+    pub last: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FeedLineRequest {
+    /// Both lines of a pair connect under the same name.
+    #[prost(string, tag = "1")]
+    pub pair: ::prost::alloc::string::String,
+    #[prost(enumeration = "FeedLine", tag = "2")]
+    pub line: i32,
+    /// Both lines must ask for the same replay. Only the latency, jitter and
+    /// drop fields may differ. Line B draws from the complement of each
+    /// seed, so equal seeds still lose and delay different events.
+    #[prost(message, optional, tag = "3")]
+    pub request: ::core::option::Option<StreamRequest>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateSessionRequest {
+    /// Unique among the server's open sessions.
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub request: ::core::option::Option<StreamRequest>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ListSessionsRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSessionsResponse {
+    /// Open named sessions, by name.
+    #[prost(message, repeated, tag = "1")]
+    pub sessions: ::prost::alloc::vec::Vec<SessionInfo>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CloseSessionRequest {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionInfo {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub request: ::core::option::Option<StreamRequest>,
+    /// Wall-clock time the session was created.
+    #[prost(int64, tag = "3")]
+    pub created_ns: i64,
+    /// Events released to the session's stream so far.
+    #[prost(uint64, tag = "4")]
+    pub events_sent: u64,
+    /// Timestamp of the latest of them.
+    #[prost(int64, optional, tag = "5")]
+    pub last_event_ns: ::core::option::Option<i64>,
+    /// How late, in wall-clock ns, the latest event went out after its due
+    /// time. Grows when the client reads slower than the replay runs.
+    #[prost(int64, tag = "6")]
+    pub lag_ns: i64,
+    /// Messages waiting for the client to read them.
+    #[prost(uint32, tag = "7")]
+    pub queued: u32,
+    /// Events lost to the request's simulated drops so far.
+    #[prost(uint64, tag = "8")]
+    pub events_dropped: u64,
+    /// Their sequences, in runs.
+    #[prost(message, repeated, tag = "9")]
+    pub dropped: ::prost::alloc::vec::Vec<SequenceRange>,
+    /// Messages discarded because the client fell behind a drop-oldest
+    /// stream.
+    #[prost(uint64, tag = "10")]
+    pub overflowed: u64,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetLogInfoRequest {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetRangeRequest {
+    #[prost(uint64, tag = "1")]
+    pub from_seq: u64,
+    /// Inclusive; unset reads to the end of the log.
+    #[prost(uint64, optional, tag = "2")]
+    pub to_seq: ::core::option::Option<u64>,
+    /// Most events returned. 0, or anything above the server's cap, means
+    /// the cap.
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRangeResponse {
+    /// In sequence order, after the server's plugin if it has one.
+    #[prost(message, repeated, tag = "1")]
+    pub events: ::prost::alloc::vec::Vec<EventMessage>,
+    /// Where the next page starts; unset once the range is exhausted.
+    #[prost(uint64, optional, tag = "2")]
+    pub next_seq: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SymbolCounts {
+    #[prost(string, tag = "1")]
+    pub symbol: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub trades: u64,
+    #[prost(uint64, tag = "3")]
+    pub quotes: u64,
+}
+/// Describes the log as stored, before any plugin or session filter. A
+/// stitched replay reports the first day's header and the days' combined
+/// counts, with sequences numbered as they are streamed.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogInfo {
+    /// File format version; 0 for events served from memory.
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint64, tag = "2")]
+    pub schema_hash: u64,
+    #[prost(string, tag = "3")]
+    pub schema: ::prost::alloc::string::String,
+    /// The header's symbol table, in order. Stitched days add the symbols
+    /// the first day lacks as they appear.
+    #[prost(string, repeated, tag = "4")]
+    pub symbols: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "5")]
+    pub record_count: u64,
+    /// Unset when there are no records.
+    #[prost(int64, optional, tag = "6")]
+    pub first_ns: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag = "7")]
+    pub last_ns: ::core::option::Option<i64>,
+    #[prost(uint64, tag = "8")]
+    pub first_sequence: u64,
+    #[prost(uint64, tag = "9")]
+    pub last_sequence: u64,
+    /// Records per symbol, by symbol.
+    #[prost(message, repeated, tag = "10")]
+    pub counts: ::prost::alloc::vec::Vec<SymbolCounts>,
+    /// File name of each log, one per stitched day.
+    #[prost(string, repeated, tag = "11")]
+    pub logs: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Decimal price of one price tick, by symbol, as ingest recorded it.
+    /// Stitched or merged logs must agree on a symbol's unit. Empty for logs
+    /// written before file version 3 and for events served from memory.
+    #[prost(map = "string, string", tag = "12")]
+    pub price_units: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscriptionCommand {
+    #[prost(uint64, tag = "1")]
+    pub subscription_id: u64,
+    #[prost(oneof = "subscription_command::Command", tags = "2, 3")]
+    pub command: ::core::option::Option<subscription_command::Command>,
+}
+/// Nested message and enum types in `SubscriptionCommand`.
+pub mod subscription_command {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Command {
+        #[prost(message, tag = "2")]
+        Subscribe(super::StreamRequest),
+        #[prost(bool, tag = "3")]
+        Unsubscribe(bool),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscriptionEnd {
+    #[prost(string, tag = "1")]
+    pub reason: ::prost::alloc::string::String,
+    /// Sequences the subscription lost to simulated drops, in runs.
+    #[prost(message, repeated, tag = "2")]
+    pub dropped: ::prost::alloc::vec::Vec<SequenceRange>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MultiplexMessage {
+    #[prost(uint64, tag = "1")]
+    pub subscription_id: u64,
+    #[prost(oneof = "multiplex_message::Body", tags = "2, 3")]
+    pub body: ::core::option::Option<multiplex_message::Body>,
+}
+/// Nested message and enum types in `MultiplexMessage`.
+pub mod multiplex_message {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Body {
+        #[prost(message, tag = "2")]
+        Event(super::EventMessage),
+        #[prost(message, tag = "3")]
+        End(super::SubscriptionEnd),
+    }
+}
+/// Steers a `Control` replay. The first command must be `start`; the rest
+/// take effect from the next event on.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ControlCommand {
+    #[prost(oneof = "control_command::Command", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub command: ::core::option::Option<control_command::Command>,
+}
+/// Nested message and enum types in `ControlCommand`.
+pub mod control_command {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Command {
+        #[prost(message, tag = "1")]
+        Start(super::StreamRequest),
+        #[prost(bool, tag = "2")]
+        Pause(bool),
+        #[prost(bool, tag = "3")]
+        Resume(bool),
+        /// New speed multiplier, which also ends max-speed replay. Ignored
+        /// unless positive.
+        #[prost(double, tag = "4")]
+        Speed(f64),
+        /// Replay from the first event at or after this time.
+        #[prost(int64, tag = "5")]
+        SeekNs(i64),
+        /// Replay from the first event at or after this sequence.
+        #[prost(uint64, tag = "6")]
+        SeekSequence(u64),
+        /// Releases more events of a step-mode replay.
+        #[prost(message, tag = "7")]
+        Step(super::Step),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Step {
+    /// Events to release; 0 counts as 1.
+    #[prost(uint32, tag = "1")]
+    pub count: u32,
+    /// Count timestamps instead, releasing every event stamped at each.
+    #[prost(bool, tag = "2")]
+    pub by_timestamp: bool,
+}
+/// Prices are counts of the symbol's tick and sizes counts of its lot (one
+/// unit unless the tick config sets `lots`), as stored in the log.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Trade {
+    #[prost(sint64, tag = "1")]
+    pub price_ticks: i64,
+    #[prost(sint64, tag = "2")]
+    pub size: i64,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Quote {
+    #[prost(sint64, tag = "1")]
+    pub bid_px: i64,
+    #[prost(sint64, tag = "2")]
+    pub bid_sz: i64,
+    #[prost(sint64, tag = "3")]
+    pub ask_px: i64,
+    #[prost(sint64, tag = "4")]
+    pub ask_sz: i64,
+}
+/// Where a stream has got to. Every event before it has been sent.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Checkpoint {
+    /// Opaque; a `StreamRequest` carrying it continues from here.
+    #[prost(string, tag = "1")]
+    pub resume_token: ::prost::alloc::string::String,
+}
+/// How far a drop-oldest stream has fallen behind its client. Sent ahead
+/// of the first message after new discards, and before the stream ends.
+/// The enclosing message carries sequence 0 and no venue or symbol.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct StreamStats {
+    /// Messages discarded so far because the client read too slowly.
+    #[prost(uint64, tag = "1")]
+    pub overflowed: u64,
+}
+/// Sent ahead of each day's events when the server stitches daily logs
+/// into one replay. The enclosing message carries the day's first
+/// timestamp, sequence 0 and no venue or symbol.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DayBoundary {
+    /// Position of the day among the stitched logs, from 0.
+    #[prost(uint32, tag = "1")]
+    pub day: u32,
+    /// File name of the day's log.
+    #[prost(string, tag = "2")]
+    pub log: ::prost::alloc::string::String,
+}
+/// The symbol's book after the event, from the same feature engine the
+/// clients use. Prices are in ticks.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Features {
+    /// Mid of the quote, or the trade price while a side is empty.
+    #[prost(double, tag = "1")]
+    pub mid: f64,
+    /// Average of the last few mids.
+    #[prost(double, tag = "2")]
+    pub rolling_mid: f64,
+    /// Zero while a side is empty.
+    #[prost(sint64, tag = "3")]
+    pub spread: i64,
+    /// (bid_sz - ask_sz) / (bid_sz + ask_sz), from -1 to 1.
+    #[prost(double, tag = "4")]
+    pub imbalance: f64,
+    /// EWMA volatility of mid log returns.
+    #[prost(double, tag = "5")]
+    pub vol: f64,
+}
+/// Every event of one closing cross, for streams that ask for batches. The
+/// enclosing message carries the cross timestamp, the first event's
+/// sequence and no venue or symbol.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClosingCross {
+    #[prost(message, repeated, tag = "1")]
+    pub events: ::prost::alloc::vec::Vec<EventMessage>,
+}
+/// Events sent as one message, for streams with a `max_batch`. The
+/// enclosing message carries the first event's timestamp and sequence and
+/// no venue or symbol. Closing crosses and day markers are never batched.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventBatch {
+    #[prost(message, repeated, tag = "1")]
+    pub events: ::prost::alloc::vec::Vec<EventMessage>,
+    #[prost(int64, tag = "2")]
+    pub first_ns: i64,
+    #[prost(int64, tag = "3")]
+    pub last_ns: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventMessage {
+    #[prost(int64, tag = "1")]
+    pub timestamp_ns: i64,
+    #[prost(uint64, tag = "2")]
+    pub sequence: u64,
+    #[prost(string, tag = "3")]
+    pub venue: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub symbol: ::prost::alloc::string::String,
+    /// Set on trades and quotes of an enriched stream.
+    #[prost(message, optional, tag = "8")]
+    pub features: ::core::option::Option<Features>,
+    /// The feed's own sequence number, when the source had one.
+    #[prost(uint64, optional, tag = "9")]
+    pub source_sequence: ::core::option::Option<u64>,
+    /// The venue's channel, when the request asked for venue channels.
+    #[prost(uint32, optional, tag = "11")]
+    pub channel_id: ::core::option::Option<u32>,
+    #[prost(oneof = "event_message::Payload", tags = "5, 6, 7, 10, 12, 13, 14")]
+    pub payload: ::core::option::Option<event_message::Payload>,
+}
+/// Nested message and enum types in `EventMessage`.
+pub mod event_message {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Payload {
+        #[prost(message, tag = "5")]
+        Trade(super::Trade),
+        #[prost(message, tag = "6")]
+        Quote(super::Quote),
+        #[prost(message, tag = "7")]
+        DayBoundary(super::DayBoundary),
+        #[prost(message, tag = "10")]
+        ClosingCross(super::ClosingCross),
+        #[prost(message, tag = "12")]
+        Batch(super::EventBatch),
+        #[prost(message, tag = "13")]
+        Checkpoint(super::Checkpoint),
+        #[prost(message, tag = "14")]
+        Stats(super::StreamStats),
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Backpressure {
+    /// The server's own strategy.
+    Server = 0,
+    /// Pacing waits for the client, so the replay falls behind with it.
+    Block = 1,
+    /// Pacing goes on and the oldest queued message is discarded. Discards
+    /// are counted in `SessionInfo.overflowed`.
+    DropOldest = 2,
+}
+impl Backpressure {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Server => "BACKPRESSURE_SERVER",
+            Self::Block => "BACKPRESSURE_BLOCK",
+            Self::DropOldest => "BACKPRESSURE_DROP_OLDEST",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "BACKPRESSURE_SERVER" => Some(Self::Server),
+            "BACKPRESSURE_BLOCK" => Some(Self::Block),
+            "BACKPRESSURE_DROP_OLDEST" => Some(Self::DropOldest),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum FeedLine {
+    A = 0,
+    B = 1,
+}
+impl FeedLine {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::A => "FEED_LINE_A",
+            Self::B => "FEED_LINE_B",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "FEED_LINE_A" => Some(Self::A),
+            "FEED_LINE_B" => Some(Self::B),
+            _ => None,
+        }
+    }
+}
+/// Generated client implementations.
+pub mod replay_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct ReplayServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl ReplayServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> ReplayServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> ReplayServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            ReplayServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn stream_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StreamRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::EventMessage>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/StreamEvents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "StreamEvents"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn multiplex(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::SubscriptionCommand,
+            >,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::MultiplexMessage>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/Multiplex",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "Multiplex"));
+            self.inner.streaming(req, path, codec).await
+        }
+        pub async fn control(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::ControlCommand>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::EventMessage>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/Control",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "Control"));
+            self.inner.streaming(req, path, codec).await
+        }
+        /// A `StreamEvents` replay registered under a name, so it shows up in
+        /// `ListSessions` and another connection can `CloseSession` it.
+        pub async fn create_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::EventMessage>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/CreateSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "CreateSession"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn list_sessions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListSessionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSessionsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/ListSessions",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "ListSessions"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Ends a named session opened with the same token; its stream ends
+        /// with CANCELLED.
+        pub async fn close_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CloseSessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::SessionInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/CloseSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "CloseSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// What the server replays: header, symbols, time bounds and counts.
+        pub async fn get_log_info(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetLogInfoRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/GetLogInfo",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "GetLogInfo"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// A page of events by sequence, at once and unpaced, for tools that
+        /// jump around the log instead of following it.
+        pub async fn get_range(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetRangeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetRangeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/GetRange",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "GetRange"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// One line of an A/B feed pair. Both lines replay the same events,
+        /// starting together once both have connected, each with its own loss
+        /// and jitter, so a client can practise arbitrating between them.
+        pub async fn stream_feed_line(
+            &mut self,
+            request: impl tonic::IntoRequest<super::FeedLineRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::EventMessage>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/replay.ReplayService/StreamFeedLine",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("replay.ReplayService", "StreamFeedLine"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod replay_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with ReplayServiceServer.
+    #[async_trait]
+    pub trait ReplayService: std::marker::Send + std::marker::Sync + 'static {
+        /// Server streaming response type for the StreamEvents method.
+        type StreamEventsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::EventMessage, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn stream_events(
+            &self,
+            request: tonic::Request<super::StreamRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamEventsStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the Multiplex method.
+        type MultiplexStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::MultiplexMessage, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn multiplex(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SubscriptionCommand>>,
+        ) -> std::result::Result<tonic::Response<Self::MultiplexStream>, tonic::Status>;
+        /// Server streaming response type for the Control method.
+        type ControlStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::EventMessage, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn control(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::ControlCommand>>,
+        ) -> std::result::Result<tonic::Response<Self::ControlStream>, tonic::Status>;
+        /// Server streaming response type for the CreateSession method.
+        type CreateSessionStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::EventMessage, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// A `StreamEvents` replay registered under a name, so it shows up in
+        /// `ListSessions` and another connection can `CloseSession` it.
+        async fn create_session(
+            &self,
+            request: tonic::Request<super::CreateSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::CreateSessionStream>,
+            tonic::Status,
+        >;
+        async fn list_sessions(
+            &self,
+            request: tonic::Request<super::ListSessionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSessionsResponse>,
+            tonic::Status,
+        >;
+        /// Ends a named session opened with the same token; its stream ends
+        /// with CANCELLED.
+        async fn close_session(
+            &self,
+            request: tonic::Request<super::CloseSessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::SessionInfo>, tonic::Status>;
+        /// What the server replays: header, symbols, time bounds and counts.
+        async fn get_log_info(
+            &self,
+            request: tonic::Request<super::GetLogInfoRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogInfo>, tonic::Status>;
+        /// A page of events by sequence, at once and unpaced, for tools that
+        /// jump around the log instead of following it.
+        async fn get_range(
+            &self,
+            request: tonic::Request<super::GetRangeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetRangeResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the StreamFeedLine method.
+        type StreamFeedLineStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::EventMessage, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// One line of an A/B feed pair. Both lines replay the same events,
+        /// starting together once both have connected, each with its own loss
+        /// and jitter, so a client can practise arbitrating between them.
+        async fn stream_feed_line(
+            &self,
+            request: tonic::Request<super::FeedLineRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamFeedLineStream>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct ReplayServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> ReplayServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for ReplayServiceServer<T>
+    where
+        T: ReplayService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/replay.ReplayService/StreamEvents" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamEventsSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::ServerStreamingService<super::StreamRequest>
+                    for StreamEventsSvc<T> {
+                        type Response = super::EventMessage;
+                        type ResponseStream = T::StreamEventsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StreamRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::stream_events(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamEventsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/Multiplex" => {
+                    #[allow(non_camel_case_types)]
+                    struct MultiplexSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::StreamingService<super::SubscriptionCommand>
+                    for MultiplexSvc<T> {
+                        type Response = super::MultiplexMessage;
+                        type ResponseStream = T::MultiplexStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::SubscriptionCommand>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::multiplex(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = MultiplexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/Control" => {
+                    #[allow(non_camel_case_types)]
+                    struct ControlSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::StreamingService<super::ControlCommand>
+                    for ControlSvc<T> {
+                        type Response = super::EventMessage;
+                        type ResponseStream = T::ControlStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::ControlCommand>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::control(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ControlSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/CreateSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreateSessionSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::ServerStreamingService<super::CreateSessionRequest>
+                    for CreateSessionSvc<T> {
+                        type Response = super::EventMessage;
+                        type ResponseStream = T::CreateSessionStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::create_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreateSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/ListSessions" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListSessionsSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::UnaryService<super::ListSessionsRequest>
+                    for ListSessionsSvc<T> {
+                        type Response = super::ListSessionsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListSessionsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::list_sessions(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListSessionsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/CloseSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct CloseSessionSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::UnaryService<super::CloseSessionRequest>
+                    for CloseSessionSvc<T> {
+                        type Response = super::SessionInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CloseSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::close_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CloseSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/GetLogInfo" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetLogInfoSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::UnaryService<super::GetLogInfoRequest>
+                    for GetLogInfoSvc<T> {
+                        type Response = super::LogInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetLogInfoRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::get_log_info(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetLogInfoSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/GetRange" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetRangeSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::UnaryService<super::GetRangeRequest>
+                    for GetRangeSvc<T> {
+                        type Response = super::GetRangeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetRangeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::get_range(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetRangeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/replay.ReplayService/StreamFeedLine" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamFeedLineSvc<T: ReplayService>(pub Arc<T>);
+                    impl<
+                        T: ReplayService,
+                    > tonic::server::ServerStreamingService<super::FeedLineRequest>
+                    for StreamFeedLineSvc<T> {
+                        type Response = super::EventMessage;
+                        type ResponseStream = T::StreamFeedLineStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::FeedLineRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as ReplayService>::stream_feed_line(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamFeedLineSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for ReplayServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "replay.ReplayService";
+    impl<T> tonic::server::NamedService for ReplayServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -0,0 +1,171 @@
+//! Event transform plugins: WebAssembly core modules run with wasmtime.
+//!
+//! A plugin exports `memory`, `alloc(len: i32) -> i32` and
+//! `transform(ptr: i32, len: i32) -> i64`. For each event the host asks
+//! `alloc` for `len` bytes, writes the event there as JSON (the `export
+//! --format jsonl` object) and calls `transform`, which returns where its
+//! output is as `ptr << 32 | len`. The output is a JSON array of events:
+//! empty to drop the event, one element to rewrite it, more to add events.
+//! The host never frees; plugins usually reuse one buffer per call.
+
+use crate::engine::ReplayError;
+use md_core::Event;
+use std::path::Path;
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+fn plugin_error(err: impl std::fmt::Display) -> ReplayError {
+    ReplayError::Plugin(err.to_string())
+}
+
+/// A compiled plugin, shared by every replay session that uses it.
+#[derive(Clone)]
+pub struct WasmPlugin {
+    engine: Engine,
+    module: Module,
+}
+
+impl WasmPlugin {
+    /// Loads a `.wasm` binary (or `.wat` text).
+    pub fn from_file(path: &Path) -> Result<Self, ReplayError> {
+        let bytes = std::fs::read(path)
+            .map_err(|err| ReplayError::Plugin(format!("reading {}: {err}", path.display())))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, bytes).map_err(|err| plugin_error(format!("{err:#}")))?;
+        Ok(Self { engine, module })
+    }
+
+    /// A fresh instance with its own memory.
+    pub fn instantiate(&self) -> Result<WasmTransform, ReplayError> {
+        let mut store = Store::new(&self.engine, ());
+        let instance = Instance::new(&mut store, &self.module, &[])
+            .map_err(|err| plugin_error(format!("{err:#}")))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| plugin_error("plugin does not export memory"))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(|err| plugin_error(format!("alloc: {err:#}")))?;
+        let transform = instance
+            .get_typed_func(&mut store, "transform")
+            .map_err(|err| plugin_error(format!("transform: {err:#}")))?;
+        Ok(WasmTransform {
+            store,
+            memory,
+            alloc,
+            transform,
+        })
+    }
+
+    /// Runs `events` through one fresh instance, keeping their order and
+    /// the order of each call's output.
+    pub fn apply(&self, events: Vec<Event>) -> Result<Vec<Event>, ReplayError> {
+        let mut instance = self.instantiate()?;
+        let mut out = Vec::with_capacity(events.len());
+        for event in &events {
+            out.extend(instance.transform(event)?);
+        }
+        Ok(out)
+    }
+}
+
+pub struct WasmTransform {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    transform: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmTransform {
+    pub fn transform(&mut self, event: &Event) -> Result<Vec<Event>, ReplayError> {
+        let input = serde_json::to_vec(event).map_err(plugin_error)?;
+        let len = i32::try_from(input.len()).map_err(plugin_error)?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|err| plugin_error(format!("alloc: {err:#}")))?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, &input)
+            .map_err(|err| plugin_error(format!("alloc returned {ptr}: {err}")))?;
+        let packed = self
+            .transform
+            .call(&mut self.store, (ptr, len))
+            .map_err(|err| plugin_error(format!("transform: {err:#}")))?;
+
+        let out_ptr = (packed as u64 >> 32) as usize;
+        let out_len = packed as u32 as usize;
+        let output = self
+            .memory
+            .data(&self.store)
+            .get(out_ptr..out_ptr + out_len)
+            .ok_or_else(|| plugin_error(format!("output {out_ptr}+{out_len} out of bounds")))?;
+        serde_json::from_slice(output)
+            .map_err(|err| plugin_error(format!("event {}: {err}", event.sequence)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps the input in `[` and `]`, i.e. passes the event through.
+    const IDENTITY: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1025))
+          (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+            (i32.store8 (i32.sub (local.get $ptr) (i32.const 1)) (i32.const 91))
+            (i32.store8 (i32.add (local.get $ptr) (local.get $len)) (i32.const 93))
+            (i64.or
+              (i64.shl
+                (i64.extend_i32_u (i32.sub (local.get $ptr) (i32.const 1)))
+                (i64.const 32))
+              (i64.extend_i32_u (i32.add (local.get $len) (i32.const 2))))))
+    "#;
+
+    /// Answers `[]` to everything.
+    const DROP_ALL: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 16) "[]")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "transform") (param i32 i32) (result i64)
+            (i64.const 0x1000000002)))
+    "#;
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::trade(1, 1, "X", "AAPL", 100, 2),
+            Event::trade(2, 2, "X", "MSFT", 300, 1),
+        ]
+    }
+
+    #[test]
+    fn identity_plugin_passes_events_through() {
+        let plugin = WasmPlugin::from_bytes(IDENTITY.as_bytes()).expect("compile");
+        assert_eq!(plugin.apply(events()).expect("apply"), events());
+    }
+
+    #[test]
+    fn plugins_can_drop_events() {
+        let plugin = WasmPlugin::from_bytes(DROP_ALL.as_bytes()).expect("compile");
+        assert!(plugin.apply(events()).expect("apply").is_empty());
+    }
+
+    #[test]
+    fn bad_plugins_are_reported() {
+        let missing = WasmPlugin::from_bytes(br#"(module (memory (export "memory") 1))"#)
+            .expect("compile")
+            .instantiate();
+        assert!(matches!(missing, Err(ReplayError::Plugin(msg)) if msg.contains("alloc")));
+
+        let garbage = IDENTITY.replace("(i32.const 91)", "(i32.const 0)");
+        let plugin = WasmPlugin::from_bytes(garbage.as_bytes()).expect("compile");
+        let err = plugin.apply(events()).expect_err("invalid output");
+        assert!(err.to_string().contains("event 1"), "{err}");
+        assert_eq!(err.kind(), md_core::ErrorKind::Config);
+    }
+}
@@ -0,0 +1,22 @@
+use crate::engine::ReplayError;
+use md_core::Event;
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct WasmPlugin {
+    _private: (),
+}
+
+impl WasmPlugin {
+    pub fn from_file(_path: &Path) -> Result<Self, ReplayError> {
+        Err(ReplayError::PluginUnavailable)
+    }
+
+    pub fn from_bytes(_bytes: &[u8]) -> Result<Self, ReplayError> {
+        Err(ReplayError::PluginUnavailable)
+    }
+
+    pub fn apply(&self, events: Vec<Event>) -> Result<Vec<Event>, ReplayError> {
+        Ok(events)
+    }
+}
@@ -0,0 +1,149 @@
+//! Message buses a replay can be published to instead of served, one
+//! message per event in the encoding gRPC clients receive.
+
+mod nats;
+mod redis;
+mod zmq;
+
+pub use nats::{NatsOptions, NatsSink};
+pub use redis::{RedisOptions, RedisSink};
+pub use zmq::{ZmqOptions, ZmqSink};
+
+use crate::engine::{stream_days, ReplayConfig, ReplayError};
+use crate::pb::{self, event_message::Payload};
+use crate::source::{LogSource, SourceEvents};
+use crate::WasmPlugin;
+use std::future::Future;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SinkError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid sink address {0}")]
+    Address(String),
+    #[error("unexpected reply from {0}")]
+    Protocol(String),
+    #[error("publish rejected: {0}")]
+    Rejected(String),
+    #[error("replay stream failed: {0}")]
+    Stream(String),
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+}
+
+/// A bus that paced events are published to.
+pub trait Sink {
+    /// Publishes one trade or quote, keyed by its symbol.
+    fn publish(
+        &mut self,
+        message: &pb::EventMessage,
+    ) -> impl Future<Output = Result<(), SinkError>>;
+
+    /// Waits until everything published so far has been accepted.
+    fn flush(&mut self) -> impl Future<Output = Result<(), SinkError>>;
+}
+
+/// Replays `source` once, paced by `config`, into `sink`, and returns how
+/// many events were published. Batches and closing crosses go out event
+/// by event; day markers are not published.
+pub async fn publish<S: Sink>(
+    source: &LogSource,
+    config: ReplayConfig,
+    plugin: Option<&WasmPlugin>,
+    sink: &mut S,
+) -> Result<u64, SinkError> {
+    let SourceEvents { mut events, days } = source.read(config.from_ns, config.to_ns)?;
+    if let Some(plugin) = plugin {
+        events = plugin.apply(events)?;
+    }
+    let (tx, mut rx) = mpsc::channel(1024);
+    tokio::spawn(stream_days(events, days, config, 1, tx));
+    let mut published = 0u64;
+    while let Some(message) = rx.recv().await {
+        let message = message.map_err(|status| SinkError::Stream(status.message().into()))?;
+        for event in flatten(message) {
+            sink.publish(&event).await?;
+            published += 1;
+        }
+    }
+    sink.flush().await?;
+    Ok(published)
+}
+
+fn flatten(message: pb::EventMessage) -> Vec<pb::EventMessage> {
+    match message.payload {
+        Some(Payload::Batch(batch)) => batch.events,
+        Some(Payload::ClosingCross(cross)) => cross.events,
+        Some(Payload::DayBoundary(_) | Payload::Checkpoint(_) | Payload::Stats(_)) | None => {
+            Vec::new()
+        }
+        Some(Payload::Trade(_) | Payload::Quote(_)) => vec![message],
+    }
+}
+
+/// `symbol` as one token of a dotted subject or key: separators,
+/// wildcards and whitespace become `_`.
+pub(crate) fn subject_token(symbol: &str) -> String {
+    if symbol.is_empty() {
+        return String::from("_");
+    }
+    symbol
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::Event;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Recorder {
+        sequences: Vec<u64>,
+        flushed: bool,
+    }
+
+    impl Sink for Recorder {
+        async fn publish(&mut self, message: &pb::EventMessage) -> Result<(), SinkError> {
+            self.sequences.push(message.sequence);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<(), SinkError> {
+            self.flushed = true;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_are_published_event_by_event() {
+        let source = LogSource::Events(Arc::from(vec![
+            Event::trade(100, 1, "X", "AAPL", 10, 1),
+            Event::trade(100, 2, "X", "MSFT", 20, 1),
+            Event::trade(200, 3, "X", "AAPL", 11, 1),
+        ]));
+        let config = ReplayConfig {
+            max_speed: true,
+            max_batch: 2,
+            ..ReplayConfig::default()
+        };
+        let mut sink = Recorder::default();
+        let published = publish(&source, config, None, &mut sink)
+            .await
+            .expect("publish");
+        assert_eq!(published, 3);
+        assert_eq!(sink.sequences, [1, 2, 3]);
+        assert!(sink.flushed);
+        assert_eq!(subject_token("BRK.B"), "BRK_B");
+        assert_eq!(subject_token(""), "_");
+    }
+}
@@ -0,0 +1,485 @@
+//! NATS publisher speaking the core text protocol, with JetStream acks
+//! when events should persist.
+
+use super::{subject_token, Sink, SinkError};
+use crate::pb;
+use prost::Message;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+use tracing::debug;
+
+const DEFAULT_PORT: u16 = 4222;
+/// JetStream acks a publisher may be waiting on at once.
+const ACK_WINDOW: u64 = 256;
+/// Longest wait for the server to answer a ping, request or publish.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// JetStream's error code for a stream name that is already taken.
+const STREAM_NAME_IN_USE: u64 = 10058;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NatsOptions {
+    /// Events go to `<prefix>.<symbol>`.
+    pub prefix: String,
+    /// Publishes into this JetStream stream and waits for its acks. The
+    /// stream is created over `<prefix>.>` unless it exists.
+    pub jetstream: Option<String>,
+}
+
+impl Default for NatsOptions {
+    fn default() -> Self {
+        Self {
+            prefix: String::from("md"),
+            jetstream: None,
+        }
+    }
+}
+
+/// `nats://[user:password@|token@]host[:port]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NatsAddress {
+    host: String,
+    port: u16,
+    user: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+}
+
+impl NatsAddress {
+    fn parse(url: &str) -> Result<Self, SinkError> {
+        let invalid = || SinkError::Address(url.to_string());
+        let rest = url.strip_prefix("nats://").unwrap_or(url);
+        let (auth, rest) = match rest.rsplit_once('@') {
+            Some((auth, rest)) => (Some(auth), rest),
+            None => (None, rest),
+        };
+        let rest = rest.trim_end_matches('/');
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (rest, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let mut address = Self {
+            host: host.to_string(),
+            port,
+            user: None,
+            password: None,
+            token: None,
+        };
+        match auth.map(|auth| auth.split_once(':')) {
+            Some(Some((user, password))) => {
+                address.user = Some(user.to_string());
+                address.password = Some(password.to_string());
+            }
+            Some(None) => address.token = auth.map(String::from),
+            None => {}
+        }
+        Ok(address)
+    }
+}
+
+/// What the server sent, as the publisher needs it.
+enum Reply {
+    Pong,
+    /// Payload of a message on the publisher's inbox.
+    Msg(Vec<u8>),
+    Err(String),
+}
+
+pub struct NatsSink {
+    writer: Arc<Mutex<BufWriter<OwnedWriteHalf>>>,
+    replies: mpsc::UnboundedReceiver<Reply>,
+    options: NatsOptions,
+    /// Replies to JetStream publishes come back under `<inbox>.<n>`.
+    inbox: String,
+    requests: u64,
+    /// Publishes not yet acked.
+    pending: u64,
+}
+
+impl NatsSink {
+    /// Connects to the server at `url` and, with JetStream, makes sure the
+    /// stream exists.
+    pub async fn connect(url: &str, options: NatsOptions) -> Result<Self, SinkError> {
+        let address = NatsAddress::parse(url)?;
+        let stream = TcpStream::connect((address.host.as_str(), address.port)).await?;
+        stream.set_nodelay(true)?;
+        let (read, write) = stream.into_split();
+        let mut read = BufReader::new(read);
+        let mut line = String::new();
+        read.read_line(&mut line).await?;
+        let info = line
+            .strip_prefix("INFO ")
+            .and_then(|info| serde_json::from_str::<Value>(info).ok())
+            .ok_or_else(|| protocol(&line))?;
+        if info["tls_required"] == true {
+            return Err(SinkError::Address(format!("{url} requires tls")));
+        }
+        let mut connect = json!({
+            "verbose": false,
+            "pedantic": false,
+            "lang": "rust",
+            "name": "md-replay",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": 1,
+        });
+        for (key, value) in [
+            ("user", address.user),
+            ("pass", address.password),
+            ("auth_token", address.token),
+        ] {
+            if let Some(value) = value {
+                connect[key] = Value::String(value);
+            }
+        }
+
+        let writer = Arc::new(Mutex::new(BufWriter::new(write)));
+        let (tx, replies) = mpsc::unbounded_channel();
+        tokio::spawn(read_replies(read, Arc::clone(&writer), tx));
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut sink = Self {
+            writer,
+            replies,
+            options,
+            inbox: format!("_INBOX.md-replay.{}.{nanos}", std::process::id()),
+            requests: 0,
+            pending: 0,
+        };
+        sink.write(&format!("CONNECT {connect}\r\n"), None).await?;
+        sink.ping().await?;
+        if let Some(stream) = sink.options.jetstream.clone() {
+            sink.create_stream(&stream).await?;
+        }
+        Ok(sink)
+    }
+
+    async fn create_stream(&mut self, stream: &str) -> Result<(), SinkError> {
+        self.write(&format!("SUB {}.* 1\r\n", self.inbox), None)
+            .await?;
+        let config = json!({
+            "name": stream,
+            "subjects": [format!("{}.>", self.options.prefix)],
+        });
+        self.requests += 1;
+        let payload = config.to_string();
+        let header = format!(
+            "PUB $JS.API.STREAM.CREATE.{stream} {}.{} {}\r\n",
+            self.inbox,
+            self.requests,
+            payload.len()
+        );
+        self.write(&header, Some(payload.as_bytes())).await?;
+        let reply = loop {
+            if let Reply::Msg(payload) = self.next_reply().await? {
+                break payload;
+            }
+        };
+        match jetstream_error(&reply) {
+            Some((Some(STREAM_NAME_IN_USE), _)) => {
+                debug!(stream, "jetstream stream exists");
+                Ok(())
+            }
+            Some((_, description)) => Err(SinkError::Rejected(description)),
+            None => Ok(()),
+        }
+    }
+
+    /// Sends `command`, followed by `payload` for a publish.
+    async fn write(&self, command: &str, payload: Option<&[u8]>) -> Result<(), SinkError> {
+        let mut writer = self.writer.lock().await;
+        writer.write_all(command.as_bytes()).await?;
+        if let Some(payload) = payload {
+            writer.write_all(payload).await?;
+            writer.write_all(b"\r\n").await?;
+        }
+        Ok(writer.flush().await?)
+    }
+
+    /// Round trip to the server; any error it reports for earlier
+    /// commands arrives first.
+    async fn ping(&mut self) -> Result<(), SinkError> {
+        self.write("PING\r\n", None).await?;
+        loop {
+            match self.next_reply().await? {
+                Reply::Pong => return Ok(()),
+                Reply::Msg(payload) => self.acked(&payload)?,
+                Reply::Err(err) => return Err(SinkError::Rejected(err)),
+            }
+        }
+    }
+
+    async fn next_reply(&mut self) -> Result<Reply, SinkError> {
+        match tokio::time::timeout(REPLY_TIMEOUT, self.replies.recv()).await {
+            Ok(Some(reply)) => Ok(reply),
+            Ok(None) => Err(protocol("connection closed")),
+            Err(_) => Err(protocol("no reply; is the subject bound to a stream?")),
+        }
+    }
+
+    fn acked(&mut self, payload: &[u8]) -> Result<(), SinkError> {
+        self.pending = self.pending.saturating_sub(1);
+        match jetstream_error(payload) {
+            Some((_, description)) => Err(SinkError::Rejected(description)),
+            None => Ok(()),
+        }
+    }
+
+    /// Handles whatever the server has already sent, without waiting.
+    fn drain(&mut self) -> Result<(), SinkError> {
+        loop {
+            match self.replies.try_recv() {
+                Ok(Reply::Pong) => {}
+                Ok(Reply::Msg(payload)) => self.acked(&payload)?,
+                Ok(Reply::Err(err)) => return Err(SinkError::Rejected(err)),
+                Err(mpsc::error::TryRecvError::Empty) => return Ok(()),
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    return Err(protocol("connection closed"))
+                }
+            }
+        }
+    }
+}
+
+impl Sink for NatsSink {
+    async fn publish(&mut self, message: &pb::EventMessage) -> Result<(), SinkError> {
+        self.drain()?;
+        let subject = format!("{}.{}", self.options.prefix, subject_token(&message.symbol));
+        let payload = message.encode_to_vec();
+        let header = if self.options.jetstream.is_some() {
+            self.requests += 1;
+            self.pending += 1;
+            format!(
+                "PUB {subject} {}.{} {}\r\n",
+                self.inbox,
+                self.requests,
+                payload.len()
+            )
+        } else {
+            format!("PUB {subject} {}\r\n", payload.len())
+        };
+        self.write(&header, Some(&payload)).await?;
+        while self.pending >= ACK_WINDOW {
+            if let Reply::Msg(payload) = self.next_reply().await? {
+                self.acked(&payload)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        while self.pending > 0 {
+            match self.next_reply().await? {
+                Reply::Pong => {}
+                Reply::Msg(payload) => self.acked(&payload)?,
+                Reply::Err(err) => return Err(SinkError::Rejected(err)),
+            }
+        }
+        self.ping().await
+    }
+}
+
+/// Answers the server's pings and forwards everything else the publisher
+/// waits on, until the connection closes.
+async fn read_replies(
+    mut read: BufReader<OwnedReadHalf>,
+    writer: Arc<Mutex<BufWriter<OwnedWriteHalf>>>,
+    tx: mpsc::UnboundedSender<Reply>,
+) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match read.read_line(&mut line).await {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                debug!(error = %err, "nats connection failed");
+                break;
+            }
+        }
+        let line = line.trim_end();
+        let reply = if line == "PING" {
+            let mut writer = writer.lock().await;
+            let ponged = writer.write_all(b"PONG\r\n").await;
+            if ponged.and(writer.flush().await).is_err() {
+                break;
+            }
+            continue;
+        } else if line == "PONG" {
+            Reply::Pong
+        } else if let Some(err) = line.strip_prefix("-ERR ") {
+            Reply::Err(err.trim_matches('\'').to_string())
+        } else if let Some(args) = line.strip_prefix("MSG ") {
+            let Some(len) = args
+                .split_whitespace()
+                .last()
+                .and_then(|len| len.parse::<usize>().ok())
+            else {
+                break;
+            };
+            let mut payload = vec![0; len + 2];
+            if read.read_exact(&mut payload).await.is_err() {
+                break;
+            }
+            payload.truncate(len);
+            Reply::Msg(payload)
+        } else {
+            // `+OK` and updated `INFO`s need no answer.
+            continue;
+        };
+        if tx.send(reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// The error code and description of a JetStream API error reply.
+fn jetstream_error(payload: &[u8]) -> Option<(Option<u64>, String)> {
+    let reply = serde_json::from_slice::<Value>(payload).ok()?;
+    let error = reply.get("error")?;
+    let description = error["description"]
+        .as_str()
+        .unwrap_or("jetstream error")
+        .to_string();
+    Some((error["err_code"].as_u64(), description))
+}
+
+fn protocol(what: &str) -> SinkError {
+    SinkError::Protocol(format!("nats server: {}", what.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::to_proto;
+    use md_core::Event;
+    use tokio::net::TcpListener;
+
+    /// A server that records every publish as (subject, payload) and acks
+    /// JetStream publishes, rejecting those to `md.BAD`.
+    async fn fake_server() -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("nats://secret@{}", listener.local_addr().expect("addr"));
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut read = BufReader::new(read);
+                    write
+                        .write_all(b"INFO {\"server_id\":\"fake\"}\r\n")
+                        .await?;
+                    let mut line = String::new();
+                    let mut seq = 0;
+                    loop {
+                        line.clear();
+                        if read.read_line(&mut line).await? == 0 {
+                            return Ok::<_, std::io::Error>(());
+                        }
+                        let words = line.split_whitespace().collect::<Vec<_>>();
+                        match words.as_slice() {
+                            ["CONNECT", connect] => assert!(connect.contains("\"secret\"")),
+                            ["PING"] => write.write_all(b"PONG\r\n").await?,
+                            ["PUB", subject, rest @ ..] => {
+                                let len = rest.last().and_then(|n| n.parse().ok()).expect("len");
+                                let mut payload = vec![0; len + 2];
+                                read.read_exact(&mut payload).await?;
+                                payload.truncate(len);
+                                let reply = match (subject, rest) {
+                                    (_, [_]) => None,
+                                    (s, _) if s.starts_with("$JS.API.STREAM.CREATE.") => {
+                                        Some(String::from(
+                                            r#"{"error":{"err_code":10058,"description":"in use"}}"#,
+                                        ))
+                                    }
+                                    (&"md.BAD", _) => Some(String::from(
+                                        r#"{"error":{"err_code":10060,"description":"no stream"}}"#,
+                                    )),
+                                    _ => {
+                                        seq += 1;
+                                        Some(format!(r#"{{"stream":"MD","seq":{seq}}}"#))
+                                    }
+                                };
+                                if let (Some(ack), [inbox, _]) = (reply, rest) {
+                                    let msg = format!("MSG {inbox} 1 {}\r\n{ack}\r\n", ack.len());
+                                    write.write_all(msg.as_bytes()).await?;
+                                }
+                                let _ = tx.send((subject.to_string(), payload));
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn publishes_per_symbol_with_and_without_jetstream() {
+        let (url, mut published) = fake_server().await;
+        let events = [
+            Event::trade(1, 1, "X", "AAPL", 100, 5),
+            Event::trade(2, 2, "X", "BRK.B", 200, 1),
+        ];
+        let mut core = NatsSink::connect(&url, NatsOptions::default())
+            .await
+            .expect("connect");
+        for event in &events {
+            core.publish(&to_proto(event)).await.expect("publish");
+        }
+        core.flush().await.expect("flush");
+        let (subject, payload) = published.recv().await.expect("published");
+        assert_eq!(subject, "md.AAPL");
+        let decoded = pb::EventMessage::decode(payload.as_slice()).expect("decode");
+        assert_eq!(decoded, to_proto(&events[0]));
+        assert_eq!(published.recv().await.expect("published").0, "md.BRK_B");
+
+        let options = NatsOptions {
+            jetstream: Some(String::from("MD")),
+            ..NatsOptions::default()
+        };
+        let mut persisted = NatsSink::connect(&url, options.clone())
+            .await
+            .expect("connect");
+        let create = published.recv().await.expect("create").0;
+        assert_eq!(create, "$JS.API.STREAM.CREATE.MD");
+        for event in &events {
+            persisted.publish(&to_proto(event)).await.expect("publish");
+        }
+        persisted.flush().await.expect("acked");
+        assert_eq!(persisted.pending, 0);
+
+        let mut rejected = NatsSink::connect(&url, options).await.expect("connect");
+        let bad = Event::trade(3, 3, "X", "BAD", 1, 1);
+        rejected.publish(&to_proto(&bad)).await.expect("sent");
+        let err = rejected.flush().await.expect_err("rejected");
+        assert!(
+            matches!(err, SinkError::Rejected(ref d) if d == "no stream"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn parses_addresses() {
+        let address = NatsAddress::parse("nats://md:pw@bus.internal:4333").expect("parse");
+        assert_eq!(
+            (address.host.as_str(), address.port),
+            ("bus.internal", 4333)
+        );
+        assert_eq!(address.user.as_deref(), Some("md"));
+        assert_eq!(address.password.as_deref(), Some("pw"));
+        let bare = NatsAddress::parse("localhost").expect("parse");
+        assert_eq!((bare.port, bare.token), (DEFAULT_PORT, None));
+        assert!(NatsAddress::parse("nats://:4222").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Redis Streams publisher speaking RESP, with `XADD`s pipelined.
+
+use super::{Sink, SinkError};
+use crate::pb;
+use prost::Message;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+const DEFAULT_PORT: u16 = 6379;
+/// `XADD`s sent before their replies are read.
+const PIPELINE_DEPTH: u64 = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedisOptions {
+    /// Events go to the stream `<prefix>:<symbol>`.
+    pub prefix: String,
+    /// Trims each stream to about this many entries as it grows.
+    pub max_len: Option<u64>,
+}
+
+impl Default for RedisOptions {
+    fn default() -> Self {
+        Self {
+            prefix: String::from("md"),
+            max_len: None,
+        }
+    }
+}
+
+/// `redis://[[user]:password@]host[:port][/db]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RedisAddress {
+    host: String,
+    port: u16,
+    user: Option<String>,
+    password: Option<String>,
+    db: Option<u32>,
+}
+
+impl RedisAddress {
+    fn parse(url: &str) -> Result<Self, SinkError> {
+        let invalid = || SinkError::Address(url.to_string());
+        let rest = url.strip_prefix("redis://").unwrap_or(url);
+        let (auth, rest) = match rest.rsplit_once('@') {
+            Some((auth, rest)) => (Some(auth), rest),
+            None => (None, rest),
+        };
+        let (rest, db) = match rest.split_once('/') {
+            Some((rest, "")) => (rest, None),
+            Some((rest, db)) => (rest, Some(db.parse().map_err(|_| invalid())?)),
+            None => (rest, None),
+        };
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (rest, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let (user, password) = match auth.map(|auth| auth.split_once(':')) {
+            Some(Some((user, password))) => {
+                ((!user.is_empty()).then(|| user.to_string()), Some(password))
+            }
+            Some(None) => (None, auth),
+            None => (None, None),
+        };
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            user,
+            password: password.map(String::from),
+            db,
+        })
+    }
+}
+
+pub struct RedisSink {
+    read: BufReader<OwnedReadHalf>,
+    write: BufWriter<OwnedWriteHalf>,
+    options: RedisOptions,
+    /// Commands sent whose replies are still unread.
+    pending: u64,
+    line: String,
+}
+
+impl RedisSink {
+    /// Connects to the server at `url`, authenticating and selecting the
+    /// database it names.
+    pub async fn connect(url: &str, options: RedisOptions) -> Result<Self, SinkError> {
+        let address = RedisAddress::parse(url)?;
+        let stream = TcpStream::connect((address.host.as_str(), address.port)).await?;
+        stream.set_nodelay(true)?;
+        let (read, write) = stream.into_split();
+        let mut sink = Self {
+            read: BufReader::new(read),
+            write: BufWriter::new(write),
+            options,
+            pending: 0,
+            line: String::new(),
+        };
+        if let Some(password) = &address.password {
+            let auth: Vec<&[u8]> = match &address.user {
+                Some(user) => vec![b"AUTH", user.as_bytes(), password.as_bytes()],
+                None => vec![b"AUTH", password.as_bytes()],
+            };
+            sink.command(&auth).await?;
+        }
+        if let Some(db) = address.db.map(|db| db.to_string()) {
+            sink.command(&[b"SELECT", db.as_bytes()]).await?;
+        }
+        sink.flush().await?;
+        Ok(sink)
+    }
+
+    /// Queues `args` as one command; its reply is read later.
+    async fn command(&mut self, args: &[&[u8]]) -> Result<(), SinkError> {
+        let mut frame = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            frame.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            frame.extend_from_slice(arg);
+            frame.extend_from_slice(b"\r\n");
+        }
+        self.write.write_all(&frame).await?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Reads one reply, failing on an error reply.
+    async fn reply(&mut self) -> Result<(), SinkError> {
+        self.line.clear();
+        if self.read.read_line(&mut self.line).await? == 0 {
+            return Err(protocol("connection closed"));
+        }
+        self.pending = self.pending.saturating_sub(1);
+        let line = self.line.trim_end();
+        let (kind, rest) = line.split_at(line.len().min(1));
+        match kind {
+            "+" | ":" => Ok(()),
+            "-" => Err(SinkError::Rejected(rest.to_string())),
+            "$" => {
+                let len = rest.parse::<i64>().map_err(|_| protocol(line))?;
+                if let Ok(len) = usize::try_from(len) {
+                    let mut value = vec![0; len + 2];
+                    self.read.read_exact(&mut value).await?;
+                }
+                Ok(())
+            }
+            _ => Err(protocol(line)),
+        }
+    }
+}
+
+impl Sink for RedisSink {
+    async fn publish(&mut self, message: &pb::EventMessage) -> Result<(), SinkError> {
+        let key = format!("{}:{}", self.options.prefix, message.symbol);
+        let sequence = message.sequence.to_string();
+        let timestamp = message.timestamp_ns.to_string();
+        let event = message.encode_to_vec();
+        let max_len = self.options.max_len.map(|n| n.to_string());
+        let mut args: Vec<&[u8]> = vec![b"XADD", key.as_bytes()];
+        if let Some(max_len) = &max_len {
+            args.extend([b"MAXLEN".as_slice(), b"~", max_len.as_bytes()]);
+        }
+        args.extend([
+            b"*".as_slice(),
+            b"sequence",
+            sequence.as_bytes(),
+            b"timestamp_ns",
+            timestamp.as_bytes(),
+            b"event",
+            &event,
+        ]);
+        self.command(&args).await?;
+        // Paced events go out as they are due; only the replies wait.
+        self.write.flush().await?;
+        if self.pending >= PIPELINE_DEPTH {
+            while self.pending > PIPELINE_DEPTH / 2 {
+                self.reply().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        self.write.flush().await?;
+        while self.pending > 0 {
+            self.reply().await?;
+        }
+        Ok(())
+    }
+}
+
+fn protocol(what: &str) -> SinkError {
+    SinkError::Protocol(format!("redis server: {}", what.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::to_proto;
+    use md_core::Event;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// A server that records every command and rejects `XADD`s to
+    /// `md:BAD`.
+    async fn fake_server() -> (String, mpsc::UnboundedReceiver<Vec<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("redis://:secret@{}/2", listener.local_addr().expect("addr"));
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut read = BufReader::new(read);
+                    let mut line = String::new();
+                    let mut id = 0;
+                    loop {
+                        line.clear();
+                        if read.read_line(&mut line).await? == 0 {
+                            return Ok::<_, std::io::Error>(());
+                        }
+                        let count = line.trim_end()[1..].parse::<usize>().expect("array");
+                        let mut args = Vec::new();
+                        for _ in 0..count {
+                            line.clear();
+                            read.read_line(&mut line).await?;
+                            let len = line.trim_end()[1..].parse::<usize>().expect("bulk");
+                            let mut arg = vec![0; len + 2];
+                            read.read_exact(&mut arg).await?;
+                            arg.truncate(len);
+                            args.push(arg);
+                        }
+                        let reply = match args[0].as_slice() {
+                            b"XADD" if args[1] == b"md:BAD" => {
+                                String::from("-WRONGTYPE not a stream\r\n")
+                            }
+                            b"XADD" => {
+                                id += 1;
+                                let id = format!("1-{id}");
+                                format!("${}\r\n{id}\r\n", id.len())
+                            }
+                            _ => String::from("+OK\r\n"),
+                        };
+                        write.write_all(reply.as_bytes()).await?;
+                        let _ = tx.send(args);
+                    }
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn appends_each_event_to_its_symbols_stream() {
+        let (url, mut commands) = fake_server().await;
+        let options = RedisOptions {
+            max_len: Some(1_000),
+            ..RedisOptions::default()
+        };
+        let mut sink = RedisSink::connect(&url, options.clone())
+            .await
+            .expect("connect");
+        let auth = commands.recv().await.expect("auth");
+        assert_eq!(auth, [b"AUTH".to_vec(), b"secret".to_vec()]);
+        let select = commands.recv().await.expect("select");
+        assert_eq!(select, [b"SELECT".to_vec(), b"2".to_vec()]);
+
+        let trade = Event::trade(5, 7, "X", "AAPL", 100, 5);
+        sink.publish(&to_proto(&trade)).await.expect("publish");
+        sink.flush().await.expect("flush");
+        let xadd = commands.recv().await.expect("xadd");
+        let head = xadd[..9].iter().map(|a| a.as_slice()).collect::<Vec<_>>();
+        assert_eq!(
+            head,
+            [
+                b"XADD".as_slice(),
+                b"md:AAPL",
+                b"MAXLEN",
+                b"~",
+                b"1000",
+                b"*",
+                b"sequence",
+                b"7",
+                b"timestamp_ns"
+            ]
+        );
+        let event = pb::EventMessage::decode(xadd[11].as_slice()).expect("decode");
+        assert_eq!(event, to_proto(&trade));
+
+        let mut rejected = RedisSink::connect(&url, options).await.expect("connect");
+        let bad = Event::trade(6, 8, "X", "BAD", 1, 1);
+        rejected.publish(&to_proto(&bad)).await.expect("queued");
+        let err = rejected.flush().await.expect_err("rejected");
+        assert!(matches!(err, SinkError::Rejected(ref e) if e.starts_with("WRONGTYPE")));
+    }
+
+    #[test]
+    fn parses_addresses() {
+        let address = RedisAddress::parse("redis://md:pw@cache.internal:6380/3").expect("parse");
+        assert_eq!(
+            (address.host.as_str(), address.port),
+            ("cache.internal", 6380)
+        );
+        assert_eq!(address.user.as_deref(), Some("md"));
+        assert_eq!(
+            (address.password.as_deref(), address.db),
+            (Some("pw"), Some(3))
+        );
+        let bare = RedisAddress::parse("localhost").expect("parse");
+        assert_eq!(
+            (bare.port, bare.password, bare.db),
+            (DEFAULT_PORT, None, None)
+        );
+        assert!(RedisAddress::parse("redis://localhost/x").is_err());
+    }
+}
@@ -0,0 +1,474 @@
+//! ZeroMQ PUB socket speaking ZMTP 3.0 with the NULL mechanism, so plain
+//! `SUB` sockets in any language can subscribe by symbol.
+
+use super::{Sink, SinkError};
+use crate::pb;
+use prost::Message;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Flag bits of a frame header.
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+/// Largest frame a subscriber may send; subscriptions are short.
+const MAX_INBOUND_FRAME: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZmqOptions {
+    /// Messages queued for one subscriber before further ones are dropped
+    /// for it, as `ZMQ_SNDHWM` does.
+    pub high_water_mark: usize,
+}
+
+impl Default for ZmqOptions {
+    fn default() -> Self {
+        Self {
+            high_water_mark: 1000,
+        }
+    }
+}
+
+/// What a subscriber's writer is handed.
+enum Outgoing {
+    /// Frames already encoded for the wire.
+    Wire(Arc<[u8]>),
+    /// Answered once everything queued before it is written.
+    Flushed(oneshot::Sender<()>),
+}
+
+struct Peer {
+    subscriptions: Arc<Mutex<Vec<Vec<u8>>>>,
+    queue: mpsc::Sender<Outgoing>,
+}
+
+impl Peer {
+    fn wants(&self, topic: &[u8]) -> bool {
+        lock(&self.subscriptions)
+            .iter()
+            .any(|prefix| topic.starts_with(prefix))
+    }
+}
+
+pub struct ZmqSink {
+    local_addr: SocketAddr,
+    peers: Arc<Mutex<Vec<Peer>>>,
+    /// How many subscribers have subscribed to anything so far.
+    subscribed: watch::Receiver<usize>,
+    accept: JoinHandle<()>,
+    dropped: u64,
+}
+
+impl ZmqSink {
+    /// Listens on `endpoint`, `tcp://host:port` with `*` for every
+    /// interface. Subscribers may connect at any time; events published
+    /// before they subscribe are not sent to them.
+    pub async fn bind(endpoint: &str, options: ZmqOptions) -> Result<Self, SinkError> {
+        let listener = TcpListener::bind(parse_endpoint(endpoint)?).await?;
+        let local_addr = listener.local_addr()?;
+        let peers = Arc::new(Mutex::new(Vec::new()));
+        let (subscribed_tx, subscribed) = watch::channel(0);
+        let accept = tokio::spawn(accept(
+            listener,
+            peers.clone(),
+            Arc::new(subscribed_tx),
+            options.high_water_mark.max(1),
+        ));
+        Ok(Self {
+            local_addr,
+            peers,
+            subscribed,
+            accept,
+            dropped: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Waits until `count` subscribers have each subscribed to a topic.
+    pub async fn wait_for_subscribers(&mut self, count: usize) -> Result<(), SinkError> {
+        self.subscribed
+            .wait_for(|subscribed| *subscribed >= count)
+            .await
+            .map_err(|_| protocol("listener stopped"))?;
+        Ok(())
+    }
+
+    /// Messages dropped because a subscriber's queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Drop for ZmqSink {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+impl Sink for ZmqSink {
+    async fn publish(&mut self, message: &pb::EventMessage) -> Result<(), SinkError> {
+        let topic = message.symbol.as_bytes();
+        let mut wire = Vec::new();
+        put_frame(&mut wire, MORE, topic);
+        put_frame(&mut wire, 0, &message.encode_to_vec());
+        let wire: Arc<[u8]> = wire.into();
+        let dropped = &mut self.dropped;
+        lock(&self.peers).retain(|peer| {
+            if !peer.wants(topic) {
+                return !peer.queue.is_closed();
+            }
+            match peer.queue.try_send(Outgoing::Wire(wire.clone())) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    *dropped += 1;
+                    true
+                }
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        let queues: Vec<_> = lock(&self.peers)
+            .iter()
+            .map(|peer| peer.queue.clone())
+            .collect();
+        for queue in queues {
+            let (done, flushed) = oneshot::channel();
+            // A subscriber that left has nothing left to flush.
+            if queue.send(Outgoing::Flushed(done)).await.is_ok() {
+                let _ = flushed.await;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn accept(
+    listener: TcpListener,
+    peers: Arc<Mutex<Vec<Peer>>>,
+    subscribed: Arc<watch::Sender<usize>>,
+    high_water_mark: usize,
+) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                debug!(%error, "zmq accept failed");
+                continue;
+            }
+        };
+        let peers = peers.clone();
+        let subscribed = subscribed.clone();
+        tokio::spawn(async move {
+            if let Err(error) = serve_peer(stream, peers, subscribed, high_water_mark).await {
+                debug!(%addr, %error, "zmq subscriber left");
+            }
+        });
+    }
+}
+
+/// Greets one subscriber, then tracks its subscriptions until it leaves.
+async fn serve_peer(
+    stream: TcpStream,
+    peers: Arc<Mutex<Vec<Peer>>>,
+    subscribed: Arc<watch::Sender<usize>>,
+    high_water_mark: usize,
+) -> Result<(), SinkError> {
+    stream.set_nodelay(true)?;
+    let (read, write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let mut write = BufWriter::new(write);
+    handshake(&mut read, &mut write).await?;
+
+    let subscriptions = Arc::new(Mutex::new(Vec::<Vec<u8>>::new()));
+    let (queue, outgoing) = mpsc::channel(high_water_mark);
+    // Weak, so the writer stops once the sink lets go of the peer.
+    let pongs = queue.downgrade();
+    lock(&peers).push(Peer {
+        subscriptions: subscriptions.clone(),
+        queue,
+    });
+    tokio::spawn(write_loop(write, outgoing));
+
+    let mut counted = false;
+    loop {
+        let (flags, body) = read_frame(&mut read).await?;
+        let (subscribe, topic) = if flags & COMMAND != 0 {
+            match command(&body)? {
+                (b"SUBSCRIBE", topic) => (true, topic),
+                (b"CANCEL", topic) => (false, topic),
+                (b"PING", ping) => {
+                    // PONG echoes the context that follows the 2-byte TTL.
+                    let mut pong = vec![4];
+                    pong.extend_from_slice(b"PONG");
+                    pong.extend_from_slice(ping.get(2..).unwrap_or_default());
+                    let mut wire = Vec::new();
+                    put_frame(&mut wire, COMMAND, &pong);
+                    if let Some(queue) = pongs.upgrade() {
+                        let _ = queue.try_send(Outgoing::Wire(wire.into()));
+                    }
+                    continue;
+                }
+                _ => continue,
+            }
+        } else {
+            match body.split_first() {
+                Some((1, topic)) => (true, topic),
+                Some((0, topic)) => (false, topic),
+                _ => continue,
+            }
+        };
+        let mut subscriptions = lock(&subscriptions);
+        if subscribe {
+            subscriptions.push(topic.to_vec());
+        } else if let Some(at) = subscriptions.iter().position(|s| s == topic) {
+            subscriptions.swap_remove(at);
+        }
+        if !counted && subscribe {
+            counted = true;
+            subscribed.send_modify(|count| *count += 1);
+        }
+    }
+}
+
+async fn write_loop(mut write: BufWriter<OwnedWriteHalf>, mut outgoing: mpsc::Receiver<Outgoing>) {
+    while let Some(next) = outgoing.recv().await {
+        match next {
+            Outgoing::Wire(wire) => {
+                if write.write_all(&wire).await.is_err() {
+                    return;
+                }
+                if outgoing.is_empty() && write.flush().await.is_err() {
+                    return;
+                }
+            }
+            Outgoing::Flushed(done) => {
+                if write.flush().await.is_err() {
+                    return;
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Exchanges greetings and `READY` commands, accepting only `SUB` and
+/// `XSUB` peers.
+async fn handshake<R, W>(read: &mut R, write: &mut W) -> Result<(), SinkError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut greeting = [0u8; 64];
+    greeting[0] = 0xFF;
+    greeting[9] = 0x7F;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    write.write_all(&greeting).await?;
+    let mut ready = vec![5];
+    ready.extend_from_slice(b"READY");
+    put_property(&mut ready, b"Socket-Type", b"PUB");
+    let mut wire = Vec::new();
+    put_frame(&mut wire, COMMAND, &ready);
+    write.write_all(&wire).await?;
+    write.flush().await?;
+
+    let mut peer = [0u8; 64];
+    read.read_exact(&mut peer).await?;
+    if peer[0] != 0xFF || peer[9] != 0x7F || peer[10] < 3 {
+        return Err(protocol("not a ZMTP 3 greeting"));
+    }
+    let mechanism = &peer[12..32];
+    if mechanism.split(|b| *b == 0).next() != Some(b"NULL".as_slice()) {
+        return Err(protocol("only the NULL mechanism is supported"));
+    }
+    let (flags, body) = read_frame(read).await?;
+    let (b"READY", properties) = command(&body)? else {
+        return Err(protocol("expected READY"));
+    };
+    if flags & COMMAND == 0 {
+        return Err(protocol("expected READY"));
+    }
+    match property(properties, b"Socket-Type")? {
+        Some(b"SUB" | b"XSUB") => Ok(()),
+        _ => Err(protocol("peer is not a SUB socket")),
+    }
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(read: &mut R) -> Result<(u8, Vec<u8>), SinkError> {
+    let flags = read.read_u8().await?;
+    let len = if flags & LONG != 0 {
+        read.read_u64().await?
+    } else {
+        u64::from(read.read_u8().await?)
+    };
+    if len > MAX_INBOUND_FRAME {
+        return Err(protocol("frame too large"));
+    }
+    let mut body = vec![0; len as usize];
+    read.read_exact(&mut body).await?;
+    Ok((flags, body))
+}
+
+fn put_frame(out: &mut Vec<u8>, flags: u8, body: &[u8]) {
+    match u8::try_from(body.len()) {
+        Ok(len) => out.extend_from_slice(&[flags, len]),
+        Err(_) => {
+            out.push(flags | LONG);
+            out.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(body);
+}
+
+fn put_property(out: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    out.push(name.len() as u8);
+    out.extend_from_slice(name);
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Splits a command body into its name and data.
+fn command(body: &[u8]) -> Result<(&[u8], &[u8]), SinkError> {
+    let (&len, rest) = body
+        .split_first()
+        .ok_or_else(|| protocol("empty command"))?;
+    let len = usize::from(len);
+    if rest.len() < len {
+        return Err(protocol("truncated command"));
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Value of `name` among a `READY` command's properties.
+fn property<'a>(mut properties: &'a [u8], name: &[u8]) -> Result<Option<&'a [u8]>, SinkError> {
+    let truncated = || protocol("truncated READY properties");
+    while let Some((&len, rest)) = properties.split_first() {
+        let (key, rest) = rest
+            .split_at_checked(usize::from(len))
+            .ok_or_else(truncated)?;
+        let (len, rest) = rest.split_at_checked(4).ok_or_else(truncated)?;
+        let len = u32::from_be_bytes(len.try_into().map_err(|_| truncated())?) as usize;
+        let (value, rest) = rest.split_at_checked(len).ok_or_else(truncated)?;
+        if key.eq_ignore_ascii_case(name) {
+            return Ok(Some(value));
+        }
+        properties = rest;
+    }
+    Ok(None)
+}
+
+/// `tcp://host:port`, with `*` for every interface.
+fn parse_endpoint(endpoint: &str) -> Result<SocketAddr, SinkError> {
+    let invalid = || SinkError::Address(endpoint.to_string());
+    let rest = endpoint.strip_prefix("tcp://").ok_or_else(invalid)?;
+    let (host, port) = rest.rsplit_once(':').ok_or_else(invalid)?;
+    let port: u16 = port.parse().map_err(|_| invalid())?;
+    let host = match host.trim_start_matches('[').trim_end_matches(']') {
+        "*" => "0.0.0.0",
+        host => host,
+    };
+    let ip = host.parse().map_err(|_| invalid())?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+fn protocol(what: &str) -> SinkError {
+    SinkError::Protocol(format!("zmq subscriber: {what}"))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::to_proto;
+    use md_core::Event;
+
+    /// Connects as a ZMTP 3.0 `SUB` socket subscribed to `topic`.
+    async fn subscriber(addr: SocketAddr, topic: &[u8]) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.expect("connect");
+        let mut greeting = [0u8; 64];
+        greeting[0] = 0xFF;
+        greeting[9] = 0x7F;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        let mut ready = vec![5];
+        ready.extend_from_slice(b"READY");
+        put_property(&mut ready, b"Socket-Type", b"SUB");
+        let mut subscribe = vec![1];
+        subscribe.extend_from_slice(topic);
+        let mut wire = greeting.to_vec();
+        put_frame(&mut wire, COMMAND, &ready);
+        put_frame(&mut wire, 0, &subscribe);
+        stream.write_all(&wire).await.expect("write");
+
+        let mut peer = [0u8; 64];
+        stream.read_exact(&mut peer).await.expect("greeting");
+        assert_eq!((peer[0], peer[9], peer[10]), (0xFF, 0x7F, 3));
+        let (flags, body) = read_frame(&mut stream).await.expect("ready");
+        assert_eq!(flags, COMMAND);
+        let (name, properties) = command(&body).expect("command");
+        assert_eq!(name, b"READY");
+        assert_eq!(
+            property(properties, b"socket-type").expect("property"),
+            Some(b"PUB".as_slice())
+        );
+        stream
+    }
+
+    #[tokio::test]
+    async fn sends_subscribed_symbols_as_topic_and_event_frames() {
+        let mut sink = ZmqSink::bind("tcp://127.0.0.1:0", ZmqOptions::default())
+            .await
+            .expect("bind");
+        let mut aapl = subscriber(sink.local_addr(), b"AAPL").await;
+        sink.wait_for_subscribers(1).await.expect("subscribed");
+
+        let msft = Event::trade(5, 7, "X", "MSFT", 100, 5);
+        let trade = Event::trade(6, 8, "X", "AAPL", 101, 5);
+        sink.publish(&to_proto(&msft)).await.expect("publish");
+        sink.publish(&to_proto(&trade)).await.expect("publish");
+        sink.flush().await.expect("flush");
+
+        let (flags, topic) = read_frame(&mut aapl).await.expect("topic");
+        assert_eq!((flags, topic.as_slice()), (MORE, b"AAPL".as_slice()));
+        let (flags, body) = read_frame(&mut aapl).await.expect("event");
+        assert_eq!(flags & MORE, 0);
+        let event = pb::EventMessage::decode(body.as_slice()).expect("decode");
+        assert_eq!(event, to_proto(&trade));
+        assert_eq!(sink.dropped(), 0);
+    }
+
+    #[test]
+    fn parses_endpoints() {
+        let any = parse_endpoint("tcp://*:5556").expect("parse");
+        assert_eq!(any, "0.0.0.0:5556".parse::<SocketAddr>().expect("addr"));
+        let v6 = parse_endpoint("tcp://[::1]:5556").expect("parse");
+        assert_eq!(v6, "[::1]:5556".parse::<SocketAddr>().expect("addr"));
+        assert!(parse_endpoint("ipc:///tmp/md").is_err());
+        assert!(parse_endpoint("tcp://*").is_err());
+    }
+
+    #[test]
+    fn long_frames_carry_an_eight_byte_size() {
+        let mut wire = Vec::new();
+        put_frame(&mut wire, 0, &[7; 300]);
+        assert_eq!(wire[0], LONG);
+        assert_eq!(&wire[1..9], &300u64.to_be_bytes());
+        assert_eq!(wire.len(), 9 + 300);
+    }
+}
@@ -0,0 +1,659 @@
+//! Where a server's events come from: one log, daily logs stitched into
+//! one continuous replay, logs interleaved into one feed, or events
+//! ingested straight into memory.
+
+use crate::engine::{read_events, DayBoundary, ReplayError};
+use md_core::{Event, Timestamp};
+use md_storage::{
+    index_path_for, EventLogFooter, EventLogHeader, EventLogReader, RecordFilter, SchemaVersion,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFile {
+    pub log: PathBuf,
+    /// Used for windowed reads when it exists.
+    pub index: Option<PathBuf>,
+}
+
+impl LogFile {
+    /// `log` with its sidecar index.
+    pub fn new(log: PathBuf) -> Self {
+        let index = Some(index_path_for(&log));
+        Self { log, index }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogSource {
+    Single(LogFile),
+    /// Days in order; each must start after the previous one ends.
+    Stitched(Vec<LogFile>),
+    /// Logs covering the same time, such as one per venue, interleaved by
+    /// timestamp as they are read. Ties go to the earlier log, then keep
+    /// each log's own order; sequences number the merged feed from 1.
+    Merged(Vec<LogFile>),
+    /// Sequenced events held in memory, with no log behind them.
+    Events(Arc<[Event]>),
+}
+
+/// Events of a read in sequence order, with the start of each stitched day
+/// they cover.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceEvents {
+    pub events: Vec<Event>,
+    pub days: Vec<DayBoundary>,
+}
+
+/// What a source holds, before any plugin or session filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogInfo {
+    /// File format version; 0 for events held in memory.
+    pub version: u16,
+    pub schema: SchemaVersion,
+    pub schema_hash: u64,
+    pub symbols: Vec<String>,
+    /// Decimal price of one stored tick per symbol, from the headers;
+    /// empty for events held in memory.
+    pub price_units: BTreeMap<String, String>,
+    /// Counts, bounds and sequences as the source streams them.
+    pub summary: EventLogFooter,
+    /// File name of each log, one per stitched day or merged log.
+    pub logs: Vec<String>,
+}
+
+impl LogSource {
+    /// Up to `limit` events with sequences in `[from_seq, to_seq]`, in
+    /// sequence order. Reading stops once `limit` are found. A single log
+    /// is scanned without decoding the records outside the range, and only
+    /// the stitched days the range covers are read. Merged logs are merged
+    /// from their start, since that numbers their sequences.
+    pub fn read_sequences(
+        &self,
+        from_seq: u64,
+        to_seq: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<Event>, ReplayError> {
+        let wanted = |e: &Event| e.sequence >= from_seq && to_seq.is_none_or(|to| e.sequence <= to);
+        let mut events = Vec::new();
+        match self {
+            Self::Single(file) => {
+                let mut reader = EventLogReader::open(&file.log)?;
+                let filter = RecordFilter {
+                    from_sequence: Some(from_seq),
+                    to_sequence: to_seq,
+                    ..RecordFilter::default()
+                };
+                while events.len() < limit {
+                    let Some(record) = reader.next_record_filtered(&filter)? else {
+                        break;
+                    };
+                    events.push(record.event);
+                }
+            }
+            Self::Stitched(files) => {
+                for day in stitched_days(files)? {
+                    let last = day.base
+                        + day
+                            .span
+                            .last_sequence
+                            .saturating_sub(day.span.first_sequence);
+                    if last < from_seq {
+                        continue;
+                    }
+                    if events.len() >= limit || to_seq.is_some_and(|to| to < day.base) {
+                        break;
+                    }
+                    // The range in the day's own numbering.
+                    let filter = RecordFilter {
+                        from_sequence: Some(
+                            day.span.first_sequence + from_seq.saturating_sub(day.base),
+                        ),
+                        to_sequence: to_seq.map(|to| day.span.first_sequence + (to - day.base)),
+                        ..RecordFilter::default()
+                    };
+                    let mut reader = EventLogReader::open(&day.file.log)?;
+                    while events.len() < limit {
+                        let Some(record) = reader.next_record_filtered(&filter)? else {
+                            break;
+                        };
+                        let mut event = record.event;
+                        event.sequence =
+                            event.sequence.saturating_sub(day.span.first_sequence) + day.base;
+                        events.push(event);
+                    }
+                }
+            }
+            Self::Merged(files) => merge_logs(files, None, |event| {
+                let past = to_seq.is_some_and(|to| event.sequence >= to);
+                if wanted(&event) {
+                    events.push(event);
+                }
+                if past || events.len() >= limit {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })?,
+            Self::Events(all) => {
+                events.extend(all.iter().filter(|e| wanted(e)).take(limit).cloned());
+            }
+        }
+        events.sort_by_key(|e| e.sequence);
+        Ok(events)
+    }
+
+    /// Header and summary of the source. A stitched source reports the
+    /// first day's header, every day's symbols and the days' combined
+    /// counts, with sequences continued as [`LogSource::read`] does. A
+    /// merged source reports the same, spanning the earliest first event
+    /// to the latest last one.
+    pub fn info(&self) -> Result<LogInfo, ReplayError> {
+        match self {
+            Self::Single(file) => {
+                let (header, summary) = describe(&file.log)?;
+                Ok(LogInfo {
+                    version: header.version,
+                    schema: header.schema,
+                    schema_hash: header.schema_hash,
+                    symbols: header.symbols,
+                    price_units: header.price_units,
+                    summary,
+                    logs: vec![file_name(&file.log)],
+                })
+            }
+            Self::Stitched(files) => stitched_info(files),
+            Self::Merged(files) => merged_info(files),
+            Self::Events(events) => {
+                let mut summary = EventLogFooter::default();
+                for event in events.iter() {
+                    summary.record(event);
+                }
+                Ok(LogInfo {
+                    version: 0,
+                    schema: SchemaVersion::CURRENT,
+                    schema_hash: SchemaVersion::CURRENT.hash(),
+                    symbols: summary.symbols.keys().cloned().collect(),
+                    price_units: BTreeMap::new(),
+                    summary,
+                    logs: Vec::new(),
+                })
+            }
+        }
+    }
+
+    /// Events between `from_ns` and `to_ns`. Stitched days are renumbered
+    /// to continue the first day's sequences, the same way whatever the
+    /// window, so a sequence names one event across sessions. Merged logs
+    /// are read from their start for the same reason, since events before
+    /// the window decide where its sequences begin.
+    pub fn read(
+        &self,
+        from_ns: Option<Timestamp>,
+        to_ns: Option<Timestamp>,
+    ) -> Result<SourceEvents, ReplayError> {
+        match self {
+            Self::Single(file) => Ok(SourceEvents {
+                events: read_events(&file.log, file.index.as_deref(), from_ns, to_ns)?,
+                days: Vec::new(),
+            }),
+            Self::Stitched(files) => read_stitched(files, from_ns, to_ns),
+            Self::Merged(files) => {
+                let mut events = read_merged(files, to_ns)?;
+                if let Some(from) = from_ns {
+                    events.retain(|e| e.timestamp_ns >= from);
+                }
+                Ok(SourceEvents {
+                    events,
+                    days: Vec::new(),
+                })
+            }
+            Self::Events(events) => Ok(SourceEvents {
+                events: events
+                    .iter()
+                    .filter(|e| from_ns.is_none_or(|from| e.timestamp_ns >= from))
+                    .filter(|e| to_ns.is_none_or(|to| e.timestamp_ns <= to))
+                    .cloned()
+                    .collect(),
+                days: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// A non-empty stitched day, with the sequence its first event takes.
+struct StitchedDay<'a> {
+    day: usize,
+    file: &'a LogFile,
+    span: EventLogFooter,
+    base: u64,
+}
+
+/// The days that hold events, numbered to continue the first day's
+/// sequences. Fails if a day starts before the previous one ends.
+fn stitched_days(files: &[LogFile]) -> Result<Vec<StitchedDay<'_>>, ReplayError> {
+    let mut days = Vec::with_capacity(files.len());
+    let mut next_sequence = None::<u64>;
+    let mut previous = None::<(&Path, Timestamp)>;
+    for (day, file) in files.iter().enumerate() {
+        let span = span(&file.log)?;
+        if span.record_count == 0 {
+            continue;
+        }
+        if let Some((prev, last_ns)) = previous {
+            if span.first_timestamp_ns < last_ns {
+                return Err(ReplayError::Stitch(format!(
+                    "{} starts at {} ns, before {} ends at {} ns",
+                    file.log.display(),
+                    span.first_timestamp_ns.as_nanos(),
+                    prev.display(),
+                    last_ns.as_nanos()
+                )));
+            }
+        }
+        previous = Some((&file.log, span.last_timestamp_ns));
+        let base = *next_sequence.get_or_insert(span.first_sequence);
+        next_sequence = Some(base + span.last_sequence.saturating_sub(span.first_sequence) + 1);
+        days.push(StitchedDay {
+            day,
+            file,
+            span,
+            base,
+        });
+    }
+    Ok(days)
+}
+
+fn read_stitched(
+    files: &[LogFile],
+    from_ns: Option<Timestamp>,
+    to_ns: Option<Timestamp>,
+) -> Result<SourceEvents, ReplayError> {
+    let mut out = SourceEvents::default();
+    for StitchedDay {
+        day,
+        file,
+        span,
+        base,
+    } in stitched_days(files)?
+    {
+        let outside = from_ns.is_some_and(|from| span.last_timestamp_ns < from)
+            || to_ns.is_some_and(|to| span.first_timestamp_ns > to);
+        if outside {
+            continue;
+        }
+        let mut events = read_events(&file.log, file.index.as_deref(), from_ns, to_ns)?;
+        if events.is_empty() {
+            continue;
+        }
+        for event in &mut events {
+            event.sequence = event.sequence.saturating_sub(span.first_sequence) + base;
+        }
+        out.days.push(DayBoundary {
+            day: u32::try_from(day).unwrap_or(u32::MAX),
+            log: file_name(&file.log),
+            first_ns: span.first_timestamp_ns,
+            first_sequence: base,
+        });
+        out.events.extend(events);
+    }
+    Ok(out)
+}
+
+fn stitched_info(files: &[LogFile]) -> Result<LogInfo, ReplayError> {
+    let mut info = None::<LogInfo>;
+    for file in files {
+        let (header, span) = describe(&file.log)?;
+        let info = info.get_or_insert_with(|| LogInfo {
+            version: header.version,
+            schema: header.schema,
+            schema_hash: header.schema_hash,
+            symbols: Vec::new(),
+            price_units: BTreeMap::new(),
+            summary: EventLogFooter::default(),
+            logs: Vec::new(),
+        });
+        for symbol in header.symbols {
+            if !info.symbols.contains(&symbol) {
+                info.symbols.push(symbol);
+            }
+        }
+        merge_price_units(&mut info.price_units, header.price_units, &file.log)
+            .map_err(ReplayError::Stitch)?;
+        info.logs.push(file_name(&file.log));
+        if span.record_count == 0 {
+            continue;
+        }
+        let summary = &mut info.summary;
+        if summary.record_count == 0 {
+            summary.first_timestamp_ns = span.first_timestamp_ns;
+            summary.first_sequence = span.first_sequence;
+            summary.last_sequence = span.first_sequence.saturating_sub(1);
+        }
+        summary.record_count += span.record_count;
+        summary.last_timestamp_ns = span.last_timestamp_ns;
+        summary.last_sequence += span.last_sequence.saturating_sub(span.first_sequence) + 1;
+        for (symbol, counts) in span.symbols {
+            let total = summary.symbols.entry(symbol).or_default();
+            total.trades += counts.trades;
+            total.quotes += counts.quotes;
+        }
+    }
+    info.ok_or_else(|| ReplayError::Stitch(String::from("no days to stitch")))
+}
+
+/// Every log's events up to `to_ns`, k-way merged by timestamp, then log,
+/// then the log's own sequence, and numbered from 1.
+fn read_merged(files: &[LogFile], to_ns: Option<Timestamp>) -> Result<Vec<Event>, ReplayError> {
+    let mut merged = Vec::new();
+    merge_logs(files, to_ns, |event| {
+        merged.push(event);
+        ControlFlow::Continue(())
+    })?;
+    Ok(merged)
+}
+
+/// Hands [`read_merged`]'s events to `visit` in order, reading each log
+/// only as far as the merge has got, until `visit` breaks.
+fn merge_logs(
+    files: &[LogFile],
+    to_ns: Option<Timestamp>,
+    mut visit: impl FnMut(Event) -> ControlFlow<()>,
+) -> Result<(), ReplayError> {
+    let filter = RecordFilter {
+        to_ns,
+        ..RecordFilter::default()
+    };
+    let mut logs = Vec::with_capacity(files.len());
+    for file in files {
+        let mut reader = EventLogReader::open(&file.log)?;
+        reader.rewind_to_data()?;
+        let head = reader.next_record_filtered(&filter)?.map(|r| r.event);
+        logs.push((reader, head));
+    }
+    let key = |event: &Event, log: usize| Reverse((event.timestamp_ns, log, event.sequence));
+    let mut heap = logs
+        .iter()
+        .enumerate()
+        .filter_map(|(log, (_, head))| head.as_ref().map(|e| key(e, log)))
+        .collect::<BinaryHeap<_>>();
+    let mut sequence = 0;
+    while let Some(Reverse((_, log, _))) = heap.pop() {
+        let (reader, head) = &mut logs[log];
+        let next = reader.next_record_filtered(&filter)?.map(|r| r.event);
+        let Some(mut event) = std::mem::replace(head, next) else {
+            continue;
+        };
+        if let Some(next) = head {
+            heap.push(key(next, log));
+        }
+        sequence += 1;
+        event.sequence = sequence;
+        if visit(event).is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn merged_info(files: &[LogFile]) -> Result<LogInfo, ReplayError> {
+    let mut info = None::<LogInfo>;
+    for file in files {
+        let (header, span) = describe(&file.log)?;
+        let info = info.get_or_insert_with(|| LogInfo {
+            version: header.version,
+            schema: header.schema,
+            schema_hash: header.schema_hash,
+            symbols: Vec::new(),
+            price_units: BTreeMap::new(),
+            summary: EventLogFooter::default(),
+            logs: Vec::new(),
+        });
+        for symbol in header.symbols {
+            if !info.symbols.contains(&symbol) {
+                info.symbols.push(symbol);
+            }
+        }
+        merge_price_units(&mut info.price_units, header.price_units, &file.log)
+            .map_err(ReplayError::Merge)?;
+        info.logs.push(file_name(&file.log));
+        if span.record_count == 0 {
+            continue;
+        }
+        let summary = &mut info.summary;
+        if summary.record_count == 0 {
+            summary.first_timestamp_ns = span.first_timestamp_ns;
+            summary.last_timestamp_ns = span.last_timestamp_ns;
+            summary.first_sequence = 1;
+        }
+        summary.record_count += span.record_count;
+        summary.first_timestamp_ns = summary.first_timestamp_ns.min(span.first_timestamp_ns);
+        summary.last_timestamp_ns = summary.last_timestamp_ns.max(span.last_timestamp_ns);
+        summary.last_sequence = summary.record_count;
+        for (symbol, counts) in span.symbols {
+            let total = summary.symbols.entry(symbol).or_default();
+            total.trades += counts.trades;
+            total.quotes += counts.quotes;
+        }
+    }
+    info.ok_or_else(|| ReplayError::Merge(String::from("no logs to merge")))
+}
+
+/// Adds a log's price units to `units`; a symbol stored in another unit
+/// than an earlier log's would mix prices of different scales.
+fn merge_price_units(
+    units: &mut BTreeMap<String, String>,
+    log_units: BTreeMap<String, String>,
+    log: &Path,
+) -> Result<(), String> {
+    for (symbol, unit) in log_units {
+        match units.get(&symbol) {
+            Some(seen) if *seen != unit => {
+                return Err(format!(
+                    "{}: price unit {unit} for {symbol} differs from {seen}",
+                    log.display()
+                ));
+            }
+            Some(_) => {}
+            None => {
+                units.insert(symbol, unit);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn file_name(log: &Path) -> String {
+    log.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The log's footer, or the same summary from a full scan for logs written
+/// without one.
+fn span(log: &Path) -> Result<EventLogFooter, ReplayError> {
+    Ok(describe(log)?.1)
+}
+
+/// The log's header and [`span`].
+fn describe(log: &Path) -> Result<(EventLogHeader, EventLogFooter), ReplayError> {
+    let reader = EventLogReader::open(log)?;
+    let header = reader.header().clone();
+    if let Some(footer) = reader.footer() {
+        return Ok((header, footer.clone()));
+    }
+    let mut footer = EventLogFooter::default();
+    for event in read_events(log, None, None, None)? {
+        footer.record(&event);
+    }
+    Ok((header, footer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_storage::{default_schema_hash, EventLogWriter};
+
+    fn write_day(name: &str, events: &[Event]) -> LogFile {
+        let path = std::env::temp_dir().join(format!(
+            "md_replay_source_{}_{name}.eventlog",
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        for event in events {
+            writer.append(event).expect("append");
+        }
+        writer.flush().expect("flush");
+        LogFile::new(path)
+    }
+
+    #[test]
+    fn stitched_days_continue_sequences_and_reject_overlap() {
+        let mon = write_day(
+            "mon",
+            &[
+                Event::trade(100, 1, "X", "AAPL", 10, 1),
+                Event::trade(200, 2, "X", "AAPL", 11, 1),
+            ],
+        );
+        let tue = write_day(
+            "tue",
+            &[
+                Event::trade(1_000, 1, "X", "AAPL", 12, 1),
+                Event::trade(1_100, 2, "X", "AAPL", 13, 1),
+            ],
+        );
+        let source = LogSource::Stitched(vec![mon.clone(), tue.clone()]);
+        let all = source.read(None, None).expect("read");
+        let seqs = all.events.iter().map(|e| e.sequence).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![1, 2, 3, 4]);
+        let days = all
+            .days
+            .iter()
+            .map(|d| (d.day, d.first_sequence, d.first_ns.as_nanos()))
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![(0, 1, 100), (1, 3, 1_000)]);
+        assert!(all.days[1].log.ends_with("tue.eventlog"));
+        let info = source.info().expect("info");
+        assert_eq!(info.logs.len(), 2);
+        assert_eq!(info.summary.record_count, 4);
+        assert_eq!(
+            (info.summary.first_sequence, info.summary.last_sequence),
+            (1, 4)
+        );
+        assert_eq!(info.summary.last_timestamp_ns, Timestamp::from_nanos(1_100));
+        assert_eq!(info.summary.symbols["AAPL"].trades, 4);
+        let third = source.read_sequences(3, Some(3), 10).expect("sequences");
+        assert_eq!(third.len(), 1);
+        assert_eq!(third[0].timestamp_ns, Timestamp::from_nanos(1_000));
+        // A page crossing into the next day stops at its limit.
+        let page = source.read_sequences(2, None, 2).expect("page");
+        let seqs = page.iter().map(|e| e.sequence).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![2, 3]);
+        assert_eq!(page[1].timestamp_ns, Timestamp::from_nanos(1_000));
+
+        let tuesday = source
+            .read(Some(Timestamp::from_nanos(1_050)), None)
+            .expect("windowed");
+        assert_eq!(tuesday.events.len(), 1);
+        assert_eq!(tuesday.events[0].sequence, 4);
+        assert_eq!(tuesday.days.len(), 1);
+        assert_eq!(tuesday.days[0].first_sequence, 3);
+
+        let err = LogSource::Stitched(vec![tue, mon])
+            .read(None, None)
+            .expect_err("overlap");
+        assert!(err.to_string().contains("mon.eventlog"), "{err}");
+    }
+
+    #[test]
+    fn merged_logs_interleave_by_time_with_stable_ties() {
+        let x = write_day(
+            "venue_x",
+            &[
+                Event::trade(100, 1, "X", "AAPL", 10, 1),
+                Event::trade(300, 2, "X", "AAPL", 11, 1),
+                Event::trade(300, 3, "X", "AAPL", 12, 1),
+            ],
+        );
+        let y = write_day(
+            "venue_y",
+            &[
+                Event::trade(200, 1, "Y", "AAPL", 20, 1),
+                Event::trade(300, 2, "Y", "AAPL", 21, 1),
+                Event::trade(400, 3, "Y", "AAPL", 22, 1),
+            ],
+        );
+        let source = LogSource::Merged(vec![x, y]);
+        let all = source.read(None, None).expect("read");
+        let feed = all
+            .events
+            .iter()
+            .map(|e| (e.sequence, e.venue.as_str(), e.timestamp_ns.as_nanos()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            feed,
+            vec![
+                (1, "X", 100),
+                (2, "Y", 200),
+                (3, "X", 300),
+                (4, "X", 300),
+                (5, "Y", 300),
+                (6, "Y", 400),
+            ]
+        );
+        assert!(all.days.is_empty());
+
+        // A window keeps the sequences the whole feed gives its events.
+        let window = source
+            .read(
+                Some(Timestamp::from_nanos(300)),
+                Some(Timestamp::from_nanos(300)),
+            )
+            .expect("window");
+        let seqs = window.events.iter().map(|e| e.sequence).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![3, 4, 5]);
+        let fifth = source.read_sequences(5, Some(5), 10).expect("sequences");
+        assert_eq!(fifth[0].venue, "Y");
+        let page = source.read_sequences(2, None, 3).expect("page");
+        let page = page
+            .iter()
+            .map(|e| (e.sequence, e.venue.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(page, vec![(2, "Y"), (3, "X"), (4, "X")]);
+
+        let info = source.info().expect("info");
+        assert_eq!(info.logs.len(), 2);
+        assert_eq!(info.summary.record_count, 6);
+        assert_eq!(
+            (info.summary.first_sequence, info.summary.last_sequence),
+            (1, 6)
+        );
+        assert_eq!(info.summary.first_timestamp_ns, Timestamp::from_nanos(100));
+        assert_eq!(info.summary.last_timestamp_ns, Timestamp::from_nanos(400));
+    }
+
+    #[test]
+    fn in_memory_events_are_windowed_like_a_log() {
+        let source = LogSource::Events(Arc::from(vec![
+            Event::trade(100, 1, "X", "AAPL", 10, 1),
+            Event::trade(200, 2, "X", "AAPL", 11, 1),
+            Event::trade(300, 3, "X", "AAPL", 12, 1),
+        ]));
+        let read = source
+            .read(
+                Some(Timestamp::from_nanos(200)),
+                Some(Timestamp::from_nanos(300)),
+            )
+            .expect("read");
+        let seqs = read.events.iter().map(|e| e.sequence).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![2, 3]);
+        assert!(read.days.is_empty());
+    }
+}
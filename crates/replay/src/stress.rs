@@ -0,0 +1,356 @@
+//! Load generator for a replay server: many concurrent `StreamEvents`
+//! sessions with varied symbol filters and speeds, summarised as latency
+//! and error distributions.
+
+use crate::engine::ReplayError;
+use crate::pb::replay_service_client::ReplayServiceClient;
+use crate::pb::StreamRequest;
+use crate::rng::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use tonic::transport::Endpoint;
+use tonic::Request;
+use tracing::warn;
+
+/// Waves a bursty run arrives in.
+const BURST_WAVES: usize = 5;
+/// Speeds sessions pick from; `None` is max speed.
+const SPEEDS: [Option<f64>; 4] = [None, Some(1.0), Some(10.0), Some(100.0)];
+
+/// When sessions open within a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StressProfile {
+    /// All at once.
+    Flat,
+    /// Evenly over the first half of the run.
+    Ramp,
+    /// In [`BURST_WAVES`] simultaneous waves over the first half of the run.
+    #[default]
+    Bursty,
+}
+
+impl FromStr for StressProfile {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "flat" => Ok(Self::Flat),
+            "ramp" => Ok(Self::Ramp),
+            "bursty" => Ok(Self::Bursty),
+            other => Err(format!(
+                "unknown stress profile {other}, expected flat, ramp or bursty"
+            )),
+        }
+    }
+}
+
+/// `symbols` is the pool filters are drawn from; without it every
+/// session streams all symbols. Sessions still running after `duration`
+/// are stopped and count as `stopped`, not as failures.
+#[derive(Debug, Clone)]
+pub struct StressOptions {
+    pub sessions: usize,
+    pub profile: StressProfile,
+    pub symbols: Vec<String>,
+    pub duration: Duration,
+    pub seed: u64,
+    pub token: Option<String>,
+}
+
+impl Default for StressOptions {
+    fn default() -> Self {
+        Self {
+            sessions: 50,
+            profile: StressProfile::default(),
+            symbols: Vec::new(),
+            duration: Duration::from_secs(30),
+            seed: 42,
+            token: None,
+        }
+    }
+}
+
+/// What one session asked for and what it got.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionOutcome {
+    pub session: usize,
+    pub start_ms: f64,
+    /// `None` for max speed.
+    pub speed: Option<f64>,
+    pub symbols: Vec<String>,
+    pub events: u64,
+    /// From opening the connection to the response headers.
+    pub connect_ms: Option<f64>,
+    /// From opening the connection to the first event.
+    pub first_event_ms: Option<f64>,
+    /// Longest wait between two events.
+    pub max_gap_ms: Option<f64>,
+    /// `completed`, `stopped`, or the gRPC status code of a failure.
+    pub end: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn of(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f64::total_cmp);
+        let at = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Self {
+            count: samples.len(),
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StressReport {
+    pub sessions: usize,
+    pub completed: usize,
+    pub stopped: usize,
+    pub failed: usize,
+    /// Failures by status code.
+    pub errors: BTreeMap<String, usize>,
+    pub events: u64,
+    pub elapsed_ms: f64,
+    pub events_per_sec: f64,
+    pub connect_ms: Distribution,
+    pub first_event_ms: Distribution,
+    pub max_gap_ms: Distribution,
+    pub outcomes: Vec<SessionOutcome>,
+}
+
+impl StressReport {
+    fn new(mut outcomes: Vec<SessionOutcome>, elapsed: Duration) -> Self {
+        outcomes.sort_by_key(|o| o.session);
+        let count = |end: &str| outcomes.iter().filter(|o| o.end == end).count();
+        let (completed, stopped) = (count("completed"), count("stopped"));
+        let mut errors = BTreeMap::new();
+        for outcome in &outcomes {
+            if outcome.end != "completed" && outcome.end != "stopped" {
+                *errors.entry(outcome.end.clone()).or_default() += 1;
+            }
+        }
+        let events = outcomes.iter().map(|o| o.events).sum::<u64>();
+        let secs = elapsed.as_secs_f64();
+        let samples = |f: fn(&SessionOutcome) -> Option<f64>| {
+            Distribution::of(outcomes.iter().filter_map(f).collect())
+        };
+        Self {
+            sessions: outcomes.len(),
+            completed,
+            stopped,
+            failed: outcomes.len() - completed - stopped,
+            errors,
+            events,
+            elapsed_ms: millis(elapsed),
+            events_per_sec: if secs > 0.0 {
+                events as f64 / secs
+            } else {
+                0.0
+            },
+            connect_ms: samples(|o| o.connect_ms),
+            first_event_ms: samples(|o| o.first_event_ms),
+            max_gap_ms: samples(|o| o.max_gap_ms),
+            outcomes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SessionPlan {
+    session: usize,
+    start: Duration,
+    speed: Option<f64>,
+    symbols: Vec<String>,
+}
+
+fn plan(options: &StressOptions) -> Vec<SessionPlan> {
+    let sessions = options.sessions.max(1);
+    let spread = options.duration / 2;
+    let mut rng = Rng::new(options.seed);
+    (0..sessions)
+        .map(|session| {
+            let start = match options.profile {
+                StressProfile::Flat => Duration::ZERO,
+                StressProfile::Ramp => spread.mul_f64(session as f64 / sessions as f64),
+                StressProfile::Bursty => {
+                    let wave = session * BURST_WAVES / sessions;
+                    spread.mul_f64(wave as f64 / BURST_WAVES as f64)
+                }
+            };
+            let speed = SPEEDS[rng.below(SPEEDS.len())];
+            // Zero picks means no filter, i.e. every symbol.
+            let picks = rng.below(options.symbols.len().min(3) + 1);
+            let mut symbols = (0..picks)
+                .map(|_| options.symbols[rng.below(options.symbols.len())].clone())
+                .collect::<Vec<_>>();
+            symbols.sort();
+            symbols.dedup();
+            SessionPlan {
+                session,
+                start,
+                speed,
+                symbols,
+            }
+        })
+        .collect()
+}
+
+/// Runs `options.sessions` sessions against `endpoint` (e.g.
+/// `http://127.0.0.1:50051`), each on its own connection.
+pub async fn run_stress(
+    endpoint: &str,
+    options: &StressOptions,
+) -> Result<StressReport, ReplayError> {
+    let endpoint = Endpoint::from_shared(endpoint.to_string())?;
+    let start = Instant::now();
+    let deadline = start + options.duration;
+    let mut tasks = JoinSet::new();
+    for session in plan(options) {
+        tasks.spawn(run_session(
+            endpoint.clone(),
+            options.token.clone(),
+            session,
+            start,
+            deadline,
+        ));
+    }
+    let mut outcomes = Vec::with_capacity(options.sessions);
+    while let Some(done) = tasks.join_next().await {
+        match done {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(err) => warn!(error = %err, "stress session task failed"),
+        }
+    }
+    Ok(StressReport::new(outcomes, start.elapsed()))
+}
+
+async fn run_session(
+    endpoint: Endpoint,
+    token: Option<String>,
+    plan: SessionPlan,
+    start: Instant,
+    deadline: Instant,
+) -> SessionOutcome {
+    let opened = start + plan.start;
+    tokio::time::sleep_until(opened).await;
+    let mut outcome = SessionOutcome {
+        session: plan.session,
+        start_ms: millis(plan.start),
+        speed: plan.speed,
+        symbols: plan.symbols.clone(),
+        events: 0,
+        connect_ms: None,
+        first_event_ms: None,
+        max_gap_ms: None,
+        end: String::from("stopped"),
+    };
+    let mut request = Request::new(StreamRequest {
+        speed: plan.speed.unwrap_or(1.0),
+        max_speed: plan.speed.is_none(),
+        symbols: plan.symbols,
+        ..StreamRequest::default()
+    });
+    if let Some(value) = token.and_then(|t| format!("Bearer {t}").parse().ok()) {
+        request.metadata_mut().insert("authorization", value);
+    }
+
+    let run = async {
+        let channel = match endpoint.connect().await {
+            Ok(channel) => channel,
+            Err(_) => return String::from("connect"),
+        };
+        let mut stream = match ReplayServiceClient::new(channel)
+            .stream_events(request)
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            Err(status) => return format!("{:?}", status.code()),
+        };
+        outcome.connect_ms = Some(millis(opened.elapsed()));
+        let mut last = Instant::now();
+        loop {
+            match stream.next().await {
+                Some(Ok(_)) => {
+                    let now = Instant::now();
+                    if outcome.events == 0 {
+                        outcome.first_event_ms = Some(millis(now - opened));
+                    } else {
+                        let gap = millis(now - last);
+                        outcome.max_gap_ms = Some(outcome.max_gap_ms.unwrap_or(0.0).max(gap));
+                    }
+                    last = now;
+                    outcome.events += 1;
+                }
+                Some(Err(status)) => return format!("{:?}", status.code()),
+                None => return String::from("completed"),
+            }
+        }
+    };
+    let end = tokio::time::timeout_at(deadline, run).await;
+    if let Ok(end) = end {
+        outcome.end = end;
+    }
+    outcome
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_are_seeded_and_shaped_by_profile() {
+        let options = StressOptions {
+            sessions: 10,
+            symbols: vec![String::from("AAPL"), String::from("MSFT")],
+            duration: Duration::from_secs(10),
+            ..StressOptions::default()
+        };
+        let bursty = plan(&options);
+        assert_eq!(bursty, plan(&options));
+        let starts = bursty.iter().map(|p| p.start.as_secs()).collect::<Vec<_>>();
+        assert_eq!(starts, vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4]);
+        assert!(bursty.iter().all(|p| p.symbols.len() <= 2));
+        assert!(bursty.iter().any(|p| p.speed.is_none()));
+
+        let flat = plan(&StressOptions {
+            profile: StressProfile::Flat,
+            ..options.clone()
+        });
+        assert!(flat.iter().all(|p| p.start.is_zero()));
+        assert_eq!("ramp".parse::<StressProfile>(), Ok(StressProfile::Ramp));
+        assert!("spiky".parse::<StressProfile>().is_err());
+    }
+
+    #[test]
+    fn distributions_pick_nearest_rank() {
+        let d = Distribution::of((1..=100).map(f64::from).collect());
+        assert_eq!(
+            (d.count, d.p50, d.p90, d.p99, d.max),
+            (100, 51.0, 90.0, 99.0, 100.0)
+        );
+        assert_eq!(Distribution::of(Vec::new()), Distribution::default());
+    }
+}
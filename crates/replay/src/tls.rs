@@ -0,0 +1,195 @@
+//! TLS for the gRPC server, terminated with rustls in front of tonic, and
+//! for the channels `replay-requests` opens to one.
+
+use crate::engine::ReplayError;
+use hyper_util::rt::TokioIo;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::{Connected, TcpConnectInfo};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tracing::debug;
+
+/// Longest a client may take over its handshake before it is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Certificate chain and private key the server presents, both PEM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsIdentity {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsIdentity {
+    pub(crate) fn acceptor(&self) -> Result<TlsAcceptor, ReplayError> {
+        let certs = CertificateDer::pem_slice_iter(&read(&self.cert)?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| pem_error(&self.cert, err))?;
+        if certs.is_empty() {
+            return Err(ReplayError::Tls(format!(
+                "{}: no certificates",
+                self.cert.display()
+            )));
+        }
+        let key = PrivateKeyDer::from_pem_slice(&read(&self.key)?)
+            .map_err(|err| pem_error(&self.key, err))?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(|err| ReplayError::Tls(err.to_string()))?;
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, ReplayError> {
+    std::fs::read(path).map_err(|err| ReplayError::Tls(format!("{}: {err}", path.display())))
+}
+
+fn pem_error(path: &Path, err: rustls::pki_types::pem::Error) -> ReplayError {
+    ReplayError::Tls(format!("{}: {err}", path.display()))
+}
+
+/// Opens a channel to `endpoint` over TLS, trusting the PEM certificates
+/// in `ca`. The host of `endpoint` is the name the server's certificate
+/// must carry.
+pub(crate) async fn connect(endpoint: &str, ca: &Path) -> Result<Channel, ReplayError> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_slice_iter(&read(ca)?) {
+        let cert = cert.map_err(|err| pem_error(ca, err))?;
+        roots
+            .add(cert)
+            .map_err(|err| ReplayError::Tls(format!("{}: {err}", ca.display())))?;
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| ReplayError::Tls(err.to_string()))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    let connector = TlsConnect(TlsConnector::from(Arc::new(config)));
+    // The handshake happens in the connector, so the channel itself is
+    // addressed as plain HTTP/2.
+    let authority = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest);
+    Ok(Endpoint::from_shared(format!("http://{authority}"))?
+        .connect_with_connector(connector)
+        .await?)
+}
+
+/// Opens the TCP connection for a channel and runs the TLS handshake on
+/// it, naming the host of the channel's URI.
+#[derive(Clone)]
+struct TlsConnect(TlsConnector);
+
+impl tower_service::Service<Uri> for TlsConnect {
+    type Response = TokioIo<tokio_rustls::client::TlsStream<TcpStream>>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.0.clone();
+        Box::pin(async move {
+            let host = uri.host().unwrap_or_default().trim_matches(['[', ']']);
+            let port = uri.port_u16().unwrap_or(443);
+            let name = ServerName::try_from(host.to_string())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            let tcp = TcpStream::connect((name.to_str().as_ref(), port)).await?;
+            Ok(TokioIo::new(connector.connect(name, tcp).await?))
+        })
+    }
+}
+
+/// Connections accepted on `listener` once their handshake completes.
+/// Handshakes run concurrently, and one that fails or stalls only drops
+/// that client.
+pub(crate) fn incoming(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+) -> ReceiverStream<io::Result<TlsConn>> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        while !tx.is_closed() {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    debug!(error = %err, "accept failed");
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    continue;
+                }
+            };
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = tx.send(Ok(TlsConn(stream))).await;
+                    }
+                    Ok(Err(err)) => debug!(%peer, error = %err, "tls handshake failed"),
+                    Err(_) => debug!(%peer, "tls handshake timed out"),
+                }
+            });
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// A TLS connection that reports its peer as a plain TCP one would, so
+/// per-connection bookkeeping sees the client's address either way.
+pub(crate) struct TlsConn(TlsStream<TcpStream>);
+
+impl Connected for TlsConn {
+    type ConnectInfo = TcpConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.0.get_ref().0.connect_info()
+    }
+}
+
+impl AsyncRead for TlsConn {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConn {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,175 @@
+//! Event transforms run in-process before pacing: each one keeps, rewrites
+//! or drops events, and a [`Transforms`] chain runs several in order.
+//! [`ReplayConfig::transforms`](crate::ReplayConfig::transforms) carries the
+//! chain a replay applies.
+
+use crate::rng::Rng;
+use md_core::{Event, Payload, Timestamp};
+use std::fmt;
+use std::sync::Arc;
+
+/// Rewrites one event at a time.
+pub trait Transform: Send + Sync {
+    /// The event to replay in place of `event`, or `None` to drop it.
+    fn apply(&self, event: Event) -> Option<Event>;
+}
+
+/// Transforms run in the order they were pushed. Cloning shares them.
+#[derive(Clone, Default)]
+pub struct Transforms(Vec<Arc<dyn Transform>>);
+
+impl Transforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, transform: impl Transform + 'static) {
+        self.0.push(Arc::new(transform));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Runs every event through the chain, keeping their order.
+    pub fn apply_all(&self, events: Vec<Event>) -> Vec<Event> {
+        if self.is_empty() {
+            return events;
+        }
+        events.into_iter().filter_map(|e| self.apply(e)).collect()
+    }
+}
+
+impl Transform for Transforms {
+    fn apply(&self, event: Event) -> Option<Event> {
+        self.0.iter().try_fold(event, |event, t| t.apply(event))
+    }
+}
+
+impl fmt::Debug for Transforms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transforms({})", self.0.len())
+    }
+}
+
+/// Multiplies trade and quote sizes by `factor`, rounding to whole units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RescaleSizes {
+    pub factor: f64,
+}
+
+impl Transform for RescaleSizes {
+    fn apply(&self, mut event: Event) -> Option<Event> {
+        let scale = |size: &mut i64| *size = (*size as f64 * self.factor).round() as i64;
+        match &mut event.payload {
+            Payload::Trade { size, .. } => scale(size),
+            Payload::Quote { bid_sz, ask_sz, .. } => {
+                scale(bid_sz);
+                scale(ask_sz);
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Replaces each symbol with `S` and eight hex digits drawn from the
+/// symbol and `salt`, so a symbol gets the same stand-in in every replay
+/// with the same salt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AnonymizeSymbols {
+    pub salt: u64,
+}
+
+impl AnonymizeSymbols {
+    pub fn anonymize(&self, symbol: &str) -> String {
+        // FNV-1a, then one splitmix step to spread it.
+        let hash = symbol.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("S{:08X}", Rng::new(hash ^ self.salt).next() >> 32)
+    }
+}
+
+impl Transform for AnonymizeSymbols {
+    fn apply(&self, mut event: Event) -> Option<Event> {
+        event.symbol = self.anonymize(&event.symbol);
+        Some(event)
+    }
+}
+
+/// Moves every timestamp by `ns`, saturating at the representable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftTimestamps {
+    pub ns: i64,
+}
+
+impl Transform for ShiftTimestamps {
+    fn apply(&self, mut event: Event) -> Option<Event> {
+        event.timestamp_ns =
+            Timestamp::from_nanos(event.timestamp_ns.as_nanos().saturating_add(self.ns));
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    /// Drops quotes.
+    struct TradesOnly;
+
+    impl Transform for TradesOnly {
+        fn apply(&self, event: Event) -> Option<Event> {
+            matches!(event.payload, Payload::Trade { .. }).then_some(event)
+        }
+    }
+
+    #[test]
+    fn chains_filter_and_rewrite_in_order() {
+        let events = vec![
+            Event::trade(100, 1, "X", "AAPL", 10, 3),
+            Event::quote(
+                200,
+                2,
+                "X",
+                "AAPL",
+                QuoteTicks {
+                    bid_px: 9,
+                    bid_sz: 5,
+                    ask_px: 11,
+                    ask_sz: 7,
+                },
+            ),
+            Event::trade(300, 3, "X", "MSFT", 20, 5),
+        ];
+        let mut chain = Transforms::new();
+        chain.push(TradesOnly);
+        chain.push(RescaleSizes { factor: 2.5 });
+        chain.push(AnonymizeSymbols { salt: 1 });
+        chain.push(ShiftTimestamps { ns: -50 });
+        let out = chain.apply_all(events);
+
+        let rows = out
+            .iter()
+            .map(|e| match e.payload {
+                Payload::Trade { size, .. } => (e.timestamp_ns.as_nanos(), e.sequence, size),
+                Payload::Quote { .. } => unreachable!("quotes were dropped"),
+            })
+            .collect::<Vec<_>>();
+        // 7.5 and 12.5 round away from zero.
+        assert_eq!(rows, vec![(50, 1, 8), (250, 3, 13)]);
+        let anonymize = AnonymizeSymbols { salt: 1 };
+        assert_eq!(out[0].symbol, anonymize.anonymize("AAPL"));
+        assert_ne!(out[0].symbol, out[1].symbol);
+        assert_eq!(out[0].symbol.len(), 9);
+        assert_ne!(
+            anonymize.anonymize("AAPL"),
+            AnonymizeSymbols { salt: 2 }.anonymize("AAPL")
+        );
+    }
+}
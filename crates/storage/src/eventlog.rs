@@ -1,35 +1,172 @@
+use crate::schema::SchemaVersion;
 use crate::StorageError;
 use crc32fast::Hasher;
-use md_core::Event;
-use std::fs::File;
+use md_core::{Event, Payload, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const FILE_MAGIC: &[u8; 8] = b"MDELOG01";
-const FILE_VERSION: u16 = 1;
-const SCHEMA_DESC: &str = "event_v1";
+const FILE_VERSION: u16 = 3;
+const MIN_FILE_VERSION: u16 = 1;
+const FOOTER_TAG: u32 = u32::MAX;
+const FOOTER_MAGIC: &[u8; 8] = b"MDEFTR01";
+const FOOTER_TRAILER_LEN: u64 = 16;
+const MAX_SALVAGE_RECORD_LEN: u32 = 1 << 20;
 
 #[derive(Debug, Clone)]
 pub struct EventLogHeader {
     pub version: u16,
     pub schema_hash: u64,
+    pub schema: SchemaVersion,
     pub symbols: Vec<String>,
+    /// Decimal price of one stored tick, for symbols the writer knew it
+    /// for. Logs written before version 3 carry none.
+    pub price_units: BTreeMap<String, String>,
     pub data_offset: u64,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolCounts {
+    pub trades: u64,
+    pub quotes: u64,
+}
+
+/// Summary written after the last record so metadata queries can skip a
+/// full scan. Logs written before version 2 have no footer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLogFooter {
+    pub record_count: u64,
+    pub first_timestamp_ns: Timestamp,
+    pub last_timestamp_ns: Timestamp,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub symbols: BTreeMap<String, SymbolCounts>,
+}
+
+impl EventLogFooter {
+    pub fn record(&mut self, event: &Event) {
+        if self.record_count == 0 {
+            self.first_timestamp_ns = event.timestamp_ns;
+            self.first_sequence = event.sequence;
+        }
+        self.record_count += 1;
+        self.last_timestamp_ns = event.timestamp_ns;
+        self.last_sequence = event.sequence;
+        let counts = self.symbols.entry(event.symbol.clone()).or_default();
+        match event.payload {
+            Payload::Trade { .. } => counts.trades += 1,
+            Payload::Quote { .. } => counts.quotes += 1,
+        }
+    }
+}
+
+impl EventLogHeader {
+    /// Position of `symbol` in the header's symbol table.
+    pub fn symbol_id(&self, symbol: &str) -> Option<u32> {
+        self.symbols
+            .iter()
+            .position(|s| s == symbol)
+            .and_then(|i| u32::try_from(i).ok())
+    }
+}
+
+/// Predicate checked against each record's leading bytes, so records that
+/// do not match are skipped without a CRC check or full decode. Skipped
+/// records are therefore not validated. `symbol_ids` index
+/// `EventLogHeader::symbols`; `None` matches every symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordFilter {
+    pub from_ns: Option<Timestamp>,
+    pub to_ns: Option<Timestamp>,
+    pub from_sequence: Option<u64>,
+    pub to_sequence: Option<u64>,
+    pub symbol_ids: Option<Vec<u32>>,
+}
+
+impl RecordFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ReadRecord {
     pub offset: u64,
     pub event: Event,
 }
 
+/// Bytes `[offset, offset + len)` that could not be parsed as records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRange {
+    pub offset: u64,
+    pub len: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum SalvageItem {
+    Record(ReadRecord),
+    Skipped(SkippedRange),
+}
+
+/// `<log>.lock`, which a writer holds locked while the log is open.
+pub fn lock_path_for(log: &Path) -> PathBuf {
+    let mut path = log.as_os_str().to_os_string();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Takes the advisory lock on `<log>.lock`, held until the returned file
+/// is closed. A second writer fails at once instead of interleaving its
+/// appends. The sidecar is left in place, since removing it would let two
+/// writers lock different files.
+fn lock_log(path: &Path) -> Result<File, StorageError> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path_for(path))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+        Err(std::fs::TryLockError::WouldBlock) => {
+            Err(StorageError::Locked(path.display().to_string()))
+        }
+        Err(std::fs::TryLockError::Error(err)) => Err(err.into()),
+    }
+}
+
+/// Appends to one log. Every way of opening a writer takes the log's lock
+/// first (see [`lock_path_for`]), so at most one writer, in any process,
+/// works on a log and its index at a time.
 pub struct EventLogWriter {
     w: BufWriter<File>,
     offset: u64,
+    footer: EventLogFooter,
+    finalized: bool,
+    _lock: File,
 }
 
 impl EventLogWriter {
     pub fn create(path: &Path, symbols: &[String], schema_hash: u64) -> Result<Self, StorageError> {
+        Self::create_with_price_units(path, symbols, schema_hash, &BTreeMap::new())
+    }
+
+    /// [`create`](Self::create), recording the decimal price of one stored
+    /// tick per symbol in the header so readers can rescale prices without
+    /// the tick config the log was ingested with.
+    pub fn create_with_price_units(
+        path: &Path,
+        symbols: &[String],
+        schema_hash: u64,
+        price_units: &BTreeMap<String, String>,
+    ) -> Result<Self, StorageError> {
+        if SchemaVersion::from_hash(schema_hash) != Some(SchemaVersion::CURRENT) {
+            return Err(StorageError::UnknownSchema(schema_hash));
+        }
+        let lock = lock_log(path)?;
         let mut w = BufWriter::new(File::create(path)?);
         let mut offset = 0u64;
 
@@ -57,11 +194,101 @@ impl EventLogWriter {
             offset += 1 + bytes.len() as u64;
         }
 
-        Ok(Self { w, offset })
+        w.write_all(&(price_units.len() as u32).to_le_bytes())?;
+        offset += 4;
+        for (symbol, unit) in price_units {
+            for field in [symbol, unit] {
+                let bytes = field.as_bytes();
+                if bytes.len() > u8::MAX as usize {
+                    return Err(StorageError::InvalidFormat(format!(
+                        "price unit field too long: {field}"
+                    )));
+                }
+                w.write_all(&[bytes.len() as u8])?;
+                w.write_all(bytes)?;
+                offset += 1 + bytes.len() as u64;
+            }
+        }
+
+        Ok(Self {
+            w,
+            offset,
+            footer: EventLogFooter::default(),
+            finalized: false,
+            _lock: lock,
+        })
+    }
+
+    /// Reopens a finished log for appending. The footer is dropped and
+    /// rebuilt from the existing records, so the result reads back exactly as
+    /// if every event had been written in one session.
+    pub fn open_append(path: &Path) -> Result<Self, StorageError> {
+        let lock = lock_log(path)?;
+        Self::append_locked(path, lock)
+    }
+
+    fn append_locked(path: &Path, lock: File) -> Result<Self, StorageError> {
+        let mut reader = EventLogReader::open(path)?;
+        if needs_migration(&reader.header) {
+            return Err(StorageError::InvalidFormat(String::from(
+                "log must be migrated before appending",
+            )));
+        }
+        let mut footer = EventLogFooter::default();
+        let mut end = reader.header.data_offset;
+        while let Some(record) = reader.next_record()? {
+            footer.record(&record.event);
+            end = reader.r.stream_position()?;
+        }
+        drop(reader);
+
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        Ok(Self {
+            w: BufWriter::new(file),
+            offset: end,
+            footer,
+            finalized: false,
+            _lock: lock,
+        })
+    }
+
+    /// Reopens a log whose writer stopped without a footer, cutting it back
+    /// to `end`, a record boundary the writer had synced, then continues as
+    /// [`open_append`](Self::open_append) does. Records torn by a crash past
+    /// `end` are dropped.
+    pub fn resume(path: &Path, end: u64) -> Result<Self, StorageError> {
+        let lock = lock_log(path)?;
+        let file = OpenOptions::new().write(true).open(path)?;
+        if file.metadata()?.len() < end {
+            return Err(StorageError::InvalidFormat(format!(
+                "log is shorter than the {end} bytes it is resumed from"
+            )));
+        }
+        file.set_len(end)?;
+        drop(file);
+        Self::append_locked(path, lock)
+    }
+
+    /// End of the last record written, or of the footer once flushed.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Running summary of everything written so far, including records that
+    /// were already present when the log was opened for appending.
+    pub fn footer(&self) -> &EventLogFooter {
+        &self.footer
     }
 
     pub fn append(&mut self, event: &Event) -> Result<u64, StorageError> {
-        let payload = bincode::serialize(event)?;
+        if self.finalized {
+            return Err(StorageError::InvalidFormat(String::from(
+                "append after footer was written",
+            )));
+        }
+        let payload = SchemaVersion::encode(event)?;
         let len = payload.len() as u32;
         let crc = crc32fast::hash(&payload);
         let record_offset = self.offset;
@@ -71,22 +298,61 @@ impl EventLogWriter {
         self.w.write_all(&payload)?;
 
         self.offset += 8 + payload.len() as u64;
+        self.footer.record(event);
         Ok(record_offset)
     }
 
+    /// Pushes buffered records to the file without writing the footer, so
+    /// readers following the log (`poll_record`) see them and a crash loses
+    /// at most what was appended since.
+    pub fn sync(&mut self) -> Result<(), StorageError> {
+        self.w.flush()?;
+        Ok(())
+    }
+
+    /// Writes the footer (once) and flushes. No records may follow.
     pub fn flush(&mut self) -> Result<(), StorageError> {
+        if !self.finalized {
+            let payload = bincode::serialize(&self.footer)?;
+            let footer_offset = self.offset;
+            self.w.write_all(&FOOTER_TAG.to_le_bytes())?;
+            self.w.write_all(&(payload.len() as u32).to_le_bytes())?;
+            self.w.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+            self.w.write_all(&payload)?;
+            self.w.write_all(&footer_offset.to_le_bytes())?;
+            self.w.write_all(FOOTER_MAGIC)?;
+            self.offset += 12 + payload.len() as u64 + FOOTER_TRAILER_LEN;
+            self.finalized = true;
+        }
         self.w.flush()?;
         Ok(())
     }
 }
 
+/// Reads one log. Readers take no lock. A log without a footer may still
+/// be growing, so it reads up to the last record that was complete when it
+/// was opened; [`poll_record`](Self::poll_record) moves that end forward.
 pub struct EventLogReader {
     r: BufReader<File>,
     header: EventLogHeader,
+    footer: Option<EventLogFooter>,
+    data_end: u64,
+    stop_offset: Option<u64>,
+    payload: Vec<u8>,
 }
 
 impl EventLogReader {
     pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::open_inner(path, false)
+    }
+
+    /// Like `open`, but a damaged footer is treated as missing so that
+    /// `next_salvaged` can still walk the records.
+    pub fn open_for_salvage(path: &Path) -> Result<Self, StorageError> {
+        Self::open_inner(path, true)
+    }
+
+    fn open_inner(path: &Path, salvage: bool) -> Result<Self, StorageError> {
         let mut r = BufReader::new(File::open(path)?);
         let mut magic = [0u8; 8];
         r.read_exact(&mut magic)?;
@@ -95,41 +361,67 @@ impl EventLogReader {
         }
 
         let version = read_u16_le(&mut r)?;
-        if version != FILE_VERSION {
+        if !(MIN_FILE_VERSION..=FILE_VERSION).contains(&version) {
             return Err(StorageError::InvalidFormat(format!(
                 "unsupported version {version}"
             )));
         }
 
         let schema_hash = read_u64_le(&mut r)?;
+        let schema = SchemaVersion::from_hash(schema_hash)
+            .ok_or(StorageError::UnknownSchema(schema_hash))?;
         let symbol_count = read_u32_le(&mut r)? as usize;
         let mut symbols = Vec::with_capacity(symbol_count);
         for _ in 0..symbol_count {
-            let mut len = [0u8; 1];
-            r.read_exact(&mut len)?;
-            let mut sym = vec![0u8; len[0] as usize];
-            r.read_exact(&mut sym)?;
-            symbols.push(
-                String::from_utf8(sym)
-                    .map_err(|_| StorageError::InvalidFormat(String::from("symbol utf8")))?,
-            );
+            symbols.push(read_short_str(&mut r, "symbol utf8")?);
+        }
+        let mut price_units = BTreeMap::new();
+        if version >= 3 {
+            for _ in 0..read_u32_le(&mut r)? {
+                let symbol = read_short_str(&mut r, "price unit utf8")?;
+                price_units.insert(symbol, read_short_str(&mut r, "price unit utf8")?);
+            }
         }
 
         let data_offset = r.stream_position()?;
         let header = EventLogHeader {
             version,
             schema_hash,
+            schema,
             symbols,
+            price_units,
             data_offset,
         };
 
-        Ok(Self { r, header })
+        let footer = match read_footer(&mut r, data_offset) {
+            Ok(found) => found,
+            Err(_) if salvage => None,
+            Err(err) => return Err(err),
+        };
+        let data_end = match &footer {
+            Some((offset, _)) => *offset,
+            None => r.get_ref().metadata()?.len(),
+        };
+        r.seek(SeekFrom::Start(data_offset))?;
+
+        Ok(Self {
+            r,
+            header,
+            footer: footer.map(|(_, f)| f),
+            data_end,
+            stop_offset: None,
+            payload: Vec::new(),
+        })
     }
 
     pub fn header(&self) -> &EventLogHeader {
         &self.header
     }
 
+    pub fn footer(&self) -> Option<&EventLogFooter> {
+        self.footer.as_ref()
+    }
+
     pub fn seek(&mut self, offset: u64) -> Result<(), StorageError> {
         self.r.seek(SeekFrom::Start(offset))?;
         Ok(())
@@ -139,8 +431,57 @@ impl EventLogReader {
         self.seek(self.header.data_offset)
     }
 
+    /// Makes `next_record` report the end of the log once the read position
+    /// reaches `offset`, typically `IndexReader::end_offset` of a time window.
+    pub fn stop_at(&mut self, offset: u64) {
+        self.stop_offset = Some(offset);
+    }
+
     pub fn next_record(&mut self) -> Result<Option<ReadRecord>, StorageError> {
+        let Some((offset, crc)) = self.read_frame()? else {
+            return Ok(None);
+        };
+        self.decode_frame(offset, crc).map(Some)
+    }
+
+    /// Next record matching `filter`. The log is in timestamp order, so the
+    /// first record stamped after `filter.to_ns` ends the read.
+    pub fn next_record_filtered(
+        &mut self,
+        filter: &RecordFilter,
+    ) -> Result<Option<ReadRecord>, StorageError> {
+        while let Some((offset, crc)) = self.read_frame()? {
+            if let Some(key) = self.header.schema.peek(&self.payload) {
+                if filter.to_ns.is_some_and(|to| key.timestamp_ns > to) {
+                    return Ok(None);
+                }
+                let symbols = &self.header.symbols;
+                let skip = filter.from_ns.is_some_and(|from| key.timestamp_ns < from)
+                    || filter.from_sequence.is_some_and(|from| key.sequence < from)
+                    || filter.to_sequence.is_some_and(|to| key.sequence > to)
+                    || filter.symbol_ids.as_ref().is_some_and(|ids| {
+                        !ids.iter().any(|&id| {
+                            symbols
+                                .get(id as usize)
+                                .is_some_and(|s| s.as_bytes() == key.symbol)
+                        })
+                    });
+                if skip {
+                    continue;
+                }
+            }
+            return self.decode_frame(offset, crc).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Reads the next record's framing and payload bytes into
+    /// `self.payload`, returning its offset and stored CRC.
+    fn read_frame(&mut self) -> Result<Option<(u64, u32)>, StorageError> {
         let offset = self.r.stream_position()?;
+        if self.stop_offset.is_some_and(|stop| offset >= stop) {
+            return Ok(None);
+        }
 
         let mut len_buf = [0u8; 4];
         match self.r.read_exact(&mut len_buf) {
@@ -149,24 +490,169 @@ impl EventLogReader {
             Err(err) => return Err(StorageError::Io(err)),
         }
 
-        let len = u32::from_le_bytes(len_buf) as usize;
+        let len = u32::from_le_bytes(len_buf);
+        if len == FOOTER_TAG {
+            return Ok(None);
+        }
+        if self.footer.is_none() && offset + 8 + u64::from(len) > self.data_end {
+            self.r.seek(SeekFrom::Start(offset))?;
+            return Ok(None);
+        }
         let crc = read_u32_le(&mut self.r)?;
-        let mut payload = vec![0u8; len];
-        self.r.read_exact(&mut payload)?;
+        self.payload.resize(len as usize, 0);
+        self.r.read_exact(&mut self.payload)?;
+        Ok(Some((offset, crc)))
+    }
 
+    fn decode_frame(&self, offset: u64, crc: u32) -> Result<ReadRecord, StorageError> {
         let mut hasher = Hasher::new();
-        hasher.update(&payload);
+        hasher.update(&self.payload);
         if hasher.finalize() != crc {
             return Err(StorageError::CrcMismatch { offset });
         }
 
-        let event = bincode::deserialize::<Event>(&payload)?;
-        Ok(Some(ReadRecord { offset, event }))
+        let event = self.header.schema.decode(&self.payload)?;
+        Ok(ReadRecord { offset, event })
+    }
+
+    /// Reads the next record of a log that may still be growing. A footer or
+    /// a partially written record yields `None` and leaves the position
+    /// where it was, so the call can be retried once the writer has
+    /// appended more.
+    pub fn poll_record(&mut self) -> Result<Option<ReadRecord>, StorageError> {
+        let offset = self.r.stream_position()?;
+        let len = self.r.get_ref().metadata()?.len();
+        if len < offset {
+            return Err(StorageError::InvalidFormat(format!(
+                "log truncated below read position {offset}"
+            )));
+        }
+        if self.footer.is_none() {
+            self.data_end = len;
+        }
+        match self.next_record() {
+            Ok(Some(record)) => Ok(Some(record)),
+            Ok(None) => {
+                self.r.seek(SeekFrom::Start(offset))?;
+                Ok(None)
+            }
+            Err(StorageError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                self.r.seek(SeekFrom::Start(offset))?;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the next record, resynchronising past damage instead of
+    /// failing. A bad record yields one `Skipped` item covering the bytes up
+    /// to the next offset that holds a length-, CRC- and decode-valid record.
+    pub fn next_salvaged(&mut self) -> Result<Option<SalvageItem>, StorageError> {
+        let offset = self.r.stream_position()?;
+        if offset >= self.data_end {
+            return Ok(None);
+        }
+        let reason = match self.record_at(offset) {
+            Ok(Some((record, next))) => {
+                self.r.seek(SeekFrom::Start(next))?;
+                return Ok(Some(SalvageItem::Record(record)));
+            }
+            Ok(None) => String::from("implausible record length"),
+            Err(StorageError::Io(err)) if err.kind() != std::io::ErrorKind::UnexpectedEof => {
+                return Err(StorageError::Io(err));
+            }
+            Err(err) => err.to_string(),
+        };
+
+        let mut resume = self.data_end;
+        for candidate in offset + 1..self.data_end {
+            if matches!(self.record_at(candidate), Ok(Some(_))) {
+                resume = candidate;
+                break;
+            }
+        }
+        self.r.seek(SeekFrom::Start(resume))?;
+        Ok(Some(SalvageItem::Skipped(SkippedRange {
+            offset,
+            len: resume - offset,
+            reason,
+        })))
+    }
+
+    /// Parses a record at `offset` without trusting anything about it.
+    /// `Ok(None)` means the length prefix is not plausible.
+    fn record_at(&mut self, offset: u64) -> Result<Option<(ReadRecord, u64)>, StorageError> {
+        if offset + 8 > self.data_end {
+            return Ok(None);
+        }
+        self.r.seek(SeekFrom::Start(offset))?;
+        let len = read_u32_le(&mut self.r)?;
+        if len == 0 || len > MAX_SALVAGE_RECORD_LEN || offset + 8 + len as u64 > self.data_end {
+            return Ok(None);
+        }
+        let crc = read_u32_le(&mut self.r)?;
+        let mut payload = vec![0u8; len as usize];
+        self.r.read_exact(&mut payload)?;
+        if crc32fast::hash(&payload) != crc {
+            return Err(StorageError::CrcMismatch { offset });
+        }
+        let event = self.header.schema.decode(&payload)?;
+        Ok(Some((
+            ReadRecord { offset, event },
+            offset + 8 + len as u64,
+        )))
     }
 }
 
+fn read_footer(
+    r: &mut BufReader<File>,
+    data_offset: u64,
+) -> Result<Option<(u64, EventLogFooter)>, StorageError> {
+    let file_len = r.get_ref().metadata()?.len();
+    if file_len < data_offset + FOOTER_TRAILER_LEN {
+        return Ok(None);
+    }
+    r.seek(SeekFrom::End(-(FOOTER_TRAILER_LEN as i64)))?;
+    let footer_offset = read_u64_le(r)?;
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != FOOTER_MAGIC || footer_offset < data_offset {
+        return Ok(None);
+    }
+
+    r.seek(SeekFrom::Start(footer_offset))?;
+    if read_u32_le(r)? != FOOTER_TAG {
+        return Err(StorageError::InvalidFormat(String::from("bad footer tag")));
+    }
+    let len = read_u32_le(r)? as usize;
+    let crc = read_u32_le(r)?;
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    if crc32fast::hash(&payload) != crc {
+        return Err(StorageError::CrcMismatch {
+            offset: footer_offset,
+        });
+    }
+    Ok(Some((footer_offset, bincode::deserialize(&payload)?)))
+}
+
 pub fn default_schema_hash() -> u64 {
-    crc32fast::hash(SCHEMA_DESC.as_bytes()) as u64
+    SchemaVersion::CURRENT.hash()
+}
+
+/// True when the log was written with an older file format or schema than
+/// this build writes, i.e. `migrate` would change it.
+pub fn needs_migration(header: &EventLogHeader) -> bool {
+    header.version < FILE_VERSION || header.schema < SchemaVersion::CURRENT
+}
+
+/// A u8-length-prefixed UTF-8 string from the header.
+fn read_short_str<R: Read>(r: &mut R, what: &str) -> Result<String, StorageError> {
+    let mut len = [0u8; 1];
+    r.read_exact(&mut len)?;
+    let mut bytes = vec![0u8; len[0] as usize];
+    r.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| StorageError::InvalidFormat(String::from(what)))
 }
 
 fn read_u16_le<R: Read>(r: &mut R) -> Result<u16, StorageError> {
@@ -208,6 +694,151 @@ mod tests {
         let first = reader.next_record().expect("next").expect("record");
         assert_eq!(first.offset, offset);
         assert_eq!(first.event.sequence, 1);
+        assert!(reader.next_record().expect("next").is_none());
+    }
+
+    #[test]
+    fn append_mode_extends_log_and_rewrites_footer() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_append_{}.eventlog",
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        writer
+            .append(&Event::trade(1, 1, "X", "AAPL", 100, 2))
+            .expect("append");
+        writer.flush().expect("flush");
+        // One writer at a time, however it was opened.
+        assert!(matches!(
+            EventLogWriter::open_append(&path),
+            Err(StorageError::Locked(_))
+        ));
+        drop(writer);
+
+        let mut writer = EventLogWriter::open_append(&path).expect("reopen");
+        assert!(EventLogWriter::create(&path, &symbols, default_schema_hash()).is_err());
+        assert_eq!(writer.footer().last_sequence, 1);
+        writer
+            .append(&Event::trade(2, 2, "X", "AAPL", 101, 2))
+            .expect("append");
+        writer.flush().expect("flush");
+
+        let mut reader = EventLogReader::open(&path).expect("reader");
+        let footer = reader.footer().cloned().expect("footer");
+        assert_eq!(footer.record_count, 2);
+        assert_eq!(footer.last_timestamp_ns.as_nanos(), 2);
+        let mut seqs = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seqs.push(record.event.sequence);
+        }
+        assert_eq!(seqs, vec![1, 2]);
+    }
+
+    #[test]
+    fn resume_cuts_back_to_the_synced_end() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_resume_{}.eventlog",
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        writer
+            .append(&Event::trade(1, 1, "X", "AAPL", 100, 2))
+            .expect("append");
+        writer.sync().expect("sync");
+        let end = writer.offset();
+        writer
+            .append(&Event::trade(2, 2, "X", "AAPL", 101, 2))
+            .expect("append");
+        writer.sync().expect("sync");
+        drop(writer);
+
+        let mut writer = EventLogWriter::resume(&path, end).expect("resume");
+        assert_eq!(writer.footer().record_count, 1);
+        writer
+            .append(&Event::trade(3, 2, "X", "AAPL", 102, 2))
+            .expect("append");
+        writer.flush().expect("flush");
+
+        let mut reader = EventLogReader::open(&path).expect("reader");
+        let mut times = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            times.push(record.event.timestamp_ns.as_nanos());
+        }
+        assert_eq!(times, vec![1, 3]);
+        assert!(EventLogWriter::resume(&path, u64::MAX).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn unknown_schema_is_rejected() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_schema_{}.eventlog",
+            std::process::id()
+        ));
+
+        assert!(matches!(
+            EventLogWriter::create(&path, &[], 0xdead_beef),
+            Err(StorageError::UnknownSchema(0xdead_beef))
+        ));
+
+        EventLogWriter::create(&path, &[], default_schema_hash())
+            .expect("writer")
+            .flush()
+            .expect("flush");
+        let mut bytes = std::fs::read(&path).expect("read file");
+        bytes[10..18].copy_from_slice(&0xdead_beefu64.to_le_bytes());
+        std::fs::write(&path, bytes).expect("rewrite file");
+        assert!(matches!(
+            EventLogReader::open(&path),
+            Err(StorageError::UnknownSchema(0xdead_beef))
+        ));
+    }
+
+    #[test]
+    fn footer_summarizes_records() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_footer_{}.eventlog",
+            std::process::id()
+        ));
+
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        writer
+            .append(&Event::trade(10, 1, "X", "AAPL", 100, 2))
+            .expect("append");
+        writer
+            .append(&Event::trade(20, 2, "X", "MSFT", 100, 2))
+            .expect("append");
+        writer
+            .append(&Event::trade(30, 3, "X", "AAPL", 101, 1))
+            .expect("append");
+        writer.flush().expect("flush");
+        assert!(writer
+            .append(&Event::trade(40, 4, "X", "AAPL", 101, 1))
+            .is_err());
+
+        let reader = EventLogReader::open(&path).expect("reader");
+        let footer = reader.footer().expect("footer");
+        assert_eq!(footer.record_count, 3);
+        assert_eq!(
+            (
+                footer.first_timestamp_ns.as_nanos(),
+                footer.last_timestamp_ns.as_nanos()
+            ),
+            (10, 30)
+        );
+        assert_eq!((footer.first_sequence, footer.last_sequence), (1, 3));
+        assert_eq!(footer.symbols["AAPL"].trades, 2);
+        assert_eq!(footer.symbols["MSFT"].trades, 1);
     }
 
     #[test]
@@ -221,14 +852,14 @@ mod tests {
         let mut writer =
             EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
                 .expect("writer");
-        writer
+        let offset = writer
             .append(&Event::trade(1, 1, "X", "AAPL", 100, 2))
             .expect("append");
         writer.flush().expect("flush");
 
         let mut bytes = std::fs::read(&path).expect("read file");
-        let last = bytes.len() - 1;
-        bytes[last] ^= 0x55;
+        let payload_start = offset as usize + 8;
+        bytes[payload_start] ^= 0x55;
         std::fs::write(&path, bytes).expect("rewrite file");
 
         let mut reader = EventLogReader::open(&path).expect("open");
@@ -238,4 +869,250 @@ mod tests {
             _ => panic!("unexpected error"),
         }
     }
+
+    #[test]
+    fn filtered_reads_skip_records_without_decoding_them() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_filtered_{}.eventlog",
+            std::process::id()
+        ));
+
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        let mut offsets = Vec::new();
+        for i in 1..=8u64 {
+            let symbol = if i % 2 == 0 { "MSFT" } else { "AAPL" };
+            offsets.push(
+                writer
+                    .append(&Event::trade(i as i64 * 10, i, "X", symbol, 100, 2))
+                    .expect("append"),
+            );
+        }
+        writer.flush().expect("flush");
+
+        // Damage the tail of a MSFT record; its leading fields stay intact.
+        let mut bytes = std::fs::read(&path).expect("read file");
+        let at = offsets[3] as usize;
+        let len = u32::from_le_bytes(bytes[at..at + 4].try_into().expect("len")) as usize;
+        bytes[at + 8 + len - 1] ^= 0xff;
+        std::fs::write(&path, bytes).expect("rewrite file");
+
+        let read_all = |filter: &RecordFilter| {
+            let mut reader = EventLogReader::open(&path).expect("open");
+            let mut seqs = Vec::new();
+            while let Some(record) = reader.next_record_filtered(filter).expect("next") {
+                seqs.push(record.event.sequence);
+            }
+            seqs
+        };
+        let header = EventLogReader::open(&path).expect("open").header().clone();
+        let aapl = RecordFilter {
+            symbol_ids: Some(vec![header.symbol_id("AAPL").expect("id")]),
+            ..RecordFilter::default()
+        };
+        assert_eq!(read_all(&aapl), vec![1, 3, 5, 7]);
+
+        let window = RecordFilter {
+            from_ns: Some(Timestamp::from_nanos(25)),
+            to_ns: Some(Timestamp::from_nanos(60)),
+            from_sequence: Some(5),
+            ..RecordFilter::default()
+        };
+        assert_eq!(read_all(&window), vec![5, 6]);
+
+        let mut reader = EventLogReader::open(&path).expect("open");
+        let unfiltered = RecordFilter::default();
+        assert!(unfiltered.is_empty());
+        let mut err = None;
+        loop {
+            match reader.next_record_filtered(&unfiltered) {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) => {
+                    err = Some(e);
+                    break;
+                }
+            }
+        }
+        assert!(matches!(err, Some(StorageError::CrcMismatch { .. })));
+    }
+
+    #[test]
+    fn salvage_resyncs_after_corrupt_record() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_salvage_{}.eventlog",
+            std::process::id()
+        ));
+
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        let mut offsets = Vec::new();
+        for i in 1..=3u64 {
+            offsets.push(
+                writer
+                    .append(&Event::trade(i as i64, i, "X", "AAPL", 100, 2))
+                    .expect("append"),
+            );
+        }
+        writer.flush().expect("flush");
+
+        let mut bytes = std::fs::read(&path).expect("read file");
+        bytes[offsets[1] as usize + 10] ^= 0xff;
+        std::fs::write(&path, bytes).expect("rewrite file");
+
+        let mut reader = EventLogReader::open_for_salvage(&path).expect("open");
+        let mut seqs = Vec::new();
+        let mut skipped = Vec::new();
+        while let Some(item) = reader.next_salvaged().expect("salvage") {
+            match item {
+                SalvageItem::Record(r) => seqs.push(r.event.sequence),
+                SalvageItem::Skipped(range) => skipped.push(range),
+            }
+        }
+        assert_eq!(seqs, vec![1, 3]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].offset, offsets[1]);
+        assert_eq!(skipped[0].offset + skipped[0].len, offsets[2]);
+    }
+
+    #[test]
+    fn readers_see_records_complete_when_opened() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_view_{}.eventlog",
+            std::process::id()
+        ));
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        writer
+            .append(&Event::trade(1, 1, "X", "AAPL", 100, 2))
+            .expect("append");
+        writer.sync().expect("sync");
+        let mut full = std::fs::read(&path).expect("read file");
+        writer
+            .append(&Event::trade(2, 2, "X", "AAPL", 101, 2))
+            .expect("append");
+        writer.sync().expect("sync");
+
+        let mut reader = EventLogReader::open(&path).expect("open");
+        writer
+            .append(&Event::trade(3, 3, "X", "AAPL", 102, 2))
+            .expect("append");
+        writer.sync().expect("sync");
+        let mut seqs = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seqs.push(record.event.sequence);
+        }
+        assert_eq!(seqs, vec![1, 2]);
+        let polled = reader
+            .poll_record()
+            .expect("poll")
+            .map(|r| r.event.sequence);
+        assert_eq!(polled, Some(3));
+        drop(writer);
+
+        // A record torn mid-write ends the view rather than failing it.
+        full.extend_from_slice(&[9, 0, 0, 0, 1, 2]);
+        std::fs::write(&path, &full).expect("write");
+        let mut reader = EventLogReader::open(&path).expect("open");
+        let first = reader.next_record().expect("next");
+        assert_eq!(first.map(|r| r.event.sequence), Some(1));
+        assert!(reader.next_record().expect("end").is_none());
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(lock_path_for(&path)).ok();
+    }
+
+    #[test]
+    fn poll_waits_for_partial_records_and_appends() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_poll_{}.eventlog",
+            std::process::id()
+        ));
+
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        writer
+            .append(&Event::trade(1, 1, "X", "AAPL", 100, 2))
+            .expect("append");
+        let second = writer
+            .append(&Event::trade(2, 2, "X", "AAPL", 101, 2))
+            .expect("append");
+        writer.flush().expect("flush");
+
+        let full = std::fs::read(&path).expect("read file");
+        drop(writer);
+        std::fs::write(&path, &full[..second as usize + 5]).expect("truncate");
+
+        let mut reader = EventLogReader::open(&path).expect("open");
+        let poll_seq = |reader: &mut EventLogReader| {
+            reader
+                .poll_record()
+                .expect("poll")
+                .map(|r| r.event.sequence)
+        };
+        assert_eq!(poll_seq(&mut reader), Some(1));
+        assert_eq!(poll_seq(&mut reader), None);
+        assert_eq!(poll_seq(&mut reader), None);
+
+        std::fs::write(&path, &full).expect("restore");
+        assert_eq!(poll_seq(&mut reader), Some(2));
+        assert_eq!(poll_seq(&mut reader), None);
+
+        let mut writer = EventLogWriter::open_append(&path).expect("reopen");
+        writer
+            .append(&Event::trade(3, 3, "X", "AAPL", 102, 2))
+            .expect("append");
+        writer.flush().expect("flush");
+        assert_eq!(poll_seq(&mut reader), Some(3));
+        assert_eq!(poll_seq(&mut reader), None);
+    }
+
+    #[test]
+    fn header_carries_price_units() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("md_replay_units_{}.eventlog", std::process::id()));
+        let symbols = vec![String::from("BTCUSDT"), String::from("IDX")];
+        let units = BTreeMap::from([
+            (String::from("BTCUSDT"), String::from("0.00000001")),
+            (String::from("IDX"), String::from("0.000001")),
+        ]);
+        let mut writer =
+            EventLogWriter::create_with_price_units(&path, &symbols, default_schema_hash(), &units)
+                .expect("writer");
+        writer
+            .append(&Event::trade(1, 1, "X", "IDX", 123_456_789_012_345_679, 1))
+            .expect("append");
+        writer.flush().expect("flush");
+        drop(writer);
+
+        let mut reader = EventLogReader::open(&path).expect("reader");
+        assert_eq!(reader.header().version, FILE_VERSION);
+        assert_eq!(reader.header().price_units, units);
+        assert!(!needs_migration(reader.header()));
+        let event = reader.next_record().expect("read").expect("record").event;
+        assert_eq!(event.symbol, "IDX");
+
+        // A version 2 header has no units and needs migrating.
+        let old = dir.join(format!(
+            "md_replay_units_v2_{}.eventlog",
+            std::process::id()
+        ));
+        let mut bytes = FILE_MAGIC.to_vec();
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&default_schema_hash().to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(b"\x04AAPL");
+        std::fs::write(&old, bytes).expect("write");
+        let reader = EventLogReader::open(&old).expect("reader");
+        assert_eq!(reader.header().symbols, ["AAPL"]);
+        assert!(reader.header().price_units.is_empty());
+        assert!(needs_migration(reader.header()));
+    }
 }
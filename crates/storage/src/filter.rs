@@ -0,0 +1,171 @@
+use crate::eventlog::{default_schema_hash, EventLogReader, EventLogWriter, RecordFilter};
+use crate::index::{index_path_for, IndexReader, IndexWriter};
+use crate::StorageError;
+use md_core::{Event, EventType, Timestamp};
+use std::path::Path;
+
+/// Empty `symbols` matches every symbol; symbol comparison ignores case to
+/// match the UI and gRPC filters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub symbols: Vec<String>,
+    pub from_ns: Option<Timestamp>,
+    pub to_ns: Option<Timestamp>,
+    pub event_type: Option<EventType>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        if self.from_ns.is_some_and(|from| event.timestamp_ns < from) {
+            return false;
+        }
+        if self.to_ns.is_some_and(|to| event.timestamp_ns > to) {
+            return false;
+        }
+        if self.event_type.is_some_and(|t| t != event.event_type) {
+            return false;
+        }
+        self.symbols.is_empty()
+            || self
+                .symbols
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&event.symbol))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterSummary {
+    /// Records decoded; records rejected on their time or symbol alone are
+    /// skipped undecoded and not counted.
+    pub read: u64,
+    pub written: u64,
+}
+
+/// Copies the events matching `filter` into a new log and index, renumbering
+/// sequences from 1. Uses the input's sidecar index, when one exists, to
+/// skip ahead to `from_ns` and to stop reading past `to_ns`.
+pub fn filter_log(
+    input: &Path,
+    out: &Path,
+    index_path: &Path,
+    filter: &EventFilter,
+    index_stride: u32,
+) -> Result<FilterSummary, StorageError> {
+    let mut reader = EventLogReader::open(input)?;
+    let input_index = index_path_for(input);
+    if (filter.from_ns.is_some() || filter.to_ns.is_some()) && input_index.exists() {
+        let index = IndexReader::open(&input_index)?;
+        if let Some(offset) = filter.from_ns.and_then(|from| index.seek_offset(from)) {
+            reader.seek(offset)?;
+        }
+        if let Some(offset) = filter.to_ns.and_then(|to| index.end_offset(to)) {
+            reader.stop_at(offset);
+        }
+    }
+
+    let symbols = reader
+        .header()
+        .symbols
+        .iter()
+        .filter(|s| {
+            filter.symbols.is_empty() || filter.symbols.iter().any(|f| f.eq_ignore_ascii_case(s))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    // Appends can add symbols the header table does not list; only filter
+    // by symbol id when the footer shows the table is complete.
+    let header = reader.header();
+    let table_complete = reader
+        .footer()
+        .is_some_and(|f| f.symbols.keys().all(|s| header.symbol_id(s).is_some()));
+    let pushdown = RecordFilter {
+        from_ns: filter.from_ns,
+        to_ns: filter.to_ns,
+        symbol_ids: (table_complete && !filter.symbols.is_empty())
+            .then(|| symbols.iter().filter_map(|s| header.symbol_id(s)).collect()),
+        ..RecordFilter::default()
+    };
+    let price_units = header
+        .price_units
+        .iter()
+        .filter(|(s, _)| symbols.contains(s))
+        .map(|(s, u)| (s.clone(), u.clone()))
+        .collect();
+    let mut writer = EventLogWriter::create_with_price_units(
+        out,
+        &symbols,
+        default_schema_hash(),
+        &price_units,
+    )?;
+    let mut idx = IndexWriter::create(index_path, index_stride)?;
+
+    let mut summary = FilterSummary {
+        read: 0,
+        written: 0,
+    };
+    while let Some(record) = reader.next_record_filtered(&pushdown)? {
+        summary.read += 1;
+        let mut event = record.event;
+        if !filter.matches(&event) {
+            continue;
+        }
+        summary.written += 1;
+        event.sequence = summary.written;
+        let offset = writer.append(&event)?;
+        idx.maybe_add(&event, offset)?;
+    }
+    writer.flush()?;
+    idx.flush()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::QuoteTicks;
+
+    #[test]
+    fn filters_and_resequences() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("md_replay_filter_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let input = dir.join("in.eventlog");
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&input, &symbols, default_schema_hash()).expect("writer");
+        let quote = QuoteTicks {
+            bid_px: 1,
+            bid_sz: 1,
+            ask_px: 2,
+            ask_sz: 1,
+        };
+        for event in [
+            Event::trade(1, 1, "X", "AAPL", 100, 1),
+            Event::quote(2, 2, "X", "AAPL", quote),
+            Event::trade(3, 3, "X", "MSFT", 200, 1),
+            Event::trade(4, 4, "X", "AAPL", 101, 1),
+            Event::trade(9, 5, "X", "AAPL", 102, 1),
+        ] {
+            writer.append(&event).expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let out = dir.join("out.eventlog");
+        let filter = EventFilter {
+            symbols: vec![String::from("aapl")],
+            from_ns: Some(Timestamp::from_nanos(1)),
+            to_ns: Some(Timestamp::from_nanos(5)),
+            event_type: Some(EventType::Trade),
+        };
+        let summary = filter_log(&input, &out, &index_path_for(&out), &filter, 1).expect("filter");
+        assert_eq!(summary.written, 2);
+
+        let mut reader = EventLogReader::open(&out).expect("reader");
+        assert_eq!(reader.header().symbols, vec![String::from("AAPL")]);
+        let mut seen = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seen.push((record.event.sequence, record.event.timestamp_ns.as_nanos()));
+        }
+        assert_eq!(seen, vec![(1, 1), (2, 4)]);
+    }
+}
@@ -1,15 +1,20 @@
 use crate::StorageError;
-use md_core::Event;
-use std::fs::File;
+use md_core::{Event, Timestamp};
+use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const IDX_MAGIC: &[u8; 8] = b"MDEIDX01";
 const IDX_VERSION: u16 = 1;
 
+/// Sidecar index location used by every command: `<log>.idx`.
+pub fn index_path_for(log: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.idx", log.display()))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct IndexEntry {
-    pub timestamp_ns: u64,
+    pub timestamp_ns: Timestamp,
     pub sequence: u64,
     pub byte_offset: u64,
 }
@@ -18,8 +23,12 @@ pub struct IndexWriter {
     w: BufWriter<File>,
     stride: u32,
     seen: u64,
+    offset: u64,
 }
 
+const IDX_HEADER_LEN: u64 = 14;
+const IDX_ENTRY_LEN: u64 = 24;
+
 impl IndexWriter {
     pub fn create(path: &Path, stride: u32) -> Result<Self, StorageError> {
         if stride == 0 {
@@ -31,14 +40,55 @@ impl IndexWriter {
         w.write_all(IDX_MAGIC)?;
         w.write_all(&IDX_VERSION.to_le_bytes())?;
         w.write_all(&stride.to_le_bytes())?;
-        Ok(Self { w, stride, seen: 0 })
+        Ok(Self {
+            w,
+            stride,
+            seen: 0,
+            offset: IDX_HEADER_LEN,
+        })
+    }
+
+    /// Continues an existing index for a log that already holds `records`
+    /// events, keeping the original stride.
+    pub fn open_append(path: &Path, records: u64) -> Result<Self, StorageError> {
+        let stride = IndexReader::open(path)?.stride;
+        let file = OpenOptions::new().append(true).open(path)?;
+        let offset = file.metadata()?.len();
+        Ok(Self {
+            w: BufWriter::new(file),
+            stride,
+            seen: records,
+            offset,
+        })
+    }
+
+    /// [`open_append`](Self::open_append) after cutting the index back to
+    /// `end` bytes, matching a log resumed with
+    /// [`EventLogWriter::resume`](crate::EventLogWriter::resume).
+    pub fn resume(path: &Path, records: u64, end: u64) -> Result<Self, StorageError> {
+        let file = OpenOptions::new().write(true).open(path)?;
+        if end < IDX_HEADER_LEN || file.metadata()?.len() < end {
+            return Err(StorageError::InvalidFormat(format!(
+                "index cannot be resumed from {end} bytes"
+            )));
+        }
+        file.set_len(end)?;
+        drop(file);
+        Self::open_append(path, records)
+    }
+
+    /// Bytes written so far, header included.
+    pub fn offset(&self) -> u64 {
+        self.offset
     }
 
     pub fn maybe_add(&mut self, event: &Event, offset: u64) -> Result<(), StorageError> {
         if self.seen.is_multiple_of(self.stride as u64) {
-            self.w.write_all(&event.timestamp_ns.to_le_bytes())?;
+            self.w
+                .write_all(&event.timestamp_ns.as_nanos().to_le_bytes())?;
             self.w.write_all(&event.sequence.to_le_bytes())?;
             self.w.write_all(&offset.to_le_bytes())?;
+            self.offset += IDX_ENTRY_LEN;
         }
         self.seen += 1;
         Ok(())
@@ -81,7 +131,7 @@ impl IndexReader {
                 Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
                 Err(err) => return Err(StorageError::Io(err)),
             }
-            let timestamp_ns = u64::from_le_bytes(ts_buf);
+            let timestamp_ns = Timestamp::from_nanos(i64::from_le_bytes(ts_buf));
             let sequence = read_u64_le(&mut r)?;
             let byte_offset = read_u64_le(&mut r)?;
             entries.push(IndexEntry {
@@ -102,7 +152,7 @@ impl IndexReader {
         &self.entries
     }
 
-    pub fn seek_offset(&self, from_ns: u64) -> Option<u64> {
+    pub fn seek_offset(&self, from_ns: Timestamp) -> Option<u64> {
         if self.entries.is_empty() {
             return None;
         }
@@ -113,6 +163,14 @@ impl IndexReader {
             Some(self.entries[idx - 1].byte_offset)
         }
     }
+
+    /// Byte offset of the first indexed record stamped after `to_ns`.
+    /// Nothing at or past it can fall inside the window, so a reader can
+    /// stop there. `None` means the window may run to the end of the log.
+    pub fn end_offset(&self, to_ns: Timestamp) -> Option<u64> {
+        let idx = self.entries.partition_point(|e| e.timestamp_ns <= to_ns);
+        self.entries.get(idx).map(|e| e.byte_offset)
+    }
 }
 
 fn read_u16_le<R: Read>(r: &mut R) -> Result<u16, StorageError> {
@@ -143,7 +201,7 @@ mod tests {
         path.push(format!("md_replay_idx_{}.idx", std::process::id()));
 
         let mut w = IndexWriter::create(&path, 2).expect("writer");
-        let events = vec![
+        let events = [
             Event::trade(100, 1, "X", "AAPL", 1, 1),
             Event::trade(200, 2, "X", "AAPL", 1, 1),
             Event::trade(300, 3, "X", "AAPL", 1, 1),
@@ -155,8 +213,11 @@ mod tests {
         w.flush().expect("flush");
 
         let idx = IndexReader::open(&path).expect("index open");
-        assert_eq!(idx.seek_offset(50), Some(0));
-        assert_eq!(idx.seek_offset(250), Some(0));
-        assert_eq!(idx.seek_offset(350), Some(200));
+        assert_eq!(idx.seek_offset(Timestamp::from_nanos(50)), Some(0));
+        assert_eq!(idx.seek_offset(Timestamp::from_nanos(250)), Some(0));
+        assert_eq!(idx.seek_offset(Timestamp::from_nanos(350)), Some(200));
+        assert_eq!(idx.end_offset(Timestamp::from_nanos(50)), Some(0));
+        assert_eq!(idx.end_offset(Timestamp::from_nanos(250)), Some(200));
+        assert_eq!(idx.end_offset(Timestamp::from_nanos(300)), None);
     }
 }
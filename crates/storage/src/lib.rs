@@ -1,14 +1,42 @@
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
 pub mod eventlog;
+pub mod filter;
 pub mod index;
+pub mod merge;
+pub mod orderlog;
+pub mod repair;
+pub mod schema;
+pub mod slice;
+pub mod split;
+pub mod stats;
+pub mod testing;
 
 pub use eventlog::{
-    default_schema_hash, EventLogHeader, EventLogReader, EventLogWriter, ReadRecord,
+    default_schema_hash, lock_path_for, needs_migration, EventLogFooter, EventLogHeader,
+    EventLogReader, EventLogWriter, ReadRecord, RecordFilter, SalvageItem, SkippedRange,
+    SymbolCounts,
+};
+pub use filter::{filter_log, EventFilter, FilterSummary};
+pub use index::{index_path_for, IndexEntry, IndexReader, IndexWriter};
+pub use merge::{merge_logs, MergeSummary};
+pub use orderlog::{write_order_log, OrderLogReader};
+pub use repair::{repair_log, RepairReport};
+pub use schema::SchemaVersion;
+pub use slice::{
+    slice_top_symbols, symbol_activity, top_symbols, ActivityBy, SliceSummary, SymbolActivity,
 };
-pub use index::{IndexEntry, IndexReader, IndexWriter};
+pub use split::{split_log, SplitBy, SplitPart};
+pub use stats::{log_stats, IndexCoverage, LogStats, StatsSource, TimeGap};
 
+use md_core::ErrorKind;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum StorageError {
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
@@ -18,4 +46,47 @@ pub enum StorageError {
     CrcMismatch { offset: u64 },
     #[error("invalid file format: {0}")]
     InvalidFormat(String),
+    #[error("unknown schema hash {0:#x}")]
+    UnknownSchema(u64),
+    #[error("{0} is locked by another writer")]
+    Locked(String),
+}
+
+impl StorageError {
+    /// A log this build cannot read (unknown schema) counts as `Config`;
+    /// anything that fails framing, CRC or decoding counts as `Corrupt`.
+    /// Another writer's lock is `Transient`, since it goes once that
+    /// writer finishes.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(err) => ErrorKind::from_io(err),
+            Self::Locked(_) => ErrorKind::Transient,
+            Self::Serialize(_) | Self::CrcMismatch { .. } | Self::InvalidFormat(_) => {
+                ErrorKind::Corrupt
+            }
+            Self::UnknownSchema(_) => ErrorKind::Config,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_storage_errors() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(StorageError::Io(missing).kind(), ErrorKind::Config);
+        assert_eq!(
+            StorageError::CrcMismatch { offset: 8 }.kind(),
+            ErrorKind::Corrupt
+        );
+        assert_eq!(StorageError::UnknownSchema(1).kind(), ErrorKind::Config);
+        let interrupted = std::io::Error::from(std::io::ErrorKind::Interrupted);
+        assert!(StorageError::Io(interrupted).is_retryable());
+    }
 }
@@ -0,0 +1,244 @@
+use crate::eventlog::{default_schema_hash, EventLogReader, EventLogWriter};
+use crate::index::IndexWriter;
+use crate::StorageError;
+use md_core::{assign_sequences, Event, OrderCheck, PendingEvent};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeSummary {
+    pub inputs: usize,
+    pub events: u64,
+}
+
+/// K-way merges `inputs` by timestamp into a new log and index. Ties keep
+/// input order, then each input's own sequence order; sequences are then
+/// reassigned from 1 over the combined stream.
+pub fn merge_logs(
+    inputs: &[PathBuf],
+    out: &Path,
+    index_path: &Path,
+    index_stride: u32,
+) -> Result<MergeSummary, StorageError> {
+    if inputs.is_empty() {
+        return Err(StorageError::InvalidFormat(String::from(
+            "merge needs at least one input log",
+        )));
+    }
+
+    let mut readers = Vec::with_capacity(inputs.len());
+    let mut heads: Vec<Option<Event>> = Vec::with_capacity(inputs.len());
+    let mut heap = BinaryHeap::new();
+    let mut symbols = BTreeSet::new();
+    let mut price_units = BTreeMap::<String, String>::new();
+    for (source, path) in inputs.iter().enumerate() {
+        let mut reader = EventLogReader::open(path)?;
+        symbols.extend(reader.header().symbols.iter().cloned());
+        // Prices of a symbol stored in different units cannot share a log.
+        for (symbol, unit) in &reader.header().price_units {
+            match price_units.get(symbol) {
+                Some(seen) if seen != unit => {
+                    return Err(StorageError::InvalidFormat(format!(
+                        "{}: price unit {unit} for {symbol} differs from {seen}",
+                        path.display()
+                    )));
+                }
+                _ => {
+                    price_units.insert(symbol.clone(), unit.clone());
+                }
+            }
+        }
+        let head = reader.next_record()?.map(|r| r.event);
+        if let Some(event) = &head {
+            heap.push(Reverse((event.timestamp_ns, source, event.sequence)));
+        }
+        heads.push(head);
+        readers.push(reader);
+    }
+
+    let mut pending = Vec::new();
+    while let Some(Reverse((_, source, _))) = heap.pop() {
+        let Some(event) = heads[source].take() else {
+            continue;
+        };
+        pending.push(PendingEvent {
+            timestamp_ns: event.timestamp_ns,
+            venue: event.venue,
+            symbol: event.symbol,
+            payload: event.payload,
+            ingest_order: pending.len() as u64,
+            source_sequence: event.source_sequence,
+        });
+        if let Some(next) = readers[source].next_record()?.map(|r| r.event) {
+            heap.push(Reverse((next.timestamp_ns, source, next.sequence)));
+            heads[source] = Some(next);
+        }
+    }
+
+    let events = assign_sequences(pending);
+    symbols.extend(events.iter().map(|e| e.symbol.clone()));
+    let symbols = symbols.into_iter().collect::<Vec<_>>();
+
+    let mut writer = EventLogWriter::create_with_price_units(
+        out,
+        &symbols,
+        default_schema_hash(),
+        &price_units,
+    )?;
+    let mut idx = IndexWriter::create(index_path, index_stride)?;
+    let mut order = OrderCheck::new("merge");
+    for event in &events {
+        order.observe(event);
+        let offset = writer.append(event)?;
+        idx.maybe_add(event, offset)?;
+    }
+    writer.flush()?;
+    idx.flush()?;
+
+    Ok(MergeSummary {
+        inputs: inputs.len(),
+        events: events.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn write_temp_log(name: &str, events: &[Event]) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_merge_{}_{}.eventlog",
+            name,
+            std::process::id()
+        ));
+        let symbols = vec![String::from("AAPL"), String::from("MSFT")];
+        let mut writer =
+            EventLogWriter::create(&path, &symbols, default_schema_hash()).expect("writer");
+        for event in events {
+            writer.append(event).expect("append");
+        }
+        writer.flush().expect("flush");
+        path
+    }
+
+    #[test]
+    fn refuses_logs_with_different_price_units() {
+        let dir = std::env::temp_dir();
+        let name = |what: &str| {
+            dir.join(format!(
+                "md_replay_merge_units_{what}_{}",
+                std::process::id()
+            ))
+        };
+        let symbols = [String::from("BTCUSDT")];
+        let mut inputs = Vec::new();
+        for (what, unit) in [("a", "0.00000001"), ("b", "0.000001")] {
+            let path = name(what);
+            let units = BTreeMap::from([(String::from("BTCUSDT"), String::from(unit))]);
+            let mut writer = EventLogWriter::create_with_price_units(
+                &path,
+                &symbols,
+                default_schema_hash(),
+                &units,
+            )
+            .expect("writer");
+            writer.flush().expect("flush");
+            inputs.push(path);
+        }
+        let (out, idx) = (name("out"), name("idx"));
+        merge_logs(&inputs[..1], &out, &idx, 16).expect("one log merges");
+        let reader = EventLogReader::open(&out).expect("reader");
+        assert_eq!(reader.header().price_units["BTCUSDT"], "0.00000001");
+        drop(reader);
+        let err = merge_logs(&inputs, &out, &idx, 16).expect_err("units differ");
+        assert!(
+            err.to_string().contains("price unit 0.000001 for BTCUSDT"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn merges_by_timestamp_and_resequences() {
+        let a = write_temp_log(
+            "a",
+            &[
+                Event::trade(1, 1, "X", "AAPL", 100, 1),
+                Event::trade(5, 2, "X", "AAPL", 101, 1),
+            ],
+        );
+        let b = write_temp_log(
+            "b",
+            &[
+                Event::trade(3, 1, "Y", "MSFT", 200, 1),
+                Event::trade(5, 2, "Y", "MSFT", 201, 1),
+            ],
+        );
+        let mut out = std::env::temp_dir();
+        out.push(format!(
+            "md_replay_merge_out_{}.eventlog",
+            std::process::id()
+        ));
+        let idx = out.with_extension("eventlog.idx");
+
+        let summary = merge_logs(&[a, b], &out, &idx, 1).expect("merge");
+        assert_eq!(summary.events, 4);
+
+        let mut reader = EventLogReader::open(&out).expect("reader");
+        let mut merged = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            merged.push(record.event);
+        }
+        let keys: Vec<(i64, u64, &str)> = merged
+            .iter()
+            .map(|e| (e.timestamp_ns.as_nanos(), e.sequence, e.venue.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![(1, 1, "X"), (3, 2, "Y"), (5, 3, "X"), (5, 4, "Y")]
+        );
+        assert_eq!(reader.footer().expect("footer").record_count, 4);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(24))]
+
+        #[test]
+        fn merge_never_inverts_timestamps(
+            inputs in prop::collection::vec(prop::collection::vec(0i64..50, 0..40), 1..5),
+        ) {
+            let mut paths = Vec::new();
+            let mut total = 0;
+            for (i, mut stamps) in inputs.into_iter().enumerate() {
+                stamps.sort_unstable();
+                total += stamps.len() as u64;
+                let events = stamps
+                    .iter()
+                    .enumerate()
+                    .map(|(seq, ts)| Event::trade(*ts, seq as u64 + 1, "X", "AAPL", 100, 1))
+                    .collect::<Vec<_>>();
+                paths.push(write_temp_log(&format!("prop_{i}"), &events));
+            }
+            let out = std::env::temp_dir().join(format!(
+                "md_replay_merge_prop_out_{}.eventlog",
+                std::process::id()
+            ));
+            let idx = out.with_extension("eventlog.idx");
+            let summary = merge_logs(&paths, &out, &idx, 4).expect("merge");
+            prop_assert_eq!(summary.events, total);
+
+            let mut reader = EventLogReader::open(&out).expect("reader");
+            let mut last = None;
+            let mut expected_seq = 1;
+            while let Some(record) = reader.next_record().expect("next") {
+                let event = record.event;
+                prop_assert_eq!(event.sequence, expected_seq);
+                prop_assert!(last.is_none_or(|ts| event.timestamp_ns >= ts));
+                last = Some(event.timestamp_ns);
+                expected_seq += 1;
+            }
+        }
+    }
+}
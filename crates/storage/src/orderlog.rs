@@ -0,0 +1,136 @@
+//! Order logs: normalized gateway records as JSON lines, one
+//! [`OrderEvent`] per line in timestamp order.
+
+use crate::StorageError;
+use md_core::{OrderEvent, Timestamp};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+
+/// Writes `orders`, which must already be in timestamp order.
+pub fn write_order_log(path: &Path, orders: &[OrderEvent]) -> Result<(), StorageError> {
+    let mut w = BufWriter::new(File::create(path)?);
+    let mut last = None;
+    for order in orders {
+        check_order(&mut last, order, order.sequence)?;
+        let line = serde_json::to_string(order).map_err(|err| {
+            StorageError::InvalidFormat(format!("order {}: {err}", order.sequence))
+        })?;
+        w.write_all(line.as_bytes())?;
+        w.write_all(b"\n")?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn check_order(
+    last: &mut Option<Timestamp>,
+    order: &OrderEvent,
+    at: u64,
+) -> Result<(), StorageError> {
+    if last.is_some_and(|last| order.timestamp_ns < last) {
+        return Err(StorageError::InvalidFormat(format!(
+            "order log not in timestamp order at {at}"
+        )));
+    }
+    *last = Some(order.timestamp_ns);
+    Ok(())
+}
+
+/// Streams an order log, failing on the first line that does not parse or
+/// goes back in time.
+pub struct OrderLogReader {
+    lines: Lines<BufReader<File>>,
+    line: u64,
+    last: Option<Timestamp>,
+}
+
+impl OrderLogReader {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Ok(Self {
+            lines: BufReader::new(File::open(path)?).lines(),
+            line: 0,
+            last: None,
+        })
+    }
+}
+
+impl Iterator for OrderLogReader {
+    type Item = Result<OrderEvent, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let order = serde_json::from_str::<OrderEvent>(&line).map_err(|err| {
+                StorageError::InvalidFormat(format!("order log line {}: {err}", self.line))
+            });
+            return Some(order.and_then(|order| {
+                check_order(&mut self.last, &order, self.line)?;
+                Ok(order)
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md_core::{OrderAction, OrderSide};
+
+    fn order(ts: i64, sequence: u64, action: OrderAction) -> OrderEvent {
+        OrderEvent {
+            timestamp_ns: Timestamp::from_nanos(ts),
+            sequence,
+            venue: String::from("X"),
+            symbol: String::from("AAPL"),
+            order_id: String::from("o-1"),
+            action,
+            side: OrderSide::Buy,
+            price_ticks: Some(10_000),
+            size: 5,
+        }
+    }
+
+    #[test]
+    fn order_log_round_trips_and_rejects_time_travel() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "md_replay_storage_orders_{}.jsonl",
+            std::process::id()
+        ));
+        let orders = vec![
+            order(10, 1, OrderAction::New),
+            order(20, 2, OrderAction::Fill),
+        ];
+        write_order_log(&path, &orders).expect("write");
+        let read = OrderLogReader::open(&path)
+            .expect("open")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("read");
+        assert_eq!(read, orders);
+
+        let backwards = [
+            order(20, 1, OrderAction::New),
+            order(10, 2, OrderAction::Cancel),
+        ];
+        assert!(write_order_log(&path, &backwards).is_err());
+        let lines = backwards
+            .iter()
+            .map(|o| serde_json::to_string(o).expect("json"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&path, lines).expect("write raw");
+        let read = OrderLogReader::open(&path)
+            .expect("open")
+            .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(read, Err(StorageError::InvalidFormat(_))));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::eventlog::{
+    default_schema_hash, EventLogReader, EventLogWriter, SalvageItem, SkippedRange,
+};
+use crate::index::IndexWriter;
+use crate::StorageError;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    pub recovered: u64,
+    /// From the footer when it survived, otherwise from sequence gaps.
+    pub dropped_records: u64,
+    pub skipped: Vec<SkippedRange>,
+}
+
+/// Copies every readable record of `input` into a fresh log and index,
+/// keeping original sequence numbers so the gaps stay visible.
+pub fn repair_log(
+    input: &Path,
+    out: &Path,
+    index_path: &Path,
+    index_stride: u32,
+) -> Result<RepairReport, StorageError> {
+    let mut reader = EventLogReader::open_for_salvage(input)?;
+    let expected = reader.footer().map(|f| f.record_count);
+    let header = reader.header();
+    let mut writer = EventLogWriter::create_with_price_units(
+        out,
+        &header.symbols,
+        default_schema_hash(),
+        &header.price_units,
+    )?;
+    let mut idx = IndexWriter::create(index_path, index_stride)?;
+
+    let mut report = RepairReport::default();
+    let mut gaps = 0u64;
+    let mut last_sequence = 0u64;
+    while let Some(item) = reader.next_salvaged()? {
+        match item {
+            SalvageItem::Record(record) => {
+                let seq = record.event.sequence;
+                gaps += seq.saturating_sub(last_sequence + 1);
+                last_sequence = last_sequence.max(seq);
+                let offset = writer.append(&record.event)?;
+                idx.maybe_add(&record.event, offset)?;
+                report.recovered += 1;
+            }
+            SalvageItem::Skipped(range) => report.skipped.push(range),
+        }
+    }
+    writer.flush()?;
+    idx.flush()?;
+
+    report.dropped_records = match expected {
+        Some(count) => count.saturating_sub(report.recovered),
+        None => gaps,
+    };
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::index_path_for;
+    use md_core::Event;
+
+    #[test]
+    fn repair_drops_corrupt_record() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("md_replay_repair_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let input = dir.join("bad.eventlog");
+        let mut writer =
+            EventLogWriter::create(&input, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        let mut offsets = Vec::new();
+        for i in 1..=4u64 {
+            offsets.push(
+                writer
+                    .append(&Event::trade(i as i64, i, "X", "AAPL", 100, 1))
+                    .expect("append"),
+            );
+        }
+        writer.flush().expect("flush");
+        let mut bytes = std::fs::read(&input).expect("read");
+        bytes[offsets[2] as usize + 9] ^= 0x0f;
+        std::fs::write(&input, bytes).expect("write");
+
+        let out = dir.join("clean.eventlog");
+        let report = repair_log(&input, &out, &index_path_for(&out), 2).expect("repair");
+        assert_eq!(report.recovered, 3);
+        assert_eq!(report.dropped_records, 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].offset, offsets[2]);
+
+        let mut reader = EventLogReader::open(&out).expect("clean log");
+        let mut seqs = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seqs.push(record.event.sequence);
+        }
+        assert_eq!(seqs, vec![1, 2, 4]);
+    }
+}
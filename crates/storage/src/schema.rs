@@ -0,0 +1,222 @@
+use crate::StorageError;
+use md_core::{Event, EventType, Payload, Timestamp};
+use serde::Deserialize;
+
+/// Event payload layouts that have ever been written to a log. Each variant
+/// owns the decoder for its layout so old logs keep reading after `Event`
+/// changes; add a variant (and bump `CURRENT`) whenever the layout changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaVersion {
+    /// Unsigned `u64` timestamps.
+    V1,
+    /// Signed `Timestamp` (i64 nanoseconds).
+    V2,
+    /// Trailing optional source sequence number.
+    V3,
+}
+
+impl SchemaVersion {
+    pub const CURRENT: SchemaVersion = SchemaVersion::V3;
+    pub const ALL: &'static [SchemaVersion] =
+        &[SchemaVersion::V1, SchemaVersion::V2, SchemaVersion::V3];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SchemaVersion::V1 => "event_v1",
+            SchemaVersion::V2 => "event_v2:timestamp_i64",
+            SchemaVersion::V3 => "event_v3:source_sequence",
+        }
+    }
+
+    pub fn hash(self) -> u64 {
+        crc32fast::hash(self.description().as_bytes()) as u64
+    }
+
+    pub fn from_hash(hash: u64) -> Option<Self> {
+        Self::ALL.iter().copied().find(|v| v.hash() == hash)
+    }
+
+    pub fn decode(self, payload: &[u8]) -> Result<Event, StorageError> {
+        match self {
+            SchemaVersion::V1 => bincode::deserialize::<EventV1>(payload)?.upgrade(),
+            SchemaVersion::V2 => Ok(bincode::deserialize::<EventV2>(payload)?.upgrade()),
+            SchemaVersion::V3 => Ok(bincode::deserialize::<Event>(payload)?),
+        }
+    }
+
+    pub fn encode(event: &Event) -> Result<Vec<u8>, StorageError> {
+        Ok(bincode::serialize(event)?)
+    }
+
+    /// Reads the leading fields without decoding the rest. Every layout
+    /// starts with timestamp, sequence, venue and symbol in bincode's
+    /// fixed-width little-endian encoding. `None` if the payload is too
+    /// short; the full decode then reports the actual error.
+    pub(crate) fn peek(self, payload: &[u8]) -> Option<RecordKey<'_>> {
+        let word = |at: usize| -> Option<[u8; 8]> { payload.get(at..at + 8)?.try_into().ok() };
+        let timestamp_ns = match self {
+            SchemaVersion::V1 => {
+                Timestamp::from_unsigned_nanos(u64::from_le_bytes(word(0)?)).ok()?
+            }
+            SchemaVersion::V2 | SchemaVersion::V3 => {
+                Timestamp::from_nanos(i64::from_le_bytes(word(0)?))
+            }
+        };
+        let sequence = u64::from_le_bytes(word(8)?);
+        let venue_len = usize::try_from(u64::from_le_bytes(word(16)?)).ok()?;
+        let symbol_at = 24usize.checked_add(venue_len)?;
+        let symbol_len = usize::try_from(u64::from_le_bytes(word(symbol_at)?)).ok()?;
+        let symbol = payload.get(symbol_at + 8..(symbol_at + 8).checked_add(symbol_len)?)?;
+        Some(RecordKey {
+            timestamp_ns,
+            sequence,
+            symbol,
+        })
+    }
+}
+
+pub(crate) struct RecordKey<'a> {
+    pub(crate) timestamp_ns: Timestamp,
+    pub(crate) sequence: u64,
+    pub(crate) symbol: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct EventV1 {
+    timestamp_ns: u64,
+    sequence: u64,
+    venue: String,
+    symbol: String,
+    event_type: EventType,
+    payload: Payload,
+}
+
+impl EventV1 {
+    fn upgrade(self) -> Result<Event, StorageError> {
+        let timestamp_ns = Timestamp::from_unsigned_nanos(self.timestamp_ns)
+            .map_err(|e| StorageError::InvalidFormat(e.to_string()))?;
+        Ok(Event {
+            timestamp_ns,
+            sequence: self.sequence,
+            venue: self.venue,
+            symbol: self.symbol,
+            event_type: self.event_type,
+            payload: self.payload,
+            source_sequence: None,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct EventV2 {
+    timestamp_ns: Timestamp,
+    sequence: u64,
+    venue: String,
+    symbol: String,
+    event_type: EventType,
+    payload: Payload,
+}
+
+impl EventV2 {
+    fn upgrade(self) -> Event {
+        Event {
+            timestamp_ns: self.timestamp_ns,
+            sequence: self.sequence,
+            venue: self.venue,
+            symbol: self.symbol,
+            event_type: self.event_type,
+            payload: self.payload,
+            source_sequence: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_resolve_to_versions() {
+        for version in SchemaVersion::ALL {
+            assert_eq!(SchemaVersion::from_hash(version.hash()), Some(*version));
+        }
+        assert_eq!(SchemaVersion::from_hash(0xdead_beef), None);
+    }
+
+    #[test]
+    fn v1_payloads_upgrade_to_signed_timestamps() {
+        #[derive(serde::Serialize)]
+        struct Legacy {
+            timestamp_ns: u64,
+            sequence: u64,
+            venue: String,
+            symbol: String,
+            event_type: EventType,
+            payload: Payload,
+        }
+        let legacy = Legacy {
+            timestamp_ns: 42,
+            sequence: 7,
+            venue: String::from("X"),
+            symbol: String::from("AAPL"),
+            event_type: EventType::Trade,
+            payload: Payload::Trade {
+                price_ticks: 100,
+                size: 1,
+            },
+        };
+        let bytes = bincode::serialize(&legacy).expect("encode");
+        let event = SchemaVersion::V1.decode(&bytes).expect("decode");
+        assert_eq!(event, Event::trade(42, 7, "X", "AAPL", 100, 1));
+
+        let mut too_late = legacy;
+        too_late.timestamp_ns = u64::MAX;
+        let bytes = bincode::serialize(&too_late).expect("encode");
+        assert!(SchemaVersion::V1.decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn peek_reads_leading_fields_only() {
+        let event = Event::trade(-5, 9, "XNAS", "MSFT", 100, 1);
+        let bytes = SchemaVersion::encode(&event).expect("encode");
+        let key = SchemaVersion::V3.peek(&bytes).expect("peek");
+        assert_eq!(key.timestamp_ns, Timestamp::from_nanos(-5));
+        assert_eq!(key.sequence, 9);
+        assert_eq!(key.symbol, b"MSFT");
+
+        let header_only = 24 + 4 + 8 + 4;
+        assert!(SchemaVersion::V3.peek(&bytes[..header_only]).is_some());
+        assert!(SchemaVersion::V3.peek(&bytes[..header_only - 1]).is_none());
+    }
+
+    #[test]
+    fn v2_payloads_upgrade_without_a_source_sequence() {
+        #[derive(serde::Serialize)]
+        struct Legacy {
+            timestamp_ns: Timestamp,
+            sequence: u64,
+            venue: String,
+            symbol: String,
+            event_type: EventType,
+            payload: Payload,
+        }
+        let legacy = Legacy {
+            timestamp_ns: Timestamp::from_nanos(-3),
+            sequence: 7,
+            venue: String::from("X"),
+            symbol: String::from("AAPL"),
+            event_type: EventType::Trade,
+            payload: Payload::Trade {
+                price_ticks: 100,
+                size: 1,
+            },
+        };
+        let bytes = bincode::serialize(&legacy).expect("encode");
+        let event = SchemaVersion::V2.decode(&bytes).expect("decode");
+        assert_eq!(event, Event::trade(-3, 7, "X", "AAPL", 100, 1));
+
+        let sequenced = Event::trade(-3, 7, "X", "AAPL", 100, 1).with_source_sequence(41);
+        let bytes = SchemaVersion::encode(&sequenced).expect("encode");
+        assert_eq!(SchemaVersion::V3.decode(&bytes).expect("decode"), sequenced);
+    }
+}
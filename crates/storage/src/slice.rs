@@ -0,0 +1,181 @@
+use crate::eventlog::EventLogReader;
+use crate::filter::{filter_log, EventFilter, FilterSummary};
+use crate::StorageError;
+use md_core::Payload;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// What makes a symbol active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityBy {
+    Trades,
+    Volume,
+}
+
+impl std::str::FromStr for ActivityBy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "trades" => Ok(Self::Trades),
+            "volume" => Ok(Self::Volume),
+            other => Err(format!(
+                "unsupported activity measure {other}, expected trades|volume"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SymbolActivity {
+    pub trades: u64,
+    /// Traded size.
+    pub volume: i64,
+}
+
+impl SymbolActivity {
+    fn measure(&self, by: ActivityBy) -> i128 {
+        match by {
+            ActivityBy::Trades => self.trades as i128,
+            ActivityBy::Volume => self.volume as i128,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceSummary {
+    /// Symbols kept, most active first.
+    pub symbols: Vec<(String, SymbolActivity)>,
+    pub filter: FilterSummary,
+}
+
+/// Trade count and volume of every symbol in the log, quoted-only ones
+/// included at zero.
+pub fn symbol_activity(input: &Path) -> Result<BTreeMap<String, SymbolActivity>, StorageError> {
+    let mut reader = EventLogReader::open(input)?;
+    let mut activity = reader
+        .header()
+        .symbols
+        .iter()
+        .map(|s| (s.clone(), SymbolActivity::default()))
+        .collect::<BTreeMap<_, _>>();
+    while let Some(record) = reader.next_record()? {
+        let entry = activity.entry(record.event.symbol).or_default();
+        if let Payload::Trade { size, .. } = record.event.payload {
+            entry.trades += 1;
+            entry.volume = entry.volume.saturating_add(size);
+        }
+    }
+    Ok(activity)
+}
+
+/// The `n` most active symbols, most active first; ties go to the symbol
+/// that sorts first.
+pub fn top_symbols(
+    activity: &BTreeMap<String, SymbolActivity>,
+    n: usize,
+    by: ActivityBy,
+) -> Vec<(String, SymbolActivity)> {
+    let mut ranked = activity
+        .iter()
+        .map(|(symbol, a)| (symbol.clone(), *a))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(sa, a), (sb, b)| b.measure(by).cmp(&a.measure(by)).then(sa.cmp(sb)));
+    ranked.truncate(n);
+    ranked
+}
+
+/// Copies the events of the `n` most active symbols into a new log and
+/// index, renumbering sequences from 1 as [`filter_log`] does.
+pub fn slice_top_symbols(
+    input: &Path,
+    out: &Path,
+    index_path: &Path,
+    n: usize,
+    by: ActivityBy,
+    index_stride: u32,
+) -> Result<SliceSummary, StorageError> {
+    let symbols = top_symbols(&symbol_activity(input)?, n, by);
+    let filter = EventFilter {
+        symbols: symbols.iter().map(|(s, _)| s.clone()).collect(),
+        ..EventFilter::default()
+    };
+    // An empty symbol list would keep everything.
+    if filter.symbols.is_empty() {
+        return Err(StorageError::InvalidFormat(String::from(
+            "no symbols to keep",
+        )));
+    }
+    let filter = filter_log(input, out, index_path, &filter, index_stride)?;
+    Ok(SliceSummary { symbols, filter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eventlog::{default_schema_hash, EventLogWriter};
+    use md_core::{Event, QuoteTicks};
+
+    #[test]
+    fn keeps_the_most_active_symbols() {
+        let dir = std::env::temp_dir().join(format!("md_replay_slice_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let input = dir.join("in.eventlog");
+        let symbols = ["AAPL", "MSFT", "SPY", "TINY"].map(String::from).to_vec();
+        let mut writer =
+            EventLogWriter::create(&input, &symbols, default_schema_hash()).expect("writer");
+        let quote = QuoteTicks {
+            bid_px: 1,
+            bid_sz: 1,
+            ask_px: 2,
+            ask_sz: 1,
+        };
+        let events = [
+            Event::trade(1, 1, "X", "AAPL", 100, 1),
+            Event::trade(2, 2, "X", "AAPL", 100, 1),
+            Event::trade(3, 3, "X", "AAPL", 100, 1),
+            Event::trade(4, 4, "X", "MSFT", 100, 500),
+            Event::trade(5, 5, "X", "SPY", 100, 10),
+            Event::trade(6, 6, "X", "SPY", 100, 10),
+            Event::quote(7, 7, "X", "TINY", quote),
+        ];
+        for event in &events {
+            writer.append(event).expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let activity = symbol_activity(&input).expect("activity");
+        assert_eq!(activity["TINY"], SymbolActivity::default());
+        let names = |by| {
+            top_symbols(&activity, 2, by)
+                .into_iter()
+                .map(|(s, _)| s)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(ActivityBy::Trades), ["AAPL", "SPY"]);
+        assert_eq!(names(ActivityBy::Volume), ["MSFT", "SPY"]);
+
+        let out = dir.join("out.eventlog");
+        let summary = slice_top_symbols(
+            &input,
+            &out,
+            &dir.join("out.eventlog.idx"),
+            2,
+            ActivityBy::Volume,
+            16,
+        )
+        .expect("slice");
+        assert_eq!(summary.filter.written, 3);
+        let mut reader = EventLogReader::open(&out).expect("reader");
+        assert_eq!(reader.header().symbols, ["MSFT", "SPY"]);
+        let mut seqs = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seqs.push((record.event.symbol, record.event.sequence));
+        }
+        assert_eq!(
+            seqs,
+            [("MSFT", 1), ("SPY", 2), ("SPY", 3)].map(|(s, n)| (s.to_string(), n))
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
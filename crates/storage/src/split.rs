@@ -0,0 +1,174 @@
+use crate::eventlog::{default_schema_hash, EventLogReader, EventLogWriter};
+use crate::index::{index_path_for, IndexWriter};
+use crate::StorageError;
+use md_core::Event;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    Symbol,
+    Day,
+}
+
+impl std::str::FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "symbol" => Ok(Self::Symbol),
+            "day" => Ok(Self::Day),
+            other => Err(format!(
+                "unsupported split key {other}, expected symbol|day"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPart {
+    pub key: String,
+    pub path: PathBuf,
+    pub events: u64,
+}
+
+struct PartWriter {
+    log: EventLogWriter,
+    idx: IndexWriter,
+    path: PathBuf,
+    events: u64,
+}
+
+/// Writes one log + index per symbol or per UTC day into `out_dir`, named
+/// `<stem>.<key>.eventlog`. Events keep their relative order and are
+/// renumbered from 1 within each part.
+pub fn split_log(
+    input: &Path,
+    out_dir: &Path,
+    by: SplitBy,
+    index_stride: u32,
+) -> Result<Vec<SplitPart>, StorageError> {
+    let mut reader = EventLogReader::open(input)?;
+    let all_symbols = reader.header().symbols.clone();
+    let all_units = reader.header().price_units.clone();
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("split"));
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut parts = BTreeMap::<String, PartWriter>::new();
+    while let Some(record) = reader.next_record()? {
+        let mut event = record.event;
+        let key = match by {
+            SplitBy::Symbol => event.symbol.clone(),
+            SplitBy::Day => event.timestamp_ns.to_utc().format("%Y-%m-%d").to_string(),
+        };
+        let part = match parts.entry(key) {
+            Entry::Occupied(slot) => slot.into_mut(),
+            Entry::Vacant(slot) => {
+                let path = out_dir.join(format!("{stem}.{}.eventlog", file_safe(slot.key())));
+                let (symbols, units) = match by {
+                    SplitBy::Symbol => (
+                        vec![event.symbol.clone()],
+                        all_units
+                            .get(&event.symbol)
+                            .map(|u| BTreeMap::from([(event.symbol.clone(), u.clone())]))
+                            .unwrap_or_default(),
+                    ),
+                    SplitBy::Day => (all_symbols.clone(), all_units.clone()),
+                };
+                slot.insert(PartWriter {
+                    log: EventLogWriter::create_with_price_units(
+                        &path,
+                        &symbols,
+                        default_schema_hash(),
+                        &units,
+                    )?,
+                    idx: IndexWriter::create(&index_path_for(&path), index_stride)?,
+                    path,
+                    events: 0,
+                })
+            }
+        };
+        part.events += 1;
+        event.sequence = part.events;
+        append(part, &event)?;
+    }
+
+    let mut out = Vec::with_capacity(parts.len());
+    for (key, mut part) in parts {
+        part.log.flush()?;
+        part.idx.flush()?;
+        out.push(SplitPart {
+            key,
+            path: part.path,
+            events: part.events,
+        });
+    }
+    Ok(out)
+}
+
+fn append(part: &mut PartWriter, event: &Event) -> Result<(), StorageError> {
+    let offset = part.log.append(event)?;
+    part.idx.maybe_add(event, offset)
+}
+
+fn file_safe(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_NS: i64 = 86_400_000_000_000;
+
+    #[test]
+    fn splits_by_symbol_and_day() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("md_replay_split_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let input = dir.join("tape.eventlog");
+        let symbols = vec![String::from("AAPL"), String::from("BRK/B")];
+        let mut writer =
+            EventLogWriter::create(&input, &symbols, default_schema_hash()).expect("writer");
+        for event in [
+            Event::trade(1, 1, "X", "AAPL", 100, 1),
+            Event::trade(2, 2, "X", "BRK/B", 200, 1),
+            Event::trade(DAY_NS + 1, 3, "X", "AAPL", 101, 1),
+        ] {
+            writer.append(&event).expect("append");
+        }
+        writer.flush().expect("flush");
+
+        let by_symbol = split_log(&input, &dir.join("sym"), SplitBy::Symbol, 1).expect("split");
+        let keys: Vec<(&str, u64)> = by_symbol
+            .iter()
+            .map(|p| (p.key.as_str(), p.events))
+            .collect();
+        assert_eq!(keys, vec![("AAPL", 2), ("BRK/B", 1)]);
+        assert!(by_symbol[1].path.ends_with("tape.BRK_B.eventlog"));
+
+        let mut reader = EventLogReader::open(&by_symbol[0].path).expect("reader");
+        let mut seen = Vec::new();
+        while let Some(record) = reader.next_record().expect("next") {
+            seen.push((record.event.sequence, record.event.timestamp_ns.as_nanos()));
+        }
+        assert_eq!(seen, vec![(1, 1), (2, DAY_NS + 1)]);
+        assert!(index_path_for(&by_symbol[0].path).exists());
+
+        let by_day = split_log(&input, &dir.join("day"), SplitBy::Day, 1).expect("split");
+        let keys: Vec<(&str, u64)> = by_day.iter().map(|p| (p.key.as_str(), p.events)).collect();
+        assert_eq!(keys, vec![("1970-01-01", 2), ("1970-01-02", 1)]);
+    }
+}
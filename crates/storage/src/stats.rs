@@ -0,0 +1,164 @@
+use crate::eventlog::{EventLogFooter, EventLogReader};
+use crate::index::IndexReader;
+use crate::StorageError;
+use md_core::Timestamp;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsSource {
+    Footer,
+    Scan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeGap {
+    pub after_sequence: u64,
+    pub from_ns: Timestamp,
+    pub to_ns: Timestamp,
+    pub duration_ns: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexCoverage {
+    pub stride: u32,
+    pub entries: usize,
+    pub expected_entries: u64,
+    pub first_timestamp_ns: Option<Timestamp>,
+    pub last_timestamp_ns: Option<Timestamp>,
+    pub last_sequence: Option<u64>,
+    /// One entry per `stride` records, none past the end of the log.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogStats {
+    pub version: u16,
+    pub schema_hash: u64,
+    pub schema: &'static str,
+    pub symbols: Vec<String>,
+    /// Decimal price of one stored tick per symbol, from the header.
+    pub price_units: BTreeMap<String, String>,
+    pub source: StatsSource,
+    pub summary: EventLogFooter,
+    /// Only filled when a gap threshold is requested, as it needs a scan.
+    pub gaps: Option<Vec<TimeGap>>,
+    pub index: Option<IndexCoverage>,
+}
+
+/// Builds the `info` report. The footer and index are used as-is; records are
+/// only scanned when the log has no footer or gaps were requested.
+pub fn log_stats(
+    log: &Path,
+    index: Option<&Path>,
+    gap_threshold_ns: Option<i64>,
+) -> Result<LogStats, StorageError> {
+    let mut reader = EventLogReader::open(log)?;
+    let header = reader.header().clone();
+    let footer = reader.footer().cloned();
+
+    let mut scanned = EventLogFooter::default();
+    let mut gaps = gap_threshold_ns.map(|_| Vec::new());
+    if footer.is_none() || gap_threshold_ns.is_some() {
+        let mut prev: Option<(Timestamp, u64)> = None;
+        while let Some(record) = reader.next_record()? {
+            let event = record.event;
+            if let (Some(threshold), Some(gaps), Some((prev_ts, prev_seq))) =
+                (gap_threshold_ns, gaps.as_mut(), prev)
+            {
+                let duration_ns = event.timestamp_ns.nanos_since(prev_ts);
+                if duration_ns >= threshold {
+                    gaps.push(TimeGap {
+                        after_sequence: prev_seq,
+                        from_ns: prev_ts,
+                        to_ns: event.timestamp_ns,
+                        duration_ns,
+                    });
+                }
+            }
+            prev = Some((event.timestamp_ns, event.sequence));
+            scanned.record(&event);
+        }
+    }
+    let (summary, source) = match footer {
+        Some(footer) => (footer, StatsSource::Footer),
+        None => (scanned, StatsSource::Scan),
+    };
+
+    let index = match index {
+        Some(path) if path.exists() => Some(index_coverage(&IndexReader::open(path)?, &summary)),
+        _ => None,
+    };
+
+    Ok(LogStats {
+        version: header.version,
+        schema_hash: header.schema_hash,
+        schema: header.schema.description(),
+        symbols: header.symbols,
+        price_units: header.price_units,
+        source,
+        summary,
+        gaps,
+        index,
+    })
+}
+
+fn index_coverage(idx: &IndexReader, summary: &EventLogFooter) -> IndexCoverage {
+    let entries = idx.entries();
+    let expected_entries = summary.record_count.div_ceil(idx.stride() as u64);
+    let last_sequence = entries.last().map(|e| e.sequence);
+    IndexCoverage {
+        stride: idx.stride(),
+        entries: entries.len(),
+        expected_entries,
+        first_timestamp_ns: entries.first().map(|e| e.timestamp_ns),
+        last_timestamp_ns: entries.last().map(|e| e.timestamp_ns),
+        last_sequence,
+        complete: entries.len() as u64 == expected_entries
+            && last_sequence.is_none_or(|seq| seq <= summary.last_sequence),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eventlog::{default_schema_hash, EventLogWriter};
+    use crate::index::{index_path_for, IndexWriter};
+    use md_core::Event;
+
+    #[test]
+    fn reports_gaps_and_index_coverage() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("md_replay_stats_{}.eventlog", std::process::id()));
+        let idx_path = index_path_for(&path);
+        let mut writer =
+            EventLogWriter::create(&path, &[String::from("AAPL")], default_schema_hash())
+                .expect("writer");
+        let mut idx = IndexWriter::create(&idx_path, 2).expect("index");
+        for (seq, ts) in [(1u64, 10i64), (2, 20), (3, 500), (4, 510), (5, 2000)] {
+            let event = Event::trade(ts, seq, "X", "AAPL", 100, 1);
+            let offset = writer.append(&event).expect("append");
+            idx.maybe_add(&event, offset).expect("index add");
+        }
+        writer.flush().expect("flush");
+        idx.flush().expect("flush index");
+
+        let stats = log_stats(&path, Some(&idx_path), Some(400)).expect("stats");
+        assert_eq!(stats.source, StatsSource::Footer);
+        assert_eq!(stats.summary.record_count, 5);
+        let gaps = stats.gaps.expect("gaps");
+        assert_eq!(
+            gaps.iter().map(|g| g.after_sequence).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        let index = stats.index.expect("index");
+        assert_eq!((index.entries, index.expected_entries), (3, 3));
+        assert!(index.complete);
+
+        let stats = log_stats(&path, None, None).expect("stats");
+        assert!(stats.gaps.is_none());
+        assert!(stats.index.is_none());
+    }
+}
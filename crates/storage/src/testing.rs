@@ -0,0 +1,78 @@
+//! Writes [`md_core::testing::Tape`] fixtures to disk.
+//!
+//! ```
+//! use md_core::testing::Tape;
+//! use md_storage::testing::TapeLog;
+//!
+//! let dir = std::env::temp_dir().join(format!("md_storage_doc_{}", std::process::id()));
+//! let log = Tape::new()
+//!     .quote("AAPL", "100.00/100.02", 10, 11)
+//!     .at("+5ms")
+//!     .trade("AAPL", "100.01", 3)
+//!     .build_log(&dir)?;
+//! assert!(log.exists());
+//! # Ok::<(), md_storage::StorageError>(())
+//! ```
+
+use crate::{default_schema_hash, index_path_for, EventLogWriter, IndexWriter, StorageError};
+use md_core::testing::Tape;
+use std::path::{Path, PathBuf};
+
+/// Index stride of fixture logs: small, so seeks are exercised on short tapes.
+pub const FIXTURE_STRIDE: u32 = 4;
+
+pub trait TapeLog {
+    /// Writes `tape.eventlog` and its index into `dir`, creating the
+    /// directory, and returns the log path.
+    fn build_log(&self, dir: &Path) -> Result<PathBuf, StorageError>;
+
+    /// Writes the log and index at `path`.
+    fn write_log(&self, path: &Path, stride: u32) -> Result<(), StorageError>;
+}
+
+impl TapeLog for Tape {
+    fn build_log(&self, dir: &Path) -> Result<PathBuf, StorageError> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join("tape.eventlog");
+        self.write_log(&path, FIXTURE_STRIDE)?;
+        Ok(path)
+    }
+
+    fn write_log(&self, path: &Path, stride: u32) -> Result<(), StorageError> {
+        let mut writer = EventLogWriter::create(path, &self.symbols(), default_schema_hash())?;
+        let mut idx = IndexWriter::create(&index_path_for(path), stride)?;
+        for event in self.build() {
+            let offset = writer.append(&event)?;
+            idx.maybe_add(&event, offset)?;
+        }
+        writer.flush()?;
+        idx.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventLogReader, IndexReader};
+
+    #[test]
+    fn fixture_log_reads_back_with_index() {
+        let dir = std::env::temp_dir().join(format!("md_storage_tape_{}", std::process::id()));
+        let tape = (0..10).fold(Tape::new(), |tape, i| {
+            tape.at("+1ms").trade("AAPL", &format!("100.{i:02}"), 1)
+        });
+        let path = tape.build_log(&dir).expect("build log");
+
+        let mut reader = EventLogReader::open(&path).expect("open");
+        assert_eq!(reader.header().symbols, vec![String::from("AAPL")]);
+        let mut events = Vec::new();
+        while let Some(record) = reader.next_record().expect("read") {
+            events.push(record.event);
+        }
+        assert_eq!(events, tape.build());
+        let index = IndexReader::open(&index_path_for(&path)).expect("index");
+        assert_eq!(index.entries().len(), 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}